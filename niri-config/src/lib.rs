@@ -14,8 +14,8 @@ use knuffel::Decode as _;
 use layer_rule::LayerRule;
 use miette::{miette, Context, IntoDiagnostic, NarratableReportHandler};
 use niri_ipc::{
-    ConfiguredMode, LayoutSwitchTarget, PositionChange, SizeChange, Transform,
-    WorkspaceReferenceArg,
+    BrightnessChange, ConfiguredMode, LayoutSwitchTarget, PositionChange, SizeChange, SnapPosition,
+    Transform, WorkspaceReferenceArg,
 };
 use smithay::backend::renderer::Color32F;
 use smithay::input::keyboard::keysyms::KEY_NoSymbol;
@@ -36,6 +36,8 @@ pub struct Config {
     pub input: Input,
     #[knuffel(children(name = "output"))]
     pub outputs: Outputs,
+    #[knuffel(child, default)]
+    pub profiles: OutputProfiles,
     #[knuffel(children(name = "spawn-at-startup"))]
     pub spawn_at_startup: Vec<SpawnAtStartup>,
     #[knuffel(child, default)]
@@ -53,9 +55,29 @@ pub struct Config {
     ]
     pub screenshot_path: Option<String>,
     #[knuffel(child, default)]
+    pub screenshot: Screenshot,
+    #[knuffel(child, default)]
+    pub screen_recording: ScreenRecording,
+    #[knuffel(child, default)]
+    pub screencast: Screencast,
+    #[knuffel(child, default)]
     pub clipboard: Clipboard,
     #[knuffel(child, default)]
+    pub drag_and_drop: DragAndDrop,
+    #[knuffel(child, default)]
     pub hotkey_overlay: HotkeyOverlay,
+    #[knuffel(child)]
+    pub crash_placeholder: Option<CrashPlaceholder>,
+    #[knuffel(child, default)]
+    pub startup_placeholder: StartupPlaceholder,
+    #[knuffel(child, default)]
+    pub urgency: Urgency,
+    #[knuffel(child, default)]
+    pub status_bar: StatusBar,
+    #[knuffel(child, default)]
+    pub watermark: Watermark,
+    #[knuffel(child, default)]
+    pub metrics: Metrics,
     #[knuffel(child, default)]
     pub animations: Animations,
     #[knuffel(child, default)]
@@ -67,11 +89,15 @@ pub struct Config {
     #[knuffel(child, default)]
     pub binds: Binds,
     #[knuffel(child, default)]
+    pub background_binds: BackgroundBinds,
+    #[knuffel(child, default)]
     pub switch_events: SwitchBinds,
     #[knuffel(child, default)]
     pub debug: DebugConfig,
     #[knuffel(children(name = "workspace"))]
     pub workspaces: Vec<Workspace>,
+    #[knuffel(child, default)]
+    pub workspace_auto_name: WorkspaceAutoName,
 }
 
 #[derive(knuffel::Decode, Debug, Default, PartialEq)]
@@ -93,11 +119,15 @@ pub struct Input {
     #[knuffel(child)]
     pub disable_power_key_handling: bool,
     #[knuffel(child)]
-    pub warp_mouse_to_focus: bool,
+    pub warp_mouse_to_focus: Option<WarpMouseToFocus>,
     #[knuffel(child)]
     pub focus_follows_mouse: Option<FocusFollowsMouse>,
     #[knuffel(child)]
     pub workspace_auto_back_and_forth: bool,
+    #[knuffel(child)]
+    pub mod_resize_nearest_corner: bool,
+    #[knuffel(child, default)]
+    pub click_to_focus: ClickToFocus,
 }
 
 #[derive(knuffel::Decode, Debug, PartialEq, Eq)]
@@ -164,6 +194,52 @@ pub enum CenterFocusedColumn {
     OnOverflow,
 }
 
+#[derive(knuffel::DecodeScalar, Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum NewColumnPosition {
+    /// New columns are added to the right of the focused column.
+    #[default]
+    AfterFocused,
+    /// New columns are added at the very left of the workspace.
+    First,
+    /// New columns are added at the very right of the workspace.
+    Last,
+}
+
+#[derive(knuffel::DecodeScalar, Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum NewWindowPositionInColumn {
+    /// New windows are added below the focused window in the column.
+    AfterFocused,
+    /// New windows are added at the top of the column.
+    Top,
+    /// New windows are added at the bottom of the column.
+    #[default]
+    Bottom,
+}
+
+#[derive(knuffel::DecodeScalar, Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum RevealNewWindows {
+    /// The view does not move for windows that open unfocused.
+    #[default]
+    Never,
+    /// The view moves to show as much of the new window as it can while keeping the focused
+    /// column fully visible.
+    Partial,
+    /// The view moves to show the whole new window.
+    Always,
+}
+
+/// Where the workspaces of a disconnected output go.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum WorkspaceSpillover {
+    /// Workspaces move to the primary output.
+    #[default]
+    Primary,
+    /// Workspaces move to the output with this name, or to the primary one if it isn't connected.
+    Output(String),
+    /// Workspaces are distributed evenly across the remaining outputs.
+    Distribute,
+}
+
 #[derive(knuffel::DecodeScalar, Debug, Default, PartialEq, Eq)]
 pub enum TrackLayout {
     /// The layout change is global.
@@ -181,6 +257,8 @@ pub struct Touchpad {
     pub tap: bool,
     #[knuffel(child)]
     pub dwt: bool,
+    #[knuffel(child, unwrap(arguments), default)]
+    pub dwt_exclude_keyboards: Vec<String>,
     #[knuffel(child)]
     pub dwtp: bool,
     #[knuffel(child)]
@@ -205,6 +283,8 @@ pub struct Touchpad {
     pub middle_emulation: bool,
     #[knuffel(child, unwrap(argument))]
     pub scroll_factor: Option<FloatOrInt<0, 100>>,
+    #[knuffel(child)]
+    pub invert_workspace_swipe: bool,
 }
 
 #[derive(knuffel::Decode, Debug, Default, PartialEq)]
@@ -331,7 +411,7 @@ impl From<TapButtonMap> for input::TapButtonMap {
     }
 }
 
-#[derive(knuffel::Decode, Debug, Default, PartialEq)]
+#[derive(knuffel::Decode, Debug, PartialEq)]
 pub struct Tablet {
     #[knuffel(child)]
     pub off: bool,
@@ -339,18 +419,87 @@ pub struct Tablet {
     pub map_to_output: Option<String>,
     #[knuffel(child)]
     pub left_handed: bool,
+    #[knuffel(child, unwrap(argument), default = true)]
+    pub rotate_with_output: bool,
 }
 
-#[derive(knuffel::Decode, Debug, Default, PartialEq)]
+impl Default for Tablet {
+    fn default() -> Self {
+        Self {
+            off: false,
+            map_to_output: None,
+            left_handed: false,
+            rotate_with_output: true,
+        }
+    }
+}
+
+#[derive(knuffel::Decode, Debug, PartialEq)]
 pub struct Touch {
     #[knuffel(child, unwrap(argument))]
     pub map_to_output: Option<String>,
+    #[knuffel(child, unwrap(argument), default = true)]
+    pub rotate_with_output: bool,
+    #[knuffel(child, unwrap(argument))]
+    pub resize_edge_width: Option<FloatOrInt<0, 1024>>,
+    #[knuffel(child)]
+    pub two_finger_resize: bool,
+}
+
+impl Default for Touch {
+    fn default() -> Self {
+        Self {
+            map_to_output: None,
+            rotate_with_output: true,
+            resize_edge_width: None,
+            two_finger_resize: false,
+        }
+    }
+}
+
+#[derive(knuffel::Decode, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WarpMouseToFocus {
+    /// Only warp when the focus moves to a different output.
+    #[knuffel(property, default)]
+    pub only_across_outputs: bool,
 }
 
 #[derive(knuffel::Decode, Debug, Clone, Copy, PartialEq)]
 pub struct FocusFollowsMouse {
     #[knuffel(property, str)]
     pub max_scroll_amount: Option<Percent>,
+    #[knuffel(child, unwrap(argument))]
+    pub delay_ms: Option<u16>,
+    #[knuffel(child, unwrap(argument), default)]
+    pub mode: FocusFollowsMouseMode,
+}
+
+#[derive(knuffel::DecodeScalar, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FocusFollowsMouseMode {
+    /// Focus windows, layer surfaces and outputs.
+    #[default]
+    All,
+    /// Focus only outputs, leaving the focused window on each output as is.
+    OutputOnly,
+}
+
+#[derive(knuffel::Decode, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ClickToFocus {
+    #[knuffel(child, unwrap(argument), default)]
+    pub tiled: FocusClick,
+    #[knuffel(child, unwrap(argument), default)]
+    pub floating: FocusClick,
+    #[knuffel(child, unwrap(argument), default)]
+    pub other_output: FocusClick,
+}
+
+#[derive(knuffel::DecodeScalar, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FocusClick {
+    /// Focus the window and send it the click.
+    #[default]
+    PassThrough,
+    /// Only focus the window.
+    Consume,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -370,15 +519,46 @@ pub struct Output {
     #[knuffel(child, unwrap(argument, str), default = Transform::Normal)]
     pub transform: Transform,
     #[knuffel(child)]
+    pub auto_rotate: bool,
+    #[knuffel(child)]
     pub position: Option<Position>,
     #[knuffel(child, unwrap(argument, str))]
     pub mode: Option<ConfiguredMode>,
     #[knuffel(child)]
     pub variable_refresh_rate: Option<Vrr>,
+    #[knuffel(child, unwrap(argument))]
+    pub max_render_fps: Option<u32>,
+    #[knuffel(child, unwrap(argument))]
+    pub color_temperature: Option<u32>,
+    #[knuffel(child, unwrap(argument))]
+    pub mirror: Option<String>,
+    #[knuffel(child)]
+    pub split: Option<OutputSplit>,
     #[knuffel(child, default = DEFAULT_BACKGROUND_COLOR)]
     pub background_color: Color,
 }
 
+#[derive(knuffel::Decode, Debug, Default, Clone, PartialEq)]
+pub struct OutputProfiles(#[knuffel(children(name = "profile"))] pub Vec<OutputProfile>);
+
+#[derive(knuffel::Decode, Debug, Clone, PartialEq)]
+pub struct OutputProfile {
+    #[knuffel(argument)]
+    pub name: String,
+    #[knuffel(children(name = "output"))]
+    pub outputs: Outputs,
+    #[knuffel(children(name = "workspace"))]
+    pub workspaces: Vec<ProfileWorkspace>,
+}
+
+#[derive(knuffel::Decode, Debug, Clone, PartialEq, Eq)]
+pub struct ProfileWorkspace {
+    #[knuffel(argument)]
+    pub name: String,
+    #[knuffel(property)]
+    pub output: String,
+}
+
 impl Output {
     pub fn is_vrr_always_on(&self) -> bool {
         self.variable_refresh_rate == Some(Vrr { on_demand: false })
@@ -400,9 +580,14 @@ impl Default for Output {
             name: String::new(),
             scale: None,
             transform: Transform::Normal,
+            auto_rotate: false,
             position: None,
             mode: None,
             variable_refresh_rate: None,
+            max_render_fps: None,
+            color_temperature: None,
+            mirror: None,
+            split: None,
             background_color: DEFAULT_BACKGROUND_COLOR,
         }
     }
@@ -416,6 +601,15 @@ pub struct OutputName {
     pub serial: Option<String>,
 }
 
+/// Minimum window size enforced by the layout, in logical pixels.
+#[derive(knuffel::Decode, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MinWindowSize {
+    #[knuffel(property)]
+    pub width: Option<u16>,
+    #[knuffel(property)]
+    pub height: Option<u16>,
+}
+
 #[derive(knuffel::Decode, Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Position {
     #[knuffel(property)]
@@ -424,6 +618,31 @@ pub struct Position {
     pub y: i32,
 }
 
+/// Splitting of one physical output into two logical outputs.
+#[derive(knuffel::Decode, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputSplit {
+    #[knuffel(argument, str)]
+    pub ratio: SplitRatio,
+    #[knuffel(property, default)]
+    pub direction: SplitDirection,
+}
+
+/// Relative sizes of the two parts of a split output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SplitRatio {
+    pub first: u16,
+    pub second: u16,
+}
+
+#[derive(knuffel::DecodeScalar, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SplitDirection {
+    /// Parts go side by side, left to right.
+    #[default]
+    Horizontal,
+    /// Parts go on top of each other, top to bottom.
+    Vertical,
+}
+
 #[derive(knuffel::Decode, Debug, Clone, PartialEq, Default)]
 pub struct Vrr {
     #[knuffel(property, default = false)]
@@ -456,10 +675,24 @@ pub struct Layout {
     pub always_center_single_column: bool,
     #[knuffel(child)]
     pub empty_workspace_above_first: bool,
+    #[knuffel(child, unwrap(argument), default)]
+    pub new_column_position: NewColumnPosition,
+    #[knuffel(child, unwrap(argument), default)]
+    pub new_window_position_in_column: NewWindowPositionInColumn,
+    #[knuffel(child, unwrap(argument), default)]
+    pub reveal_new_windows: RevealNewWindows,
     #[knuffel(child, unwrap(argument), default = Self::default().gaps)]
     pub gaps: FloatOrInt<0, 65535>,
     #[knuffel(child, default)]
     pub struts: Struts,
+    #[knuffel(child, default)]
+    pub workspace_spillover: WorkspaceSpillover,
+    #[knuffel(child, unwrap(argument))]
+    pub floating_min_visible: Option<FloatOrInt<1, 65535>>,
+    #[knuffel(child, unwrap(argument), default = true)]
+    pub move_window_to_workspace_focus: bool,
+    #[knuffel(child, default)]
+    pub min_window_size: MinWindowSize,
 }
 
 impl Default for Layout {
@@ -474,9 +707,16 @@ impl Default for Layout {
             center_focused_column: Default::default(),
             always_center_single_column: false,
             empty_workspace_above_first: false,
+            new_column_position: Default::default(),
+            new_window_position_in_column: Default::default(),
+            reveal_new_windows: Default::default(),
             gaps: FloatOrInt(16.),
             struts: Default::default(),
             preset_window_heights: Default::default(),
+            workspace_spillover: Default::default(),
+            floating_min_visible: None,
+            move_window_to_workspace_focus: true,
+            min_window_size: Default::default(),
         }
     }
 }
@@ -485,6 +725,12 @@ impl Default for Layout {
 pub struct SpawnAtStartup {
     #[knuffel(arguments)]
     pub command: Vec<String>,
+    #[knuffel(property, default)]
+    pub restart_on_crash: bool,
+    #[knuffel(property)]
+    pub only_on_output: Option<String>,
+    #[knuffel(property)]
+    pub delay_ms: Option<u32>,
 }
 
 #[derive(knuffel::Decode, Debug, Clone, Copy, PartialEq)]
@@ -796,12 +1042,268 @@ pub struct Struts {
 pub struct HotkeyOverlay {
     #[knuffel(child)]
     pub skip_at_startup: bool,
+    #[knuffel(child, unwrap(argument))]
+    pub show_on_mod_hold_ms: Option<u32>,
+}
+
+#[derive(knuffel::Decode, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CrashPlaceholder {
+    #[knuffel(child, unwrap(argument), default = 5000)]
+    pub timeout_ms: u32,
+}
+
+#[derive(knuffel::Decode, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StartupPlaceholder {
+    #[knuffel(child)]
+    pub off: bool,
+}
+
+#[derive(knuffel::Decode, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Urgency {
+    #[knuffel(child, unwrap(argument))]
+    pub reminder_interval_ms: Option<u32>,
+}
+
+#[derive(knuffel::Decode, Debug, Clone, Copy, PartialEq)]
+pub struct StatusBar {
+    #[knuffel(child)]
+    pub on: bool,
+    #[knuffel(child, unwrap(argument), default = Self::default().height)]
+    pub height: u16,
+    #[knuffel(child, default = Self::default().background_color)]
+    pub background_color: Color,
+    #[knuffel(child, default = Self::default().text_color)]
+    pub text_color: Color,
+    #[knuffel(child, default = Self::default().active_workspace_color)]
+    pub active_workspace_color: Color,
+}
+
+impl Default for StatusBar {
+    fn default() -> Self {
+        Self {
+            on: false,
+            height: 24,
+            background_color: Color::from_rgba8_unpremul(0x1a, 0x1a, 0x1a, 0xff),
+            text_color: Color::from_rgba8_unpremul(0xdd, 0xdd, 0xdd, 0xff),
+            active_workspace_color: Color::from_rgba8_unpremul(0x7f, 0xc8, 0xff, 0x80),
+        }
+    }
+}
+
+#[derive(knuffel::Decode, Debug, Clone, PartialEq)]
+pub struct Watermark {
+    #[knuffel(child)]
+    pub on: bool,
+    #[knuffel(child, unwrap(argument))]
+    pub text: Option<String>,
+    #[knuffel(child, unwrap(argument))]
+    pub image: Option<String>,
+    #[knuffel(child, unwrap(argument), default = Self::default().position)]
+    pub position: RelativeTo,
+    #[knuffel(child, unwrap(argument), default = Self::default().opacity)]
+    pub opacity: FloatOrInt<0, 1>,
+    #[knuffel(child, unwrap(arguments), default)]
+    pub outputs: Vec<String>,
+    #[knuffel(child)]
+    pub screencast_only: bool,
+}
+
+impl Default for Watermark {
+    fn default() -> Self {
+        Self {
+            on: false,
+            text: None,
+            image: None,
+            position: RelativeTo::BottomRight,
+            opacity: FloatOrInt(0.5),
+            outputs: Vec::new(),
+            screencast_only: false,
+        }
+    }
+}
+
+#[derive(knuffel::Decode, Debug, Clone, PartialEq)]
+pub struct Screenshot {
+    /// Overrides the top-level `screenshot-path` when set.
+    #[knuffel(child)]
+    pub path: Option<ScreenshotPath>,
+    #[knuffel(child, default)]
+    pub format: ScreenshotFormat,
+    #[knuffel(child, unwrap(argument), default = Self::default().include_pointer)]
+    pub include_pointer: bool,
+    #[knuffel(child, default)]
+    pub feedback: ScreenshotFeedback,
+}
+
+impl Default for Screenshot {
+    fn default() -> Self {
+        Self {
+            path: None,
+            format: ScreenshotFormat::default(),
+            include_pointer: true,
+            feedback: ScreenshotFeedback::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScreenshotPath(pub Option<String>);
+
+#[derive(knuffel::Decode, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScreenshotFormat {
+    #[knuffel(argument, default)]
+    pub kind: ImageFormat,
+    #[knuffel(property, default = Self::default().quality)]
+    pub quality: u8,
+}
+
+impl Default for ScreenshotFormat {
+    fn default() -> Self {
+        Self {
+            kind: ImageFormat::default(),
+            quality: 90,
+        }
+    }
+}
+
+#[derive(knuffel::Decode, Debug, Clone, Copy, PartialEq)]
+pub struct ScreenshotFeedback {
+    #[knuffel(argument, default)]
+    pub kind: ScreenshotFeedbackKind,
+    #[knuffel(property, default = Self::default().duration_ms)]
+    pub duration_ms: u16,
+    #[knuffel(property, str)]
+    pub color: Option<Color>,
+}
+
+impl Default for ScreenshotFeedback {
+    fn default() -> Self {
+        Self {
+            kind: ScreenshotFeedbackKind::default(),
+            duration_ms: 150,
+            color: None,
+        }
+    }
+}
+
+#[derive(knuffel::DecodeScalar, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ScreenshotFeedbackKind {
+    #[default]
+    None,
+    Flash,
+    Shutter,
+}
+
+#[derive(knuffel::DecodeScalar, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    #[default]
+    Png,
+    Jpeg,
+    Webp,
+}
+
+impl ImageFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Webp => "webp",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Jpeg => "image/jpeg",
+            ImageFormat::Webp => "image/webp",
+        }
+    }
+}
+
+#[derive(knuffel::Decode, Debug, Clone, PartialEq, Eq)]
+pub struct ScreenRecording {
+    #[knuffel(child, unwrap(argument), default = Self::default().path)]
+    pub path: String,
+}
+
+impl Default for ScreenRecording {
+    fn default() -> Self {
+        Self {
+            path: String::from("~/Videos/Screencasts/Screencast from %Y-%m-%d %H-%M-%S.mp4"),
+        }
+    }
+}
+
+#[derive(knuffel::Decode, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Screencast {
+    #[knuffel(child, unwrap(argument), default)]
+    pub default_cursor_mode: ScreencastCursorMode,
+    #[knuffel(child, unwrap(argument))]
+    pub max_fps: Option<u32>,
+}
+
+#[derive(knuffel::DecodeScalar, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ScreencastCursorMode {
+    /// The cursor is not shown in the screencast.
+    Hidden,
+    /// The cursor is drawn into the screencast frames.
+    #[default]
+    Embedded,
+    /// The cursor is sent alongside the frames as metadata for the viewer to draw.
+    Metadata,
 }
 
 #[derive(knuffel::Decode, Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub struct Clipboard {
     #[knuffel(child)]
     pub disable_primary: bool,
+    #[knuffel(child, unwrap(argument))]
+    pub history_size: Option<u16>,
+    #[knuffel(child)]
+    pub sync_primary_to_clipboard: bool,
+    #[knuffel(child)]
+    pub sync_clipboard_to_primary: bool,
+}
+
+#[derive(knuffel::Decode, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DragAndDrop {
+    #[knuffel(child, unwrap(argument))]
+    pub default_action: Option<DndAction>,
+    #[knuffel(child, unwrap(argument))]
+    pub copy_modifier: Option<DndModifier>,
+    #[knuffel(child, unwrap(argument))]
+    pub move_modifier: Option<DndModifier>,
+    #[knuffel(child)]
+    pub show_action_indicator: bool,
+}
+
+#[derive(knuffel::Decode, Debug, Default, Clone, PartialEq, Eq)]
+pub struct Metrics {
+    /// Address to serve the metrics on. The exporter is disabled when unset.
+    #[knuffel(child, unwrap(argument))]
+    pub listen: Option<String>,
+    /// Whether to split per-output metrics by output name.
+    #[knuffel(child)]
+    pub label_outputs: bool,
+    /// Whether to split IPC request metrics by request type.
+    #[knuffel(child)]
+    pub label_ipc_requests: bool,
+}
+
+#[derive(knuffel::DecodeScalar, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DndAction {
+    Copy,
+    Move,
+    /// Let the user pick the action upon drop.
+    Ask,
+}
+
+#[derive(knuffel::DecodeScalar, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DndModifier {
+    Ctrl,
+    Shift,
+    Alt,
+    Super,
 }
 
 #[derive(knuffel::Decode, Debug, Clone, PartialEq)]
@@ -1038,11 +1540,33 @@ pub struct Workspace {
     pub name: WorkspaceName,
     #[knuffel(child, unwrap(argument))]
     pub open_on_output: Option<String>,
+    #[knuffel(child)]
+    pub return_on_connect: bool,
+    #[knuffel(child, unwrap(argument))]
+    pub icon: Option<String>,
+    #[knuffel(child, unwrap(argument, str))]
+    pub auto_close_if_empty_after: Option<HumanDuration>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct WorkspaceName(pub String);
 
+#[derive(knuffel::Decode, Debug, Default, Clone, PartialEq)]
+pub struct WorkspaceAutoName {
+    #[knuffel(children(name = "match"))]
+    pub rules: Vec<WorkspaceAutoNameRule>,
+}
+
+#[derive(knuffel::Decode, Debug, Clone, PartialEq)]
+pub struct WorkspaceAutoNameRule {
+    #[knuffel(property, str)]
+    pub app_id: Option<RegexEq>,
+    #[knuffel(property, str)]
+    pub title: Option<RegexEq>,
+    #[knuffel(property)]
+    pub name: String,
+}
+
 #[derive(knuffel::Decode, Debug, Default, Clone, PartialEq)]
 pub struct WindowRule {
     #[knuffel(children(name = "match"))]
@@ -1060,6 +1584,8 @@ pub struct WindowRule {
     #[knuffel(child, unwrap(argument))]
     pub open_on_workspace: Option<String>,
     #[knuffel(child, unwrap(argument))]
+    pub open_on_workspace_fallback: Option<OpenOnWorkspaceFallback>,
+    #[knuffel(child, unwrap(argument))]
     pub open_maximized: Option<bool>,
     #[knuffel(child, unwrap(argument))]
     pub open_fullscreen: Option<bool>,
@@ -1067,6 +1593,12 @@ pub struct WindowRule {
     pub open_floating: Option<bool>,
     #[knuffel(child, unwrap(argument))]
     pub open_focused: Option<bool>,
+    #[knuffel(child, unwrap(argument))]
+    pub never_steal_focus: Option<bool>,
+    #[knuffel(child, unwrap(argument))]
+    pub reveal_on_open: Option<RevealNewWindows>,
+    #[knuffel(child, unwrap(argument, str))]
+    pub force_initial_size: Option<ForcedSize>,
 
     // Rules applied dynamically.
     #[knuffel(child, unwrap(argument))]
@@ -1077,6 +1609,8 @@ pub struct WindowRule {
     pub max_width: Option<u16>,
     #[knuffel(child, unwrap(argument))]
     pub max_height: Option<u16>,
+    #[knuffel(child)]
+    pub fullscreen_size: Option<FullscreenSize>,
 
     #[knuffel(child, default)]
     pub focus_ring: BorderRule,
@@ -1087,12 +1621,16 @@ pub struct WindowRule {
     #[knuffel(child, unwrap(argument))]
     pub draw_border_with_background: Option<bool>,
     #[knuffel(child, unwrap(argument))]
+    pub draw_titlebar: Option<bool>,
+    #[knuffel(child, unwrap(argument))]
     pub opacity: Option<f32>,
     #[knuffel(child)]
     pub geometry_corner_radius: Option<CornerRadius>,
     #[knuffel(child, unwrap(argument))]
     pub clip_to_geometry: Option<bool>,
     #[knuffel(child, unwrap(argument))]
+    pub clip_csd_shadow: Option<bool>,
+    #[knuffel(child, unwrap(argument))]
     pub block_out_from: Option<BlockOutFrom>,
     #[knuffel(child, unwrap(argument))]
     pub variable_refresh_rate: Option<bool>,
@@ -1100,6 +1638,14 @@ pub struct WindowRule {
     pub default_floating_position: Option<FloatingPosition>,
     #[knuffel(child, unwrap(argument))]
     pub scroll_factor: Option<FloatOrInt<0, 100>>,
+    #[knuffel(child, unwrap(argument))]
+    pub warp_mouse_to_focus: Option<bool>,
+    #[knuffel(child, unwrap(argument))]
+    pub picture_in_picture: Option<bool>,
+    #[knuffel(child, unwrap(argument))]
+    pub click_through: Option<bool>,
+    #[knuffel(child, unwrap(argument))]
+    pub confirm_close: Option<bool>,
 }
 
 #[derive(knuffel::Decode, Debug, Default, Clone, PartialEq)]
@@ -1108,6 +1654,10 @@ pub struct Match {
     pub app_id: Option<RegexEq>,
     #[knuffel(property, str)]
     pub title: Option<RegexEq>,
+    #[knuffel(property, str)]
+    pub mark: Option<RegexEq>,
+    #[knuffel(property, str)]
+    pub pwa_name: Option<RegexEq>,
     #[knuffel(property)]
     pub is_active: Option<bool>,
     #[knuffel(property)]
@@ -1117,7 +1667,45 @@ pub struct Match {
     #[knuffel(property)]
     pub is_floating: Option<bool>,
     #[knuffel(property)]
+    pub is_fullscreen: Option<bool>,
+    #[knuffel(property)]
+    pub is_maximized: Option<bool>,
+    #[knuffel(property)]
     pub at_startup: Option<bool>,
+    #[knuffel(property)]
+    pub is_x11: Option<bool>,
+}
+
+/// What to do when the workspace from `open-on-workspace` doesn't exist.
+#[derive(knuffel::DecodeScalar, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OpenOnWorkspaceFallback {
+    /// Open the window as if `open-on-workspace` wasn't set.
+    #[default]
+    Current,
+    /// Create a named workspace and open the window there.
+    Create,
+    /// Hold back the window until the workspace appears.
+    Wait,
+}
+
+/// Window size forced by a window rule, in logical pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ForcedSize {
+    pub width: u16,
+    pub height: u16,
+}
+
+/// Duration written with units, like `90s`, `5m` or `1h30m`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HumanDuration(pub Duration);
+
+/// Size requested from a fullscreen window instead of the output size, in logical pixels.
+#[derive(knuffel::Decode, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FullscreenSize {
+    #[knuffel(child, unwrap(argument))]
+    pub width: u16,
+    #[knuffel(child, unwrap(argument))]
+    pub height: u16,
 }
 
 #[derive(Debug, Default, Clone, Copy, PartialEq)]
@@ -1205,6 +1793,56 @@ pub enum RelativeTo {
 #[derive(Debug, Default, PartialEq)]
 pub struct Binds(pub Vec<Bind>);
 
+impl Binds {
+    /// Binds used when the config has none, so that the session remains usable.
+    ///
+    /// Super+Shift+T spawns a terminal and Super+Shift+E quits.
+    ///
+    /// The terminal is `$TERMINAL` if set, otherwise the first one found among common terminals.
+    pub fn emergency() -> Self {
+        let bind = |keysym, action| Bind {
+            key: Key {
+                trigger: Trigger::Keysym(keysym),
+                modifiers: Modifiers::SUPER | Modifiers::SHIFT,
+            },
+            action,
+            repeat: false,
+            cooldown: None,
+            allow_when_locked: false,
+            // Clients must not be able to take these away with a shortcut inhibitor.
+            allow_inhibiting: false,
+            warp_mouse_to_focus: true,
+        };
+
+        Self(vec![
+            bind(
+                Keysym::t,
+                Action::Spawn(
+                    vec![
+                        String::from("sh"),
+                        String::from("-c"),
+                        String::from(EMERGENCY_TERMINAL_SCRIPT),
+                    ],
+                    vec![],
+                ),
+            ),
+            bind(Keysym::e, Action::Quit(false)),
+        ])
+    }
+}
+
+/// Runs `$TERMINAL`, or the first common terminal that is installed.
+const EMERGENCY_TERMINAL_SCRIPT: &str = r#"
+if [ -n "$TERMINAL" ]; then exec $TERMINAL; fi
+for t in xdg-terminal-exec alacritty foot kitty wezterm ghostty gnome-terminal konsole xterm; do
+    if command -v "$t" >/dev/null; then exec "$t"; fi
+done
+"#;
+
+/// Scroll binds that trigger when the pointer is over an empty area of the workspace.
+#[derive(Debug, Default, PartialEq)]
+pub struct BackgroundBinds(pub Binds);
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Bind {
     pub key: Key,
@@ -1213,6 +1851,7 @@ pub struct Bind {
     pub cooldown: Option<Duration>,
     pub allow_when_locked: bool,
     pub allow_inhibiting: bool,
+    pub warp_mouse_to_focus: bool,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
@@ -1239,6 +1878,22 @@ pub enum Trigger {
     TouchpadScrollRight,
 }
 
+impl Trigger {
+    pub fn is_scroll(self) -> bool {
+        matches!(
+            self,
+            Trigger::WheelScrollDown
+                | Trigger::WheelScrollUp
+                | Trigger::WheelScrollLeft
+                | Trigger::WheelScrollRight
+                | Trigger::TouchpadScrollDown
+                | Trigger::TouchpadScrollUp
+                | Trigger::TouchpadScrollLeft
+                | Trigger::TouchpadScrollRight
+        )
+    }
+}
+
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
     pub struct Modifiers : u8 {
@@ -1279,10 +1934,32 @@ pub enum Action {
     Suspend,
     PowerOffMonitors,
     PowerOnMonitors,
+    SetOutputColorTemperature(
+        #[knuffel(argument)] u32,
+        #[knuffel(property(name = "output"))] Option<String>,
+    ),
+    SetOutputBrightness(
+        #[knuffel(argument, str)] BrightnessChange,
+        #[knuffel(property(name = "output"))] Option<String>,
+    ),
     ToggleDebugTint,
     DebugToggleOpaqueRegions,
     DebugToggleDamage,
-    Spawn(#[knuffel(arguments)] Vec<String>),
+    DebugToggleAnimationClockFreeze,
+    ClearBindCooldowns,
+    DebugStepAnimationClock(#[knuffel(argument)] u32),
+    Spawn(
+        #[knuffel(arguments)] Vec<String>,
+        #[knuffel(children)] Vec<EnvironmentVariable>,
+    ),
+    SpawnWithRules(
+        #[knuffel(arguments)] Vec<String>,
+        #[knuffel(property(name = "open-floating"))] Option<bool>,
+        #[knuffel(property(name = "open-fullscreen"))] Option<bool>,
+        #[knuffel(property(name = "default-width"))] Option<u16>,
+        #[knuffel(property(name = "default-height"))] Option<u16>,
+        #[knuffel(property(name = "here"), default)] bool,
+    ),
     DoScreenTransition(#[knuffel(property(name = "delay-ms"))] Option<u16>),
     #[knuffel(skip)]
     ConfirmScreenshot {
@@ -1300,6 +1977,14 @@ pub enum Action {
         id: u64,
         write_to_disk: bool,
     },
+    ToggleScreenRecord,
+    ToggleScreenRecordWindow,
+    #[knuffel(skip)]
+    ToggleScreenRecordWindowById(u64),
+    SetDynamicCastTarget,
+    #[knuffel(skip)]
+    SetDynamicCastTargetById(u64),
+    SetCastMaxFps(#[knuffel(argument)] u32),
     ToggleKeyboardShortcutsInhibit,
     CloseWindow,
     #[knuffel(skip)]
@@ -1310,6 +1995,7 @@ pub enum Action {
     #[knuffel(skip)]
     FocusWindow(u64),
     FocusWindowPrevious,
+    FocusPreviousInWorkspace,
     FocusColumnLeft,
     FocusColumnRight,
     FocusColumnFirst,
@@ -1356,17 +2042,31 @@ pub enum Action {
     FocusWorkspaceUp,
     FocusWorkspace(#[knuffel(argument)] WorkspaceReference),
     FocusWorkspacePrevious,
-    MoveWindowToWorkspaceDown,
-    MoveWindowToWorkspaceUp,
-    MoveWindowToWorkspace(#[knuffel(argument)] WorkspaceReference),
+    MoveWindowToWorkspaceDown(#[knuffel(property(name = "focus"))] Option<bool>),
+    MoveWindowToWorkspaceUp(#[knuffel(property(name = "focus"))] Option<bool>),
+    MoveWindowToWorkspace(
+        #[knuffel(argument)] WorkspaceReference,
+        #[knuffel(property(name = "focus"))] Option<bool>,
+    ),
     #[knuffel(skip)]
     MoveWindowToWorkspaceById {
         window_id: u64,
         reference: WorkspaceReference,
+        focus: Option<bool>,
+    },
+    #[knuffel(skip)]
+    MoveWindowToWorkspaceOnOutput {
+        window_id: Option<u64>,
+        output: String,
+        index: usize,
+        focus: Option<bool>,
     },
-    MoveColumnToWorkspaceDown,
-    MoveColumnToWorkspaceUp,
-    MoveColumnToWorkspace(#[knuffel(argument)] WorkspaceReference),
+    MoveColumnToWorkspaceDown(#[knuffel(property(name = "focus"))] Option<bool>),
+    MoveColumnToWorkspaceUp(#[knuffel(property(name = "focus"))] Option<bool>),
+    MoveColumnToWorkspace(
+        #[knuffel(argument)] WorkspaceReference,
+        #[knuffel(property(name = "focus"))] Option<bool>,
+    ),
     MoveWorkspaceDown,
     MoveWorkspaceUp,
     MoveWorkspaceToIndex(#[knuffel(argument)] usize),
@@ -1375,6 +2075,13 @@ pub enum Action {
         new_idx: usize,
         reference: WorkspaceReference,
     },
+    SwapWorkspaces(
+        #[knuffel(argument)] WorkspaceReference,
+        #[knuffel(argument)] WorkspaceReference,
+    ),
+    SortWorkspacesByName,
+    CompactWorkspaces,
+    MoveWorkspacesToAssignedOutputs,
     #[knuffel(skip)]
     MoveWorkspaceToMonitorByRef {
         output_name: String,
@@ -1390,24 +2097,30 @@ pub enum Action {
     UnsetWorkspaceName,
     #[knuffel(skip)]
     UnsetWorkSpaceNameByRef(#[knuffel(argument)] WorkspaceReference),
+    RenameWorkspace,
+    ToggleStatusBar,
+    ToggleWatermark,
+    ShowClipboardHistory,
+    #[knuffel(skip)]
+    SetClipboardFromHistory(u64),
     FocusMonitorLeft,
     FocusMonitorRight,
     FocusMonitorDown,
     FocusMonitorUp,
     FocusMonitorPrevious,
     FocusMonitorNext,
-    MoveWindowToMonitorLeft,
-    MoveWindowToMonitorRight,
-    MoveWindowToMonitorDown,
-    MoveWindowToMonitorUp,
-    MoveWindowToMonitorPrevious,
-    MoveWindowToMonitorNext,
-    MoveColumnToMonitorLeft,
-    MoveColumnToMonitorRight,
-    MoveColumnToMonitorDown,
-    MoveColumnToMonitorUp,
-    MoveColumnToMonitorPrevious,
-    MoveColumnToMonitorNext,
+    MoveWindowToMonitorLeft(#[knuffel(property(name = "focus"))] Option<bool>),
+    MoveWindowToMonitorRight(#[knuffel(property(name = "focus"))] Option<bool>),
+    MoveWindowToMonitorDown(#[knuffel(property(name = "focus"))] Option<bool>),
+    MoveWindowToMonitorUp(#[knuffel(property(name = "focus"))] Option<bool>),
+    MoveWindowToMonitorPrevious(#[knuffel(property(name = "focus"))] Option<bool>),
+    MoveWindowToMonitorNext(#[knuffel(property(name = "focus"))] Option<bool>),
+    MoveColumnToMonitorLeft(#[knuffel(property(name = "focus"))] Option<bool>),
+    MoveColumnToMonitorRight(#[knuffel(property(name = "focus"))] Option<bool>),
+    MoveColumnToMonitorDown(#[knuffel(property(name = "focus"))] Option<bool>),
+    MoveColumnToMonitorUp(#[knuffel(property(name = "focus"))] Option<bool>),
+    MoveColumnToMonitorPrevious(#[knuffel(property(name = "focus"))] Option<bool>),
+    MoveColumnToMonitorNext(#[knuffel(property(name = "focus"))] Option<bool>),
     SetWindowWidth(#[knuffel(argument, str)] SizeChange),
     #[knuffel(skip)]
     SetWindowWidthById {
@@ -1449,6 +2162,14 @@ pub enum Action {
     MoveWindowToTiling,
     #[knuffel(skip)]
     MoveWindowToTilingById(u64),
+    ToggleWindowSelectMode,
+    ToggleWindowSelected,
+    #[knuffel(skip)]
+    ToggleWindowSelectedById(u64),
+    CloseSelectedWindows,
+    MoveSelectedWindowsToWorkspace(#[knuffel(argument)] WorkspaceReference),
+    MoveSelectedWindowsToFloating,
+    MoveSelectedWindowsToTiling,
     FocusFloating,
     FocusTiling,
     SwitchFocusBetweenFloatingAndTiling,
@@ -1458,9 +2179,33 @@ pub enum Action {
         x: PositionChange,
         y: PositionChange,
     },
+    SnapFloatingWindow(#[knuffel(argument, str)] SnapPosition),
+    #[knuffel(skip)]
+    SnapFloatingWindowById {
+        id: u64,
+        position: SnapPosition,
+    },
+    CyclePictureInPictureCorner,
+    #[knuffel(skip)]
+    CyclePictureInPictureCornerById(u64),
+    BringWindowOnScreen,
+    #[knuffel(skip)]
+    BringWindowOnScreenById(u64),
     ToggleWindowRuleOpacity,
     #[knuffel(skip)]
     ToggleWindowRuleOpacityById(u64),
+    SetWindowMark(#[knuffel(argument)] String),
+    #[knuffel(skip)]
+    SetWindowMarkById {
+        id: u64,
+        mark: String,
+    },
+    UnsetWindowMark(#[knuffel(argument)] Option<String>),
+    #[knuffel(skip)]
+    UnsetWindowMarkById {
+        id: u64,
+        mark: Option<String>,
+    },
 }
 
 impl From<niri_ipc::Action> for Action {
@@ -1469,7 +2214,28 @@ impl From<niri_ipc::Action> for Action {
             niri_ipc::Action::Quit { skip_confirmation } => Self::Quit(skip_confirmation),
             niri_ipc::Action::PowerOffMonitors {} => Self::PowerOffMonitors,
             niri_ipc::Action::PowerOnMonitors {} => Self::PowerOnMonitors,
-            niri_ipc::Action::Spawn { command } => Self::Spawn(command),
+            niri_ipc::Action::SetOutputColorTemperature { kelvin, output } => {
+                Self::SetOutputColorTemperature(kelvin, output)
+            }
+            niri_ipc::Action::SetOutputBrightness { change, output } => {
+                Self::SetOutputBrightness(change, output)
+            }
+            niri_ipc::Action::Spawn { command } => Self::Spawn(command, vec![]),
+            niri_ipc::Action::SpawnWithRules {
+                command,
+                open_floating,
+                open_fullscreen,
+                default_width,
+                default_height,
+                here,
+            } => Self::SpawnWithRules(
+                command,
+                open_floating,
+                open_fullscreen,
+                default_width,
+                default_height,
+                here,
+            ),
             niri_ipc::Action::DoScreenTransition { delay_ms } => Self::DoScreenTransition(delay_ms),
             niri_ipc::Action::Screenshot {} => Self::Screenshot,
             niri_ipc::Action::ScreenshotScreen { write_to_disk } => {
@@ -1483,12 +2249,25 @@ impl From<niri_ipc::Action> for Action {
                 id: Some(id),
                 write_to_disk,
             } => Self::ScreenshotWindowById { id, write_to_disk },
+            niri_ipc::Action::ToggleScreenRecord {} => Self::ToggleScreenRecord,
+            niri_ipc::Action::ToggleScreenRecordWindow { id: None } => {
+                Self::ToggleScreenRecordWindow
+            }
+            niri_ipc::Action::ToggleScreenRecordWindow { id: Some(id) } => {
+                Self::ToggleScreenRecordWindowById(id)
+            }
+            niri_ipc::Action::SetDynamicCastTarget { id: None } => Self::SetDynamicCastTarget,
+            niri_ipc::Action::SetDynamicCastTarget { id: Some(id) } => {
+                Self::SetDynamicCastTargetById(id)
+            }
+            niri_ipc::Action::SetCastMaxFps { fps } => Self::SetCastMaxFps(fps),
             niri_ipc::Action::CloseWindow { id: None } => Self::CloseWindow,
             niri_ipc::Action::CloseWindow { id: Some(id) } => Self::CloseWindowById(id),
             niri_ipc::Action::FullscreenWindow { id: None } => Self::FullscreenWindow,
             niri_ipc::Action::FullscreenWindow { id: Some(id) } => Self::FullscreenWindowById(id),
             niri_ipc::Action::FocusWindow { id } => Self::FocusWindow(id),
             niri_ipc::Action::FocusWindowPrevious {} => Self::FocusWindowPrevious,
+            niri_ipc::Action::FocusPreviousInWorkspace {} => Self::FocusPreviousInWorkspace,
             niri_ipc::Action::FocusColumnLeft {} => Self::FocusColumnLeft,
             niri_ipc::Action::FocusColumnRight {} => Self::FocusColumnRight,
             niri_ipc::Action::FocusColumnFirst {} => Self::FocusColumnFirst,
@@ -1548,23 +2327,45 @@ impl From<niri_ipc::Action> for Action {
                 Self::FocusWorkspace(WorkspaceReference::from(reference))
             }
             niri_ipc::Action::FocusWorkspacePrevious {} => Self::FocusWorkspacePrevious,
-            niri_ipc::Action::MoveWindowToWorkspaceDown {} => Self::MoveWindowToWorkspaceDown,
-            niri_ipc::Action::MoveWindowToWorkspaceUp {} => Self::MoveWindowToWorkspaceUp,
+            niri_ipc::Action::MoveWindowToWorkspaceDown { focus } => {
+                Self::MoveWindowToWorkspaceDown(focus)
+            }
+            niri_ipc::Action::MoveWindowToWorkspaceUp { focus } => {
+                Self::MoveWindowToWorkspaceUp(focus)
+            }
             niri_ipc::Action::MoveWindowToWorkspace {
                 window_id: None,
                 reference,
-            } => Self::MoveWindowToWorkspace(WorkspaceReference::from(reference)),
+                focus,
+            } => Self::MoveWindowToWorkspace(WorkspaceReference::from(reference), focus),
             niri_ipc::Action::MoveWindowToWorkspace {
                 window_id: Some(window_id),
                 reference,
+                focus,
             } => Self::MoveWindowToWorkspaceById {
                 window_id,
                 reference: WorkspaceReference::from(reference),
+                focus,
+            },
+            niri_ipc::Action::MoveWindowToWorkspaceOnOutput {
+                window_id,
+                output,
+                index,
+                focus,
+            } => Self::MoveWindowToWorkspaceOnOutput {
+                window_id,
+                output,
+                index,
+                focus,
             },
-            niri_ipc::Action::MoveColumnToWorkspaceDown {} => Self::MoveColumnToWorkspaceDown,
-            niri_ipc::Action::MoveColumnToWorkspaceUp {} => Self::MoveColumnToWorkspaceUp,
-            niri_ipc::Action::MoveColumnToWorkspace { reference } => {
-                Self::MoveColumnToWorkspace(WorkspaceReference::from(reference))
+            niri_ipc::Action::MoveColumnToWorkspaceDown { focus } => {
+                Self::MoveColumnToWorkspaceDown(focus)
+            }
+            niri_ipc::Action::MoveColumnToWorkspaceUp { focus } => {
+                Self::MoveColumnToWorkspaceUp(focus)
+            }
+            niri_ipc::Action::MoveColumnToWorkspace { reference, focus } => {
+                Self::MoveColumnToWorkspace(WorkspaceReference::from(reference), focus)
             }
             niri_ipc::Action::MoveWorkspaceDown {} => Self::MoveWorkspaceDown,
             niri_ipc::Action::MoveWorkspaceUp {} => Self::MoveWorkspaceUp,
@@ -1583,24 +2384,49 @@ impl From<niri_ipc::Action> for Action {
             niri_ipc::Action::UnsetWorkspaceName {
                 reference: Some(reference),
             } => Self::UnsetWorkSpaceNameByRef(WorkspaceReference::from(reference)),
+            niri_ipc::Action::RenameWorkspace {} => Self::RenameWorkspace,
+            niri_ipc::Action::ToggleStatusBar {} => Self::ToggleStatusBar,
+            niri_ipc::Action::ToggleWatermark {} => Self::ToggleWatermark,
+            niri_ipc::Action::ShowClipboardHistory {} => Self::ShowClipboardHistory,
+            niri_ipc::Action::SetClipboardFromHistory { id } => Self::SetClipboardFromHistory(id),
             niri_ipc::Action::FocusMonitorLeft {} => Self::FocusMonitorLeft,
             niri_ipc::Action::FocusMonitorRight {} => Self::FocusMonitorRight,
             niri_ipc::Action::FocusMonitorDown {} => Self::FocusMonitorDown,
             niri_ipc::Action::FocusMonitorUp {} => Self::FocusMonitorUp,
             niri_ipc::Action::FocusMonitorPrevious {} => Self::FocusMonitorPrevious,
             niri_ipc::Action::FocusMonitorNext {} => Self::FocusMonitorNext,
-            niri_ipc::Action::MoveWindowToMonitorLeft {} => Self::MoveWindowToMonitorLeft,
-            niri_ipc::Action::MoveWindowToMonitorRight {} => Self::MoveWindowToMonitorRight,
-            niri_ipc::Action::MoveWindowToMonitorDown {} => Self::MoveWindowToMonitorDown,
-            niri_ipc::Action::MoveWindowToMonitorUp {} => Self::MoveWindowToMonitorUp,
-            niri_ipc::Action::MoveWindowToMonitorPrevious {} => Self::MoveWindowToMonitorPrevious,
-            niri_ipc::Action::MoveWindowToMonitorNext {} => Self::MoveWindowToMonitorNext,
-            niri_ipc::Action::MoveColumnToMonitorLeft {} => Self::MoveColumnToMonitorLeft,
-            niri_ipc::Action::MoveColumnToMonitorRight {} => Self::MoveColumnToMonitorRight,
-            niri_ipc::Action::MoveColumnToMonitorDown {} => Self::MoveColumnToMonitorDown,
-            niri_ipc::Action::MoveColumnToMonitorUp {} => Self::MoveColumnToMonitorUp,
-            niri_ipc::Action::MoveColumnToMonitorPrevious {} => Self::MoveColumnToMonitorPrevious,
-            niri_ipc::Action::MoveColumnToMonitorNext {} => Self::MoveColumnToMonitorNext,
+            niri_ipc::Action::MoveWindowToMonitorLeft { focus } => {
+                Self::MoveWindowToMonitorLeft(focus)
+            }
+            niri_ipc::Action::MoveWindowToMonitorRight { focus } => {
+                Self::MoveWindowToMonitorRight(focus)
+            }
+            niri_ipc::Action::MoveWindowToMonitorDown { focus } => {
+                Self::MoveWindowToMonitorDown(focus)
+            }
+            niri_ipc::Action::MoveWindowToMonitorUp { focus } => Self::MoveWindowToMonitorUp(focus),
+            niri_ipc::Action::MoveWindowToMonitorPrevious { focus } => {
+                Self::MoveWindowToMonitorPrevious(focus)
+            }
+            niri_ipc::Action::MoveWindowToMonitorNext { focus } => {
+                Self::MoveWindowToMonitorNext(focus)
+            }
+            niri_ipc::Action::MoveColumnToMonitorLeft { focus } => {
+                Self::MoveColumnToMonitorLeft(focus)
+            }
+            niri_ipc::Action::MoveColumnToMonitorRight { focus } => {
+                Self::MoveColumnToMonitorRight(focus)
+            }
+            niri_ipc::Action::MoveColumnToMonitorDown { focus } => {
+                Self::MoveColumnToMonitorDown(focus)
+            }
+            niri_ipc::Action::MoveColumnToMonitorUp { focus } => Self::MoveColumnToMonitorUp(focus),
+            niri_ipc::Action::MoveColumnToMonitorPrevious { focus } => {
+                Self::MoveColumnToMonitorPrevious(focus)
+            }
+            niri_ipc::Action::MoveColumnToMonitorNext { focus } => {
+                Self::MoveColumnToMonitorNext(focus)
+            }
             niri_ipc::Action::SetWindowWidth { id: None, change } => Self::SetWindowWidth(change),
             niri_ipc::Action::SetWindowWidth {
                 id: Some(id),
@@ -1646,6 +2472,15 @@ impl From<niri_ipc::Action> for Action {
                 index,
                 reference: None,
             } => Self::MoveWorkspaceToIndex(index),
+            niri_ipc::Action::SwapWorkspaces { ref_a, ref_b } => Self::SwapWorkspaces(
+                WorkspaceReference::from(ref_a),
+                WorkspaceReference::from(ref_b),
+            ),
+            niri_ipc::Action::SortWorkspacesByName {} => Self::SortWorkspacesByName,
+            niri_ipc::Action::CompactWorkspaces {} => Self::CompactWorkspaces,
+            niri_ipc::Action::MoveWorkspacesToAssignedOutputs {} => {
+                Self::MoveWorkspacesToAssignedOutputs
+            }
             niri_ipc::Action::MoveWorkspaceToMonitor {
                 output,
                 reference: Some(reference),
@@ -1661,6 +2496,11 @@ impl From<niri_ipc::Action> for Action {
             niri_ipc::Action::ToggleDebugTint {} => Self::ToggleDebugTint,
             niri_ipc::Action::DebugToggleOpaqueRegions {} => Self::DebugToggleOpaqueRegions,
             niri_ipc::Action::DebugToggleDamage {} => Self::DebugToggleDamage,
+            niri_ipc::Action::DebugToggleAnimationClockFreeze {} => {
+                Self::DebugToggleAnimationClockFreeze
+            }
+            niri_ipc::Action::ClearBindCooldowns {} => Self::ClearBindCooldowns,
+            niri_ipc::Action::DebugStepAnimationClock { ms } => Self::DebugStepAnimationClock(ms),
             niri_ipc::Action::ToggleWindowFloating { id: None } => Self::ToggleWindowFloating,
             niri_ipc::Action::ToggleWindowFloating { id: Some(id) } => {
                 Self::ToggleWindowFloatingById(id)
@@ -1673,6 +2513,19 @@ impl From<niri_ipc::Action> for Action {
             niri_ipc::Action::MoveWindowToTiling { id: Some(id) } => {
                 Self::MoveWindowToTilingById(id)
             }
+            niri_ipc::Action::ToggleWindowSelectMode {} => Self::ToggleWindowSelectMode,
+            niri_ipc::Action::ToggleWindowSelected { id: None } => Self::ToggleWindowSelected,
+            niri_ipc::Action::ToggleWindowSelected { id: Some(id) } => {
+                Self::ToggleWindowSelectedById(id)
+            }
+            niri_ipc::Action::CloseSelectedWindows {} => Self::CloseSelectedWindows,
+            niri_ipc::Action::MoveSelectedWindowsToWorkspace { reference } => {
+                Self::MoveSelectedWindowsToWorkspace(WorkspaceReference::from(reference))
+            }
+            niri_ipc::Action::MoveSelectedWindowsToFloating {} => {
+                Self::MoveSelectedWindowsToFloating
+            }
+            niri_ipc::Action::MoveSelectedWindowsToTiling {} => Self::MoveSelectedWindowsToTiling,
             niri_ipc::Action::FocusFloating {} => Self::FocusFloating,
             niri_ipc::Action::FocusTiling {} => Self::FocusTiling,
             niri_ipc::Action::SwitchFocusBetweenFloatingAndTiling {} => {
@@ -1681,10 +2534,35 @@ impl From<niri_ipc::Action> for Action {
             niri_ipc::Action::MoveFloatingWindow { id, x, y } => {
                 Self::MoveFloatingWindowById { id, x, y }
             }
+            niri_ipc::Action::SnapFloatingWindow { id: None, position } => {
+                Self::SnapFloatingWindow(position)
+            }
+            niri_ipc::Action::SnapFloatingWindow {
+                id: Some(id),
+                position,
+            } => Self::SnapFloatingWindowById { id, position },
+            niri_ipc::Action::CyclePictureInPictureCorner { id: None } => {
+                Self::CyclePictureInPictureCorner
+            }
+            niri_ipc::Action::CyclePictureInPictureCorner { id: Some(id) } => {
+                Self::CyclePictureInPictureCornerById(id)
+            }
+            niri_ipc::Action::BringWindowOnScreen { id: None } => Self::BringWindowOnScreen,
+            niri_ipc::Action::BringWindowOnScreen { id: Some(id) } => {
+                Self::BringWindowOnScreenById(id)
+            }
             niri_ipc::Action::ToggleWindowRuleOpacity { id: None } => Self::ToggleWindowRuleOpacity,
             niri_ipc::Action::ToggleWindowRuleOpacity { id: Some(id) } => {
                 Self::ToggleWindowRuleOpacityById(id)
             }
+            niri_ipc::Action::SetWindowMark { id: None, mark } => Self::SetWindowMark(mark),
+            niri_ipc::Action::SetWindowMark { id: Some(id), mark } => {
+                Self::SetWindowMarkById { id, mark }
+            }
+            niri_ipc::Action::UnsetWindowMark { id: None, mark } => Self::UnsetWindowMark(mark),
+            niri_ipc::Action::UnsetWindowMark { id: Some(id), mark } => {
+                Self::UnsetWindowMarkById { id, mark }
+            }
         }
     }
 }
@@ -1851,6 +2729,14 @@ pub enum PreviewRender {
 }
 
 impl Config {
+    /// Returns the screenshot path template, or `None` if screenshots shouldn't be saved to disk.
+    pub fn screenshot_path_template(&self) -> Option<&str> {
+        match &self.screenshot.path {
+            Some(ScreenshotPath(path)) => path.as_deref(),
+            None => self.screenshot_path.as_deref(),
+        }
+    }
+
     pub fn load(path: &Path) -> miette::Result<Self> {
         let _span = tracy_client::span!("Config::load");
         Self::load_internal(path).context("error loading config")
@@ -2263,6 +3149,34 @@ impl Outputs {
     pub fn find_mut(&mut self, name: &OutputName) -> Option<&mut Output> {
         self.0.iter_mut().find(|o| name.matches(&o.name))
     }
+
+    /// Returns these outputs with the profile outputs in front, so that they take precedence.
+    pub fn with_profile(&self, profile: Option<&OutputProfile>) -> Self {
+        let profile_outputs = profile.into_iter().flat_map(|p| &p.outputs.0);
+        Self(profile_outputs.chain(&self.0).cloned().collect())
+    }
+}
+
+impl OutputProfiles {
+    /// Returns the first profile that describes exactly this set of connected outputs.
+    pub fn find_matching(&self, connected: &[OutputName]) -> Option<&OutputProfile> {
+        self.0.iter().find(|p| p.matches(connected))
+    }
+}
+
+impl OutputProfile {
+    /// Returns whether this profile describes exactly this set of connected outputs.
+    pub fn matches(&self, connected: &[OutputName]) -> bool {
+        self.outputs.0.len() == connected.len()
+            && connected
+                .iter()
+                .all(|name| self.outputs.find(name).is_some())
+            && self
+                .outputs
+                .0
+                .iter()
+                .all(|output| connected.iter().any(|name| name.matches(&output.name)))
+    }
 }
 
 impl OutputName {
@@ -2372,6 +3286,18 @@ impl OutputName {
     }
 }
 
+impl<S> knuffel::Decode<S> for ScreenshotPath
+where
+    S: knuffel::traits::ErrorSpan,
+{
+    fn decode_node(
+        node: &knuffel::ast::SpannedNode<S>,
+        ctx: &mut knuffel::decode::Context<S>,
+    ) -> Result<Self, DecodeError<S>> {
+        parse_arg_node("path", node, ctx).map(Self)
+    }
+}
+
 impl<S> knuffel::Decode<S> for DefaultPresetSize
 where
     S: knuffel::traits::ErrorSpan,
@@ -2392,9 +3318,54 @@ where
                     "expected no more than one child",
                 ));
             }
-            PresetSize::decode_node(child, ctx).map(Some).map(Self)
-        } else {
-            Ok(Self(None))
+            PresetSize::decode_node(child, ctx).map(Some).map(Self)
+        } else {
+            Ok(Self(None))
+        }
+    }
+}
+
+impl<S> knuffel::Decode<S> for WorkspaceSpillover
+where
+    S: knuffel::traits::ErrorSpan,
+{
+    fn decode_node(
+        node: &knuffel::ast::SpannedNode<S>,
+        ctx: &mut knuffel::decode::Context<S>,
+    ) -> Result<Self, DecodeError<S>> {
+        expect_only_children(node, ctx);
+
+        let mut children = node.children();
+
+        let Some(child) = children.next() else {
+            return Err(DecodeError::missing(
+                node,
+                "expected one of `primary`, `output` or `distribute`",
+            ));
+        };
+        if let Some(unwanted_child) = children.next() {
+            ctx.emit_error(DecodeError::unexpected(
+                unwanted_child,
+                "node",
+                "expected no more than one child",
+            ));
+        }
+
+        match &**child.node_name {
+            "primary" => {
+                knuffel::decode::check_flag_node(child, ctx);
+                Ok(Self::Primary)
+            }
+            "output" => parse_arg_node("name", child, ctx).map(Self::Output),
+            "distribute" => {
+                knuffel::decode::check_flag_node(child, ctx);
+                Ok(Self::Distribute)
+            }
+            name_str => Err(DecodeError::unexpected(
+                child,
+                "node",
+                format!("unexpected node `{}`", name_str.escape_default()),
+            )),
         }
     }
 }
@@ -3046,6 +4017,33 @@ where
     }
 }
 
+impl<S> knuffel::Decode<S> for BackgroundBinds
+where
+    S: knuffel::traits::ErrorSpan,
+{
+    fn decode_node(
+        node: &knuffel::ast::SpannedNode<S>,
+        ctx: &mut knuffel::decode::Context<S>,
+    ) -> Result<Self, DecodeError<S>> {
+        for child in node.children() {
+            // Invalid keys will be reported when decoding the binds.
+            let Ok(key) = child.node_name.parse::<Key>() else {
+                continue;
+            };
+
+            if !key.trigger.is_scroll() {
+                ctx.emit_error(DecodeError::unexpected(
+                    &child.node_name,
+                    "keybind",
+                    "only scroll binds are supported in background-binds",
+                ));
+            }
+        }
+
+        Binds::decode_node(node, ctx).map(Self)
+    }
+}
+
 impl<S> knuffel::Decode<S> for Bind
 where
     S: knuffel::traits::ErrorSpan,
@@ -3080,6 +4078,7 @@ where
         let mut allow_when_locked = false;
         let mut allow_when_locked_node = None;
         let mut allow_inhibiting = true;
+        let mut warp_mouse_to_focus = true;
         for (name, val) in &node.properties {
             match &***name {
                 "repeat" => {
@@ -3097,6 +4096,9 @@ where
                 "allow-inhibiting" => {
                     allow_inhibiting = knuffel::traits::DecodeScalar::decode(val, ctx)?;
                 }
+                "warp-mouse-to-focus" => {
+                    warp_mouse_to_focus = knuffel::traits::DecodeScalar::decode(val, ctx)?;
+                }
                 name_str => {
                     ctx.emit_error(DecodeError::unexpected(
                         name,
@@ -3114,11 +4116,12 @@ where
         // even if their contents are not valid.
         let dummy = Self {
             key,
-            action: Action::Spawn(vec![]),
+            action: Action::Spawn(vec![], vec![]),
             repeat: true,
             cooldown: None,
             allow_when_locked: false,
             allow_inhibiting: true,
+            warp_mouse_to_focus: true,
         };
 
         if let Some(child) = children.next() {
@@ -3131,7 +4134,7 @@ where
             }
             match Action::decode_node(child, ctx) {
                 Ok(action) => {
-                    if !matches!(action, Action::Spawn(_)) {
+                    if !matches!(action, Action::Spawn(..)) {
                         if let Some(node) = allow_when_locked_node {
                             ctx.emit_error(DecodeError::unexpected(
                                 node,
@@ -3154,6 +4157,7 @@ where
                         cooldown,
                         allow_when_locked,
                         allow_inhibiting,
+                        warp_mouse_to_focus,
                     })
                 }
                 Err(e) => {
@@ -3301,6 +4305,89 @@ impl FromStr for TapButtonMap {
     }
 }
 
+impl FromStr for ForcedSize {
+    type Err = miette::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((width, height)) = s.split_once('x') else {
+            return Err(miette!("size must be in the WIDTHxHEIGHT format"));
+        };
+
+        let width: u16 = width.parse().map_err(|_| miette!("error parsing width"))?;
+        let height: u16 = height
+            .parse()
+            .map_err(|_| miette!("error parsing height"))?;
+        if width == 0 || height == 0 {
+            return Err(miette!("width and height must be positive"));
+        }
+
+        Ok(Self { width, height })
+    }
+}
+
+impl FromStr for HumanDuration {
+    type Err = miette::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(miette!("duration is empty"));
+        }
+
+        let mut total = Duration::ZERO;
+        let mut rest = s;
+        while !rest.is_empty() {
+            let digits = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            if digits == 0 {
+                return Err(miette!("expected a duration like 90s, 5m or 1h30m"));
+            }
+            let value: u32 = rest[..digits]
+                .parse()
+                .map_err(|_| miette!("error parsing value"))?;
+            rest = &rest[digits..];
+
+            let unit_len = rest
+                .find(|c: char| c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let unit = match &rest[..unit_len] {
+                "ms" => Duration::from_millis(1),
+                "s" => Duration::from_secs(1),
+                "m" => Duration::from_secs(60),
+                "h" => Duration::from_secs(60 * 60),
+                "" => return Err(miette!("value is missing a unit (ms, s, m or h)")),
+                _ => return Err(miette!("unknown unit, expected ms, s, m or h")),
+            };
+            rest = &rest[unit_len..];
+            total += unit * value;
+        }
+
+        Ok(Self(total))
+    }
+}
+
+impl FromStr for SplitRatio {
+    type Err = miette::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((first, second)) = s.split_once(':') else {
+            return Err(miette!("ratio must be in the A:B format"));
+        };
+
+        let first: u16 = first
+            .parse()
+            .map_err(|_| miette!("error parsing first part"))?;
+        let second: u16 = second
+            .parse()
+            .map_err(|_| miette!("error parsing second part"))?;
+        if first == 0 || second == 0 {
+            return Err(miette!("ratio parts must be positive"));
+        }
+
+        Ok(Self { first, second })
+    }
+}
+
 impl FromStr for Percent {
     type Err = miette::Error;
 
@@ -3358,6 +4445,7 @@ mod tests {
                 touchpad {
                     tap
                     dwt
+                    dwt-exclude-keyboards "Foot Pedal" "Macro Pad"
                     dwtp
                     click-method "clickfinger"
                     accel-speed 0.2
@@ -3367,6 +4455,7 @@ mod tests {
                     tap-button-map "left-middle-right"
                     disabled-on-external-mouse
                     scroll-factor 0.9
+                    invert-workspace-swipe
                 }
 
                 mouse {
@@ -3405,6 +4494,9 @@ mod tests {
 
                 touch {
                     map-to-output "eDP-1"
+                    rotate-with-output false
+                    resize-edge-width 16
+                    two-finger-resize
                 }
 
                 disable-power-key-handling
@@ -3412,17 +4504,40 @@ mod tests {
                 warp-mouse-to-focus
                 focus-follows-mouse
                 workspace-auto-back-and-forth
+                mod-resize-nearest-corner
+
+                click-to-focus {
+                    floating "consume"
+                    other-output "consume"
+                }
             }
 
             output "eDP-1" {
                 scale 2
                 transform "flipped-90"
+                auto-rotate
                 position x=10 y=20
                 mode "1920x1080@144"
                 variable-refresh-rate on-demand=true
+                max-render-fps 60
+                color-temperature 4500
+                mirror "HDMI-A-1"
+                split "2:1" direction="vertical"
                 background-color "rgba(25, 25, 102, 1.0)"
             }
 
+            profiles {
+                profile "docked" {
+                    output "eDP-1" {
+                        off
+                    }
+                    output "HDMI-A-1" {
+                        position x=0 y=0
+                    }
+                    workspace "workspace-1" output="HDMI-A-1"
+                }
+            }
+
             layout {
                 focus-ring {
                     width 5
@@ -3466,6 +4581,15 @@ mod tests {
 
                 center-focused-column "on-overflow"
 
+                new-window-position-in-column "after-focused"
+
+                workspace-spillover { output "HDMI-A-1"; }
+
+                floating-min-visible 100
+                move-window-to-workspace-focus false
+                min-window-size width=200 height=150
+                reveal-new-windows "partial"
+
                 insert-hint {
                     color "rgb(255, 200, 127)"
                     gradient from="rgba(10, 20, 30, 1.0)" to="#0080ffff" relative-to="workspace-view"
@@ -3473,6 +4597,7 @@ mod tests {
             }
 
             spawn-at-startup "alacritty" "-e" "fish"
+            spawn-at-startup "waybar" restart-on-crash=true only-on-output="eDP-1" delay-ms=500
 
             prefer-no-csd
 
@@ -3485,12 +4610,70 @@ mod tests {
 
             screenshot-path "~/Screenshots/screenshot.png"
 
+            screenshot {
+                path "~/Screenshots/%{app-id}.jpg"
+                format "jpeg" quality=80
+                include-pointer false
+                feedback "flash" duration-ms=200 color="#ffffff80"
+            }
+
+            screen-recording {
+                path "~/Videos/%{app-id}.webm"
+            }
+
+            screencast {
+                default-cursor-mode "metadata"
+                max-fps 30
+            }
+
             clipboard {
                 disable-primary
+                history-size 50
+                sync-primary-to-clipboard
+            }
+
+            drag-and-drop {
+                default-action "move"
+                copy-modifier "ctrl"
+                move-modifier "shift"
+                show-action-indicator
             }
 
             hotkey-overlay {
                 skip-at-startup
+                show-on-mod-hold-ms 700
+            }
+
+            crash-placeholder {
+                timeout-ms 3000
+            }
+
+            startup-placeholder {
+                off
+            }
+
+            urgency {
+                reminder-interval-ms 60000
+            }
+
+            status-bar {
+                on
+                height 28
+                active-workspace-color "#ff0000"
+            }
+
+            watermark {
+                on
+                text "DEMO"
+                position "top-right"
+                opacity 0.25
+                outputs "eDP-1" "HDMI-A-1"
+                screencast-only
+            }
+
+            metrics {
+                listen "127.0.0.1:9464"
+                label-outputs
             }
 
             animations {
@@ -3515,16 +4698,32 @@ mod tests {
 
             window-rule {
                 match app-id=".*alacritty"
-                exclude title="~"
+                exclude title="~" mark="^scratch$"
                 exclude is-active=true is-focused=false
+                exclude is-fullscreen=true is-maximized=false
+                exclude is-x11=true
+                exclude pwa-name="^YouTube Music$"
 
                 open-on-output "eDP-1"
+                open-on-workspace-fallback "create"
                 open-maximized true
                 open-fullscreen false
                 open-floating false
                 open-focused true
+                never-steal-focus true
+                reveal-on-open "always"
+                force-initial-size "1280x720"
                 default-window-height { fixed 500; }
                 default-floating-position x=100 y=-200 relative-to="bottom-left"
+                warp-mouse-to-focus false
+                draw-titlebar true
+                fullscreen-size {
+                    width 1920
+                    height 1080
+                }
+                picture-in-picture true
+                click-through false
+                confirm-close true
 
                 focus-ring {
                     off
@@ -3540,14 +4739,17 @@ mod tests {
             layer-rule {
                 match namespace="^notifications$"
                 block-out-from "screencast"
+                click-through true
             }
 
             binds {
                 Mod+Escape { toggle-keyboard-shortcuts-inhibit; }
                 Mod+Shift+Escape allow-inhibiting=true { toggle-keyboard-shortcuts-inhibit; }
                 Mod+T allow-when-locked=true { spawn "alacritty"; }
+                Mod+Shift+T { spawn "alacritty" { GDK_SCALE "2"; WINIT_UNIX_BACKEND null; }; }
                 Mod+Q { close-window; }
                 Mod+Shift+H { focus-monitor-left; }
+                Mod+Shift+J warp-mouse-to-focus=false { focus-monitor-down; }
                 Mod+Ctrl+Shift+L { move-window-to-monitor-right; }
                 Mod+Comma { consume-window-into-column; }
                 Mod+1 { focus-workspace 1; }
@@ -3556,6 +4758,10 @@ mod tests {
                 Mod+WheelScrollDown cooldown-ms=150 { focus-workspace-down; }
             }
 
+            background-binds {
+                Shift+WheelScrollDown { focus-column-right; }
+            }
+
             switch-events {
                 tablet-mode-on { spawn "bash" "-c" "gsettings set org.gnome.desktop.a11y.applications screen-keyboard-enabled true"; }
                 tablet-mode-off { spawn "bash" "-c" "gsettings set org.gnome.desktop.a11y.applications screen-keyboard-enabled false"; }
@@ -3567,9 +4773,17 @@ mod tests {
 
             workspace "workspace-1" {
                 open-on-output "eDP-1"
+                return-on-connect
+                icon "💬"
+                auto-close-if-empty-after "1h30m"
             }
             workspace "workspace-2"
             workspace "workspace-3"
+
+            workspace-auto-name {
+                match app-id="firefox" name="web"
+                match title="vim$" name="code"
+            }
             "##,
             Config {
                 input: Input {
@@ -3587,6 +4801,10 @@ mod tests {
                         off: false,
                         tap: true,
                         dwt: true,
+                        dwt_exclude_keyboards: vec![
+                            String::from("Foot Pedal"),
+                            String::from("Macro Pad"),
+                        ],
                         dwtp: true,
                         click_method: Some(ClickMethod::Clickfinger),
                         natural_scroll: false,
@@ -3599,6 +4817,7 @@ mod tests {
                         disabled_on_external_mouse: true,
                         middle_emulation: false,
                         scroll_factor: Some(FloatOrInt(0.9)),
+                        invert_workspace_swipe: true,
                     },
                     mouse: Mouse {
                         off: false,
@@ -3634,22 +4853,37 @@ mod tests {
                         off: false,
                         map_to_output: Some("eDP-1".to_owned()),
                         left_handed: false,
+                        rotate_with_output: true,
                     },
                     touch: Touch {
                         map_to_output: Some("eDP-1".to_owned()),
+                        rotate_with_output: false,
+                        resize_edge_width: Some(FloatOrInt(16.)),
+                        two_finger_resize: true,
                     },
                     disable_power_key_handling: true,
-                    warp_mouse_to_focus: true,
+                    warp_mouse_to_focus: Some(WarpMouseToFocus {
+                        only_across_outputs: false,
+                    }),
                     focus_follows_mouse: Some(FocusFollowsMouse {
                         max_scroll_amount: None,
+                        delay_ms: None,
+                        mode: FocusFollowsMouseMode::All,
                     }),
                     workspace_auto_back_and_forth: true,
+                    mod_resize_nearest_corner: true,
+                    click_to_focus: ClickToFocus {
+                        tiled: FocusClick::PassThrough,
+                        floating: FocusClick::Consume,
+                        other_output: FocusClick::Consume,
+                    },
                 },
                 outputs: Outputs(vec![Output {
                     off: false,
                     name: "eDP-1".to_owned(),
                     scale: Some(FloatOrInt(2.)),
                     transform: Transform::Flipped90,
+                    auto_rotate: true,
                     position: Some(Position { x: 10, y: 20 }),
                     mode: Some(ConfiguredMode {
                         width: 1920,
@@ -3657,8 +4891,37 @@ mod tests {
                         refresh: Some(144.),
                     }),
                     variable_refresh_rate: Some(Vrr { on_demand: true }),
+                    max_render_fps: Some(60),
+                    color_temperature: Some(4500),
+                    mirror: Some("HDMI-A-1".to_owned()),
+                    split: Some(OutputSplit {
+                        ratio: SplitRatio {
+                            first: 2,
+                            second: 1,
+                        },
+                        direction: SplitDirection::Vertical,
+                    }),
                     background_color: Color::from_rgba8_unpremul(25, 25, 102, 255),
                 }]),
+                profiles: OutputProfiles(vec![OutputProfile {
+                    name: "docked".to_owned(),
+                    outputs: Outputs(vec![
+                        Output {
+                            off: true,
+                            name: "eDP-1".to_owned(),
+                            ..Default::default()
+                        },
+                        Output {
+                            name: "HDMI-A-1".to_owned(),
+                            position: Some(Position { x: 0, y: 0 }),
+                            ..Default::default()
+                        },
+                    ]),
+                    workspaces: vec![ProfileWorkspace {
+                        name: "workspace-1".to_owned(),
+                        output: "HDMI-A-1".to_owned(),
+                    }],
+                }]),
                 layout: Layout {
                     focus_ring: FocusRing {
                         off: false,
@@ -3731,10 +4994,31 @@ mod tests {
                     center_focused_column: CenterFocusedColumn::OnOverflow,
                     always_center_single_column: false,
                     empty_workspace_above_first: false,
+                    new_column_position: NewColumnPosition::AfterFocused,
+                    new_window_position_in_column: NewWindowPositionInColumn::AfterFocused,
+                    workspace_spillover: WorkspaceSpillover::Output(String::from("HDMI-A-1")),
+                    floating_min_visible: Some(FloatOrInt(100.)),
+                    move_window_to_workspace_focus: false,
+                    min_window_size: MinWindowSize {
+                        width: Some(200),
+                        height: Some(150),
+                    },
+                    reveal_new_windows: RevealNewWindows::Partial,
                 },
-                spawn_at_startup: vec![SpawnAtStartup {
-                    command: vec!["alacritty".to_owned(), "-e".to_owned(), "fish".to_owned()],
-                }],
+                spawn_at_startup: vec![
+                    SpawnAtStartup {
+                        command: vec!["alacritty".to_owned(), "-e".to_owned(), "fish".to_owned()],
+                        restart_on_crash: false,
+                        only_on_output: None,
+                        delay_ms: None,
+                    },
+                    SpawnAtStartup {
+                        command: vec!["waybar".to_owned()],
+                        restart_on_crash: true,
+                        only_on_output: Some(String::from("eDP-1")),
+                        delay_ms: Some(500),
+                    },
+                ],
                 prefer_no_csd: true,
                 cursor: Cursor {
                     xcursor_theme: String::from("breeze_cursors"),
@@ -3743,11 +5027,66 @@ mod tests {
                     hide_after_inactive_ms: Some(3000),
                 },
                 screenshot_path: Some(String::from("~/Screenshots/screenshot.png")),
+                screenshot: Screenshot {
+                    path: Some(ScreenshotPath(Some(String::from("~/Screenshots/%{app-id}.jpg")))),
+                    format: ScreenshotFormat {
+                        kind: ImageFormat::Jpeg,
+                        quality: 80,
+                    },
+                    include_pointer: false,
+                    feedback: ScreenshotFeedback {
+                        kind: ScreenshotFeedbackKind::Flash,
+                        duration_ms: 200,
+                        color: Some(Color::from_rgba8_unpremul(255, 255, 255, 128)),
+                    },
+                },
+                screen_recording: ScreenRecording {
+                    path: String::from("~/Videos/%{app-id}.webm"),
+                },
+                screencast: Screencast {
+                    default_cursor_mode: ScreencastCursorMode::Metadata,
+                    max_fps: Some(30),
+                },
                 clipboard: Clipboard {
                     disable_primary: true,
+                    history_size: Some(50),
+                    sync_primary_to_clipboard: true,
+                    sync_clipboard_to_primary: false,
+                },
+                drag_and_drop: DragAndDrop {
+                    default_action: Some(DndAction::Move),
+                    copy_modifier: Some(DndModifier::Ctrl),
+                    move_modifier: Some(DndModifier::Shift),
+                    show_action_indicator: true,
                 },
                 hotkey_overlay: HotkeyOverlay {
                     skip_at_startup: true,
+                    show_on_mod_hold_ms: Some(700),
+                },
+                crash_placeholder: Some(CrashPlaceholder { timeout_ms: 3000 }),
+                startup_placeholder: StartupPlaceholder { off: true },
+                urgency: Urgency {
+                    reminder_interval_ms: Some(60000),
+                },
+                status_bar: StatusBar {
+                    on: true,
+                    height: 28,
+                    active_workspace_color: Color::from_rgba8_unpremul(255, 0, 0, 255),
+                    ..Default::default()
+                },
+                watermark: Watermark {
+                    on: true,
+                    text: Some(String::from("DEMO")),
+                    image: None,
+                    position: RelativeTo::TopRight,
+                    opacity: FloatOrInt(0.25),
+                    outputs: vec![String::from("eDP-1"), String::from("HDMI-A-1")],
+                    screencast_only: true,
+                },
+                metrics: Metrics {
+                    listen: Some(String::from("127.0.0.1:9464")),
+                    label_outputs: true,
+                    label_ipc_requests: false,
                 },
                 animations: Animations {
                     slowdown: 2.,
@@ -3789,43 +5128,116 @@ mod tests {
                     matches: vec![Match {
                         app_id: Some(RegexEq::from_str(".*alacritty").unwrap()),
                         title: None,
+                        mark: None,
+                        pwa_name: None,
                         is_active: None,
                         is_focused: None,
                         is_active_in_column: None,
                         is_floating: None,
+                        is_fullscreen: None,
+                        is_maximized: None,
                         at_startup: None,
+                        is_x11: None,
                     }],
                     excludes: vec![
                         Match {
                             app_id: None,
                             title: Some(RegexEq::from_str("~").unwrap()),
+                            mark: Some(RegexEq::from_str("^scratch$").unwrap()),
+                            pwa_name: None,
                             is_active: None,
                             is_focused: None,
                             is_active_in_column: None,
                             is_floating: None,
+                            is_fullscreen: None,
+                            is_maximized: None,
                             at_startup: None,
+                            is_x11: None,
                         },
                         Match {
                             app_id: None,
                             title: None,
+                            mark: None,
+                            pwa_name: None,
                             is_active: Some(true),
                             is_focused: Some(false),
                             is_active_in_column: None,
                             is_floating: None,
+                            is_fullscreen: None,
+                            is_maximized: None,
+                            at_startup: None,
+                            is_x11: None,
+                        },
+                        Match {
+                            app_id: None,
+                            title: None,
+                            mark: None,
+                            pwa_name: None,
+                            is_active: None,
+                            is_focused: None,
+                            is_active_in_column: None,
+                            is_floating: None,
+                            is_fullscreen: Some(true),
+                            is_maximized: Some(false),
+                            at_startup: None,
+                            is_x11: None,
+                        },
+                        Match {
+                            app_id: None,
+                            title: None,
+                            mark: None,
+                            pwa_name: None,
+                            is_active: None,
+                            is_focused: None,
+                            is_active_in_column: None,
+                            is_floating: None,
+                            is_fullscreen: None,
+                            is_maximized: None,
+                            at_startup: None,
+                            is_x11: Some(true),
+                        },
+                        Match {
+                            app_id: None,
+                            title: None,
+                            mark: None,
+                            pwa_name: Some(RegexEq::from_str("^YouTube Music$").unwrap()),
+                            is_active: None,
+                            is_focused: None,
+                            is_active_in_column: None,
+                            is_floating: None,
+                            is_fullscreen: None,
+                            is_maximized: None,
                             at_startup: None,
+                            is_x11: None,
                         },
                     ],
                     open_on_output: Some("eDP-1".to_owned()),
+                    open_on_workspace_fallback: Some(OpenOnWorkspaceFallback::Create),
                     open_maximized: Some(true),
                     open_fullscreen: Some(false),
                     open_floating: Some(false),
                     open_focused: Some(true),
+                    never_steal_focus: Some(true),
+                    reveal_on_open: Some(RevealNewWindows::Always),
+                    force_initial_size: Some(ForcedSize {
+                        width: 1280,
+                        height: 720,
+                    }),
                     default_window_height: Some(DefaultPresetSize(Some(PresetSize::Fixed(500)))),
                     default_floating_position: Some(FloatingPosition {
                         x: FloatOrInt(100.),
                         y: FloatOrInt(-200.),
                         relative_to: RelativeTo::BottomLeft,
                     }),
+                    warp_mouse_to_focus: Some(false),
+                    draw_titlebar: Some(true),
+                    fullscreen_size: Some(FullscreenSize {
+                        width: 1920,
+                        height: 1080,
+                    }),
+                    picture_in_picture: Some(true),
+                    click_through: Some(false),
+                    confirm_close: Some(true),
                     focus_ring: BorderRule {
                         off: true,
                         width: Some(FloatOrInt(3.)),
@@ -3849,22 +5261,46 @@ mod tests {
                         block_out_from: Some(BlockOutFrom::Screencast),
                         shadow: ShadowRule::default(),
                         geometry_corner_radius: None,
+                        click_through: Some(true),
                     }
                 ],
                 workspaces: vec![
                     Workspace {
                         name: WorkspaceName("workspace-1".to_string()),
                         open_on_output: Some("eDP-1".to_string()),
+                        return_on_connect: true,
+                        icon: Some("💬".to_string()),
+                        auto_close_if_empty_after: Some(HumanDuration(Duration::from_secs(5400))),
                     },
                     Workspace {
                         name: WorkspaceName("workspace-2".to_string()),
                         open_on_output: None,
+                        return_on_connect: false,
+                        icon: None,
+                        auto_close_if_empty_after: None,
                     },
                     Workspace {
                         name: WorkspaceName("workspace-3".to_string()),
                         open_on_output: None,
+                        return_on_connect: false,
+                        icon: None,
+                        auto_close_if_empty_after: None,
                     },
                 ],
+                workspace_auto_name: WorkspaceAutoName {
+                    rules: vec![
+                        WorkspaceAutoNameRule {
+                            app_id: Some(RegexEq::from_str("firefox").unwrap()),
+                            title: None,
+                            name: String::from("web"),
+                        },
+                        WorkspaceAutoNameRule {
+                            app_id: None,
+                            title: Some(RegexEq::from_str("vim$").unwrap()),
+                            name: String::from("code"),
+                        },
+                    ],
+                },
                 binds: Binds(vec![
                     Bind {
                         key: Key {
@@ -3876,6 +5312,7 @@ mod tests {
                         cooldown: None,
                         allow_when_locked: false,
                         allow_inhibiting: false,
+                        warp_mouse_to_focus: true,
                     },
                     Bind {
                         key: Key {
@@ -3887,17 +5324,43 @@ mod tests {
                         cooldown: None,
                         allow_when_locked: false,
                         allow_inhibiting: false,
+                        warp_mouse_to_focus: true,
                     },
                     Bind {
                         key: Key {
                             trigger: Trigger::Keysym(Keysym::t),
                             modifiers: Modifiers::COMPOSITOR,
                         },
-                        action: Action::Spawn(vec!["alacritty".to_owned()]),
+                        action: Action::Spawn(vec!["alacritty".to_owned()], vec![]),
                         repeat: true,
                         cooldown: None,
                         allow_when_locked: true,
                         allow_inhibiting: true,
+                        warp_mouse_to_focus: true,
+                    },
+                    Bind {
+                        key: Key {
+                            trigger: Trigger::Keysym(Keysym::t),
+                            modifiers: Modifiers::COMPOSITOR | Modifiers::SHIFT,
+                        },
+                        action: Action::Spawn(
+                            vec!["alacritty".to_owned()],
+                            vec![
+                                EnvironmentVariable {
+                                    name: String::from("GDK_SCALE"),
+                                    value: Some(String::from("2")),
+                                },
+                                EnvironmentVariable {
+                                    name: String::from("WINIT_UNIX_BACKEND"),
+                                    value: None,
+                                },
+                            ],
+                        ),
+                        repeat: true,
+                        cooldown: None,
+                        allow_when_locked: false,
+                        allow_inhibiting: true,
+                        warp_mouse_to_focus: true,
                     },
                     Bind {
                         key: Key {
@@ -3909,6 +5372,7 @@ mod tests {
                         cooldown: None,
                         allow_when_locked: false,
                         allow_inhibiting: true,
+                        warp_mouse_to_focus: true,
                     },
                     Bind {
                         key: Key {
@@ -3920,17 +5384,31 @@ mod tests {
                         cooldown: None,
                         allow_when_locked: false,
                         allow_inhibiting: true,
+                        warp_mouse_to_focus: true,
+                    },
+                    Bind {
+                        key: Key {
+                            trigger: Trigger::Keysym(Keysym::j),
+                            modifiers: Modifiers::COMPOSITOR | Modifiers::SHIFT,
+                        },
+                        action: Action::FocusMonitorDown,
+                        repeat: true,
+                        cooldown: None,
+                        allow_when_locked: false,
+                        allow_inhibiting: true,
+                        warp_mouse_to_focus: false,
                     },
                     Bind {
                         key: Key {
                             trigger: Trigger::Keysym(Keysym::l),
                             modifiers: Modifiers::COMPOSITOR | Modifiers::SHIFT | Modifiers::CTRL,
                         },
-                        action: Action::MoveWindowToMonitorRight,
+                        action: Action::MoveWindowToMonitorRight(None),
                         repeat: true,
                         cooldown: None,
                         allow_when_locked: false,
                         allow_inhibiting: true,
+                        warp_mouse_to_focus: true,
                     },
                     Bind {
                         key: Key {
@@ -3942,6 +5420,7 @@ mod tests {
                         cooldown: None,
                         allow_when_locked: false,
                         allow_inhibiting: true,
+                        warp_mouse_to_focus: true,
                     },
                     Bind {
                         key: Key {
@@ -3953,6 +5432,7 @@ mod tests {
                         cooldown: None,
                         allow_when_locked: false,
                         allow_inhibiting: true,
+                        warp_mouse_to_focus: true,
                     },
                     Bind {
                         key: Key {
@@ -3966,6 +5446,7 @@ mod tests {
                         cooldown: None,
                         allow_when_locked: false,
                         allow_inhibiting: true,
+                        warp_mouse_to_focus: true,
                     },
                     Bind {
                         key: Key {
@@ -3977,6 +5458,7 @@ mod tests {
                         cooldown: None,
                         allow_when_locked: false,
                         allow_inhibiting: false,
+                        warp_mouse_to_focus: true,
                     },
                     Bind {
                         key: Key {
@@ -3988,8 +5470,21 @@ mod tests {
                         cooldown: Some(Duration::from_millis(150)),
                         allow_when_locked: false,
                         allow_inhibiting: true,
+                        warp_mouse_to_focus: true,
                     },
                 ]),
+                background_binds: BackgroundBinds(Binds(vec![Bind {
+                    key: Key {
+                        trigger: Trigger::WheelScrollDown,
+                        modifiers: Modifiers::SHIFT,
+                    },
+                    action: Action::FocusColumnRight,
+                    repeat: true,
+                    cooldown: None,
+                    allow_when_locked: false,
+                    allow_inhibiting: true,
+                    warp_mouse_to_focus: true,
+                }])),
                 switch_events: SwitchBinds {
                     lid_open: None,
                     lid_close: None,
@@ -4047,6 +5542,54 @@ mod tests {
         assert!("1920x1080@60Hz".parse::<ConfiguredMode>().is_err());
     }
 
+    #[test]
+    fn parse_forced_size() {
+        assert_eq!(
+            "1280x720".parse::<ForcedSize>().unwrap(),
+            ForcedSize {
+                width: 1280,
+                height: 720,
+            },
+        );
+
+        assert!("1280".parse::<ForcedSize>().is_err());
+        assert!("1280x".parse::<ForcedSize>().is_err());
+        assert!("0x720".parse::<ForcedSize>().is_err());
+        assert!("-1280x720".parse::<ForcedSize>().is_err());
+    }
+
+    #[test]
+    fn parse_human_duration() {
+        let parse = |s: &str| s.parse::<HumanDuration>().map(|d| d.0);
+
+        assert_eq!(parse("90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse("5m").unwrap(), Duration::from_secs(300));
+        assert_eq!(parse("1h30m").unwrap(), Duration::from_secs(5400));
+        assert_eq!(parse("250ms").unwrap(), Duration::from_millis(250));
+
+        assert!(parse("").is_err());
+        assert!(parse("5").is_err());
+        assert!(parse("m").is_err());
+        assert!(parse("5d").is_err());
+        assert!(parse("-5m").is_err());
+    }
+
+    #[test]
+    fn parse_split_ratio() {
+        assert_eq!(
+            "2:1".parse::<SplitRatio>().unwrap(),
+            SplitRatio {
+                first: 2,
+                second: 1,
+            },
+        );
+
+        assert!("1".parse::<SplitRatio>().is_err());
+        assert!("1:".parse::<SplitRatio>().is_err());
+        assert!("0:1".parse::<SplitRatio>().is_err());
+        assert!("1:1:1".parse::<SplitRatio>().is_err());
+    }
+
     #[test]
     fn parse_size_change() {
         assert_eq!(
@@ -4078,6 +5621,33 @@ mod tests {
         assert!("10% ".parse::<SizeChange>().is_err());
     }
 
+    #[test]
+    fn parse_brightness_change() {
+        assert_eq!(
+            "50".parse::<BrightnessChange>().unwrap(),
+            BrightnessChange::SetPercent(50.),
+        );
+        assert_eq!(
+            "50%".parse::<BrightnessChange>().unwrap(),
+            BrightnessChange::SetPercent(50.),
+        );
+        assert_eq!(
+            "+5".parse::<BrightnessChange>().unwrap(),
+            BrightnessChange::AdjustPercent(5.),
+        );
+        assert_eq!(
+            "-5%".parse::<BrightnessChange>().unwrap(),
+            BrightnessChange::AdjustPercent(-5.),
+        );
+
+        assert!("-".parse::<BrightnessChange>().is_err());
+        assert!("%".parse::<BrightnessChange>().is_err());
+        assert!("5%%".parse::<BrightnessChange>().is_err());
+        assert!("nan".parse::<BrightnessChange>().is_err());
+        assert!("+inf".parse::<BrightnessChange>().is_err());
+        assert!("150".parse::<BrightnessChange>().is_err());
+    }
+
     #[test]
     fn parse_position_change() {
         assert_eq!(
@@ -4215,6 +5785,38 @@ mod tests {
         assert_eq!(config.input.keyboard.repeat_rate, 25);
     }
 
+    #[test]
+    fn parse_warp_mouse_to_focus() {
+        let config = Config::parse(
+            "config.kdl",
+            "input { warp-mouse-to-focus only-across-outputs=true; }",
+        )
+        .unwrap();
+        assert_eq!(
+            config.input.warp_mouse_to_focus,
+            Some(WarpMouseToFocus {
+                only_across_outputs: true,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_focus_follows_mouse() {
+        let config = Config::parse(
+            "config.kdl",
+            r#"input { focus-follows-mouse { delay-ms 150; mode "output-only"; }; }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.input.focus_follows_mouse,
+            Some(FocusFollowsMouse {
+                max_scroll_amount: None,
+                delay_ms: Some(150),
+                mode: FocusFollowsMouseMode::OutputOnly,
+            })
+        );
+    }
+
     fn make_output_name(
         connector: &str,
         make: Option<&str>,
@@ -4290,6 +5892,104 @@ mod tests {
         assert!(!check("unknown unknown unknown", "DP-2", None, None, None));
     }
 
+    #[test]
+    fn test_output_profile_match() {
+        let config = Config::parse(
+            "config.kdl",
+            r#"
+            profiles {
+                profile "docked" {
+                    output "eDP-1"
+                    output "Some Company Some Monitor 1234"
+                }
+            }
+            "#,
+        )
+        .unwrap();
+        let profile = &config.profiles.0[0];
+
+        let laptop = make_output_name("eDP-1", None, None, None);
+        let monitor = make_output_name(
+            "DP-2",
+            Some("Some Company"),
+            Some("Some Monitor"),
+            Some("1234"),
+        );
+        let other = make_output_name("DP-3", None, None, None);
+
+        assert!(profile.matches(&[laptop.clone(), monitor.clone()]));
+        assert!(profile.matches(&[monitor.clone(), laptop.clone()]));
+        assert!(!profile.matches(&[laptop.clone()]));
+        assert!(!profile.matches(&[laptop.clone(), other.clone()]));
+        assert!(!profile.matches(&[laptop, monitor, other]));
+    }
+
+    #[test]
+    fn test_output_profile_selection() {
+        let config = Config::parse(
+            "config.kdl",
+            r#"
+            output "eDP-1" {
+                scale 1.5
+            }
+
+            output "DP-3" {
+                scale 1
+            }
+
+            profiles {
+                profile "docked" {
+                    output "eDP-1" {
+                        off
+                    }
+                    output "DP-2"
+                }
+
+                profile "docked-again" {
+                    output "DP-2"
+                    output "eDP-1"
+                }
+
+                profile "laptop" {
+                    output "eDP-1" {
+                        scale 2
+                    }
+                }
+            }
+            "#,
+        )
+        .unwrap();
+
+        let laptop = make_output_name("eDP-1", None, None, None);
+        let monitor = make_output_name("DP-2", None, None, None);
+        let other = make_output_name("DP-3", None, None, None);
+
+        // The first matching profile wins.
+        let find = |connected: &[OutputName]| {
+            let profile = config.profiles.find_matching(connected);
+            profile.map(|p| p.name.as_str())
+        };
+        assert_eq!(find(&[laptop.clone(), monitor.clone()]), Some("docked"));
+        assert_eq!(find(&[laptop.clone()]), Some("laptop"));
+        assert_eq!(find(&[laptop.clone(), other.clone()]), None);
+        assert_eq!(find(&[]), None);
+
+        // Profile outputs take precedence, other outputs keep the config file settings.
+        let profile = config.profiles.find_matching(&[laptop.clone()]);
+        let outputs = config.outputs.with_profile(profile);
+        assert_eq!(outputs.find(&laptop).unwrap().scale, Some(FloatOrInt(2.)));
+        assert_eq!(outputs.find(&other).unwrap().scale, Some(FloatOrInt(1.)));
+
+        let profile = config.profiles.find_matching(&[laptop.clone(), monitor]);
+        let outputs = config.outputs.with_profile(profile);
+        let output = outputs.find(&laptop).unwrap();
+        assert!(output.off);
+        assert_eq!(output.scale, None);
+
+        // Without a profile, only the config file outputs remain.
+        assert_eq!(config.outputs.with_profile(None), config.outputs);
+    }
+
     #[test]
     fn test_output_name_sorting() {
         let mut names = vec![
@@ -0,0 +1,192 @@
+//! Async helper for communicating over the niri socket.
+//!
+//! The helper works with any runtime: it wraps a connected stream implementing the
+//! [`futures_io`](https://docs.rs/futures-io) `AsyncRead` and `AsyncWrite` traits. For example,
+//! with async-std or smol, pass their `UnixStream` directly. With tokio, wrap its `UnixStream`
+//! with `tokio_util::compat::TokioAsyncReadCompatExt::compat()`.
+//!
+//! ```no_run
+//! use futures_util::io::{AsyncRead, AsyncWrite};
+//! use niri_ipc::async_socket::AsyncSocket;
+//! use niri_ipc::{Request, Response};
+//!
+//! // Stream connected to niri_ipc::socket::socket_path().
+//! async fn print_events(stream: impl AsyncRead + AsyncWrite + Unpin) -> std::io::Result<()> {
+//!     let mut socket = AsyncSocket::new(stream);
+//!
+//!     let reply = socket.send(Request::EventStream).await?;
+//!     assert!(matches!(reply, Ok(Response::Handled)));
+//!
+//!     loop {
+//!         let event = socket.next_event().await?;
+//!         println!("{event:?}");
+//!     }
+//! }
+//! ```
+
+use std::io;
+
+use futures_util::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+
+use crate::{Event, Reply, Request};
+
+/// Async helper for communicating over the niri socket.
+///
+/// This is the async counterpart of [`Socket`](crate::socket::Socket). Unlike it, the connection
+/// stays open after a request, so you can keep reading [`Event`]s after requesting an
+/// [`EventStream`][Request::EventStream].
+pub struct AsyncSocket<S> {
+    stream: BufReader<S>,
+    buf: String,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncSocket<S> {
+    /// Wraps a stream connected to the niri socket.
+    ///
+    /// You can get the socket path with [`socket_path()`](crate::socket::socket_path).
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream: BufReader::new(stream),
+            buf: String::new(),
+        }
+    }
+
+    /// Sends a request to niri and returns the reply.
+    ///
+    /// Return values:
+    ///
+    /// * `Ok(Ok(response))`: successful [`Response`](crate::Response) from niri
+    /// * `Ok(Err(message))`: error message from niri
+    /// * `Err(error)`: error communicating with niri
+    ///
+    /// Niri handles a single request per connection, so apart from an event stream, connect
+    /// again for every request.
+    pub async fn send(&mut self, request: Request) -> io::Result<Reply> {
+        let mut buf = serde_json::to_string(&request).unwrap();
+        buf.push('\n');
+
+        let stream = self.stream.get_mut();
+        stream.write_all(buf.as_bytes()).await?;
+        stream.flush().await?;
+
+        self.read_line().await?;
+        let reply = serde_json::from_str(&self.buf)?;
+        Ok(reply)
+    }
+
+    /// Reads the next event after requesting an [`EventStream`][Request::EventStream].
+    pub async fn next_event(&mut self) -> io::Result<Event> {
+        self.read_line().await?;
+        let event = serde_json::from_str(&self.buf)?;
+        Ok(event)
+    }
+
+    /// Returns the wrapped stream.
+    pub fn into_inner(self) -> S {
+        self.stream.into_inner()
+    }
+
+    async fn read_line(&mut self) -> io::Result<()> {
+        self.buf.clear();
+        let read = self.stream.read_line(&mut self.buf).await?;
+        if read == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "niri closed the connection",
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    use futures_util::io::Cursor;
+    use futures_util::FutureExt as _;
+
+    use super::*;
+    use crate::Response;
+
+    /// Stream that reads prepared data and records writes.
+    struct TestStream {
+        read: Cursor<Vec<u8>>,
+        written: Vec<u8>,
+    }
+
+    impl TestStream {
+        fn new(read: &str) -> Self {
+            Self {
+                read: Cursor::new(read.as_bytes().to_vec()),
+                written: Vec::new(),
+            }
+        }
+    }
+
+    impl AsyncRead for TestStream {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            Pin::new(&mut self.read).poll_read(cx, buf)
+        }
+    }
+
+    impl AsyncWrite for TestStream {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.written.extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn send_and_read_events() {
+        let stream = TestStream::new(concat!(
+            r#"{"Ok":"Handled"}"#,
+            "\n",
+            r#"{"KeyboardLayoutSwitched":{"idx":1}}"#,
+            "\n",
+        ));
+        let mut socket = AsyncSocket::new(stream);
+
+        let reply = socket.send(Request::EventStream).now_or_never().unwrap();
+        assert!(matches!(reply.unwrap(), Ok(Response::Handled)));
+
+        let event = socket.next_event().now_or_never().unwrap();
+        assert!(matches!(
+            event.unwrap(),
+            Event::KeyboardLayoutSwitched { idx: 1 }
+        ));
+
+        // The stream ended.
+        let event = socket.next_event().now_or_never().unwrap();
+        assert_eq!(event.unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+
+        let stream = socket.into_inner();
+        assert_eq!(stream.written, b"\"EventStream\"\n");
+    }
+
+    #[test]
+    fn error_reply() {
+        let stream = TestStream::new(concat!(r#"{"Err":"example compositor error"}"#, "\n"));
+        let mut socket = AsyncSocket::new(stream);
+
+        let reply = socket.send(Request::ReturnError).now_or_never().unwrap();
+        assert_eq!(reply.unwrap().unwrap_err(), "example compositor error");
+    }
+}
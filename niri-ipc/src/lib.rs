@@ -4,9 +4,10 @@
 //! [`Reply`], which is a `Result` wrapping a [`Response`]. If you requested an event stream, you
 //! can keep reading [`Event`]s from the socket after the response.
 //!
-//! You can use the [`socket::Socket`] helper if you're fine with blocking communication. However,
-//! it is a fairly simple helper, so if you need async, or if you're using a different language,
-//! you are encouraged to communicate with the socket manually.
+//! You can use the [`socket::Socket`] helper if you're fine with blocking communication. For async
+//! code, enable the `async` feature and use `async_socket::AsyncSocket`, which works with any
+//! runtime. These are fairly simple helpers, so if you're using a different language, you are
+//! encouraged to communicate with the socket manually.
 //!
 //! 1. Read the socket filesystem path from [`socket::SOCKET_PATH_ENV`] (`$NIRI_SOCKET`).
 //! 2. Connect to the socket and write a JSON-formatted [`Request`] on a single line. You can follow
@@ -15,6 +16,11 @@
 //! 4. If you requested an event stream, niri will keep responding with JSON-formatted [`Event`]s,
 //!    on a single line each.
 //!
+//! To cut down on round-trips, you can also write a JSON array of [`Request`]s on the single line.
+//! Niri will respond with a JSON array of [`Reply`]s in the same order. Requests that only read the
+//! state, like [`Request::Outputs`], [`Request::Workspaces`] and [`Request::Windows`], see a
+//! consistent state within one batch. An event stream cannot be requested in a batch.
+//!
 //! ## Backwards compatibility
 //!
 //! This crate follows the niri version. It is **not** API-stable in terms of the Rust semver. In
@@ -32,6 +38,7 @@
 //! This crate defines the following features:
 //! - `json-schema`: derives the [schemars](https://lib.rs/crates/schemars) `JsonSchema` trait for
 //!   the types.
+//! - `async`: adds the `async_socket` module with a runtime-agnostic async helper for the socket.
 //! - `clap`: derives the clap CLI parsing traits for some types. Used internally by niri itself.
 #![warn(missing_docs)]
 
@@ -40,6 +47,8 @@ use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "async")]
+pub mod async_socket;
 pub mod socket;
 pub mod state;
 
@@ -65,6 +74,35 @@ pub enum Request {
     FocusedWindow,
     /// Perform an action.
     Action(Action),
+    /// Perform several actions in order.
+    ///
+    /// The actions are performed together, without redrawing the screen in between, so their
+    /// intermediate states are never visible.
+    Actions(Vec<Action>),
+    /// Perform an action on every window matching a filter.
+    ///
+    /// The action is performed once for every matching window, as if the window id was passed to
+    /// it. Only actions that accept a window id are supported.
+    ///
+    /// All windows are matched and acted upon in one go, so windows opening or changing in the
+    /// meantime cannot interfere.
+    BulkAction {
+        /// Windows to perform the action on.
+        filter: WindowFilter,
+        /// Action to perform.
+        action: Action,
+    },
+    /// Perform an action on the most recently focused window matching a filter.
+    ///
+    /// This lets you target a window by its app ID, title, mark or workspace instead of by id.
+    /// Only actions that accept a window id are supported. Windows that were never focused are
+    /// picked last.
+    MatchedAction {
+        /// Filter selecting the window.
+        filter: WindowFilter,
+        /// Action to perform.
+        action: Action,
+    },
     /// Change output configuration temporarily.
     ///
     /// The configuration is changed temporarily and not saved into the config file. If the output
@@ -76,6 +114,31 @@ pub enum Request {
         /// Configuration to apply.
         action: OutputAction,
     },
+    /// Create a virtual output.
+    ///
+    /// Virtual outputs aren't shown on any monitor, but otherwise work like regular outputs. For
+    /// example, they can be screencast to another device to use it as an extra monitor.
+    ///
+    /// The compositor replies with the name of the new output.
+    CreateVirtualOutput {
+        /// Width in physical pixels.
+        width: u16,
+        /// Height in physical pixels.
+        height: u16,
+        /// Scale of the output.
+        ///
+        /// If `None`, the scale comes from the output config, or is picked automatically.
+        scale: Option<f64>,
+        /// Workspaces to move to the new output.
+        ///
+        /// The first workspace becomes active on the new output. The focus stays where it was.
+        workspaces: Vec<WorkspaceReferenceArg>,
+    },
+    /// Remove a virtual output.
+    RemoveVirtualOutput {
+        /// Name of the virtual output.
+        output: String,
+    },
     /// Start continuously receiving events from the compositor.
     ///
     /// The compositor should reply with `Reply::Ok(Response::Handled)`, then continuously send
@@ -91,6 +154,22 @@ pub enum Request {
     /// been removed. This can happen if the corresponding [`Event::WorkspacesChanged`] arrives
     /// before the corresponding [`Event::WindowOpenedOrChanged`].
     EventStream,
+    /// Start continuously receiving a subset of events from the compositor.
+    ///
+    /// Works like [`Request::EventStream`], but only the events of the listed classes (including
+    /// their initial state) are sent, which avoids needless wakeups.
+    FilteredEventStream {
+        /// Classes of events to receive.
+        subscribe: Vec<EventClass>,
+    },
+    /// Request information about the Wayland protocols implemented by niri.
+    Protocols,
+    /// Request an explanation of the current keyboard focus.
+    ExplainFocus,
+    /// Request the binds that are currently on cooldown.
+    BindCooldowns,
+    /// Request the clipboard history, most recent entry first.
+    ClipboardHistory,
     /// Respond with an error (for testing error handling).
     ReturnError,
 }
@@ -131,6 +210,20 @@ pub enum Response {
     FocusedWindow(Option<Window>),
     /// Output configuration change result.
     OutputConfigChanged(OutputConfigChanged),
+    /// Ids of the windows that a bulk action was performed on.
+    BulkActionApplied(Vec<u64>),
+    /// Id of the window that a matched action was performed on.
+    MatchedActionApplied(u64),
+    /// Name of the created virtual output.
+    VirtualOutputCreated(String),
+    /// Information about the Wayland protocol globals advertised by niri.
+    Protocols(Vec<WaylandProtocol>),
+    /// Explanation of the current keyboard focus.
+    FocusExplanation(FocusExplanation),
+    /// Binds that are currently on cooldown.
+    BindCooldowns(Vec<BindCooldown>),
+    /// Clipboard history, most recent entry first.
+    ClipboardHistory(Vec<ClipboardEntry>),
 }
 
 /// Actions that niri can perform.
@@ -152,12 +245,79 @@ pub enum Action {
     PowerOffMonitors {},
     /// Power on all monitors via DPMS.
     PowerOnMonitors {},
+    /// Set the color temperature of an output.
+    ///
+    /// Like the output actions, this changes the configuration temporarily.
+    #[cfg_attr(
+        feature = "clap",
+        clap(about = "Set the color temperature of the focused output or a specific output")
+    )]
+    SetOutputColorTemperature {
+        /// Color temperature in kelvin, 6500 being neutral.
+        #[cfg_attr(feature = "clap", arg())]
+        kelvin: u32,
+
+        /// Name of the output.
+        ///
+        /// If `None`, uses the focused output.
+        #[cfg_attr(feature = "clap", arg(long))]
+        output: Option<String>,
+    },
+    /// Change the brightness of an output.
+    #[cfg_attr(
+        feature = "clap",
+        clap(about = "Change the brightness of the laptop panel or a specific output")
+    )]
+    SetOutputBrightness {
+        /// Brightness to set in percent, or a change like "+10" or "-10".
+        #[cfg_attr(feature = "clap", arg(allow_hyphen_values = true))]
+        change: BrightnessChange,
+
+        /// Name of the output.
+        ///
+        /// If `None`, uses the laptop panel, or the focused output if there's no laptop panel.
+        #[cfg_attr(feature = "clap", arg(long))]
+        output: Option<String>,
+    },
     /// Spawn a command.
     Spawn {
         /// Command to spawn.
         #[cfg_attr(feature = "clap", arg(last = true, required = true))]
         command: Vec<String>,
     },
+    /// Spawn a command and apply extra rules to the window it opens.
+    ///
+    /// The rules are matched to the window through the XDG activation token passed to the
+    /// command, so they only apply to applications that use it.
+    #[cfg_attr(
+        feature = "clap",
+        clap(about = "Spawn a command and apply extra rules to the window it opens")
+    )]
+    SpawnWithRules {
+        /// Command to spawn.
+        #[cfg_attr(feature = "clap", arg(last = true, required = true))]
+        command: Vec<String>,
+
+        /// Whether the window should open floating.
+        #[cfg_attr(feature = "clap", arg(long))]
+        open_floating: Option<bool>,
+
+        /// Whether the window should open fullscreen.
+        #[cfg_attr(feature = "clap", arg(long))]
+        open_fullscreen: Option<bool>,
+
+        /// Width in logical pixels for the window to open with.
+        #[cfg_attr(feature = "clap", arg(long))]
+        default_width: Option<u16>,
+
+        /// Height in logical pixels for the window to open with.
+        #[cfg_attr(feature = "clap", arg(long))]
+        default_height: Option<u16>,
+
+        /// Open the window on the focused output, ignoring other rules that place it elsewhere.
+        #[cfg_attr(feature = "clap", arg(long))]
+        here: bool,
+    },
     /// Do a screen transition.
     DoScreenTransition {
         /// Delay in milliseconds for the screen to freeze before starting the transition.
@@ -188,6 +348,47 @@ pub enum Action {
         #[cfg_attr(feature = "clap", arg(short = 'd', long, action = clap::ArgAction::Set, default_value_t = true))]
         write_to_disk: bool,
     },
+    /// Start or stop recording the focused output to a file.
+    ///
+    /// If the screenshot UI is open, records the selected region instead. If a recording is
+    /// already in progress, stops it.
+    ///
+    /// The recording is saved according to the `screen-recording` config section.
+    ToggleScreenRecord {},
+    /// Start or stop recording a window to a file.
+    ///
+    /// If a recording is already in progress, stops it.
+    #[cfg_attr(
+        feature = "clap",
+        clap(about = "Start or stop recording the focused window to a file")
+    )]
+    ToggleScreenRecordWindow {
+        /// Id of the window to record.
+        ///
+        /// If `None`, uses the focused window.
+        #[cfg_attr(feature = "clap", arg(long))]
+        id: Option<u64>,
+    },
+    /// Redirect the ongoing window screencasts to a different window.
+    ///
+    /// The casts keep their streams, so the consumers (e.g. OBS) don't need to do anything.
+    #[cfg_attr(
+        feature = "clap",
+        clap(about = "Redirect the ongoing window screencasts to the focused window")
+    )]
+    SetDynamicCastTarget {
+        /// Id of the window to cast.
+        ///
+        /// If `None`, uses the focused window.
+        #[cfg_attr(feature = "clap", arg(long))]
+        id: Option<u64>,
+    },
+    /// Limit the frame rate of the ongoing screencasts.
+    SetCastMaxFps {
+        /// Maximum frames per second, or 0 to remove the limit.
+        #[cfg_attr(feature = "clap", arg())]
+        fps: u32,
+    },
     /// Close a window.
     #[cfg_attr(feature = "clap", clap(about = "Close the focused window"))]
     CloseWindow {
@@ -217,6 +418,8 @@ pub enum Action {
     },
     /// Focus the previously focused window.
     FocusWindowPrevious {},
+    /// Focus the window that was focused before the current one on the focused workspace.
+    FocusPreviousInWorkspace {},
     /// Focus the column to the left.
     FocusColumnLeft {},
     /// Focus the column to the right.
@@ -332,9 +535,21 @@ pub enum Action {
     /// Focus the previous workspace.
     FocusWorkspacePrevious {},
     /// Move the focused window to the workspace below.
-    MoveWindowToWorkspaceDown {},
+    MoveWindowToWorkspaceDown {
+        /// Whether to focus the moved window.
+        ///
+        /// If `None`, uses the `move-window-to-workspace-focus` layout setting.
+        #[cfg_attr(feature = "clap", arg(long, action = clap::ArgAction::Set))]
+        focus: Option<bool>,
+    },
     /// Move the focused window to the workspace above.
-    MoveWindowToWorkspaceUp {},
+    MoveWindowToWorkspaceUp {
+        /// Whether to focus the moved window.
+        ///
+        /// If `None`, uses the `move-window-to-workspace-focus` layout setting.
+        #[cfg_attr(feature = "clap", arg(long, action = clap::ArgAction::Set))]
+        focus: Option<bool>,
+    },
     /// Move a window to a workspace.
     #[cfg_attr(
         feature = "clap",
@@ -350,16 +565,65 @@ pub enum Action {
         /// Reference (index or name) of the workspace to move the window to.
         #[cfg_attr(feature = "clap", arg())]
         reference: WorkspaceReferenceArg,
+
+        /// Whether to focus the moved window.
+        ///
+        /// If `None`, uses the `move-window-to-workspace-focus` layout setting.
+        #[cfg_attr(feature = "clap", arg(long, action = clap::ArgAction::Set))]
+        focus: Option<bool>,
+    },
+    /// Move a window to a workspace on a specific output, by index.
+    ///
+    /// An index past the last workspace on the output moves the window to a new workspace at the
+    /// end.
+    MoveWindowToWorkspaceOnOutput {
+        /// Id of the window to move.
+        ///
+        /// If `None`, uses the focused window.
+        #[cfg_attr(feature = "clap", arg(long))]
+        window_id: Option<u64>,
+
+        /// Name of the output to move the window to.
+        #[cfg_attr(feature = "clap", arg(long))]
+        output: String,
+
+        /// Index of the workspace on the output, starting from 1.
+        #[cfg_attr(feature = "clap", arg(long))]
+        index: usize,
+
+        /// Whether to focus the moved window.
+        ///
+        /// If `None`, uses the `move-window-to-workspace-focus` layout setting.
+        #[cfg_attr(feature = "clap", arg(long, action = clap::ArgAction::Set))]
+        focus: Option<bool>,
     },
     /// Move the focused column to the workspace below.
-    MoveColumnToWorkspaceDown {},
+    MoveColumnToWorkspaceDown {
+        /// Whether to focus the moved column.
+        ///
+        /// If `None`, uses the `move-window-to-workspace-focus` layout setting.
+        #[cfg_attr(feature = "clap", arg(long, action = clap::ArgAction::Set))]
+        focus: Option<bool>,
+    },
     /// Move the focused column to the workspace above.
-    MoveColumnToWorkspaceUp {},
+    MoveColumnToWorkspaceUp {
+        /// Whether to focus the moved column.
+        ///
+        /// If `None`, uses the `move-window-to-workspace-focus` layout setting.
+        #[cfg_attr(feature = "clap", arg(long, action = clap::ArgAction::Set))]
+        focus: Option<bool>,
+    },
     /// Move the focused column to a workspace by reference (index or name).
     MoveColumnToWorkspace {
         /// Reference (index or name) of the workspace to move the column to.
         #[cfg_attr(feature = "clap", arg())]
         reference: WorkspaceReferenceArg,
+
+        /// Whether to focus the moved column.
+        ///
+        /// If `None`, uses the `move-window-to-workspace-focus` layout setting.
+        #[cfg_attr(feature = "clap", arg(long, action = clap::ArgAction::Set))]
+        focus: Option<bool>,
     },
     /// Move the focused workspace down.
     MoveWorkspaceDown {},
@@ -381,6 +645,22 @@ pub enum Action {
         #[cfg_attr(feature = "clap", arg(long))]
         reference: Option<WorkspaceReferenceArg>,
     },
+    /// Swap the positions of two workspaces, possibly on different monitors.
+    SwapWorkspaces {
+        /// Reference (index or name) of the first workspace.
+        #[cfg_attr(feature = "clap", arg())]
+        ref_a: WorkspaceReferenceArg,
+
+        /// Reference (index or name) of the second workspace.
+        #[cfg_attr(feature = "clap", arg())]
+        ref_b: WorkspaceReferenceArg,
+    },
+    /// Sort the workspaces on every monitor by name, putting the named workspaces first.
+    SortWorkspacesByName {},
+    /// Remove the empty unnamed workspaces on every monitor, including the focused one.
+    CompactWorkspaces {},
+    /// Move the named workspaces back to their configured `open-on-output` outputs.
+    MoveWorkspacesToAssignedOutputs {},
     /// Set the name of a workspace.
     #[cfg_attr(
         feature = "clap",
@@ -409,6 +689,20 @@ pub enum Action {
         #[cfg_attr(feature = "clap", arg())]
         reference: Option<WorkspaceReferenceArg>,
     },
+    /// Show a text entry to type a new name for the focused workspace.
+    RenameWorkspace {},
+    /// Show or hide the built-in status bar.
+    ToggleStatusBar {},
+    /// Show or hide the watermark.
+    ToggleWatermark {},
+    /// Show the clipboard history to pick an entry to put back into the clipboard.
+    ShowClipboardHistory {},
+    /// Put a clipboard history entry back into the clipboard.
+    SetClipboardFromHistory {
+        /// Id of the clipboard history entry.
+        #[cfg_attr(feature = "clap", arg(long))]
+        id: u64,
+    },
     /// Focus the monitor to the left.
     FocusMonitorLeft {},
     /// Focus the monitor to the right.
@@ -422,29 +716,101 @@ pub enum Action {
     /// Focus the next monitor.
     FocusMonitorNext {},
     /// Move the focused window to the monitor to the left.
-    MoveWindowToMonitorLeft {},
+    MoveWindowToMonitorLeft {
+        /// Whether to focus the moved window.
+        ///
+        /// If `None`, uses the `move-window-to-workspace-focus` layout setting.
+        #[cfg_attr(feature = "clap", arg(long, action = clap::ArgAction::Set))]
+        focus: Option<bool>,
+    },
     /// Move the focused window to the monitor to the right.
-    MoveWindowToMonitorRight {},
+    MoveWindowToMonitorRight {
+        /// Whether to focus the moved window.
+        ///
+        /// If `None`, uses the `move-window-to-workspace-focus` layout setting.
+        #[cfg_attr(feature = "clap", arg(long, action = clap::ArgAction::Set))]
+        focus: Option<bool>,
+    },
     /// Move the focused window to the monitor below.
-    MoveWindowToMonitorDown {},
+    MoveWindowToMonitorDown {
+        /// Whether to focus the moved window.
+        ///
+        /// If `None`, uses the `move-window-to-workspace-focus` layout setting.
+        #[cfg_attr(feature = "clap", arg(long, action = clap::ArgAction::Set))]
+        focus: Option<bool>,
+    },
     /// Move the focused window to the monitor above.
-    MoveWindowToMonitorUp {},
+    MoveWindowToMonitorUp {
+        /// Whether to focus the moved window.
+        ///
+        /// If `None`, uses the `move-window-to-workspace-focus` layout setting.
+        #[cfg_attr(feature = "clap", arg(long, action = clap::ArgAction::Set))]
+        focus: Option<bool>,
+    },
     /// Move the focused window to the previous monitor.
-    MoveWindowToMonitorPrevious {},
+    MoveWindowToMonitorPrevious {
+        /// Whether to focus the moved window.
+        ///
+        /// If `None`, uses the `move-window-to-workspace-focus` layout setting.
+        #[cfg_attr(feature = "clap", arg(long, action = clap::ArgAction::Set))]
+        focus: Option<bool>,
+    },
     /// Move the focused window to the next monitor.
-    MoveWindowToMonitorNext {},
+    MoveWindowToMonitorNext {
+        /// Whether to focus the moved window.
+        ///
+        /// If `None`, uses the `move-window-to-workspace-focus` layout setting.
+        #[cfg_attr(feature = "clap", arg(long, action = clap::ArgAction::Set))]
+        focus: Option<bool>,
+    },
     /// Move the focused column to the monitor to the left.
-    MoveColumnToMonitorLeft {},
+    MoveColumnToMonitorLeft {
+        /// Whether to focus the moved column.
+        ///
+        /// If `None`, uses the `move-window-to-workspace-focus` layout setting.
+        #[cfg_attr(feature = "clap", arg(long, action = clap::ArgAction::Set))]
+        focus: Option<bool>,
+    },
     /// Move the focused column to the monitor to the right.
-    MoveColumnToMonitorRight {},
+    MoveColumnToMonitorRight {
+        /// Whether to focus the moved column.
+        ///
+        /// If `None`, uses the `move-window-to-workspace-focus` layout setting.
+        #[cfg_attr(feature = "clap", arg(long, action = clap::ArgAction::Set))]
+        focus: Option<bool>,
+    },
     /// Move the focused column to the monitor below.
-    MoveColumnToMonitorDown {},
+    MoveColumnToMonitorDown {
+        /// Whether to focus the moved column.
+        ///
+        /// If `None`, uses the `move-window-to-workspace-focus` layout setting.
+        #[cfg_attr(feature = "clap", arg(long, action = clap::ArgAction::Set))]
+        focus: Option<bool>,
+    },
     /// Move the focused column to the monitor above.
-    MoveColumnToMonitorUp {},
+    MoveColumnToMonitorUp {
+        /// Whether to focus the moved column.
+        ///
+        /// If `None`, uses the `move-window-to-workspace-focus` layout setting.
+        #[cfg_attr(feature = "clap", arg(long, action = clap::ArgAction::Set))]
+        focus: Option<bool>,
+    },
     /// Move the focused column to the previous monitor.
-    MoveColumnToMonitorPrevious {},
+    MoveColumnToMonitorPrevious {
+        /// Whether to focus the moved column.
+        ///
+        /// If `None`, uses the `move-window-to-workspace-focus` layout setting.
+        #[cfg_attr(feature = "clap", arg(long, action = clap::ArgAction::Set))]
+        focus: Option<bool>,
+    },
     /// Move the focused column to the next monitor.
-    MoveColumnToMonitorNext {},
+    MoveColumnToMonitorNext {
+        /// Whether to focus the moved column.
+        ///
+        /// If `None`, uses the `move-window-to-workspace-focus` layout setting.
+        #[cfg_attr(feature = "clap", arg(long, action = clap::ArgAction::Set))]
+        focus: Option<bool>,
+    },
     /// Change the width of a window.
     #[cfg_attr(
         feature = "clap",
@@ -557,6 +923,21 @@ pub enum Action {
     DebugToggleOpaqueRegions {},
     /// Toggle visualization of output damage.
     DebugToggleDamage {},
+    /// Toggle freezing the animation clock.
+    ///
+    /// While frozen, animations don't advance on their own, only through
+    /// `debug-step-animation-clock`.
+    DebugToggleAnimationClockFreeze {},
+    /// Clear the cooldowns of all binds, so that they can trigger right away.
+    ClearBindCooldowns {},
+    /// Advance the animation clock by a number of milliseconds.
+    ///
+    /// The step does not take animation slowdown into account.
+    DebugStepAnimationClock {
+        /// Number of milliseconds to advance the clock by.
+        #[cfg_attr(feature = "clap", arg())]
+        ms: u32,
+    },
     /// Move the focused window between the floating and the tiling layout.
     ToggleWindowFloating {
         /// Id of the window to move.
@@ -581,6 +962,30 @@ pub enum Action {
         #[cfg_attr(feature = "clap", arg(long))]
         id: Option<u64>,
     },
+    /// Toggle the window select mode, where clicking windows selects them.
+    ///
+    /// Leaving the select mode clears the selection.
+    ToggleWindowSelectMode {},
+    /// Select or deselect the focused window, entering the window select mode.
+    ToggleWindowSelected {
+        /// Id of the window to select or deselect.
+        ///
+        /// If `None`, uses the focused window.
+        #[cfg_attr(feature = "clap", arg(long))]
+        id: Option<u64>,
+    },
+    /// Close the selected windows and leave the window select mode.
+    CloseSelectedWindows {},
+    /// Move the selected windows to a workspace and leave the window select mode.
+    MoveSelectedWindowsToWorkspace {
+        /// Reference (index or name) of the workspace to move the windows to.
+        #[cfg_attr(feature = "clap", arg())]
+        reference: WorkspaceReferenceArg,
+    },
+    /// Move the selected windows to the floating layout and leave the window select mode.
+    MoveSelectedWindowsToFloating {},
+    /// Move the selected windows to the tiling layout and leave the window select mode.
+    MoveSelectedWindowsToTiling {},
     /// Switches focus to the floating layout.
     FocusFloating {},
     /// Switches focus to the tiling layout.
@@ -610,6 +1015,47 @@ pub enum Action {
         )]
         y: PositionChange,
     },
+    /// Snap a floating window to a half or a quarter of the working area.
+    #[cfg_attr(
+        feature = "clap",
+        clap(about = "Snap the focused floating window to a half or a quarter of the screen")
+    )]
+    SnapFloatingWindow {
+        /// Id of the window to snap.
+        ///
+        /// If `None`, uses the focused window.
+        #[cfg_attr(feature = "clap", arg(long))]
+        id: Option<u64>,
+
+        /// Where to snap the window.
+        #[cfg_attr(feature = "clap", arg())]
+        position: SnapPosition,
+    },
+    /// Move a picture-in-picture window to the next corner of the screen clockwise.
+    #[cfg_attr(
+        feature = "clap",
+        clap(about = "Move a picture-in-picture window to the next corner of the screen")
+    )]
+    CyclePictureInPictureCorner {
+        /// Id of the window to move.
+        ///
+        /// If `None`, uses the focused picture-in-picture window, or any picture-in-picture window
+        /// on the focused workspace.
+        #[cfg_attr(feature = "clap", arg(long))]
+        id: Option<u64>,
+    },
+    /// Move a floating window so that it is fully visible within the working area.
+    #[cfg_attr(
+        feature = "clap",
+        clap(about = "Move the focused floating window fully on screen")
+    )]
+    BringWindowOnScreen {
+        /// Id of the window to move.
+        ///
+        /// If `None`, uses the focused window.
+        #[cfg_attr(feature = "clap", arg(long))]
+        id: Option<u64>,
+    },
     /// Toggle the opacity of a window.
     #[cfg_attr(
         feature = "clap",
@@ -622,6 +1068,38 @@ pub enum Action {
         #[cfg_attr(feature = "clap", arg(long))]
         id: Option<u64>,
     },
+    /// Set a mark on a window.
+    ///
+    /// A mark can be set on only one window at a time. Setting it on a window removes it from any
+    /// other window.
+    #[cfg_attr(feature = "clap", clap(about = "Set a mark on the focused window"))]
+    SetWindowMark {
+        /// Id of the window.
+        ///
+        /// If `None`, uses the focused window.
+        #[cfg_attr(feature = "clap", arg(long))]
+        id: Option<u64>,
+
+        /// Mark to set.
+        mark: String,
+    },
+    /// Remove a mark from a window.
+    #[cfg_attr(
+        feature = "clap",
+        clap(about = "Remove a mark from the focused window")
+    )]
+    UnsetWindowMark {
+        /// Id of the window.
+        ///
+        /// If `None`, uses the focused window.
+        #[cfg_attr(feature = "clap", arg(long))]
+        id: Option<u64>,
+
+        /// Mark to remove.
+        ///
+        /// If `None`, removes all marks from the window.
+        mark: Option<String>,
+    },
 }
 
 /// Change in window or column size.
@@ -648,6 +1126,49 @@ pub enum PositionChange {
     AdjustFixed(f64),
 }
 
+/// Part of the working area to snap a floating window to.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub enum SnapPosition {
+    /// The left half.
+    Left,
+    /// The right half.
+    Right,
+    /// The top half.
+    Top,
+    /// The bottom half.
+    Bottom,
+    /// The top left quarter.
+    TopLeft,
+    /// The top right quarter.
+    TopRight,
+    /// The bottom left quarter.
+    BottomLeft,
+    /// The bottom right quarter.
+    BottomRight,
+}
+
+/// Filter selecting windows for [`Request::BulkAction`] and [`Request::MatchedAction`].
+///
+/// A window must match all of the set fields. Unset fields match any window.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::Args))]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct WindowFilter {
+    /// Regular expression that the window app ID must match.
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub app_id: Option<String>,
+    /// Regular expression that the window title must match.
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub title: Option<String>,
+    /// Regular expression that one of the window marks must match.
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub mark: Option<String>,
+    /// Reference (id, index or name) of the workspace that the window must be on.
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub workspace: Option<WorkspaceReferenceArg>,
+}
+
 /// Workspace reference (id, index or name) to operate on.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
@@ -717,6 +1238,23 @@ pub enum OutputAction {
         #[cfg_attr(feature = "clap", command(flatten))]
         vrr: VrrToSet,
     },
+    /// Set the output brightness.
+    ///
+    /// Uses the backlight for laptop panels and DDC/CI for external monitors.
+    Brightness {
+        /// Brightness to set in percent, or a change like "+10" or "-10".
+        #[cfg_attr(feature = "clap", arg(allow_hyphen_values = true))]
+        percent: BrightnessChange,
+    },
+    /// Mirror another output.
+    ///
+    /// The mirroring output is taken out of the layout and shows the contents of the other output
+    /// scaled to fit.
+    Mirror {
+        /// Name of the output to mirror, or "off" to stop mirroring.
+        #[cfg_attr(feature = "clap", arg())]
+        mirror: MirrorToSet,
+    },
 }
 
 /// Output mode to set.
@@ -798,8 +1336,28 @@ pub struct VrrToSet {
     pub on_demand: bool,
 }
 
+/// Output to mirror.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub enum MirrorToSet {
+    /// Stop mirroring.
+    Off,
+    /// Mirror the output with this name.
+    Output(String),
+}
+
+/// Change in output brightness.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub enum BrightnessChange {
+    /// Set the brightness in percent.
+    SetPercent(f64),
+    /// Add or subtract to the current brightness in percent.
+    AdjustPercent(f64),
+}
+
 /// Connected output.
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 pub struct Output {
     /// Name of the output.
@@ -822,6 +1380,10 @@ pub struct Output {
     pub vrr_supported: bool,
     /// Whether variable refresh rate is enabled on the output.
     pub vrr_enabled: bool,
+    /// Current brightness in percent.
+    ///
+    /// `None` if niri cannot control the brightness of this output, or if it wasn't read yet.
+    pub brightness: Option<f64>,
     /// Logical output information.
     ///
     /// `None` if the output is not mapped to any logical output (for example, if it is disabled).
@@ -920,6 +1482,33 @@ pub struct Window {
     ///
     /// If the window isn't floating then it is in the tiling layout.
     pub is_floating: bool,
+    /// Whether this window wants attention.
+    ///
+    /// Currently, this is set when niri blocks an activation request from a window with the
+    /// `never-steal-focus` window rule. It is cleared once the window is focused.
+    pub is_urgent: bool,
+    /// Time when this window became urgent, in milliseconds of `CLOCK_MONOTONIC`.
+    ///
+    /// `None` if the window isn't urgent. Compare with the current `CLOCK_MONOTONIC` time to find
+    /// out how long the window has been waiting for attention.
+    pub urgent_since_ms: Option<u64>,
+    /// Marks set on this window.
+    pub marks: Vec<String>,
+    /// Position and size of this window.
+    pub layout: WindowLayout,
+}
+
+/// Position and size of a window.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct WindowLayout {
+    /// Position of the top-left corner of the window in the global logical coordinate space.
+    ///
+    /// `None` if the window isn't currently visible on any output, for example when it is on an
+    /// inactive workspace.
+    pub pos: Option<(f64, f64)>,
+    /// Size of the window in logical pixels, excluding borders and other decorations.
+    pub window_size: (i32, i32),
 }
 
 /// Output configuration change result.
@@ -955,6 +1544,16 @@ pub struct Workspace {
     pub idx: u8,
     /// Optional name of the workspace.
     pub name: Option<String>,
+    /// Name derived from the windows on the workspace by the `workspace-auto-name` config rules.
+    ///
+    /// Only unnamed workspaces get an automatic name. It is `None` when the workspace is empty
+    /// or none of its windows match a rule. Bars can show it in place of the missing name.
+    pub auto_name: Option<String>,
+    /// Icon of the workspace, if set in the config.
+    ///
+    /// This is an arbitrary string meant for bars and other UI to show, often an emoji or an icon
+    /// font glyph. Only named workspaces can have an icon.
+    pub icon: Option<String>,
     /// Name of the output that the workspace is on.
     ///
     /// Can be `None` if no outputs are currently connected.
@@ -1021,6 +1620,128 @@ pub struct LayerSurface {
     pub keyboard_interactivity: LayerSurfaceKeyboardInteractivity,
 }
 
+/// A Wayland protocol global advertised by niri.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct WaylandProtocol {
+    /// Interface name of the global, for example `xdg_wm_base`.
+    pub interface: String,
+    /// Highest version of the global that niri advertises.
+    ///
+    /// Clients may bind any version up to this one.
+    pub version: u32,
+    /// Whether the global is hidden from sandboxed clients.
+    ///
+    /// Sandboxed clients are those connected through the security-context protocol, for example
+    /// Flatpak apps.
+    pub privileged: bool,
+}
+
+/// A bind on cooldown, for debugging.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct BindCooldown {
+    /// Human-readable name of the bind key, for example `Super + Wheel Scroll Down`.
+    pub key: String,
+    /// Time left until the bind can trigger again, in milliseconds.
+    pub remaining_ms: u64,
+}
+
+/// Entry of the clipboard history.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct ClipboardEntry {
+    /// Unique id of this entry.
+    pub id: u64,
+    /// Mime types that the entry is available in.
+    pub mime_types: Vec<String>,
+    /// Text contents, if the entry is available as text.
+    pub text: Option<String>,
+    /// Size of the biggest representation of the entry, in bytes.
+    pub size: u64,
+}
+
+/// Explanation of the current keyboard focus, for debugging.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct FocusExplanation {
+    /// What currently has keyboard focus.
+    pub focus: FocusTarget,
+    /// Whether the session is locked.
+    ///
+    /// The lock screen takes keyboard focus over everything else.
+    pub is_locked: bool,
+    /// Whether the screenshot UI is open.
+    ///
+    /// The screenshot UI takes keyboard focus over everything but the lock screen.
+    pub is_screenshot_ui_open: bool,
+    /// Namespace of the layer-shell surface with on-demand keyboard focus, if any.
+    ///
+    /// Layer-shell surfaces get on-demand focus when clicked, and keep it until something else
+    /// is focused.
+    pub on_demand_layer: Option<String>,
+    /// Id of the window focused in the layout, if any.
+    ///
+    /// This window has keyboard focus when nothing above takes it.
+    pub layout_focus: Option<u64>,
+    /// Recent keyboard focus changes, oldest first.
+    pub history: Vec<FocusTransition>,
+}
+
+/// Keyboard focus target.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub enum FocusTarget {
+    /// Nothing has keyboard focus.
+    Nothing,
+    /// A window.
+    Window {
+        /// Id of the window.
+        id: u64,
+    },
+    /// A layer-shell surface.
+    LayerShell {
+        /// Namespace provided by the layer-shell client.
+        namespace: String,
+    },
+    /// The lock screen.
+    LockScreen,
+    /// The screenshot UI.
+    ScreenshotUi,
+}
+
+/// A change of the keyboard focus.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct FocusTransition {
+    /// New keyboard focus target.
+    pub target: FocusTarget,
+    /// What caused the change.
+    pub reason: FocusReason,
+}
+
+/// Cause of a keyboard focus change.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub enum FocusReason {
+    /// A key, mouse or switch bind.
+    Bind,
+    /// An action requested through IPC.
+    Ipc,
+    /// A click, tap or tablet tool tip press.
+    Click,
+    /// The pointer moving onto something with focus-follows-mouse.
+    FocusFollowsMouse,
+    /// An activation request from a client.
+    Activation,
+    /// A newly opened window.
+    WindowOpened,
+    /// Focus returning to the layout after a layer-shell surface closed.
+    FocusRestored,
+    /// Anything else, for example a surface going away.
+    Other,
+}
+
 /// A compositor event.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
@@ -1053,6 +1774,17 @@ pub enum Event {
         /// Id of the new active window, if any.
         active_window_id: Option<u64>,
     },
+    /// A named workspace was closed after staying empty for too long.
+    ///
+    /// Sent for workspaces with `auto-close-if-empty-after` in the config. The workspace loses its
+    /// name and goes away, which a following [`Event::WorkspacesChanged`] reflects, so this event
+    /// doesn't change the workspace state.
+    WorkspaceAutoClosed {
+        /// Id of the closed workspace.
+        id: u64,
+        /// Name that the workspace had.
+        name: String,
+    },
     /// The window configuration has changed.
     WindowsChanged {
         /// The new window configuration.
@@ -1073,6 +1805,14 @@ pub enum Event {
         /// Id of the removed window.
         id: u64,
     },
+    /// The positions or sizes of windows changed.
+    ///
+    /// While windows are animating, these events are throttled, so not every intermediate state
+    /// is sent. However, the final state after the animations end is always sent.
+    WindowLayoutsChanged {
+        /// Pairs consisting of a window id and its new position and size.
+        changes: Vec<(u64, WindowLayout)>,
+    },
     /// Window focus changed.
     ///
     /// All other windows are no longer focused.
@@ -1080,6 +1820,17 @@ pub enum Event {
         /// Id of the newly focused window, or `None` if no window is now focused.
         id: Option<u64>,
     },
+    /// A window is still urgent.
+    ///
+    /// Sent periodically while a window stays urgent, according to the `urgency` config section,
+    /// so that bars can escalate the urgency indication over time. This event doesn't change the
+    /// window state.
+    WindowUrgencyReminder {
+        /// Id of the urgent window.
+        id: u64,
+        /// How long the window has been urgent, in milliseconds.
+        urgent_for_ms: u64,
+    },
     /// The configured keyboard layouts have changed.
     KeyboardLayoutsChanged {
         /// The new keyboard layout configuration.
@@ -1090,6 +1841,110 @@ pub enum Event {
         /// Index of the newly active layout.
         idx: u8,
     },
+    /// The output configuration has changed.
+    ///
+    /// Sent when an already connected output changes, for example when a new output config is
+    /// applied.
+    OutputsChanged {
+        /// The new output configuration, keyed by output name.
+        ///
+        /// This configuration completely replaces the previous configuration. I.e. if any outputs
+        /// are missing from here, then they were disconnected.
+        outputs: HashMap<String, Output>,
+    },
+    /// A new output was connected.
+    OutputConnected {
+        /// The new output.
+        output: Output,
+    },
+    /// An output was disconnected.
+    OutputDisconnected {
+        /// Name of the disconnected output.
+        name: String,
+    },
+    /// The layer-shell surfaces have changed.
+    LayersChanged {
+        /// The new layer-shell surfaces.
+        ///
+        /// This list completely replaces the previous list. I.e. if any surfaces are missing from
+        /// here, then they were closed.
+        layers: Vec<LayerSurface>,
+    },
+    /// The screenshot UI opened or closed.
+    ScreenshotUiOpenedOrClosed {
+        /// Whether the screenshot UI is now open.
+        is_open: bool,
+    },
+    /// The exit confirmation dialog opened or closed.
+    ExitConfirmDialogOpenedOrClosed {
+        /// Whether the exit confirmation dialog is now open.
+        is_open: bool,
+    },
+    /// A workspace switch gesture progressed, started or ended.
+    ///
+    /// While the gesture is ongoing, these events are throttled, so not every intermediate
+    /// progress value is sent. Once the gesture ends, niri animates the switch to its final
+    /// workspace, which is signaled with [`Event::WorkspaceActivated`] as usual.
+    WorkspaceSwitchGestureChanged {
+        /// Name of the output where the gesture is happening.
+        output: String,
+        /// Progress of the gesture, or `None` if the gesture has ended.
+        ///
+        /// Negative values move towards the workspace above, and positive values towards the
+        /// workspace below. The absolute value goes from 0 at the start of the gesture to 1 at the
+        /// neighboring workspace, and can slightly overshoot due to rubber banding.
+        progress: Option<f64>,
+    },
+}
+
+/// Class of compositor events, for subscribing to a subset of the event stream.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub enum EventClass {
+    /// Workspace events, including workspace activation and active window changes.
+    Workspaces,
+    /// Window events, including focus, layout and urgency changes.
+    Windows,
+    /// Keyboard layout events.
+    KeyboardLayouts,
+    /// Output events.
+    Outputs,
+    /// Layer-shell surface events.
+    Layers,
+    /// Events about built-in UI, like the screenshot UI, opening and closing.
+    Ui,
+    /// Progress of ongoing gestures, like the workspace switch gesture.
+    Gestures,
+}
+
+impl Event {
+    /// Returns the class of this event.
+    pub fn class(&self) -> EventClass {
+        match self {
+            Event::WorkspacesChanged { .. }
+            | Event::WorkspaceActivated { .. }
+            | Event::WorkspaceActiveWindowChanged { .. }
+            | Event::WorkspaceAutoClosed { .. } => EventClass::Workspaces,
+            Event::WindowsChanged { .. }
+            | Event::WindowOpenedOrChanged { .. }
+            | Event::WindowClosed { .. }
+            | Event::WindowLayoutsChanged { .. }
+            | Event::WindowFocusChanged { .. }
+            | Event::WindowUrgencyReminder { .. } => EventClass::Windows,
+            Event::KeyboardLayoutsChanged { .. } | Event::KeyboardLayoutSwitched { .. } => {
+                EventClass::KeyboardLayouts
+            }
+            Event::OutputsChanged { .. }
+            | Event::OutputConnected { .. }
+            | Event::OutputDisconnected { .. } => EventClass::Outputs,
+            Event::LayersChanged { .. } => EventClass::Layers,
+            Event::ScreenshotUiOpenedOrClosed { .. }
+            | Event::ExitConfirmDialogOpenedOrClosed { .. } => EventClass::Ui,
+            Event::WorkspaceSwitchGestureChanged { .. } => EventClass::Gestures,
+        }
+    }
 }
 
 impl FromStr for WorkspaceReferenceArg {
@@ -1150,6 +2005,58 @@ impl FromStr for SizeChange {
     }
 }
 
+impl FromStr for MirrorToSet {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("off") {
+            return Ok(Self::Off);
+        }
+
+        if s.is_empty() {
+            return Err("output name is missing");
+        }
+
+        Ok(Self::Output(s.to_owned()))
+    }
+}
+
+impl FromStr for BrightnessChange {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = s.strip_suffix('%').unwrap_or(s);
+        let change = match value.bytes().next() {
+            Some(b'-' | b'+') => {
+                let value = value.parse().map_err(|_| "error parsing value")?;
+                Self::AdjustPercent(value)
+            }
+            Some(_) => {
+                let value = value.parse().map_err(|_| "error parsing value")?;
+                Self::SetPercent(value)
+            }
+            None => return Err("value is missing"),
+        };
+        change.validate()?;
+        Ok(change)
+    }
+}
+
+impl BrightnessChange {
+    /// Checks that the value is a finite number and that set values are between 0 and 100.
+    pub fn validate(self) -> Result<(), &'static str> {
+        match self {
+            Self::SetPercent(percent) if !(0. ..=100.).contains(&percent) => {
+                Err("brightness must be between 0 and 100")
+            }
+            Self::AdjustPercent(delta) if !delta.is_finite() => {
+                Err("brightness change must be a finite number")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
 impl FromStr for PositionChange {
     type Err = &'static str;
 
@@ -1184,6 +2091,27 @@ impl FromStr for LayoutSwitchTarget {
     }
 }
 
+impl FromStr for SnapPosition {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "left" => Ok(Self::Left),
+            "right" => Ok(Self::Right),
+            "top" => Ok(Self::Top),
+            "bottom" => Ok(Self::Bottom),
+            "top-left" => Ok(Self::TopLeft),
+            "top-right" => Ok(Self::TopRight),
+            "bottom-left" => Ok(Self::BottomLeft),
+            "bottom-right" => Ok(Self::BottomRight),
+            _ => Err(concat!(
+                r#"invalid snap position, can be "left", "right", "top", "bottom", "#,
+                r#""top-left", "top-right", "bottom-left" or "bottom-right""#,
+            )),
+        }
+    }
+}
+
 impl FromStr for Transform {
     type Err = &'static str;
 
@@ -4,13 +4,26 @@ use std::env;
 use std::io::{self, BufRead, BufReader, Write};
 use std::net::Shutdown;
 use std::os::unix::net::UnixStream;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use crate::{Event, Reply, Request};
 
 /// Name of the environment variable containing the niri IPC socket path.
 pub const SOCKET_PATH_ENV: &str = "NIRI_SOCKET";
 
+/// Returns the path of the default niri IPC socket.
+///
+/// The path is taken from the [`SOCKET_PATH_ENV`] environment variable.
+pub fn socket_path() -> io::Result<PathBuf> {
+    let path = env::var_os(SOCKET_PATH_ENV).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{SOCKET_PATH_ENV} is not set, are you running this within niri?"),
+        )
+    })?;
+    Ok(PathBuf::from(path))
+}
+
 /// Helper for blocking communication over the niri socket.
 ///
 /// This struct is used to communicate with the niri IPC server. It handles the socket connection
@@ -25,13 +38,7 @@ impl Socket {
     /// This is equivalent to calling [`Self::connect_to`] with the path taken from the
     /// [`SOCKET_PATH_ENV`] environment variable.
     pub fn connect() -> io::Result<Self> {
-        let socket_path = env::var_os(SOCKET_PATH_ENV).ok_or_else(|| {
-            io::Error::new(
-                io::ErrorKind::NotFound,
-                format!("{SOCKET_PATH_ENV} is not set, are you running this within niri?"),
-            )
-        })?;
-        Self::connect_to(socket_path)
+        Self::connect_to(socket_path()?)
     }
 
     /// Connects to the niri IPC socket at the given path.
@@ -74,4 +81,28 @@ impl Socket {
 
         Ok((reply, events))
     }
+
+    /// Sends a batch of requests to niri and returns their replies, in the same order.
+    ///
+    /// Requests that only read the state, like [`Outputs`][Request::Outputs] and
+    /// [`Windows`][Request::Windows], see a consistent state within a batch. An
+    /// [`EventStream`][Request::EventStream] cannot be requested in a batch.
+    ///
+    /// Returns an error if there was an error communicating with niri. Errors from niri are
+    /// returned for every request separately.
+    pub fn send_batch(self, requests: &[Request]) -> io::Result<Vec<Reply>> {
+        let Self { mut stream } = self;
+
+        let mut buf = serde_json::to_string(requests).unwrap();
+        stream.write_all(buf.as_bytes())?;
+        stream.shutdown(Shutdown::Write)?;
+
+        let mut reader = BufReader::new(stream);
+
+        buf.clear();
+        reader.read_line(&mut buf)?;
+
+        let replies = serde_json::from_str(&buf)?;
+        Ok(replies)
+    }
 }
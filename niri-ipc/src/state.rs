@@ -9,7 +9,7 @@
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 
-use crate::{Event, KeyboardLayouts, Window, Workspace};
+use crate::{Event, KeyboardLayouts, LayerSurface, Output, Window, Workspace};
 
 /// Part of the state communicated via the event stream.
 pub trait EventStreamStatePart {
@@ -40,6 +40,15 @@ pub struct EventStreamState {
 
     /// State of the keyboard layouts.
     pub keyboard_layouts: KeyboardLayoutsState,
+
+    /// State of outputs.
+    pub outputs: OutputsState,
+
+    /// State of layer-shell surfaces.
+    pub layers: LayersState,
+
+    /// State of the built-in UI.
+    pub ui: UiState,
 }
 
 /// The workspaces state communicated over the event stream.
@@ -63,12 +72,38 @@ pub struct KeyboardLayoutsState {
     pub keyboard_layouts: Option<KeyboardLayouts>,
 }
 
+/// The outputs state communicated over the event stream.
+#[derive(Debug, Default)]
+pub struct OutputsState {
+    /// Map from an output name to the output.
+    pub outputs: HashMap<String, Output>,
+}
+
+/// The layer-shell surfaces state communicated over the event stream.
+#[derive(Debug, Default)]
+pub struct LayersState {
+    /// Layer-shell surfaces across all outputs.
+    pub layers: Vec<LayerSurface>,
+}
+
+/// The built-in UI state communicated over the event stream.
+#[derive(Debug, Default)]
+pub struct UiState {
+    /// Whether the screenshot UI is open.
+    pub is_screenshot_ui_open: bool,
+    /// Whether the exit confirmation dialog is open.
+    pub is_exit_confirm_dialog_open: bool,
+}
+
 impl EventStreamStatePart for EventStreamState {
     fn replicate(&self) -> Vec<Event> {
         let mut events = Vec::new();
         events.extend(self.workspaces.replicate());
         events.extend(self.windows.replicate());
         events.extend(self.keyboard_layouts.replicate());
+        events.extend(self.outputs.replicate());
+        events.extend(self.layers.replicate());
+        events.extend(self.ui.replicate());
         events
     }
 
@@ -76,6 +111,9 @@ impl EventStreamStatePart for EventStreamState {
         let event = self.workspaces.apply(event)?;
         let event = self.windows.apply(event)?;
         let event = self.keyboard_layouts.apply(event)?;
+        let event = self.outputs.apply(event)?;
+        let event = self.layers.apply(event)?;
+        let event = self.ui.apply(event)?;
         Some(event)
     }
 }
@@ -157,6 +195,13 @@ impl EventStreamStatePart for WindowsState {
                 let win = self.windows.remove(&id);
                 win.expect("closed window was missing from the map");
             }
+            Event::WindowLayoutsChanged { changes } => {
+                for (id, layout) in changes {
+                    let win = self.windows.get_mut(&id);
+                    let win = win.expect("changed window was missing from the map");
+                    win.layout = layout;
+                }
+            }
             Event::WindowFocusChanged { id } => {
                 for win in self.windows.values_mut() {
                     win.is_focused = Some(win.id) == id;
@@ -192,3 +237,70 @@ impl EventStreamStatePart for KeyboardLayoutsState {
         None
     }
 }
+
+impl EventStreamStatePart for OutputsState {
+    fn replicate(&self) -> Vec<Event> {
+        let outputs = self.outputs.clone();
+        vec![Event::OutputsChanged { outputs }]
+    }
+
+    fn apply(&mut self, event: Event) -> Option<Event> {
+        match event {
+            Event::OutputsChanged { outputs } => {
+                self.outputs = outputs;
+            }
+            Event::OutputConnected { output } => {
+                self.outputs.insert(output.name.clone(), output);
+            }
+            Event::OutputDisconnected { name } => {
+                let output = self.outputs.remove(&name);
+                output.expect("disconnected output was missing from the map");
+            }
+            event => return Some(event),
+        }
+        None
+    }
+}
+
+impl EventStreamStatePart for LayersState {
+    fn replicate(&self) -> Vec<Event> {
+        let layers = self.layers.clone();
+        vec![Event::LayersChanged { layers }]
+    }
+
+    fn apply(&mut self, event: Event) -> Option<Event> {
+        match event {
+            Event::LayersChanged { layers } => {
+                self.layers = layers;
+            }
+            event => return Some(event),
+        }
+        None
+    }
+}
+
+impl EventStreamStatePart for UiState {
+    fn replicate(&self) -> Vec<Event> {
+        vec![
+            Event::ScreenshotUiOpenedOrClosed {
+                is_open: self.is_screenshot_ui_open,
+            },
+            Event::ExitConfirmDialogOpenedOrClosed {
+                is_open: self.is_exit_confirm_dialog_open,
+            },
+        ]
+    }
+
+    fn apply(&mut self, event: Event) -> Option<Event> {
+        match event {
+            Event::ScreenshotUiOpenedOrClosed { is_open } => {
+                self.is_screenshot_ui_open = is_open;
+            }
+            Event::ExitConfirmDialogOpenedOrClosed { is_open } => {
+                self.is_exit_confirm_dialog_open = is_open;
+            }
+            event => return Some(event),
+        }
+        None
+    }
+}
@@ -19,6 +19,7 @@ pub mod gradient_srgb_alpha;
 pub mod gradient_srgblinear;
 pub mod gradient_srgblinear_alpha;
 pub mod layout;
+pub mod spring;
 pub mod tile;
 pub mod window;
 
@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+use niri::animation::tuning::{suggest_spring, SpringFeel};
+use niri::animation::{Animation, Spring};
+use niri::render_helpers::solid_color::{SolidColorBuffer, SolidColorRenderElement};
+use smithay::backend::renderer::element::{Kind, RenderElement};
+use smithay::backend::renderer::gles::GlesRenderer;
+use smithay::utils::{Physical, Point, Size};
+
+use super::{Args, TestCase};
+
+const SQUARE_SIZE: f64 = 100.;
+
+/// Moves a square back and forth using the spring suggested for a feel.
+pub struct SpringTuning {
+    anim: Animation,
+    square: SolidColorBuffer,
+    target: SolidColorBuffer,
+}
+
+impl SpringTuning {
+    pub fn snappy(args: Args) -> Self {
+        Self::new(args, SpringFeel::Snappy)
+    }
+
+    pub fn smooth(args: Args) -> Self {
+        Self::new(args, SpringFeel::Smooth)
+    }
+
+    fn new(args: Args, feel: SpringFeel) -> Self {
+        let suggestion = suggest_spring(feel);
+        info!("suggested spring:\n{}", suggestion.config_snippet(feel));
+
+        let spring = Spring {
+            from: 0.,
+            to: 1.,
+            initial_velocity: 0.,
+            params: suggestion.params(),
+        };
+
+        Self {
+            anim: Animation::spring(args.clock, spring),
+            square: SolidColorBuffer::new((SQUARE_SIZE, SQUARE_SIZE), [1., 0.64, 0.28, 1.]),
+            target: SolidColorBuffer::new((SQUARE_SIZE, SQUARE_SIZE), [1., 1., 1., 0.2]),
+        }
+    }
+}
+
+impl TestCase for SpringTuning {
+    fn are_animations_ongoing(&self) -> bool {
+        true
+    }
+
+    fn advance_animations(&mut self, _current_time: Duration) {
+        if self.anim.is_done() {
+            let (from, to) = (self.anim.to(), self.anim.from());
+            self.anim = self.anim.restarted(from, to, 0.);
+        }
+    }
+
+    fn render(
+        &mut self,
+        _renderer: &mut GlesRenderer,
+        size: Size<i32, Physical>,
+    ) -> Vec<Box<dyn RenderElement<GlesRenderer>>> {
+        let size = size.to_f64();
+        let travel = (size.w - SQUARE_SIZE * 3.).max(0.);
+        let y = (size.h - SQUARE_SIZE) / 2.;
+
+        let x = |value: f64| SQUARE_SIZE + value * travel;
+
+        [
+            SolidColorRenderElement::from_buffer(
+                &self.square,
+                Point::from((x(self.anim.value()), y)),
+                1.,
+                Kind::Unspecified,
+            ),
+            SolidColorRenderElement::from_buffer(
+                &self.target,
+                Point::from((x(self.anim.to()), y)),
+                1.,
+                Kind::Unspecified,
+            ),
+        ]
+        .into_iter()
+        .map(|elem| Box::new(elem) as _)
+        .collect()
+    }
+}
@@ -1,12 +1,13 @@
 #[macro_use]
 extern crate tracing;
 
-use std::env;
+use std::{env, fs};
 
 use adw::prelude::{AdwApplicationWindowExt, NavigationPageExt};
 use cases::Args;
 use gtk::prelude::{ApplicationExt, ApplicationExtManual, BoxExt, GtkWindowExt, WidgetExt};
 use gtk::{gdk, gio, glib};
+use niri::animation::tuning::{suggest_spring, SpringFeel};
 use smithay_view::SmithayView;
 use tracing_subscriber::EnvFilter;
 
@@ -24,6 +25,7 @@ use crate::cases::gradient_srgb_alpha::GradientSrgbAlpha;
 use crate::cases::gradient_srgblinear::GradientSrgbLinear;
 use crate::cases::gradient_srgblinear_alpha::GradientSrgbLinearAlpha;
 use crate::cases::layout::Layout;
+use crate::cases::spring::SpringTuning;
 use crate::cases::tile::Tile;
 use crate::cases::window::Window;
 use crate::cases::TestCase;
@@ -41,12 +43,44 @@ fn main() -> glib::ExitCode {
         .with_env_filter(env_filter)
         .init();
 
+    let mut args = env::args().skip(1);
+    if args.next().as_deref() == Some("tune-spring") {
+        return tune_spring(args);
+    }
+
     let app = adw::Application::new(None::<&str>, gio::ApplicationFlags::NON_UNIQUE);
     app.connect_startup(on_startup);
     app.connect_activate(build_ui);
     app.run()
 }
 
+/// Finds the spring closest to the requested feel and prints or saves a config snippet for it.
+fn tune_spring(mut args: impl Iterator<Item = String>) -> glib::ExitCode {
+    let feel = args.next();
+    let feel = match feel.as_deref().unwrap_or("snappy").parse::<SpringFeel>() {
+        Ok(feel) => feel,
+        Err(err) => {
+            error!("{err}");
+            return glib::ExitCode::FAILURE;
+        }
+    };
+
+    let suggestion = suggest_spring(feel);
+    let snippet = suggestion.config_snippet(feel);
+
+    if let Some(path) = args.next() {
+        if let Err(err) = fs::write(&path, snippet) {
+            error!("error writing {path}: {err}");
+            return glib::ExitCode::FAILURE;
+        }
+        info!("wrote the spring config snippet to {path}");
+    } else {
+        print!("{snippet}");
+    }
+
+    glib::ExitCode::SUCCESS
+}
+
 fn on_startup(_app: &adw::Application) {
     // Load our CSS.
     let provider = gtk::CssProvider::new();
@@ -116,6 +150,9 @@ fn build_ui(app: &adw::Application) {
         "Layout - Open To The Left - Big",
     );
 
+    s.add(SpringTuning::snappy, "Spring - Snappy");
+    s.add(SpringTuning::smooth, "Spring - Smooth");
+
     s.add(GradientAngle::new, "Gradient - Angle");
     s.add(GradientArea::new, "Gradient - Area");
     s.add(GradientSrgb::new, "Gradient - Srgb");
@@ -210,6 +210,10 @@ impl LayoutElement for TestWindow {
         false
     }
 
+    fn title(&self) -> Option<String> {
+        None
+    }
+
     fn output_enter(&self, _output: &Output) {}
 
     fn output_leave(&self, _output: &Output) {}
@@ -220,6 +224,8 @@ impl LayoutElement for TestWindow {
 
     fn set_active_in_column(&mut self, _active: bool) {}
 
+    fn set_maximized(&mut self, _maximized: bool) {}
+
     fn set_floating(&mut self, _floating: bool) {}
 
     fn set_bounds(&self, _bounds: Size<i32, Logical>) {}
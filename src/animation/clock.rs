@@ -26,6 +26,7 @@ struct AdjustableClock {
     last_seen_time: Duration,
     rate: f64,
     complete_instantly: bool,
+    frozen: bool,
 }
 
 impl Clock {
@@ -76,6 +77,26 @@ impl Clock {
     pub fn set_complete_instantly(&mut self, value: bool) {
         self.inner.borrow_mut().set_complete_instantly(value);
     }
+
+    /// Returns whether the clock is frozen.
+    pub fn is_frozen(&self) -> bool {
+        self.inner.borrow().is_frozen()
+    }
+
+    /// Sets whether the clock is frozen.
+    ///
+    /// A frozen clock keeps returning the same time until it is unfrozen or explicitly stepped
+    /// with [`Clock::step`].
+    pub fn set_frozen(&mut self, value: bool) {
+        self.inner.borrow_mut().set_frozen(value);
+    }
+
+    /// Advances the clock by the given amount, regardless of the rate.
+    ///
+    /// This is mainly useful for stepping a frozen clock.
+    pub fn step(&mut self, delta: Duration) {
+        self.inner.borrow_mut().step(delta);
+    }
 }
 
 impl PartialEq for Clock {
@@ -113,6 +134,7 @@ impl AdjustableClock {
             last_seen_time: time,
             rate: 1.,
             complete_instantly: false,
+            frozen: false,
         }
     }
 
@@ -132,6 +154,21 @@ impl AdjustableClock {
         self.complete_instantly = value;
     }
 
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    pub fn set_frozen(&mut self, value: bool) {
+        // Catch up to the underlying time so that the time doesn't jump on freezing or unfreezing.
+        self.now();
+        self.frozen = value;
+    }
+
+    pub fn step(&mut self, delta: Duration) {
+        self.now();
+        self.current_time = self.current_time.saturating_add(delta);
+    }
+
     pub fn now(&mut self) -> Duration {
         let time = self.inner.now();
 
@@ -139,6 +176,12 @@ impl AdjustableClock {
             return self.current_time;
         }
 
+        // While frozen, keep following the underlying time without advancing.
+        if self.frozen {
+            self.last_seen_time = time;
+            return self.current_time;
+        }
+
         if self.last_seen_time < time {
             let delta = time - self.last_seen_time;
             let delta = delta.mul_f64(self.rate);
@@ -199,4 +242,29 @@ mod tests {
         assert_eq!(clock.now_unadjusted(), Duration::from_millis(250));
         assert_eq!(clock.now(), Duration::from_millis(275));
     }
+
+    #[test]
+    fn freeze_and_step() {
+        let mut clock = Clock::with_time(Duration::ZERO);
+        clock.set_rate(0.5);
+
+        clock.set_unadjusted(Duration::from_millis(100));
+        assert_eq!(clock.now(), Duration::from_millis(50));
+
+        clock.set_frozen(true);
+        clock.set_unadjusted(Duration::from_millis(300));
+        assert_eq!(clock.now(), Duration::from_millis(50));
+
+        clock.step(Duration::from_millis(16));
+        assert_eq!(clock.now(), Duration::from_millis(66));
+
+        clock.set_unadjusted(Duration::from_millis(400));
+        assert_eq!(clock.now(), Duration::from_millis(66));
+
+        clock.set_frozen(false);
+        assert_eq!(clock.now(), Duration::from_millis(66));
+
+        clock.set_unadjusted(Duration::from_millis(500));
+        assert_eq!(clock.now(), Duration::from_millis(116));
+    }
 }
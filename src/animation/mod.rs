@@ -4,7 +4,9 @@ use keyframe::functions::{EaseOutCubic, EaseOutQuad};
 use keyframe::EasingFunction;
 
 mod spring;
-pub use spring::{Spring, SpringParams};
+pub use spring::{Spring, SpringMetrics, SpringParams};
+
+pub mod tuning;
 
 mod clock;
 pub use clock::Clock;
@@ -8,6 +8,18 @@ pub struct SpringParams {
     pub epsilon: f64,
 }
 
+/// Measured characteristics of a spring animation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpringMetrics {
+    /// Largest distance past the target, as a fraction of the animated distance.
+    pub overshoot: f64,
+    /// Time after which the spring stays within [`SETTLE_THRESHOLD`] of the target.
+    pub settle_time: Duration,
+}
+
+/// Fraction of the animated distance that counts as settled for [`SpringMetrics`].
+pub const SETTLE_THRESHOLD: f64 = 0.01;
+
 #[derive(Debug, Clone, Copy)]
 pub struct Spring {
     pub from: f64,
@@ -127,6 +139,39 @@ impl Spring {
         Some(Duration::from_millis(u64::from(i)))
     }
 
+    /// Measures the overshoot and the settle time by sampling the spring every millisecond.
+    pub fn metrics(&self) -> SpringMetrics {
+        let distance = self.to - self.from;
+        if distance.abs() <= f64::EPSILON {
+            return SpringMetrics {
+                overshoot: 0.,
+                settle_time: Duration::ZERO,
+            };
+        }
+
+        // Don't sample forever for springs that never stop.
+        let duration = self.duration().min(Duration::from_secs(10));
+        let samples = duration.as_millis() as u32;
+
+        let mut overshoot = 0f64;
+        let mut settle_time = Duration::ZERO;
+        for i in 0..=samples {
+            let t = f64::from(i) / 1000.;
+            // Positive when past the target in the direction of the animation.
+            let past_target = (self.oscillate(t) - self.to) / distance;
+
+            overshoot = overshoot.max(past_target);
+            if past_target.abs() > SETTLE_THRESHOLD {
+                settle_time = Duration::from_millis(u64::from(i) + 1);
+            }
+        }
+
+        SpringMetrics {
+            overshoot,
+            settle_time,
+        }
+    }
+
     /// Returns the spring position at a given time in seconds.
     fn oscillate(&self, t: f64) -> f64 {
         let b = self.params.damping;
@@ -166,3 +211,38 @@ impl Spring {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spring(damping_ratio: f64, stiffness: f64) -> Spring {
+        Spring {
+            from: 0.,
+            to: 1.,
+            initial_velocity: 0.,
+            params: SpringParams::new(damping_ratio, stiffness, 0.0001),
+        }
+    }
+
+    #[test]
+    fn critically_damped_does_not_overshoot() {
+        let metrics = spring(1., 800.).metrics();
+        assert!(metrics.overshoot < 1e-6, "{metrics:?}");
+        assert!(!metrics.settle_time.is_zero());
+    }
+
+    #[test]
+    fn underdamped_overshoots() {
+        let metrics = spring(0.6, 1000.).metrics();
+        // The first peak of an underdamped spring is at exp(-pi * z / sqrt(1 - z^2)).
+        assert!((metrics.overshoot - 0.0948).abs() < 0.001, "{metrics:?}");
+    }
+
+    #[test]
+    fn stiffer_settles_faster() {
+        let slow = spring(1., 400.).metrics();
+        let fast = spring(1., 1600.).metrics();
+        assert!(fast.settle_time < slow.settle_time);
+    }
+}
@@ -0,0 +1,161 @@
+//! Search for spring parameters that match a desired feel.
+
+use std::fmt::Write as _;
+use std::str::FromStr;
+use std::time::Duration;
+
+use super::spring::{Spring, SpringMetrics, SpringParams};
+
+/// Epsilon used for the suggested springs, same as most of the default animations.
+const EPSILON: f64 = 0.0001;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpringFeel {
+    /// Quick to settle, with a slight bounce.
+    Snappy,
+    /// Slower to settle, with no bounce.
+    Smooth,
+}
+
+/// Suggested spring parameters, along with their measured characteristics.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpringSuggestion {
+    pub damping_ratio: f64,
+    pub stiffness: u32,
+    pub epsilon: f64,
+    pub metrics: SpringMetrics,
+}
+
+impl SpringFeel {
+    /// Returns the target overshoot and settle time.
+    pub fn target(self) -> (f64, Duration) {
+        match self {
+            SpringFeel::Snappy => (0.02, Duration::from_millis(250)),
+            SpringFeel::Smooth => (0., Duration::from_millis(500)),
+        }
+    }
+}
+
+impl FromStr for SpringFeel {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "snappy" => Ok(Self::Snappy),
+            "smooth" => Ok(Self::Smooth),
+            _ => Err(r#"invalid feel, can be "snappy" or "smooth""#),
+        }
+    }
+}
+
+impl SpringSuggestion {
+    pub fn params(&self) -> SpringParams {
+        SpringParams::new(self.damping_ratio, f64::from(self.stiffness), self.epsilon)
+    }
+
+    /// Returns an `animations` config section using these parameters for all spring animations.
+    pub fn config_snippet(&self, feel: SpringFeel) -> String {
+        let SpringSuggestion {
+            damping_ratio,
+            stiffness,
+            epsilon,
+            metrics,
+        } = self;
+
+        let spring = format!(
+            "spring damping-ratio={damping_ratio:.2} stiffness={stiffness} epsilon={epsilon}"
+        );
+
+        let mut snippet = String::new();
+        writeln!(
+            snippet,
+            "// {feel:?} springs: {:.1}% overshoot, settle in {} ms.",
+            metrics.overshoot * 100.,
+            metrics.settle_time.as_millis(),
+        )
+        .unwrap();
+        snippet.push_str("animations {\n");
+        for name in [
+            "workspace-switch",
+            "horizontal-view-movement",
+            "window-movement",
+            "window-resize",
+            "config-notification-open-close",
+        ] {
+            writeln!(snippet, "    {name} {{\n        {spring}\n    }}").unwrap();
+        }
+        snippet.push_str("}\n");
+        snippet
+    }
+}
+
+/// Sweeps spring parameters and returns the ones closest to the desired feel.
+pub fn suggest_spring(feel: SpringFeel) -> SpringSuggestion {
+    let _span = tracy_client::span!("suggest_spring");
+
+    let (target_overshoot, target_settle_time) = feel.target();
+
+    let mut best: Option<(f64, SpringSuggestion)> = None;
+    for damping_ratio in (10..=20).map(|x| f64::from(x) * 0.05) {
+        for stiffness in (2..=60).map(|x| x * 50) {
+            let spring = Spring {
+                from: 0.,
+                to: 1.,
+                initial_velocity: 0.,
+                params: SpringParams::new(damping_ratio, f64::from(stiffness), EPSILON),
+            };
+            let metrics = spring.metrics();
+
+            // Overshoot is much more noticeable than a few extra milliseconds, so weigh it
+            // heavily.
+            let settle_error =
+                (metrics.settle_time.as_secs_f64() - target_settle_time.as_secs_f64()).abs()
+                    / target_settle_time.as_secs_f64();
+            let overshoot_error = (metrics.overshoot - target_overshoot).abs() * 10.;
+            let error = settle_error + overshoot_error;
+
+            if best
+                .as_ref()
+                .map_or(true, |(best_error, _)| error < *best_error)
+            {
+                let suggestion = SpringSuggestion {
+                    damping_ratio,
+                    stiffness,
+                    epsilon: EPSILON,
+                    metrics,
+                };
+                best = Some((error, suggestion));
+            }
+        }
+    }
+
+    best.unwrap().1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suggestions_match_feel() {
+        for feel in [SpringFeel::Snappy, SpringFeel::Smooth] {
+            let (overshoot, settle_time) = feel.target();
+            let suggestion = suggest_spring(feel);
+            let metrics = suggestion.metrics;
+
+            assert!(
+                (metrics.overshoot - overshoot).abs() < 0.01,
+                "{feel:?}: {metrics:?}"
+            );
+            let settle_diff = metrics.settle_time.as_secs_f64() - settle_time.as_secs_f64();
+            assert!(settle_diff.abs() < 0.05, "{feel:?}: {metrics:?}");
+        }
+    }
+
+    #[test]
+    fn snippet_parses() {
+        let suggestion = suggest_spring(SpringFeel::Snappy);
+        let snippet = suggestion.config_snippet(SpringFeel::Snappy);
+        niri_config::Config::parse("config.kdl", &snippet).unwrap();
+    }
+}
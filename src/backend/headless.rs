@@ -80,6 +80,7 @@ impl Headless {
                 current_mode: Some(0),
                 vrr_supported: false,
                 vrr_enabled: false,
+                brightness: None,
                 logical: Some(logical_output(&output)),
             },
         );
@@ -115,6 +116,7 @@ impl Headless {
             RedrawState::WaitingForVBlank { .. } => unreachable!(),
             RedrawState::WaitingForEstimatedVBlank(_) => unreachable!(),
             RedrawState::WaitingForEstimatedVBlankAndQueued(_) => unreachable!(),
+            RedrawState::WaitingForThrottle(_) => unreachable!(),
         }
 
         output_state.frame_callback_sequence = output_state.frame_callback_sequence.wrapping_add(1);
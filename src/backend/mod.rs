@@ -20,6 +20,8 @@ pub use winit::Winit;
 pub mod headless;
 pub use headless::Headless;
 
+pub mod virtual_output;
+
 pub enum Backend {
     Tty(Tty),
     Winit(Winit),
@@ -89,7 +91,7 @@ impl Backend {
     ) -> RenderResult {
         match self {
             Backend::Tty(tty) => tty.render(niri, output, target_presentation_time),
-            Backend::Winit(winit) => winit.render(niri, output),
+            Backend::Winit(winit) => winit.render(niri, output, target_presentation_time),
             Backend::Headless(headless) => headless.render(niri, output),
         }
     }
@@ -178,6 +180,33 @@ impl Backend {
         }
     }
 
+    /// Creates a virtual output and returns its name.
+    ///
+    /// Returns `None` if the backend doesn't support virtual outputs.
+    pub fn create_virtual_output(
+        &mut self,
+        niri: &mut Niri,
+        width: u16,
+        height: u16,
+    ) -> Option<String> {
+        match self {
+            Backend::Tty(tty) => Some(tty.create_virtual_output(niri, width, height)),
+            Backend::Winit(winit) => Some(winit.create_virtual_output(niri, width, height)),
+            Backend::Headless(_) => None,
+        }
+    }
+
+    /// Removes a virtual output by name.
+    ///
+    /// Returns `false` if there's no such virtual output.
+    pub fn remove_virtual_output(&mut self, niri: &mut Niri, name: &str) -> bool {
+        match self {
+            Backend::Tty(tty) => tty.remove_virtual_output(niri, name),
+            Backend::Winit(winit) => winit.remove_virtual_output(niri, name),
+            Backend::Headless(_) => false,
+        }
+    }
+
     pub fn on_output_config_changed(&mut self, niri: &mut Niri) {
         match self {
             Backend::Tty(tty) => tty.on_output_config_changed(niri),
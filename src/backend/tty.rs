@@ -55,6 +55,7 @@ use smithay_drm_extras::drm_scanner::{DrmScanEvent, DrmScanner};
 use wayland_protocols::wp::linux_dmabuf::zv1::server::zwp_linux_dmabuf_feedback_v1::TrancheFlags;
 use wayland_protocols::wp::presentation_time::server::wp_presentation_feedback;
 
+use super::virtual_output::{split_geometries, VirtualOutputs};
 use super::{IpcOutputMap, RenderResult};
 use crate::backend::OutputId;
 use crate::frame_clock::FrameClock;
@@ -62,7 +63,7 @@ use crate::niri::{Niri, RedrawState, State};
 use crate::render_helpers::debug::draw_damage;
 use crate::render_helpers::renderer::AsGlesRenderer;
 use crate::render_helpers::{resources, shaders, RenderTarget};
-use crate::utils::{get_monotonic_time, is_laptop_panel, logical_output};
+use crate::utils::{color_temperature, get_monotonic_time, is_laptop_panel, logical_output};
 
 const SUPPORTED_COLOR_FORMATS: [Fourcc; 4] = [
     Fourcc::Xrgb8888,
@@ -82,6 +83,9 @@ pub struct Tty {
     primary_node: DrmNode,
     // DRM render node corresponding to the primary GPU.
     primary_render_node: DrmNode,
+    // The primary GPU was removed and there was no other GPU to fall back to. The next added GPU
+    // will become the primary one.
+    primary_gpu_lost: bool,
     // Devices indexed by DRM node (not necessarily the render node).
     devices: HashMap<DrmNode, OutputDevice>,
     // The dma-buf global corresponds to the output device (the primary GPU). It is only `Some()`
@@ -91,6 +95,8 @@ pub struct Tty {
     update_output_config_on_resume: bool,
     // Whether the debug tinting is enabled.
     debug_tint: bool,
+    // Outputs created at runtime that aren't backed by any connector.
+    virtual_outputs: VirtualOutputs,
     ipc_outputs: Arc<Mutex<IpcOutputMap>>,
 }
 
@@ -328,10 +334,12 @@ impl Tty {
             gpu_manager,
             primary_node,
             primary_render_node,
+            primary_gpu_lost: false,
             devices: HashMap::new(),
             dmabuf_global: None,
             update_output_config_on_resume: false,
             debug_tint: false,
+            virtual_outputs: VirtualOutputs::new(),
             ipc_outputs: Arc::new(Mutex::new(HashMap::new())),
         })
     }
@@ -514,6 +522,13 @@ impl Tty {
             .add_node(render_node, gbm.clone())
             .context("error adding render node to GPU manager")?;
 
+        if self.primary_gpu_lost {
+            info!("adopting {render_node} as the primary GPU");
+            self.primary_node = node;
+            self.primary_render_node = render_node;
+            self.primary_gpu_lost = false;
+        }
+
         if node == self.primary_node || render_node == self.primary_render_node {
             if node == self.primary_node {
                 debug!("this is the primary node");
@@ -522,65 +537,7 @@ impl Tty {
                 debug!("this is the primary render node");
             }
 
-            let mut renderer = self
-                .gpu_manager
-                .single_renderer(&render_node)
-                .context("error creating renderer")?;
-
-            if let Err(err) = renderer.bind_wl_display(&niri.display_handle) {
-                warn!("error binding wl-display in EGL: {err:?}");
-            }
-
-            let gles_renderer = renderer.as_gles_renderer();
-            resources::init(gles_renderer);
-            shaders::init(gles_renderer);
-
-            let config = self.config.borrow();
-            if let Some(src) = config.animations.window_resize.custom_shader.as_deref() {
-                shaders::set_custom_resize_program(gles_renderer, Some(src));
-            }
-            if let Some(src) = config.animations.window_close.custom_shader.as_deref() {
-                shaders::set_custom_close_program(gles_renderer, Some(src));
-            }
-            if let Some(src) = config.animations.window_open.custom_shader.as_deref() {
-                shaders::set_custom_open_program(gles_renderer, Some(src));
-            }
-            drop(config);
-
-            niri.update_shaders();
-
-            // Create the dmabuf global.
-            let primary_formats = renderer.dmabuf_formats();
-            let default_feedback =
-                DmabufFeedbackBuilder::new(render_node.dev_id(), primary_formats.clone())
-                    .build()
-                    .context("error building default dmabuf feedback")?;
-            let dmabuf_global = niri
-                .dmabuf_state
-                .create_global_with_default_feedback::<State>(
-                    &niri.display_handle,
-                    &default_feedback,
-                );
-            assert!(self.dmabuf_global.replace(dmabuf_global).is_none());
-
-            // Update the dmabuf feedbacks for all surfaces.
-            for device in self.devices.values_mut() {
-                for surface in device.surfaces.values_mut() {
-                    match surface_dmabuf_feedback(
-                        &surface.compositor,
-                        primary_formats.clone(),
-                        self.primary_render_node,
-                        device.render_node,
-                    ) {
-                        Ok(feedback) => {
-                            surface.dmabuf_feedback = Some(feedback);
-                        }
-                        Err(err) => {
-                            warn!("error building dmabuf feedback: {err:?}");
-                        }
-                    }
-                }
-            }
+            self.init_primary_gpu(niri, render_node)?;
         }
 
         let token = niri
@@ -620,6 +577,68 @@ impl Tty {
         Ok(())
     }
 
+    /// Sets up rendering and the dma-buf global on the primary GPU.
+    fn init_primary_gpu(&mut self, niri: &mut Niri, render_node: DrmNode) -> anyhow::Result<()> {
+        let mut renderer = self
+            .gpu_manager
+            .single_renderer(&render_node)
+            .context("error creating renderer")?;
+
+        if let Err(err) = renderer.bind_wl_display(&niri.display_handle) {
+            warn!("error binding wl-display in EGL: {err:?}");
+        }
+
+        let gles_renderer = renderer.as_gles_renderer();
+        resources::init(gles_renderer);
+        shaders::init(gles_renderer);
+
+        let config = self.config.borrow();
+        if let Some(src) = config.animations.window_resize.custom_shader.as_deref() {
+            shaders::set_custom_resize_program(gles_renderer, Some(src));
+        }
+        if let Some(src) = config.animations.window_close.custom_shader.as_deref() {
+            shaders::set_custom_close_program(gles_renderer, Some(src));
+        }
+        if let Some(src) = config.animations.window_open.custom_shader.as_deref() {
+            shaders::set_custom_open_program(gles_renderer, Some(src));
+        }
+        drop(config);
+
+        niri.update_shaders();
+
+        // Create the dmabuf global.
+        let primary_formats = renderer.dmabuf_formats();
+        let default_feedback =
+            DmabufFeedbackBuilder::new(render_node.dev_id(), primary_formats.clone())
+                .build()
+                .context("error building default dmabuf feedback")?;
+        let dmabuf_global = niri
+            .dmabuf_state
+            .create_global_with_default_feedback::<State>(&niri.display_handle, &default_feedback);
+        assert!(self.dmabuf_global.replace(dmabuf_global).is_none());
+
+        // Update the dmabuf feedbacks for all surfaces.
+        for device in self.devices.values_mut() {
+            for surface in device.surfaces.values_mut() {
+                match surface_dmabuf_feedback(
+                    &surface.compositor,
+                    primary_formats.clone(),
+                    self.primary_render_node,
+                    device.render_node,
+                ) {
+                    Ok(feedback) => {
+                        surface.dmabuf_feedback = Some(feedback);
+                    }
+                    Err(err) => {
+                        warn!("error building dmabuf feedback: {err:?}");
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     fn device_changed(&mut self, device_id: dev_t, niri: &mut Niri) {
         debug!("device changed: {device_id}");
 
@@ -753,7 +772,9 @@ impl Tty {
             lease_state.disable_global::<State>();
         }
 
-        if node == self.primary_node || device.render_node == self.primary_render_node {
+        let was_primary =
+            node == self.primary_node || device.render_node == self.primary_render_node;
+        if was_primary {
             match self.gpu_manager.single_renderer(&device.render_node) {
                 Ok(mut renderer) => renderer.unbind_wl_display(),
                 Err(err) => {
@@ -792,9 +813,55 @@ impl Tty {
         self.gpu_manager.as_mut().remove_node(&device.render_node);
         niri.event_loop.remove(device.token);
 
+        if was_primary {
+            self.fall_back_to_another_gpu(niri);
+        }
+
         self.refresh_ipc_outputs(niri);
     }
 
+    /// Switches rendering over to another GPU after the primary one went away, for example when
+    /// an eGPU is unplugged or its driver is reset.
+    fn fall_back_to_another_gpu(&mut self, niri: &mut Niri) {
+        let seat_primary = udev::primary_gpu(self.session.seat())
+            .ok()
+            .flatten()
+            .and_then(|path| DrmNode::from_path(path).ok());
+
+        // Prefer the GPU that the system considers primary, then any other remaining one.
+        let fallback = self
+            .devices
+            .iter()
+            .find(|(node, _)| Some(**node) == seat_primary)
+            .or_else(|| self.devices.iter().min_by_key(|(node, _)| node.dev_id()))
+            .map(|(node, device)| (*node, device.render_node));
+
+        let Some((node, render_node)) = fallback else {
+            warn!("primary GPU removed and there's no other GPU, waiting for a new one");
+            self.primary_gpu_lost = true;
+            return;
+        };
+
+        info!("primary GPU removed, switching to {render_node}");
+        self.primary_node = node;
+        self.primary_render_node = render_node;
+
+        if let Err(err) = self.init_primary_gpu(niri, render_node) {
+            warn!("error initializing the new primary GPU: {err:?}");
+            self.primary_gpu_lost = true;
+            return;
+        }
+
+        // The window contents were textures on the old GPU which are now gone. send_configure()
+        // sends a configure right away, even with no pending changes, so every client gets one
+        // and commits again. Clients that attach a new buffer get it imported on the new GPU.
+        niri.layout.with_windows(|mapped, _, _| {
+            mapped.toplevel().send_configure();
+        });
+
+        niri.queue_redraw_all();
+    }
+
     fn connector_connected(
         &mut self,
         niri: &mut Niri,
@@ -1072,6 +1139,13 @@ impl Tty {
         assert!(res.is_none(), "crtc must not have already existed");
 
         niri.add_output(output.clone(), Some(refresh_interval(mode)), vrr_enabled);
+        self.refresh_output_split(niri, &output);
+
+        if let Some(ramp) = self.color_temperature_ramp(&output) {
+            if let Err(err) = self.set_gamma(&output, Some(ramp)) {
+                warn!("error setting color temperature: {err:?}");
+            }
+        }
 
         if niri.monitors_active {
             // Redraw the new monitor.
@@ -1118,21 +1192,59 @@ impl Tty {
 
         debug!("disconnecting connector: {:?}", surface.name.connector);
 
+        // Mirroring outputs aren't in the global space, so look through all of them.
         let output = niri
-            .global_space
-            .outputs()
+            .output_state
+            .keys()
             .find(|output| {
-                let tty_state: &TtyOutputState = output.user_data().get().unwrap();
-                tty_state.node == node && tty_state.crtc == crtc
+                // Virtual outputs have no TTY state.
+                let tty_state: Option<&TtyOutputState> = output.user_data().get();
+                tty_state.is_some_and(|s| s.node == node && s.crtc == crtc)
             })
             .cloned();
         if let Some(output) = output {
+            self.virtual_outputs.remove_split(niri, &output);
             niri.remove_output(&output);
         } else {
             error!("missing output for crtc {crtc:?}");
         };
     }
 
+    /// Splits the output into parts or joins it back according to the config.
+    fn refresh_output_split(&mut self, niri: &mut Niri, output: &Output) {
+        let split = {
+            let config = self.config.borrow();
+            let name = output.user_data().get::<OutputName>().unwrap();
+            config.outputs.find(name).and_then(|c| c.split)
+        };
+
+        // Recreate the parts when the split config or the output mode changes.
+        let size = output
+            .current_transform()
+            .transform_size(output.current_mode().unwrap().size);
+        let geometries = split
+            .map(|split| Vec::from(split_geometries(size, split)))
+            .unwrap_or_default();
+        let current: Vec<_> = niri.output_state[output]
+            .split_parts
+            .iter()
+            .map(|(_, geo)| *geo)
+            .collect();
+        if geometries == current {
+            return;
+        }
+
+        if !current.is_empty() {
+            niri.set_split_parts(output, Vec::new());
+            self.virtual_outputs.remove_split(niri, output);
+        }
+
+        if let Some(split) = split {
+            let parts = self.virtual_outputs.create_split(niri, output, split);
+            niri.set_split_parts(output, parts);
+        }
+    }
+
     fn on_vblank(
         &mut self,
         niri: &mut Niri,
@@ -1199,18 +1311,23 @@ impl Tty {
             .message(&message, 0);
 
         let Some(output) = niri
-            .global_space
-            .outputs()
+            .output_state
+            .keys()
             .find(|output| {
-                let tty_state: &TtyOutputState = output.user_data().get().unwrap();
-                tty_state.node == node && tty_state.crtc == crtc
+                // Virtual outputs have no TTY state.
+                let tty_state: Option<&TtyOutputState> = output.user_data().get();
+                tty_state.is_some_and(|s| s.node == node && s.crtc == crtc)
             })
             .cloned()
         else {
-            error!("missing output in global space for {name}");
+            error!("missing output state for {name}");
             return;
         };
 
+        // Parts of a split output are shown on this output, so they follow its vblanks.
+        self.virtual_outputs
+            .on_split_parent_vblank(niri, &output, presentation_time);
+
         let Some(output_state) = niri.output_state.get_mut(&output) else {
             error!("missing output state for {name}");
             return;
@@ -1221,7 +1338,8 @@ impl Tty {
             state @ (RedrawState::Idle
             | RedrawState::Queued
             | RedrawState::WaitingForEstimatedVBlank(_)
-            | RedrawState::WaitingForEstimatedVBlankAndQueued(_)) => {
+            | RedrawState::WaitingForEstimatedVBlankAndQueued(_)
+            | RedrawState::WaitingForThrottle(_)) => {
                 // This is an error!() because it shouldn't happen, but on some systems it somehow
                 // does. Kernel sending rogue vblank events?
                 //
@@ -1231,6 +1349,9 @@ impl Tty {
                     "unexpected redraw state for output {name} (should be WaitingForVBlank); \
                      can happen when resuming from sleep or powering on monitors: {state:?}"
                 );
+                if let RedrawState::WaitingForThrottle(token) = state {
+                    niri.event_loop.remove(token);
+                }
                 true
             }
         };
@@ -1289,6 +1410,11 @@ impl Tty {
         output_state.frame_clock.presented(presentation_time);
 
         if redraw_needed || output_state.unfinished_animations_remain {
+            if let Some(delay) = output_state.frame_clock.throttle_delay(now) {
+                queue_throttled_redraw_timer(niri, output, delay);
+                return;
+            }
+
             let vblank_frame = tracy_client::Client::running()
                 .unwrap()
                 .non_continuous_frame(surface.vblank_frame_name);
@@ -1300,12 +1426,15 @@ impl Tty {
         }
     }
 
-    fn on_estimated_vblank_timer(&self, niri: &mut Niri, output: Output) {
+    fn on_estimated_vblank_timer(&mut self, niri: &mut Niri, output: Output) {
         let span = tracy_client::span!("Tty::on_estimated_vblank_timer");
 
         let name = output.name();
         span.emit_text(&name);
 
+        self.virtual_outputs
+            .on_split_parent_vblank(niri, &output, Duration::ZERO);
+
         let Some(output_state) = niri.output_state.get_mut(&output) else {
             error!("missing output state for {name}");
             return;
@@ -1324,9 +1453,19 @@ impl Tty {
                 output_state.redraw_state = RedrawState::Queued;
                 return;
             }
+            RedrawState::WaitingForThrottle(_) => unreachable!(),
         }
 
+        // Nothing was presented, but the max render FPS should hold across such frames too.
+        let now = get_monotonic_time();
+        output_state.frame_clock.estimated_vblank(now);
+
         if output_state.unfinished_animations_remain {
+            if let Some(delay) = output_state.frame_clock.throttle_delay(now) {
+                queue_throttled_redraw_timer(niri, output, delay);
+                return;
+            }
+
             niri.queue_redraw(&output);
         } else {
             niri.send_frame_callbacks(&output);
@@ -1358,6 +1497,27 @@ impl Tty {
 
         let mut rv = RenderResult::Skipped;
 
+        if self.virtual_outputs.contains(output) {
+            if !self.session.is_active() {
+                return rv;
+            }
+
+            let mut renderer = match self.gpu_manager.single_renderer(&self.primary_render_node) {
+                Ok(renderer) => renderer,
+                Err(err) => {
+                    warn!("error creating renderer for primary GPU: {err:?}");
+                    return rv;
+                }
+            };
+
+            return self.virtual_outputs.render(
+                niri,
+                renderer.as_gles_renderer(),
+                output,
+                target_presentation_time,
+            );
+        }
+
         let tty_state: &TtyOutputState = output.user_data().get().unwrap();
         let Some(device) = self.devices.get_mut(&tty_state.node) else {
             error!("missing output device");
@@ -1467,6 +1627,7 @@ impl Tty {
                                 RedrawState::WaitingForEstimatedVBlankAndQueued(token) => {
                                     niri.event_loop.remove(token);
                                 }
+                                RedrawState::WaitingForThrottle(_) => unreachable!(),
                             };
 
                             // We queued this frame successfully, so the current client buffers were
@@ -1559,7 +1720,10 @@ impl Tty {
     }
 
     pub fn get_gamma_size(&self, output: &Output) -> anyhow::Result<u32> {
-        let tty_state = output.user_data().get::<TtyOutputState>().unwrap();
+        // Virtual outputs don't support gamma.
+        let Some(tty_state) = output.user_data().get::<TtyOutputState>() else {
+            return Ok(0);
+        };
         let crtc = tty_state.crtc;
 
         let device = self
@@ -1580,7 +1744,10 @@ impl Tty {
     }
 
     pub fn set_gamma(&mut self, output: &Output, ramp: Option<Vec<u16>>) -> anyhow::Result<()> {
-        let tty_state = output.user_data().get::<TtyOutputState>().unwrap();
+        let tty_state = output
+            .user_data()
+            .get::<TtyOutputState>()
+            .context("output doesn't support gamma")?;
         let crtc = tty_state.crtc;
 
         let device = self
@@ -1603,6 +1770,25 @@ impl Tty {
         }
     }
 
+    /// Returns the gamma ramp for the color temperature configured for the output.
+    ///
+    /// Returns `None` if the color temperature is unset or neutral.
+    pub fn color_temperature_ramp(&self, output: &Output) -> Option<Vec<u16>> {
+        let name = output.user_data().get::<OutputName>().unwrap();
+        let kelvin = self.config.borrow().outputs.find(name)?.color_temperature?;
+
+        let gamma_size = match self.get_gamma_size(output) {
+            Ok(0) => return None,
+            Ok(size) => size,
+            Err(err) => {
+                warn!("error getting gamma size: {err:?}");
+                return None;
+            }
+        };
+
+        color_temperature::gamma_ramp(gamma_size as usize, kelvin)
+    }
+
     fn refresh_ipc_outputs(&self, niri: &mut Niri) {
         let _span = tracy_client::span!("Tty::refresh_ipc_outputs");
 
@@ -1664,8 +1850,8 @@ impl Tty {
                     .global_space
                     .outputs()
                     .find(|output| {
-                        let tty_state: &TtyOutputState = output.user_data().get().unwrap();
-                        tty_state.node == *node && tty_state.crtc == crtc
+                        let tty_state: Option<&TtyOutputState> = output.user_data().get();
+                        tty_state.is_some_and(|s| s.node == *node && s.crtc == crtc)
                     })
                     .map(logical_output);
 
@@ -1675,6 +1861,10 @@ impl Tty {
                     OutputId::next()
                 });
 
+                // Pick up changes from outside niri and monitors replugged into this connector.
+                niri.brightness.refresh(&connector_name);
+                let brightness = niri.brightness.get(&connector_name);
+
                 let ipc_output = niri_ipc::Output {
                     name: connector_name,
                     make: output_name.make.unwrap_or_else(|| "Unknown".into()),
@@ -1685,6 +1875,7 @@ impl Tty {
                     current_mode,
                     vrr_supported,
                     vrr_enabled,
+                    brightness,
                     logical,
                 };
 
@@ -1692,11 +1883,27 @@ impl Tty {
             }
         }
 
+        self.virtual_outputs.add_ipc_outputs(&mut ipc_outputs);
+
         let mut guard = self.ipc_outputs.lock().unwrap();
         *guard = ipc_outputs;
         niri.ipc_outputs_changed = true;
     }
 
+    pub fn create_virtual_output(&mut self, niri: &mut Niri, width: u16, height: u16) -> String {
+        let name = self.virtual_outputs.create(niri, width, height);
+        self.refresh_ipc_outputs(niri);
+        name
+    }
+
+    pub fn remove_virtual_output(&mut self, niri: &mut Niri, name: &str) -> bool {
+        let removed = self.virtual_outputs.remove(niri, name);
+        if removed {
+            self.refresh_ipc_outputs(niri);
+        }
+        removed
+    }
+
     pub fn ipc_outputs(&self) -> Arc<Mutex<IpcOutputMap>> {
         self.ipc_outputs.clone()
     }
@@ -1743,8 +1950,8 @@ impl Tty {
         }
         for (&node, device) in self.devices.iter_mut() {
             for (&crtc, surface) in device.surfaces.iter_mut() {
-                let tty_state: &TtyOutputState = output.user_data().get().unwrap();
-                if tty_state.node == node && tty_state.crtc == crtc {
+                let tty_state: Option<&TtyOutputState> = output.user_data().get();
+                if tty_state.is_some_and(|s| s.node == node && s.crtc == crtc) {
                     let word = if enable_vrr { "enabling" } else { "disabling" };
                     if let Err(err) = surface.compositor.use_vrr(enable_vrr) {
                         warn!(
@@ -1831,11 +2038,11 @@ impl Tty {
                 }
 
                 let output = niri
-                    .global_space
-                    .outputs()
+                    .output_state
+                    .keys()
                     .find(|output| {
-                        let tty_state: &TtyOutputState = output.user_data().get().unwrap();
-                        tty_state.node == node && tty_state.crtc == crtc
+                        let tty_state: Option<&TtyOutputState> = output.user_data().get();
+                        tty_state.is_some_and(|s| s.node == node && s.crtc == crtc)
                     })
                     .cloned();
                 let Some(output) = output else {
@@ -1892,10 +2099,12 @@ impl Tty {
                     let wl_mode = Mode::from(mode);
                     output.change_current_state(Some(wl_mode), None, None, None);
                     output.set_preferred(wl_mode);
+                    let max_render_fps = output_state.frame_clock.max_render_fps();
                     output_state.frame_clock = FrameClock::new(
                         Some(refresh_interval(mode)),
                         surface.compositor.vrr_enabled(),
                     );
+                    output_state.frame_clock.set_max_render_fps(max_render_fps);
                     niri.output_resized(&output);
                 }
             }
@@ -1946,6 +2155,17 @@ impl Tty {
             }
         }
 
+        // Virtual outputs have no TTY state.
+        let outputs: Vec<_> = niri
+            .output_state
+            .keys()
+            .filter(|output| output.user_data().get::<TtyOutputState>().is_some())
+            .cloned()
+            .collect();
+        for output in outputs {
+            self.refresh_output_split(niri, &output);
+        }
+
         self.refresh_ipc_outputs(niri);
     }
 
@@ -2321,6 +2541,7 @@ fn queue_estimated_vblank_timer(
             output_state.redraw_state = RedrawState::WaitingForEstimatedVBlank(token);
             return;
         }
+        RedrawState::WaitingForThrottle(_) => unreachable!(),
     }
 
     let now = get_monotonic_time();
@@ -2352,6 +2573,40 @@ fn queue_estimated_vblank_timer(
     output_state.redraw_state = RedrawState::WaitingForEstimatedVBlank(token);
 }
 
+/// Holds off the next redraw to stay under the max render FPS.
+///
+/// Redraws queued in the meantime are batched into the one frame rendered after the delay.
+fn queue_throttled_redraw_timer(niri: &mut Niri, output: Output, delay: Duration) {
+    trace!("throttling redraw for {delay:?}");
+
+    let timer = Timer::from_duration(delay);
+    let token = niri
+        .event_loop
+        .insert_source(timer, {
+            let output = output.clone();
+            move |_, _, data| {
+                let niri = &mut data.niri;
+                let output_state = niri.output_state.get_mut(&output).unwrap();
+                match mem::replace(&mut output_state.redraw_state, RedrawState::Idle) {
+                    RedrawState::WaitingForThrottle(_) => (),
+                    _ => unreachable!(),
+                }
+                niri.queue_redraw(&output);
+                TimeoutAction::Drop
+            }
+        })
+        .unwrap();
+
+    let output_state = niri.output_state.get_mut(&output).unwrap();
+    match mem::replace(
+        &mut output_state.redraw_state,
+        RedrawState::WaitingForThrottle(token),
+    ) {
+        RedrawState::Idle => (),
+        _ => unreachable!(),
+    }
+}
+
 fn pick_mode(
     connector: &connector::Info,
     target: Option<niri_ipc::ConfiguredMode>,
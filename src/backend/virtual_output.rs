@@ -0,0 +1,486 @@
+//! Virtual outputs that aren't backed by a physical monitor.
+//!
+//! Virtual outputs are created and removed at runtime through IPC. Their contents aren't shown
+//! anywhere, but otherwise they work like regular outputs, so they can be screencast, for example
+//! to use a tablet as an extra monitor over VNC.
+//!
+//! Parts of split physical outputs are virtual outputs too. They're created by the backend
+//! according to the config, and the physical output shows their contents side by side.
+
+use std::mem;
+use std::time::Duration;
+
+use niri_config::{OutputName, OutputSplit, SplitDirection};
+use smithay::backend::renderer::damage::OutputDamageTracker;
+use smithay::backend::renderer::gles::GlesRenderer;
+use smithay::desktop::utils::OutputPresentationFeedback;
+use smithay::output::{Mode, Output, PhysicalProperties, Subpixel};
+use smithay::reexports::calloop::timer::{TimeoutAction, Timer};
+use smithay::reexports::wayland_protocols::wp::presentation_time::server::wp_presentation_feedback;
+use smithay::utils::{Physical, Rectangle, Size};
+use smithay::wayland::presentation::Refresh;
+
+use super::{IpcOutputMap, OutputId, RenderResult};
+use crate::niri::{Niri, RedrawState};
+use crate::render_helpers::RenderTarget;
+use crate::utils::{get_monotonic_time, logical_output};
+
+/// Refresh rate of virtual outputs, in mHz.
+const REFRESH_RATE: i32 = 60_000;
+
+#[derive(Default)]
+pub struct VirtualOutputs {
+    outputs: Vec<VirtualOutput>,
+}
+
+struct VirtualOutput {
+    id: OutputId,
+    /// Number in the output name, or 0 for parts of split outputs.
+    n: u32,
+    output: Output,
+    /// Physical output that this virtual output is a part of.
+    split_parent: Option<Output>,
+    /// Refresh rate in mHz.
+    refresh: i32,
+    damage_tracker: OutputDamageTracker,
+    /// Feedback for the last frame of a split output part, until the physical output presents it.
+    pending_feedback: Option<OutputPresentationFeedback>,
+}
+
+impl VirtualOutputs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn contains(&self, output: &Output) -> bool {
+        self.outputs.iter().any(|o| o.output == *output)
+    }
+
+    /// Creates a virtual output and returns its name.
+    pub fn create(&mut self, niri: &mut Niri, width: u16, height: u16) -> String {
+        // Reuse the numbers of removed outputs to keep the names predictable.
+        let n = (1..)
+            .find(|n| self.outputs.iter().all(|o| o.n != *n))
+            .unwrap();
+
+        // Same naming as in wlroots, so that tools like wayvnc feel at home.
+        let connector = format!("HEADLESS-{n}");
+        let make = String::from("niri");
+        let model = String::from("Virtual");
+
+        let output = Output::new(
+            connector.clone(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: make.clone(),
+                model: model.clone(),
+            },
+        );
+
+        let mode = Mode {
+            size: Size::from((i32::from(width), i32::from(height))),
+            refresh: REFRESH_RATE,
+        };
+        output.change_current_state(Some(mode), None, None, None);
+        output.set_preferred(mode);
+
+        output.user_data().insert_if_missing(|| OutputName {
+            connector: connector.clone(),
+            make: Some(make),
+            model: Some(model),
+            serial: None,
+        });
+
+        let damage_tracker = OutputDamageTracker::from_output(&output);
+        niri.add_output(output.clone(), Some(refresh_interval(REFRESH_RATE)), false);
+
+        self.outputs.push(VirtualOutput {
+            id: OutputId::next(),
+            n,
+            output,
+            split_parent: None,
+            refresh: REFRESH_RATE,
+            damage_tracker,
+            pending_feedback: None,
+        });
+
+        connector
+    }
+
+    /// Creates the parts of a split output.
+    ///
+    /// Returns the parts along with their geometry within the physical output.
+    pub fn create_split(
+        &mut self,
+        niri: &mut Niri,
+        parent: &Output,
+        split: OutputSplit,
+    ) -> Vec<(Output, Rectangle<i32, Physical>)> {
+        let parent_mode = parent.current_mode().unwrap();
+        let parent_size = parent.current_transform().transform_size(parent_mode.size);
+        let parent_properties = parent.physical_properties();
+        let parent_size_mm = parent
+            .current_transform()
+            .transform_size(parent_properties.size);
+        let refresh = if parent_mode.refresh > 0 {
+            parent_mode.refresh
+        } else {
+            REFRESH_RATE
+        };
+
+        let mut parts = Vec::new();
+        for (idx, geometry) in split_geometries(parent_size, split).into_iter().enumerate() {
+            let connector = format!("{}-{}", parent.name(), idx + 1);
+
+            let size_mm = if parent_size.w > 0 && parent_size.h > 0 {
+                Size::from((
+                    parent_size_mm.w * geometry.size.w / parent_size.w,
+                    parent_size_mm.h * geometry.size.h / parent_size.h,
+                ))
+            } else {
+                Size::from((0, 0))
+            };
+
+            let output = Output::new(
+                connector.clone(),
+                PhysicalProperties {
+                    size: size_mm,
+                    subpixel: parent_properties.subpixel,
+                    make: parent_properties.make.clone(),
+                    model: parent_properties.model.clone(),
+                },
+            );
+
+            let mode = Mode {
+                size: geometry.size,
+                refresh,
+            };
+            output.change_current_state(Some(mode), None, None, None);
+            output.set_preferred(mode);
+
+            // Parts are configured by their connector name alone, so that they don't match the
+            // physical output's make and model.
+            output.user_data().insert_if_missing(|| OutputName {
+                connector,
+                make: None,
+                model: None,
+                serial: None,
+            });
+
+            let damage_tracker = OutputDamageTracker::from_output(&output);
+            niri.add_output(output.clone(), Some(refresh_interval(refresh)), false);
+
+            self.outputs.push(VirtualOutput {
+                id: OutputId::next(),
+                n: 0,
+                output: output.clone(),
+                split_parent: Some(parent.clone()),
+                refresh,
+                damage_tracker,
+                pending_feedback: None,
+            });
+
+            parts.push((output, geometry));
+        }
+
+        parts
+    }
+
+    /// Removes the parts of a split output.
+    pub fn remove_split(&mut self, niri: &mut Niri, parent: &Output) {
+        self.outputs.retain(|o| {
+            if o.split_parent.as_ref() == Some(parent) {
+                niri.remove_output(&o.output);
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    /// Removes the virtual output with the given name.
+    ///
+    /// Returns `false` if there's no such virtual output.
+    pub fn remove(&mut self, niri: &mut Niri, name: &str) -> bool {
+        // Parts of split outputs follow the config and can't be removed by hand.
+        let Some(idx) = self
+            .outputs
+            .iter()
+            .position(|o| o.split_parent.is_none() && o.output.name().eq_ignore_ascii_case(name))
+        else {
+            return false;
+        };
+
+        let virtual_output = self.outputs.remove(idx);
+        niri.remove_output(&virtual_output.output);
+        true
+    }
+
+    pub fn add_ipc_outputs(&self, ipc_outputs: &mut IpcOutputMap) {
+        for virtual_output in &self.outputs {
+            let output = &virtual_output.output;
+            let physical_properties = output.physical_properties();
+            let size = output.current_mode().unwrap().size;
+
+            ipc_outputs.insert(
+                virtual_output.id,
+                niri_ipc::Output {
+                    name: output.name(),
+                    make: physical_properties.make,
+                    model: physical_properties.model,
+                    serial: None,
+                    physical_size: None,
+                    modes: vec![niri_ipc::Mode {
+                        width: size.w.clamp(0, u16::MAX as i32) as u16,
+                        height: size.h.clamp(0, u16::MAX as i32) as u16,
+                        refresh_rate: virtual_output.refresh as u32,
+                        is_preferred: true,
+                    }],
+                    current_mode: Some(0),
+                    vrr_supported: false,
+                    vrr_enabled: false,
+                    brightness: None,
+                    logical: Some(logical_output(output)),
+                },
+            );
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        niri: &mut Niri,
+        renderer: &mut GlesRenderer,
+        output: &Output,
+        target_presentation_time: Duration,
+    ) -> RenderResult {
+        let _span = tracy_client::span!("VirtualOutputs::render");
+
+        let Some(virtual_output) = self.outputs.iter_mut().find(|o| o.output == *output) else {
+            error!("missing virtual output");
+            return RenderResult::Skipped;
+        };
+
+        // The contents aren't shown anywhere, so there's no need to draw them. However, the damage
+        // and the element states still drive frame callbacks and presentation feedback.
+        let elements = niri.render::<GlesRenderer>(renderer, output, true, RenderTarget::Output);
+        let (has_damage, states) = match virtual_output.damage_tracker.damage_output(1, &elements) {
+            Ok((damage, states)) => (damage.is_some(), states),
+            Err(err) => {
+                warn!("error computing virtual output damage: {err:?}");
+                return RenderResult::Skipped;
+            }
+        };
+
+        niri.update_primary_scanout_output(output, &states);
+
+        // Parts of split outputs are shown by their physical output, so they wait for its vblank.
+        if virtual_output.split_parent.is_some() {
+            let output_state = niri.output_state.get_mut(output).unwrap();
+            match mem::take(&mut output_state.redraw_state) {
+                RedrawState::Queued => (),
+                RedrawState::WaitingForEstimatedVBlankAndQueued(token) => {
+                    niri.event_loop.remove(token);
+                }
+                _ => unreachable!(),
+            }
+            output_state.redraw_state = RedrawState::WaitingForVBlank {
+                redraw_needed: false,
+            };
+
+            if has_damage {
+                virtual_output.pending_feedback =
+                    Some(niri.take_presentation_feedbacks(output, &states));
+                return RenderResult::Submitted;
+            } else {
+                return RenderResult::NoDamage;
+            }
+        }
+
+        let rv = if has_damage {
+            let mut presentation_feedbacks = niri.take_presentation_feedbacks(output, &states);
+            presentation_feedbacks.presented::<_, smithay::utils::Monotonic>(
+                get_monotonic_time(),
+                Refresh::fixed(refresh_interval(virtual_output.refresh)),
+                0,
+                wp_presentation_feedback::Kind::empty(),
+            );
+
+            RenderResult::Submitted
+        } else {
+            RenderResult::NoDamage
+        };
+
+        let refresh = virtual_output.refresh;
+        queue_vblank_timer(niri, output.clone(), refresh, target_presentation_time);
+
+        rv
+    }
+
+    /// Finishes the frames of the parts of a split output on its vblank.
+    ///
+    /// The presentation time is zero when it's unknown or when nothing was presented.
+    pub fn on_split_parent_vblank(
+        &mut self,
+        niri: &mut Niri,
+        parent: &Output,
+        presentation_time: Duration,
+    ) {
+        let _span = tracy_client::span!("VirtualOutputs::on_split_parent_vblank");
+
+        for virtual_output in &mut self.outputs {
+            if virtual_output.split_parent.as_ref() != Some(parent) {
+                continue;
+            }
+
+            let output = &virtual_output.output;
+            let Some(output_state) = niri.output_state.get_mut(output) else {
+                error!("missing output state for {}", output.name());
+                continue;
+            };
+
+            let redraw_needed =
+                match mem::replace(&mut output_state.redraw_state, RedrawState::Idle) {
+                    RedrawState::WaitingForVBlank { redraw_needed } => redraw_needed,
+                    // The part didn't render since the last vblank.
+                    state => {
+                        output_state.redraw_state = state;
+                        continue;
+                    }
+                };
+
+            if let Some(mut feedback) = virtual_output.pending_feedback.take() {
+                let (time, flags) = if presentation_time.is_zero() {
+                    (
+                        get_monotonic_time(),
+                        wp_presentation_feedback::Kind::empty(),
+                    )
+                } else {
+                    (
+                        presentation_time,
+                        wp_presentation_feedback::Kind::Vsync
+                            | wp_presentation_feedback::Kind::HwClock,
+                    )
+                };
+                feedback.presented::<_, smithay::utils::Monotonic>(
+                    time,
+                    Refresh::fixed(refresh_interval(virtual_output.refresh)),
+                    0,
+                    flags,
+                );
+            }
+
+            output_state.frame_clock.presented(presentation_time);
+            output_state.frame_callback_sequence =
+                output_state.frame_callback_sequence.wrapping_add(1);
+
+            if redraw_needed || output_state.unfinished_animations_remain {
+                niri.queue_redraw(output);
+            } else {
+                niri.send_frame_callbacks(output);
+            }
+        }
+    }
+}
+
+/// Computes the geometry of the parts of a split output of the given size.
+pub fn split_geometries(
+    size: Size<i32, Physical>,
+    split: OutputSplit,
+) -> [Rectangle<i32, Physical>; 2] {
+    let total = i64::from(split.ratio.first) + i64::from(split.ratio.second);
+
+    match split.direction {
+        SplitDirection::Horizontal => {
+            let w = (i64::from(size.w) * i64::from(split.ratio.first) / total) as i32;
+            [
+                Rectangle::new((0, 0).into(), (w, size.h).into()),
+                Rectangle::new((w, 0).into(), (size.w - w, size.h).into()),
+            ]
+        }
+        SplitDirection::Vertical => {
+            let h = (i64::from(size.h) * i64::from(split.ratio.first) / total) as i32;
+            [
+                Rectangle::new((0, 0).into(), (size.w, h).into()),
+                Rectangle::new((0, h).into(), (size.w, size.h - h).into()),
+            ]
+        }
+    }
+}
+
+fn refresh_interval(refresh: i32) -> Duration {
+    Duration::from_nanos(1_000_000_000_000 / refresh as u64)
+}
+
+/// Queues a timer to fire at the time of the made-up vblank.
+fn queue_vblank_timer(
+    niri: &mut Niri,
+    output: Output,
+    refresh: i32,
+    target_presentation_time: Duration,
+) {
+    let output_state = niri.output_state.get_mut(&output).unwrap();
+    match mem::take(&mut output_state.redraw_state) {
+        RedrawState::Idle => unreachable!(),
+        RedrawState::Queued => (),
+        RedrawState::WaitingForVBlank { .. } => unreachable!(),
+        RedrawState::WaitingForEstimatedVBlank(token)
+        | RedrawState::WaitingForEstimatedVBlankAndQueued(token) => {
+            output_state.redraw_state = RedrawState::WaitingForEstimatedVBlank(token);
+            return;
+        }
+        RedrawState::WaitingForThrottle(_) => unreachable!(),
+    }
+
+    let now = get_monotonic_time();
+    let duration = target_presentation_time.saturating_sub(now);
+    let duration = if duration.is_zero() {
+        refresh_interval(refresh)
+    } else {
+        duration
+    };
+
+    let timer = Timer::from_duration(duration);
+    let token = niri
+        .event_loop
+        .insert_source(timer, move |_, _, data| {
+            on_vblank_timer(&mut data.niri, &output, target_presentation_time);
+            TimeoutAction::Drop
+        })
+        .unwrap();
+    output_state.redraw_state = RedrawState::WaitingForEstimatedVBlank(token);
+}
+
+fn on_vblank_timer(niri: &mut Niri, output: &Output, presentation_time: Duration) {
+    let _span = tracy_client::span!("virtual_output::on_vblank_timer");
+
+    let Some(output_state) = niri.output_state.get_mut(output) else {
+        error!("missing output state for {}", output.name());
+        return;
+    };
+
+    // Pretend that the frame was presented, so that the frame clock keeps a steady pace.
+    output_state.frame_clock.presented(presentation_time);
+
+    // We waited for the timer, now we can send frame callbacks again.
+    output_state.frame_callback_sequence = output_state.frame_callback_sequence.wrapping_add(1);
+
+    match mem::replace(&mut output_state.redraw_state, RedrawState::Idle) {
+        RedrawState::Idle => unreachable!(),
+        RedrawState::Queued => unreachable!(),
+        RedrawState::WaitingForVBlank { .. } => unreachable!(),
+        RedrawState::WaitingForEstimatedVBlank(_) => (),
+        // The timer fired just in front of a redraw.
+        RedrawState::WaitingForEstimatedVBlankAndQueued(_) => {
+            output_state.redraw_state = RedrawState::Queued;
+            return;
+        }
+        RedrawState::WaitingForThrottle(_) => unreachable!(),
+    }
+
+    if output_state.unfinished_animations_remain {
+        niri.queue_redraw(output);
+    } else {
+        niri.send_frame_callbacks(output);
+    }
+}
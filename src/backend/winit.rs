@@ -3,6 +3,7 @@ use std::collections::HashMap;
 use std::mem;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use niri_config::{Config, OutputName};
 use smithay::backend::allocator::dmabuf::Dmabuf;
@@ -17,6 +18,7 @@ use smithay::reexports::winit::dpi::LogicalSize;
 use smithay::reexports::winit::window::Window;
 use smithay::wayland::presentation::Refresh;
 
+use super::virtual_output::VirtualOutputs;
 use super::{IpcOutputMap, OutputId, RenderResult};
 use crate::niri::{Niri, RedrawState, State};
 use crate::render_helpers::debug::draw_damage;
@@ -28,6 +30,7 @@ pub struct Winit {
     output: Output,
     backend: WinitGraphicsBackend<GlesRenderer>,
     damage_tracker: OutputDamageTracker,
+    virtual_outputs: VirtualOutputs,
     ipc_outputs: Arc<Mutex<IpcOutputMap>>,
 }
 
@@ -84,6 +87,7 @@ impl Winit {
                 current_mode: Some(0),
                 vrr_supported: false,
                 vrr_enabled: false,
+                brightness: None,
                 logical: Some(logical_output(&output)),
             },
         )])));
@@ -106,7 +110,8 @@ impl Winit {
 
                     {
                         let mut ipc_outputs = winit.ipc_outputs.lock().unwrap();
-                        let output = ipc_outputs.values_mut().next().unwrap();
+                        let name = winit.output.name();
+                        let output = ipc_outputs.values_mut().find(|o| o.name == name).unwrap();
                         let mode = &mut output.modes[0];
                         mode.width = size.w.clamp(0, u16::MAX as i32) as u16;
                         mode.height = size.h.clamp(0, u16::MAX as i32) as u16;
@@ -131,6 +136,7 @@ impl Winit {
             output,
             backend,
             damage_tracker,
+            virtual_outputs: VirtualOutputs::new(),
             ipc_outputs,
         })
     }
@@ -172,9 +178,23 @@ impl Winit {
         Some(f(self.backend.renderer()))
     }
 
-    pub fn render(&mut self, niri: &mut Niri, output: &Output) -> RenderResult {
+    pub fn render(
+        &mut self,
+        niri: &mut Niri,
+        output: &Output,
+        target_presentation_time: Duration,
+    ) -> RenderResult {
         let _span = tracy_client::span!("Winit::render");
 
+        if self.virtual_outputs.contains(output) {
+            return self.virtual_outputs.render(
+                niri,
+                self.backend.renderer(),
+                output,
+                target_presentation_time,
+            );
+        }
+
         // Render the elements.
         let mut elements = niri.render::<GlesRenderer>(
             self.backend.renderer(),
@@ -235,6 +255,7 @@ impl Winit {
             RedrawState::WaitingForVBlank { .. } => unreachable!(),
             RedrawState::WaitingForEstimatedVBlank(_) => unreachable!(),
             RedrawState::WaitingForEstimatedVBlankAndQueued(_) => unreachable!(),
+            RedrawState::WaitingForThrottle(_) => unreachable!(),
         }
 
         output_state.frame_callback_sequence = output_state.frame_callback_sequence.wrapping_add(1);
@@ -263,6 +284,28 @@ impl Winit {
         }
     }
 
+    pub fn create_virtual_output(&mut self, niri: &mut Niri, width: u16, height: u16) -> String {
+        let name = self.virtual_outputs.create(niri, width, height);
+        self.refresh_virtual_ipc_outputs(niri);
+        name
+    }
+
+    pub fn remove_virtual_output(&mut self, niri: &mut Niri, name: &str) -> bool {
+        let removed = self.virtual_outputs.remove(niri, name);
+        if removed {
+            self.refresh_virtual_ipc_outputs(niri);
+        }
+        removed
+    }
+
+    fn refresh_virtual_ipc_outputs(&self, niri: &mut Niri) {
+        let mut ipc_outputs = self.ipc_outputs.lock().unwrap();
+        let name = self.output.name();
+        ipc_outputs.retain(|_, o| o.name == name);
+        self.virtual_outputs.add_ipc_outputs(&mut ipc_outputs);
+        niri.ipc_outputs_changed = true;
+    }
+
     pub fn ipc_outputs(&self) -> Arc<Mutex<IpcOutputMap>> {
         self.ipc_outputs.clone()
     }
@@ -2,7 +2,7 @@ use std::ffi::OsString;
 use std::path::PathBuf;
 
 use clap::{Parser, Subcommand};
-use niri_ipc::{Action, OutputAction};
+use niri_ipc::{Action, EventClass, OutputAction, WindowFilter, WorkspaceReferenceArg};
 
 use crate::utils::version;
 
@@ -33,6 +33,14 @@ pub struct Cli {
     pub subcommand: Option<Sub>,
 }
 
+/// Single action of `niri msg actions`.
+#[derive(Parser)]
+#[command(no_binary_name = true)]
+pub struct ActionArg {
+    #[command(subcommand)]
+    pub action: Action,
+}
+
 #[derive(Subcommand)]
 pub enum Sub {
     /// Communicate with the running niri instance.
@@ -42,6 +50,20 @@ pub enum Sub {
         /// Format output as JSON.
         #[arg(short, long)]
         json: bool,
+        /// Print every item with a template, like `{id} {app_id} {title}`.
+        ///
+        /// Fields are the ones from the JSON output. Nested fields are separated with dots, like
+        /// `{logical.scale}`. Use `{{` and `}}` for literal braces.
+        ///
+        /// Supported for `outputs`, `workspaces`, `windows`, `focused-output` and
+        /// `focused-window`.
+        #[arg(short, long, conflicts_with = "json")]
+        format: Option<String>,
+        /// Keep running and print the state again every time it changes.
+        ///
+        /// Supported for `outputs`, `workspaces`, `windows` and `layers`.
+        #[arg(short, long)]
+        watch: bool,
     },
     /// Validate the config file.
     Validate {
@@ -73,7 +95,35 @@ pub enum Msg {
     /// Print information about the focused window.
     FocusedWindow,
     /// Perform an action.
+    ///
+    /// With window filters, the action is performed on the most recently focused window matching
+    /// them. Only actions that accept a window id support filters.
     Action {
+        #[command(flatten)]
+        filter: WindowFilter,
+        #[command(subcommand)]
+        action: Action,
+    },
+    /// Perform several actions in order.
+    ///
+    /// Separate the actions with a `;` argument, escaped from the shell, for example `niri msg
+    /// actions focus-workspace 2 \; center-column`. The screen isn't redrawn in between.
+    Actions {
+        #[arg(
+            required = true,
+            trailing_var_arg = true,
+            allow_hyphen_values = true,
+            value_name = "ACTION"
+        )]
+        actions: Vec<String>,
+    },
+    /// Perform an action on all windows matching a filter.
+    ///
+    /// Only actions that accept a window id are supported. Without any filter, the action is
+    /// performed on all windows.
+    BulkAction {
+        #[command(flatten)]
+        filter: WindowFilter,
         #[command(subcommand)]
         action: Action,
     },
@@ -92,8 +142,52 @@ pub enum Msg {
         #[command(subcommand)]
         action: OutputAction,
     },
+    /// Create a virtual output.
+    ///
+    /// Virtual outputs aren't shown on any monitor, but otherwise work like regular outputs. For
+    /// example, they can be screencast to another device to use it as an extra monitor.
+    CreateVirtualOutput {
+        /// Width in physical pixels.
+        #[arg()]
+        width: u16,
+        /// Height in physical pixels.
+        #[arg()]
+        height: u16,
+        /// Scale of the output.
+        ///
+        /// If not set, the scale comes from the output config, or is picked automatically.
+        #[arg(long)]
+        scale: Option<f64>,
+        /// Workspace (index or name) to move to the new output.
+        ///
+        /// Can be given several times. The first workspace is shown on the output, and the focus
+        /// stays where it was.
+        #[arg(long = "workspace")]
+        workspaces: Vec<WorkspaceReferenceArg>,
+    },
+    /// Remove a virtual output.
+    RemoveVirtualOutput {
+        /// Name of the virtual output.
+        #[arg()]
+        output: String,
+    },
     /// Start continuously receiving events from the compositor.
-    EventStream,
+    EventStream {
+        /// Class of events to receive.
+        ///
+        /// Can be given several times, or as a comma-separated list. If not set, all events are
+        /// received.
+        #[arg(long, value_delimiter = ',')]
+        subscribe: Vec<EventClass>,
+    },
+    /// List the Wayland protocol globals advertised by niri.
+    Protocols,
+    /// Explain what has keyboard focus and how it got there.
+    ExplainFocus,
+    /// List the binds that are currently on cooldown.
+    BindCooldowns,
+    /// List the clipboard history, most recent entry first.
+    ClipboardHistory,
     /// Print the version of the running niri instance.
     Version,
     /// Request an error from the running niri instance.
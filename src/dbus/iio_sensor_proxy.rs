@@ -0,0 +1,87 @@
+//! Client for iio-sensor-proxy, which reports the device orientation from the accelerometer.
+
+use std::thread;
+
+use anyhow::Context;
+use smithay::reexports::calloop;
+use smithay::utils::Transform;
+use zbus::blocking::{Connection, Proxy};
+
+/// Starts watching the device orientation on a separate thread.
+///
+/// Orientation changes are sent as the rotation to apply to outputs.
+pub fn start(to_niri: calloop::channel::Sender<Transform>) {
+    let res = thread::Builder::new()
+        .name("Accelerometer".to_owned())
+        .spawn(move || {
+            if let Err(err) = run(to_niri) {
+                warn!("error watching the accelerometer: {err:?}");
+            }
+        });
+    if let Err(err) = res {
+        warn!("error spawning the accelerometer thread: {err:?}");
+    }
+}
+
+fn run(to_niri: calloop::channel::Sender<Transform>) -> anyhow::Result<()> {
+    let conn = Connection::system().context("error connecting to system bus")?;
+    let proxy = Proxy::new(
+        &conn,
+        "net.hadess.SensorProxy",
+        "/net/hadess/SensorProxy",
+        "net.hadess.SensorProxy",
+    )
+    .context("error creating iio-sensor-proxy proxy")?;
+
+    let has_accelerometer: bool = proxy
+        .get_property("HasAccelerometer")
+        .context("error querying iio-sensor-proxy")?;
+    if !has_accelerometer {
+        info!("no accelerometer found, auto-rotation will not work");
+        return Ok(());
+    }
+
+    // Readings only update while the accelerometer is claimed. The claim is released when our
+    // connection closes.
+    proxy
+        .call_method("ClaimAccelerometer", &())
+        .context("error claiming the accelerometer")?;
+
+    // Subscribe before reading the current value so as not to miss changes in between.
+    let changes = proxy.receive_property_changed::<String>("AccelerometerOrientation");
+
+    let orientation: String = proxy
+        .get_property("AccelerometerOrientation")
+        .context("error reading the orientation")?;
+    if let Some(rotation) = parse_orientation(&orientation) {
+        if to_niri.send(rotation).is_err() {
+            return Ok(());
+        }
+    }
+
+    for change in changes {
+        let orientation = change.get().context("error reading the orientation")?;
+        trace!("accelerometer orientation: {orientation}");
+
+        // Undefined orientation, for example when lying flat, keeps the last rotation.
+        let Some(rotation) = parse_orientation(&orientation) else {
+            continue;
+        };
+        if to_niri.send(rotation).is_err() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_orientation(orientation: &str) -> Option<Transform> {
+    // Same mapping as in mutter.
+    match orientation {
+        "normal" => Some(Transform::Normal),
+        "left-up" => Some(Transform::_90),
+        "bottom-up" => Some(Transform::_180),
+        "right-up" => Some(Transform::_270),
+        _ => None,
+    }
+}
@@ -6,6 +6,7 @@ use crate::niri::State;
 pub mod freedesktop_screensaver;
 pub mod gnome_shell_introspect;
 pub mod gnome_shell_screenshot;
+pub mod iio_sensor_proxy;
 pub mod mutter_display_config;
 pub mod mutter_service_channel;
 
@@ -4,6 +4,7 @@ use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 
 use serde::Deserialize;
+use smithay::utils::{Logical, Rectangle};
 use zbus::fdo::RequestNameFlags;
 use zbus::object_server::{InterfaceRef, SignalEmitter};
 use zbus::zvariant::{DeserializeDict, OwnedObjectPath, SerializeDict, Type, Value};
@@ -30,14 +31,23 @@ pub struct Session {
     stopped: Arc<AtomicBool>,
 }
 
-#[derive(Debug, Default, Deserialize, Type, Clone, Copy)]
+#[derive(Debug, Deserialize, Type, Clone, Copy, PartialEq, Eq)]
 pub enum CursorMode {
-    #[default]
     Hidden = 0,
     Embedded = 1,
     Metadata = 2,
 }
 
+impl From<niri_config::ScreencastCursorMode> for CursorMode {
+    fn from(value: niri_config::ScreencastCursorMode) -> Self {
+        match value {
+            niri_config::ScreencastCursorMode::Hidden => Self::Hidden,
+            niri_config::ScreencastCursorMode::Embedded => Self::Embedded,
+            niri_config::ScreencastCursorMode::Metadata => Self::Metadata,
+        }
+    }
+}
+
 #[derive(Debug, DeserializeDict, Type)]
 #[zvariant(signature = "dict")]
 struct RecordMonitorProperties {
@@ -58,12 +68,27 @@ struct RecordWindowProperties {
     _is_recording: Option<bool>,
 }
 
+#[derive(Debug, DeserializeDict, Type)]
+#[zvariant(signature = "dict")]
+struct RecordAreaProperties {
+    #[zvariant(rename = "cursor-mode")]
+    cursor_mode: Option<CursorMode>,
+    #[zvariant(rename = "is-recording")]
+    _is_recording: Option<bool>,
+}
+
 static STREAM_ID: AtomicUsize = AtomicUsize::new(0);
+static SESSION_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns a new cast session id, unique among all casts including our own screen recordings.
+pub fn next_session_id() -> usize {
+    SESSION_ID.fetch_add(1, Ordering::SeqCst)
+}
 
 #[derive(Clone)]
 pub struct Stream {
     target: StreamTarget,
-    cursor_mode: CursorMode,
+    cursor_mode: Option<CursorMode>,
     was_started: Arc<AtomicBool>,
     to_niri: calloop::channel::Sender<ScreenCastToNiri>,
 }
@@ -73,14 +98,22 @@ enum StreamTarget {
     // FIXME: update on scale changes and whatnot.
     Output(niri_ipc::Output),
     Window { id: u64 },
+    Area(CastArea),
 }
 
 #[derive(Debug, Clone)]
 pub enum StreamTargetId {
     Output { name: String },
     Window { id: u64 },
+    Area(CastArea),
 }
 
+/// Area of an area screencast in global logical coordinates.
+///
+/// Empty until the user selects the area, for casts that ask for an interactive selection. niri
+/// fills it in once the selection is confirmed.
+pub type CastArea = Arc<Mutex<Option<Rectangle<i32, Logical>>>>;
+
 #[derive(Debug, SerializeDict, Type, Value)]
 #[zvariant(signature = "dict")]
 struct StreamParameters {
@@ -94,7 +127,8 @@ pub enum ScreenCastToNiri {
     StartCast {
         session_id: usize,
         target: StreamTargetId,
-        cursor_mode: CursorMode,
+        /// Cursor mode requested by the client, if any.
+        cursor_mode: Option<CursorMode>,
         signal_ctx: SignalEmitter<'static>,
     },
     StopCast {
@@ -115,8 +149,7 @@ impl ScreenCast {
             ));
         }
 
-        static NUMBER: AtomicUsize = AtomicUsize::new(0);
-        let session_id = NUMBER.fetch_add(1, Ordering::SeqCst);
+        let session_id = next_session_id();
         let path = format!("/org/gnome/Mutter/ScreenCast/Session/u{}", session_id);
         let path = OwnedObjectPath::try_from(path).unwrap();
 
@@ -210,7 +243,7 @@ impl Session {
         );
         let path = OwnedObjectPath::try_from(path).unwrap();
 
-        let cursor_mode = properties.cursor_mode.unwrap_or_default();
+        let cursor_mode = properties.cursor_mode;
 
         let target = StreamTarget::Output(output);
         let stream = Stream::new(target, cursor_mode, self.to_niri.clone());
@@ -243,7 +276,7 @@ impl Session {
         );
         let path = OwnedObjectPath::try_from(path).unwrap();
 
-        let cursor_mode = properties.cursor_mode.unwrap_or_default();
+        let cursor_mode = properties.cursor_mode;
 
         let target = StreamTarget::Window {
             id: properties.window_id,
@@ -265,6 +298,53 @@ impl Session {
         Ok(path)
     }
 
+    /// Records an area in global logical coordinates.
+    ///
+    /// An empty area asks the user to select the area interactively with the screenshot UI. The
+    /// stream starts once the selection is confirmed, and the session is closed if it's cancelled.
+    async fn record_area(
+        &mut self,
+        #[zbus(object_server)] server: &ObjectServer,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        properties: RecordAreaProperties,
+    ) -> fdo::Result<OwnedObjectPath> {
+        debug!(x, y, width, height, ?properties, "record_area");
+
+        let rect = if width <= 0 || height <= 0 {
+            None
+        } else {
+            Some(Rectangle::new((x, y).into(), (width, height).into()))
+        };
+
+        let path = format!(
+            "/org/gnome/Mutter/ScreenCast/Stream/u{}",
+            STREAM_ID.fetch_add(1, Ordering::SeqCst)
+        );
+        let path = OwnedObjectPath::try_from(path).unwrap();
+
+        let cursor_mode = properties.cursor_mode;
+
+        let target = StreamTarget::Area(Arc::new(Mutex::new(rect)));
+        let stream = Stream::new(target, cursor_mode, self.to_niri.clone());
+        match server.at(&path, stream.clone()).await {
+            Ok(true) => {
+                let iface = server.interface(&path).await.unwrap();
+                self.streams.lock().unwrap().push((stream, iface));
+            }
+            Ok(false) => return Err(fdo::Error::Failed("stream path already exists".to_owned())),
+            Err(err) => {
+                return Err(fdo::Error::Failed(format!(
+                    "error creating stream object: {err:?}"
+                )))
+            }
+        }
+
+        Ok(path)
+    }
+
     #[zbus(signal)]
     async fn closed(ctxt: &SignalEmitter<'_>) -> zbus::Result<()>;
 }
@@ -285,6 +365,17 @@ impl Stream {
                     size: (logical.width as i32, logical.height as i32),
                 }
             }
+            StreamTarget::Area(area) => match *area.lock().unwrap() {
+                Some(rect) => StreamParameters {
+                    position: (rect.loc.x, rect.loc.y),
+                    size: (rect.size.w, rect.size.h),
+                },
+                // The stream only starts once the user selects the area.
+                None => StreamParameters {
+                    position: (0, 0),
+                    size: (1, 1),
+                },
+            },
             StreamTarget::Window { .. } => {
                 // Does any consumer need this?
                 StreamParameters {
@@ -351,7 +442,7 @@ impl Drop for Session {
 impl Stream {
     fn new(
         target: StreamTarget,
-        cursor_mode: CursorMode,
+        cursor_mode: Option<CursorMode>,
         to_niri: calloop::channel::Sender<ScreenCastToNiri>,
     ) -> Self {
         Self {
@@ -387,6 +478,7 @@ impl StreamTarget {
                 name: output.name.clone(),
             },
             StreamTarget::Window { id } => StreamTargetId::Window { id: *id },
+            StreamTarget::Area(area) => StreamTargetId::Area(area.clone()),
         }
     }
 }
@@ -6,8 +6,11 @@ use crate::utils::get_monotonic_time;
 #[derive(Debug)]
 pub struct FrameClock {
     last_presentation_time: Option<Duration>,
+    /// Time of the last VBlank when nothing was presented, only used for throttling.
+    last_estimated_vblank_time: Option<Duration>,
     refresh_interval_ns: Option<NonZeroU64>,
     vrr: bool,
+    max_render_fps: Option<u32>,
 }
 
 impl FrameClock {
@@ -21,8 +24,10 @@ impl FrameClock {
 
         Self {
             last_presentation_time: None,
+            last_estimated_vblank_time: None,
             refresh_interval_ns,
             vrr,
+            max_render_fps: None,
         }
     }
 
@@ -44,6 +49,48 @@ impl FrameClock {
         self.vrr
     }
 
+    pub fn set_max_render_fps(&mut self, fps: Option<u32>) {
+        self.max_render_fps = fps.filter(|fps| *fps > 0);
+    }
+
+    pub fn max_render_fps(&self) -> Option<u32> {
+        self.max_render_fps
+    }
+
+    /// Returns how long to wait before rendering the next frame to stay under the max render FPS.
+    pub fn throttle_delay(&self, now: Duration) -> Option<Duration> {
+        let min_interval_ns = 1_000_000_000 / u64::from(self.max_render_fps?);
+        let refresh_interval_ns = self.refresh_interval_ns?.get();
+        let last_presentation_time = self
+            .last_presentation_time
+            .max(self.last_estimated_vblank_time)?;
+
+        let render_at = if self.vrr {
+            // With VRR, the frame is presented as soon as it's rendered.
+            if min_interval_ns <= refresh_interval_ns {
+                return None;
+            }
+
+            last_presentation_time + Duration::from_nanos(min_interval_ns)
+        } else {
+            // Without VRR, we can only present on VBlanks, so skip as many of them as needed.
+            // Allow for some imprecision so that e.g. a 60 FPS cap on a 120 Hz monitor doesn't
+            // skip two VBlanks due to rounding.
+            let tolerance_ns = refresh_interval_ns / 100;
+            let frames = min_interval_ns
+                .saturating_sub(tolerance_ns)
+                .div_ceil(refresh_interval_ns);
+            if frames <= 1 {
+                return None;
+            }
+
+            // Start rendering right after the VBlank preceding the target one.
+            last_presentation_time + Duration::from_nanos((frames - 1) * refresh_interval_ns)
+        };
+
+        render_at.checked_sub(now).filter(|delay| !delay.is_zero())
+    }
+
     pub fn presented(&mut self, presentation_time: Duration) {
         if presentation_time.is_zero() {
             // Not interested in these.
@@ -53,6 +100,13 @@ impl FrameClock {
         self.last_presentation_time = Some(presentation_time);
     }
 
+    /// Records a VBlank that passed without presenting anything.
+    ///
+    /// Rendering at such a VBlank still counts towards the max render FPS.
+    pub fn estimated_vblank(&mut self, time: Duration) {
+        self.last_estimated_vblank_time = Some(time);
+    }
+
     pub fn next_presentation_time(&self) -> Duration {
         let mut now = get_monotonic_time();
 
@@ -96,3 +150,77 @@ impl FrameClock {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_clock(refresh_ns: u64, vrr: bool, max_fps: u32) -> FrameClock {
+        let mut clock = FrameClock::new(Some(Duration::from_nanos(refresh_ns)), vrr);
+        clock.set_max_render_fps(Some(max_fps));
+        clock.presented(Duration::from_secs(1));
+        clock
+    }
+
+    #[test]
+    fn throttle_skips_vblanks() {
+        let now = Duration::from_secs(1);
+
+        // 120 Hz capped to 60 FPS: render every other VBlank.
+        let clock = make_clock(8_333_333, false, 60);
+        assert_eq!(
+            clock.throttle_delay(now),
+            Some(Duration::from_nanos(8_333_333))
+        );
+
+        // 144 Hz capped to 60 FPS: render every third VBlank.
+        let clock = make_clock(6_944_444, false, 60);
+        assert_eq!(
+            clock.throttle_delay(now),
+            Some(Duration::from_nanos(13_888_888))
+        );
+        assert_eq!(clock.throttle_delay(now + Duration::from_millis(14)), None);
+
+        // Cap above the refresh rate does nothing.
+        let clock = make_clock(16_666_666, false, 144);
+        assert_eq!(clock.throttle_delay(now), None);
+    }
+
+    #[test]
+    fn throttle_vrr() {
+        let now = Duration::from_secs(1);
+
+        let clock = make_clock(6_944_444, true, 60);
+        assert_eq!(
+            clock.throttle_delay(now),
+            Some(Duration::from_nanos(16_666_666))
+        );
+
+        let clock = make_clock(6_944_444, true, 240);
+        assert_eq!(clock.throttle_delay(now), None);
+    }
+
+    #[test]
+    fn throttle_after_estimated_vblank() {
+        // 120 Hz capped to 60 FPS, with the last real presentation long ago.
+        let mut clock = make_clock(8_333_333, false, 60);
+        let now = Duration::from_secs(2);
+        assert_eq!(clock.throttle_delay(now), None);
+
+        clock.estimated_vblank(now);
+        assert_eq!(
+            clock.throttle_delay(now),
+            Some(Duration::from_nanos(8_333_333))
+        );
+    }
+
+    #[test]
+    fn no_throttle_without_limit() {
+        let mut clock = FrameClock::new(Some(Duration::from_nanos(6_944_444)), false);
+        clock.presented(Duration::from_secs(1));
+        assert_eq!(clock.throttle_delay(Duration::from_secs(1)), None);
+
+        clock.set_max_render_fps(Some(0));
+        assert_eq!(clock.throttle_delay(Duration::from_secs(1)), None);
+    }
+}
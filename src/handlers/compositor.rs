@@ -1,6 +1,6 @@
 use std::collections::hash_map::Entry;
 
-use niri_ipc::PositionChange;
+use niri_ipc::{FocusReason, PositionChange};
 use smithay::backend::renderer::utils::{on_commit_buffer_handler, with_renderer_surface_state};
 use smithay::input::pointer::{CursorImageStatus, CursorImageSurfaceData};
 use smithay::reexports::calloop::Interest;
@@ -22,8 +22,8 @@ use super::xdg_shell::add_mapped_toplevel_pre_commit_hook;
 use crate::handlers::XDG_ACTIVATION_TOKEN_TIMEOUT;
 use crate::layout::{ActivateWindow, AddWindowTarget};
 use crate::niri::{ClientState, State};
-use crate::utils::send_scale_transform;
 use crate::utils::transaction::Transaction;
+use crate::utils::{send_scale_transform, with_toplevel_role};
 use crate::window::{InitialConfigureState, Mapped, ResolvedWindowRules, Unmapped};
 
 impl CompositorHandler for State {
@@ -91,6 +91,8 @@ impl CompositorHandler for State {
                         window,
                         state,
                         activation_token_data,
+                        startup_placeholder,
+                        spawn_rules: _,
                     } = entry.remove();
 
                     window.on_commit();
@@ -131,7 +133,12 @@ impl CompositorHandler for State {
                     let is_floating = rules.compute_open_floating(toplevel);
 
                     // Figure out if we should activate the window.
-                    let activate = rules.open_focused.map(|focus| {
+                    let open_focused = if rules.never_steal_focus == Some(true) {
+                        Some(false)
+                    } else {
+                        rules.open_focused
+                    };
+                    let activate = open_focused.map(|focus| {
                         if focus {
                             ActivateWindow::Yes
                         } else {
@@ -170,6 +177,19 @@ impl CompositorHandler for State {
                         })
                         .map(|(mapped, _)| mapped.window.clone());
 
+                    // A restarted application takes over the slot of its crashed window.
+                    let app_id = with_toplevel_role(toplevel, |role| role.app_id.clone());
+                    let crashed = app_id.as_deref().and_then(|app_id| {
+                        self.niri
+                            .layout
+                            .windows()
+                            .find(|(_, mapped)| {
+                                let placeholder = mapped.crash_placeholder();
+                                placeholder.and_then(|p| p.app_id()) == Some(app_id)
+                            })
+                            .map(|(_, mapped)| mapped.window.clone())
+                    });
+
                     // The mapped pre-commit hook deals with dma-bufs on its own.
                     self.remove_default_dmabuf_pre_commit_hook(toplevel.wl_surface());
                     let hook = add_mapped_toplevel_pre_commit_hook(toplevel);
@@ -183,6 +203,8 @@ impl CompositorHandler for State {
                         AddWindowTarget::Workspace(id)
                     } else if let Some(output) = &output {
                         AddWindowTarget::Output(output)
+                    } else if let Some(crashed) = &crashed {
+                        AddWindowTarget::NextTo(crashed)
                     } else {
                         AddWindowTarget::Auto
                     };
@@ -196,12 +218,29 @@ impl CompositorHandler for State {
                         activate,
                     );
 
+                    // The window replaces its startup placeholder, if any.
+                    if let Some(id) = startup_placeholder {
+                        self.niri.layout.remove_startup_placeholder(id);
+                    } else if let Some(app_id) = &app_id {
+                        self.niri
+                            .layout
+                            .remove_startup_placeholder_for_app_id(app_id);
+                    }
+
+                    // Next to it, the placeholder of the crashed window is no longer needed.
+                    if let Some(crashed) = crashed {
+                        self.niri.layout.remove_window(&crashed, Transaction::new());
+                    }
+
                     if let Some(output) = output.cloned() {
                         self.niri.layout.start_open_animation_for_window(&window);
 
                         let new_focus = self.niri.layout.focus().map(|m| &m.window);
                         if new_focus == Some(&window) {
                             // We activated the newly opened window.
+                            self.niri
+                                .focus_history
+                                .set_reason(FocusReason::WindowOpened);
                             self.maybe_warp_cursor_to_focus();
                             self.niri.layer_shell_on_demand_focus = None;
                         }
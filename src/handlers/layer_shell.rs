@@ -1,3 +1,5 @@
+use std::cell::Cell;
+
 use smithay::backend::renderer::utils::with_renderer_surface_state;
 use smithay::delegate_layer_shell;
 use smithay::desktop::{layer_map_for_output, LayerSurface, PopupKind, WindowSurfaceType};
@@ -38,13 +40,29 @@ impl WlrLayerShellHandler for State {
             return;
         };
 
+        // Mirroring outputs only show the contents of another output.
+        if self
+            .niri
+            .output_state
+            .get(&output)
+            .is_some_and(|state| state.mirror_source.is_some())
+        {
+            debug!("new layer surface is on a mirroring output, closing");
+            surface.send_close();
+            return;
+        }
+
         let wl_surface = surface.wl_surface().clone();
         let is_new = self.niri.unmapped_layer_surfaces.insert(wl_surface);
         assert!(is_new);
 
         let mut map = layer_map_for_output(&output);
-        map.map_layer(&LayerSurface::new(surface, namespace))
-            .unwrap();
+        let layer = LayerSurface::new(surface, namespace);
+        layer
+            .user_data()
+            .insert_if_missing(|| IpcLayerState(Cell::new(IpcLayerState::of(&layer))));
+        map.map_layer(&layer).unwrap();
+        self.niri.ipc_layers_changed = true;
     }
 
     fn layer_destroyed(&mut self, surface: WlrLayerSurface) {
@@ -62,6 +80,7 @@ impl WlrLayerShellHandler for State {
             }) {
             map.unmap_layer(&layer);
             self.niri.mapped_layer_surfaces.remove(&layer);
+            self.niri.ipc_layers_changed = true;
             Some(output)
         } else {
             None
@@ -77,6 +96,15 @@ impl WlrLayerShellHandler for State {
 }
 delegate_layer_shell!(State);
 
+/// Layer surface properties shown over IPC that can change on commit.
+struct IpcLayerState(Cell<(Layer, wlr_layer::KeyboardInteractivity)>);
+
+impl IpcLayerState {
+    fn of(layer: &LayerSurface) -> (Layer, wlr_layer::KeyboardInteractivity) {
+        (layer.layer(), layer.cached_state().keyboard_interactivity)
+    }
+}
+
 impl State {
     pub fn layer_shell_handle_commit(&mut self, surface: &WlSurface) -> bool {
         let mut root_surface = surface.clone();
@@ -181,6 +209,14 @@ impl State {
 
                 layer.layer_surface().send_configure();
             }
+
+            // Layer surfaces commit often, so only refresh the IPC layers on actual changes.
+            let ipc_state = IpcLayerState::of(layer);
+            if let Some(last) = layer.user_data().get::<IpcLayerState>() {
+                if last.0.replace(ipc_state) != ipc_state {
+                    self.niri.ipc_layers_changed = true;
+                }
+            }
             drop(map);
 
             // This will call queue_redraw() inside.
@@ -5,10 +5,11 @@ mod xdg_shell;
 use std::fs::File;
 use std::io::Write;
 use std::os::fd::OwnedFd;
-use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
 
+use niri_config::DndModifier;
+use niri_ipc::FocusReason;
 use smithay::backend::allocator::dmabuf::Dmabuf;
 use smithay::backend::drm::DrmNode;
 use smithay::backend::input::{InputEvent, TabletToolDescriptor};
@@ -21,6 +22,7 @@ use smithay::output::Output;
 use smithay::reexports::rustix::fs::{fcntl_setfl, OFlags};
 use smithay::reexports::wayland_protocols::xdg::shell::server::xdg_toplevel;
 use smithay::reexports::wayland_protocols_wlr::screencopy::v1::server::zwlr_screencopy_manager_v1::ZwlrScreencopyManagerV1;
+use smithay::reexports::wayland_server::protocol::wl_data_device_manager::DndAction;
 use smithay::reexports::wayland_server::protocol::wl_data_source::WlDataSource;
 use smithay::reexports::wayland_server::protocol::wl_output::WlOutput;
 use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
@@ -44,14 +46,14 @@ use smithay::wayland::security_context::{
     SecurityContext, SecurityContextHandler, SecurityContextListenerSource,
 };
 use smithay::wayland::selection::data_device::{
-    set_data_device_focus, ClientDndGrabHandler, DataDeviceHandler, DataDeviceState,
-    ServerDndGrabHandler,
+    default_action_chooser, set_data_device_focus, ClientDndGrabHandler, DataDeviceHandler,
+    DataDeviceState, ServerDndGrabHandler,
 };
 use smithay::wayland::selection::primary_selection::{
     set_primary_focus, PrimarySelectionHandler, PrimarySelectionState,
 };
 use smithay::wayland::selection::wlr_data_control::{DataControlHandler, DataControlState};
-use smithay::wayland::selection::{SelectionHandler, SelectionTarget};
+use smithay::wayland::selection::{SelectionHandler, SelectionSource, SelectionTarget};
 use smithay::wayland::session_lock::{
     LockSurface, SessionLockHandler, SessionLockManagerState, SessionLocker,
 };
@@ -71,11 +73,13 @@ use smithay::{
 };
 
 pub use crate::handlers::xdg_shell::KdeDecorationsModeState;
+use crate::layout::ActivateWindow;
 use crate::niri::{DndIcon, NewClient, State};
 use crate::protocols::foreign_toplevel::{
     self, ForeignToplevelHandler, ForeignToplevelManagerState,
 };
 use crate::protocols::gamma_control::{GammaControlHandler, GammaControlManagerState};
+use crate::protocols::image_copy_capture::{ImageCopyCaptureHandler, ImageCopyCaptureState};
 use crate::protocols::mutter_x11_interop::MutterX11InteropHandler;
 use crate::protocols::output_management::{OutputManagementHandler, OutputManagementManagerState};
 use crate::protocols::screencopy::{Screencopy, ScreencopyHandler, ScreencopyManagerState};
@@ -84,10 +88,12 @@ use crate::protocols::virtual_pointer::{
     VirtualPointerInputBackend, VirtualPointerManagerState, VirtualPointerMotionAbsoluteEvent,
     VirtualPointerMotionEvent,
 };
+use crate::utils::clipboard_history::{self, SelectionData};
 use crate::utils::{output_size, send_scale_transform, with_toplevel_role};
 use crate::{
-    delegate_foreign_toplevel, delegate_gamma_control, delegate_mutter_x11_interop,
-    delegate_output_management, delegate_screencopy, delegate_virtual_pointer,
+    delegate_fixes, delegate_foreign_toplevel, delegate_gamma_control, delegate_image_copy_capture,
+    delegate_mutter_x11_interop, delegate_output_management, delegate_screencopy,
+    delegate_virtual_pointer,
 };
 
 pub const XDG_ACTIVATION_TOKEN_TIMEOUT: Duration = Duration::from_secs(10);
@@ -277,19 +283,46 @@ delegate_keyboard_shortcuts_inhibit!(State);
 delegate_virtual_keyboard_manager!(State);
 
 impl SelectionHandler for State {
-    type SelectionUserData = Arc<[u8]>;
+    type SelectionUserData = SelectionData;
+
+    fn new_selection(
+        &mut self,
+        ty: SelectionTarget,
+        source: Option<SelectionSource>,
+        _seat: Seat<Self>,
+    ) {
+        let serial = self.niri.clipboard_history.selection_changed(ty);
+
+        let Some(source) = source else {
+            return;
+        };
+        if !self.niri.wants_client_selection(ty) {
+            return;
+        }
+        let Some(mime_types) = clipboard_history::mime_types_to_read(source.mime_types()) else {
+            return;
+        };
+
+        // The new selection is only stored once this handler returns.
+        self.niri.event_loop.insert_idle(move |state| {
+            state.niri.read_client_selection(ty, serial, mime_types);
+        });
+    }
 
     fn send_selection(
         &mut self,
         _ty: SelectionTarget,
-        _mime_type: String,
+        mime_type: String,
         fd: OwnedFd,
         _seat: Seat<Self>,
         user_data: &Self::SelectionUserData,
     ) {
         let _span = tracy_client::span!("send_selection");
 
-        let buf = user_data.clone();
+        let Some((_, buf)) = user_data.iter().find(|(m, _)| *m == mime_type) else {
+            return;
+        };
+        let buf = buf.clone();
         thread::spawn(move || {
             // Clear O_NONBLOCK, otherwise File::write_all() will stop halfway.
             if let Err(err) = fcntl_setfl(&fd, OFlags::empty()) {
@@ -306,6 +339,48 @@ impl DataDeviceHandler for State {
     fn data_device_state(&self) -> &DataDeviceState {
         &self.niri.data_device_state
     }
+
+    fn action_choice(&mut self, available: DndAction, preferred: DndAction) -> DndAction {
+        let config = self.niri.config.borrow().drag_and_drop;
+        let mods = self.niri.seat.get_keyboard().unwrap().modifier_state();
+
+        let is_pressed = |modifier| match modifier {
+            DndModifier::Ctrl => mods.ctrl,
+            DndModifier::Shift => mods.shift,
+            DndModifier::Alt => mods.alt,
+            DndModifier::Super => mods.logo,
+        };
+
+        // A held modifier takes precedence over the configured default, which in turn takes
+        // precedence over the action preferred by the drop target.
+        let forced = if config.copy_modifier.is_some_and(is_pressed) {
+            Some(DndAction::Copy)
+        } else if config.move_modifier.is_some_and(is_pressed) {
+            Some(DndAction::Move)
+        } else {
+            None
+        };
+        let default = config.default_action.map(|action| match action {
+            niri_config::DndAction::Copy => DndAction::Copy,
+            niri_config::DndAction::Move => DndAction::Move,
+            niri_config::DndAction::Ask => DndAction::Ask,
+        });
+        let preferred = [forced, default]
+            .into_iter()
+            .flatten()
+            .find(|action| available.contains(*action))
+            .unwrap_or(preferred);
+
+        let action = default_action_chooser(available, preferred);
+
+        if self.niri.dnd_action.is_some() && self.niri.dnd_action != Some(action) {
+            self.niri.dnd_action = Some(action);
+            // FIXME: more granular
+            self.niri.queue_redraw_all();
+        }
+
+        action
+    }
 }
 
 impl ClientDndGrabHandler for State {
@@ -332,6 +407,7 @@ impl ClientDndGrabHandler for State {
             (0, 0).into()
         };
         self.niri.dnd_icon = icon.map(|surface| DndIcon { surface, offset });
+        self.niri.dnd_action = Some(DndAction::empty());
         // FIXME: more granular
         self.niri.queue_redraw_all();
     }
@@ -370,6 +446,7 @@ impl ClientDndGrabHandler for State {
         }
 
         self.niri.dnd_icon = None;
+        self.niri.dnd_action = None;
         // FIXME: more granular
         self.niri.queue_redraw_all();
     }
@@ -476,6 +553,7 @@ impl SecurityContextHandler for State {
                     client,
                     restricted: true,
                     credentials_unknown: false,
+                    is_xwayland_satellite: false,
                 });
             })
             .unwrap();
@@ -509,6 +587,7 @@ impl ForeignToplevelHandler for State {
     fn activate(&mut self, wl_surface: WlSurface) {
         if let Some((mapped, _)) = self.niri.layout.find_window_and_output(&wl_surface) {
             let window = mapped.window.clone();
+            self.niri.focus_history.set_reason(FocusReason::Activation);
             self.niri.layout.activate_window(&window);
             self.niri.layer_shell_on_demand_focus = None;
             self.niri.queue_redraw_all();
@@ -517,7 +596,8 @@ impl ForeignToplevelHandler for State {
 
     fn close(&mut self, wl_surface: WlSurface) {
         if let Some((mapped, _)) = self.niri.layout.find_window_and_output(&wl_surface) {
-            mapped.toplevel().send_close();
+            let window = mapped.window.clone();
+            self.close_window(&window);
         }
     }
 
@@ -537,9 +617,12 @@ impl ForeignToplevelHandler for State {
 
             if let Some(requested_output) = wl_output.as_ref().and_then(Output::from_resource) {
                 if &requested_output != current_output {
-                    self.niri
-                        .layout
-                        .move_to_output(Some(&window), &requested_output, None);
+                    self.niri.layout.move_to_output(
+                        Some(&window),
+                        &requested_output,
+                        None,
+                        ActivateWindow::Smart,
+                    );
                 }
             }
 
@@ -584,6 +667,17 @@ impl ScreencopyHandler for State {
 }
 delegate_screencopy!(State);
 
+impl ImageCopyCaptureHandler for State {
+    fn image_copy_capture_state(&mut self) -> &mut ImageCopyCaptureState {
+        &mut self.niri.image_copy_capture_state
+    }
+
+    fn capture_requested(&mut self, output: &Output) {
+        self.niri.queue_redraw(output);
+    }
+}
+delegate_image_copy_capture!(State);
+
 impl VirtualPointerHandler for State {
     fn virtual_pointer_manager_state(&mut self) -> &mut VirtualPointerManagerState {
         &mut self.niri.virtual_pointer_state
@@ -678,7 +772,10 @@ impl GammaControlHandler for State {
     }
 
     fn set_gamma(&mut self, output: &Output, ramp: Option<Vec<u16>>) -> Option<()> {
-        match self.backend.tty().set_gamma(output, ramp) {
+        let tty = self.backend.tty();
+        // When the client resets the gamma, go back to the configured color temperature.
+        let ramp = ramp.or_else(|| tty.color_temperature_ramp(output));
+        match tty.set_gamma(output, ramp) {
             Ok(()) => Some(()),
             Err(err) => {
                 warn!("error setting gamma for output {}: {err:?}", output.name());
@@ -716,17 +813,46 @@ impl XdgActivationHandler for State {
         token_data: XdgActivationTokenData,
         surface: WlSurface,
     ) {
+        let mut placeholder = self.niri.startup_placeholders.remove(token.as_str());
+        let spawn_rules = self.niri.spawn_rules.remove(token.as_str());
+
         if token_data.timestamp.elapsed() < XDG_ACTIVATION_TOKEN_TIMEOUT {
             if let Some((mapped, _)) = self.niri.layout.find_window_and_output(&surface) {
                 let window = mapped.window.clone();
-                self.niri.layout.activate_window(&window);
-                self.niri.layer_shell_on_demand_focus = None;
-                self.niri.queue_redraw_all();
+
+                if mapped.rules().never_steal_focus == Some(true)
+                    && !self.niri.is_activation_user_initiated(&token_data)
+                {
+                    debug!("blocked activation request for a never-steal-focus window");
+                    if let Some((mapped, _)) = self.niri.layout.find_window_and_output_mut(&surface)
+                    {
+                        mapped.set_urgent();
+                    }
+                } else {
+                    self.niri.focus_history.set_reason(FocusReason::Activation);
+                    self.niri.layout.activate_window(&window);
+                    self.niri.layer_shell_on_demand_focus = None;
+                    self.niri.queue_redraw_all();
+                }
             } else if let Some(unmapped) = self.niri.unmapped_windows.get_mut(&surface) {
                 unmapped.activation_token_data = Some(token_data);
+
+                // Keep the placeholder until the window maps.
+                unmapped.startup_placeholder = placeholder.take();
+
+                if spawn_rules.is_some() {
+                    unmapped.spawn_rules = spawn_rules;
+                    let toplevel = unmapped.toplevel().clone();
+                    self.reconfigure_unmapped(&toplevel);
+                }
             }
         }
 
+        if let Some(id) = placeholder {
+            self.niri.layout.remove_startup_placeholder(id);
+            self.niri.queue_redraw_all();
+        }
+
         self.niri.activation_state.remove_token(&token);
     }
 }
@@ -750,4 +876,6 @@ delegate_output_management!(State);
 impl MutterX11InteropHandler for State {}
 delegate_mutter_x11_interop!(State);
 
+delegate_fixes!(State);
+
 delegate_single_pixel_buffer!(State);
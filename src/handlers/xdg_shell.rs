@@ -1,7 +1,11 @@
 use std::cell::Cell;
+use std::time::Duration;
 
+use calloop::timer::{TimeoutAction, Timer};
 use calloop::Interest;
-use niri_config::PresetSize;
+use niri_config::{
+    OpenOnWorkspaceFallback, PresetSize, Workspace as WorkspaceConfig, WorkspaceName,
+};
 use smithay::desktop::{
     find_popup_root_surface, get_popup_toplevel_coords, layer_map_for_output, utils, LayerSurface,
     PopupKeyboardGrab, PopupKind, PopupManager, PopupPointerGrab, PopupUngrabStrategy, Window,
@@ -43,11 +47,22 @@ use crate::input::resize_grab::ResizeGrab;
 use crate::input::touch_move_grab::TouchMoveGrab;
 use crate::input::touch_resize_grab::TouchResizeGrab;
 use crate::input::{PointerOrTouchStartData, DOUBLE_CLICK_TIME};
+use crate::layout::startup_placeholder::StartupApp;
+use crate::layout::{ActivateWindow, LayoutElement as _};
 use crate::niri::{PopupGrabState, State};
+use crate::ui::confirm_dialog::ConfirmDialog;
+use crate::utils::desktop_entries::find_icon;
+use crate::utils::spawning::{exit_status_of_process, is_crash, spawn};
 use crate::utils::transaction::Transaction;
-use crate::utils::{get_monotonic_time, output_matches_name, send_scale_transform, ResizeEdge};
+use crate::utils::{
+    get_monotonic_time, output_matches_name, send_scale_transform, with_toplevel_role, ResizeEdge,
+};
+use crate::window::crash_placeholder::CrashPlaceholder;
 use crate::window::{InitialConfigureState, ResolvedWindowRules, Unmapped, WindowRef};
 
+/// How long a window waits for its `open-on-workspace` to appear.
+const WORKSPACE_WAIT_TIMEOUT: Duration = Duration::from_secs(10);
+
 impl XdgShellHandler for State {
     fn xdg_shell_state(&mut self) -> &mut XdgShellState {
         &mut self.niri.xdg_shell_state
@@ -435,9 +450,12 @@ impl XdgShellHandler for State {
 
             if let Some(requested_output) = requested_output {
                 if &requested_output != current_output {
-                    self.niri
-                        .layout
-                        .move_to_output(Some(&window), &requested_output, None);
+                    self.niri.layout.move_to_output(
+                        Some(&window),
+                        &requested_output,
+                        None,
+                        ActivateWindow::Smart,
+                    );
                 }
             }
 
@@ -448,7 +466,9 @@ impl XdgShellHandler for State {
             toplevel.send_configure();
         } else if let Some(unmapped) = self.niri.unmapped_windows.get_mut(toplevel.wl_surface()) {
             match &mut unmapped.state {
-                InitialConfigureState::NotConfigured { wants_fullscreen } => {
+                InitialConfigureState::NotConfigured {
+                    wants_fullscreen, ..
+                } => {
                     *wants_fullscreen = Some(requested_output);
 
                     // The required configure will be the initial configure.
@@ -522,7 +542,9 @@ impl XdgShellHandler for State {
             toplevel.send_configure();
         } else if let Some(unmapped) = self.niri.unmapped_windows.get_mut(toplevel.wl_surface()) {
             match &mut unmapped.state {
-                InitialConfigureState::NotConfigured { wants_fullscreen } => {
+                InitialConfigureState::NotConfigured {
+                    wants_fullscreen, ..
+                } => {
                     *wants_fullscreen = None;
 
                     // The required configure will be the initial configure.
@@ -642,43 +664,38 @@ impl XdgShellHandler for State {
             return;
         };
         let window = mapped.window.clone();
+        let pid = mapped.credentials().map(|credentials| credentials.pid);
         let output = output.clone();
 
+        // Nothing is left to confirm closing.
+        if self
+            .niri
+            .close_confirm_dialog
+            .as_ref()
+            .is_some_and(|(w, _)| *w == window)
+        {
+            self.niri.close_confirm_dialog = None;
+            self.niri.queue_redraw_all();
+        }
+
         #[cfg(feature = "xdp-gnome-screencast")]
         self.niri
             .stop_casts_for_target(crate::pw_utils::CastTarget::Window {
                 id: mapped.id().get(),
             });
 
-        self.backend.with_primary_renderer(|renderer| {
-            self.niri.layout.store_unmap_snapshot(renderer, &window);
-        });
-
-        let transaction = Transaction::new();
-        let blocker = transaction.blocker();
-        self.backend.with_primary_renderer(|renderer| {
-            self.niri
-                .layout
-                .start_close_animation_for_window(renderer, &window, blocker);
-        });
-
-        let active_window = self.niri.layout.focus().map(|m| &m.window);
-        let was_active = active_window == Some(&window);
-
-        self.niri.layout.remove_window(&window, transaction.clone());
-        self.add_default_dmabuf_pre_commit_hook(surface.wl_surface());
-
-        // If this is the only instance, then this transaction will complete immediately, so no
-        // need to set the timer.
-        if !transaction.is_last() {
-            transaction.register_deadline_timer(&self.niri.event_loop);
-        }
-
-        if was_active {
-            self.maybe_warp_cursor_to_focus();
+        // When a client disconnects, it is already gone by the time its objects are destroyed.
+        // Clients closing their windows normally destroy them while still connected, but a clean
+        // exit can also leave that to the disconnect, so check how the process actually exited.
+        let crashed = surface.wl_surface().client().is_none()
+            && pid.and_then(exit_status_of_process).is_some_and(is_crash);
+        if crashed && self.leave_crash_placeholder(&surface) {
+            self.niri.queue_redraw(&output);
+            return;
         }
 
-        self.niri.queue_redraw(&output);
+        self.remove_closed_window(&window, &output);
+        self.add_default_dmabuf_pre_commit_hook(surface.wl_surface());
     }
 
     fn popup_destroyed(&mut self, surface: PopupSurface) {
@@ -806,6 +823,199 @@ impl XdgForeignHandler for State {
 delegate_xdg_foreign!(State);
 
 impl State {
+    /// Removes a closed window from the layout with a close animation.
+    fn remove_closed_window(&mut self, window: &Window, output: &Output) {
+        self.backend.with_primary_renderer(|renderer| {
+            self.niri.layout.store_unmap_snapshot(renderer, window);
+        });
+
+        let transaction = Transaction::new();
+        let blocker = transaction.blocker();
+        self.backend.with_primary_renderer(|renderer| {
+            self.niri
+                .layout
+                .start_close_animation_for_window(renderer, window, blocker);
+        });
+
+        let active_window = self.niri.layout.focus().map(|m| &m.window);
+        let was_active = active_window == Some(window);
+
+        self.niri.layout.remove_window(window, transaction.clone());
+
+        // If this is the only instance, then this transaction will complete immediately, so no
+        // need to set the timer.
+        if !transaction.is_last() {
+            transaction.register_deadline_timer(&self.niri.event_loop);
+        }
+
+        if was_active {
+            self.maybe_warp_cursor_to_focus();
+        }
+
+        self.niri.queue_redraw(output);
+    }
+
+    /// Keeps the window of a crashed client in the layout as a placeholder, if enabled.
+    ///
+    /// Returns `false` if the window should close as usual.
+    fn leave_crash_placeholder(&mut self, toplevel: &ToplevelSurface) -> bool {
+        let Some(config) = self.niri.config.borrow().crash_placeholder else {
+            return false;
+        };
+
+        let (app_id, title) =
+            with_toplevel_role(toplevel, |role| (role.app_id.clone(), role.title.clone()));
+        let entry = app_id.as_deref().and_then(|app_id| {
+            let mut desktop_entries = self.niri.desktop_entries.lock().unwrap();
+            desktop_entries.find_by_app_id(app_id).cloned()
+        });
+
+        // Reverse-DNS app IDs like org.gnome.Nautilus usually end with the app name.
+        let name = app_id
+            .as_deref()
+            .and_then(|app_id| app_id.rsplit('.').next())
+            .or(title.as_deref())
+            .unwrap_or("Application")
+            .to_owned();
+        let icon = entry.as_ref().and_then(|entry| entry.icon.as_deref());
+        let app = StartupApp {
+            name,
+            app_id,
+            icon: icon.and_then(find_icon),
+        };
+        let command = entry.map(|entry| entry.command);
+
+        let clock = self.niri.clock.clone();
+        let Some((mapped, _)) = self
+            .niri
+            .layout
+            .find_window_and_output_mut(toplevel.wl_surface())
+        else {
+            return false;
+        };
+
+        let timeout = Duration::from_millis(u64::from(config.timeout_ms));
+        let size = mapped.size();
+        mapped.set_crashed(CrashPlaceholder::new(app, command, size, timeout, clock));
+        let window = mapped.window.clone();
+
+        self.niri
+            .event_loop
+            .insert_source(Timer::from_duration(timeout), move |_, _, state| {
+                let time_left = state
+                    .niri
+                    .layout
+                    .windows()
+                    .find(|(_, mapped)| mapped.window == window)
+                    .and_then(|(_, mapped)| mapped.crash_placeholder())
+                    .map(|placeholder| placeholder.time_left());
+
+                // The placeholder may be gone already, for example if the restarted application
+                // took it over.
+                match time_left {
+                    Some(time_left) if !time_left.is_zero() => TimeoutAction::ToDuration(time_left),
+                    Some(_) => {
+                        state.dismiss_crash_placeholder(&window);
+                        TimeoutAction::Drop
+                    }
+                    None => TimeoutAction::Drop,
+                }
+            })
+            .unwrap();
+
+        true
+    }
+
+    /// Closes the placeholder of a crashed window.
+    pub fn dismiss_crash_placeholder(&mut self, window: &Window) {
+        let output = self
+            .niri
+            .layout
+            .windows()
+            .find(|(_, mapped)| mapped.window == *window && mapped.is_crashed())
+            .map(|(mon, _)| mon.map(|mon| mon.output().clone()));
+        let Some(output) = output else {
+            return;
+        };
+
+        if let Some(output) = output {
+            self.remove_closed_window(window, &output);
+        } else {
+            // Placeholders on workspaces without an output have nothing to animate.
+            self.niri.layout.remove_window(window, Transaction::new());
+        }
+    }
+
+    /// Closes the window, first asking for confirmation if a window rule requires it.
+    pub fn close_window(&mut self, window: &Window) {
+        let Some((_, mapped)) = self
+            .niri
+            .layout
+            .windows()
+            .find(|(_, m)| m.window == *window)
+        else {
+            return;
+        };
+
+        if mapped.rules().confirm_close == Some(true) && !mapped.is_crashed() {
+            let name = with_toplevel_role(mapped.toplevel(), |role| {
+                role.title.clone().or_else(|| role.app_id.clone())
+            });
+            match ConfirmDialog::close_window(name.as_deref()) {
+                Ok(mut dialog) => {
+                    dialog.show();
+                    self.niri.close_confirm_dialog = Some((window.clone(), dialog));
+                    self.niri.queue_redraw_all();
+                    return;
+                }
+                Err(err) => warn!("error creating the close confirm dialog: {err:?}"),
+            }
+        }
+
+        self.close_window_confirmed(window);
+    }
+
+    /// Closes the window without asking for confirmation.
+    ///
+    /// The placeholder of a crashed window is dismissed instead.
+    pub fn close_window_confirmed(&mut self, window: &Window) {
+        let Some((_, mapped)) = self
+            .niri
+            .layout
+            .windows()
+            .find(|(_, m)| m.window == *window)
+        else {
+            return;
+        };
+
+        if mapped.is_crashed() {
+            self.dismiss_crash_placeholder(window);
+        } else {
+            mapped.toplevel().send_close();
+        }
+    }
+
+    /// Restarts the application of a crashed window through its desktop entry.
+    pub fn restart_crashed_window(&mut self, window: &Window) {
+        let mut command = None;
+        self.niri.layout.with_windows_mut(|mapped, _| {
+            if mapped.window == *window {
+                command = mapped
+                    .crash_placeholder_mut()
+                    .and_then(|placeholder| placeholder.restart());
+            }
+        });
+        let Some(command) = command else {
+            return;
+        };
+
+        let (token, _) = self.niri.activation_state.create_external_token(None);
+        spawn(command, Some(token));
+
+        // FIXME: granular
+        self.niri.queue_redraw_all();
+    }
+
     pub fn send_initial_configure(&mut self, toplevel: &ToplevelSurface) {
         let _span = tracy_client::span!("State::send_initial_configure");
 
@@ -820,14 +1030,73 @@ impl State {
             WindowRef::Unmapped(unmapped),
             self.niri.is_at_startup,
         );
+        let forced_size = rules
+            .force_initial_size
+            .filter(|_| WindowRef::Unmapped(unmapped).is_x11());
 
         let Unmapped { window, state, .. } = unmapped;
 
-        let InitialConfigureState::NotConfigured { wants_fullscreen } = state else {
+        let InitialConfigureState::NotConfigured {
+            wants_fullscreen,
+            waiting_for_workspace,
+            workspace_wait_timed_out,
+        } = state
+        else {
             error!("window must not be already configured in send_initial_configure()");
             return;
         };
 
+        // Handle the target workspace not existing.
+        let was_waiting = waiting_for_workspace.take().is_some();
+        if let Some(name) = rules.open_on_workspace.as_deref() {
+            if self.niri.layout.find_workspace_by_name(name).is_none() {
+                let fallback = if *workspace_wait_timed_out {
+                    OpenOnWorkspaceFallback::Current
+                } else {
+                    rules.open_on_workspace_fallback.unwrap_or_default()
+                };
+
+                match fallback {
+                    OpenOnWorkspaceFallback::Current => (),
+                    OpenOnWorkspaceFallback::Create => {
+                        // The workspace may be declared in the config and auto-closed since, in
+                        // which case it should come back with its configured name and settings.
+                        let ws_config = config
+                            .workspaces
+                            .iter()
+                            .find(|ws| ws.name.0.eq_ignore_ascii_case(name))
+                            .cloned()
+                            .unwrap_or_else(|| WorkspaceConfig {
+                                name: WorkspaceName(name.to_owned()),
+                                open_on_output: rules.open_on_output.clone(),
+                                return_on_connect: false,
+                                icon: None,
+                                auto_close_if_empty_after: None,
+                            });
+                        self.niri.layout.ensure_named_workspace(&ws_config);
+                    }
+                    OpenOnWorkspaceFallback::Wait => {
+                        // The client won't map the window until it receives the initial
+                        // configure, so hold it back until the workspace appears.
+                        *waiting_for_workspace = Some(name.to_owned());
+
+                        if !was_waiting {
+                            let toplevel = toplevel.clone();
+                            let timer = Timer::from_duration(WORKSPACE_WAIT_TIMEOUT);
+                            self.niri
+                                .event_loop
+                                .insert_source(timer, move |_, _, state| {
+                                    state.on_workspace_wait_timeout(&toplevel);
+                                    TimeoutAction::Drop
+                                })
+                                .unwrap();
+                        }
+                        return;
+                    }
+                }
+            }
+        }
+
         // Pick the target monitor. First, check if we had a workspace set in the window rules.
         let mon = rules
             .open_on_workspace
@@ -914,6 +1183,14 @@ impl State {
             height = ws.resolve_default_height(rules.default_height, false);
             floating_height = ws.resolve_default_height(rules.default_height, true);
 
+            // Some X11 apps map with unreasonable sizes, so replace the default size entirely.
+            if let Some(size) = forced_size {
+                width = Some(PresetSize::Fixed(i32::from(size.width)));
+                floating_width = width;
+                height = Some(PresetSize::Fixed(i32::from(size.height)));
+                floating_height = height;
+            }
+
             let configure_width = if is_floating {
                 floating_width
             } else if is_full_width {
@@ -973,6 +1250,79 @@ impl State {
         });
     }
 
+    /// Redoes the initial configure of an unmapped window after its rules changed.
+    ///
+    /// Windows that weren't configured yet will pick up the new rules on their own, except those
+    /// held back waiting for a workspace.
+    pub fn reconfigure_unmapped(&mut self, toplevel: &ToplevelSurface) {
+        let Some(unmapped) = self.niri.unmapped_windows.get_mut(toplevel.wl_surface()) else {
+            return;
+        };
+
+        match &unmapped.state {
+            InitialConfigureState::NotConfigured {
+                waiting_for_workspace: None,
+                ..
+            } => return,
+            InitialConfigureState::NotConfigured { .. } => (),
+            InitialConfigureState::Configured { output, .. } => {
+                // Start over, remembering whether the window asked to be fullscreen.
+                let wants_fullscreen = toplevel.with_pending_state(|state| {
+                    let is_fullscreen = state.states.contains(xdg_toplevel::State::Fullscreen);
+                    state.states.unset(xdg_toplevel::State::Fullscreen);
+                    is_fullscreen.then(|| output.clone())
+                });
+                unmapped.state = InitialConfigureState::NotConfigured {
+                    wants_fullscreen,
+                    waiting_for_workspace: None,
+                    workspace_wait_timed_out: false,
+                };
+            }
+        }
+
+        self.send_initial_configure(toplevel);
+    }
+
+    /// Opens a window that is still waiting for its workspace on the current one instead.
+    fn on_workspace_wait_timeout(&mut self, toplevel: &ToplevelSurface) {
+        let Some(unmapped) = self.niri.unmapped_windows.get_mut(toplevel.wl_surface()) else {
+            return;
+        };
+        let InitialConfigureState::NotConfigured {
+            waiting_for_workspace: Some(name),
+            workspace_wait_timed_out,
+            ..
+        } = &mut unmapped.state
+        else {
+            return;
+        };
+
+        debug!("workspace {name:?} did not appear in time, opening the window on the current one");
+        *workspace_wait_timed_out = true;
+        self.send_initial_configure(toplevel);
+    }
+
+    /// Sends the initial configure to windows whose target workspace has appeared.
+    pub fn refresh_windows_waiting_for_workspace(&mut self) {
+        let toplevels: Vec<_> = self
+            .niri
+            .unmapped_windows
+            .values()
+            .filter(|unmapped| match &unmapped.state {
+                InitialConfigureState::NotConfigured {
+                    waiting_for_workspace: Some(name),
+                    ..
+                } => self.niri.layout.find_workspace_by_name(name).is_some(),
+                _ => false,
+            })
+            .map(|unmapped| unmapped.toplevel().clone())
+            .collect();
+
+        for toplevel in toplevels {
+            self.send_initial_configure(&toplevel);
+        }
+    }
+
     /// Should be called on `WlSurface::commit`
     pub fn popups_handle_commit(&mut self, surface: &WlSurface) {
         self.niri.popups.commit(surface);
@@ -0,0 +1,139 @@
+//! Disable-while-typing that can exclude some keyboards.
+//!
+//! libinput pairs touchpads with keyboards on its own and has no way to exclude a keyboard from
+//! disable-while-typing. So when the config excludes some keyboards, niri turns off the libinput
+//! setting and ignores touchpad events while the other keyboards are typing.
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// How long the touchpad stays disabled after a key press.
+///
+/// Same as in libinput: short after a single key press, and longer once the user keeps typing.
+const TIMEOUT: Duration = Duration::from_millis(200);
+const TIMEOUT_TYPING: Duration = Duration::from_millis(500);
+
+/// evdev codes of modifier keys, which libinput doesn't count as typing either.
+const MODIFIER_KEYS: [u32; 9] = [
+    29,  // KEY_LEFTCTRL
+    42,  // KEY_LEFTSHIFT
+    54,  // KEY_RIGHTSHIFT
+    56,  // KEY_LEFTALT
+    97,  // KEY_RIGHTCTRL
+    100, // KEY_RIGHTALT
+    125, // KEY_LEFTMETA
+    126, // KEY_RIGHTMETA
+    464, // KEY_FN
+];
+
+#[derive(Debug, Default)]
+pub struct DwtTracker {
+    /// Touchpad events are ignored until this time.
+    typing_until: Option<Duration>,
+    /// Touchpad buttons pressed while typing, whose releases must be ignored too.
+    ignored_buttons: HashSet<u32>,
+}
+
+impl DwtTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a key press with the given evdev code from a keyboard that is not excluded.
+    pub fn on_key_press(&mut self, code: u32, now: Duration) {
+        if MODIFIER_KEYS.contains(&code) {
+            return;
+        }
+
+        let timeout = if self.is_typing(now) {
+            TIMEOUT_TYPING
+        } else {
+            TIMEOUT
+        };
+        self.typing_until = Some(now + timeout);
+    }
+
+    /// Returns whether touchpad motion and scrolling should be ignored.
+    pub fn is_typing(&self, now: Duration) -> bool {
+        self.typing_until.is_some_and(|until| now < until)
+    }
+
+    /// Returns whether a touchpad button event should be ignored.
+    ///
+    /// Presses are ignored while typing, and so are the releases of the ignored presses, so that
+    /// clients never see a button stuck or released without a press.
+    pub fn should_ignore_button(&mut self, button: u32, pressed: bool, now: Duration) -> bool {
+        if pressed {
+            let ignore = self.is_typing(now);
+            if ignore {
+                self.ignored_buttons.insert(button);
+            }
+            ignore
+        } else {
+            self.ignored_buttons.remove(&button)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY_A: u32 = 30;
+    const BTN_LEFT: u32 = 0x110;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn key_press_disables_for_timeout() {
+        let mut tracker = DwtTracker::new();
+        assert!(!tracker.is_typing(ms(1000)));
+
+        tracker.on_key_press(KEY_A, ms(1000));
+        assert!(tracker.is_typing(ms(1000)));
+        assert!(tracker.is_typing(ms(1199)));
+        assert!(!tracker.is_typing(ms(1200)));
+    }
+
+    #[test]
+    fn continued_typing_extends_timeout() {
+        let mut tracker = DwtTracker::new();
+        tracker.on_key_press(KEY_A, ms(1000));
+        tracker.on_key_press(KEY_A, ms(1100));
+        assert!(tracker.is_typing(ms(1599)));
+        assert!(!tracker.is_typing(ms(1600)));
+    }
+
+    #[test]
+    fn modifiers_are_not_typing() {
+        let mut tracker = DwtTracker::new();
+        for code in MODIFIER_KEYS {
+            tracker.on_key_press(code, ms(1000));
+        }
+        assert!(!tracker.is_typing(ms(1000)));
+    }
+
+    #[test]
+    fn release_of_ignored_press_is_ignored() {
+        let mut tracker = DwtTracker::new();
+        tracker.on_key_press(KEY_A, ms(1000));
+        assert!(tracker.should_ignore_button(BTN_LEFT, true, ms(1050)));
+
+        // Typing stopped, but the release still matches an ignored press.
+        assert!(tracker.should_ignore_button(BTN_LEFT, false, ms(2000)));
+
+        // Buttons work normally afterwards.
+        assert!(!tracker.should_ignore_button(BTN_LEFT, true, ms(2100)));
+        assert!(!tracker.should_ignore_button(BTN_LEFT, false, ms(2200)));
+    }
+
+    #[test]
+    fn release_of_press_before_typing_is_not_ignored() {
+        let mut tracker = DwtTracker::new();
+        assert!(!tracker.should_ignore_button(BTN_LEFT, true, ms(1000)));
+        tracker.on_key_press(KEY_A, ms(1050));
+        assert!(!tracker.should_ignore_button(BTN_LEFT, false, ms(1100)));
+    }
+}
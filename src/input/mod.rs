@@ -6,8 +6,11 @@ use std::time::Duration;
 
 use calloop::timer::{TimeoutAction, Timer};
 use input::event::gesture::GestureEventCoordinates as _;
-use niri_config::{Action, Bind, Binds, Key, Modifiers, SwitchBinds, Trigger};
-use niri_ipc::LayoutSwitchTarget;
+use niri_config::{
+    Action, Bind, Binds, ClickToFocus, FocusClick, Key, Modifiers, SwitchBinds, Trigger,
+    WorkspaceReference,
+};
+use niri_ipc::{FocusReason, LayoutSwitchTarget};
 use smithay::backend::input::{
     AbsolutePositionEvent, Axis, AxisSource, ButtonState, Device, DeviceCapability, Event,
     GestureBeginEvent, GestureEndEvent, GesturePinchUpdateEvent as _, GestureSwipeUpdateEvent as _,
@@ -32,20 +35,26 @@ use smithay::output::Output;
 use smithay::utils::{Logical, Point, Rectangle, Transform, SERIAL_COUNTER};
 use smithay::wayland::keyboard_shortcuts_inhibit::KeyboardShortcutsInhibitor;
 use smithay::wayland::pointer_constraints::{with_pointer_constraint, PointerConstraint};
+use smithay::wayland::shell::wlr_layer::Layer;
 use smithay::wayland::tablet_manager::{TabletDescriptor, TabletSeatTrait};
 use touch_move_grab::TouchMoveGrab;
 
 use self::move_grab::MoveGrab;
 use self::resize_grab::ResizeGrab;
 use self::spatial_movement_grab::SpatialMovementGrab;
+use self::touch_resize_grab::TouchResizeGrab;
 use crate::layout::scrolling::ScrollDirection;
-use crate::layout::LayoutElement as _;
-use crate::niri::State;
+use crate::layout::{ActivateWindow, LayoutElement as _};
+use crate::niri::{ScreenRecordTarget, State};
 use crate::ui::screenshot_ui::ScreenshotUi;
-use crate::utils::spawning::spawn;
-use crate::utils::{center, get_monotonic_time, ResizeEdge};
+use crate::utils::spawning::{spawn, spawn_with_env};
+use crate::utils::{
+    center, get_monotonic_time, map_absolute_position, ResizeEdge, ScreenshotWindowInfo,
+};
+use crate::window::SpawnRules;
 
 pub mod backend_ext;
+pub mod dwt_tracker;
 pub mod move_grab;
 pub mod resize_grab;
 pub mod scroll_tracker;
@@ -90,6 +99,10 @@ impl State {
     {
         let _span = tracy_client::span!("process_input_event");
 
+        if self.should_ignore_while_typing(&event) {
+            return;
+        }
+
         // Make sure some logic like workspace clean-up has a chance to run before doing actions.
         self.niri.advance_animations();
 
@@ -118,9 +131,24 @@ impl State {
             self.niri.reset_pointer_inactivity_timer();
         }
 
+        // Only libinput event timestamps are on the monotonic clock.
+        #[cfg(feature = "metrics")]
+        if matches!(self.backend, crate::backend::Backend::Tty(_)) {
+            if let Some(time) = input_event_time(&event) {
+                let latency = get_monotonic_time().saturating_sub(Duration::from_micros(time));
+                self.niri.metrics.record_input_latency(latency);
+            }
+        }
+
         let hide_hotkey_overlay =
             self.niri.hotkey_overlay.is_open() && should_hide_hotkey_overlay(&event);
 
+        // Keyboard input is tracked in on_keyboard(), other input means the Mod key isn't held
+        // alone anymore.
+        if !matches!(event, InputEvent::Keyboard { .. }) && should_hide_hotkey_overlay(&event) {
+            self.niri.cancel_hotkey_overlay_mod_hold_timer();
+        }
+
         let hide_exit_confirm_dialog = self
             .niri
             .exit_confirm_dialog
@@ -128,6 +156,17 @@ impl State {
             .is_some_and(|d| d.is_open())
             && should_hide_exit_confirm_dialog(&event);
 
+        let hide_close_confirm_dialog =
+            self.niri.close_confirm_dialog.is_some() && should_hide_exit_confirm_dialog(&event);
+
+        // Key presses are typed into the rename dialog, so only other input hides it.
+        let hide_workspace_rename_dialog = self.niri.workspace_rename_dialog.is_open()
+            && !matches!(event, InputEvent::Keyboard { .. })
+            && should_hide_exit_confirm_dialog(&event);
+        let hide_clipboard_history_picker = self.niri.clipboard_history_picker.is_open()
+            && !matches!(event, InputEvent::Keyboard { .. })
+            && should_hide_exit_confirm_dialog(&event);
+
         use InputEvent::*;
         match event {
             DeviceAdded { device } => self.on_device_added(device),
@@ -169,6 +208,78 @@ impl State {
                 self.niri.queue_redraw_all();
             }
         }
+
+        if hide_close_confirm_dialog && self.niri.close_confirm_dialog.take().is_some() {
+            self.niri.queue_redraw_all();
+        }
+
+        if hide_workspace_rename_dialog && self.niri.workspace_rename_dialog.hide() {
+            self.niri.queue_redraw_all();
+        }
+
+        if hide_clipboard_history_picker && self.niri.clipboard_history_picker.hide() {
+            self.niri.queue_redraw_all();
+        }
+    }
+
+    /// Tracks typing and returns whether to drop a touchpad event because of it.
+    ///
+    /// This is only needed when some keyboards are excluded from disable-while-typing, otherwise
+    /// libinput handles it.
+    fn should_ignore_while_typing<I: InputBackend + 'static>(
+        &mut self,
+        event: &InputEvent<I>,
+    ) -> bool
+    where
+        I::Device: 'static,
+    {
+        let config = self.niri.config.borrow();
+        let c = &config.input.touchpad;
+        if !c.dwt || c.dwt_exclude_keyboards.is_empty() {
+            return false;
+        }
+
+        fn is_touchpad<D: 'static>(device: &D) -> bool {
+            (device as &dyn Any)
+                .downcast_ref::<input::Device>()
+                .is_some_and(|device| device.config_tap_finger_count() > 0)
+        }
+
+        let internal_keyboards = &self.niri.internal_keyboards;
+        let is_built_in = |device: &I::Device| {
+            (device as &dyn Any)
+                .downcast_ref::<input::Device>()
+                .is_some_and(|device| internal_keyboards.contains(device))
+        };
+
+        let now = get_monotonic_time();
+        let tracker = &mut self.niri.dwt_tracker;
+        match event {
+            InputEvent::Keyboard { event } => {
+                // Like libinput, only count the built-in keyboards, minus the excluded ones.
+                let device = event.device();
+                let name = device.name();
+                let excluded = c.dwt_exclude_keyboards.iter().any(|n| *n == name);
+                if event.state() == KeyState::Pressed && is_built_in(&device) && !excluded {
+                    // xkb keycodes are evdev codes offset by 8.
+                    let code = event.key_code().raw().saturating_sub(8);
+                    tracker.on_key_press(code, now);
+                }
+                false
+            }
+            InputEvent::PointerMotion { event } => {
+                is_touchpad(&event.device()) && tracker.is_typing(now)
+            }
+            InputEvent::PointerAxis { event } => {
+                is_touchpad(&event.device()) && tracker.is_typing(now)
+            }
+            InputEvent::PointerButton { event } => {
+                let pressed = event.state() == ButtonState::Pressed;
+                is_touchpad(&event.device())
+                    && tracker.should_ignore_button(event.button_code(), pressed, now)
+            }
+            _ => false,
+        }
     }
 
     pub fn process_libinput_event(&mut self, event: &mut InputEvent<LibinputInputBackend>) {
@@ -200,6 +311,10 @@ impl State {
                     {
                         device.led_update(led_state.into());
                     }
+
+                    if is_internal_keyboard(device) {
+                        self.niri.internal_keyboards.insert(device.clone());
+                    }
                 }
 
                 if device.has_capability(input::DeviceCapability::Touch) {
@@ -211,6 +326,7 @@ impl State {
             InputEvent::DeviceRemoved { device } => {
                 self.niri.touch.remove(device);
                 self.niri.tablets.remove(device);
+                self.niri.internal_keyboards.remove(device);
                 self.niri.devices.remove(device);
             }
             _ => (),
@@ -274,11 +390,12 @@ impl State {
         let device_output = device_output.as_ref();
         let (target_geo, keep_ratio, px, transform) =
             if let Some(output) = device_output.or_else(|| self.niri.output_for_tablet()) {
+                let rotate_with_output = self.niri.config.borrow().input.tablet.rotate_with_output;
                 (
                     self.niri.global_space.output_geometry(output).unwrap(),
                     true,
                     1. / output.current_scale().fractional_scale(),
-                    output.current_transform(),
+                    self.niri.input_device_transform(output, rotate_with_output),
                 )
             } else {
                 let geo = self.global_bounding_rectangle()?;
@@ -292,10 +409,9 @@ impl State {
                 (geo, false, 1. / scale, Transform::Normal)
             };
 
-        let mut pos = {
-            let size = transform.invert().transform_size(target_geo.size);
-            transform.transform_point_in(event.position_transformed(size), &size.to_f64())
-        };
+        let mut pos = map_absolute_position(transform, target_geo.size, |size| {
+            event.position_transformed(size)
+        });
 
         if keep_ratio {
             pos.x /= target_geo.size.w as f64;
@@ -371,11 +487,27 @@ impl State {
             serial,
             time,
             |this, mods, keysym| {
-                let bindings = &this.niri.config.borrow().binds;
                 let key_code = event.key_code();
                 let modified = keysym.modified_sym();
                 let raw = keysym.raw_latin_sym_or_raw_current_sym();
 
+                // The fallback lock takes all key presses, like the rename dialog below, except
+                // for binds that work while locked, such as VT switching.
+                #[cfg(feature = "fallback-lock")]
+                if pressed
+                    && this.niri.fallback_lock.is_some()
+                    && !this.is_bind_allowed_when_locked(comp_mod, modified, raw, *mods)
+                {
+                    if let Some(fallback_lock) = &mut this.niri.fallback_lock {
+                        this.niri.suppressed_keys.insert(key_code);
+                        if let Some(password) = fallback_lock.on_key_press(modified, *mods) {
+                            this.check_fallback_lock_password(password);
+                        }
+                        this.niri.queue_redraw_all();
+                        return FilterResult::Intercept(None);
+                    }
+                }
+
                 if let Some(dialog) = &this.niri.exit_confirm_dialog {
                     if dialog.is_open() && pressed && raw == Some(Keysym::Return) {
                         info!("quitting after confirming exit dialog");
@@ -383,9 +515,51 @@ impl State {
                     }
                 }
 
+                if pressed && raw == Some(Keysym::Return) {
+                    if let Some((window, _)) = this.niri.close_confirm_dialog.take() {
+                        info!("closing window after confirming close dialog");
+                        // Don't send Enter to the window being closed.
+                        this.niri.suppressed_keys.insert(key_code);
+                        this.close_window_confirmed(&window);
+                        this.niri.queue_redraw_all();
+                        return FilterResult::Intercept(None);
+                    }
+                }
+
+                // The rename dialog takes all key presses. Releases go through the usual path,
+                // which intercepts them since the presses were suppressed.
+                if pressed && this.niri.workspace_rename_dialog.is_open() {
+                    this.niri.suppressed_keys.insert(key_code);
+                    let dialog = &mut this.niri.workspace_rename_dialog;
+                    if let Some((id, name)) = dialog.on_key_press(modified, *mods) {
+                        let reference = Some(WorkspaceReference::Id(id.get()));
+                        if name.is_empty() {
+                            this.niri.layout.unset_workspace_name(reference);
+                        } else {
+                            this.niri.layout.set_workspace_name(name, reference);
+                        }
+                    }
+                    this.niri.queue_redraw_all();
+                    return FilterResult::Intercept(None);
+                }
+
+                // Same for the clipboard history picker.
+                if pressed && this.niri.clipboard_history_picker.is_open() {
+                    this.niri.suppressed_keys.insert(key_code);
+                    if let Some(id) = this.niri.clipboard_history_picker.on_key_press(modified) {
+                        this.niri.set_clipboard_from_history(id);
+                    }
+                    this.niri.queue_redraw_all();
+                    return FilterResult::Intercept(None);
+                }
+
+                if !is_inhibiting_shortcuts {
+                    this.update_hotkey_overlay_mod_hold(comp_mod, raw, pressed, *mods);
+                }
+
                 should_intercept_key(
                     &mut this.niri.suppressed_keys,
-                    bindings,
+                    &this.niri.config.borrow().binds,
                     comp_mod,
                     key_code,
                     modified,
@@ -410,6 +584,106 @@ impl State {
         self.start_key_repeat(bind);
     }
 
+    /// Returns whether the key press triggers a bind that works while the session is locked.
+    #[cfg(feature = "fallback-lock")]
+    fn is_bind_allowed_when_locked(
+        &self,
+        comp_mod: CompositorMod,
+        modified: Keysym,
+        raw: Option<Keysym>,
+        mods: ModifiersState,
+    ) -> bool {
+        let config = self.niri.config.borrow();
+        let bind = find_bind(
+            &config.binds,
+            comp_mod,
+            modified,
+            raw,
+            mods,
+            config.input.disable_power_key_handling,
+        );
+        bind.is_some_and(|bind| bind.allow_when_locked || allowed_when_locked(&bind.action))
+    }
+
+    /// Shows the hotkey overlay after holding the Mod key alone, and hides it on release.
+    fn update_hotkey_overlay_mod_hold(
+        &mut self,
+        comp_mod: CompositorMod,
+        raw: Option<Keysym>,
+        pressed: bool,
+        mods: ModifiersState,
+    ) {
+        self.niri.cancel_hotkey_overlay_mod_hold_timer();
+
+        if !pressed {
+            if self.niri.hotkey_overlay.hide_on_mod_release() {
+                self.niri.queue_redraw_all();
+            }
+            return;
+        }
+
+        if is_mod_pressed_alone(comp_mod, raw, mods) {
+            self.niri.start_hotkey_overlay_mod_hold_timer();
+        }
+    }
+
+    /// Performs the action on every selected window, then leaves the window select mode.
+    fn do_action_on_selected_windows(&mut self, action: impl Fn(u64) -> Action) {
+        let ids: Vec<_> = self
+            .niri
+            .layout
+            .windows()
+            .filter(|(_, mapped)| mapped.is_selected())
+            .map(|(_, mapped)| mapped.id().get())
+            .collect();
+
+        self.niri.leave_window_select_mode();
+
+        for id in ids {
+            self.do_action(action(id), false);
+        }
+    }
+
+    /// Returns whether to focus a window or column moved to another workspace or monitor.
+    fn move_focus(&self, focus: Option<bool>) -> bool {
+        let config = self.niri.config.borrow();
+        focus.unwrap_or(config.layout.move_window_to_workspace_focus)
+    }
+
+    fn move_window_to_monitor(&mut self, output: &Output, focus: Option<bool>) {
+        if self.move_focus(focus) {
+            self.niri
+                .layout
+                .move_to_output(None, output, None, ActivateWindow::Yes);
+            self.niri.layout.focus_output(output);
+            if !self.maybe_warp_cursor_to_focus_centered() {
+                self.move_cursor_to_output(output);
+            }
+        } else {
+            self.niri
+                .layout
+                .move_to_output(None, output, None, ActivateWindow::No);
+            self.maybe_warp_cursor_to_focus();
+            // FIXME: granular
+            self.niri.queue_redraw_all();
+        }
+    }
+
+    fn move_column_to_monitor(&mut self, output: &Output, focus: Option<bool>) {
+        if self.move_focus(focus) {
+            self.niri.layout.move_column_to_output(output, None, true);
+            self.niri.layout.focus_output(output);
+            if !self.maybe_warp_cursor_to_focus_centered() {
+                self.move_cursor_to_output(output);
+            }
+        } else {
+            self.niri.layout.move_column_to_output(output, None, false);
+            self.maybe_warp_cursor_to_focus();
+            // FIXME: granular
+            self.niri.queue_redraw_all();
+        }
+    }
+
     fn start_key_repeat(&mut self, bind: Bind) {
         if !bind.repeat {
             return;
@@ -462,7 +736,7 @@ impl State {
 
     pub fn handle_bind(&mut self, bind: Bind) {
         let Some(cooldown) = bind.cooldown else {
-            self.do_action(bind.action, bind.allow_when_locked);
+            self.do_bind_action(bind);
             return;
         };
 
@@ -486,13 +760,22 @@ impl State {
                         TimeoutAction::Drop
                     })
                     .unwrap();
-                entry.insert(token);
+                entry.insert((token, get_monotonic_time() + cooldown));
 
-                self.do_action(bind.action, bind.allow_when_locked);
+                self.do_bind_action(bind);
             }
         }
     }
 
+    fn do_bind_action(&mut self, bind: Bind) {
+        // Mouse warps only happen synchronously inside the action, so it's enough to suppress
+        // them for its duration.
+        self.niri.suppress_warp_mouse_to_focus = !bind.warp_mouse_to_focus;
+        self.niri.focus_history.set_reason(FocusReason::Bind);
+        self.do_action(bind.action, bind.allow_when_locked);
+        self.niri.suppress_warp_mouse_to_focus = false;
+    }
+
     pub fn do_action(&mut self, action: Action, allow_when_locked: bool) {
         if self.niri.is_locked() && !(allow_when_locked || allowed_when_locked(&action)) {
             return;
@@ -532,6 +815,12 @@ impl State {
             Action::PowerOnMonitors => {
                 self.niri.activate_monitors(&mut self.backend);
             }
+            Action::SetOutputColorTemperature(kelvin, output) => {
+                self.set_output_color_temperature(output.as_deref(), kelvin);
+            }
+            Action::SetOutputBrightness(change, output) => {
+                self.set_output_brightness(output.as_deref(), change);
+            }
             Action::ToggleDebugTint => {
                 self.backend.toggle_debug_tint();
                 self.niri.queue_redraw_all();
@@ -543,8 +832,57 @@ impl State {
             Action::DebugToggleDamage => {
                 self.niri.debug_toggle_damage();
             }
-            Action::Spawn(command) => {
+            Action::DebugToggleAnimationClockFreeze => {
+                let frozen = !self.niri.clock.is_frozen();
+                self.niri.clock.set_frozen(frozen);
+                self.niri.queue_redraw_all();
+            }
+            Action::ClearBindCooldowns => {
+                self.niri.clear_bind_cooldowns();
+            }
+            Action::DebugStepAnimationClock(ms) => {
+                self.niri.clock.step(Duration::from_millis(u64::from(ms)));
+                self.niri.queue_redraw_all();
+            }
+            Action::Spawn(command, env) => {
+                let (token, _) = self.niri.activation_state.create_external_token(None);
+
+                if let Some(program) = command.first() {
+                    self.niri.add_startup_placeholder(program, &token);
+                }
+
+                spawn_with_env(command, Some(token.clone()), env);
+            }
+            Action::SpawnWithRules(
+                command,
+                open_floating,
+                open_fullscreen,
+                default_width,
+                default_height,
+                here,
+            ) => {
                 let (token, _) = self.niri.activation_state.create_external_token(None);
+
+                if let Some(program) = command.first() {
+                    self.niri.add_startup_placeholder(program, &token);
+                }
+
+                let open_on_output = if here {
+                    self.niri.layout.active_output().map(|output| output.name())
+                } else {
+                    None
+                };
+                let rules = SpawnRules {
+                    open_floating,
+                    open_fullscreen,
+                    default_width,
+                    default_height,
+                    open_on_output,
+                };
+                self.niri
+                    .spawn_rules
+                    .insert(token.as_str().to_owned(), rules);
+
                 spawn(command, Some(token.clone()));
             }
             Action::DoScreenTransition(delay_ms) => {
@@ -567,12 +905,30 @@ impl State {
                     return;
                 }
 
+                // The screenshot UI could be selecting an area for a screencast.
+                #[cfg(feature = "xdp-gnome-screencast")]
+                if self.confirm_area_cast_selection() {
+                    self.niri.close_screenshot_ui();
+                    self.niri
+                        .cursor_manager
+                        .set_cursor_image(CursorImageStatus::default_named());
+                    self.niri.queue_redraw_all();
+                    return;
+                }
+
                 self.backend.with_primary_renderer(|renderer| {
                     match self.niri.screenshot_ui.capture(renderer) {
                         Ok((size, pixels)) => {
-                            if let Err(err) = self.niri.save_screenshot(size, pixels, write_to_disk)
+                            let window = ScreenshotWindowInfo::default();
+                            if let Err(err) =
+                                self.niri
+                                    .save_screenshot(size, pixels, write_to_disk, window)
                             {
                                 warn!("error saving screenshot: {err:?}");
+                            } else if let Some(output) =
+                                self.niri.screenshot_ui.selection_output().cloned()
+                            {
+                                self.niri.screenshot_feedback.show(&output);
                             }
                         }
                         Err(err) => {
@@ -581,7 +937,7 @@ impl State {
                     }
                 });
 
-                self.niri.screenshot_ui.close();
+                self.niri.close_screenshot_ui();
                 self.niri
                     .cursor_manager
                     .set_cursor_image(CursorImageStatus::default_named());
@@ -592,7 +948,7 @@ impl State {
                     return;
                 }
 
-                self.niri.screenshot_ui.close();
+                self.niri.close_screenshot_ui();
                 self.niri
                     .cursor_manager
                     .set_cursor_image(CursorImageStatus::default_named());
@@ -608,31 +964,51 @@ impl State {
             Action::ScreenshotWindow(write_to_disk) => {
                 let focus = self.niri.layout.focus_with_output();
                 if let Some((mapped, output)) = focus {
-                    self.backend.with_primary_renderer(|renderer| {
-                        if let Err(err) =
-                            self.niri
-                                .screenshot_window(renderer, output, mapped, write_to_disk)
-                        {
-                            warn!("error taking screenshot: {err:?}");
-                        }
+                    let output = output.clone();
+                    let res = self.backend.with_primary_renderer(|renderer| {
+                        self.niri
+                            .screenshot_window(renderer, &output, mapped, write_to_disk)
                     });
+                    match res {
+                        Some(Ok(())) => self.niri.screenshot_feedback.show(&output),
+                        Some(Err(err)) => warn!("error taking screenshot: {err:?}"),
+                        None => (),
+                    }
                 }
             }
             Action::ScreenshotWindowById { id, write_to_disk } => {
-                let mut windows = self.niri.layout.windows();
-                let window = windows.find(|(_, m)| m.id().get() == id);
+                let window = self.niri.layout.windows().find(|(_, m)| m.id().get() == id);
                 if let Some((Some(monitor), mapped)) = window {
-                    let output = monitor.output();
-                    self.backend.with_primary_renderer(|renderer| {
-                        if let Err(err) =
-                            self.niri
-                                .screenshot_window(renderer, output, mapped, write_to_disk)
-                        {
-                            warn!("error taking screenshot: {err:?}");
-                        }
+                    let output = monitor.output().clone();
+                    let res = self.backend.with_primary_renderer(|renderer| {
+                        self.niri
+                            .screenshot_window(renderer, &output, mapped, write_to_disk)
                     });
+                    match res {
+                        Some(Ok(())) => self.niri.screenshot_feedback.show(&output),
+                        Some(Err(err)) => warn!("error taking screenshot: {err:?}"),
+                        None => (),
+                    }
                 }
             }
+            Action::ToggleScreenRecord => {
+                self.toggle_screen_record(ScreenRecordTarget::Output);
+            }
+            Action::ToggleScreenRecordWindow => {
+                self.toggle_screen_record(ScreenRecordTarget::Window { id: None });
+            }
+            Action::ToggleScreenRecordWindowById(id) => {
+                self.toggle_screen_record(ScreenRecordTarget::Window { id: Some(id) });
+            }
+            Action::SetDynamicCastTarget => {
+                self.set_dynamic_cast_target(None);
+            }
+            Action::SetDynamicCastTargetById(id) => {
+                self.set_dynamic_cast_target(Some(id));
+            }
+            Action::SetCastMaxFps(fps) => {
+                self.set_cast_max_fps(fps);
+            }
             Action::ToggleKeyboardShortcutsInhibit => {
                 if let Some(inhibitor) = self.niri.keyboard_focus.surface().and_then(|surface| {
                     self.niri
@@ -647,14 +1023,16 @@ impl State {
                 }
             }
             Action::CloseWindow => {
-                if let Some(mapped) = self.niri.layout.focus() {
-                    mapped.toplevel().send_close();
+                let focus = self.niri.layout.focus().map(|m| m.window.clone());
+                if let Some(window) = focus {
+                    self.close_window(&window);
                 }
             }
             Action::CloseWindowById(id) => {
                 let window = self.niri.layout.windows().find(|(_, m)| m.id().get() == id);
-                if let Some((_, mapped)) = window {
-                    mapped.toplevel().send_close();
+                let window = window.map(|(_, m)| m.window.clone());
+                if let Some(window) = window {
+                    self.close_window(&window);
                 }
             }
             Action::FullscreenWindow => {
@@ -686,6 +1064,13 @@ impl State {
                     self.focus_window(&window);
                 }
             }
+            Action::FocusPreviousInWorkspace => {
+                self.niri.layout.focus_previous_in_workspace();
+                self.maybe_warp_cursor_to_focus();
+                self.niri.layer_shell_on_demand_focus = None;
+                // FIXME: granular
+                self.niri.queue_redraw_all();
+            }
             Action::SwitchLayout(action) => {
                 let keyboard = &self.niri.seat.get_keyboard().unwrap();
                 keyboard.with_xkb_state(self, |mut state| match action {
@@ -987,22 +1372,31 @@ impl State {
                 // FIXME: granular
                 self.niri.queue_redraw_all();
             }
-            Action::MoveWindowToWorkspaceDown => {
-                self.niri.layout.move_to_workspace_down();
+            Action::MoveWindowToWorkspaceDown(focus) => {
+                let focus = self.move_focus(focus);
+                self.niri.layout.move_to_workspace_down(focus);
                 self.maybe_warp_cursor_to_focus();
                 // FIXME: granular
                 self.niri.queue_redraw_all();
             }
-            Action::MoveWindowToWorkspaceUp => {
-                self.niri.layout.move_to_workspace_up();
+            Action::MoveWindowToWorkspaceUp(focus) => {
+                let focus = self.move_focus(focus);
+                self.niri.layout.move_to_workspace_up(focus);
                 self.maybe_warp_cursor_to_focus();
                 // FIXME: granular
                 self.niri.queue_redraw_all();
             }
-            Action::MoveWindowToWorkspace(reference) => {
+            Action::MoveWindowToWorkspace(reference, focus) => {
                 if let Some((mut output, index)) =
                     self.niri.find_output_and_workspace_index(reference)
                 {
+                    let focus = self.move_focus(focus);
+                    let activate = if focus {
+                        ActivateWindow::Yes
+                    } else {
+                        ActivateWindow::No
+                    };
+
                     // The source output is always the active output, so if the target output is
                     // also the active output, we don't need to use move_to_output().
                     if let Some(active) = self.niri.layout.active_output() {
@@ -1012,13 +1406,15 @@ impl State {
                     }
 
                     if let Some(output) = output {
-                        self.niri.layout.move_to_output(None, &output, Some(index));
+                        self.niri
+                            .layout
+                            .move_to_output(None, &output, Some(index), activate);
 
-                        if !self.maybe_warp_cursor_to_focus_centered() {
+                        if focus && !self.maybe_warp_cursor_to_focus_centered() {
                             self.move_cursor_to_output(&output);
                         }
                     } else {
-                        self.niri.layout.move_to_workspace(None, index);
+                        self.niri.layout.move_to_workspace(None, index, activate);
                         self.maybe_warp_cursor_to_focus();
                     }
 
@@ -1029,7 +1425,15 @@ impl State {
             Action::MoveWindowToWorkspaceById {
                 window_id: id,
                 reference,
+                focus,
             } => {
+                // Moving a window by id only focuses it if it was focused before.
+                let activate = if self.move_focus(focus) {
+                    ActivateWindow::Smart
+                } else {
+                    ActivateWindow::No
+                };
+
                 let window = self.niri.layout.windows().find(|(_, m)| m.id().get() == id);
                 let window = window.map(|(_, m)| m.window.clone());
                 if let Some(window) = window {
@@ -1043,9 +1447,12 @@ impl State {
                             .is_some_and(|active| output.as_ref() == Some(active));
 
                         if let Some(output) = output {
-                            self.niri
-                                .layout
-                                .move_to_output(Some(&window), &output, Some(index));
+                            self.niri.layout.move_to_output(
+                                Some(&window),
+                                &output,
+                                Some(index),
+                                activate,
+                            );
 
                             // If the active output changed (window was moved and focused).
                             #[allow(clippy::collapsible_if)]
@@ -1057,7 +1464,9 @@ impl State {
                                 }
                             }
                         } else {
-                            self.niri.layout.move_to_workspace(Some(&window), index);
+                            self.niri
+                                .layout
+                                .move_to_workspace(Some(&window), index, activate);
 
                             // If we focused the target window.
                             let new_focus = self.niri.layout.focus();
@@ -1071,22 +1480,72 @@ impl State {
                     }
                 }
             }
-            Action::MoveColumnToWorkspaceDown => {
-                self.niri.layout.move_column_to_workspace_down();
+            Action::MoveWindowToWorkspaceOnOutput {
+                window_id,
+                output,
+                index,
+                focus,
+            } => {
+                let window = match window_id {
+                    Some(id) => {
+                        let window = self.niri.layout.windows().find(|(_, m)| m.id().get() == id);
+                        let Some((_, mapped)) = window else {
+                            return;
+                        };
+                        Some(mapped.window.clone())
+                    }
+                    None => None,
+                };
+                let Some(output) = self.niri.output_by_name_match(&output).cloned() else {
+                    return;
+                };
+
+                // Moving a window by id only focuses it if it was focused before.
+                let activate = match (self.move_focus(focus), window_id) {
+                    (false, _) => ActivateWindow::No,
+                    (true, Some(_)) => ActivateWindow::Smart,
+                    (true, None) => ActivateWindow::Yes,
+                };
+
+                let target_was_active = self.niri.layout.active_output() == Some(&output);
+                self.niri.layout.move_to_workspace_on_output(
+                    window.as_ref(),
+                    &output,
+                    index.saturating_sub(1),
+                    activate,
+                );
+
+                if !target_was_active && self.niri.layout.active_output() == Some(&output) {
+                    if !self.maybe_warp_cursor_to_focus_centered() {
+                        self.move_cursor_to_output(&output);
+                    }
+                } else if activate != ActivateWindow::No {
+                    self.maybe_warp_cursor_to_focus();
+                }
+
+                // FIXME: granular
+                self.niri.queue_redraw_all();
+            }
+            Action::MoveColumnToWorkspaceDown(focus) => {
+                let focus = self.move_focus(focus);
+                self.niri.layout.move_column_to_workspace_down(focus);
                 self.maybe_warp_cursor_to_focus();
                 // FIXME: granular
                 self.niri.queue_redraw_all();
             }
-            Action::MoveColumnToWorkspaceUp => {
-                self.niri.layout.move_column_to_workspace_up();
+            Action::MoveColumnToWorkspaceUp(focus) => {
+                let focus = self.move_focus(focus);
+                self.niri.layout.move_column_to_workspace_up(focus);
                 self.maybe_warp_cursor_to_focus();
                 // FIXME: granular
                 self.niri.queue_redraw_all();
             }
-            Action::MoveColumnToWorkspace(reference) => {
+            Action::MoveColumnToWorkspace(reference, focus) => {
                 if let Some((mut output, index)) =
                     self.niri.find_output_and_workspace_index(reference)
                 {
+                    let focus = self.move_focus(focus);
+
                     if let Some(active) = self.niri.layout.active_output() {
                         if output.as_ref() == Some(active) {
                             output = None;
@@ -1096,12 +1555,12 @@ impl State {
                     if let Some(output) = output {
                         self.niri
                             .layout
-                            .move_column_to_workspace_on_output(&output, index);
-                        if !self.maybe_warp_cursor_to_focus_centered() {
+                            .move_column_to_output(&output, Some(index), focus);
+                        if focus && !self.maybe_warp_cursor_to_focus_centered() {
                             self.move_cursor_to_output(&output);
                         }
                     } else {
-                        self.niri.layout.move_column_to_workspace(index);
+                        self.niri.layout.move_column_to_workspace(index, focus);
                         self.maybe_warp_cursor_to_focus();
                     }
 
@@ -1183,6 +1642,47 @@ impl State {
                     self.niri.queue_redraw_all();
                 }
             }
+            Action::SwapWorkspaces(ref_a, ref_b) => {
+                let layout = &mut self.niri.layout;
+                let id_a = layout.find_workspace_by_ref(ref_a).map(|ws| ws.id());
+                let id_b = layout.find_workspace_by_ref(ref_b).map(|ws| ws.id());
+                if let (Some(id_a), Some(id_b)) = (id_a, id_b) {
+                    let old_output = self.niri.layout.active_output().cloned();
+                    self.niri.layout.swap_workspaces(id_a, id_b);
+
+                    // The focus follows the focused workspace to its new monitor.
+                    let new_output = self.niri.layout.active_output().cloned();
+                    if let Some(new_output) = new_output.filter(|o| Some(o) != old_output.as_ref())
+                    {
+                        if !self.maybe_warp_cursor_to_focus_centered() {
+                            self.move_cursor_to_output(&new_output);
+                        }
+                    } else {
+                        self.maybe_warp_cursor_to_focus();
+                    }
+
+                    // FIXME: granular
+                    self.niri.queue_redraw_all();
+                }
+            }
+            Action::SortWorkspacesByName => {
+                self.niri.layout.sort_workspaces_by_name();
+                // FIXME: granular
+                self.niri.queue_redraw_all();
+            }
+            Action::CompactWorkspaces => {
+                self.niri.layout.compact_workspaces();
+                // FIXME: granular
+                self.niri.queue_redraw_all();
+            }
+            Action::MoveWorkspacesToAssignedOutputs => {
+                let ws_configs = self.niri.config.borrow().workspaces.clone();
+                self.niri
+                    .layout
+                    .move_workspaces_to_assigned_outputs(&ws_configs);
+                // FIXME: granular
+                self.niri.queue_redraw_all();
+            }
             Action::SetWorkspaceName(name) => {
                 self.niri.layout.set_workspace_name(name, None);
             }
@@ -1195,6 +1695,41 @@ impl State {
             Action::UnsetWorkSpaceNameByRef(reference) => {
                 self.niri.layout.unset_workspace_name(Some(reference));
             }
+            Action::RenameWorkspace => {
+                let output = self.niri.layout.active_output().cloned();
+                if let (Some(output), Some(ws)) = (output, self.niri.layout.active_workspace()) {
+                    let name = ws.name().map(String::as_str);
+                    if self
+                        .niri
+                        .workspace_rename_dialog
+                        .show(ws.id(), output, name)
+                    {
+                        self.niri.queue_redraw_all();
+                    }
+                }
+            }
+            Action::ShowClipboardHistory => {
+                if let Some(output) = self.niri.layout.active_output().cloned() {
+                    let entries = self.niri.clipboard_history.entries();
+                    if self.niri.clipboard_history_picker.show(output, entries) {
+                        self.niri.queue_redraw_all();
+                    }
+                }
+            }
+            Action::SetClipboardFromHistory(id) => {
+                self.niri.set_clipboard_from_history(id);
+            }
+            Action::ToggleStatusBar => {
+                self.niri.status_bar.toggle();
+                let height = self.niri.status_bar.reserved_height();
+                self.niri.layout.set_status_bar_height(height);
+                // FIXME: granular
+                self.niri.queue_redraw_all();
+            }
+            Action::ToggleWatermark => {
+                self.niri.watermark.toggle();
+                self.niri.queue_redraw_all();
+            }
             Action::ConsumeWindowIntoColumn => {
                 self.niri.layout.consume_into_column();
                 // This does not cause immediate focus or window size change, so warping mouse to
@@ -1323,112 +1858,64 @@ impl State {
                     self.niri.layer_shell_on_demand_focus = None;
                 }
             }
-            Action::MoveWindowToMonitorLeft => {
+            Action::MoveWindowToMonitorLeft(focus) => {
                 if let Some(output) = self.niri.output_left() {
-                    self.niri.layout.move_to_output(None, &output, None);
-                    self.niri.layout.focus_output(&output);
-                    if !self.maybe_warp_cursor_to_focus_centered() {
-                        self.move_cursor_to_output(&output);
-                    }
+                    self.move_window_to_monitor(&output, focus);
                 }
             }
-            Action::MoveWindowToMonitorRight => {
+            Action::MoveWindowToMonitorRight(focus) => {
                 if let Some(output) = self.niri.output_right() {
-                    self.niri.layout.move_to_output(None, &output, None);
-                    self.niri.layout.focus_output(&output);
-                    if !self.maybe_warp_cursor_to_focus_centered() {
-                        self.move_cursor_to_output(&output);
-                    }
+                    self.move_window_to_monitor(&output, focus);
                 }
             }
-            Action::MoveWindowToMonitorDown => {
+            Action::MoveWindowToMonitorDown(focus) => {
                 if let Some(output) = self.niri.output_down() {
-                    self.niri.layout.move_to_output(None, &output, None);
-                    self.niri.layout.focus_output(&output);
-                    if !self.maybe_warp_cursor_to_focus_centered() {
-                        self.move_cursor_to_output(&output);
-                    }
+                    self.move_window_to_monitor(&output, focus);
                 }
             }
-            Action::MoveWindowToMonitorUp => {
+            Action::MoveWindowToMonitorUp(focus) => {
                 if let Some(output) = self.niri.output_up() {
-                    self.niri.layout.move_to_output(None, &output, None);
-                    self.niri.layout.focus_output(&output);
-                    if !self.maybe_warp_cursor_to_focus_centered() {
-                        self.move_cursor_to_output(&output);
-                    }
+                    self.move_window_to_monitor(&output, focus);
                 }
             }
-            Action::MoveWindowToMonitorPrevious => {
+            Action::MoveWindowToMonitorPrevious(focus) => {
                 if let Some(output) = self.niri.output_previous() {
-                    self.niri.layout.move_to_output(None, &output, None);
-                    self.niri.layout.focus_output(&output);
-                    if !self.maybe_warp_cursor_to_focus_centered() {
-                        self.move_cursor_to_output(&output);
-                    }
+                    self.move_window_to_monitor(&output, focus);
                 }
             }
-            Action::MoveWindowToMonitorNext => {
+            Action::MoveWindowToMonitorNext(focus) => {
                 if let Some(output) = self.niri.output_next() {
-                    self.niri.layout.move_to_output(None, &output, None);
-                    self.niri.layout.focus_output(&output);
-                    if !self.maybe_warp_cursor_to_focus_centered() {
-                        self.move_cursor_to_output(&output);
-                    }
+                    self.move_window_to_monitor(&output, focus);
                 }
             }
-            Action::MoveColumnToMonitorLeft => {
+            Action::MoveColumnToMonitorLeft(focus) => {
                 if let Some(output) = self.niri.output_left() {
-                    self.niri.layout.move_column_to_output(&output);
-                    self.niri.layout.focus_output(&output);
-                    if !self.maybe_warp_cursor_to_focus_centered() {
-                        self.move_cursor_to_output(&output);
-                    }
+                    self.move_column_to_monitor(&output, focus);
                 }
             }
-            Action::MoveColumnToMonitorRight => {
+            Action::MoveColumnToMonitorRight(focus) => {
                 if let Some(output) = self.niri.output_right() {
-                    self.niri.layout.move_column_to_output(&output);
-                    self.niri.layout.focus_output(&output);
-                    if !self.maybe_warp_cursor_to_focus_centered() {
-                        self.move_cursor_to_output(&output);
-                    }
+                    self.move_column_to_monitor(&output, focus);
                 }
             }
-            Action::MoveColumnToMonitorDown => {
+            Action::MoveColumnToMonitorDown(focus) => {
                 if let Some(output) = self.niri.output_down() {
-                    self.niri.layout.move_column_to_output(&output);
-                    self.niri.layout.focus_output(&output);
-                    if !self.maybe_warp_cursor_to_focus_centered() {
-                        self.move_cursor_to_output(&output);
-                    }
+                    self.move_column_to_monitor(&output, focus);
                 }
             }
-            Action::MoveColumnToMonitorUp => {
+            Action::MoveColumnToMonitorUp(focus) => {
                 if let Some(output) = self.niri.output_up() {
-                    self.niri.layout.move_column_to_output(&output);
-                    self.niri.layout.focus_output(&output);
-                    if !self.maybe_warp_cursor_to_focus_centered() {
-                        self.move_cursor_to_output(&output);
-                    }
+                    self.move_column_to_monitor(&output, focus);
                 }
             }
-            Action::MoveColumnToMonitorPrevious => {
+            Action::MoveColumnToMonitorPrevious(focus) => {
                 if let Some(output) = self.niri.output_previous() {
-                    self.niri.layout.move_column_to_output(&output);
-                    self.niri.layout.focus_output(&output);
-                    if !self.maybe_warp_cursor_to_focus_centered() {
-                        self.move_cursor_to_output(&output);
-                    }
+                    self.move_column_to_monitor(&output, focus);
                 }
             }
-            Action::MoveColumnToMonitorNext => {
+            Action::MoveColumnToMonitorNext(focus) => {
                 if let Some(output) = self.niri.output_next() {
-                    self.niri.layout.move_column_to_output(&output);
-                    self.niri.layout.focus_output(&output);
-                    if !self.maybe_warp_cursor_to_focus_centered() {
-                        self.move_cursor_to_output(&output);
-                    }
+                    self.move_column_to_monitor(&output, focus);
                 }
             }
             Action::SetColumnWidth(change) => {
@@ -1590,6 +2077,42 @@ impl State {
                     self.niri.queue_redraw_all();
                 }
             }
+            Action::ToggleWindowSelectMode => {
+                if self.niri.window_select_mode {
+                    self.niri.leave_window_select_mode();
+                } else {
+                    self.niri.window_select_mode = true;
+                }
+            }
+            Action::ToggleWindowSelected => {
+                if let Some(mapped) = self.niri.layout.focus() {
+                    let window = mapped.window.clone();
+                    self.niri.toggle_window_selected(&window);
+                }
+            }
+            Action::ToggleWindowSelectedById(id) => {
+                let window = self.niri.layout.windows().find(|(_, m)| m.id().get() == id);
+                let window = window.map(|(_, m)| m.window.clone());
+                if let Some(window) = window {
+                    self.niri.toggle_window_selected(&window);
+                }
+            }
+            Action::CloseSelectedWindows => {
+                self.do_action_on_selected_windows(Action::CloseWindowById);
+            }
+            Action::MoveSelectedWindowsToWorkspace(reference) => {
+                self.do_action_on_selected_windows(|window_id| Action::MoveWindowToWorkspaceById {
+                    window_id,
+                    reference: reference.clone(),
+                    focus: None,
+                });
+            }
+            Action::MoveSelectedWindowsToFloating => {
+                self.do_action_on_selected_windows(Action::MoveWindowToFloatingById);
+            }
+            Action::MoveSelectedWindowsToTiling => {
+                self.do_action_on_selected_windows(Action::MoveWindowToTilingById);
+            }
             Action::FocusFloating => {
                 self.niri.layout.focus_floating();
                 self.maybe_warp_cursor_to_focus();
@@ -1626,6 +2149,52 @@ impl State {
                 // FIXME: granular
                 self.niri.queue_redraw_all();
             }
+            Action::SnapFloatingWindow(position) => {
+                self.niri.layout.snap_floating_window(None, position);
+                // FIXME: granular
+                self.niri.queue_redraw_all();
+            }
+            Action::SnapFloatingWindowById { id, position } => {
+                let window = self.niri.layout.windows().find(|(_, m)| m.id().get() == id);
+                let window = window.map(|(_, m)| m.window.clone());
+                if let Some(window) = window {
+                    self.niri
+                        .layout
+                        .snap_floating_window(Some(&window), position);
+                    // FIXME: granular
+                    self.niri.queue_redraw_all();
+                }
+            }
+            Action::CyclePictureInPictureCorner => {
+                self.niri.layout.cycle_picture_in_picture_corner(None);
+                // FIXME: granular
+                self.niri.queue_redraw_all();
+            }
+            Action::CyclePictureInPictureCornerById(id) => {
+                let window = self.niri.layout.windows().find(|(_, m)| m.id().get() == id);
+                let window = window.map(|(_, m)| m.window.clone());
+                if let Some(window) = window {
+                    self.niri
+                        .layout
+                        .cycle_picture_in_picture_corner(Some(&window));
+                    // FIXME: granular
+                    self.niri.queue_redraw_all();
+                }
+            }
+            Action::BringWindowOnScreen => {
+                self.niri.layout.bring_window_on_screen(None);
+                // FIXME: granular
+                self.niri.queue_redraw_all();
+            }
+            Action::BringWindowOnScreenById(id) => {
+                let window = self.niri.layout.windows().find(|(_, m)| m.id().get() == id);
+                let window = window.map(|(_, m)| m.window.clone());
+                if let Some(window) = window {
+                    self.niri.layout.bring_window_on_screen(Some(&window));
+                    // FIXME: granular
+                    self.niri.queue_redraw_all();
+                }
+            }
             Action::ToggleWindowRuleOpacity => {
                 let active_window = self
                     .niri
@@ -1654,6 +2223,22 @@ impl State {
                     }
                 }
             }
+            Action::SetWindowMark(mark) => {
+                if let Some(id) = self.niri.layout.focus().map(|m| m.id().get()) {
+                    self.niri.set_window_mark(id, mark);
+                }
+            }
+            Action::SetWindowMarkById { id, mark } => {
+                self.niri.set_window_mark(id, mark);
+            }
+            Action::UnsetWindowMark(mark) => {
+                if let Some(id) = self.niri.layout.focus().map(|m| m.id().get()) {
+                    self.niri.unset_window_mark(id, mark.as_deref());
+                }
+            }
+            Action::UnsetWindowMarkById { id, mark } => {
+                self.niri.unset_window_mark(id, mark.as_deref());
+            }
         }
     }
 
@@ -1846,11 +2431,14 @@ impl State {
         &mut self,
         event: I::PointerMotionAbsoluteEvent,
     ) {
-        let Some(pos) = self.compute_absolute_location(&event, None).or_else(|| {
-            self.global_bounding_rectangle().map(|output_geo| {
-                event.position_transformed(output_geo.size) + output_geo.loc.to_f64()
+        let Some(pos) = self
+            .compute_absolute_location(&event, None, true)
+            .or_else(|| {
+                self.global_bounding_rectangle().map(|output_geo| {
+                    event.position_transformed(output_geo.size) + output_geo.loc.to_f64()
+                })
             })
-        }) else {
+        else {
             return;
         };
 
@@ -1921,6 +2509,8 @@ impl State {
         }
 
         if ButtonState::Pressed == button_state {
+            self.niri.focus_history.set_reason(FocusReason::Click);
+
             let mods = self.niri.seat.get_keyboard().unwrap().modifier_state();
             let modifiers = modifiers_from_state(mods);
 
@@ -1950,8 +2540,88 @@ impl State {
             self.niri.pointer_hidden = false;
             self.niri.tablet_cursor_location = None;
 
+            // Clicking a workspace on the status bar focuses it.
+            if button == Some(MouseButton::Left)
+                && !pointer.is_grabbed()
+                && !self.niri.is_locked()
+                && !self.niri.screenshot_ui.is_open()
+            {
+                let location = pointer.current_location();
+                if let Some((output, pos_within_output)) = self.niri.output_under(location) {
+                    let is_covered = self
+                        .niri
+                        .layout
+                        .monitor_for_output(output)
+                        .is_some_and(|mon| mon.render_above_top_layer());
+                    if !is_covered && self.niri.status_bar.is_under(output, pos_within_output) {
+                        let output = output.clone();
+                        if let Some(id) = self
+                            .niri
+                            .status_bar
+                            .workspace_under(&output, pos_within_output)
+                        {
+                            let reference = WorkspaceReference::Id(id.get());
+                            if let Some((_, index)) =
+                                self.niri.find_output_and_workspace_index(reference)
+                            {
+                                self.niri.layout.focus_output(&output);
+                                self.niri.layout.switch_workspace(index);
+                                self.niri.layer_shell_on_demand_focus = None;
+                                // FIXME: granular
+                                self.niri.queue_redraw_all();
+                            }
+                        }
+                        self.niri.suppressed_buttons.insert(button_code);
+                        return;
+                    }
+                }
+            }
+
             if let Some(mapped) = self.niri.window_under_cursor() {
                 let window = mapped.window.clone();
+                let is_crashed = mapped.is_crashed();
+
+                // Figure out what kind of window this click focuses before activating it.
+                let focus_click = focus_click(
+                    &self.niri.config.borrow().input.click_to_focus,
+                    self.niri.layout.focus().is_some_and(|m| m.window == window),
+                    self.niri.output_under_cursor().as_ref() != self.niri.layout.active_output(),
+                    mapped.is_floating(),
+                );
+
+                // Check if we clicked the close button on the server-side titlebar.
+                if button == Some(MouseButton::Left) && !pointer.is_grabbed() {
+                    let location = pointer.current_location();
+                    let (output, pos_within_output) = self.niri.output_under(location).unwrap();
+                    if let Some(mapped) = self
+                        .niri
+                        .layout
+                        .titlebar_close_button_under(output, pos_within_output)
+                    {
+                        let window = mapped.window.clone();
+                        self.close_window(&window);
+                        self.niri.suppressed_buttons.insert(button_code);
+                        return;
+                    }
+                }
+
+                // In the window select mode, clicking windows selects them instead.
+                if button == Some(MouseButton::Left)
+                    && self.niri.window_select_mode
+                    && !pointer.is_grabbed()
+                {
+                    self.niri.toggle_window_selected(&window);
+                    self.niri.suppressed_buttons.insert(button_code);
+                    return;
+                }
+
+                // Clicking the placeholder of a crashed window restarts the application.
+                if button == Some(MouseButton::Left) && is_crashed && !pointer.is_grabbed() {
+                    self.niri.layout.activate_window(&window);
+                    self.restart_crashed_window(&window);
+                    self.niri.suppressed_buttons.insert(button_code);
+                    return;
+                }
 
                 // Check if we need to start an interactive move.
                 if button == Some(MouseButton::Left) && !pointer.is_grabbed() {
@@ -1993,11 +2663,21 @@ impl State {
                     if mod_down {
                         let location = pointer.current_location();
                         let (output, pos_within_output) = self.niri.output_under(location).unwrap();
-                        let edges = self
-                            .niri
-                            .layout
-                            .resize_edges_under(output, pos_within_output)
-                            .unwrap();
+
+                        // Floating windows can optionally resize from the closest corner, no
+                        // matter where they were grabbed.
+                        let nearest_corner = mapped.is_floating()
+                            && self.niri.config.borrow().input.mod_resize_nearest_corner;
+                        let edges = if nearest_corner {
+                            self.niri
+                                .layout
+                                .resize_corner_under(output, pos_within_output)
+                        } else {
+                            self.niri
+                                .layout
+                                .resize_edges_under(output, pos_within_output)
+                        };
+                        let edges = edges.unwrap();
 
                         if !edges.is_empty() {
                             // See if we got a double resize-click gesture.
@@ -2062,6 +2742,15 @@ impl State {
 
                 // FIXME: granular.
                 self.niri.queue_redraw_all();
+
+                // Swallow the click if it only focuses the window, unless it started a
+                // move or a resize.
+                if focus_click == FocusClick::Consume && !pointer.is_grabbed() {
+                    self.niri.suppressed_buttons.insert(button_code);
+                    // Focus changed, so the pointer contents may have too.
+                    self.update_pointer_contents();
+                    return;
+                }
             } else if let Some(output) = self.niri.output_under_cursor() {
                 self.niri.layout.activate_output(&output);
 
@@ -2156,14 +2845,26 @@ impl State {
             // Wayland. If there's no bind, reset the accumulator.
             let mods = self.niri.seat.get_keyboard().unwrap().modifier_state();
             let modifiers = modifiers_from_state(mods);
-            if self.niri.mods_with_wheel_binds.contains(&modifiers) {
+            let has_bind = self.niri.mods_with_wheel_binds.contains(&modifiers);
+            // Background binds apply only if there's no regular bind.
+            let on_background = !has_bind
+                && self
+                    .niri
+                    .mods_with_background_wheel_binds
+                    .contains(&modifiers)
+                && self.niri.is_pointer_over_workspace_background();
+            if has_bind || on_background {
                 let comp_mod = self.backend.mod_key();
 
                 let horizontal = horizontal_amount_v120.unwrap_or(0.);
                 let ticks = self.niri.horizontal_wheel_tracker.accumulate(horizontal);
                 if ticks != 0 {
                     let config = self.niri.config.borrow();
-                    let bindings = &config.binds;
+                    let bindings = if on_background {
+                        &config.background_binds.0
+                    } else {
+                        &config.binds
+                    };
                     let bind_left =
                         find_configured_bind(bindings, comp_mod, Trigger::WheelScrollLeft, mods);
                     let bind_right =
@@ -2186,7 +2887,11 @@ impl State {
                 let ticks = self.niri.vertical_wheel_tracker.accumulate(vertical);
                 if ticks != 0 {
                     let config = self.niri.config.borrow();
-                    let bindings = &config.binds;
+                    let bindings = if on_background {
+                        &config.background_binds.0
+                    } else {
+                        &config.binds
+                    };
                     let bind_up =
                         find_configured_bind(bindings, comp_mod, Trigger::WheelScrollUp, mods);
                     let bind_down =
@@ -2219,7 +2924,14 @@ impl State {
         if source == AxisSource::Finger {
             let mods = self.niri.seat.get_keyboard().unwrap().modifier_state();
             let modifiers = modifiers_from_state(mods);
-            if self.niri.mods_with_finger_scroll_binds.contains(&modifiers) {
+            let has_bind = self.niri.mods_with_finger_scroll_binds.contains(&modifiers);
+            let on_background = !has_bind
+                && self
+                    .niri
+                    .mods_with_background_finger_scroll_binds
+                    .contains(&modifiers)
+                && self.niri.is_pointer_over_workspace_background();
+            if has_bind || on_background {
                 let comp_mod = self.backend.mod_key();
 
                 let horizontal = horizontal_amount.unwrap_or(0.);
@@ -2229,7 +2941,11 @@ impl State {
                     .accumulate(horizontal);
                 if ticks != 0 {
                     let config = self.niri.config.borrow();
-                    let bindings = &config.binds;
+                    let bindings = if on_background {
+                        &config.background_binds.0
+                    } else {
+                        &config.binds
+                    };
                     let bind_left =
                         find_configured_bind(bindings, comp_mod, Trigger::TouchpadScrollLeft, mods);
                     let bind_right = find_configured_bind(
@@ -2259,7 +2975,11 @@ impl State {
                     .accumulate(vertical);
                 if ticks != 0 {
                     let config = self.niri.config.borrow();
-                    let bindings = &config.binds;
+                    let bindings = if on_background {
+                        &config.background_binds.0
+                    } else {
+                        &config.binds
+                    };
                     let bind_up =
                         find_configured_bind(bindings, comp_mod, Trigger::TouchpadScrollUp, mods);
                     let bind_down =
@@ -2399,6 +3119,8 @@ impl State {
         if let Some(tool) = tool {
             match event.tip_state() {
                 TabletToolTipState::Down => {
+                    self.niri.focus_history.set_reason(FocusReason::Click);
+
                     let serial = SERIAL_COUNTER.next_serial();
                     tool.tip_down(serial, event.time_msec());
 
@@ -2537,6 +3259,13 @@ impl State {
             }
         }
 
+        // Some people want the workspace swipe to go the other way from natural scrolling.
+        let config = self.niri.config.borrow();
+        if config.input.touchpad.invert_workspace_swipe {
+            delta_y = -delta_y;
+        }
+        drop(config);
+
         if let Some((cx, cy)) = &mut self.niri.gesture_swipe_3f_cumulative {
             *cx += delta_x;
             *cy += delta_y;
@@ -2741,16 +3470,16 @@ impl State {
         &self,
         evt: &impl AbsolutePositionEvent<I>,
         fallback_output: Option<&Output>,
+        rotate_with_output: bool,
     ) -> Option<Point<f64, Logical>> {
         let output = evt.device().output(self);
         let output = output.as_ref().or(fallback_output)?;
         let output_geo = self.niri.global_space.output_geometry(output).unwrap();
-        let transform = output.current_transform();
-        let size = transform.invert().transform_size(output_geo.size);
-        Some(
-            transform.transform_point_in(evt.position_transformed(size), &size.to_f64())
-                + output_geo.loc.to_f64(),
-        )
+        let transform = self.niri.input_device_transform(output, rotate_with_output);
+        let pos = map_absolute_position(transform, output_geo.size, |size| {
+            evt.position_transformed(size)
+        });
+        Some(pos + output_geo.loc.to_f64())
     }
 
     /// Computes the cursor position for the touch event.
@@ -2760,7 +3489,8 @@ impl State {
         &self,
         evt: &impl AbsolutePositionEvent<I>,
     ) -> Option<Point<f64, Logical>> {
-        self.compute_absolute_location(evt, self.niri.output_for_touch())
+        let rotate_with_output = self.niri.config.borrow().input.touch.rotate_with_output;
+        self.compute_absolute_location(evt, self.niri.output_for_touch(), rotate_with_output)
     }
 
     fn on_touch_down<I: InputBackend>(&mut self, evt: I::TouchDownEvent) {
@@ -2771,10 +3501,38 @@ impl State {
             return;
         };
 
+        self.niri.focus_history.set_reason(FocusReason::Click);
+
         let serial = SERIAL_COUNTER.next_serial();
 
         let under = self.niri.contents_under(touch_location);
 
+        let slot = evt.slot();
+        self.niri
+            .touch_points
+            .insert(slot, (touch_location, under.window.clone()));
+
+        // Touches just outside of a window edge can start an interactive resize, unless they hit
+        // something drawn above the windows.
+        let mut resize_near = None;
+        let resize_edge_width = self.niri.config.borrow().input.touch.resize_edge_width;
+        let two_finger_resize = self.niri.config.borrow().input.touch.two_finger_resize;
+        if let Some(edge_width) = resize_edge_width {
+            let is_above_windows = under
+                .layer
+                .as_ref()
+                .is_some_and(|layer| matches!(layer.layer(), Layer::Top | Layer::Overlay));
+            if under.window.is_none() && !is_above_windows {
+                if let Some((output, pos_within_output)) = self.niri.output_under(touch_location) {
+                    resize_near = self
+                        .niri
+                        .layout
+                        .resize_edges_near(output, pos_within_output, edge_width.0)
+                        .map(|(mapped, edges)| (mapped.window.clone(), edges));
+                }
+            }
+        }
+
         if !handle.is_grabbed() {
             if let Some(window) = under.window {
                 self.niri.layout.activate_window(&window);
@@ -2803,6 +3561,58 @@ impl State {
                         let grab = TouchMoveGrab::new(start_data, window.clone());
                         handle.set_grab(self, grab, serial);
                     }
+                } else if let Some((first_slot, first_location)) = two_finger_resize
+                    .then(|| {
+                        self.niri.touch_points.iter().find_map(|(s, (loc, w))| {
+                            (*s != slot && w.as_ref() == Some(&window)).then_some((*s, *loc))
+                        })
+                    })
+                    .flatten()
+                {
+                    // A second finger on a floating window starts a two-finger resize.
+                    let is_floating = self
+                        .niri
+                        .layout
+                        .windows()
+                        .any(|(_, m)| m.window == window && m.is_floating());
+                    if is_floating
+                        && self
+                            .niri
+                            .layout
+                            .interactive_resize_begin(window.clone(), ResizeEdge::BOTTOM_RIGHT)
+                    {
+                        let start_data = TouchGrabStartData {
+                            focus: None,
+                            slot: first_slot,
+                            location: first_location,
+                        };
+                        let grab = TouchResizeGrab::new_two_finger(
+                            start_data,
+                            window.clone(),
+                            slot,
+                            touch_location,
+                        );
+                        handle.set_grab(self, grab, serial);
+                    }
+                }
+
+                // FIXME: granular.
+                self.niri.queue_redraw_all();
+            } else if let Some((window, edges)) = resize_near {
+                self.niri.layout.activate_window(&window);
+
+                if self
+                    .niri
+                    .layout
+                    .interactive_resize_begin(window.clone(), edges)
+                {
+                    let start_data = TouchGrabStartData {
+                        focus: None,
+                        slot,
+                        location: touch_location,
+                    };
+                    let grab = TouchResizeGrab::new(start_data, window);
+                    handle.set_grab(self, grab, serial);
                 }
 
                 // FIXME: granular.
@@ -2834,6 +3644,8 @@ impl State {
         let Some(handle) = self.niri.seat.get_touch() else {
             return;
         };
+        self.niri.touch_points.remove(&evt.slot());
+
         let serial = SERIAL_COUNTER.next_serial();
         handle.up(
             self,
@@ -2851,6 +3663,10 @@ impl State {
         let Some(touch_location) = self.compute_touch_location(&evt) else {
             return;
         };
+        if let Some((location, _)) = self.niri.touch_points.get_mut(&evt.slot()) {
+            *location = touch_location;
+        }
+
         let under = self.niri.contents_under(touch_location);
         handle.motion(
             self,
@@ -2869,6 +3685,8 @@ impl State {
         handle.frame(self);
     }
     fn on_touch_cancel<I: InputBackend>(&mut self, _evt: I::TouchCancelEvent) {
+        self.niri.touch_points.clear();
+
         let Some(handle) = self.niri.seat.get_touch() else {
             return;
         };
@@ -2893,6 +3711,7 @@ impl State {
         };
 
         if let Some(action) = action {
+            self.niri.focus_history.set_reason(FocusReason::Bind);
             self.do_action(action, true);
         }
     }
@@ -2958,6 +3777,7 @@ fn should_intercept_key(
                     // But logically, nothing can inhibit its actions. Only opening it can be
                     // inhibited.
                     allow_inhibiting: false,
+                    warp_mouse_to_focus: true,
                 });
             }
         }
@@ -3023,6 +3843,7 @@ fn find_bind(
             // It also makes no sense to inhibit the default power key handling.
             // Hardcoded binds must never be inhibited.
             allow_inhibiting: false,
+            warp_mouse_to_focus: true,
         });
     }
 
@@ -3081,7 +3902,41 @@ fn find_configured_switch_action(
     };
     switch_action
         .as_ref()
-        .map(|switch_action| Action::Spawn(switch_action.spawn.clone()))
+        .map(|switch_action| Action::Spawn(switch_action.spawn.clone(), vec![]))
+}
+
+/// Returns what a click on this window should do.
+fn focus_click(
+    config: &ClickToFocus,
+    is_focused: bool,
+    on_other_output: bool,
+    is_floating: bool,
+) -> FocusClick {
+    if is_focused {
+        FocusClick::PassThrough
+    } else if on_other_output {
+        config.other_output
+    } else if is_floating {
+        config.floating
+    } else {
+        config.tiled
+    }
+}
+
+/// Returns whether the pressed key is the compositor Mod key with no other modifiers held.
+fn is_mod_pressed_alone(
+    comp_mod: CompositorMod,
+    raw: Option<Keysym>,
+    mods: ModifiersState,
+) -> bool {
+    let (comp_mod, keysyms) = match comp_mod {
+        CompositorMod::Super => (Modifiers::SUPER, [Keysym::Super_L, Keysym::Super_R]),
+        CompositorMod::Alt => (Modifiers::ALT, [Keysym::Alt_L, Keysym::Alt_R]),
+    };
+
+    // The modifiers already include the key being pressed.
+    let is_mod_key = raw.is_some_and(|raw| keysyms.contains(&raw));
+    is_mod_key && modifiers_from_state(mods) == comp_mod
 }
 
 fn modifiers_from_state(mods: ModifiersState) -> Modifiers {
@@ -3179,6 +4034,23 @@ fn should_reset_pointer_inactivity_timer<I: InputBackend>(event: &InputEvent<I>)
     )
 }
 
+/// Returns the timestamp of the event in microseconds, for events where latency matters.
+#[cfg(feature = "metrics")]
+fn input_event_time<I: InputBackend>(event: &InputEvent<I>) -> Option<u64> {
+    let time = match event {
+        InputEvent::Keyboard { event } => event.time(),
+        InputEvent::PointerMotion { event } => event.time(),
+        InputEvent::PointerMotionAbsolute { event } => event.time(),
+        InputEvent::PointerButton { event } => event.time(),
+        InputEvent::PointerAxis { event } => event.time(),
+        InputEvent::TouchDown { event } => event.time(),
+        InputEvent::TouchMotion { event } => event.time(),
+        InputEvent::TabletToolAxis { event } => event.time(),
+        _ => return None,
+    };
+    Some(time)
+}
+
 fn allowed_when_locked(action: &Action) -> bool {
     matches!(
         action,
@@ -3187,6 +4059,7 @@ fn allowed_when_locked(action: &Action) -> bool {
             | Action::Suspend
             | Action::PowerOffMonitors
             | Action::PowerOnMonitors
+            | Action::SetOutputBrightness(_, _)
             | Action::SwitchLayout(_)
             | Action::ToggleKeyboardShortcutsInhibit
     )
@@ -3200,6 +4073,8 @@ fn allowed_during_screenshot(action: &Action) -> bool {
             | Action::Suspend
             | Action::PowerOffMonitors
             | Action::PowerOnMonitors
+            | Action::SetOutputBrightness(_, _)
+            | Action::ToggleScreenRecord
     )
 }
 
@@ -3216,7 +4091,8 @@ pub fn apply_libinput_settings(config: &niri_config::Input, device: &mut input::
             input::SendEventsMode::ENABLED
         });
         let _ = device.config_tap_set_enabled(c.tap);
-        let _ = device.config_dwt_set_enabled(c.dwt);
+        // With excluded keyboards, niri disables the touchpad while typing on its own.
+        let _ = device.config_dwt_set_enabled(c.dwt && c.dwt_exclude_keyboards.is_empty());
         let _ = device.config_dwtp_set_enabled(c.dwtp);
         let _ = device.config_scroll_set_natural_scroll_enabled(c.natural_scroll);
         let _ = device.config_accel_set_speed(c.accel_speed);
@@ -3401,6 +4277,48 @@ pub fn apply_libinput_settings(config: &niri_config::Input, device: &mut input::
     }
 }
 
+/// Replaces empty binds with the emergency binds, so that the user doesn't get locked out.
+///
+/// Returns `true` if the emergency binds were added.
+pub fn add_emergency_binds_if_needed(binds: &mut Binds) -> bool {
+    if !binds.0.is_empty() {
+        return false;
+    }
+
+    warn!("the config has no binds, adding emergency binds");
+    *binds = Binds::emergency();
+    true
+}
+
+/// Returns whether the keyboard is built in, using the same rules as libinput's
+/// disable-while-typing.
+///
+/// The integration quirk wins if there is one, otherwise only keyboards on the i8042 bus count.
+fn is_internal_keyboard(device: &input::Device) -> bool {
+    // Same as BUS_I8042 from linux/input.h.
+    const BUS_I8042: &str = "0011";
+
+    let Some(udev_device) = (unsafe { device.udev_device() }) else {
+        return false;
+    };
+
+    let integration = udev_device.property_value("LIBINPUT_ATTR_KEYBOARD_INTEGRATION");
+    match integration.and_then(|value| value.to_str()) {
+        Some("internal") => return true,
+        Some("external") => return false,
+        _ => (),
+    }
+
+    udev_device
+        .parent()
+        .and_then(|parent| {
+            parent
+                .attribute_value("id/bustype")
+                .map(|x| x.to_str() == Some(BUS_I8042))
+        })
+        .unwrap_or(false)
+}
+
 pub fn mods_with_binds(
     comp_mod: CompositorMod,
     binds: &Binds,
@@ -3471,10 +4389,14 @@ pub fn mods_with_finger_scroll_binds(comp_mod: CompositorMod, binds: &Binds) ->
 
 #[cfg(test)]
 mod tests {
-    use std::cell::Cell;
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
+    use niri_config::Config;
 
     use super::*;
     use crate::animation::Clock;
+    use crate::ui::hotkey_overlay::HotkeyOverlay;
 
     #[test]
     fn bindings_suppress_keys() {
@@ -3489,6 +4411,7 @@ mod tests {
             cooldown: None,
             allow_when_locked: false,
             allow_inhibiting: true,
+            warp_mouse_to_focus: true,
         }]);
 
         let comp_mod = CompositorMod::Super;
@@ -3674,6 +4597,7 @@ mod tests {
                 cooldown: None,
                 allow_when_locked: false,
                 allow_inhibiting: true,
+                warp_mouse_to_focus: true,
             },
             Bind {
                 key: Key {
@@ -3685,6 +4609,7 @@ mod tests {
                 cooldown: None,
                 allow_when_locked: false,
                 allow_inhibiting: true,
+                warp_mouse_to_focus: true,
             },
             Bind {
                 key: Key {
@@ -3696,6 +4621,7 @@ mod tests {
                 cooldown: None,
                 allow_when_locked: false,
                 allow_inhibiting: true,
+                warp_mouse_to_focus: true,
             },
             Bind {
                 key: Key {
@@ -3707,6 +4633,7 @@ mod tests {
                 cooldown: None,
                 allow_when_locked: false,
                 allow_inhibiting: true,
+                warp_mouse_to_focus: true,
             },
             Bind {
                 key: Key {
@@ -3718,6 +4645,7 @@ mod tests {
                 cooldown: None,
                 allow_when_locked: false,
                 allow_inhibiting: true,
+                warp_mouse_to_focus: true,
             },
         ]);
 
@@ -3840,4 +4768,117 @@ mod tests {
             None,
         );
     }
+
+    #[test]
+    fn click_to_focus_cases() {
+        let config = ClickToFocus {
+            tiled: FocusClick::PassThrough,
+            floating: FocusClick::Consume,
+            other_output: FocusClick::Consume,
+        };
+
+        // Clicks on the focused window always go through.
+        assert_eq!(
+            focus_click(&config, true, true, true),
+            FocusClick::PassThrough
+        );
+
+        assert_eq!(
+            focus_click(&config, false, false, false),
+            FocusClick::PassThrough
+        );
+        assert_eq!(
+            focus_click(&config, false, false, true),
+            FocusClick::Consume
+        );
+
+        // Other output takes precedence over the window kind.
+        let config = ClickToFocus {
+            other_output: FocusClick::PassThrough,
+            ..config
+        };
+        assert_eq!(
+            focus_click(&config, false, true, true),
+            FocusClick::PassThrough
+        );
+
+        let config = ClickToFocus {
+            tiled: FocusClick::Consume,
+            ..ClickToFocus::default()
+        };
+        assert_eq!(
+            focus_click(&config, false, true, false),
+            FocusClick::PassThrough
+        );
+        assert_eq!(
+            focus_click(&config, false, false, false),
+            FocusClick::Consume
+        );
+    }
+
+    #[test]
+    fn hotkey_overlay_mod_hold() {
+        let super_held = ModifiersState {
+            logo: true,
+            ..Default::default()
+        };
+        let super_shift_held = ModifiersState {
+            shift: true,
+            ..super_held
+        };
+
+        // The timer starts only for the Mod key on its own.
+        let comp_mod = CompositorMod::Super;
+        assert!(is_mod_pressed_alone(
+            comp_mod,
+            Some(Keysym::Super_L),
+            super_held
+        ));
+        assert!(is_mod_pressed_alone(
+            comp_mod,
+            Some(Keysym::Super_R),
+            super_held
+        ));
+        assert!(!is_mod_pressed_alone(
+            comp_mod,
+            Some(Keysym::Super_L),
+            super_shift_held
+        ));
+        assert!(!is_mod_pressed_alone(comp_mod, Some(Keysym::a), super_held));
+        assert!(!is_mod_pressed_alone(
+            comp_mod,
+            Some(Keysym::Alt_L),
+            super_held
+        ));
+        assert!(!is_mod_pressed_alone(comp_mod, None, super_held));
+
+        let alt_held = ModifiersState {
+            alt: true,
+            ..Default::default()
+        };
+        let comp_mod = CompositorMod::Alt;
+        assert!(is_mod_pressed_alone(
+            comp_mod,
+            Some(Keysym::Alt_L),
+            alt_held
+        ));
+        assert!(!is_mod_pressed_alone(
+            comp_mod,
+            Some(Keysym::Super_L),
+            super_held
+        ));
+
+        // The overlay opened on Mod hold hides on release, unlike one opened with the bind.
+        let config = Rc::new(RefCell::new(Config::default()));
+        let mut overlay = HotkeyOverlay::new(config, CompositorMod::Super);
+        assert!(overlay.show_on_mod_hold());
+        assert!(overlay.hide_on_mod_release());
+        assert!(!overlay.is_open());
+
+        assert!(overlay.show());
+        assert!(!overlay.hide_on_mod_release());
+        assert!(overlay.is_open());
+        assert!(!overlay.show_on_mod_hold());
+        assert!(!overlay.hide_on_mod_release());
+    }
 }
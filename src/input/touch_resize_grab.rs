@@ -1,3 +1,4 @@
+use smithay::backend::input::TouchSlot;
 use smithay::desktop::Window;
 use smithay::input::touch::{
     DownEvent, GrabStartData as TouchGrabStartData, MotionEvent, OrientationEvent, ShapeEvent,
@@ -11,11 +12,46 @@ use crate::niri::State;
 pub struct TouchResizeGrab {
     start_data: TouchGrabStartData<State>,
     window: Window,
+    /// Second finger of a two-finger resize.
+    second_finger: Option<SecondFinger>,
+}
+
+struct SecondFinger {
+    slot: TouchSlot,
+    /// Distance between the two fingers at the start of the resize.
+    start_spread: Point<f64, Logical>,
+    /// Current locations of the first and the second finger.
+    locations: (Point<f64, Logical>, Point<f64, Logical>),
 }
 
 impl TouchResizeGrab {
     pub fn new(start_data: TouchGrabStartData<State>, window: Window) -> Self {
-        Self { start_data, window }
+        Self {
+            start_data,
+            window,
+            second_finger: None,
+        }
+    }
+
+    /// Creates a grab that resizes the window as the distance between two fingers changes.
+    ///
+    /// `start_data` is for the first finger.
+    pub fn new_two_finger(
+        start_data: TouchGrabStartData<State>,
+        window: Window,
+        slot: TouchSlot,
+        location: Point<f64, Logical>,
+    ) -> Self {
+        let locations = (start_data.location, location);
+        Self {
+            start_data,
+            window,
+            second_finger: Some(SecondFinger {
+                slot,
+                start_spread: spread(locations),
+                locations,
+            }),
+        }
     }
 
     fn on_ungrab(&mut self, state: &mut State) {
@@ -44,7 +80,11 @@ impl TouchGrab<State> for TouchResizeGrab {
     ) {
         handle.up(data, event, seq);
 
-        if event.slot != self.start_data.slot {
+        let is_second_finger = self
+            .second_finger
+            .as_ref()
+            .is_some_and(|second| event.slot == second.slot);
+        if event.slot != self.start_data.slot && !is_second_finger {
             return;
         }
 
@@ -61,12 +101,25 @@ impl TouchGrab<State> for TouchResizeGrab {
     ) {
         handle.motion(data, None, event, seq);
 
-        if event.slot != self.start_data.slot {
-            return;
-        }
+        let delta = if let Some(second) = &mut self.second_finger {
+            if event.slot == self.start_data.slot {
+                second.locations.0 = event.location;
+            } else if event.slot == second.slot {
+                second.locations.1 = event.location;
+            } else {
+                return;
+            }
+
+            spread(second.locations) - second.start_spread
+        } else {
+            if event.slot != self.start_data.slot {
+                return;
+            }
+
+            event.location - self.start_data.location
+        };
 
         if self.window.alive() {
-            let delta = event.location - self.start_data.location;
             let ongoing = data
                 .niri
                 .layout
@@ -117,3 +170,8 @@ impl TouchGrab<State> for TouchResizeGrab {
         self.on_ungrab(data);
     }
 }
+
+fn spread(locations: (Point<f64, Logical>, Point<f64, Logical>)) -> Point<f64, Logical> {
+    let (a, b) = locations;
+    Point::from(((a.x - b.x).abs(), (a.y - b.y).abs()))
+}
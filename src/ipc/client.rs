@@ -2,33 +2,82 @@ use std::iter::Peekable;
 use std::slice;
 
 use anyhow::{anyhow, bail, Context};
+use clap::Parser as _;
 use niri_config::OutputName;
 use niri_ipc::socket::Socket;
 use niri_ipc::{
-    Event, KeyboardLayouts, LogicalOutput, Mode, Output, OutputConfigChanged, Request, Response,
-    Transform, Window,
+    Event, EventClass, FocusReason, FocusTarget, KeyboardLayouts, LogicalOutput, Mode, Output,
+    OutputConfigChanged, Request, Response, Transform, Window, WindowFilter,
 };
-use serde_json::json;
-
-use crate::cli::Msg;
-use crate::utils::version;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::cli::{ActionArg, Msg};
+use crate::utils::{get_monotonic_time, version};
+
+pub fn handle_msg(msg: Msg, json: bool, format: Option<&str>) -> anyhow::Result<()> {
+    if format.is_some()
+        && !matches!(
+            msg,
+            Msg::Outputs | Msg::Workspaces | Msg::Windows | Msg::FocusedOutput | Msg::FocusedWindow
+        )
+    {
+        bail!(
+            "--format is only supported for outputs, workspaces, windows, \
+             focused-output and focused-window"
+        );
+    }
 
-pub fn handle_msg(msg: Msg, json: bool) -> anyhow::Result<()> {
     let request = match &msg {
         Msg::Version => Request::Version,
         Msg::Outputs => Request::Outputs,
         Msg::FocusedWindow => Request::FocusedWindow,
         Msg::FocusedOutput => Request::FocusedOutput,
-        Msg::Action { action } => Request::Action(action.clone()),
+        Msg::Action { filter, action } => {
+            if *filter == WindowFilter::default() {
+                Request::Action(action.clone())
+            } else {
+                Request::MatchedAction {
+                    filter: filter.clone(),
+                    action: action.clone(),
+                }
+            }
+        }
+        Msg::Actions { actions } => Request::Actions(parse_actions(actions)?),
+        Msg::BulkAction { filter, action } => Request::BulkAction {
+            filter: filter.clone(),
+            action: action.clone(),
+        },
         Msg::Output { output, action } => Request::Output {
             output: output.clone(),
             action: action.clone(),
         },
+        Msg::CreateVirtualOutput {
+            width,
+            height,
+            scale,
+            workspaces,
+        } => Request::CreateVirtualOutput {
+            width: *width,
+            height: *height,
+            scale: *scale,
+            workspaces: workspaces.clone(),
+        },
+        Msg::RemoveVirtualOutput { output } => Request::RemoveVirtualOutput {
+            output: output.clone(),
+        },
         Msg::Workspaces => Request::Workspaces,
         Msg::Windows => Request::Windows,
         Msg::Layers => Request::Layers,
         Msg::KeyboardLayouts => Request::KeyboardLayouts,
-        Msg::EventStream => Request::EventStream,
+        Msg::EventStream { subscribe } if subscribe.is_empty() => Request::EventStream,
+        Msg::EventStream { subscribe } => Request::FilteredEventStream {
+            subscribe: subscribe.clone(),
+        },
+        Msg::Protocols => Request::Protocols,
+        Msg::ExplainFocus => Request::ExplainFocus,
+        Msg::BindCooldowns => Request::BindCooldowns,
+        Msg::ClipboardHistory => Request::ClipboardHistory,
         Msg::RequestError => Request::ReturnError,
     };
 
@@ -131,6 +180,10 @@ pub fn handle_msg(msg: Msg, json: bool) -> anyhow::Result<()> {
                 .collect::<Vec<_>>();
             outputs.sort_unstable_by(|a, b| a.0.compare(&b.0));
 
+            if let Some(format) = format {
+                return print_formatted(format, outputs.iter().map(|(_name, output)| output));
+            }
+
             for (_name, output) in outputs.into_iter() {
                 print_output(output)?;
                 println!();
@@ -147,6 +200,10 @@ pub fn handle_msg(msg: Msg, json: bool) -> anyhow::Result<()> {
                 return Ok(());
             }
 
+            if let Some(format) = format {
+                return print_formatted(format, &window);
+            }
+
             if let Some(window) = window {
                 print_window(&window);
             } else {
@@ -167,6 +224,10 @@ pub fn handle_msg(msg: Msg, json: bool) -> anyhow::Result<()> {
 
             windows.sort_unstable_by(|a, b| a.id.cmp(&b.id));
 
+            if let Some(format) = format {
+                return print_formatted(format, &windows);
+            }
+
             for window in windows {
                 print_window(&window);
                 println!();
@@ -246,17 +307,38 @@ pub fn handle_msg(msg: Msg, json: bool) -> anyhow::Result<()> {
                 return Ok(());
             }
 
+            if let Some(format) = format {
+                return print_formatted(format, &output);
+            }
+
             if let Some(output) = output {
                 print_output(output)?;
             } else {
                 println!("No output is focused.");
             }
         }
-        Msg::Action { .. } => {
-            let Response::Handled = response else {
+        Msg::Action { .. } | Msg::Actions { .. } => {
+            let (Response::Handled | Response::MatchedActionApplied(_)) = response else {
                 bail!("unexpected response: expected Handled, got {response:?}");
             };
         }
+        Msg::BulkAction { .. } => {
+            let Response::BulkActionApplied(ids) = response else {
+                bail!("unexpected response: expected BulkActionApplied, got {response:?}");
+            };
+
+            if json {
+                let ids = serde_json::to_string(&ids).context("error formatting response")?;
+                println!("{ids}");
+                return Ok(());
+            }
+
+            match ids.len() {
+                0 => println!("No windows matched the filter."),
+                1 => println!("Performed the action on 1 window."),
+                n => println!("Performed the action on {n} windows."),
+            }
+        }
         Msg::Output { output, .. } => {
             let Response::OutputConfigChanged(response) = response else {
                 bail!("unexpected response: expected OutputConfigChanged, got {response:?}");
@@ -274,6 +356,24 @@ pub fn handle_msg(msg: Msg, json: bool) -> anyhow::Result<()> {
                 println!("The change will apply when it is connected.");
             }
         }
+        Msg::CreateVirtualOutput { .. } => {
+            let Response::VirtualOutputCreated(name) = response else {
+                bail!("unexpected response: expected VirtualOutputCreated, got {response:?}");
+            };
+
+            if json {
+                let name = serde_json::to_string(&name).context("error formatting response")?;
+                println!("{name}");
+                return Ok(());
+            }
+
+            println!("Created virtual output \"{name}\".");
+        }
+        Msg::RemoveVirtualOutput { .. } => {
+            let Response::Handled = response else {
+                bail!("unexpected response: expected Handled, got {response:?}");
+            };
+        }
         Msg::Workspaces => {
             let Response::Workspaces(mut response) = response else {
                 bail!("unexpected response: expected Workspaces, got {response:?}");
@@ -286,14 +386,18 @@ pub fn handle_msg(msg: Msg, json: bool) -> anyhow::Result<()> {
                 return Ok(());
             }
 
+            response.sort_by_key(|ws| ws.idx);
+            response.sort_by(|a, b| a.output.cmp(&b.output));
+
+            if let Some(format) = format {
+                return print_formatted(format, &response);
+            }
+
             if response.is_empty() {
                 println!("No workspaces.");
                 return Ok(());
             }
 
-            response.sort_by_key(|ws| ws.idx);
-            response.sort_by(|a, b| a.output.cmp(&b.output));
-
             let mut current_output = if let Some(output) = response[0].output.as_deref() {
                 println!("Output \"{output}\":");
                 Some(output)
@@ -316,10 +420,17 @@ pub fn handle_msg(msg: Msg, json: bool) -> anyhow::Result<()> {
                 let idx = ws.idx;
                 let name = if let Some(name) = ws.name.as_deref() {
                     format!(" \"{name}\"")
+                } else if let Some(auto_name) = ws.auto_name.as_deref() {
+                    format!(" ({auto_name})")
                 } else {
                     String::new()
                 };
-                println!("{is_active}{idx}{name}");
+                let icon = if let Some(icon) = ws.icon.as_deref() {
+                    format!(" {icon}")
+                } else {
+                    String::new()
+                };
+                println!("{is_active}{idx}{name}{icon}");
             }
         }
         Msg::KeyboardLayouts => {
@@ -343,7 +454,135 @@ pub fn handle_msg(msg: Msg, json: bool) -> anyhow::Result<()> {
                 println!("{is_active}{idx} {name}");
             }
         }
-        Msg::EventStream => {
+        Msg::Protocols => {
+            let Response::Protocols(mut protocols) = response else {
+                bail!("unexpected response: expected Protocols, got {response:?}");
+            };
+
+            if json {
+                let protocols =
+                    serde_json::to_string(&protocols).context("error formatting response")?;
+                println!("{protocols}");
+                return Ok(());
+            }
+
+            protocols.sort_unstable_by(|a, b| a.interface.cmp(&b.interface));
+            let width = protocols.iter().map(|p| p.interface.len()).max();
+            let width = width.unwrap_or(0);
+
+            for protocol in protocols {
+                let privileged = if protocol.privileged {
+                    " (hidden from sandboxed clients)"
+                } else {
+                    ""
+                };
+                println!(
+                    "{:width$} v{}{privileged}",
+                    protocol.interface, protocol.version
+                );
+            }
+        }
+        Msg::BindCooldowns => {
+            let Response::BindCooldowns(cooldowns) = response else {
+                bail!("unexpected response: expected BindCooldowns, got {response:?}");
+            };
+
+            if json {
+                let cooldowns =
+                    serde_json::to_string(&cooldowns).context("error formatting response")?;
+                println!("{cooldowns}");
+                return Ok(());
+            }
+
+            if cooldowns.is_empty() {
+                println!("No binds on cooldown.");
+            }
+
+            for cooldown in cooldowns {
+                println!("{}: {} ms left", cooldown.key, cooldown.remaining_ms);
+            }
+        }
+        Msg::ClipboardHistory => {
+            let Response::ClipboardHistory(entries) = response else {
+                bail!("unexpected response: expected ClipboardHistory, got {response:?}");
+            };
+
+            if json {
+                let entries =
+                    serde_json::to_string(&entries).context("error formatting response")?;
+                println!("{entries}");
+                return Ok(());
+            }
+
+            if entries.is_empty() {
+                println!("The clipboard history is empty.");
+            }
+
+            for entry in entries {
+                let contents = match &entry.text {
+                    Some(text) => format!("{text:?}"),
+                    None => entry.mime_types.join(", "),
+                };
+                println!("Entry {}: {contents} ({} bytes)", entry.id, entry.size);
+            }
+        }
+        Msg::ExplainFocus => {
+            let Response::FocusExplanation(explanation) = response else {
+                bail!("unexpected response: expected FocusExplanation, got {response:?}");
+            };
+
+            if json {
+                let explanation =
+                    serde_json::to_string(&explanation).context("error formatting response")?;
+                println!("{explanation}");
+                return Ok(());
+            }
+
+            println!(
+                "Keyboard focus: {}",
+                format_focus_target(&explanation.focus)
+            );
+
+            println!();
+            println!("Focus stack, from the top:");
+            let lock = if explanation.is_locked {
+                "locked"
+            } else {
+                "unlocked"
+            };
+            println!("  Lock screen: {lock}");
+            let screenshot_ui = if explanation.is_screenshot_ui_open {
+                "open"
+            } else {
+                "closed"
+            };
+            println!("  Screenshot UI: {screenshot_ui}");
+            if let Some(namespace) = &explanation.on_demand_layer {
+                println!("  On-demand layer-shell focus: \"{namespace}\"");
+            } else {
+                println!("  On-demand layer-shell focus: none");
+            }
+            if let Some(id) = explanation.layout_focus {
+                println!("  Layout: window {id}");
+            } else {
+                println!("  Layout: no window");
+            }
+
+            println!();
+            if explanation.history.is_empty() {
+                println!("No recent focus changes.");
+            } else {
+                println!("Recent focus changes, newest first:");
+                for transition in explanation.history.iter().rev() {
+                    println!(
+                        "  {}: {}",
+                        format_focus_target(&transition.target),
+                        format_focus_reason(transition.reason)
+                    );
+                }
+            }
+        }
+        Msg::EventStream { .. } => {
             let Response::Handled = response else {
                 bail!("unexpected response: expected Handled, got {response:?}");
             };
@@ -378,6 +617,9 @@ pub fn handle_msg(msg: Msg, json: bool) -> anyhow::Result<()> {
                              active window changed to {active_window_id:?}"
                         );
                     }
+                    Event::WorkspaceAutoClosed { id, name } => {
+                        println!("Workspace {id} closed after staying empty: {name}");
+                    }
                     Event::WindowsChanged { windows } => {
                         println!("Windows changed: {windows:?}");
                     }
@@ -387,15 +629,49 @@ pub fn handle_msg(msg: Msg, json: bool) -> anyhow::Result<()> {
                     Event::WindowClosed { id } => {
                         println!("Window closed: {id}");
                     }
+                    Event::WindowLayoutsChanged { changes } => {
+                        println!("Window layouts changed: {changes:?}");
+                    }
                     Event::WindowFocusChanged { id } => {
                         println!("Window focus changed: {id:?}");
                     }
+                    Event::WindowUrgencyReminder { id, urgent_for_ms } => {
+                        let secs = urgent_for_ms / 1000;
+                        println!("Window {id} urgent for {secs} s");
+                    }
                     Event::KeyboardLayoutsChanged { keyboard_layouts } => {
                         println!("Keyboard layouts changed: {keyboard_layouts:?}");
                     }
                     Event::KeyboardLayoutSwitched { idx } => {
                         println!("Keyboard layout switched: {idx}");
                     }
+                    Event::OutputsChanged { outputs } => {
+                        println!("Outputs changed: {outputs:?}");
+                    }
+                    Event::OutputConnected { output } => {
+                        println!("Output connected: {output:?}");
+                    }
+                    Event::OutputDisconnected { name } => {
+                        println!("Output disconnected: {name}");
+                    }
+                    Event::LayersChanged { layers } => {
+                        println!("Layers changed: {layers:?}");
+                    }
+                    Event::ScreenshotUiOpenedOrClosed { is_open } => {
+                        let word = if is_open { "opened" } else { "closed" };
+                        println!("Screenshot UI {word}");
+                    }
+                    Event::ExitConfirmDialogOpenedOrClosed { is_open } => {
+                        let word = if is_open { "opened" } else { "closed" };
+                        println!("Exit confirm dialog {word}");
+                    }
+                    Event::WorkspaceSwitchGestureChanged { output, progress } => {
+                        if let Some(progress) = progress {
+                            println!("Workspace switch gesture on {output}: {progress:.2}");
+                        } else {
+                            println!("Workspace switch gesture on {output} ended");
+                        }
+                    }
                 }
             }
         }
@@ -404,6 +680,83 @@ pub fn handle_msg(msg: Msg, json: bool) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Parses the arguments of `niri msg actions`, where actions are separated with `;`.
+fn parse_actions(args: &[String]) -> anyhow::Result<Vec<niri_ipc::Action>> {
+    args.split(|arg| arg == ";")
+        .enumerate()
+        .map(|(idx, args)| {
+            if args.is_empty() {
+                bail!("action {} is empty", idx + 1);
+            }
+
+            let arg = ActionArg::try_parse_from(args)
+                .map_err(|err| anyhow!("error parsing action {}: {err}", idx + 1))?;
+            Ok(arg.action)
+        })
+        .collect()
+}
+
+/// Prints the requested state, then prints it again every time a relevant event arrives.
+pub fn watch_msg(msg: Msg, json: bool, format: Option<&str>) -> anyhow::Result<()> {
+    let (class, is_relevant): (_, fn(&Event) -> bool) = match msg {
+        Msg::Windows => (EventClass::Windows, |event| {
+            matches!(
+                event,
+                Event::WindowsChanged { .. }
+                    | Event::WindowOpenedOrChanged { .. }
+                    | Event::WindowClosed { .. }
+                    | Event::WindowFocusChanged { .. }
+            )
+        }),
+        Msg::Workspaces => (EventClass::Workspaces, |event| {
+            matches!(
+                event,
+                Event::WorkspacesChanged { .. }
+                    | Event::WorkspaceActivated { .. }
+                    | Event::WorkspaceActiveWindowChanged { .. }
+            )
+        }),
+        Msg::Outputs => (EventClass::Outputs, |_| true),
+        Msg::Layers => (EventClass::Layers, |_| true),
+        _ => bail!("--watch is only supported for outputs, workspaces, windows and layers"),
+    };
+    // Only unit variants get here, so the message can be recreated for every print.
+    let make_msg = || match msg {
+        Msg::Windows => Msg::Windows,
+        Msg::Workspaces => Msg::Workspaces,
+        Msg::Outputs => Msg::Outputs,
+        Msg::Layers => Msg::Layers,
+        _ => unreachable!(),
+    };
+
+    let socket = Socket::connect().context("error connecting to the niri socket")?;
+    let (reply, mut read_event) = socket
+        .send(Request::FilteredEventStream {
+            subscribe: vec![class],
+        })
+        .context("error communicating with niri")?;
+    let Response::Handled = reply.map_err(|err_msg| anyhow!(err_msg))? else {
+        bail!("unexpected response to the event stream request");
+    };
+
+    // The event stream starts with the full state, so this prints the initial state too.
+    let mut is_first = true;
+    loop {
+        let event = read_event().context("error reading event from niri")?;
+        if !is_relevant(&event) {
+            continue;
+        }
+
+        // Separate the states for human-readable output. JSON is printed one state per line.
+        if !json && format.is_none() && !is_first {
+            println!();
+        }
+        is_first = false;
+
+        handle_msg(make_msg(), json, format)?;
+    }
+}
+
 fn print_output(output: Output) -> anyhow::Result<()> {
     let Output {
         name,
@@ -415,6 +768,7 @@ fn print_output(output: Output) -> anyhow::Result<()> {
         current_mode,
         vrr_supported,
         vrr_enabled,
+        brightness,
         logical,
     } = output;
 
@@ -445,6 +799,10 @@ fn print_output(output: Output) -> anyhow::Result<()> {
         println!("  Variable refresh rate: not supported");
     }
 
+    if let Some(brightness) = brightness {
+        println!("  Brightness: {brightness:.0}%");
+    }
+
     if let Some((width, height)) = physical_size {
         println!("  Physical size: {width}x{height} mm");
     } else {
@@ -521,6 +879,17 @@ fn print_window(window: &Window) {
         if window.is_floating { "yes" } else { "no" }
     );
 
+    println!(
+        "  Is urgent: {}",
+        if window.is_urgent { "yes" } else { "no" }
+    );
+
+    if let Some(since) = window.urgent_since_ms {
+        let now = get_monotonic_time().as_millis() as u64;
+        let secs = now.saturating_sub(since) / 1000;
+        println!("  Urgent for: {secs} s");
+    }
+
     if let Some(pid) = window.pid {
         println!("  PID: {pid}");
     } else {
@@ -532,4 +901,174 @@ fn print_window(window: &Window) {
     } else {
         println!("  Workspace ID: (none)");
     }
+
+    let (width, height) = window.layout.window_size;
+    println!("  Size: {width}x{height}");
+
+    if let Some((x, y)) = window.layout.pos {
+        println!("  Position: {x}, {y}");
+    } else {
+        println!("  Position: (not visible)");
+    }
+
+    if !window.marks.is_empty() {
+        let marks = window.marks.join("\", \"");
+        println!("  Marks: \"{marks}\"");
+    }
+}
+
+/// Prints every item on its own line according to a `--format` template.
+fn print_formatted<T: Serialize>(
+    template: &str,
+    items: impl IntoIterator<Item = T>,
+) -> anyhow::Result<()> {
+    for item in items {
+        let value = serde_json::to_value(item).context("error formatting response")?;
+        println!("{}", format_template(template, &value)?);
+    }
+    Ok(())
+}
+
+/// Fills a template like `{id} {app_id}` with the fields of a JSON value.
+///
+/// Nested fields and array elements are separated with dots, like `{modes.0.width}`. Strings are
+/// printed without quotes, and missing optional values as nothing.
+fn format_template(template: &str, value: &Value) -> anyhow::Result<String> {
+    let mut rv = String::new();
+
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.next_if_eq(&'{').is_some() => rv.push('{'),
+            '}' if chars.next_if_eq(&'}').is_some() => rv.push('}'),
+            '{' => {
+                let mut path = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => path.push(c),
+                        None => bail!("unclosed {{ in the format template"),
+                    }
+                }
+
+                let field = path
+                    .split('.')
+                    .try_fold(value, |value, key| match key.parse::<usize>() {
+                        Ok(idx) if value.is_array() => value.get(idx),
+                        _ => value.get(key),
+                    })
+                    .with_context(|| format!("unknown field in the format template: {path}"))?;
+
+                match field {
+                    Value::Null => (),
+                    Value::String(string) => rv.push_str(string),
+                    field => rv.push_str(&field.to_string()),
+                }
+            }
+            '}' => bail!("unmatched }} in the format template, use }}}} for a literal brace"),
+            c => rv.push(c),
+        }
+    }
+
+    Ok(rv)
+}
+
+fn format_focus_target(target: &FocusTarget) -> String {
+    match target {
+        FocusTarget::Nothing => String::from("nothing"),
+        FocusTarget::Window { id } => format!("window {id}"),
+        FocusTarget::LayerShell { namespace } => format!("layer-shell surface \"{namespace}\""),
+        FocusTarget::LockScreen => String::from("lock screen"),
+        FocusTarget::ScreenshotUi => String::from("screenshot UI"),
+    }
+}
+
+fn format_focus_reason(reason: FocusReason) -> &'static str {
+    match reason {
+        FocusReason::Bind => "bind",
+        FocusReason::Ipc => "IPC action",
+        FocusReason::Click => "click",
+        FocusReason::FocusFollowsMouse => "focus follows mouse",
+        FocusReason::Activation => "activation request",
+        FocusReason::WindowOpened => "window opened",
+        FocusReason::FocusRestored => "restored after layer-shell surface closed",
+        FocusReason::Other => "other",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_template_fields() {
+        let value = json!({
+            "id": 3,
+            "title": "Terminal",
+            "app_id": null,
+            "logical": { "scale": 1.5 },
+            "modes": [{ "width": 1920 }],
+        });
+
+        let format = |template| format_template(template, &value).unwrap();
+        assert_eq!(format("{id} {title}"), "3 Terminal");
+        assert_eq!(format("[{app_id}]"), "[]");
+        assert_eq!(format("{logical.scale}"), "1.5");
+        assert_eq!(format("{modes.0.width}"), "1920");
+        assert_eq!(format("{{id}} {logical}"), r#"{id} {"scale":1.5}"#);
+
+        assert!(format_template("{nope}", &value).is_err());
+        assert!(format_template("{id", &value).is_err());
+        assert!(format_template("id}", &value).is_err());
+    }
+
+    #[test]
+    fn parse_actions_splits_on_semicolons() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+
+        let err = parse_actions(&args(&[
+            "focus-column-left",
+            ";",
+            "close-window",
+            "--id",
+            "5",
+            ";",
+            "spawn",
+            "--",
+            "foot",
+            ";",
+        ]))
+        .unwrap_err();
+        assert_eq!(err.to_string(), "action 4 is empty");
+
+        let actions = parse_actions(&args(&[
+            "focus-column-left",
+            ";",
+            "close-window",
+            "--id",
+            "5",
+            ";",
+            "spawn",
+            "--",
+            "foot",
+            "-e",
+            "htop",
+        ]))
+        .unwrap();
+        assert_eq!(actions.len(), 3);
+        assert!(matches!(actions[0], niri_ipc::Action::FocusColumnLeft {}));
+        assert!(matches!(
+            actions[1],
+            niri_ipc::Action::CloseWindow { id: Some(5) }
+        ));
+        assert!(matches!(
+            &actions[2],
+            niri_ipc::Action::Spawn { command } if command == &["foot", "-e", "htop"]
+        ));
+
+        let err = parse_actions(&args(&["focus-column-left", ";", "no-such-action"])).unwrap_err();
+        assert!(err.to_string().starts_with("error parsing action 2:"));
+
+        assert!(parse_actions(&args(&[";", "close-window"])).is_err());
+    }
 }
@@ -1,10 +1,11 @@
-use std::cell::RefCell;
-use std::collections::HashSet;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
 use std::ffi::OsStr;
 use std::os::unix::net::{UnixListener, UnixStream};
 use std::path::PathBuf;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use std::{env, io, process};
 
 use anyhow::Context;
@@ -14,25 +15,38 @@ use calloop::io::Async;
 use directories::BaseDirs;
 use futures_util::io::{AsyncReadExt, BufReader};
 use futures_util::{select_biased, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, FutureExt as _};
-use niri_config::OutputName;
+use niri_config::{Config, OutputName, RegexEq, WorkspaceReference};
 use niri_ipc::state::{EventStreamState, EventStreamStatePart as _};
-use niri_ipc::{Event, KeyboardLayouts, OutputConfigChanged, Reply, Request, Response, Workspace};
+use niri_ipc::{
+    Action, Event, EventClass, FocusReason, KeyboardLayouts, OutputConfigChanged, Reply, Request,
+    Response, WindowFilter, WindowLayout, Workspace, WorkspaceReferenceArg,
+};
 use smithay::desktop::layer_map_for_output;
 use smithay::reexports::calloop::generic::Generic;
+use smithay::reexports::calloop::timer::{TimeoutAction, Timer};
 use smithay::reexports::calloop::{Interest, LoopHandle, Mode, PostAction};
 use smithay::reexports::rustix::fs::unlink;
+use smithay::utils::Rectangle;
 use smithay::wayland::shell::wlr_layer::{KeyboardInteractivity, Layer};
 
-use crate::backend::IpcOutputMap;
+use crate::backend::{Backend, IpcOutputMap};
 use crate::layout::workspace::WorkspaceId;
+use crate::layout::LayoutElement as _;
 use crate::niri::State;
-use crate::utils::{version, with_toplevel_role};
+use crate::protocols::registry::protocol_globals;
+use crate::utils::{get_monotonic_time, version, with_toplevel_role};
 use crate::window::Mapped;
 
 // If an event stream client fails to read events fast enough that we accumulate more than this
 // number in our buffer, we drop that event stream client.
 const EVENT_STREAM_BUFFER_SIZE: usize = 64;
 
+// While animations are ongoing, window layout changes are sent at most this often.
+const WINDOW_LAYOUTS_THROTTLE: Duration = Duration::from_millis(50);
+
+// Gesture progress changes are sent at most this often.
+const GESTURE_PROGRESS_THROTTLE: Duration = Duration::from_millis(16);
+
 pub struct IpcServer {
     /// Path to the IPC socket.
     ///
@@ -40,6 +54,20 @@ pub struct IpcServer {
     pub socket_path: Option<PathBuf>,
     event_streams: Rc<RefCell<Vec<EventStreamSender>>>,
     event_stream_state: Rc<RefCell<EventStreamState>>,
+    /// Time when window layout changes were last sent.
+    last_window_layouts_sent: Cell<Option<Duration>>,
+    /// Whether a timer is queued to send throttled window layout changes.
+    window_layouts_timer_queued: Cell<bool>,
+    /// Last sent workspace switch gesture progress, by output name.
+    gesture_progress_sent: RefCell<HashMap<String, f64>>,
+    /// Time when gesture progress changes were last sent.
+    last_gesture_progress_sent: Cell<Option<Duration>>,
+    /// Whether a timer is queued to send throttled gesture progress changes.
+    gesture_progress_timer_queued: Cell<bool>,
+    /// Number of reminders sent for every urgent window.
+    urgency_reminders_sent: RefCell<HashMap<u64, u32>>,
+    /// Whether a timer is queued to send the next urgency reminder.
+    urgency_timer_queued: Cell<bool>,
 }
 
 struct ClientCtx {
@@ -59,6 +87,16 @@ struct EventStreamClient {
 struct EventStreamSender {
     events: Sender<Event>,
     disconnect: Sender<()>,
+    /// Classes of events that the client subscribed to, or `None` for all events.
+    subscribe: Option<Vec<EventClass>>,
+}
+
+impl EventStreamSender {
+    fn wants(&self, event: &Event) -> bool {
+        self.subscribe
+            .as_ref()
+            .map_or(true, |classes| classes.contains(&event.class()))
+    }
 }
 
 impl IpcServer {
@@ -101,6 +139,13 @@ impl IpcServer {
             socket_path,
             event_streams: Rc::new(RefCell::new(Vec::new())),
             event_stream_state: Rc::new(RefCell::new(EventStreamState::default())),
+            last_window_layouts_sent: Cell::new(None),
+            window_layouts_timer_queued: Cell::new(false),
+            gesture_progress_sent: RefCell::new(HashMap::new()),
+            last_gesture_progress_sent: Cell::new(None),
+            gesture_progress_timer_queued: Cell::new(false),
+            urgency_reminders_sent: RefCell::new(HashMap::new()),
+            urgency_timer_queued: Cell::new(false),
         })
     }
 
@@ -108,6 +153,10 @@ impl IpcServer {
         let mut streams = self.event_streams.borrow_mut();
         let mut to_remove = Vec::new();
         for (idx, stream) in streams.iter_mut().enumerate() {
+            if !stream.wants(&event) {
+                continue;
+            }
+
             match stream.events.try_send(event.clone()) {
                 Ok(()) => (),
                 Err(TrySendError::Closed(_)) => to_remove.push(idx),
@@ -186,28 +235,34 @@ async fn handle_client(ctx: ClientCtx, stream: Async<'static, UnixStream>) -> an
         .await
         .context("error reading request")?;
 
+    // A JSON array is a batch of requests, replied to with an array of replies.
+    if buf.trim_start().starts_with('[') {
+        return handle_batch(&ctx, &buf, write).await;
+    }
+
     let request = serde_json::from_str(&buf)
         .context("error parsing request")
         .map_err(|err| err.to_string());
-    let requested_error = matches!(request, Ok(Request::ReturnError));
-    let requested_event_stream = matches!(request, Ok(Request::EventStream));
-
-    let reply = match request {
-        Ok(request) => process(&ctx, request).await,
-        Err(err) => Err(err),
+    // Outer None if this isn't an event stream, inner None to stream all events.
+    let event_stream_subscribe = match &request {
+        Ok(Request::EventStream) => Some(None),
+        Ok(Request::FilteredEventStream { subscribe }) => Some(Some(subscribe.clone())),
+        _ => None,
     };
 
-    if let Err(err) = &reply {
-        if !requested_error {
+    let reply = match request {
+        Ok(request) => process_logged(&ctx, request).await,
+        Err(err) => {
             warn!("error processing IPC request: {err:?}");
+            Err(err)
         }
-    }
+    };
 
     let mut buf = serde_json::to_vec(&reply).context("error formatting reply")?;
     buf.push(b'\n');
     write.write_all(&buf).await.context("error writing reply")?;
 
-    if requested_event_stream {
+    if let Some(subscribe) = event_stream_subscribe {
         let (events_tx, events_rx) = async_channel::bounded(EVENT_STREAM_BUFFER_SIZE);
         let (disconnect_tx, disconnect_rx) = async_channel::bounded(1);
 
@@ -226,30 +281,101 @@ async fn handle_client(ctx: ClientCtx, stream: Async<'static, UnixStream>) -> an
             warn!("error scheduling IPC event stream future: {err:?}");
         }
 
+        let sender = EventStreamSender {
+            events: events_tx,
+            disconnect: disconnect_tx,
+            subscribe,
+        };
+
         // Send the initial state.
         {
             let state = ctx.event_stream_state.borrow();
             for event in state.replicate() {
-                events_tx
+                if !sender.wants(&event) {
+                    continue;
+                }
+
+                sender
+                    .events
                     .try_send(event)
                     .expect("initial event burst had more events than buffer size");
             }
         }
 
         // Add it to the list.
-        {
-            let mut streams = ctx.event_streams.borrow_mut();
-            let sender = EventStreamSender {
-                events: events_tx,
-                disconnect: disconnect_tx,
-            };
-            streams.push(sender);
-        }
+        ctx.event_streams.borrow_mut().push(sender);
     }
 
     Ok(())
 }
 
+/// Processes a batch of requests one after another and writes all replies at once.
+///
+/// The requests that only read the state don't yield to the event loop, so a batch of them sees
+/// a consistent state.
+async fn handle_batch(
+    ctx: &ClientCtx,
+    buf: &str,
+    mut write: impl AsyncWrite + Unpin,
+) -> anyhow::Result<()> {
+    let requests: Result<Vec<serde_json::Value>, _> = serde_json::from_str(buf);
+
+    let reply = match requests {
+        Ok(requests) => {
+            let mut replies = Vec::with_capacity(requests.len());
+            for request in requests {
+                let reply = match serde_json::from_value(request) {
+                    Ok(Request::EventStream | Request::FilteredEventStream { .. }) => {
+                        Err(String::from("event stream cannot be requested in a batch"))
+                    }
+                    Ok(request) => process_logged(ctx, request).await,
+                    Err(err) => {
+                        let err = anyhow::Error::new(err).context("error parsing request");
+                        warn!("error processing IPC request: {err:?}");
+                        Err(err.to_string())
+                    }
+                };
+                replies.push(reply);
+            }
+            serde_json::to_vec(&replies)
+        }
+        Err(err) => {
+            let err = anyhow::Error::new(err).context("error parsing request batch");
+            warn!("error processing IPC request: {err:?}");
+            let reply: Reply = Err(err.to_string());
+            serde_json::to_vec(&reply)
+        }
+    };
+
+    let mut buf = reply.context("error formatting reply")?;
+    buf.push(b'\n');
+    write.write_all(&buf).await.context("error writing reply")?;
+
+    Ok(())
+}
+
+/// Processes a request, recording metrics and logging errors.
+async fn process_logged(ctx: &ClientCtx, request: Request) -> Reply {
+    #[cfg(feature = "metrics")]
+    {
+        let request = request.clone();
+        ctx.event_loop.insert_idle(move |state| {
+            state.niri.metrics.record_ipc_request(&request);
+        });
+    }
+
+    let requested_error = matches!(request, Request::ReturnError);
+    let reply = process(ctx, request).await;
+
+    if let Err(err) = &reply {
+        if !requested_error {
+            warn!("error processing IPC request: {err:?}");
+        }
+    }
+
+    reply
+}
+
 async fn process(ctx: &ClientCtx, request: Request) -> Reply {
     let response = match request {
         Request::ReturnError => return Err(String::from("example compositor error")),
@@ -270,45 +396,56 @@ async fn process(ctx: &ClientCtx, request: Request) -> Reply {
             Response::Windows(windows)
         }
         Request::Layers => {
+            let state = ctx.event_stream_state.borrow();
+            let layers = state.layers.layers.clone();
+            Response::Layers(layers)
+        }
+        Request::ExplainFocus => {
             let (tx, rx) = async_channel::bounded(1);
             ctx.event_loop.insert_idle(move |state| {
-                let mut layers = Vec::new();
-                for output in state.niri.global_space.outputs() {
-                    let name = output.name();
-                    for surface in layer_map_for_output(output).layers() {
-                        let layer = match surface.layer() {
-                            Layer::Background => niri_ipc::Layer::Background,
-                            Layer::Bottom => niri_ipc::Layer::Bottom,
-                            Layer::Top => niri_ipc::Layer::Top,
-                            Layer::Overlay => niri_ipc::Layer::Overlay,
-                        };
-                        let keyboard_interactivity =
-                            match surface.cached_state().keyboard_interactivity {
-                                KeyboardInteractivity::None => {
-                                    niri_ipc::LayerSurfaceKeyboardInteractivity::None
-                                }
-                                KeyboardInteractivity::Exclusive => {
-                                    niri_ipc::LayerSurfaceKeyboardInteractivity::Exclusive
-                                }
-                                KeyboardInteractivity::OnDemand => {
-                                    niri_ipc::LayerSurfaceKeyboardInteractivity::OnDemand
-                                }
-                            };
-
-                        layers.push(niri_ipc::LayerSurface {
-                            namespace: surface.namespace().to_owned(),
-                            output: name.clone(),
-                            layer,
-                            keyboard_interactivity,
-                        });
-                    }
-                }
-
-                let _ = tx.send_blocking(layers);
+                let _ = tx.send_blocking(state.niri.explain_focus());
             });
             let result = rx.recv().await;
-            let layers = result.map_err(|_| String::from("error getting layers info"))?;
-            Response::Layers(layers)
+            let explanation = result.map_err(|_| String::from("error getting focus info"))?;
+            Response::FocusExplanation(explanation)
+        }
+        Request::BindCooldowns => {
+            let (tx, rx) = async_channel::bounded(1);
+            ctx.event_loop.insert_idle(move |state| {
+                let comp_mod = state.backend.mod_key();
+                let _ = tx.send_blocking(state.niri.bind_cooldowns(comp_mod));
+            });
+            let result = rx.recv().await;
+            let cooldowns = result.map_err(|_| String::from("error getting bind cooldowns"))?;
+            Response::BindCooldowns(cooldowns)
+        }
+        Request::ClipboardHistory => {
+            let (tx, rx) = async_channel::bounded(1);
+            ctx.event_loop.insert_idle(move |state| {
+                let entries = state.niri.clipboard_history.entries();
+                let _ = tx.send_blocking(entries.map(|entry| entry.to_ipc()).collect());
+            });
+            let result = rx.recv().await;
+            let entries = result.map_err(|_| String::from("error getting clipboard history"))?;
+            Response::ClipboardHistory(entries)
+        }
+        Request::Protocols => {
+            let (tx, rx) = async_channel::bounded(1);
+            ctx.event_loop.insert_idle(move |state| {
+                let is_tty = matches!(state.backend, Backend::Tty(_));
+                let _ = tx.send_blocking(is_tty);
+            });
+            let result = rx.recv().await;
+            let is_tty = result.map_err(|_| String::from("error getting backend info"))?;
+
+            let protocols = protocol_globals(is_tty)
+                .map(|global| niri_ipc::WaylandProtocol {
+                    interface: global.interface.to_owned(),
+                    version: global.version,
+                    privileged: global.privileged,
+                })
+                .collect();
+            Response::Protocols(protocols)
         }
         Request::KeyboardLayouts => {
             let state = ctx.event_stream_state.borrow();
@@ -323,6 +460,8 @@ async fn process(ctx: &ClientCtx, request: Request) -> Reply {
             Response::FocusedWindow(window)
         }
         Request::Action(action) => {
+            validate_action(&action)?;
+
             let (tx, rx) = async_channel::bounded(1);
 
             let action = niri_config::Action::from(action);
@@ -330,6 +469,7 @@ async fn process(ctx: &ClientCtx, request: Request) -> Reply {
                 // Make sure some logic like workspace clean-up has a chance to run before doing
                 // actions.
                 state.niri.advance_animations();
+                state.niri.focus_history.set_reason(FocusReason::Ipc);
                 state.do_action(action, false);
                 let _ = tx.send_blocking(());
             });
@@ -340,7 +480,73 @@ async fn process(ctx: &ClientCtx, request: Request) -> Reply {
             let _ = rx.recv().await;
             Response::Handled
         }
+        Request::Actions(actions) => {
+            for action in &actions {
+                validate_action(action)?;
+            }
+
+            let (tx, rx) = async_channel::bounded(1);
+
+            let actions: Vec<_> = actions.into_iter().map(niri_config::Action::from).collect();
+            ctx.event_loop.insert_idle(move |state| {
+                state.niri.focus_history.set_reason(FocusReason::Ipc);
+                for action in actions {
+                    // Make sure some logic like workspace clean-up has a chance to run before
+                    // every action, as if they were sent separately.
+                    state.niri.advance_animations();
+                    state.do_action(action, false);
+                }
+                let _ = tx.send_blocking(());
+            });
+
+            let _ = rx.recv().await;
+            Response::Handled
+        }
+        Request::BulkAction { filter, action } => {
+            if with_window_id(&action, 0).is_none() {
+                return Err(String::from("the action does not accept a window id"));
+            }
+
+            let filter = ParsedWindowFilter::parse(filter)?;
+
+            let (tx, rx) = async_channel::bounded(1);
+            ctx.event_loop.insert_idle(move |state| {
+                // Make sure some logic like workspace clean-up has a chance to run before doing
+                // actions.
+                state.niri.advance_animations();
+                let result = state.do_bulk_action(&filter, &action);
+                let _ = tx.send_blocking(result);
+            });
+
+            let result = rx.recv().await;
+            let ids = result.map_err(|_| String::from("error performing bulk action"))??;
+            Response::BulkActionApplied(ids)
+        }
+        Request::MatchedAction { filter, action } => {
+            if with_window_id(&action, 0).is_none() {
+                return Err(String::from("the action does not accept a window id"));
+            }
+
+            let filter = ParsedWindowFilter::parse(filter)?;
+
+            let (tx, rx) = async_channel::bounded(1);
+            ctx.event_loop.insert_idle(move |state| {
+                // Make sure some logic like workspace clean-up has a chance to run before doing
+                // actions.
+                state.niri.advance_animations();
+                let result = state.do_matched_action(&filter, &action);
+                let _ = tx.send_blocking(result);
+            });
+
+            let result = rx.recv().await;
+            let id = result.map_err(|_| String::from("error performing action"))??;
+            Response::MatchedActionApplied(id)
+        }
         Request::Output { output, action } => {
+            if let niri_ipc::OutputAction::Brightness { percent } = action {
+                percent.validate().map_err(String::from)?;
+            }
+
             let ipc_outputs = ctx.ipc_outputs.lock().unwrap();
             let found = ipc_outputs
                 .values()
@@ -358,6 +564,44 @@ async fn process(ctx: &ClientCtx, request: Request) -> Reply {
 
             Response::OutputConfigChanged(response)
         }
+        Request::CreateVirtualOutput {
+            width,
+            height,
+            scale,
+            workspaces,
+        } => {
+            if width == 0 || height == 0 {
+                return Err(String::from("virtual output size must be positive"));
+            }
+            if scale.is_some_and(|scale| !(scale.is_finite() && scale > 0.)) {
+                return Err(String::from("virtual output scale must be positive"));
+            }
+
+            let (tx, rx) = async_channel::bounded(1);
+            ctx.event_loop.insert_idle(move |state| {
+                let name = state.create_virtual_output(width, height, scale, workspaces);
+                let _ = tx.send_blocking(name);
+            });
+            let result = rx.recv().await;
+            let name = result.map_err(|_| String::from("error creating virtual output"))?;
+            let name = name
+                .ok_or_else(|| String::from("virtual outputs are not supported on this backend"))?;
+            Response::VirtualOutputCreated(name)
+        }
+        Request::RemoveVirtualOutput { output } => {
+            let (tx, rx) = async_channel::bounded(1);
+            let name = output.clone();
+            ctx.event_loop.insert_idle(move |state| {
+                let removed = state.backend.remove_virtual_output(&mut state.niri, &name);
+                let _ = tx.send_blocking(removed);
+            });
+            let result = rx.recv().await;
+            let removed = result.map_err(|_| String::from("error removing virtual output"))?;
+            if !removed {
+                return Err(format!("no virtual output named {output}"));
+            }
+            Response::Handled
+        }
         Request::FocusedOutput => {
             let (tx, rx) = async_channel::bounded(1);
             ctx.event_loop.insert_idle(move |state| {
@@ -384,7 +628,7 @@ async fn process(ctx: &ClientCtx, request: Request) -> Reply {
             let output = result.map_err(|_| String::from("error getting active output info"))?;
             Response::FocusedOutput(output)
         }
-        Request::EventStream => Response::Handled,
+        Request::EventStream | Request::FilteredEventStream { .. } => Response::Handled,
     };
 
     Ok(response)
@@ -417,7 +661,11 @@ async fn handle_event_stream_client(client: EventStreamClient) -> anyhow::Result
     Ok(())
 }
 
-fn make_ipc_window(mapped: &Mapped, workspace_id: Option<WorkspaceId>) -> niri_ipc::Window {
+fn make_ipc_window(
+    mapped: &Mapped,
+    workspace_id: Option<WorkspaceId>,
+    layout: WindowLayout,
+) -> niri_ipc::Window {
     with_toplevel_role(mapped.toplevel(), |role| niri_ipc::Window {
         id: mapped.id().get(),
         title: role.title.clone(),
@@ -426,10 +674,205 @@ fn make_ipc_window(mapped: &Mapped, workspace_id: Option<WorkspaceId>) -> niri_i
         workspace_id: workspace_id.map(|id| id.get()),
         is_focused: mapped.is_focused(),
         is_floating: mapped.is_floating(),
+        is_urgent: mapped.is_urgent(),
+        urgent_since_ms: mapped.urgent_since().map(|t| t.as_millis() as u64),
+        marks: mapped.marks().to_vec(),
+        layout,
     })
 }
 
+/// Returns the icon configured for the workspace with this name.
+fn workspace_icon<'a>(config: &'a Config, name: Option<&String>) -> Option<&'a str> {
+    let name = name?;
+    let ws_config = config
+        .workspaces
+        .iter()
+        .find(|ws| ws.name.0.eq_ignore_ascii_case(name))?;
+    ws_config.icon.as_deref()
+}
+
+fn make_ipc_window_layout(mapped: &Mapped, pos: Option<(f64, f64)>) -> WindowLayout {
+    let size = mapped.size();
+    WindowLayout {
+        pos,
+        window_size: (size.w, size.h),
+    }
+}
+
+/// Checks action arguments that can't be checked when parsing.
+fn validate_action(action: &Action) -> Result<(), String> {
+    if let Action::SetOutputBrightness { change, .. } = action {
+        change.validate().map_err(String::from)?;
+    }
+
+    Ok(())
+}
+
+/// Returns the action targeting the window with the given id.
+///
+/// Returns `None` if the action cannot target a specific window.
+fn with_window_id(action: &Action, window_id: u64) -> Option<Action> {
+    let id = Some(window_id);
+    let action = match action.clone() {
+        Action::CloseWindow { .. } => Action::CloseWindow { id },
+        Action::FullscreenWindow { .. } => Action::FullscreenWindow { id },
+        Action::FocusWindow { .. } => Action::FocusWindow { id: window_id },
+        Action::ConsumeOrExpelWindowLeft { .. } => Action::ConsumeOrExpelWindowLeft { id },
+        Action::ConsumeOrExpelWindowRight { .. } => Action::ConsumeOrExpelWindowRight { id },
+        Action::CenterWindow { .. } => Action::CenterWindow { id },
+        Action::MoveWindowToWorkspace {
+            reference, focus, ..
+        } => Action::MoveWindowToWorkspace {
+            window_id: id,
+            reference,
+            focus,
+        },
+        Action::MoveWindowToWorkspaceOnOutput {
+            output,
+            index,
+            focus,
+            ..
+        } => Action::MoveWindowToWorkspaceOnOutput {
+            window_id: id,
+            output,
+            index,
+            focus,
+        },
+        Action::SetWindowWidth { change, .. } => Action::SetWindowWidth { id, change },
+        Action::SetWindowHeight { change, .. } => Action::SetWindowHeight { id, change },
+        Action::ResetWindowHeight { .. } => Action::ResetWindowHeight { id },
+        Action::SwitchPresetWindowWidth { .. } => Action::SwitchPresetWindowWidth { id },
+        Action::SwitchPresetWindowHeight { .. } => Action::SwitchPresetWindowHeight { id },
+        Action::ToggleWindowFloating { .. } => Action::ToggleWindowFloating { id },
+        Action::MoveWindowToFloating { .. } => Action::MoveWindowToFloating { id },
+        Action::MoveWindowToTiling { .. } => Action::MoveWindowToTiling { id },
+        Action::ToggleWindowSelected { .. } => Action::ToggleWindowSelected { id },
+        Action::MoveFloatingWindow { x, y, .. } => Action::MoveFloatingWindow { id, x, y },
+        Action::SnapFloatingWindow { position, .. } => Action::SnapFloatingWindow { id, position },
+        Action::CyclePictureInPictureCorner { .. } => Action::CyclePictureInPictureCorner { id },
+        Action::BringWindowOnScreen { .. } => Action::BringWindowOnScreen { id },
+        Action::ToggleWindowRuleOpacity { .. } => Action::ToggleWindowRuleOpacity { id },
+        Action::SetWindowMark { mark, .. } => Action::SetWindowMark { id, mark },
+        Action::UnsetWindowMark { mark, .. } => Action::UnsetWindowMark { id, mark },
+        _ => return None,
+    };
+    Some(action)
+}
+
+/// [`WindowFilter`] with its regexes parsed.
+struct ParsedWindowFilter {
+    app_id: Option<RegexEq>,
+    title: Option<RegexEq>,
+    mark: Option<RegexEq>,
+    workspace: Option<WorkspaceReferenceArg>,
+}
+
+impl ParsedWindowFilter {
+    fn parse(filter: WindowFilter) -> Result<Self, String> {
+        let parse_regex = |re: &Option<String>, what: &str| {
+            re.as_deref()
+                .map(|re| re.parse::<RegexEq>())
+                .transpose()
+                .map_err(|err| format!("invalid {what} regex: {err}"))
+        };
+
+        Ok(Self {
+            app_id: parse_regex(&filter.app_id, "app-id")?,
+            title: parse_regex(&filter.title, "title")?,
+            mark: parse_regex(&filter.mark, "mark")?,
+            workspace: filter.workspace,
+        })
+    }
+}
+
 impl State {
+    /// Performs the action on every window matching the filter.
+    ///
+    /// Returns the ids of the matching windows.
+    fn do_bulk_action(
+        &mut self,
+        filter: &ParsedWindowFilter,
+        action: &Action,
+    ) -> Result<Vec<u64>, String> {
+        let ids: Vec<_> = self
+            .matching_windows(filter)?
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+
+        for &id in &ids {
+            // The action was checked to accept a window id before getting here.
+            let action = with_window_id(action, id).unwrap();
+            self.niri.focus_history.set_reason(FocusReason::Ipc);
+            self.do_action(niri_config::Action::from(action), false);
+        }
+
+        Ok(ids)
+    }
+
+    /// Performs the action on the most recently focused window matching the filter.
+    ///
+    /// Returns the id of that window.
+    fn do_matched_action(
+        &mut self,
+        filter: &ParsedWindowFilter,
+        action: &Action,
+    ) -> Result<u64, String> {
+        let (id, _) = self
+            .matching_windows(filter)?
+            .into_iter()
+            .max_by_key(|(_, focus_timestamp)| *focus_timestamp)
+            .ok_or_else(|| String::from("no window matches the filter"))?;
+
+        // The action was checked to accept a window id before getting here.
+        let action = with_window_id(action, id).unwrap();
+        self.niri.focus_history.set_reason(FocusReason::Ipc);
+        self.do_action(niri_config::Action::from(action), false);
+
+        Ok(id)
+    }
+
+    /// Returns the ids of the windows matching the filter, along with their focus timestamps.
+    fn matching_windows(
+        &self,
+        filter: &ParsedWindowFilter,
+    ) -> Result<Vec<(u64, Option<Duration>)>, String> {
+        let workspace_id = match &filter.workspace {
+            Some(reference) => {
+                let reference = WorkspaceReference::from(reference.clone());
+                let ws = self.niri.layout.find_workspace_by_ref(reference);
+                let ws = ws.ok_or_else(|| String::from("workspace not found"))?;
+                Some(ws.id())
+            }
+            None => None,
+        };
+
+        let matches = |re: &Option<RegexEq>, value: &Option<String>| match re {
+            Some(re) => value.as_deref().is_some_and(|value| re.0.is_match(value)),
+            None => true,
+        };
+
+        let mut windows = Vec::new();
+        self.niri.layout.with_windows(|mapped, _, ws_id| {
+            if workspace_id.is_some() && ws_id != workspace_id {
+                return;
+            }
+
+            let is_match = with_toplevel_role(mapped.toplevel(), |role| {
+                matches(&filter.app_id, &role.app_id) && matches(&filter.title, &role.title)
+            });
+            let is_match = is_match
+                && filter.mark.as_ref().map_or(true, |re| {
+                    mapped.marks().iter().any(|mark| re.0.is_match(mark))
+                });
+            if is_match {
+                windows.push((mapped.id().get(), mapped.focus_timestamp()));
+            }
+        });
+
+        Ok(windows)
+    }
+
     pub fn ipc_keyboard_layouts_changed(&mut self) {
         let keyboard = self.niri.seat.get_keyboard().unwrap();
         let keyboard_layouts = keyboard.with_xkb_state(self, |context| {
@@ -478,9 +921,227 @@ impl State {
         server.send_event(event);
     }
 
+    pub fn ipc_refresh_outputs(&mut self, ipc_outputs: &IpcOutputMap) {
+        let Some(server) = &self.niri.ipc_server else {
+            return;
+        };
+
+        let _span = tracy_client::span!("State::ipc_refresh_outputs");
+
+        let mut state = server.event_stream_state.borrow_mut();
+        let state = &mut state.outputs;
+
+        let outputs: HashMap<_, _> = ipc_outputs
+            .values()
+            .map(|output| (output.name.clone(), output.clone()))
+            .collect();
+
+        // Changes to already connected outputs can only be signaled with the full output map.
+        let need_outputs_changed = outputs.iter().any(|(name, output)| {
+            state
+                .outputs
+                .get(name)
+                .is_some_and(|ipc_output| ipc_output != output)
+        });
+
+        let mut events = Vec::new();
+        if need_outputs_changed {
+            events.push(Event::OutputsChanged { outputs });
+        } else {
+            for name in state.outputs.keys() {
+                if !outputs.contains_key(name) {
+                    let name = name.clone();
+                    events.push(Event::OutputDisconnected { name });
+                }
+            }
+
+            for (name, output) in outputs {
+                if !state.outputs.contains_key(&name) {
+                    events.push(Event::OutputConnected { output });
+                }
+            }
+        }
+
+        for event in events {
+            state.apply(event.clone());
+            server.send_event(event);
+        }
+    }
+
+    pub fn ipc_workspace_auto_closed(&mut self, id: WorkspaceId, name: String) {
+        let Some(server) = &self.niri.ipc_server else {
+            return;
+        };
+
+        let id = id.get();
+        server.send_event(Event::WorkspaceAutoClosed { id, name });
+    }
+
+    pub fn ipc_refresh_ui(&mut self) {
+        let Some(server) = &self.niri.ipc_server else {
+            return;
+        };
+
+        let mut state = server.event_stream_state.borrow_mut();
+        let state = &mut state.ui;
+
+        let mut events = Vec::new();
+
+        let is_open = self.niri.screenshot_ui.is_open();
+        if state.is_screenshot_ui_open != is_open {
+            events.push(Event::ScreenshotUiOpenedOrClosed { is_open });
+        }
+
+        let is_open = self
+            .niri
+            .exit_confirm_dialog
+            .as_ref()
+            .is_some_and(|d| d.is_open());
+        if state.is_exit_confirm_dialog_open != is_open {
+            events.push(Event::ExitConfirmDialogOpenedOrClosed { is_open });
+        }
+
+        for event in events {
+            state.apply(event.clone());
+            server.send_event(event);
+        }
+    }
+
+    pub fn ipc_refresh_layers(&mut self) {
+        if !self.niri.ipc_layers_changed {
+            return;
+        }
+        self.niri.ipc_layers_changed = false;
+
+        let Some(server) = &self.niri.ipc_server else {
+            return;
+        };
+
+        let mut state = server.event_stream_state.borrow_mut();
+        let state = &mut state.layers;
+
+        let mut layers = Vec::new();
+        for output in self.niri.global_space.outputs() {
+            let name = output.name();
+            for surface in layer_map_for_output(output).layers() {
+                let layer = match surface.layer() {
+                    Layer::Background => niri_ipc::Layer::Background,
+                    Layer::Bottom => niri_ipc::Layer::Bottom,
+                    Layer::Top => niri_ipc::Layer::Top,
+                    Layer::Overlay => niri_ipc::Layer::Overlay,
+                };
+                let keyboard_interactivity = match surface.cached_state().keyboard_interactivity {
+                    KeyboardInteractivity::None => {
+                        niri_ipc::LayerSurfaceKeyboardInteractivity::None
+                    }
+                    KeyboardInteractivity::Exclusive => {
+                        niri_ipc::LayerSurfaceKeyboardInteractivity::Exclusive
+                    }
+                    KeyboardInteractivity::OnDemand => {
+                        niri_ipc::LayerSurfaceKeyboardInteractivity::OnDemand
+                    }
+                };
+
+                layers.push(niri_ipc::LayerSurface {
+                    namespace: surface.namespace().to_owned(),
+                    output: name.clone(),
+                    layer,
+                    keyboard_interactivity,
+                });
+            }
+        }
+
+        if state.layers == layers {
+            return;
+        }
+
+        let event = Event::LayersChanged { layers };
+        state.apply(event.clone());
+        server.send_event(event);
+    }
+
     pub fn ipc_refresh_layout(&mut self) {
         self.ipc_refresh_workspaces();
         self.ipc_refresh_windows();
+        self.ipc_refresh_gestures();
+    }
+
+    fn ipc_refresh_gestures(&mut self) {
+        let Some(server) = &self.niri.ipc_server else {
+            return;
+        };
+
+        let layout = &self.niri.layout;
+        let mut sent = server.gesture_progress_sent.borrow_mut();
+
+        let mut events = Vec::new();
+        let mut progress_changes = Vec::new();
+        let mut seen = HashSet::new();
+        for output in layout.outputs() {
+            let Some(mon) = layout.monitor_for_output(output) else {
+                continue;
+            };
+            let Some(progress) = mon.workspace_switch_gesture_progress() else {
+                continue;
+            };
+
+            let name = mon.output_name();
+            seen.insert(name.clone());
+            if sent.get(name) != Some(&progress) {
+                progress_changes.push((name.clone(), progress));
+            }
+        }
+
+        // Ended gestures are sent right away.
+        sent.retain(|name, _| {
+            if seen.contains(name) {
+                return true;
+            }
+
+            events.push(Event::WorkspaceSwitchGestureChanged {
+                output: name.clone(),
+                progress: None,
+            });
+            false
+        });
+
+        // Throttle progress changes. They are only recorded as sent when they are sent, so a
+        // later refresh picks them up; queue one in case nothing else triggers it.
+        if !progress_changes.is_empty() {
+            let now = get_monotonic_time();
+            let throttled = server
+                .last_gesture_progress_sent
+                .get()
+                .is_some_and(|last| now < last + GESTURE_PROGRESS_THROTTLE);
+
+            if !throttled {
+                server.last_gesture_progress_sent.set(Some(now));
+                for (output, progress) in progress_changes {
+                    sent.insert(output.clone(), progress);
+                    events.push(Event::WorkspaceSwitchGestureChanged {
+                        output,
+                        progress: Some(progress),
+                    });
+                }
+            } else if !server.gesture_progress_timer_queued.replace(true) {
+                let timer = Timer::from_duration(GESTURE_PROGRESS_THROTTLE);
+                self.niri
+                    .event_loop
+                    .insert_source(timer, |_, _, state| {
+                        if let Some(server) = &state.niri.ipc_server {
+                            server.gesture_progress_timer_queued.set(false);
+                        }
+                        state.ipc_refresh_gestures();
+                        TimeoutAction::Drop
+                    })
+                    .unwrap();
+            }
+        }
+
+        drop(sent);
+        for event in events {
+            server.send_event(event);
+        }
     }
 
     fn ipc_refresh_workspaces(&mut self) {
@@ -495,6 +1156,7 @@ impl State {
 
         let mut events = Vec::new();
         let layout = &self.niri.layout;
+        let config = self.niri.config.borrow();
         let focused_ws_id = layout.active_workspace().map(|ws| ws.id().get());
 
         // Check for workspace changes.
@@ -514,6 +1176,8 @@ impl State {
             let output_name = mon.map(|mon| mon.output_name());
             if ipc_ws.idx != u8::try_from(ws_idx + 1).unwrap_or(u8::MAX)
                 || ipc_ws.name.as_ref() != ws.name()
+                || ipc_ws.auto_name.as_ref() != ws.auto_name()
+                || ipc_ws.icon.as_deref() != workspace_icon(&config, ws.name())
                 || ipc_ws.output.as_ref() != output_name
             {
                 need_workspaces_changed = true;
@@ -558,6 +1222,8 @@ impl State {
                         id,
                         idx: u8::try_from(ws_idx + 1).unwrap_or(u8::MAX),
                         name: ws.name().cloned(),
+                        auto_name: ws.auto_name().cloned(),
+                        icon: workspace_icon(&config, ws.name()).map(String::from),
                         output: mon.map(|mon| mon.output_name().clone()),
                         is_active: mon.is_some_and(|mon| mon.active_workspace_idx() == ws_idx),
                         is_focused: Some(id) == focused_ws_id,
@@ -588,9 +1254,27 @@ impl State {
         let mut events = Vec::new();
         let layout = &self.niri.layout;
 
+        // Find the global positions of windows visible on outputs.
+        let mut positions = HashMap::new();
+        layout.with_visible_windows(|mapped, output, pos| {
+            let Some(output_geo) = self.niri.global_space.output_geometry(output) else {
+                return;
+            };
+
+            // Skip windows scrolled out of view.
+            let rect = Rectangle::new(pos, mapped.size().to_f64());
+            if !rect.overlaps(Rectangle::from_size(output_geo.size.to_f64())) {
+                return;
+            }
+
+            let pos = pos + output_geo.loc.to_f64();
+            positions.insert(mapped.id().get(), (pos.x, pos.y));
+        });
+
         // Check for window changes.
         let mut seen = HashSet::new();
         let mut focused_id = None;
+        let mut layout_changes = Vec::new();
         layout.with_windows(|mapped, _, ws_id| {
             let id = mapped.id().get();
             seen.insert(id);
@@ -599,22 +1283,26 @@ impl State {
                 focused_id = Some(id);
             }
 
+            let window_layout = make_ipc_window_layout(mapped, positions.get(&id).copied());
+
             let Some(ipc_win) = state.windows.get(&id) else {
-                let window = make_ipc_window(mapped, ws_id);
+                let window = make_ipc_window(mapped, ws_id, window_layout);
                 events.push(Event::WindowOpenedOrChanged { window });
                 return;
             };
 
             let workspace_id = ws_id.map(|id| id.get());
-            let mut changed =
-                ipc_win.workspace_id != workspace_id || ipc_win.is_floating != mapped.is_floating();
+            let mut changed = ipc_win.workspace_id != workspace_id
+                || ipc_win.is_floating != mapped.is_floating()
+                || ipc_win.is_urgent != mapped.is_urgent()
+                || ipc_win.marks != mapped.marks();
 
             changed |= with_toplevel_role(mapped.toplevel(), |role| {
                 ipc_win.title != role.title || ipc_win.app_id != role.app_id
             });
 
             if changed {
-                let window = make_ipc_window(mapped, ws_id);
+                let window = make_ipc_window(mapped, ws_id, window_layout);
                 events.push(Event::WindowOpenedOrChanged { window });
                 return;
             }
@@ -622,8 +1310,95 @@ impl State {
             if mapped.is_focused() && !ipc_win.is_focused {
                 events.push(Event::WindowFocusChanged { id: Some(id) });
             }
+
+            if ipc_win.layout != window_layout {
+                layout_changes.push((id, window_layout));
+            }
         });
 
+        // Throttle layout changes during animations. The state isn't updated for throttled
+        // changes, so they will be sent on a later refresh; queue one in case nothing else
+        // triggers it.
+        if !layout_changes.is_empty() {
+            let now = get_monotonic_time();
+            let throttled = layout.are_animations_ongoing(None)
+                && server
+                    .last_window_layouts_sent
+                    .get()
+                    .is_some_and(|last| now < last + WINDOW_LAYOUTS_THROTTLE);
+
+            if !throttled {
+                server.last_window_layouts_sent.set(Some(now));
+                events.push(Event::WindowLayoutsChanged {
+                    changes: layout_changes,
+                });
+            } else if !server.window_layouts_timer_queued.replace(true) {
+                let timer = Timer::from_duration(WINDOW_LAYOUTS_THROTTLE);
+                self.niri
+                    .event_loop
+                    .insert_source(timer, |_, _, state| {
+                        if let Some(server) = &state.niri.ipc_server {
+                            server.window_layouts_timer_queued.set(false);
+                        }
+                        state.ipc_refresh_windows();
+                        TimeoutAction::Drop
+                    })
+                    .unwrap();
+            }
+        }
+
+        // Remind about windows that stay urgent.
+        let interval = self.niri.config.borrow().urgency.reminder_interval_ms;
+        let interval = interval
+            .filter(|ms| *ms > 0)
+            .map(|ms| Duration::from_millis(u64::from(ms)));
+        let mut reminders_sent = server.urgency_reminders_sent.borrow_mut();
+        let mut urgent_ids = HashSet::new();
+        let mut next_reminder: Option<Duration> = None;
+        if let Some(interval) = interval {
+            let now = get_monotonic_time();
+            layout.with_windows(|mapped, _, _| {
+                let Some(since) = mapped.urgent_since() else {
+                    return;
+                };
+                let id = mapped.id().get();
+                urgent_ids.insert(id);
+
+                let urgent_for = now.saturating_sub(since);
+                let due = (urgent_for.as_millis() / interval.as_millis()) as u32;
+                let sent = reminders_sent.entry(id).or_insert(0);
+                if *sent < due {
+                    *sent = due;
+                    events.push(Event::WindowUrgencyReminder {
+                        id,
+                        urgent_for_ms: urgent_for.as_millis() as u64,
+                    });
+                }
+
+                let next = (interval * (*sent + 1)).saturating_sub(urgent_for);
+                next_reminder = Some(next_reminder.map_or(next, |n| n.min(next)));
+            });
+        }
+        // Windows that become urgent again start counting from scratch.
+        reminders_sent.retain(|id, _| urgent_ids.contains(id));
+        drop(reminders_sent);
+
+        if let Some(next) = next_reminder {
+            if !server.urgency_timer_queued.replace(true) {
+                let timer = Timer::from_duration(next);
+                self.niri
+                    .event_loop
+                    .insert_source(timer, |_, _, state| {
+                        if let Some(server) = &state.niri.ipc_server {
+                            server.urgency_timer_queued.set(false);
+                        }
+                        state.ipc_refresh_windows();
+                        TimeoutAction::Drop
+                    })
+                    .unwrap();
+            }
+        }
+
         // Check for closed windows.
         let mut ipc_focused_id = None;
         for (id, ipc_win) in &state.windows {
@@ -648,3 +1423,57 @@ impl State {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sender(subscribe: Option<Vec<EventClass>>) -> EventStreamSender {
+        EventStreamSender {
+            events: async_channel::bounded(1).0,
+            disconnect: async_channel::bounded(1).0,
+            subscribe,
+        }
+    }
+
+    #[test]
+    fn parse_event_stream_requests() {
+        let request: Request = serde_json::from_str(r#""EventStream""#).unwrap();
+        assert!(matches!(request, Request::EventStream));
+        assert_eq!(
+            serde_json::to_string(&Request::EventStream).unwrap(),
+            r#""EventStream""#
+        );
+
+        let request: Request =
+            serde_json::from_str(r#"{"FilteredEventStream":{"subscribe":["keyboard-layouts"]}}"#)
+                .unwrap();
+        let Request::FilteredEventStream { subscribe } = request else {
+            panic!("wrong request: {request:?}");
+        };
+        assert_eq!(subscribe, [EventClass::KeyboardLayouts]);
+    }
+
+    #[test]
+    fn event_stream_filtering() {
+        let window = Event::WindowClosed { id: 1 };
+        let layout = Event::KeyboardLayoutSwitched { idx: 0 };
+        let workspace = Event::WorkspaceActivated {
+            id: 1,
+            focused: true,
+        };
+
+        let all = sender(None);
+        assert!(all.wants(&window));
+        assert!(all.wants(&layout));
+        assert!(all.wants(&workspace));
+
+        let some = sender(Some(vec![EventClass::Windows, EventClass::KeyboardLayouts]));
+        assert!(some.wants(&window));
+        assert!(some.wants(&layout));
+        assert!(!some.wants(&workspace));
+
+        let none = sender(Some(vec![]));
+        assert!(!none.wants(&window));
+    }
+}
@@ -19,6 +19,9 @@ pub struct ResolvedLayerRules {
 
     /// Corner radius to assume this layer surface has.
     pub geometry_corner_radius: Option<CornerRadius>,
+
+    /// Whether pointer input should pass through this layer surface to whatever is below.
+    pub click_through: bool,
 }
 
 impl ResolvedLayerRules {
@@ -37,6 +40,7 @@ impl ResolvedLayerRules {
                 inactive_color: None,
             },
             geometry_corner_radius: None,
+            click_through: false,
         }
     }
 
@@ -73,6 +77,9 @@ impl ResolvedLayerRules {
             if let Some(x) = rule.geometry_corner_radius {
                 resolved.geometry_corner_radius = Some(x);
             }
+            if let Some(x) = rule.click_through {
+                resolved.click_through = x;
+            }
 
             resolved.shadow.merge_with(&rule.shadow);
         }
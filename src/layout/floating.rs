@@ -2,12 +2,13 @@ use std::cmp::max;
 use std::iter::zip;
 use std::rc::Rc;
 
-use niri_config::{PresetSize, RelativeTo};
-use niri_ipc::{PositionChange, SizeChange};
+use niri_config::{CornerRadius, PresetSize, RelativeTo};
+use niri_ipc::{PositionChange, SizeChange, SnapPosition};
 use smithay::backend::renderer::gles::GlesRenderer;
 use smithay::utils::{Logical, Point, Rectangle, Scale, Serial, Size};
 
 use super::closing_window::{ClosingWindow, ClosingWindowRenderElement};
+use super::insert_hint_element::{InsertHintElement, InsertHintRenderElement};
 use super::scrolling::ColumnWidth;
 use super::tile::{Tile, TileRenderElement, TileRenderSnapshot};
 use super::workspace::{InteractiveResize, ResolvedSize};
@@ -51,6 +52,12 @@ pub struct FloatingSpace<W: LayoutElement> {
     /// Windows in the closing animation.
     closing_windows: Vec<ClosingWindow>,
 
+    /// Preview of where the interactively moved window will snap.
+    snap_hint: Option<SnapHint>,
+
+    /// Insert hint element for rendering the snap hint.
+    snap_hint_element: InsertHintElement,
+
     /// View size for this space.
     view_size: Size<f64, Logical>,
 
@@ -71,9 +78,16 @@ niri_render_elements! {
     FloatingSpaceRenderElement<R> => {
         Tile = TileRenderElement<R>,
         ClosingWindow = ClosingWindowRenderElement,
+        SnapHint = InsertHintRenderElement,
     }
 }
 
+#[derive(Debug)]
+pub struct SnapHint {
+    pub position: SnapPosition,
+    pub corner_radius: CornerRadius,
+}
+
 /// Extra per-tile data.
 #[derive(Debug, Clone, Copy, PartialEq)]
 struct Data {
@@ -90,11 +104,15 @@ struct Data {
 
     /// Working area used for conversions.
     working_area: Rectangle<f64, Logical>,
+
+    /// How much of the tile must remain within the working area.
+    min_visible: Option<f64>,
 }
 
 impl Data {
     pub fn new<W: LayoutElement>(
         working_area: Rectangle<f64, Logical>,
+        min_visible: Option<f64>,
         tile: &Tile<W>,
         logical_pos: Point<f64, Logical>,
     ) -> Self {
@@ -103,6 +121,7 @@ impl Data {
             logical_pos: Point::default(),
             size: Size::default(),
             working_area,
+            min_visible,
         };
         rv.update(tile);
         rv.set_logical_pos(logical_pos);
@@ -134,9 +153,14 @@ impl Data {
     fn recompute_logical_pos(&mut self) {
         let mut logical_pos = Self::scale_by_working_area(self.working_area, self.pos);
 
-        // Make sure the window doesn't go too much off-screen. Numbers taken from Mutter.
-        let min_on_screen_hor = f64::clamp(self.size.w / 4., 10., 75.);
-        let min_on_screen_ver = f64::clamp(self.size.h / 4., 10., 75.);
+        // Make sure the window doesn't go too much off-screen. Default numbers taken from Mutter.
+        let (min_on_screen_hor, min_on_screen_ver) = match self.min_visible {
+            Some(min) => (f64::min(min, self.size.w), f64::min(min, self.size.h)),
+            None => (
+                f64::clamp(self.size.w / 4., 10., 75.),
+                f64::clamp(self.size.h / 4., 10., 75.),
+            ),
+        };
         let max_off_screen_hor = f64::max(0., self.size.w - min_on_screen_hor);
         let max_off_screen_ver = f64::max(0., self.size.h - min_on_screen_ver);
 
@@ -156,12 +180,17 @@ impl Data {
         self.logical_pos = logical_pos;
     }
 
-    pub fn update_config(&mut self, working_area: Rectangle<f64, Logical>) {
-        if self.working_area == working_area {
+    pub fn update_config(
+        &mut self,
+        working_area: Rectangle<f64, Logical>,
+        min_visible: Option<f64>,
+    ) {
+        if self.working_area == working_area && self.min_visible == min_visible {
             return;
         }
 
         self.working_area = working_area;
+        self.min_visible = min_visible;
         self.recompute_logical_pos();
     }
 
@@ -211,6 +240,8 @@ impl<W: LayoutElement> FloatingSpace<W> {
             active_window_id: None,
             interactive_resize: None,
             closing_windows: Vec::new(),
+            snap_hint: None,
+            snap_hint_element: InsertHintElement::new(options.insert_hint),
             view_size,
             working_area,
             scale,
@@ -229,19 +260,27 @@ impl<W: LayoutElement> FloatingSpace<W> {
         for (tile, data) in zip(&mut self.tiles, &mut self.data) {
             tile.update_config(view_size, scale, options.clone());
             data.update(tile);
-            data.update_config(working_area);
+            data.update_config(working_area, options.floating_min_visible);
         }
 
+        self.snap_hint_element.update_config(options.insert_hint);
+
         self.view_size = view_size;
         self.working_area = working_area;
         self.scale = scale;
         self.options = options;
+
+        for idx in 0..self.tiles.len() {
+            self.anchor_picture_in_picture(idx);
+        }
     }
 
     pub fn update_shaders(&mut self) {
         for tile in &mut self.tiles {
             tile.update_shaders();
         }
+
+        self.snap_hint_element.update_shaders();
     }
 
     pub fn advance_animations(&mut self) {
@@ -269,6 +308,17 @@ impl<W: LayoutElement> FloatingSpace<W> {
             tile_view_rect.loc -= offset + tile.render_offset();
             tile.update_render_elements(is_active, tile_view_rect);
         }
+
+        if let Some(snap_hint) = &self.snap_hint {
+            let area = self.snap_area(snap_hint.position);
+            let view_rect = Rectangle::new(view_rect.loc - area.loc, view_rect.size);
+            self.snap_hint_element.update_render_elements(
+                area.size,
+                view_rect,
+                snap_hint.corner_radius,
+                self.scale,
+            );
+        }
     }
 
     pub fn tiles(&self) -> impl Iterator<Item = &Tile<W>> + '_ {
@@ -391,6 +441,7 @@ impl<W: LayoutElement> FloatingSpace<W> {
         // Restore the previous floating window size, and in case the tile is fullscreen,
         // unfullscreen it.
         let floating_size = tile.floating_window_size;
+        let min_size = tile.window_min_size();
         let win = tile.window_mut();
         let mut size = if win.is_pending_fullscreen() {
             // If the window was fullscreen without a floating size, ask for (0, 0).
@@ -404,7 +455,6 @@ impl<W: LayoutElement> FloatingSpace<W> {
 
         // Apply min/max size window rules. If requesting a concrete size, apply completely; if
         // requesting (0, 0), apply only when min/max results in a fixed size.
-        let min_size = win.min_size();
         let max_size = win.max_size();
         size.w = ensure_min_max_size_maybe_zero(size.w, min_size.w, max_size.w);
         size.h = ensure_min_max_size_maybe_zero(size.h, min_size.h, max_size.h);
@@ -423,11 +473,21 @@ impl<W: LayoutElement> FloatingSpace<W> {
             }
         }
 
-        let pos = self.stored_or_default_tile_pos(&tile).unwrap_or_else(|| {
+        let mut pos = self.stored_or_default_tile_pos(&tile).unwrap_or_else(|| {
             center_preferring_top_left_in_area(self.working_area, tile.tile_size())
         });
+        if tile.is_picture_in_picture() {
+            let size = tile.tile_size();
+            let corner = self.picture_in_picture_corner(pos, size);
+            pos = self.picture_in_picture_pos(corner, size);
+        }
 
-        let data = Data::new(self.working_area, &tile, pos);
+        let data = Data::new(
+            self.working_area,
+            self.options.floating_min_visible,
+            &tile,
+            pos,
+        );
         self.data.insert(idx, data);
         self.tiles.insert(idx, tile);
 
@@ -713,8 +773,8 @@ impl<W: LayoutElement> FloatingSpace<W> {
         };
         let win_width = win_width.round().clamp(1., MAX_PX) as i32;
 
+        let min_size = tile.window_min_size();
         let win = tile.window_mut();
-        let min_size = win.min_size();
         let max_size = win.max_size();
 
         let win_width = ensure_min_max_size(win_width, min_size.w, max_size.w);
@@ -760,8 +820,8 @@ impl<W: LayoutElement> FloatingSpace<W> {
         };
         let win_height = win_height.round().clamp(1., MAX_PX) as i32;
 
+        let min_size = tile.window_min_size();
         let win = tile.window_mut();
-        let min_size = win.min_size();
         let max_size = win.max_size();
 
         let win_height = ensure_min_max_size(win_height, min_size.h, max_size.h);
@@ -833,7 +893,14 @@ impl<W: LayoutElement> FloatingSpace<W> {
         }
     }
 
-    fn move_to(&mut self, idx: usize, new_pos: Point<f64, Logical>, animate: bool) {
+    fn move_to(&mut self, idx: usize, mut new_pos: Point<f64, Logical>, animate: bool) {
+        // Picture-in-picture windows always go to the closest corner.
+        if self.tiles[idx].is_picture_in_picture() {
+            let size = self.data[idx].size;
+            let corner = self.picture_in_picture_corner(new_pos, size);
+            new_pos = self.picture_in_picture_pos(corner, size);
+        }
+
         if animate {
             self.move_and_animate(idx, new_pos);
         } else {
@@ -904,6 +971,161 @@ impl<W: LayoutElement> FloatingSpace<W> {
         self.move_to(idx, new_pos, true);
     }
 
+    /// Moves the window so that it is fully within the working area, as far as it fits.
+    pub fn bring_window_on_screen(&mut self, id: Option<&W::Id>) {
+        let Some(id) = id.or(self.active_window_id.as_ref()).cloned() else {
+            return;
+        };
+        let idx = self.idx_of(&id).unwrap();
+
+        let data = &self.data[idx];
+        let new_pos = self.clamp_within_working_area(data.logical_pos, data.size);
+        if new_pos == data.logical_pos {
+            return;
+        }
+
+        self.move_to(idx, new_pos, true);
+    }
+
+    /// Resizes and moves the window to occupy a half or a quarter of the working area.
+    pub fn snap_window(&mut self, id: Option<&W::Id>, position: SnapPosition, animate: bool) {
+        let Some(id) = id.or(self.active_window_id.as_ref()).cloned() else {
+            return;
+        };
+        let idx = self.idx_of(&id).unwrap();
+
+        let area = self.snap_area(position);
+
+        let tile = &mut self.tiles[idx];
+        tile.floating_preset_width_idx = None;
+        tile.floating_preset_height_idx = None;
+
+        let win_width = tile.window_width_for_tile_width(area.size.w);
+        let win_height = tile.window_height_for_tile_height(area.size.h);
+        let win_width = win_width.round().max(1.) as i32;
+        let win_height = win_height.round().max(1.) as i32;
+
+        let min_size = tile.window_min_size();
+        let win = tile.window_mut();
+        let max_size = win.max_size();
+        let win_width = ensure_min_max_size(win_width, min_size.w, max_size.w);
+        let win_height = ensure_min_max_size(win_height, min_size.h, max_size.h);
+
+        let win_size = Size::from((win_width, win_height));
+        win.request_size_once(win_size, animate);
+
+        self.move_to(idx, area.loc, animate);
+    }
+
+    /// Returns the part of the working area that a window snapped to `position` occupies.
+    pub fn snap_area(&self, position: SnapPosition) -> Rectangle<f64, Logical> {
+        let mut area = self.working_area;
+        let half = area.size.downscale(2.);
+
+        match position {
+            SnapPosition::Left | SnapPosition::Right => area.size.w = half.w,
+            SnapPosition::Top | SnapPosition::Bottom => area.size.h = half.h,
+            _ => area.size = half,
+        }
+
+        if matches!(
+            position,
+            SnapPosition::Right | SnapPosition::TopRight | SnapPosition::BottomRight
+        ) {
+            area.loc.x += half.w;
+        }
+        if matches!(
+            position,
+            SnapPosition::Bottom | SnapPosition::BottomLeft | SnapPosition::BottomRight
+        ) {
+            area.loc.y += half.h;
+        }
+
+        area
+    }
+
+    /// Moves a picture-in-picture window to the next corner of the working area clockwise.
+    pub fn cycle_picture_in_picture_corner(&mut self, id: Option<&W::Id>) {
+        let Some(id) = id.or(self.active_window_id.as_ref()).cloned() else {
+            return;
+        };
+        let idx = self.idx_of(&id).unwrap();
+
+        if !self.tiles[idx].is_picture_in_picture() {
+            return;
+        }
+
+        let data = &self.data[idx];
+        let corner = match self.picture_in_picture_corner(data.logical_pos, data.size) {
+            RelativeTo::TopLeft => RelativeTo::TopRight,
+            RelativeTo::TopRight => RelativeTo::BottomRight,
+            RelativeTo::BottomRight => RelativeTo::BottomLeft,
+            RelativeTo::BottomLeft => RelativeTo::TopLeft,
+        };
+        let new_pos = self.picture_in_picture_pos(corner, data.size);
+        self.move_to(idx, new_pos, true);
+    }
+
+    /// Returns the working area corner closest to a tile at `pos`.
+    fn picture_in_picture_corner(
+        &self,
+        pos: Point<f64, Logical>,
+        size: Size<f64, Logical>,
+    ) -> RelativeTo {
+        let area = self.working_area;
+        let center = pos + size.downscale(2.).to_point();
+        let area_center = area.loc + area.size.downscale(2.).to_point();
+
+        match (center.x < area_center.x, center.y < area_center.y) {
+            (true, true) => RelativeTo::TopLeft,
+            (false, true) => RelativeTo::TopRight,
+            (true, false) => RelativeTo::BottomLeft,
+            (false, false) => RelativeTo::BottomRight,
+        }
+    }
+
+    /// Returns the position of a picture-in-picture tile anchored to a working area corner.
+    fn picture_in_picture_pos(
+        &self,
+        corner: RelativeTo,
+        size: Size<f64, Logical>,
+    ) -> Point<f64, Logical> {
+        let area = self.working_area;
+        let gap = self.options.gaps;
+
+        let mut pos = area.loc + Point::from((gap, gap));
+        if corner == RelativeTo::TopRight || corner == RelativeTo::BottomRight {
+            pos.x = area.loc.x + area.size.w - size.w - gap;
+        }
+        if corner == RelativeTo::BottomLeft || corner == RelativeTo::BottomRight {
+            pos.y = area.loc.y + area.size.h - size.h - gap;
+        }
+        pos
+    }
+
+    /// Keeps a picture-in-picture tile in its corner as its size or the working area changes.
+    fn anchor_picture_in_picture(&mut self, idx: usize) {
+        if !self.tiles[idx].is_picture_in_picture() {
+            return;
+        }
+
+        let data = &self.data[idx];
+        let corner = self.picture_in_picture_corner(data.logical_pos, data.size);
+        let pos = self.picture_in_picture_pos(corner, data.size);
+        self.data[idx].set_logical_pos(pos);
+    }
+
+    pub fn set_snap_hint(&mut self, snap_hint: SnapHint) {
+        if self.options.insert_hint.off {
+            return;
+        }
+        self.snap_hint = Some(snap_hint);
+    }
+
+    pub fn clear_snap_hint(&mut self) {
+        self.snap_hint = None;
+    }
+
     pub fn descendants_added(&mut self, id: &W::Id) -> bool {
         let Some(idx) = self.idx_of(id) else {
             return false;
@@ -945,6 +1167,8 @@ impl<W: LayoutElement> FloatingSpace<W> {
             data.set_logical_pos(data.logical_pos + offset);
         }
 
+        self.anchor_picture_in_picture(tile_idx);
+
         true
     }
 
@@ -955,19 +1179,39 @@ impl<W: LayoutElement> FloatingSpace<W> {
         scale: Scale<f64>,
         target: RenderTarget,
         focus_ring: bool,
+        only_picture_in_picture: bool,
     ) -> Vec<FloatingSpaceRenderElement<R>> {
         let mut rv = Vec::new();
 
-        // Draw the closing windows on top of the other windows.
-        //
-        // FIXME: I guess this should rather preserve the stacking order when the window is closed.
-        for closing in self.closing_windows.iter().rev() {
-            let elem = closing.render(renderer.as_gles_renderer(), view_rect, scale, target);
-            rv.push(elem.into());
+        // When floating windows are hidden behind a fullscreen window, only the
+        // picture-in-picture ones are drawn on top of it.
+        if !only_picture_in_picture {
+            // Draw the snap hint.
+            if let Some(snap_hint) = &self.snap_hint {
+                let area = self.snap_area(snap_hint.position);
+                rv.extend(
+                    self.snap_hint_element
+                        .render(renderer, area.loc)
+                        .map(FloatingSpaceRenderElement::SnapHint),
+                );
+            }
+
+            // Draw the closing windows on top of the other windows.
+            //
+            // FIXME: I guess this should rather preserve the stacking order when the window is
+            // closed.
+            for closing in self.closing_windows.iter().rev() {
+                let elem = closing.render(renderer.as_gles_renderer(), view_rect, scale, target);
+                rv.push(elem.into());
+            }
         }
 
         let active = self.active_window_id.clone();
         for (tile, tile_pos) in self.tiles_with_render_positions() {
+            if only_picture_in_picture && !tile.is_picture_in_picture() {
+                continue;
+            }
+
             // For the active tile, draw the focus ring.
             let focus_ring = focus_ring && Some(tile.window().id()) == active.as_ref();
 
@@ -1063,6 +1307,7 @@ impl<W: LayoutElement> FloatingSpace<W> {
 
             win.set_active_in_column(true);
             win.set_floating(true);
+            win.set_maximized(false);
 
             let is_active = is_active && Some(win.id()) == active.as_ref();
             win.set_activated(is_active);
@@ -33,16 +33,19 @@
 
 use std::cmp::min;
 use std::collections::HashMap;
+use std::iter::zip;
 use std::mem;
 use std::rc::Rc;
 use std::time::Duration;
 
+use floating::SnapHint;
 use monitor::MonitorAddWindowTarget;
 use niri_config::{
-    CenterFocusedColumn, Config, CornerRadius, FloatOrInt, PresetSize, Struts,
-    Workspace as WorkspaceConfig, WorkspaceReference,
+    CenterFocusedColumn, Config, CornerRadius, FloatOrInt, NewColumnPosition,
+    NewWindowPositionInColumn, PresetSize, RevealNewWindows, Struts, Workspace as WorkspaceConfig,
+    WorkspaceReference, WorkspaceSpillover,
 };
-use niri_ipc::{PositionChange, SizeChange};
+use niri_ipc::{PositionChange, SizeChange, SnapPosition};
 use scrolling::{Column, ColumnWidth, InsertHint, InsertPosition};
 use smithay::backend::renderer::element::surface::WaylandSurfaceRenderElement;
 use smithay::backend::renderer::element::Id;
@@ -55,10 +58,12 @@ use workspace::{WorkspaceAddWindowTarget, WorkspaceId};
 
 pub use self::monitor::MonitorRenderElement;
 use self::monitor::{Monitor, WorkspaceSwitch};
+use self::startup_placeholder::{StartupApp, StartupPlaceholder, StartupPlaceholderId};
 use self::workspace::{OutputId, Workspace};
 use crate::animation::Clock;
 use crate::layout::scrolling::ScrollDirection;
 use crate::niri_render_elements;
+use crate::render_helpers::primary_gpu_texture::PrimaryGpuTextureRenderElement;
 use crate::render_helpers::renderer::NiriRenderer;
 use crate::render_helpers::snapshot::RenderSnapshot;
 use crate::render_helpers::solid_color::{SolidColorBuffer, SolidColorRenderElement};
@@ -80,7 +85,9 @@ pub mod monitor;
 pub mod opening_window;
 pub mod scrolling;
 pub mod shadow;
+pub mod startup_placeholder;
 pub mod tile;
+pub mod titlebar;
 pub mod workspace;
 
 #[cfg(test)]
@@ -92,6 +99,12 @@ pub const RESIZE_ANIMATION_THRESHOLD: f64 = 10.;
 /// Pointer needs to move this far to pull a window from the layout.
 const INTERACTIVE_MOVE_START_THRESHOLD: f64 = 256. * 256.;
 
+/// Pointer needs to be this close to an output edge to snap a floating window being moved.
+const SNAP_EDGE_THRESHOLD: f64 = 8.;
+
+/// Fraction of the output edge length near each end that snaps to a quarter instead of a half.
+const SNAP_CORNER_FRACTION: f64 = 0.25;
+
 /// Size-relative units.
 pub struct SizeFrac;
 
@@ -99,6 +112,8 @@ niri_render_elements! {
     LayoutElementRenderElement<R> => {
         Wayland = WaylandSurfaceRenderElement<R>,
         SolidColor = SolidColorRenderElement,
+        // Placeholder contents of a crashed window.
+        Texture = PrimaryGpuTextureRenderElement,
     }
 }
 
@@ -187,6 +202,7 @@ pub trait LayoutElement {
     fn max_size(&self) -> Size<i32, Logical>;
     fn is_wl_surface(&self, wl_surface: &WlSurface) -> bool;
     fn has_ssd(&self) -> bool;
+    fn title(&self) -> Option<String>;
     fn set_preferred_scale_transform(&self, scale: output::Scale, transform: Transform);
     fn output_enter(&self, output: &Output);
     fn output_leave(&self, output: &Output);
@@ -194,8 +210,11 @@ pub trait LayoutElement {
     fn set_activated(&mut self, active: bool);
     fn set_active_in_column(&mut self, active: bool);
     fn set_floating(&mut self, floating: bool);
+    fn set_maximized(&mut self, maximized: bool);
     fn set_bounds(&self, bounds: Size<i32, Logical>);
     fn is_ignoring_opacity_window_rule(&self) -> bool;
+    /// Whether the window is selected for a batch action and should be marked as such.
+    fn is_selected(&self) -> bool;
 
     fn configure_intent(&self) -> ConfigureIntent;
     fn send_pending_configure(&mut self);
@@ -277,12 +296,22 @@ pub struct Layout<W: LayoutElement> {
     last_active_workspace_id: HashMap<String, WorkspaceId>,
     /// Ongoing interactive move.
     interactive_move: Option<InteractiveMoveState<W>>,
+    /// Window that was focused before the layout gave up keyboard focus.
+    ///
+    /// This is used to restore the focus exactly once a transient layer-shell surface (e.g. an app
+    /// launcher) goes away, even if the layout focus moved in the meantime.
+    ///
+    /// The window id does not necessarily point to a valid window. If it doesn't, then it is
+    /// simply ignored.
+    saved_focus: Option<W::Id>,
     /// Clock for driving animations.
     clock: Clock,
     /// Time that we last updated render elements for.
     update_render_elements_time: Duration,
     /// Configurable properties of the layout.
     options: Rc<Options>,
+    /// Height reserved at the top of the working area for the built-in status bar.
+    status_bar_height: f64,
 }
 
 #[derive(Debug)]
@@ -316,12 +345,24 @@ pub struct Options {
     pub center_focused_column: CenterFocusedColumn,
     pub always_center_single_column: bool,
     pub empty_workspace_above_first: bool,
+    pub new_column_position: NewColumnPosition,
+    pub new_window_position_in_column: NewWindowPositionInColumn,
+    /// Whether the view moves to show windows that open unfocused.
+    pub reveal_new_windows: RevealNewWindows,
     /// Column or window widths that `toggle_width()` switches between.
     pub preset_column_widths: Vec<PresetSize>,
     /// Initial width for new columns.
     pub default_column_width: Option<PresetSize>,
     /// Window height that `toggle_window_height()` switches between.
     pub preset_window_heights: Vec<PresetSize>,
+    /// Where the workspaces of a disconnected output go.
+    pub workspace_spillover: WorkspaceSpillover,
+    /// How much of a floating window must remain within the working area, in logical pixels.
+    ///
+    /// `None` uses a fraction of the window size.
+    pub floating_min_visible: Option<f64>,
+    /// Minimum window size, unless overridden by window rules.
+    pub min_window_size: niri_config::MinWindowSize,
     pub animations: niri_config::Animations,
     // Debug flags.
     pub disable_resize_throttling: bool,
@@ -340,6 +381,9 @@ impl Default for Options {
             center_focused_column: Default::default(),
             always_center_single_column: false,
             empty_workspace_above_first: false,
+            new_column_position: Default::default(),
+            new_window_position_in_column: Default::default(),
+            reveal_new_windows: Default::default(),
             preset_column_widths: vec![
                 PresetSize::Proportion(1. / 3.),
                 PresetSize::Proportion(0.5),
@@ -354,6 +398,9 @@ impl Default for Options {
                 PresetSize::Proportion(0.5),
                 PresetSize::Proportion(2. / 3.),
             ],
+            workspace_spillover: Default::default(),
+            floating_min_visible: None,
+            min_window_size: Default::default(),
         }
     }
 }
@@ -473,6 +520,51 @@ impl<W: LayoutElement> InteractiveMoveData<W> {
         // Round to physical pixels.
         pos.to_physical_precise_round(scale).to_logical(scale)
     }
+
+    /// Returns where the floating window will snap if dropped at the current pointer position.
+    fn snap_position(&self) -> Option<SnapPosition> {
+        // Picture-in-picture windows go to the closest corner instead.
+        if !self.is_floating || self.tile.is_picture_in_picture() {
+            return None;
+        }
+
+        let size = output_size(&self.output);
+        let pos = self.pointer_pos_within_output;
+
+        let near_left = pos.x < SNAP_EDGE_THRESHOLD;
+        let near_right = size.w - SNAP_EDGE_THRESHOLD <= pos.x;
+        let near_top = pos.y < SNAP_EDGE_THRESHOLD;
+        let near_bottom = size.h - SNAP_EDGE_THRESHOLD <= pos.y;
+
+        let in_left_corner = pos.x < size.w * SNAP_CORNER_FRACTION;
+        let in_right_corner = size.w * (1. - SNAP_CORNER_FRACTION) <= pos.x;
+        let in_top_corner = pos.y < size.h * SNAP_CORNER_FRACTION;
+        let in_bottom_corner = size.h * (1. - SNAP_CORNER_FRACTION) <= pos.y;
+
+        let position = if near_left || near_right {
+            match (near_left, in_top_corner, in_bottom_corner) {
+                (true, true, _) => SnapPosition::TopLeft,
+                (true, _, true) => SnapPosition::BottomLeft,
+                (true, _, _) => SnapPosition::Left,
+                (false, true, _) => SnapPosition::TopRight,
+                (false, _, true) => SnapPosition::BottomRight,
+                (false, _, _) => SnapPosition::Right,
+            }
+        } else if near_top || near_bottom {
+            match (near_top, in_left_corner, in_right_corner) {
+                (true, true, _) => SnapPosition::TopLeft,
+                (true, _, true) => SnapPosition::TopRight,
+                (true, _, _) => SnapPosition::Top,
+                (false, true, _) => SnapPosition::BottomLeft,
+                (false, _, true) => SnapPosition::BottomRight,
+                (false, _, _) => SnapPosition::Bottom,
+            }
+        } else {
+            return None;
+        };
+
+        Some(position)
+    }
 }
 
 impl ActivateWindow {
@@ -518,12 +610,18 @@ impl Options {
             center_focused_column: layout.center_focused_column,
             always_center_single_column: layout.always_center_single_column,
             empty_workspace_above_first: layout.empty_workspace_above_first,
+            new_column_position: layout.new_column_position,
+            new_window_position_in_column: layout.new_window_position_in_column,
+            reveal_new_windows: layout.reveal_new_windows,
             preset_column_widths,
             default_column_width,
             animations: config.animations.clone(),
             disable_resize_throttling: config.debug.disable_resize_throttling,
             disable_transactions: config.debug.disable_transactions,
             preset_window_heights,
+            workspace_spillover: layout.workspace_spillover.clone(),
+            floating_min_visible: layout.floating_min_visible.map(|x| x.0),
+            min_window_size: layout.min_window_size,
         }
     }
 
@@ -549,9 +647,11 @@ impl<W: LayoutElement> Layout<W> {
             is_active: true,
             last_active_workspace_id: HashMap::new(),
             interactive_move: None,
+            saved_focus: None,
             clock,
             update_render_elements_time: Duration::ZERO,
             options: Rc::new(options),
+            status_bar_height: 0.,
         }
     }
 
@@ -571,9 +671,11 @@ impl<W: LayoutElement> Layout<W> {
             is_active: true,
             last_active_workspace_id: HashMap::new(),
             interactive_move: None,
+            saved_focus: None,
             clock,
             update_render_elements_time: Duration::ZERO,
             options: opts,
+            status_bar_height: 0.,
         }
     }
 
@@ -584,75 +686,72 @@ impl<W: LayoutElement> Layout<W> {
                 primary_idx,
                 active_monitor_idx,
             } => {
-                let primary = &mut monitors[primary_idx];
-
                 let ws_id_to_activate = self.last_active_workspace_id.remove(&output.name());
-                let mut active_workspace_idx = None;
-
-                let mut stopped_primary_ws_switch = false;
 
+                // Take back this output's workspaces from wherever they spilled over to.
                 let mut workspaces = vec![];
-                for i in (0..primary.workspaces.len()).rev() {
-                    if primary.workspaces[i].original_output.matches(&output) {
-                        let ws = primary.workspaces.remove(i);
-
-                        // FIXME: this can be coded in a way that the workspace switch won't be
-                        // affected if the removed workspace is invisible. But this is good enough
-                        // for now.
-                        if primary.workspace_switch.is_some() {
-                            primary.workspace_switch = None;
-                            stopped_primary_ws_switch = true;
-                        }
-
-                        // The user could've closed a window while remaining on this workspace, on
-                        // another monitor. However, we will add an empty workspace in the end
-                        // instead.
-                        if ws.has_windows_or_name() {
-                            if Some(ws.id()) == ws_id_to_activate {
-                                active_workspace_idx = Some(workspaces.len());
+                for mon in &mut monitors {
+                    let mut stopped_ws_switch = false;
+
+                    let mut mon_workspaces = vec![];
+                    for i in (0..mon.workspaces.len()).rev() {
+                        if mon.workspaces[i].original_output.matches(&output) {
+                            let ws = mon.workspaces.remove(i);
+
+                            // FIXME: this can be coded in a way that the workspace switch won't be
+                            // affected if the removed workspace is invisible. But this is good
+                            // enough for now.
+                            if mon.workspace_switch.is_some() {
+                                mon.workspace_switch = None;
+                                stopped_ws_switch = true;
                             }
 
-                            workspaces.push(ws);
-                        }
+                            // The user could've closed a window while remaining on this workspace,
+                            // on another monitor. However, we will add an empty workspace in the
+                            // end instead.
+                            if ws.has_windows_or_name() {
+                                mon_workspaces.push(ws);
+                            }
 
-                        if i <= primary.active_workspace_idx
-                            // Generally when moving the currently active workspace, we want to
-                            // fall back to the workspace above, so as not to end up on the last
-                            // empty workspace. However, with empty workspace above first, when
-                            // moving the workspace at index 1 (first non-empty), we want to stay
-                            // at index 1, so as once again not to end up on an empty workspace.
-                            //
-                            // This comes into play at compositor startup when having named
-                            // workspaces set up across multiple monitors. Without this check, the
-                            // first monitor to connect can end up with the first empty workspace
-                            // focused instead of the first named workspace.
-                            && !(self.options.empty_workspace_above_first
-                                && primary.active_workspace_idx == 1)
-                        {
-                            primary.active_workspace_idx =
-                                primary.active_workspace_idx.saturating_sub(1);
+                            if i <= mon.active_workspace_idx
+                                // Generally when moving the currently active workspace, we want to
+                                // fall back to the workspace above, so as not to end up on the
+                                // last empty workspace. However, with empty workspace above first,
+                                // when moving the workspace at index 1 (first non-empty), we want
+                                // to stay at index 1, so as once again not to end up on an empty
+                                // workspace.
+                                //
+                                // This comes into play at compositor startup when having named
+                                // workspaces set up across multiple monitors. Without this check,
+                                // the first monitor to connect can end up with the first empty
+                                // workspace focused instead of the first named workspace.
+                                && !(self.options.empty_workspace_above_first
+                                    && mon.active_workspace_idx == 1)
+                            {
+                                mon.active_workspace_idx =
+                                    mon.active_workspace_idx.saturating_sub(1);
+                            }
                         }
                     }
-                }
 
-                // If we stopped a workspace switch, then we might need to clean up workspaces.
-                // Also if empty_workspace_above_first is set and there are only 2 workspaces left,
-                // both will be empty and one of them needs to be removed. clean_up_workspaces
-                // takes care of this.
+                    // If we stopped a workspace switch, then we might need to clean up workspaces.
+                    // Also if empty_workspace_above_first is set and there are only 2 workspaces
+                    // left, both will be empty and one of them needs to be removed.
+                    // clean_up_workspaces takes care of this.
+                    if stopped_ws_switch
+                        || (mon.options.empty_workspace_above_first && mon.workspaces.len() == 2)
+                    {
+                        mon.clean_up_workspaces();
+                    }
 
-                if stopped_primary_ws_switch
-                    || (primary.options.empty_workspace_above_first
-                        && primary.workspaces.len() == 2)
-                {
-                    primary.clean_up_workspaces();
+                    mon_workspaces.reverse();
+                    workspaces.extend(mon_workspaces);
                 }
 
-                workspaces.reverse();
-
-                if let Some(idx) = &mut active_workspace_idx {
-                    *idx = workspaces.len() - *idx - 1;
-                }
-                let mut active_workspace_idx = active_workspace_idx.unwrap_or(0);
+                let mut active_workspace_idx = workspaces
+                    .iter()
+                    .position(|ws| Some(ws.id()) == ws_id_to_activate)
+                    .unwrap_or(0);
 
                 // Make sure there's always an empty workspace.
                 workspaces.push(Workspace::new(
@@ -767,43 +866,28 @@ impl<W: LayoutElement> Layout<W> {
                         active_monitor_idx = active_monitor_idx.saturating_sub(1);
                     }
 
-                    let primary = &mut monitors[primary_idx];
-                    for ws in &mut workspaces {
-                        ws.set_output(Some(primary.output.clone()));
-                    }
-
-                    let mut stopped_primary_ws_switch = false;
-                    if !workspaces.is_empty() && primary.workspace_switch.is_some() {
-                        // FIXME: if we're adding workspaces to currently invisible positions
-                        // (outside the workspace switch), we don't need to cancel it.
-                        primary.workspace_switch = None;
-                        stopped_primary_ws_switch = true;
-                    }
-
-                    let empty_was_focused =
-                        primary.active_workspace_idx == primary.workspaces.len() - 1;
-
-                    // Push the workspaces from the removed monitor in the end, right before the
-                    // last, empty, workspace.
-                    let empty = primary.workspaces.remove(primary.workspaces.len() - 1);
-                    primary.workspaces.extend(workspaces);
-                    primary.workspaces.push(empty);
-
-                    // If empty_workspace_above_first is set and the first workspace is now no
-                    // longer empty, add a new empty workspace on top.
-                    if primary.options.empty_workspace_above_first
-                        && primary.workspaces[0].has_windows_or_name()
-                    {
-                        primary.add_workspace_top();
-                    }
-
-                    // If the empty workspace was focused on the primary monitor, keep it focused.
-                    if empty_was_focused {
-                        primary.active_workspace_idx = primary.workspaces.len() - 1;
+                    // Decide which monitor each of the workspaces goes to.
+                    let mut targets: Vec<Vec<_>> = monitors.iter().map(|_| Vec::new()).collect();
+                    match &self.options.workspace_spillover {
+                        WorkspaceSpillover::Primary => targets[primary_idx] = workspaces,
+                        WorkspaceSpillover::Output(name) => {
+                            let idx = monitors
+                                .iter()
+                                .position(|mon| output_matches_name(&mon.output, name))
+                                .unwrap_or(primary_idx);
+                            targets[idx] = workspaces;
+                        }
+                        WorkspaceSpillover::Distribute => {
+                            // Go round-robin starting from the primary monitor.
+                            let count = monitors.len();
+                            for (i, ws) in workspaces.into_iter().enumerate() {
+                                targets[(primary_idx + i) % count].push(ws);
+                            }
+                        }
                     }
 
-                    if stopped_primary_ws_switch {
-                        primary.clean_up_workspaces();
+                    for (mon, workspaces) in zip(&mut monitors, targets) {
+                        mon.append_workspaces(workspaces);
                     }
 
                     MonitorSet::Normal {
@@ -937,16 +1021,17 @@ impl<W: LayoutElement> Layout<W> {
                     *active_monitor_idx = mon_idx;
                 }
 
-                // Set the default height for scrolling windows.
+                // Set the default height for scrolling windows, and show them if needed.
                 if !is_floating {
+                    let ws = mon
+                        .workspaces
+                        .iter_mut()
+                        .find(|ws| ws.has_window(&id))
+                        .unwrap();
                     if let Some(change) = scrolling_height {
-                        let ws = mon
-                            .workspaces
-                            .iter_mut()
-                            .find(|ws| ws.has_window(&id))
-                            .unwrap();
                         ws.set_window_height(Some(&id), change);
                     }
+                    ws.reveal_new_window(&id);
                 }
 
                 Some(&mon.output)
@@ -961,12 +1046,12 @@ impl<W: LayoutElement> Layout<W> {
                             ));
                         }
 
-                        (0, WorkspaceAddWindowTarget::Auto)
+                        (0, WorkspaceAddWindowTarget::NewWindow)
                     }
                     AddWindowTarget::Output(_) => panic!(),
                     AddWindowTarget::Workspace(ws_id) => {
                         let ws_idx = workspaces.iter().position(|ws| ws.id() == ws_id).unwrap();
-                        (ws_idx, WorkspaceAddWindowTarget::Auto)
+                        (ws_idx, WorkspaceAddWindowTarget::NewWindow)
                     }
                     AddWindowTarget::NextTo(next_to) => {
                         if self
@@ -983,7 +1068,7 @@ impl<W: LayoutElement> Layout<W> {
                             .is_some()
                         {
                             // The next_to window is being interactively moved.
-                            (0, WorkspaceAddWindowTarget::Auto)
+                            (0, WorkspaceAddWindowTarget::NewWindow)
                         } else {
                             let ws_idx = workspaces
                                 .iter()
@@ -1005,11 +1090,12 @@ impl<W: LayoutElement> Layout<W> {
                     is_floating,
                 );
 
-                // Set the default height for scrolling windows.
+                // Set the default height for scrolling windows, and show them if needed.
                 if !is_floating {
                     if let Some(change) = scrolling_height {
                         ws.set_window_height(Some(&id), change);
                     }
+                    ws.reveal_new_window(&id);
                 }
 
                 None
@@ -1583,6 +1669,24 @@ impl<W: LayoutElement> Layout<W> {
         }
     }
 
+    /// Calls `f` for every window visible on an output, with its render position on that output.
+    pub fn with_visible_windows(&self, mut f: impl FnMut(&W, &Output, Point<f64, Logical>)) {
+        if let Some(InteractiveMoveState::Moving(move_)) = &self.interactive_move {
+            let pos = move_.tile_render_location() + move_.tile.window_loc();
+            f(move_.tile.window(), &move_.output, pos);
+        }
+
+        let MonitorSet::Normal { monitors, .. } = &self.monitor_set else {
+            return;
+        };
+
+        for mon in monitors {
+            for (win, pos) in mon.windows_with_render_positions() {
+                f(win, &mon.output, pos);
+            }
+        }
+    }
+
     pub fn with_windows_mut(&mut self, mut f: impl FnMut(&mut W, Option<&Output>)) {
         if let Some(InteractiveMoveState::Moving(move_)) = &mut self.interactive_move {
             f(move_.tile.window_mut(), Some(&move_.output));
@@ -1709,7 +1813,7 @@ impl<W: LayoutElement> Layout<W> {
             }
         }
 
-        self.move_column_to_output(output);
+        self.move_column_to_output(output, None, true);
         true
     }
 
@@ -1720,7 +1824,7 @@ impl<W: LayoutElement> Layout<W> {
             }
         }
 
-        self.move_column_to_output(output);
+        self.move_column_to_output(output, None, true);
         true
     }
 
@@ -1884,6 +1988,16 @@ impl<W: LayoutElement> Layout<W> {
         true
     }
 
+    pub fn focus_previous_in_workspace(&mut self) {
+        let Some(workspace) = self.active_workspace() else {
+            return;
+        };
+        let Some(id) = workspace.previously_active_window().cloned() else {
+            return;
+        };
+        self.activate_window(&id);
+    }
+
     pub fn focus_down(&mut self) {
         let Some(monitor) = self.active_monitor() else {
             return;
@@ -1940,21 +2054,26 @@ impl<W: LayoutElement> Layout<W> {
         monitor.focus_window_or_workspace_up();
     }
 
-    pub fn move_to_workspace_up(&mut self) {
+    pub fn move_to_workspace_up(&mut self, focus: bool) {
         let Some(monitor) = self.active_monitor() else {
             return;
         };
-        monitor.move_to_workspace_up();
+        monitor.move_to_workspace_up(focus);
     }
 
-    pub fn move_to_workspace_down(&mut self) {
+    pub fn move_to_workspace_down(&mut self, focus: bool) {
         let Some(monitor) = self.active_monitor() else {
             return;
         };
-        monitor.move_to_workspace_down();
+        monitor.move_to_workspace_down(focus);
     }
 
-    pub fn move_to_workspace(&mut self, window: Option<&W::Id>, idx: usize) {
+    pub fn move_to_workspace(
+        &mut self,
+        window: Option<&W::Id>,
+        idx: usize,
+        activate: ActivateWindow,
+    ) {
         if let Some(InteractiveMoveState::Moving(move_)) = &mut self.interactive_move {
             if window.is_none() || window == Some(move_.tile.window().id()) {
                 return;
@@ -1977,34 +2096,28 @@ impl<W: LayoutElement> Layout<W> {
             };
             monitor
         };
-        monitor.move_to_workspace(window, idx);
+        monitor.move_to_workspace(window, idx, activate);
     }
 
-    pub fn move_column_to_workspace_up(&mut self) {
+    pub fn move_column_to_workspace_up(&mut self, focus: bool) {
         let Some(monitor) = self.active_monitor() else {
             return;
         };
-        monitor.move_column_to_workspace_up();
+        monitor.move_column_to_workspace_up(focus);
     }
 
-    pub fn move_column_to_workspace_down(&mut self) {
+    pub fn move_column_to_workspace_down(&mut self, focus: bool) {
         let Some(monitor) = self.active_monitor() else {
             return;
         };
-        monitor.move_column_to_workspace_down();
+        monitor.move_column_to_workspace_down(focus);
     }
 
-    pub fn move_column_to_workspace(&mut self, idx: usize) {
+    pub fn move_column_to_workspace(&mut self, idx: usize, focus: bool) {
         let Some(monitor) = self.active_monitor() else {
             return;
         };
-        monitor.move_column_to_workspace(idx);
-    }
-
-    pub fn move_column_to_workspace_on_output(&mut self, output: &Output, idx: usize) {
-        self.move_column_to_output(output);
-        self.focus_output(output);
-        self.move_column_to_workspace(idx);
+        monitor.move_column_to_workspace(idx, focus);
     }
 
     pub fn switch_workspace_up(&mut self) {
@@ -2089,6 +2202,34 @@ impl<W: LayoutElement> Layout<W> {
         workspace.center_window(id);
     }
 
+    /// Remembers the focused window to be restored later with [`Layout::restore_focus()`].
+    pub fn save_focus(&mut self) {
+        self.saved_focus = self.focus().map(|win| win.id().clone());
+    }
+
+    /// Forgets the window remembered with [`Layout::save_focus()`].
+    pub fn clear_saved_focus(&mut self) {
+        self.saved_focus = None;
+    }
+
+    /// Focuses the window remembered with [`Layout::save_focus()`], if it still exists.
+    ///
+    /// Returns whether the focus changed.
+    pub fn restore_focus(&mut self) -> bool {
+        let Some(id) = self.saved_focus.take() else {
+            return false;
+        };
+
+        if self.focus().is_some_and(|win| win.id() == &id) || !self.has_window(&id) {
+            return false;
+        }
+
+        // This activates both the window's column and the window within the column, and switches
+        // to its monitor and workspace.
+        self.activate_window(&id);
+        true
+    }
+
     pub fn focus(&self) -> Option<&W> {
         self.focus_with_output().map(|(win, _out)| win)
     }
@@ -2145,6 +2286,25 @@ impl<W: LayoutElement> Layout<W> {
         mon.window_under(pos_within_output)
     }
 
+    /// Returns the window whose server-side titlebar close button is under the cursor.
+    pub fn titlebar_close_button_under(
+        &self,
+        output: &Output,
+        pos_within_output: Point<f64, Logical>,
+    ) -> Option<&W> {
+        let MonitorSet::Normal { monitors, .. } = &self.monitor_set else {
+            return None;
+        };
+
+        // The interactively moved window is under the cursor, and it's being dragged.
+        if self.interactive_move.is_some() {
+            return None;
+        }
+
+        let mon = monitors.iter().find(|mon| &mon.output == output)?;
+        mon.titlebar_close_button_under(pos_within_output)
+    }
+
     pub fn resize_edges_under(
         &self,
         output: &Output,
@@ -2158,6 +2318,37 @@ impl<W: LayoutElement> Layout<W> {
         mon.resize_edges_under(pos_within_output)
     }
 
+    /// Returns the corner of the window under the cursor that is closest to the cursor.
+    pub fn resize_corner_under(
+        &self,
+        output: &Output,
+        pos_within_output: Point<f64, Logical>,
+    ) -> Option<ResizeEdge> {
+        let MonitorSet::Normal { monitors, .. } = &self.monitor_set else {
+            return None;
+        };
+
+        let mon = monitors.iter().find(|mon| &mon.output == output)?;
+        mon.resize_corner_under(pos_within_output)
+    }
+
+    /// Returns the window just outside of which the position is, along with the nearby edges.
+    ///
+    /// The position must be within `edge_width` of the window edges, but not on the window itself.
+    pub fn resize_edges_near(
+        &self,
+        output: &Output,
+        pos_within_output: Point<f64, Logical>,
+        edge_width: f64,
+    ) -> Option<(&W, ResizeEdge)> {
+        let MonitorSet::Normal { monitors, .. } = &self.monitor_set else {
+            return None;
+        };
+
+        let mon = monitors.iter().find(|mon| &mon.output == output)?;
+        mon.resize_edges_near(pos_within_output, edge_width)
+    }
+
     #[cfg(test)]
     fn verify_invariants(&self) {
         use std::collections::HashSet;
@@ -2277,22 +2468,22 @@ impl<W: LayoutElement> Layout<W> {
                 assert!(after_idx < monitor.workspaces.len());
             }
 
-            if idx == primary_idx {
-                for ws in &monitor.workspaces {
-                    if ws.original_output.matches(&monitor.output) {
-                        // This is the primary monitor's own workspace.
-                        continue;
-                    }
-
-                    let own_monitor_exists = monitors
-                        .iter()
-                        .any(|m| ws.original_output.matches(&m.output));
-                    assert!(
-                        !own_monitor_exists,
-                        "primary monitor cannot have workspaces for which their own monitor exists"
-                    );
+            for ws in &monitor.workspaces {
+                if ws.original_output.matches(&monitor.output) {
+                    // This is the monitor's own workspace.
+                    continue;
                 }
-            } else {
+
+                let own_monitor_exists = monitors
+                    .iter()
+                    .any(|m| ws.original_output.matches(&m.output));
+                assert!(
+                    !own_monitor_exists,
+                    "monitor cannot have workspaces for which their own monitor exists"
+                );
+            }
+
+            if idx != primary_idx {
                 assert!(
                     monitor
                         .workspaces
@@ -2504,6 +2695,7 @@ impl<W: LayoutElement> Layout<W> {
         let _span = tracy_client::span!("Layout::update_insert_hint::clear");
         for ws in self.workspaces_mut() {
             ws.clear_insert_hint();
+            ws.clear_snap_hint();
         }
 
         if !matches!(self.interactive_move, Some(InteractiveMoveState::Moving(_))) {
@@ -2517,8 +2709,9 @@ impl<W: LayoutElement> Layout<W> {
             return;
         }
 
-        // No insert hint when targeting floating.
-        if move_.is_floating {
+        // When targeting floating, only show a hint if the window will snap.
+        let snap_position = move_.snap_position();
+        if move_.is_floating && snap_position.is_none() {
             self.interactive_move = Some(InteractiveMoveState::Moving(move_));
             return;
         }
@@ -2534,8 +2727,6 @@ impl<W: LayoutElement> Layout<W> {
                     .find(|ws| ws.id() == ws_id)
                     .unwrap();
 
-                let position = ws.get_insert_position(move_.pointer_pos_within_output - offset);
-
                 let rules = move_.tile.window().rules();
                 let border_width = move_.tile.effective_border_width().unwrap_or(0.);
                 let corner_radius = rules
@@ -2544,18 +2735,57 @@ impl<W: LayoutElement> Layout<W> {
                         radius.expanded_by(border_width as f32)
                     });
 
-                ws.set_insert_hint(InsertHint {
-                    position,
-                    width: move_.width,
-                    is_full_width: move_.is_full_width,
-                    corner_radius,
-                });
+                if let Some(position) = snap_position {
+                    ws.set_snap_hint(SnapHint {
+                        position,
+                        corner_radius,
+                    });
+                } else {
+                    let position = ws.get_insert_position(move_.pointer_pos_within_output - offset);
+
+                    ws.set_insert_hint(InsertHint {
+                        position,
+                        width: move_.width,
+                        is_full_width: move_.is_full_width,
+                        corner_radius,
+                    });
+                }
             }
         }
 
         self.interactive_move = Some(InteractiveMoveState::Moving(move_));
     }
 
+    /// Shows a placeholder for a spawned application on the active workspace.
+    pub fn add_startup_placeholder(&mut self, id: StartupPlaceholderId, app: StartupApp) {
+        let placeholder = StartupPlaceholder::new(id, app);
+        if let Some(ws) = self.active_workspace_mut() {
+            ws.add_startup_placeholder(placeholder);
+        }
+    }
+
+    pub fn has_startup_placeholder(&self, id: StartupPlaceholderId) -> bool {
+        self.workspaces()
+            .any(|(_, _, ws)| ws.has_startup_placeholder(id))
+    }
+
+    pub fn remove_startup_placeholder(&mut self, id: StartupPlaceholderId) {
+        for ws in self.workspaces_mut() {
+            if ws.remove_startup_placeholder(id) {
+                return;
+            }
+        }
+    }
+
+    /// Removes a placeholder that looks like it belongs to a window with this app ID.
+    pub fn remove_startup_placeholder_for_app_id(&mut self, app_id: &str) {
+        for ws in self.workspaces_mut() {
+            if ws.remove_startup_placeholder_for_app_id(app_id) {
+                return;
+            }
+        }
+    }
+
     pub fn ensure_named_workspace(&mut self, ws_config: &WorkspaceConfig) {
         if self.find_workspace_by_name(&ws_config.name.0).is_some() {
             return;
@@ -2610,7 +2840,21 @@ impl<W: LayoutElement> Layout<W> {
     }
 
     pub fn update_config(&mut self, config: &Config) {
-        self.update_options(Options::from_config(config));
+        let mut options = Options::from_config(config);
+        options.struts.top.0 += self.status_bar_height;
+        self.update_options(options);
+    }
+
+    /// Keeps windows clear of the built-in status bar of this height.
+    pub fn set_status_bar_height(&mut self, height: f64) {
+        if self.status_bar_height == height {
+            return;
+        }
+
+        let mut options = Options::clone(&self.options);
+        options.struts.top.0 += height - self.status_bar_height;
+        self.status_bar_height = height;
+        self.update_options(options);
     }
 
     fn update_options(&mut self, options: Options) {
@@ -2786,6 +3030,7 @@ impl<W: LayoutElement> Layout<W> {
                 // When going to floating, restore the floating window size.
                 if move_.is_floating {
                     let floating_size = move_.tile.floating_window_size;
+                    let min_size = move_.tile.window_min_size();
                     let win = move_.tile.window_mut();
                     let mut size =
                         floating_size.unwrap_or_else(|| win.expected_size().unwrap_or_default());
@@ -2793,7 +3038,6 @@ impl<W: LayoutElement> Layout<W> {
                     // Apply min/max size window rules. If requesting a concrete size, apply
                     // completely; if requesting (0, 0), apply only when min/max results in a fixed
                     // size.
-                    let min_size = win.min_size();
                     let max_size = win.max_size();
                     size.w = ensure_min_max_size_maybe_zero(size.w, min_size.w, max_size.w);
                     size.h = ensure_min_max_size_maybe_zero(size.h, min_size.h, max_size.h);
@@ -2867,6 +3111,63 @@ impl<W: LayoutElement> Layout<W> {
         workspace.switch_focus_floating_tiling();
     }
 
+    pub fn snap_floating_window(&mut self, id: Option<&W::Id>, position: SnapPosition) {
+        if let Some(InteractiveMoveState::Moving(move_)) = &mut self.interactive_move {
+            if id.is_none() || id == Some(move_.tile.window().id()) {
+                return;
+            }
+        }
+
+        let workspace = if let Some(id) = id {
+            Some(self.workspaces_mut().find(|ws| ws.has_window(id)).unwrap())
+        } else {
+            self.active_workspace_mut()
+        };
+
+        let Some(workspace) = workspace else {
+            return;
+        };
+        workspace.snap_floating_window(id, position, true);
+    }
+
+    pub fn cycle_picture_in_picture_corner(&mut self, id: Option<&W::Id>) {
+        if let Some(InteractiveMoveState::Moving(move_)) = &mut self.interactive_move {
+            if id == Some(move_.tile.window().id()) {
+                return;
+            }
+        }
+
+        let workspace = if let Some(id) = id {
+            Some(self.workspaces_mut().find(|ws| ws.has_window(id)).unwrap())
+        } else {
+            self.active_workspace_mut()
+        };
+
+        let Some(workspace) = workspace else {
+            return;
+        };
+        workspace.cycle_picture_in_picture_corner(id);
+    }
+
+    pub fn bring_window_on_screen(&mut self, id: Option<&W::Id>) {
+        if let Some(InteractiveMoveState::Moving(move_)) = &mut self.interactive_move {
+            if id.is_none() || id == Some(move_.tile.window().id()) {
+                return;
+            }
+        }
+
+        let workspace = if let Some(id) = id {
+            Some(self.workspaces_mut().find(|ws| ws.has_window(id)).unwrap())
+        } else {
+            self.active_workspace_mut()
+        };
+
+        let Some(workspace) = workspace else {
+            return;
+        };
+        workspace.bring_window_on_screen(id);
+    }
+
     pub fn move_floating_window(
         &mut self,
         id: Option<&W::Id>,
@@ -2913,6 +3214,7 @@ impl<W: LayoutElement> Layout<W> {
         window: Option<&W::Id>,
         output: &Output,
         target_ws_idx: Option<usize>,
+        activate: ActivateWindow,
     ) {
         if let Some(InteractiveMoveState::Moving(move_)) = &mut self.interactive_move {
             if window.is_none() || window == Some(move_.tile.window().id()) {
@@ -2955,9 +3257,11 @@ impl<W: LayoutElement> Layout<W> {
             let ws_id = monitors[new_idx].workspaces[workspace_idx].id();
 
             let mon = &mut monitors[mon_idx];
-            let activate = window.map_or(true, |win| {
-                mon_idx == *active_monitor_idx
-                    && mon.active_window().map(|win| win.id()) == Some(win)
+            let activate = activate.map_smart(|| {
+                window.map_or(true, |win| {
+                    mon_idx == *active_monitor_idx
+                        && mon.active_window().map(|win| win.id()) == Some(win)
+                })
             });
             let activate = if activate {
                 ActivateWindow::Yes
@@ -3000,7 +3304,46 @@ impl<W: LayoutElement> Layout<W> {
         }
     }
 
-    pub fn move_column_to_output(&mut self, output: &Output) {
+    /// Moves a window to the workspace with this index on the output.
+    ///
+    /// An index past the last workspace targets the empty workspace at the end of the output, so
+    /// the window ends up on a new workspace.
+    pub fn move_to_workspace_on_output(
+        &mut self,
+        window: Option<&W::Id>,
+        output: &Output,
+        idx: usize,
+        activate: ActivateWindow,
+    ) {
+        if let Some(InteractiveMoveState::Moving(move_)) = &mut self.interactive_move {
+            if window.is_none() || window == Some(move_.tile.window().id()) {
+                return;
+            }
+        }
+
+        let active_output = self.active_output().cloned();
+        let Some(mon) = self.monitor_for_output_mut(output) else {
+            return;
+        };
+        let idx = min(idx, mon.workspaces.len() - 1);
+
+        let is_same_output = match window {
+            Some(window) => mon.has_window(window),
+            None => active_output.as_ref() == Some(output),
+        };
+        if is_same_output {
+            mon.move_to_workspace(window, idx, activate);
+        } else {
+            self.move_to_output(window, output, Some(idx), activate);
+        }
+    }
+
+    pub fn move_column_to_output(
+        &mut self,
+        output: &Output,
+        target_ws_idx: Option<usize>,
+        activate: bool,
+    ) {
         if let MonitorSet::Normal {
             monitors,
             active_monitor_idx,
@@ -3013,19 +3356,31 @@ impl<W: LayoutElement> Layout<W> {
                 .unwrap();
 
             let current = &mut monitors[*active_monitor_idx];
-            let ws = current.active_workspace();
+            if current.active_workspace().floating_is_active() {
+                let activate = if activate {
+                    ActivateWindow::Yes
+                } else {
+                    ActivateWindow::No
+                };
+                self.move_to_output(None, output, target_ws_idx, activate);
+                return;
+            }
 
-            if ws.floating_is_active() {
-                self.move_to_output(None, output, None);
+            let current_ws_idx = current.active_workspace_idx;
+            let target = &monitors[new_idx];
+            let workspace_idx = target_ws_idx
+                .map(|idx| min(idx, target.workspaces.len() - 1))
+                .unwrap_or(target.active_workspace_idx);
+            if new_idx == *active_monitor_idx && workspace_idx == current_ws_idx {
                 return;
             }
 
-            let Some(column) = ws.remove_active_column() else {
+            let current = &mut monitors[*active_monitor_idx];
+            let Some(column) = current.active_workspace().remove_active_column() else {
                 return;
             };
 
-            let workspace_idx = monitors[new_idx].active_workspace_idx;
-            self.add_column_by_idx(new_idx, workspace_idx, column, true);
+            self.add_column_by_idx(new_idx, workspace_idx, column, activate);
         }
     }
 
@@ -3183,6 +3538,60 @@ impl<W: LayoutElement> Layout<W> {
         res
     }
 
+    /// Moves workspaces to an output and activates the first of them there.
+    ///
+    /// Unlike the move-workspace-to-monitor actions, the focus stays on the current output, and
+    /// the workspaces keep their original output rather than sticking to this one.
+    pub fn move_workspaces_to_output(&mut self, ids: &[WorkspaceId], output: &Output) {
+        let active_output = self.active_output().cloned();
+
+        for id in ids {
+            let Some((old_output, old_idx, original_output)) = self
+                .workspaces()
+                .find(|(_, _, ws)| ws.id() == *id)
+                .map(|(mon, idx, ws)| {
+                    let old_output = mon.map(|mon| mon.output.clone());
+                    (old_output, idx, ws.original_output.clone())
+                })
+            else {
+                continue;
+            };
+
+            self.move_workspace_to_output_by_id(old_idx, old_output, output.clone());
+
+            if let Some(ws) = self.workspaces_mut().find(|ws| ws.id() == *id) {
+                ws.original_output = original_output;
+            }
+        }
+
+        let MonitorSet::Normal {
+            monitors,
+            active_monitor_idx,
+            ..
+        } = &mut self.monitor_set
+        else {
+            return;
+        };
+
+        if let Some(active_output) = active_output {
+            if let Some(idx) = monitors.iter().position(|mon| mon.output == active_output) {
+                *active_monitor_idx = idx;
+            }
+        }
+
+        let Some(mon) = monitors.iter_mut().find(|mon| mon.output == *output) else {
+            return;
+        };
+        let first_idx = ids
+            .iter()
+            .find_map(|id| mon.workspaces.iter().position(|ws| ws.id() == *id));
+        if let Some(idx) = first_idx {
+            mon.active_workspace_idx = idx;
+            mon.workspace_switch = None;
+            mon.clean_up_workspaces();
+        }
+    }
+
     pub fn set_fullscreen(&mut self, window: &W::Id, is_fullscreen: bool) {
         if let Some(InteractiveMoveState::Moving(move_)) = &self.interactive_move {
             if move_.tile.window().id() == window {
@@ -3534,6 +3943,7 @@ impl<W: LayoutElement> Layout<W> {
                 // Unfullscreen.
                 let floating_size = tile.floating_window_size;
                 let unfullscreen_to_floating = tile.unfullscreen_to_floating;
+                let min_size = tile.window_min_size();
                 let win = tile.window_mut();
                 if win.is_pending_fullscreen() {
                     // If we're unfullscreening to floating, use the stored floating size,
@@ -3547,7 +3957,6 @@ impl<W: LayoutElement> Layout<W> {
                     // Apply min/max size window rules. If requesting a concrete size, apply
                     // completely; if requesting (0, 0), apply only when min/max results in a fixed
                     // size.
-                    let min_size = win.min_size();
                     let max_size = win.max_size();
                     size.w = ensure_min_max_size_maybe_zero(size.w, min_size.w, max_size.w);
                     size.h = ensure_min_max_size_maybe_zero(size.h, min_size.h, max_size.h);
@@ -3700,6 +4109,7 @@ impl<W: LayoutElement> Layout<W> {
 
                 let win_id = move_.tile.window().id().clone();
                 let window_render_loc = move_.tile_render_location() + move_.tile.window_loc();
+                let snap_position = move_.snap_position();
 
                 match position {
                     InsertPosition::NewColumn(column_idx) => {
@@ -3763,6 +4173,10 @@ impl<W: LayoutElement> Layout<W> {
                 let new_window_render_loc = offset + tile_render_loc + tile.window_loc();
 
                 tile.animate_move_from(window_render_loc - new_window_render_loc);
+
+                if let Some(position) = snap_position {
+                    ws.snap_floating_window(Some(&win_id), position, true);
+                }
             }
             MonitorSet::NoOutputs { workspaces, .. } => {
                 if workspaces.is_empty() {
@@ -3924,6 +4338,132 @@ impl<W: LayoutElement> Layout<W> {
         monitor.move_workspace_to_idx(old_idx, new_idx);
     }
 
+    /// Exchanges the positions of two workspaces, possibly on different monitors.
+    ///
+    /// If the focused workspace is swapped, the focus follows it.
+    pub fn swap_workspaces(&mut self, id_a: WorkspaceId, id_b: WorkspaceId) {
+        if id_a == id_b {
+            return;
+        }
+
+        let (monitors, active_monitor_idx) = match &mut self.monitor_set {
+            MonitorSet::Normal {
+                monitors,
+                active_monitor_idx,
+                ..
+            } => (monitors, active_monitor_idx),
+            MonitorSet::NoOutputs { workspaces } => {
+                let idx_a = workspaces.iter().position(|ws| ws.id() == id_a);
+                let idx_b = workspaces.iter().position(|ws| ws.id() == id_b);
+                if let (Some(idx_a), Some(idx_b)) = (idx_a, idx_b) {
+                    workspaces.swap(idx_a, idx_b);
+                }
+                return;
+            }
+        };
+
+        let find = |id| {
+            monitors.iter().enumerate().find_map(|(mon_idx, mon)| {
+                let ws_idx = mon.workspaces.iter().position(|ws| ws.id() == id)?;
+                Some((mon_idx, ws_idx))
+            })
+        };
+        let (Some((mon_a, idx_a)), Some((mon_b, idx_b))) = (find(id_a), find(id_b)) else {
+            return;
+        };
+
+        if mon_a == mon_b {
+            monitors[mon_a].swap_workspaces(idx_a, idx_b);
+            return;
+        }
+
+        // Order the monitors so that the focused workspace, if swapped, ends up on `to`.
+        let focused_id = monitors[*active_monitor_idx].active_workspace_ref().id();
+        let ((from_mon, from_idx), (to_mon, to_idx)) = if focused_id == id_b {
+            ((mon_b, idx_b), (mon_a, idx_a))
+        } else {
+            ((mon_a, idx_a), (mon_b, idx_b))
+        };
+        let activate = focused_id == id_a || focused_id == id_b;
+
+        let (from, to) = if from_mon < to_mon {
+            let (left, right) = monitors.split_at_mut(to_mon);
+            (&mut left[from_mon], &mut right[0])
+        } else {
+            let (left, right) = monitors.split_at_mut(from_mon);
+            (&mut right[0], &mut left[to_mon])
+        };
+        from.swap_workspaces_with(from_idx, to, to_idx, activate);
+
+        if activate {
+            *active_monitor_idx = to_mon;
+        }
+    }
+
+    pub fn sort_workspaces_by_name(&mut self) {
+        match &mut self.monitor_set {
+            MonitorSet::Normal { monitors, .. } => {
+                for mon in monitors {
+                    mon.sort_workspaces_by_name();
+                }
+            }
+            MonitorSet::NoOutputs { workspaces } => {
+                workspaces.sort_by_cached_key(|ws| {
+                    (
+                        ws.name.is_none(),
+                        ws.name.as_ref().map(|n| n.to_lowercase()),
+                    )
+                });
+            }
+        }
+    }
+
+    pub fn compact_workspaces(&mut self) {
+        // With no outputs, empty unnamed workspaces are removed right away.
+        if let MonitorSet::Normal { monitors, .. } = &mut self.monitor_set {
+            for mon in monitors {
+                mon.compact_workspaces();
+            }
+        }
+    }
+
+    /// Moves named workspaces back to their `open-on-output` outputs.
+    ///
+    /// Workspaces whose output is currently disconnected will move to it once it connects.
+    pub fn move_workspaces_to_assigned_outputs(&mut self, ws_configs: &[WorkspaceConfig]) {
+        for ws_config in ws_configs {
+            let Some(output_name) = &ws_config.open_on_output else {
+                continue;
+            };
+
+            let name = &ws_config.name.0;
+            let Some(ws) = self.workspaces_mut().find(|ws| {
+                ws.name
+                    .as_ref()
+                    .is_some_and(|ws_name| ws_name.eq_ignore_ascii_case(name))
+            }) else {
+                continue;
+            };
+            ws.original_output = OutputId::from_name(output_name.clone());
+            let old_output = ws.current_output().cloned();
+
+            let Some(new_output) = self
+                .outputs()
+                .find(|output| output_matches_name(output, output_name))
+                .cloned()
+            else {
+                continue;
+            };
+
+            if old_output.as_ref() == Some(&new_output) {
+                continue;
+            }
+
+            let (old_idx, _) = self.find_workspace_by_name(name).unwrap();
+            self.move_workspace_to_output_by_id(old_idx, old_output, new_output);
+        }
+    }
+
     pub fn set_workspace_name(&mut self, name: String, reference: Option<WorkspaceReference>) {
         // ignore the request if the name is already used by another workspace
         if self.find_workspace_by_name(&name).is_some() {
@@ -4160,6 +4700,7 @@ impl<W: LayoutElement> Layout<W> {
 
             win.set_active_in_column(true);
             win.set_floating(move_.is_floating);
+            win.set_maximized(false);
             win.set_activated(true);
 
             win.set_interactive_resize(None);
@@ -4177,6 +4718,8 @@ impl<W: LayoutElement> Layout<W> {
                 ..
             } => {
                 for (idx, mon) in monitors.iter_mut().enumerate() {
+                    mon.gather_picture_in_picture_windows();
+
                     let is_active = self.is_active
                         && idx == *active_monitor_idx
                         && !matches!(self.interactive_move, Some(InteractiveMoveState::Moving(_)));
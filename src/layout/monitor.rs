@@ -45,6 +45,8 @@ pub struct Monitor<W: LayoutElement> {
     pub(super) previous_workspace_id: Option<WorkspaceId>,
     /// In-progress switch between workspaces.
     pub(super) workspace_switch: Option<WorkspaceSwitch>,
+    /// ID of the workspace that picture-in-picture windows were last gathered on.
+    picture_in_picture_workspace_id: Option<WorkspaceId>,
     /// Clock for driving animations.
     pub(super) clock: Clock,
     /// Configurable properties of the layout.
@@ -139,6 +141,7 @@ impl<W: LayoutElement> Monitor<W> {
             workspaces,
             active_workspace_idx: 0,
             previous_workspace_id: None,
+            picture_in_picture_workspace_id: None,
             workspace_switch: None,
             clock,
             options,
@@ -250,7 +253,27 @@ impl<W: LayoutElement> Monitor<W> {
         // monitor. So we can use any workspace, not necessarily the exact target workspace.
         let tile = self.workspaces[0].make_tile(window);
 
-        self.add_tile(tile, target, activate, width, is_full_width, is_floating);
+        // Picture-in-picture windows open on the active workspace.
+        let target = if tile.is_picture_in_picture() {
+            MonitorAddWindowTarget::Auto
+        } else {
+            target
+        };
+
+        let (workspace_idx, mut target) = self.resolve_add_window_target(target);
+        if matches!(target, WorkspaceAddWindowTarget::Auto) {
+            target = WorkspaceAddWindowTarget::NewWindow;
+        }
+
+        self.add_tile_to_workspace(
+            workspace_idx,
+            tile,
+            target,
+            activate,
+            width,
+            is_full_width,
+            is_floating,
+        );
     }
 
     pub fn add_column(&mut self, mut workspace_idx: usize, column: Column<W>, activate: bool) {
@@ -285,7 +308,23 @@ impl<W: LayoutElement> Monitor<W> {
         is_full_width: bool,
         is_floating: bool,
     ) {
-        let (mut workspace_idx, target) = match target {
+        let (workspace_idx, target) = self.resolve_add_window_target(target);
+        self.add_tile_to_workspace(
+            workspace_idx,
+            tile,
+            target,
+            activate,
+            width,
+            is_full_width,
+            is_floating,
+        );
+    }
+
+    fn resolve_add_window_target<'a>(
+        &self,
+        target: MonitorAddWindowTarget<'a, W>,
+    ) -> (usize, WorkspaceAddWindowTarget<'a, W>) {
+        match target {
             MonitorAddWindowTarget::Auto => {
                 (self.active_workspace_idx, WorkspaceAddWindowTarget::Auto)
             }
@@ -301,13 +340,25 @@ impl<W: LayoutElement> Monitor<W> {
             MonitorAddWindowTarget::NextTo(win_id) => {
                 let idx = self
                     .workspaces
-                    .iter_mut()
+                    .iter()
                     .position(|ws| ws.has_window(win_id))
                     .unwrap();
                 (idx, WorkspaceAddWindowTarget::NextTo(win_id))
             }
-        };
+        }
+    }
 
+    #[allow(clippy::too_many_arguments)]
+    fn add_tile_to_workspace(
+        &mut self,
+        mut workspace_idx: usize,
+        tile: Tile<W>,
+        target: WorkspaceAddWindowTarget<W>,
+        activate: ActivateWindow,
+        width: ColumnWidth,
+        is_full_width: bool,
+        is_floating: bool,
+    ) {
         let workspace = &mut self.workspaces[workspace_idx];
 
         workspace.add_tile(tile, target, activate, width, is_full_width, is_floating);
@@ -357,6 +408,46 @@ impl<W: LayoutElement> Monitor<W> {
         }
     }
 
+    /// Adds workspaces from a disconnected monitor right before the last, empty, workspace.
+    pub fn append_workspaces(&mut self, mut workspaces: Vec<Workspace<W>>) {
+        if workspaces.is_empty() {
+            return;
+        }
+
+        for ws in &mut workspaces {
+            ws.set_output(Some(self.output.clone()));
+        }
+
+        let mut stopped_ws_switch = false;
+        if self.workspace_switch.is_some() {
+            // FIXME: if we're adding workspaces to currently invisible positions (outside the
+            // workspace switch), we don't need to cancel it.
+            self.workspace_switch = None;
+            stopped_ws_switch = true;
+        }
+
+        let empty_was_focused = self.active_workspace_idx == self.workspaces.len() - 1;
+
+        let empty = self.workspaces.remove(self.workspaces.len() - 1);
+        self.workspaces.extend(workspaces);
+        self.workspaces.push(empty);
+
+        // If empty_workspace_above_first is set and the first workspace is now no longer empty,
+        // add a new empty workspace on top.
+        if self.options.empty_workspace_above_first && self.workspaces[0].has_windows_or_name() {
+            self.add_workspace_top();
+        }
+
+        // If the empty workspace was focused, keep it focused.
+        if empty_was_focused {
+            self.active_workspace_idx = self.workspaces.len() - 1;
+        }
+
+        if stopped_ws_switch {
+            self.clean_up_workspaces();
+        }
+    }
+
     pub fn clean_up_workspaces(&mut self) {
         assert!(self.workspace_switch.is_none());
 
@@ -423,13 +514,13 @@ impl<W: LayoutElement> Monitor<W> {
 
     pub fn move_down_or_to_workspace_down(&mut self) {
         if !self.active_workspace().move_down() {
-            self.move_to_workspace_down();
+            self.move_to_workspace_down(true);
         }
     }
 
     pub fn move_up_or_to_workspace_up(&mut self) {
         if !self.active_workspace().move_up() {
-            self.move_to_workspace_up();
+            self.move_to_workspace_up(true);
         }
     }
 
@@ -493,7 +584,7 @@ impl<W: LayoutElement> Monitor<W> {
         }
     }
 
-    pub fn move_to_workspace_up(&mut self) {
+    pub fn move_to_workspace_up(&mut self, focus: bool) {
         let source_workspace_idx = self.active_workspace_idx;
 
         let new_idx = source_workspace_idx.saturating_sub(1);
@@ -507,20 +598,26 @@ impl<W: LayoutElement> Monitor<W> {
             return;
         };
 
+        let activate = if focus {
+            ActivateWindow::Yes
+        } else {
+            ActivateWindow::No
+        };
+
         self.add_tile(
             removed.tile,
             MonitorAddWindowTarget::Workspace {
                 id: new_id,
                 column_idx: None,
             },
-            ActivateWindow::Yes,
+            activate,
             removed.width,
             removed.is_full_width,
             removed.is_floating,
         );
     }
 
-    pub fn move_to_workspace_down(&mut self) {
+    pub fn move_to_workspace_down(&mut self, focus: bool) {
         let source_workspace_idx = self.active_workspace_idx;
 
         let new_idx = min(source_workspace_idx + 1, self.workspaces.len() - 1);
@@ -534,20 +631,31 @@ impl<W: LayoutElement> Monitor<W> {
             return;
         };
 
+        let activate = if focus {
+            ActivateWindow::Yes
+        } else {
+            ActivateWindow::No
+        };
+
         self.add_tile(
             removed.tile,
             MonitorAddWindowTarget::Workspace {
                 id: new_id,
                 column_idx: None,
             },
-            ActivateWindow::Yes,
+            activate,
             removed.width,
             removed.is_full_width,
             removed.is_floating,
         );
     }
 
-    pub fn move_to_workspace(&mut self, window: Option<&W::Id>, idx: usize) {
+    pub fn move_to_workspace(
+        &mut self,
+        window: Option<&W::Id>,
+        idx: usize,
+        activate: ActivateWindow,
+    ) {
         let source_workspace_idx = if let Some(window) = window {
             self.workspaces
                 .iter()
@@ -563,8 +671,10 @@ impl<W: LayoutElement> Monitor<W> {
         }
         let new_id = self.workspaces[new_idx].id();
 
-        let activate = window.map_or(true, |win| {
-            self.active_window().map(|win| win.id()) == Some(win)
+        let activate = activate.map_smart(|| {
+            window.map_or(true, |win| {
+                self.active_window().map(|win| win.id()) == Some(win)
+            })
         });
         let activate = if activate {
             ActivateWindow::Yes
@@ -599,7 +709,54 @@ impl<W: LayoutElement> Monitor<W> {
         }
     }
 
-    pub fn move_column_to_workspace_up(&mut self) {
+    /// Moves picture-in-picture windows from other workspaces to the active workspace.
+    ///
+    /// Only does anything after a workspace switch, so that the windows can still be moved to
+    /// other workspaces explicitly.
+    pub fn gather_picture_in_picture_windows(&mut self) {
+        // Wait for the workspace switch to finish so the windows don't jump around mid-gesture.
+        if self.workspace_switch.is_some() {
+            return;
+        }
+
+        let active_id = self.workspaces[self.active_workspace_idx].id();
+        if self.picture_in_picture_workspace_id == Some(active_id) {
+            return;
+        }
+        self.picture_in_picture_workspace_id = Some(active_id);
+
+        let ids: Vec<_> = self
+            .workspaces
+            .iter()
+            .filter(|ws| ws.id() != active_id)
+            .flat_map(|ws| ws.picture_in_picture_windows())
+            .map(|win| win.id().clone())
+            .collect();
+        if ids.is_empty() {
+            return;
+        }
+
+        for id in ids {
+            let workspace = self.workspaces.iter_mut().find(|ws| ws.has_window(&id));
+            let removed = workspace.unwrap().remove_tile(&id, Transaction::new());
+
+            self.add_tile(
+                removed.tile,
+                MonitorAddWindowTarget::Workspace {
+                    id: active_id,
+                    column_idx: None,
+                },
+                ActivateWindow::No,
+                removed.width,
+                removed.is_full_width,
+                removed.is_floating,
+            );
+        }
+
+        self.clean_up_workspaces();
+    }
+
+    pub fn move_column_to_workspace_up(&mut self, focus: bool) {
         let source_workspace_idx = self.active_workspace_idx;
 
         let new_idx = source_workspace_idx.saturating_sub(1);
@@ -609,7 +766,7 @@ impl<W: LayoutElement> Monitor<W> {
 
         let workspace = &mut self.workspaces[source_workspace_idx];
         if workspace.floating_is_active() {
-            self.move_to_workspace_up();
+            self.move_to_workspace_up(focus);
             return;
         }
 
@@ -617,10 +774,10 @@ impl<W: LayoutElement> Monitor<W> {
             return;
         };
 
-        self.add_column(new_idx, column, true);
+        self.add_column(new_idx, column, focus);
     }
 
-    pub fn move_column_to_workspace_down(&mut self) {
+    pub fn move_column_to_workspace_down(&mut self, focus: bool) {
         let source_workspace_idx = self.active_workspace_idx;
 
         let new_idx = min(source_workspace_idx + 1, self.workspaces.len() - 1);
@@ -630,7 +787,7 @@ impl<W: LayoutElement> Monitor<W> {
 
         let workspace = &mut self.workspaces[source_workspace_idx];
         if workspace.floating_is_active() {
-            self.move_to_workspace_down();
+            self.move_to_workspace_down(focus);
             return;
         }
 
@@ -638,10 +795,10 @@ impl<W: LayoutElement> Monitor<W> {
             return;
         };
 
-        self.add_column(new_idx, column, true);
+        self.add_column(new_idx, column, focus);
     }
 
-    pub fn move_column_to_workspace(&mut self, idx: usize) {
+    pub fn move_column_to_workspace(&mut self, idx: usize, focus: bool) {
         let source_workspace_idx = self.active_workspace_idx;
 
         let new_idx = min(idx, self.workspaces.len() - 1);
@@ -651,7 +808,12 @@ impl<W: LayoutElement> Monitor<W> {
 
         let workspace = &mut self.workspaces[source_workspace_idx];
         if workspace.floating_is_active() {
-            self.move_to_workspace(None, idx);
+            let activate = if focus {
+                ActivateWindow::Yes
+            } else {
+                ActivateWindow::No
+            };
+            self.move_to_workspace(None, idx, activate);
             return;
         }
 
@@ -659,7 +821,7 @@ impl<W: LayoutElement> Monitor<W> {
             return;
         };
 
-        self.add_column(new_idx, column, true);
+        self.add_column(new_idx, column, focus);
     }
 
     pub fn switch_workspace_up(&mut self) {
@@ -911,6 +1073,119 @@ impl<W: LayoutElement> Monitor<W> {
         self.clean_up_workspaces();
     }
 
+    /// Swaps the positions of two workspaces on this monitor.
+    ///
+    /// The active workspace stays active at its new position.
+    pub fn swap_workspaces(&mut self, idx_a: usize, idx_b: usize) {
+        if idx_a == idx_b {
+            return;
+        }
+
+        let active_id = self.workspaces[self.active_workspace_idx].id();
+        self.workspaces.swap(idx_a, idx_b);
+        self.settle_swapped_workspaces(Some(active_id));
+    }
+
+    /// Swaps a workspace on this monitor with a workspace on another monitor.
+    ///
+    /// The monitors keep showing the same positions, unless `activate` is set, in which case
+    /// `other` activates the workspace that came from this monitor.
+    pub fn swap_workspaces_with(
+        &mut self,
+        idx: usize,
+        other: &mut Monitor<W>,
+        other_idx: usize,
+        activate: bool,
+    ) {
+        std::mem::swap(&mut self.workspaces[idx], &mut other.workspaces[other_idx]);
+
+        let ws = &mut self.workspaces[idx];
+        ws.set_output(Some(self.output.clone()));
+        ws.original_output = OutputId::new(&self.output);
+
+        let ws = &mut other.workspaces[other_idx];
+        ws.set_output(Some(other.output.clone()));
+        ws.original_output = OutputId::new(&other.output);
+        let id = ws.id();
+
+        self.settle_swapped_workspaces(None);
+        other.settle_swapped_workspaces(activate.then_some(id));
+    }
+
+    /// Restores the empty workspaces at the edges after a swap and activates the given workspace.
+    fn settle_swapped_workspaces(&mut self, activate: Option<WorkspaceId>) {
+        if self.workspaces.last().unwrap().has_windows_or_name() {
+            self.add_workspace_bottom();
+        }
+        if self.options.empty_workspace_above_first && self.workspaces[0].has_windows_or_name() {
+            self.add_workspace_top();
+        }
+
+        // Like the other workspace moves, the swap is instant: a switch animation would scroll
+        // through whatever workspace now sits at the old position.
+        if let Some(id) = activate {
+            let idx = self.workspaces.iter().position(|ws| ws.id() == id).unwrap();
+            let previous_workspace_id = self.previous_workspace_id;
+            self.activate_workspace(idx);
+            self.workspace_switch = None;
+            self.previous_workspace_id = previous_workspace_id;
+        }
+
+        // Otherwise, the workspaces are cleaned up at the end of the switch.
+        if self.workspace_switch.is_none() {
+            self.clean_up_workspaces();
+        }
+    }
+
+    /// Sorts the named workspaces by name and puts them above the unnamed ones.
+    ///
+    /// Unnamed workspaces keep their relative order.
+    pub fn sort_workspaces_by_name(&mut self) {
+        // Keep the empty workspaces at the edges in place.
+        let range_start = if self.options.empty_workspace_above_first {
+            1
+        } else {
+            0
+        };
+        let range_end = self.workspaces.len() - 1;
+        if range_end <= range_start {
+            return;
+        }
+
+        let active_id = self.workspaces[self.active_workspace_idx].id();
+
+        self.workspaces[range_start..range_end].sort_by_cached_key(|ws| {
+            (
+                ws.name.is_none(),
+                ws.name.as_ref().map(|n| n.to_lowercase()),
+            )
+        });
+
+        self.active_workspace_idx = self
+            .workspaces
+            .iter()
+            .position(|ws| ws.id() == active_id)
+            .unwrap();
+        self.workspace_switch = None;
+
+        self.clean_up_workspaces();
+    }
+
+    /// Removes all empty unnamed workspaces, including the active one.
+    pub fn compact_workspaces(&mut self) {
+        self.workspace_switch = None;
+
+        let idx = self.active_workspace_idx;
+        let is_edge = idx == self.workspaces.len() - 1
+            || (self.options.empty_workspace_above_first && idx == 0);
+        if !is_edge && !self.workspaces[idx].has_windows_or_name() {
+            // The workspace below takes the place of the removed one.
+            self.workspaces.remove(idx);
+        }
+
+        self.clean_up_workspaces();
+    }
+
     /// Returns the geometry of the active tile relative to and clamped to the output.
     ///
     /// During animations, assumes the final view position.
@@ -975,6 +1250,16 @@ impl<W: LayoutElement> Monitor<W> {
         first.into_iter().chain(second)
     }
 
+    /// Returns the visible windows with their render positions relative to the output.
+    pub fn windows_with_render_positions(&self) -> impl Iterator<Item = (&W, Point<f64, Logical>)> {
+        self.workspaces_with_render_positions()
+            .flat_map(|(ws, offset)| {
+                ws.tiles_with_render_positions()
+                    .filter(|(_, _, visible)| *visible)
+                    .map(move |(tile, pos, _)| (tile.window(), offset + pos + tile.window_loc()))
+            })
+    }
+
     pub fn workspace_under(
         &self,
         pos_within_output: Point<f64, Logical>,
@@ -996,11 +1281,36 @@ impl<W: LayoutElement> Monitor<W> {
         Some((win, win_pos.map(|p| p + offset)))
     }
 
+    pub fn titlebar_close_button_under(
+        &self,
+        pos_within_output: Point<f64, Logical>,
+    ) -> Option<&W> {
+        let (ws, offset) = self.workspace_under(pos_within_output)?;
+        ws.titlebar_close_button_under(pos_within_output - offset)
+    }
+
     pub fn resize_edges_under(&self, pos_within_output: Point<f64, Logical>) -> Option<ResizeEdge> {
         let (ws, offset) = self.workspace_under(pos_within_output)?;
         ws.resize_edges_under(pos_within_output - offset)
     }
 
+    pub fn resize_corner_under(
+        &self,
+        pos_within_output: Point<f64, Logical>,
+    ) -> Option<ResizeEdge> {
+        let (ws, offset) = self.workspace_under(pos_within_output)?;
+        ws.resize_corner_under(pos_within_output - offset)
+    }
+
+    pub fn resize_edges_near(
+        &self,
+        pos_within_output: Point<f64, Logical>,
+        edge_width: f64,
+    ) -> Option<(&W, ResizeEdge)> {
+        let (ws, offset) = self.workspace_under(pos_within_output)?;
+        ws.resize_edges_near(pos_within_output - offset, edge_width)
+    }
+
     pub fn render_above_top_layer(&self) -> bool {
         // Render above the top layer only if the view is stationary.
         if self.workspace_switch.is_some() {
@@ -1065,6 +1375,18 @@ impl<W: LayoutElement> Monitor<W> {
             })
     }
 
+    /// Returns how far an ongoing workspace switch gesture has moved.
+    ///
+    /// The progress is negative towards the workspace above, and positive towards the workspace
+    /// below, reaching 1 at the neighboring workspace.
+    pub fn workspace_switch_gesture_progress(&self) -> Option<f64> {
+        let Some(WorkspaceSwitch::Gesture(gesture)) = &self.workspace_switch else {
+            return None;
+        };
+
+        Some(gesture.current_idx - gesture.center_idx as f64)
+    }
+
     pub fn workspace_switch_gesture_begin(&mut self, is_touchpad: bool) {
         let center_idx = self.active_workspace_idx;
         let current_idx = self
@@ -3,7 +3,10 @@ use std::iter::{self, zip};
 use std::rc::Rc;
 use std::time::Duration;
 
-use niri_config::{CenterFocusedColumn, CornerRadius, PresetSize, Struts};
+use niri_config::{
+    CenterFocusedColumn, CornerRadius, NewColumnPosition, NewWindowPositionInColumn, PresetSize,
+    RevealNewWindows, Struts,
+};
 use niri_ipc::SizeChange;
 use ordered_float::NotNan;
 use smithay::backend::renderer::gles::GlesRenderer;
@@ -11,6 +14,9 @@ use smithay::utils::{Logical, Point, Rectangle, Scale, Serial, Size};
 
 use super::closing_window::{ClosingWindow, ClosingWindowRenderElement};
 use super::insert_hint_element::{InsertHintElement, InsertHintRenderElement};
+use super::startup_placeholder::{
+    StartupPlaceholder, StartupPlaceholderId, StartupPlaceholderRenderElement,
+};
 use super::tile::{Tile, TileRenderElement, TileRenderSnapshot};
 use super::workspace::{InteractiveResize, ResolvedSize};
 use super::{ConfigureIntent, InteractiveResizeData, LayoutElement, Options, RemovedTile};
@@ -72,6 +78,11 @@ pub struct ScrollingSpace<W: LayoutElement> {
     /// Insert hint element for rendering.
     insert_hint_element: InsertHintElement,
 
+    /// Placeholders for spawned applications that haven't opened a window yet.
+    ///
+    /// Only the most recent one is shown, where its window is expected to appear.
+    startup_placeholders: Vec<StartupPlaceholder>,
+
     /// View size for this space.
     view_size: Size<f64, Logical>,
 
@@ -95,6 +106,7 @@ niri_render_elements! {
         Tile = TileRenderElement<R>,
         ClosingWindow = ClosingWindowRenderElement,
         InsertHint = InsertHintRenderElement,
+        StartupPlaceholder = StartupPlaceholderRenderElement,
     }
 }
 
@@ -271,6 +283,7 @@ impl<W: LayoutElement> ScrollingSpace<W> {
             closing_windows: Vec::new(),
             insert_hint: None,
             insert_hint_element: InsertHintElement::new(options.insert_hint),
+            startup_placeholders: Vec::new(),
             view_size,
             working_area,
             scale,
@@ -307,6 +320,10 @@ impl<W: LayoutElement> ScrollingSpace<W> {
         }
 
         self.insert_hint_element.update_shaders();
+
+        for placeholder in &mut self.startup_placeholders {
+            placeholder.update_shaders();
+        }
     }
 
     pub fn advance_animations(&mut self) {
@@ -351,7 +368,7 @@ impl<W: LayoutElement> ScrollingSpace<W> {
         }
 
         if let Some(insert_hint) = &self.insert_hint {
-            if let Some(area) = self.insert_hint_area(insert_hint) {
+            if let Some(area) = self.insert_hint_area(&insert_hint.position) {
                 let view_rect = Rectangle::new(area.loc.upscale(-1.), view_size);
                 self.insert_hint_element.update_render_elements(
                     area.size,
@@ -361,6 +378,14 @@ impl<W: LayoutElement> ScrollingSpace<W> {
                 );
             }
         }
+
+        if let Some(area) = self.startup_placeholder_area() {
+            let view_rect = Rectangle::new(area.loc.upscale(-1.), view_size);
+            let scale = self.scale;
+            if let Some(placeholder) = self.startup_placeholders.last_mut() {
+                placeholder.update_render_elements(area.size, view_rect, scale);
+            }
+        }
     }
 
     pub fn tiles(&self) -> impl Iterator<Item = &Tile<W>> + '_ {
@@ -705,6 +730,34 @@ impl<W: LayoutElement> ScrollingSpace<W> {
         self.insert_hint = None;
     }
 
+    pub fn add_startup_placeholder(&mut self, placeholder: StartupPlaceholder) {
+        self.startup_placeholders.push(placeholder);
+    }
+
+    pub fn has_startup_placeholder(&self, id: StartupPlaceholderId) -> bool {
+        self.startup_placeholders.iter().any(|p| p.id() == id)
+    }
+
+    pub fn remove_startup_placeholder(&mut self, id: StartupPlaceholderId) -> bool {
+        let len = self.startup_placeholders.len();
+        self.startup_placeholders.retain(|p| p.id() != id);
+        self.startup_placeholders.len() != len
+    }
+
+    /// Removes the oldest placeholder that looks like it belongs to a window with this app ID.
+    pub fn remove_startup_placeholder_for_app_id(&mut self, app_id: &str) -> bool {
+        let idx = self
+            .startup_placeholders
+            .iter()
+            .position(|p| p.matches_app_id(app_id));
+        if let Some(idx) = idx {
+            self.startup_placeholders.remove(idx);
+            true
+        } else {
+            false
+        }
+    }
+
     pub fn get_insert_position(&self, pos: Point<f64, Logical>) -> InsertPosition {
         if self.columns.is_empty() {
             return InsertPosition::NewColumn(0);
@@ -790,8 +843,8 @@ impl<W: LayoutElement> ScrollingSpace<W> {
     ) {
         let prev_next_x = self.column_x(col_idx + 1);
 
+        let tile_idx = tile_idx.unwrap_or_else(|| self.new_tile_idx_in_column(col_idx));
         let target_column = &mut self.columns[col_idx];
-        let tile_idx = tile_idx.unwrap_or(target_column.tiles.len());
         let was_fullscreen = target_column.tiles[target_column.active_tile_idx].is_fullscreen();
 
         target_column.add_tile_at(tile_idx, tile, true);
@@ -827,6 +880,29 @@ impl<W: LayoutElement> ScrollingSpace<W> {
         }
     }
 
+    /// Returns the index where a column for a new window should go, according to the config.
+    pub fn new_column_idx(&self) -> usize {
+        if self.columns.is_empty() {
+            return 0;
+        }
+
+        match self.options.new_column_position {
+            NewColumnPosition::AfterFocused => self.active_column_idx + 1,
+            NewColumnPosition::First => 0,
+            NewColumnPosition::Last => self.columns.len(),
+        }
+    }
+
+    /// Returns the index where a new tile should be added into a column, according to the config.
+    fn new_tile_idx_in_column(&self, col_idx: usize) -> usize {
+        let column = &self.columns[col_idx];
+        match self.options.new_window_position_in_column {
+            NewWindowPositionInColumn::AfterFocused => column.active_tile_idx + 1,
+            NewWindowPositionInColumn::Top => 0,
+            NewWindowPositionInColumn::Bottom => column.tiles.len(),
+        }
+    }
+
     pub fn add_tile_right_of(
         &mut self,
         right_of: &W::Id,
@@ -905,6 +981,45 @@ impl<W: LayoutElement> ScrollingSpace<W> {
         }
     }
 
+    /// Moves the view to show a new window that opened unfocused, if configured to.
+    pub fn reveal_new_window(&mut self, window: &W::Id) {
+        let Some(idx) = self.columns.iter().position(|col| col.contains(window)) else {
+            return;
+        };
+        if idx == self.active_column_idx {
+            return;
+        }
+
+        let col = &self.columns[idx];
+        let tile = col.tiles.iter().find(|tile| tile.window().id() == window);
+        let reveal = tile
+            .and_then(|tile| tile.window().rules().reveal_on_open)
+            .unwrap_or(self.options.reveal_new_windows);
+        if reveal == RevealNewWindows::Never {
+            return;
+        }
+
+        let col_x = self.column_x(idx);
+        let mut view_pos =
+            col_x + self.compute_new_view_offset_fit(None, col_x, col.width(), col.is_fullscreen);
+
+        let active_x = self.column_x(self.active_column_idx);
+        if reveal == RevealNewWindows::Partial {
+            // Move back as little as needed to keep the focused column fully visible.
+            let active = &self.columns[self.active_column_idx];
+            view_pos = active_x
+                + self.compute_new_view_offset_fit(
+                    Some(view_pos),
+                    active_x,
+                    active.width(),
+                    active.is_fullscreen,
+                );
+        }
+
+        let config = self.options.animations.horizontal_view_movement.0;
+        self.animate_view_offset_with_config(self.active_column_idx, view_pos - active_x, config);
+    }
+
     pub fn remove_active_tile(&mut self, transaction: Transaction) -> Option<RemovedTile<W>> {
         if self.columns.is_empty() {
             return None;
@@ -1569,13 +1684,19 @@ impl<W: LayoutElement> ScrollingSpace<W> {
                 Transaction::new(),
                 Some(self.options.animations.window_movement.0),
             );
-            self.add_tile_to_column(target_column_idx, None, tile, source_tile_was_active);
+            let target_tile_idx = self.new_tile_idx_in_column(target_column_idx);
+            self.add_tile_to_column(
+                target_column_idx,
+                Some(target_tile_idx),
+                tile,
+                source_tile_was_active,
+            );
 
             let target_column = &mut self.columns[target_column_idx];
             offset.x -= target_column.render_offset().x;
-            offset += prev_off - target_column.tile_offset(target_column.tiles.len() - 1);
+            offset += prev_off - target_column.tile_offset(target_tile_idx);
 
-            let new_tile = target_column.tiles.last_mut().unwrap();
+            let new_tile = &mut target_column.tiles[target_tile_idx];
             new_tile.animate_move_from(offset);
         } else {
             // Move out of column.
@@ -1665,12 +1786,18 @@ impl<W: LayoutElement> ScrollingSpace<W> {
                 Transaction::new(),
                 Some(self.options.animations.window_movement.0),
             );
-            self.add_tile_to_column(target_column_idx, None, tile, source_tile_was_active);
+            let target_tile_idx = self.new_tile_idx_in_column(target_column_idx);
+            self.add_tile_to_column(
+                target_column_idx,
+                Some(target_tile_idx),
+                tile,
+                source_tile_was_active,
+            );
 
             let target_column = &mut self.columns[target_column_idx];
-            offset += prev_off - target_column.tile_offset(target_column.tiles.len() - 1);
+            offset += prev_off - target_column.tile_offset(target_tile_idx);
 
-            let new_tile = target_column.tiles.last_mut().unwrap();
+            let new_tile = &mut target_column.tiles[target_tile_idx];
             new_tile.animate_move_from(offset);
         } else {
             // Move out of column.
@@ -1723,13 +1850,19 @@ impl<W: LayoutElement> ScrollingSpace<W> {
         let prev_off = self.columns[source_column_idx].tile_offset(0);
 
         let removed = self.remove_tile_by_idx(source_column_idx, 0, Transaction::new(), None);
-        self.add_tile_to_column(target_column_idx, None, removed.tile, false);
+        let target_tile_idx = self.new_tile_idx_in_column(target_column_idx);
+        self.add_tile_to_column(
+            target_column_idx,
+            Some(target_tile_idx),
+            removed.tile,
+            false,
+        );
 
         let target_column = &mut self.columns[target_column_idx];
-        offset += prev_off - target_column.tile_offset(target_column.tiles.len() - 1);
+        offset += prev_off - target_column.tile_offset(target_tile_idx);
         offset.x -= target_column.render_offset().x;
 
-        let new_tile = target_column.tiles.last_mut().unwrap();
+        let new_tile = &mut target_column.tiles[target_tile_idx];
         new_tile.animate_move_from(offset);
     }
 
@@ -2054,8 +2187,8 @@ impl<W: LayoutElement> ScrollingSpace<W> {
             })
     }
 
-    fn insert_hint_area(&self, insert_hint: &InsertHint) -> Option<Rectangle<f64, Logical>> {
-        let mut hint_area = match insert_hint.position {
+    fn insert_hint_area(&self, position: &InsertPosition) -> Option<Rectangle<f64, Logical>> {
+        let mut hint_area = match *position {
             InsertPosition::NewColumn(column_index) => {
                 if column_index == 0 || column_index == self.columns.len() {
                     let size =
@@ -2132,7 +2265,7 @@ impl<W: LayoutElement> ScrollingSpace<W> {
         let view_size = self.view_size;
 
         // Make sure the hint is at least partially visible.
-        if matches!(insert_hint.position, InsertPosition::NewColumn(_)) {
+        if matches!(position, InsertPosition::NewColumn(_)) {
             hint_area.loc.x = hint_area.loc.x.max(-hint_area.size.w / 2.);
             hint_area.loc.x = hint_area.loc.x.min(view_size.w - hint_area.size.w / 2.);
         }
@@ -2145,6 +2278,50 @@ impl<W: LayoutElement> ScrollingSpace<W> {
         Some(hint_area)
     }
 
+    /// Returns the area of the shown startup placeholder, where a new column would appear.
+    ///
+    /// The placeholder starts right after the active column and takes up the default column
+    /// width, covering the columns to the right of it.
+    fn startup_placeholder_area(&self) -> Option<Rectangle<f64, Logical>> {
+        if self.startup_placeholders.is_empty() {
+            return None;
+        }
+
+        let width = match self.options.default_column_width {
+            Some(preset) => {
+                match resolve_preset_size(preset, &self.options, self.working_area.size.w) {
+                    ResolvedSize::Tile(size) => size,
+                    ResolvedSize::Window(size) => size,
+                }
+            }
+            // The window picks its own width, which isn't known in advance.
+            None => 300.,
+        };
+        let size = Size::from((width, self.working_area.size.h - self.options.gaps * 2.));
+
+        let mut area = if self.columns.is_empty() {
+            // The first window on an empty workspace will cancel out any view offset.
+            let view_offset = if self.is_centering_focused_column() {
+                self.compute_new_view_offset_centered(Some(0.), 0., size.w, false)
+            } else {
+                self.compute_new_view_offset_fit(Some(0.), 0., size.w, false)
+            };
+            let loc = Point::from((-view_offset, self.working_area.loc.y + self.options.gaps));
+            Rectangle::new(loc, size)
+        } else {
+            let x = self.column_x(self.active_column_idx + 1) - self.view_pos();
+            let loc = Point::from((x, self.working_area.loc.y + self.options.gaps));
+            Rectangle::new(loc, size)
+        };
+
+        // Round to physical pixels.
+        area = area
+            .to_physical_precise_round(self.scale)
+            .to_logical(self.scale);
+
+        Some(area)
+    }
+
     /// Returns the geometry of the active tile relative to and clamped to the view.
     ///
     /// During animations, assumes the final view position.
@@ -2397,7 +2574,7 @@ impl<W: LayoutElement> ScrollingSpace<W> {
 
         // Draw the insert hint.
         if let Some(insert_hint) = &self.insert_hint {
-            if let Some(area) = self.insert_hint_area(insert_hint) {
+            if let Some(area) = self.insert_hint_area(&insert_hint.position) {
                 rv.extend(
                     self.insert_hint_element
                         .render(renderer, area.loc)
@@ -2406,6 +2583,17 @@ impl<W: LayoutElement> ScrollingSpace<W> {
             }
         }
 
+        // Draw the startup placeholder.
+        if let Some(area) = self.startup_placeholder_area() {
+            if let Some(placeholder) = self.startup_placeholders.last() {
+                rv.extend(
+                    placeholder
+                        .render(renderer, area, self.scale)
+                        .map(ScrollingSpaceRenderElement::StartupPlaceholder),
+                );
+            }
+        }
+
         // Draw the closing windows on top of the other windows.
         let view_rect = Rectangle::new(Point::from((self.view_pos(), 0.)), self.view_size);
         for closing in self.closing_windows.iter().rev() {
@@ -2899,6 +3087,7 @@ impl<W: LayoutElement> ScrollingSpace<W> {
                 let active_in_column = col.active_tile_idx == tile_idx;
                 win.set_active_in_column(active_in_column);
                 win.set_floating(false);
+                win.set_maximized(col.is_full_width);
 
                 let active = is_active && self.active_column_idx == col_idx && active_in_column;
                 win.set_activated(active);
@@ -3822,9 +4011,8 @@ impl<W: LayoutElement> Column<W> {
         window_height = f64::min(height_left, window_height);
 
         // Clamp it against the window height constraints.
-        let win = &self.tiles[tile_idx].window();
-        let min_h = win.min_size().h;
-        let max_h = win.max_size().h;
+        let min_h = tile.window_min_size().h;
+        let max_h = tile.window().max_size().h;
 
         if max_h > 0 {
             window_height = f64::min(window_height, f64::from(max_h));
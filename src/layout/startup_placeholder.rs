@@ -0,0 +1,334 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::f64::consts::{FRAC_PI_2, TAU};
+use std::fmt;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::ensure;
+use niri_config::{Color, CornerRadius, FloatOrInt};
+use ordered_float::NotNan;
+use pangocairo::cairo::{self, ImageSurface};
+use pangocairo::pango::FontDescription;
+use smithay::backend::renderer::element::Kind;
+use smithay::reexports::gbm::Format as Fourcc;
+use smithay::utils::{Logical, Rectangle, Size, Transform};
+
+use super::focus_ring::{FocusRing, FocusRingRenderElement};
+use crate::niri_render_elements;
+use crate::render_helpers::memory::MemoryBuffer;
+use crate::render_helpers::primary_gpu_texture::PrimaryGpuTextureRenderElement;
+use crate::render_helpers::renderer::NiriRenderer;
+use crate::render_helpers::texture::{TextureBuffer, TextureRenderElement};
+use crate::utils::id::IdCounter;
+use crate::utils::{get_monotonic_time, to_physical_precise_round};
+
+/// How long to show a placeholder if no window shows up for it.
+///
+/// Matches how long the activation token given to the spawned process stays valid.
+pub const STARTUP_PLACEHOLDER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Delay before showing a placeholder, so that it doesn't flash for fast-starting applications.
+pub const STARTUP_PLACEHOLDER_SHOW_DELAY: Duration = Duration::from_millis(250);
+
+/// How often the spinner moves on to the next dot.
+pub const STARTUP_PLACEHOLDER_SPINNER_STEP: Duration = Duration::from_millis(100);
+
+const BACKGROUND_COLOR: Color = Color::new_unpremul(0.5, 0.5, 0.5, 0.25);
+const LETTER_COLOR: Color = Color::new_unpremul(0.35, 0.35, 0.35, 1.);
+const ICON_SIZE: i32 = 64;
+const SPINNER_SIZE: i32 = 32;
+const SPACING: i32 = 24;
+const SPINNER_DOTS: usize = 8;
+const FONT: &str = "sans bold 32px";
+
+/// Application that a placeholder is shown for.
+#[derive(Debug, Clone, Default)]
+pub struct StartupApp {
+    /// Name of the spawned program.
+    pub name: String,
+    /// App ID that the application window is expected to have, if known.
+    pub app_id: Option<String>,
+    /// Path to the application icon in PNG format.
+    pub icon: Option<PathBuf>,
+}
+
+static STARTUP_PLACEHOLDER_ID_COUNTER: IdCounter = IdCounter::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StartupPlaceholderId(u64);
+
+impl StartupPlaceholderId {
+    pub fn next() -> StartupPlaceholderId {
+        StartupPlaceholderId(STARTUP_PLACEHOLDER_ID_COUNTER.next())
+    }
+}
+
+/// Placeholder shown in place of an application that was spawned but hasn't opened a window yet.
+pub struct StartupPlaceholder {
+    id: StartupPlaceholderId,
+
+    /// Application that is starting up.
+    app: StartupApp,
+
+    /// Monotonic time when the placeholder was created.
+    ///
+    /// The placeholder doesn't use the animation clock, which stops when animations are off.
+    created_at: Duration,
+
+    /// Background of the placeholder.
+    background: FocusRing,
+
+    /// Rendered icon and spinner, for every scale and spinner step.
+    buffers: RefCell<HashMap<(NotNan<f64>, usize), Option<MemoryBuffer>>>,
+}
+
+niri_render_elements! {
+    StartupPlaceholderRenderElement => {
+        Background = FocusRingRenderElement,
+        Content = PrimaryGpuTextureRenderElement,
+    }
+}
+
+impl StartupPlaceholder {
+    pub fn new(id: StartupPlaceholderId, app: StartupApp) -> Self {
+        Self {
+            id,
+            app,
+            created_at: get_monotonic_time(),
+            background: FocusRing::new(niri_config::FocusRing {
+                off: false,
+                width: FloatOrInt(0.),
+                active_color: BACKGROUND_COLOR,
+                inactive_color: BACKGROUND_COLOR,
+                active_gradient: None,
+                inactive_gradient: None,
+            }),
+            buffers: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn id(&self) -> StartupPlaceholderId {
+        self.id
+    }
+
+    /// Returns whether a window with this app ID likely belongs to the placeholder.
+    pub fn matches_app_id(&self, app_id: &str) -> bool {
+        if let Some(expected) = &self.app.app_id {
+            if expected.eq_ignore_ascii_case(app_id) {
+                return true;
+            }
+        }
+
+        if self.app.name.is_empty() {
+            return false;
+        }
+
+        // Reverse-DNS app IDs like org.gnome.Nautilus often end with the program name.
+        let last = app_id.rsplit('.').next().unwrap_or(app_id);
+        self.app.name.eq_ignore_ascii_case(app_id) || self.app.name.eq_ignore_ascii_case(last)
+    }
+
+    pub fn update_shaders(&mut self) {
+        self.background.update_shaders();
+    }
+
+    pub fn update_render_elements(
+        &mut self,
+        size: Size<f64, Logical>,
+        view_rect: Rectangle<f64, Logical>,
+        scale: f64,
+    ) {
+        self.background.update_render_elements(
+            size,
+            true,
+            false,
+            view_rect,
+            CornerRadius::default(),
+            scale,
+        );
+    }
+
+    pub fn render<R: NiriRenderer>(
+        &self,
+        renderer: &mut R,
+        area: Rectangle<f64, Logical>,
+        scale: f64,
+    ) -> impl Iterator<Item = StartupPlaceholderRenderElement> {
+        let mut rv = Vec::new();
+
+        let elapsed = get_monotonic_time().saturating_sub(self.created_at);
+        if elapsed < STARTUP_PLACEHOLDER_SHOW_DELAY {
+            return rv.into_iter();
+        }
+
+        let step = (elapsed.as_millis() / STARTUP_PLACEHOLDER_SPINNER_STEP.as_millis()) as usize
+            % SPINNER_DOTS;
+
+        if let Some(buffer) = self.content_buffer(scale, step) {
+            if let Ok(buffer) =
+                TextureBuffer::from_memory_buffer(renderer.as_gles_renderer(), &buffer)
+            {
+                let size = buffer.logical_size();
+                let loc = area.loc + (area.size.to_point() - size.to_point()).downscale(2.);
+                let loc = loc.to_physical_precise_round(scale).to_logical(scale);
+
+                let elem = TextureRenderElement::from_texture_buffer(
+                    buffer,
+                    loc,
+                    1.,
+                    None,
+                    None,
+                    Kind::Unspecified,
+                );
+                rv.push(PrimaryGpuTextureRenderElement(elem).into());
+            }
+        }
+
+        rv.extend(
+            self.background
+                .render(renderer, area.loc)
+                .map(StartupPlaceholderRenderElement::from),
+        );
+
+        rv.into_iter()
+    }
+
+    fn content_buffer(&self, scale: f64, step: usize) -> Option<MemoryBuffer> {
+        let mut buffers = self.buffers.borrow_mut();
+        buffers
+            .entry((NotNan::new(scale).unwrap(), step))
+            .or_insert_with(|| {
+                render(scale, &self.app.name, self.app.icon.as_deref(), step)
+                    .map_err(|err| warn!("error rendering startup placeholder: {err:?}"))
+                    .ok()
+            })
+            .clone()
+    }
+}
+
+impl fmt::Debug for StartupPlaceholder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StartupPlaceholder")
+            .field("id", &self.id)
+            .field("app", &self.app)
+            .field("created_at", &self.created_at)
+            .finish_non_exhaustive()
+    }
+}
+
+fn render(
+    scale: f64,
+    app_name: &str,
+    icon: Option<&Path>,
+    step: usize,
+) -> anyhow::Result<MemoryBuffer> {
+    let _span = tracy_client::span!("startup_placeholder::render");
+
+    let icon_size: i32 = to_physical_precise_round(scale, ICON_SIZE);
+    let spinner_size: i32 = to_physical_precise_round(scale, SPINNER_SIZE);
+    let spacing: i32 = to_physical_precise_round(scale, SPACING);
+
+    let width = icon_size;
+    let height = icon_size + spacing + spinner_size;
+
+    let surface = ImageSurface::create(cairo::Format::ARgb32, width, height)?;
+    let cr = cairo::Context::new(&surface)?;
+
+    let mut has_icon = false;
+    if let Some(icon) = icon {
+        match draw_icon(&cr, icon, icon_size) {
+            Ok(()) => has_icon = true,
+            Err(err) => warn!("error drawing icon {icon:?}: {err:?}"),
+        }
+    }
+    if !has_icon {
+        draw_letter(&cr, app_name, icon_size, scale)?;
+    }
+
+    // The dot at the current step is the brightest, and the ones behind it fade out.
+    let cx = f64::from(width) / 2.;
+    let cy = f64::from(icon_size + spacing) + f64::from(spinner_size) / 2.;
+    let dot_radius = f64::from(spinner_size) / 10.;
+    let radius = f64::from(spinner_size) / 2. - dot_radius;
+    for dot in 0..SPINNER_DOTS {
+        let angle = dot as f64 / SPINNER_DOTS as f64 * TAU - FRAC_PI_2;
+        let age = (step + SPINNER_DOTS - dot) % SPINNER_DOTS;
+        let alpha = 1. - age as f64 / SPINNER_DOTS as f64;
+
+        cr.set_source_rgba(1., 1., 1., alpha);
+        cr.arc(
+            cx + radius * angle.cos(),
+            cy + radius * angle.sin(),
+            dot_radius,
+            0.,
+            TAU,
+        );
+        cr.fill()?;
+    }
+    drop(cr);
+
+    let data = surface.take_data().unwrap();
+    let buffer = MemoryBuffer::new(
+        data.to_vec(),
+        Fourcc::Argb8888,
+        (width, height),
+        scale,
+        Transform::Normal,
+    );
+
+    Ok(buffer)
+}
+
+pub fn draw_icon(cr: &cairo::Context, path: &Path, size: i32) -> anyhow::Result<()> {
+    let mut file = File::open(path)?;
+    let icon = ImageSurface::create_from_png(&mut file)?;
+    ensure!(icon.width() > 0 && icon.height() > 0, "icon is empty");
+
+    cr.save()?;
+    cr.scale(
+        f64::from(size) / f64::from(icon.width()),
+        f64::from(size) / f64::from(icon.height()),
+    );
+    cr.set_source_surface(&icon, 0., 0.)?;
+    cr.paint()?;
+    cr.restore()?;
+
+    Ok(())
+}
+
+/// Draws the first letter of the app name in a circle, for apps without an icon.
+pub fn draw_letter(
+    cr: &cairo::Context,
+    app_name: &str,
+    size: i32,
+    scale: f64,
+) -> anyhow::Result<()> {
+    let half = f64::from(size) / 2.;
+    let [r, g, b, a] = LETTER_COLOR.to_array_unpremul();
+    cr.set_source_rgba(r.into(), g.into(), b.into(), a.into());
+    cr.arc(half, half, half, 0., TAU);
+    cr.fill()?;
+
+    let letter: String = app_name
+        .chars()
+        .take(1)
+        .flat_map(char::to_uppercase)
+        .collect();
+
+    let mut font = FontDescription::from_string(FONT);
+    font.set_absolute_size(to_physical_precise_round(scale, font.size()));
+
+    let layout = pangocairo::functions::create_layout(cr);
+    layout.context().set_round_glyph_positions(false);
+    layout.set_font_description(Some(&font));
+    layout.set_text(&letter);
+
+    let (width, height) = layout.pixel_size();
+    cr.move_to(half - f64::from(width) / 2., half - f64::from(height) / 2.);
+    cr.set_source_rgb(1., 1., 1.);
+    pangocairo::functions::show_layout(cr, &layout);
+
+    Ok(())
+}
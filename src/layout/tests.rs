@@ -8,6 +8,8 @@ use smithay::utils::Rectangle;
 
 use super::*;
 
+mod script;
+
 impl<W: LayoutElement> Default for Layout<W> {
     fn default() -> Self {
         Self::with_options(Clock::with_time(Duration::ZERO), Default::default())
@@ -25,6 +27,7 @@ struct TestWindowInner {
     max_size: Size<i32, Logical>,
     pending_fullscreen: Cell<bool>,
     pending_activated: Cell<bool>,
+    rules: ResolvedWindowRules,
 }
 
 #[derive(Debug, Clone)]
@@ -57,6 +60,10 @@ impl TestWindowParams {
 
 impl TestWindow {
     fn new(params: TestWindowParams) -> Self {
+        Self::with_rules(params, ResolvedWindowRules::empty())
+    }
+
+    fn with_rules(params: TestWindowParams, rules: ResolvedWindowRules) -> Self {
         Self(Rc::new(TestWindowInner {
             id: params.id,
             parent_id: Cell::new(params.parent_id),
@@ -67,6 +74,7 @@ impl TestWindow {
             max_size: params.min_max_size.1,
             pending_fullscreen: Cell::new(false),
             pending_activated: Cell::new(false),
+            rules,
         }))
     }
 
@@ -155,6 +163,10 @@ impl LayoutElement for TestWindow {
         false
     }
 
+    fn title(&self) -> Option<String> {
+        None
+    }
+
     fn output_enter(&self, _output: &Output) {}
 
     fn output_leave(&self, _output: &Output) {}
@@ -171,6 +183,10 @@ impl LayoutElement for TestWindow {
         false
     }
 
+    fn is_selected(&self) -> bool {
+        false
+    }
+
     fn configure_intent(&self) -> ConfigureIntent {
         ConfigureIntent::CanSend
     }
@@ -181,6 +197,8 @@ impl LayoutElement for TestWindow {
 
     fn set_floating(&mut self, _floating: bool) {}
 
+    fn set_maximized(&mut self, _maximized: bool) {}
+
     fn is_fullscreen(&self) -> bool {
         false
     }
@@ -200,8 +218,7 @@ impl LayoutElement for TestWindow {
     fn refresh(&self) {}
 
     fn rules(&self) -> &ResolvedWindowRules {
-        static EMPTY: ResolvedWindowRules = ResolvedWindowRules::empty();
-        &EMPTY
+        &self.0.rules
     }
 
     fn animation_snapshot(&self) -> Option<&LayoutElementRenderSnapshot> {
@@ -251,6 +268,19 @@ fn arbitrary_position_change() -> impl Strategy<Value = PositionChange> {
     ]
 }
 
+fn arbitrary_snap_position() -> impl Strategy<Value = SnapPosition> {
+    prop_oneof![
+        Just(SnapPosition::Left),
+        Just(SnapPosition::Right),
+        Just(SnapPosition::Top),
+        Just(SnapPosition::Bottom),
+        Just(SnapPosition::TopLeft),
+        Just(SnapPosition::TopRight),
+        Just(SnapPosition::BottomLeft),
+        Just(SnapPosition::BottomRight),
+    ]
+}
+
 fn arbitrary_min_max() -> impl Strategy<Value = (i32, i32)> {
     prop_oneof![
         Just((0, 0)),
@@ -380,6 +410,9 @@ enum Op {
     FocusWindowOrWorkspaceDown,
     FocusWindowOrWorkspaceUp,
     FocusWindow(#[proptest(strategy = "1..=5usize")] usize),
+    FocusPreviousInWorkspace,
+    SaveFocus,
+    RestoreFocus,
     MoveColumnLeft,
     MoveColumnRight,
     MoveColumnToFirst,
@@ -418,10 +451,11 @@ enum Op {
         window_id: Option<usize>,
         #[proptest(strategy = "0..=4usize")]
         workspace_idx: usize,
+        focus: bool,
     },
     MoveColumnToWorkspaceDown,
     MoveColumnToWorkspaceUp,
-    MoveColumnToWorkspace(#[proptest(strategy = "0..=4usize")] usize),
+    MoveColumnToWorkspace(#[proptest(strategy = "0..=4usize")] usize, bool),
     MoveWorkspaceDown,
     MoveWorkspaceUp,
     MoveWorkspaceToIndex {
@@ -430,6 +464,15 @@ enum Op {
         #[proptest(strategy = "0..=4usize")]
         target_idx: usize,
     },
+    SwapWorkspaces {
+        #[proptest(strategy = "0..=5usize")]
+        idx_a: usize,
+        #[proptest(strategy = "0..=5usize")]
+        idx_b: usize,
+    },
+    SortWorkspacesByName,
+    CompactWorkspaces,
+    MoveWorkspacesToAssignedOutputs,
     MoveWorkspaceToMonitor {
         #[proptest(strategy = "proptest::option::of(1..=5usize)")]
         ws_name: Option<usize>,
@@ -453,8 +496,24 @@ enum Op {
         output_id: usize,
         #[proptest(strategy = "proptest::option::of(0..=4usize)")]
         target_ws_idx: Option<usize>,
+        focus: bool,
+    },
+    MoveWindowToWorkspaceOnOutput {
+        #[proptest(strategy = "proptest::option::of(1..=5usize)")]
+        window_id: Option<usize>,
+        #[proptest(strategy = "1..=5usize")]
+        output_id: usize,
+        #[proptest(strategy = "0..=5usize")]
+        workspace_idx: usize,
+        focus: bool,
+    },
+    MoveColumnToOutput {
+        #[proptest(strategy = "1..=5usize")]
+        output_id: usize,
+        #[proptest(strategy = "proptest::option::of(0..=4usize)")]
+        target_ws_idx: Option<usize>,
+        focus: bool,
     },
-    MoveColumnToOutput(#[proptest(strategy = "1..=5usize")] usize),
     SwitchPresetColumnWidth,
     SwitchPresetWindowWidth {
         #[proptest(strategy = "proptest::option::of(1..=5usize)")]
@@ -503,6 +562,20 @@ enum Op {
         y: PositionChange,
         animate: bool,
     },
+    SnapFloatingWindow {
+        #[proptest(strategy = "proptest::option::of(1..=5usize)")]
+        id: Option<usize>,
+        #[proptest(strategy = "arbitrary_snap_position()")]
+        position: SnapPosition,
+    },
+    CyclePictureInPictureCorner {
+        #[proptest(strategy = "proptest::option::of(1..=5usize)")]
+        id: Option<usize>,
+    },
+    BringWindowOnScreen {
+        #[proptest(strategy = "proptest::option::of(1..=5usize)")]
+        id: Option<usize>,
+    },
     SetParent {
         #[proptest(strategy = "1..=5usize")]
         id: usize,
@@ -685,6 +758,9 @@ impl Op {
                 layout.ensure_named_workspace(&WorkspaceConfig {
                     name: WorkspaceName(format!("ws{ws_name}")),
                     open_on_output: output_name.map(|name| format!("output{name}")),
+                    return_on_connect: false,
+                    icon: None,
+                    auto_close_if_empty_after: None,
                 });
             }
             Op::UnnameWorkspace { ws_name } => {
@@ -924,6 +1000,11 @@ impl Op {
             Op::FocusWindowOrWorkspaceDown => layout.focus_window_or_workspace_down(),
             Op::FocusWindowOrWorkspaceUp => layout.focus_window_or_workspace_up(),
             Op::FocusWindow(id) => layout.activate_window(&id),
+            Op::FocusPreviousInWorkspace => layout.focus_previous_in_workspace(),
+            Op::SaveFocus => layout.save_focus(),
+            Op::RestoreFocus => {
+                layout.restore_focus();
+            }
             Op::MoveColumnLeft => layout.move_left(),
             Op::MoveColumnRight => layout.move_right(),
             Op::MoveColumnToFirst => layout.move_column_to_first(),
@@ -971,22 +1052,29 @@ impl Op {
                 layout.switch_workspace_auto_back_and_forth(idx)
             }
             Op::FocusWorkspacePrevious => layout.switch_workspace_previous(),
-            Op::MoveWindowToWorkspaceDown => layout.move_to_workspace_down(),
-            Op::MoveWindowToWorkspaceUp => layout.move_to_workspace_up(),
+            Op::MoveWindowToWorkspaceDown => layout.move_to_workspace_down(true),
+            Op::MoveWindowToWorkspaceUp => layout.move_to_workspace_up(true),
             Op::MoveWindowToWorkspace {
                 window_id,
                 workspace_idx,
+                focus,
             } => {
                 let window_id = window_id.filter(|id| layout.has_window(id));
-                layout.move_to_workspace(window_id.as_ref(), workspace_idx);
+                let activate = if focus {
+                    ActivateWindow::Smart
+                } else {
+                    ActivateWindow::No
+                };
+                layout.move_to_workspace(window_id.as_ref(), workspace_idx, activate);
             }
-            Op::MoveColumnToWorkspaceDown => layout.move_column_to_workspace_down(),
-            Op::MoveColumnToWorkspaceUp => layout.move_column_to_workspace_up(),
-            Op::MoveColumnToWorkspace(idx) => layout.move_column_to_workspace(idx),
+            Op::MoveColumnToWorkspaceDown => layout.move_column_to_workspace_down(true),
+            Op::MoveColumnToWorkspaceUp => layout.move_column_to_workspace_up(true),
+            Op::MoveColumnToWorkspace(idx, focus) => layout.move_column_to_workspace(idx, focus),
             Op::MoveWindowToOutput {
                 window_id,
                 output_id: id,
                 target_ws_idx,
+                focus,
             } => {
                 let name = format!("output{id}");
                 let Some(output) = layout.outputs().find(|o| o.name() == name).cloned() else {
@@ -996,18 +1084,74 @@ impl Op {
 
                 let window_id = window_id.filter(|id| layout.has_window(id));
                 let target_ws_idx = target_ws_idx.filter(|idx| mon.workspaces.len() > *idx);
-                layout.move_to_output(window_id.as_ref(), &output, target_ws_idx);
+                let activate = if focus {
+                    ActivateWindow::Smart
+                } else {
+                    ActivateWindow::No
+                };
+                layout.move_to_output(window_id.as_ref(), &output, target_ws_idx, activate);
+            }
+            Op::MoveWindowToWorkspaceOnOutput {
+                window_id,
+                output_id: id,
+                workspace_idx,
+                focus,
+            } => {
+                let name = format!("output{id}");
+                let Some(output) = layout.outputs().find(|o| o.name() == name).cloned() else {
+                    return;
+                };
+
+                let window_id = window_id.filter(|id| layout.has_window(id));
+                let activate = if focus {
+                    ActivateWindow::Smart
+                } else {
+                    ActivateWindow::No
+                };
+                layout.move_to_workspace_on_output(
+                    window_id.as_ref(),
+                    &output,
+                    workspace_idx,
+                    activate,
+                );
             }
-            Op::MoveColumnToOutput(id) => {
+            Op::MoveColumnToOutput {
+                output_id: id,
+                target_ws_idx,
+                focus,
+            } => {
                 let name = format!("output{id}");
                 let Some(output) = layout.outputs().find(|o| o.name() == name).cloned() else {
                     return;
                 };
 
-                layout.move_column_to_output(&output);
+                layout.move_column_to_output(&output, target_ws_idx, focus);
             }
             Op::MoveWorkspaceDown => layout.move_workspace_down(),
             Op::MoveWorkspaceUp => layout.move_workspace_up(),
+            Op::SortWorkspacesByName => layout.sort_workspaces_by_name(),
+            Op::SwapWorkspaces { idx_a, idx_b } => {
+                // Index into all workspaces to also reach the ones on other monitors.
+                let ids: Vec<_> = layout.workspaces().map(|(_, _, ws)| ws.id()).collect();
+                let (Some(id_a), Some(id_b)) = (ids.get(idx_a), ids.get(idx_b)) else {
+                    return;
+                };
+                layout.swap_workspaces(*id_a, *id_b);
+            }
+            Op::CompactWorkspaces => layout.compact_workspaces(),
+            Op::MoveWorkspacesToAssignedOutputs => {
+                // Assign every named workspace to the output with the same number.
+                let ws_configs: Vec<_> = (0..5)
+                    .map(|n| WorkspaceConfig {
+                        name: WorkspaceName(format!("ws{n}")),
+                        open_on_output: Some(format!("output{n}")),
+                        return_on_connect: false,
+                        icon: None,
+                        auto_close_if_empty_after: None,
+                    })
+                    .collect();
+                layout.move_workspaces_to_assigned_outputs(&ws_configs);
+            }
             Op::MoveWorkspaceToIndex {
                 ws_name: Some(ws_name),
                 target_idx,
@@ -1124,6 +1268,18 @@ impl Op {
                 let id = id.filter(|id| layout.has_window(id));
                 layout.move_floating_window(id.as_ref(), x, y, animate);
             }
+            Op::SnapFloatingWindow { id, position } => {
+                let id = id.filter(|id| layout.has_window(id));
+                layout.snap_floating_window(id.as_ref(), position);
+            }
+            Op::CyclePictureInPictureCorner { id } => {
+                let id = id.filter(|id| layout.has_window(id));
+                layout.cycle_picture_in_picture_corner(id.as_ref());
+            }
+            Op::BringWindowOnScreen { id } => {
+                let id = id.filter(|id| layout.has_window(id));
+                layout.bring_window_on_screen(id.as_ref());
+            }
             Op::SetParent {
                 id,
                 mut new_parent_id,
@@ -1420,6 +1576,8 @@ fn operations_dont_panic() {
         Op::FocusWindowDownOrColumnLeft,
         Op::FocusWindowDownOrColumnRight,
         Op::FocusWindowOrWorkspaceDown,
+        Op::FocusPreviousInWorkspace,
+        Op::SwapWorkspaces { idx_a: 0, idx_b: 1 },
         Op::MoveColumnLeft,
         Op::MoveColumnRight,
         Op::MoveColumnLeftOrToMonitorLeft(0),
@@ -1436,15 +1594,17 @@ fn operations_dont_panic() {
         Op::MoveWindowToWorkspace {
             window_id: None,
             workspace_idx: 1,
+            focus: true,
         },
         Op::MoveWindowToWorkspace {
             window_id: None,
             workspace_idx: 2,
+            focus: true,
         },
         Op::MoveColumnToWorkspaceDown,
         Op::MoveColumnToWorkspaceUp,
-        Op::MoveColumnToWorkspace(1),
-        Op::MoveColumnToWorkspace(2),
+        Op::MoveColumnToWorkspace(1, true),
+        Op::MoveColumnToWorkspace(2, true),
         Op::MoveWindowDown,
         Op::MoveWindowDownOrToWorkspaceDown,
         Op::MoveWindowUp,
@@ -1506,6 +1666,7 @@ fn operations_from_starting_state_dont_panic() {
             window_id: None,
             output_id: 2,
             target_ws_idx: None,
+            focus: true,
         },
         Op::FocusOutput(1),
         Op::Communicate(1),
@@ -1589,6 +1750,8 @@ fn operations_from_starting_state_dont_panic() {
         Op::FocusWindowDownOrColumnLeft,
         Op::FocusWindowDownOrColumnRight,
         Op::FocusWindowOrWorkspaceDown,
+        Op::FocusPreviousInWorkspace,
+        Op::SwapWorkspaces { idx_a: 0, idx_b: 1 },
         Op::MoveColumnLeft,
         Op::MoveColumnRight,
         Op::MoveColumnLeftOrToMonitorLeft(0),
@@ -1606,20 +1769,23 @@ fn operations_from_starting_state_dont_panic() {
         Op::MoveWindowToWorkspace {
             window_id: None,
             workspace_idx: 1,
+            focus: true,
         },
         Op::MoveWindowToWorkspace {
             window_id: None,
             workspace_idx: 2,
+            focus: true,
         },
         Op::MoveWindowToWorkspace {
             window_id: None,
             workspace_idx: 3,
+            focus: true,
         },
         Op::MoveColumnToWorkspaceDown,
         Op::MoveColumnToWorkspaceUp,
-        Op::MoveColumnToWorkspace(1),
-        Op::MoveColumnToWorkspace(2),
-        Op::MoveColumnToWorkspace(3),
+        Op::MoveColumnToWorkspace(1, true),
+        Op::MoveColumnToWorkspace(2, true),
+        Op::MoveColumnToWorkspace(3, true),
         Op::MoveWindowDown,
         Op::MoveWindowDownOrToWorkspaceDown,
         Op::MoveWindowUp,
@@ -1722,6 +1888,7 @@ fn move_to_workspace_by_idx_does_not_leave_empty_workspaces() {
         Op::MoveWindowToWorkspace {
             window_id: Some(0),
             workspace_idx: 2,
+            focus: true,
         },
     ];
 
@@ -1891,6 +2058,45 @@ fn workspace_cleanup_during_switch() {
     check_ops(&ops);
 }
 
+#[test]
+fn startup_placeholder_does_not_animate() {
+    let mut layout = check_ops(&[Op::AddOutput(1)]);
+
+    let id = StartupPlaceholderId::next();
+    let app = StartupApp {
+        name: String::from("foot"),
+        ..Default::default()
+    };
+    layout.add_startup_placeholder(id, app);
+    assert!(layout.has_startup_placeholder(id));
+
+    // The timeout is up to the caller, so the placeholder stays regardless of the clock.
+    Op::AdvanceAnimations { msec_delta: 20000 }.apply(&mut layout);
+    assert!(layout.has_startup_placeholder(id));
+    assert!(!layout.are_animations_ongoing(None));
+
+    layout.remove_startup_placeholder(id);
+    assert!(!layout.has_startup_placeholder(id));
+}
+
+#[test]
+fn startup_placeholder_removed_by_app_id() {
+    let mut layout = check_ops(&[Op::AddOutput(1)]);
+
+    let id = StartupPlaceholderId::next();
+    let app = StartupApp {
+        name: String::from("nautilus"),
+        ..Default::default()
+    };
+    layout.add_startup_placeholder(id, app);
+
+    layout.remove_startup_placeholder_for_app_id("org.gnome.TextEditor");
+    assert!(layout.has_startup_placeholder(id));
+
+    layout.remove_startup_placeholder_for_app_id("org.gnome.Nautilus");
+    assert!(!layout.has_startup_placeholder(id));
+}
+
 #[test]
 fn workspace_transfer_during_switch() {
     let ops = [
@@ -2085,72 +2291,103 @@ fn open_right_of_on_different_workspace_ewaf() {
 }
 
 #[test]
-fn unfullscreen_view_offset_not_reset_on_removal() {
+fn restore_focus_after_workspace_switch() {
     let ops = [
         Op::AddOutput(1),
         Op::AddWindow {
             params: TestWindowParams::new(0),
         },
-        Op::FullscreenWindow(0),
         Op::AddWindow {
             params: TestWindowParams::new(1),
         },
-        Op::ConsumeOrExpelWindowRight { id: None },
+        Op::ConsumeWindowIntoColumn,
+        Op::AddWindow {
+            params: TestWindowParams::new(2),
+        },
+        Op::FocusColumnLeft,
+        Op::FocusWindowUp,
+        Op::SaveFocus,
+        Op::FocusWorkspaceDown,
+        Op::AddWindow {
+            params: TestWindowParams::new(3),
+        },
+        Op::FocusWorkspaceDown,
+        Op::RestoreFocus,
     ];
 
-    check_ops(&ops);
+    let layout = check_ops(&ops);
+    assert_eq!(layout.focus().map(|win| win.id()), Some(&0));
+
+    let MonitorSet::Normal { monitors, .. } = layout.monitor_set else {
+        unreachable!()
+    };
+    assert_eq!(monitors[0].active_workspace_idx, 0);
+    assert_eq!(monitors[0].workspaces[0].scrolling().active_column_idx(), 0);
 }
 
 #[test]
-fn unfullscreen_view_offset_not_reset_on_consume() {
+fn focus_previous_in_workspace() {
     let ops = [
         Op::AddOutput(1),
         Op::AddWindow {
-            params: TestWindowParams::new(0),
+            params: TestWindowParams::new(1),
         },
-        Op::FullscreenWindow(0),
         Op::AddWindow {
-            params: TestWindowParams::new(1),
+            params: TestWindowParams::new(2),
         },
-        Op::ConsumeWindowIntoColumn,
+        Op::AddWindow {
+            params: TestWindowParams::new(3),
+        },
+        Op::Refresh { is_active: true },
+        Op::FocusWindow(1),
+        Op::Refresh { is_active: true },
+        Op::FocusPreviousInWorkspace,
     ];
 
-    check_ops(&ops);
-}
+    let mut layout = check_ops(&ops);
+    assert_eq!(layout.focus().map(|win| win.id()), Some(&3));
 
-#[test]
-fn unfullscreen_view_offset_not_reset_on_quick_double_toggle() {
+    // Focus on other workspaces doesn't count.
     let ops = [
-        Op::AddOutput(1),
+        Op::Refresh { is_active: true },
+        Op::FocusWorkspaceDown,
         Op::AddWindow {
-            params: TestWindowParams::new(0),
+            params: TestWindowParams::new(4),
         },
-        Op::FullscreenWindow(0),
-        Op::FullscreenWindow(0),
+        Op::Refresh { is_active: true },
+        Op::FocusWorkspaceUp,
+        Op::FocusPreviousInWorkspace,
     ];
-
-    check_ops(&ops);
+    for op in ops {
+        op.apply(&mut layout);
+        layout.verify_invariants();
+    }
+    assert_eq!(layout.focus().map(|win| win.id()), Some(&1));
 }
 
 #[test]
-fn unfullscreen_view_offset_set_on_fullscreening_inactive_tile_in_column() {
+fn restore_focus_across_outputs() {
     let ops = [
         Op::AddOutput(1),
+        Op::AddOutput(2),
         Op::AddWindow {
             params: TestWindowParams::new(0),
         },
+        Op::SaveFocus,
+        Op::FocusOutput(2),
         Op::AddWindow {
             params: TestWindowParams::new(1),
         },
-        Op::ConsumeOrExpelWindowLeft { id: None },
-        Op::FullscreenWindow(0),
+        Op::FocusWorkspaceDown,
+        Op::RestoreFocus,
     ];
 
-    check_ops(&ops);
+    let layout = check_ops(&ops);
+    assert_eq!(layout.focus().map(|win| win.id()), Some(&0));
 }
 
 #[test]
-fn unfullscreen_view_offset_not_reset_on_gesture() {
+fn restore_focus_ignores_closed_window() {
     let ops = [
         Op::AddOutput(1),
         Op::AddWindow {
@@ -2159,98 +2396,95 @@ fn unfullscreen_view_offset_not_reset_on_gesture() {
         Op::AddWindow {
             params: TestWindowParams::new(1),
         },
-        Op::FullscreenWindow(1),
-        Op::ViewOffsetGestureBegin {
-            output_idx: 1,
-            is_touchpad: true,
-        },
-        Op::ViewOffsetGestureEnd {
-            is_touchpad: Some(true),
-        },
+        Op::SaveFocus,
+        Op::FocusWorkspaceDown,
+        Op::FocusWorkspaceUp,
+        Op::FocusColumnLeft,
+        Op::CloseWindow(1),
+        Op::FocusWorkspaceDown,
+        Op::RestoreFocus,
     ];
 
-    check_ops(&ops);
+    let layout = check_ops(&ops);
+    assert!(layout.focus().is_none());
 }
 
 #[test]
-fn removing_all_outputs_preserves_empty_named_workspaces() {
+fn auto_closed_workspace_is_removed_when_inactive() {
     let ops = [
         Op::AddOutput(1),
-        Op::AddNamedWorkspace {
-            ws_name: 1,
-            output_name: None,
+        Op::AddWindow {
+            params: TestWindowParams::new(0),
         },
         Op::AddNamedWorkspace {
-            ws_name: 2,
-            output_name: None,
+            ws_name: 1,
+            output_name: Some(1),
         },
-        Op::RemoveOutput(1),
     ];
+    let mut layout = check_ops(&ops);
 
-    let layout = check_ops(&ops);
-
-    let MonitorSet::NoOutputs { workspaces } = layout.monitor_set else {
-        unreachable!()
-    };
+    // Auto-close unnames the empty workspace by its id, which cleans it up.
+    let (_, ws) = layout.find_workspace_by_name("ws1").unwrap();
+    let id = ws.id();
+    assert_ne!(layout.active_workspace().unwrap().id(), id);
+    let count = layout.workspaces().count();
 
-    assert_eq!(workspaces.len(), 2);
+    layout.unname_workspace_by_id(id);
+    layout.verify_invariants();
+    assert!(layout.find_workspace_by_name("ws1").is_none());
+    assert!(layout.workspaces().all(|(_, _, ws)| ws.id() != id));
+    assert_eq!(layout.workspaces().count(), count - 1);
 }
 
 #[test]
-fn config_change_updates_cached_sizes() {
-    let mut config = Config::default();
-    config.layout.border.off = false;
-    config.layout.border.width = FloatOrInt(2.);
-
-    let mut layout = Layout::new(Clock::default(), &config);
-
-    Op::AddWindow {
-        params: TestWindowParams {
-            bbox: Rectangle::from_size(Size::from((1280, 200))),
-            ..TestWindowParams::new(1)
-        },
-    }
-    .apply(&mut layout);
+fn auto_closed_workspace_is_removed_without_outputs() {
+    let ops = [Op::AddNamedWorkspace {
+        ws_name: 1,
+        output_name: None,
+    }];
+    let mut layout = check_ops(&ops);
 
-    config.layout.border.width = FloatOrInt(4.);
-    layout.update_config(&config);
+    let (_, ws) = layout.find_workspace_by_name("ws1").unwrap();
+    let id = ws.id();
 
+    layout.unname_workspace_by_id(id);
     layout.verify_invariants();
+    assert_eq!(layout.workspaces().count(), 0);
 }
 
 #[test]
-fn preset_height_change_removes_preset() {
-    let mut config = Config::default();
-    config.layout.preset_window_heights = vec![PresetSize::Fixed(1), PresetSize::Fixed(2)];
-
-    let mut layout = Layout::new(Clock::default(), &config);
-
+fn new_column_position_first() {
     let ops = [
         Op::AddOutput(1),
+        Op::AddWindow {
+            params: TestWindowParams::new(0),
+        },
         Op::AddWindow {
             params: TestWindowParams::new(1),
         },
         Op::AddWindow {
             params: TestWindowParams::new(2),
         },
-        Op::ConsumeOrExpelWindowLeft { id: None },
-        Op::SwitchPresetWindowHeight { id: None },
-        Op::SwitchPresetWindowHeight { id: None },
     ];
-    for op in ops {
-        op.apply(&mut layout);
-    }
-
-    // Leave only one.
-    config.layout.preset_window_heights = vec![PresetSize::Fixed(1)];
 
-    layout.update_config(&config);
+    let options = Options {
+        new_column_position: NewColumnPosition::First,
+        ..Default::default()
+    };
+    let layout = check_ops_with_options(options, &ops);
 
-    layout.verify_invariants();
+    let ws = layout.active_workspace().unwrap();
+    let ids: Vec<_> = ws
+        .scrolling()
+        .tiles()
+        .map(|tile| *tile.window().id())
+        .collect();
+    assert_eq!(ids, [2, 1, 0]);
+    assert_eq!(ws.scrolling().active_column_idx(), 0);
 }
 
 #[test]
-fn set_window_height_recomputes_to_auto() {
+fn new_column_position_does_not_affect_moves() {
     let ops = [
         Op::AddOutput(1),
         Op::AddWindow {
@@ -2259,27 +2493,34 @@ fn set_window_height_recomputes_to_auto() {
         Op::AddWindow {
             params: TestWindowParams::new(1),
         },
-        Op::ConsumeOrExpelWindowLeft { id: None },
+        Op::FocusColumnRight,
+        Op::FocusWorkspaceDown,
         Op::AddWindow {
             params: TestWindowParams::new(2),
         },
-        Op::ConsumeOrExpelWindowLeft { id: None },
-        Op::SetWindowHeight {
-            id: None,
-            change: SizeChange::SetFixed(100),
-        },
-        Op::FocusWindowUp,
-        Op::SetWindowHeight {
-            id: None,
-            change: SizeChange::SetFixed(200),
-        },
+        Op::FocusWorkspaceUp,
+        // Window 0 moves in after the focused column, like it would without the option.
+        Op::MoveWindowToWorkspaceDown,
     ];
 
-    check_ops(&ops);
+    let options = Options {
+        new_column_position: NewColumnPosition::First,
+        ..Default::default()
+    };
+    let layout = check_ops_with_options(options, &ops);
+
+    let ws = layout.active_workspace().unwrap();
+    let ids: Vec<_> = ws
+        .scrolling()
+        .tiles()
+        .map(|tile| *tile.window().id())
+        .collect();
+    assert_eq!(ids, [2, 0]);
+    assert_eq!(layout.focus().map(|win| win.id()), Some(&0));
 }
 
 #[test]
-fn one_window_in_column_becomes_weight_1() {
+fn new_window_position_in_column_after_focused() {
     let ops = [
         Op::AddOutput(1),
         Op::AddWindow {
@@ -2289,18 +2530,246 @@ fn one_window_in_column_becomes_weight_1() {
             params: TestWindowParams::new(1),
         },
         Op::ConsumeOrExpelWindowLeft { id: None },
+        Op::FocusWindowUp,
         Op::AddWindow {
             params: TestWindowParams::new(2),
         },
         Op::ConsumeOrExpelWindowLeft { id: None },
-        Op::SetWindowHeight {
-            id: None,
-            change: SizeChange::SetFixed(100),
-        },
-        Op::Communicate(2),
-        Op::FocusWindowUp,
-        Op::SetWindowHeight {
-            id: None,
+    ];
+
+    let options = Options {
+        new_window_position_in_column: NewWindowPositionInColumn::AfterFocused,
+        ..Default::default()
+    };
+    let layout = check_ops_with_options(options, &ops);
+
+    let ws = layout.active_workspace().unwrap();
+    let ids: Vec<_> = ws
+        .scrolling()
+        .tiles()
+        .map(|tile| *tile.window().id())
+        .collect();
+    assert_eq!(ids, [0, 2, 1]);
+    assert_eq!(layout.focus().map(|win| win.id()), Some(&2));
+}
+
+#[test]
+fn unfullscreen_view_offset_not_reset_on_removal() {
+    let ops = [
+        Op::AddOutput(1),
+        Op::AddWindow {
+            params: TestWindowParams::new(0),
+        },
+        Op::FullscreenWindow(0),
+        Op::AddWindow {
+            params: TestWindowParams::new(1),
+        },
+        Op::ConsumeOrExpelWindowRight { id: None },
+    ];
+
+    check_ops(&ops);
+}
+
+#[test]
+fn unfullscreen_view_offset_not_reset_on_consume() {
+    let ops = [
+        Op::AddOutput(1),
+        Op::AddWindow {
+            params: TestWindowParams::new(0),
+        },
+        Op::FullscreenWindow(0),
+        Op::AddWindow {
+            params: TestWindowParams::new(1),
+        },
+        Op::ConsumeWindowIntoColumn,
+    ];
+
+    check_ops(&ops);
+}
+
+#[test]
+fn unfullscreen_view_offset_not_reset_on_quick_double_toggle() {
+    let ops = [
+        Op::AddOutput(1),
+        Op::AddWindow {
+            params: TestWindowParams::new(0),
+        },
+        Op::FullscreenWindow(0),
+        Op::FullscreenWindow(0),
+    ];
+
+    check_ops(&ops);
+}
+
+#[test]
+fn unfullscreen_view_offset_set_on_fullscreening_inactive_tile_in_column() {
+    let ops = [
+        Op::AddOutput(1),
+        Op::AddWindow {
+            params: TestWindowParams::new(0),
+        },
+        Op::AddWindow {
+            params: TestWindowParams::new(1),
+        },
+        Op::ConsumeOrExpelWindowLeft { id: None },
+        Op::FullscreenWindow(0),
+    ];
+
+    check_ops(&ops);
+}
+
+#[test]
+fn unfullscreen_view_offset_not_reset_on_gesture() {
+    let ops = [
+        Op::AddOutput(1),
+        Op::AddWindow {
+            params: TestWindowParams::new(0),
+        },
+        Op::AddWindow {
+            params: TestWindowParams::new(1),
+        },
+        Op::FullscreenWindow(1),
+        Op::ViewOffsetGestureBegin {
+            output_idx: 1,
+            is_touchpad: true,
+        },
+        Op::ViewOffsetGestureEnd {
+            is_touchpad: Some(true),
+        },
+    ];
+
+    check_ops(&ops);
+}
+
+#[test]
+fn removing_all_outputs_preserves_empty_named_workspaces() {
+    let ops = [
+        Op::AddOutput(1),
+        Op::AddNamedWorkspace {
+            ws_name: 1,
+            output_name: None,
+        },
+        Op::AddNamedWorkspace {
+            ws_name: 2,
+            output_name: None,
+        },
+        Op::RemoveOutput(1),
+    ];
+
+    let layout = check_ops(&ops);
+
+    let MonitorSet::NoOutputs { workspaces } = layout.monitor_set else {
+        unreachable!()
+    };
+
+    assert_eq!(workspaces.len(), 2);
+}
+
+#[test]
+fn config_change_updates_cached_sizes() {
+    let mut config = Config::default();
+    config.layout.border.off = false;
+    config.layout.border.width = FloatOrInt(2.);
+
+    let mut layout = Layout::new(Clock::default(), &config);
+
+    Op::AddWindow {
+        params: TestWindowParams {
+            bbox: Rectangle::from_size(Size::from((1280, 200))),
+            ..TestWindowParams::new(1)
+        },
+    }
+    .apply(&mut layout);
+
+    config.layout.border.width = FloatOrInt(4.);
+    layout.update_config(&config);
+
+    layout.verify_invariants();
+}
+
+#[test]
+fn preset_height_change_removes_preset() {
+    let mut config = Config::default();
+    config.layout.preset_window_heights = vec![PresetSize::Fixed(1), PresetSize::Fixed(2)];
+
+    let mut layout = Layout::new(Clock::default(), &config);
+
+    let ops = [
+        Op::AddOutput(1),
+        Op::AddWindow {
+            params: TestWindowParams::new(1),
+        },
+        Op::AddWindow {
+            params: TestWindowParams::new(2),
+        },
+        Op::ConsumeOrExpelWindowLeft { id: None },
+        Op::SwitchPresetWindowHeight { id: None },
+        Op::SwitchPresetWindowHeight { id: None },
+    ];
+    for op in ops {
+        op.apply(&mut layout);
+    }
+
+    // Leave only one.
+    config.layout.preset_window_heights = vec![PresetSize::Fixed(1)];
+
+    layout.update_config(&config);
+
+    layout.verify_invariants();
+}
+
+#[test]
+fn set_window_height_recomputes_to_auto() {
+    let ops = [
+        Op::AddOutput(1),
+        Op::AddWindow {
+            params: TestWindowParams::new(0),
+        },
+        Op::AddWindow {
+            params: TestWindowParams::new(1),
+        },
+        Op::ConsumeOrExpelWindowLeft { id: None },
+        Op::AddWindow {
+            params: TestWindowParams::new(2),
+        },
+        Op::ConsumeOrExpelWindowLeft { id: None },
+        Op::SetWindowHeight {
+            id: None,
+            change: SizeChange::SetFixed(100),
+        },
+        Op::FocusWindowUp,
+        Op::SetWindowHeight {
+            id: None,
+            change: SizeChange::SetFixed(200),
+        },
+    ];
+
+    check_ops(&ops);
+}
+
+#[test]
+fn one_window_in_column_becomes_weight_1() {
+    let ops = [
+        Op::AddOutput(1),
+        Op::AddWindow {
+            params: TestWindowParams::new(0),
+        },
+        Op::AddWindow {
+            params: TestWindowParams::new(1),
+        },
+        Op::ConsumeOrExpelWindowLeft { id: None },
+        Op::AddWindow {
+            params: TestWindowParams::new(2),
+        },
+        Op::ConsumeOrExpelWindowLeft { id: None },
+        Op::SetWindowHeight {
+            id: None,
+            change: SizeChange::SetFixed(100),
+        },
+        Op::Communicate(2),
+        Op::FocusWindowUp,
+        Op::SetWindowHeight {
+            id: None,
             change: SizeChange::SetFixed(200),
         },
         Op::Communicate(1),
@@ -2778,37 +3247,265 @@ fn interactive_resize_to_negative() {
 }
 
 #[test]
-fn windows_on_other_workspaces_remain_activated() {
+fn snap_floating_window_to_quarter() {
     let ops = [
-        Op::AddOutput(3),
+        Op::AddOutput(1),
         Op::AddWindow {
-            params: TestWindowParams::new(3),
+            params: TestWindowParams::new(1),
+        },
+        Op::ToggleWindowFloating { id: Some(1) },
+        Op::SnapFloatingWindow {
+            id: None,
+            position: SnapPosition::BottomRight,
         },
-        Op::FocusWorkspaceDown,
-        Op::Refresh { is_active: true },
     ];
 
     let layout = check_ops(&ops);
-    let (_, win) = layout.windows().next().unwrap();
-    assert!(win.0.pending_activated.get());
+    let win = layout.windows().next().unwrap().1;
+    assert_eq!(win.requested_size().unwrap(), Size::from((640, 360)));
 }
 
 #[test]
-fn stacking_add_parent_brings_up_child() {
+fn interactive_move_floating_snaps_at_edge() {
     let ops = [
-        Op::AddOutput(0),
+        Op::AddOutput(1),
         Op::AddWindow {
-            params: TestWindowParams {
-                is_floating: true,
-                parent_id: Some(1),
-                ..TestWindowParams::new(0)
-            },
+            params: TestWindowParams::new(1),
         },
-        Op::AddWindow {
-            params: TestWindowParams {
-                is_floating: true,
-                ..TestWindowParams::new(1)
-            },
+        Op::ToggleWindowFloating { id: Some(1) },
+        Op::InteractiveMoveBegin {
+            window: 1,
+            output_idx: 1,
+            px: 640.,
+            py: 360.,
+        },
+        Op::InteractiveMoveUpdate {
+            window: 1,
+            dx: -640.,
+            dy: 0.,
+            output_idx: 1,
+            px: 0.,
+            py: 360.,
+        },
+        Op::InteractiveMoveEnd { window: 1 },
+    ];
+
+    let layout = check_ops(&ops);
+    let win = layout.windows().next().unwrap().1;
+    assert_eq!(win.requested_size().unwrap(), Size::from((640, 720)));
+}
+
+fn add_picture_in_picture_window(layout: &mut Layout<TestWindow>, id: usize) {
+    let rules = ResolvedWindowRules {
+        picture_in_picture: Some(true),
+        ..ResolvedWindowRules::empty()
+    };
+    let win = TestWindow::with_rules(TestWindowParams::new(id), rules);
+    layout.add_window(
+        win,
+        AddWindowTarget::Auto,
+        None,
+        None,
+        false,
+        true,
+        ActivateWindow::default(),
+    );
+    layout.verify_invariants();
+}
+
+#[test]
+fn picture_in_picture_follows_active_workspace() {
+    let mut layout = check_ops(&[Op::AddOutput(1)]);
+    add_picture_in_picture_window(&mut layout, 1);
+
+    Op::FocusWorkspaceDown.apply(&mut layout);
+    Op::AdvanceAnimations { msec_delta: 1000 }.apply(&mut layout);
+    Op::Refresh { is_active: true }.apply(&mut layout);
+    layout.verify_invariants();
+
+    let ws = layout.active_workspace().unwrap();
+    assert!(ws.has_window(&1));
+    assert_eq!(layout.workspaces().count(), 2);
+}
+
+#[test]
+fn picture_in_picture_stays_where_moved() {
+    let mut layout = check_ops(&[Op::AddOutput(1)]);
+    add_picture_in_picture_window(&mut layout, 1);
+    Op::Refresh { is_active: true }.apply(&mut layout);
+
+    let ops = [
+        Op::MoveWindowToWorkspace {
+            window_id: Some(1),
+            workspace_idx: 1,
+            focus: false,
+        },
+        Op::AdvanceAnimations { msec_delta: 1000 },
+        Op::Refresh { is_active: true },
+    ];
+    for op in ops {
+        op.apply(&mut layout);
+        layout.verify_invariants();
+    }
+
+    // Without a workspace switch, the window stays on the workspace it was moved to.
+    let ws = layout.active_workspace().unwrap();
+    assert!(!ws.has_window(&1));
+    assert!(layout.has_window(&1));
+}
+
+fn first_tile_pos(layout: &Layout<TestWindow>) -> Point<f64, Logical> {
+    let ws = layout.active_workspace().unwrap();
+    let (_, pos, _) = ws.tiles_with_render_positions().next().unwrap();
+    pos
+}
+
+#[test]
+fn picture_in_picture_cycles_corners() {
+    let mut layout = check_ops(&[Op::AddOutput(1)]);
+    add_picture_in_picture_window(&mut layout, 1);
+
+    // Opens in the bottom-right corner.
+    assert_eq!(
+        first_tile_pos(&layout),
+        Point::from((1280. - 100. - 16., 720. - 200. - 16.))
+    );
+
+    Op::CyclePictureInPictureCorner { id: None }.apply(&mut layout);
+    Op::AdvanceAnimations { msec_delta: 1000 }.apply(&mut layout);
+    assert_eq!(
+        first_tile_pos(&layout),
+        Point::from((16., 720. - 200. - 16.))
+    );
+
+    Op::CyclePictureInPictureCorner { id: None }.apply(&mut layout);
+    Op::AdvanceAnimations { msec_delta: 1000 }.apply(&mut layout);
+    assert_eq!(first_tile_pos(&layout), Point::from((16., 16.)));
+}
+
+#[test]
+fn bring_floating_window_on_screen() {
+    let ops = [
+        Op::AddOutput(1),
+        Op::AddWindow {
+            params: TestWindowParams {
+                is_floating: true,
+                ..TestWindowParams::new(1)
+            },
+        },
+        Op::MoveFloatingWindow {
+            id: None,
+            x: PositionChange::SetFixed(5000.),
+            y: PositionChange::SetFixed(0.),
+            animate: false,
+        },
+    ];
+    let options = Options {
+        floating_min_visible: Some(50.),
+        ..Options::default()
+    };
+    let mut layout = check_ops_with_options(options, &ops);
+
+    // Only the minimum visible strip remains on screen.
+    assert_eq!(first_tile_pos(&layout), Point::from((1280. - 50., 0.)));
+
+    Op::BringWindowOnScreen { id: None }.apply(&mut layout);
+    Op::AdvanceAnimations { msec_delta: 1000 }.apply(&mut layout);
+    assert_eq!(first_tile_pos(&layout), Point::from((1280. - 100., 0.)));
+}
+
+fn window_output_name(layout: &Layout<TestWindow>, id: usize) -> String {
+    let (mon, _, _) = layout
+        .workspaces()
+        .find(|(_, _, ws)| ws.has_window(&id))
+        .unwrap();
+    mon.unwrap().output_name().clone()
+}
+
+#[test]
+fn workspace_spillover_to_named_output() {
+    let ops = [
+        Op::AddOutput(1),
+        Op::AddOutput(2),
+        Op::AddOutput(3),
+        Op::FocusOutput(3),
+        Op::AddWindow {
+            params: TestWindowParams::new(1),
+        },
+        Op::RemoveOutput(3),
+    ];
+
+    let options = Options {
+        workspace_spillover: WorkspaceSpillover::Output(String::from("output2")),
+        ..Default::default()
+    };
+    let mut layout = check_ops_with_options(options, &ops);
+    assert_eq!(window_output_name(&layout, 1), "output2");
+
+    // The workspace returns once the output reconnects.
+    Op::AddOutput(3).apply(&mut layout);
+    layout.verify_invariants();
+    assert_eq!(window_output_name(&layout, 1), "output3");
+}
+
+#[test]
+fn workspace_spillover_distribute() {
+    let ops = [
+        Op::AddOutput(1),
+        Op::AddOutput(2),
+        Op::AddOutput(3),
+        Op::FocusOutput(3),
+        Op::AddWindow {
+            params: TestWindowParams::new(1),
+        },
+        Op::FocusWorkspaceDown,
+        Op::AddWindow {
+            params: TestWindowParams::new(2),
+        },
+        Op::RemoveOutput(3),
+    ];
+
+    let options = Options {
+        workspace_spillover: WorkspaceSpillover::Distribute,
+        ..Default::default()
+    };
+    let layout = check_ops_with_options(options, &ops);
+    assert_eq!(window_output_name(&layout, 1), "output1");
+    assert_eq!(window_output_name(&layout, 2), "output2");
+}
+
+#[test]
+fn windows_on_other_workspaces_remain_activated() {
+    let ops = [
+        Op::AddOutput(3),
+        Op::AddWindow {
+            params: TestWindowParams::new(3),
+        },
+        Op::FocusWorkspaceDown,
+        Op::Refresh { is_active: true },
+    ];
+
+    let layout = check_ops(&ops);
+    let (_, win) = layout.windows().next().unwrap();
+    assert!(win.0.pending_activated.get());
+}
+
+#[test]
+fn stacking_add_parent_brings_up_child() {
+    let ops = [
+        Op::AddOutput(0),
+        Op::AddWindow {
+            params: TestWindowParams {
+                is_floating: true,
+                parent_id: Some(1),
+                ..TestWindowParams::new(0)
+            },
+        },
+        Op::AddWindow {
+            params: TestWindowParams {
+                is_floating: true,
+                ..TestWindowParams::new(1)
+            },
         },
     ];
 
@@ -2917,12 +3614,41 @@ fn move_window_to_workspace_with_different_active_output() {
         Op::MoveWindowToWorkspace {
             window_id: Some(0),
             workspace_idx: 2,
+            focus: true,
         },
     ];
 
     check_ops(&ops);
 }
 
+#[test]
+fn move_window_to_workspace_on_output_past_the_end() {
+    let ops = [
+        Op::AddOutput(1),
+        Op::AddOutput(2),
+        Op::AddWindow {
+            params: TestWindowParams::new(1),
+        },
+        Op::AddWindow {
+            params: TestWindowParams::new(2),
+        },
+        Op::MoveWindowToWorkspaceOnOutput {
+            window_id: Some(1),
+            output_id: 2,
+            workspace_idx: 5,
+            focus: false,
+        },
+    ];
+
+    let layout = check_ops(&ops);
+    let output = layout.outputs().find(|o| o.name() == "output2").unwrap();
+    let mon = layout.monitor_for_output(output).unwrap();
+    // The window ended up on a new workspace, followed by a new empty one.
+    assert_eq!(mon.workspaces.len(), 2);
+    assert!(mon.workspaces[0].has_window(&1));
+    assert_eq!(layout.active_output().unwrap().name(), "output1");
+}
+
 #[test]
 fn set_first_workspace_name() {
     let ops = [
@@ -3002,6 +3728,260 @@ fn move_workspace_to_same_monitor_doesnt_reorder() {
     assert_eq!(counts, &[1, 2, 0]);
 }
 
+#[test]
+fn sort_workspaces_by_name() {
+    let ops = [
+        Op::AddOutput(0),
+        Op::AddWindow {
+            params: TestWindowParams::new(0),
+        },
+        Op::FocusWorkspaceDown,
+        Op::SetWorkspaceName {
+            new_ws_name: 2,
+            ws_name: None,
+        },
+        Op::FocusWorkspaceDown,
+        Op::SetWorkspaceName {
+            new_ws_name: 1,
+            ws_name: None,
+        },
+        Op::SortWorkspacesByName,
+    ];
+
+    let layout = check_ops(&ops);
+    let names: Vec<_> = layout
+        .workspaces()
+        .map(|(_, _, ws)| ws.name().map(String::as_str))
+        .collect();
+    assert_eq!(names, &[Some("ws1"), Some("ws2"), None, None]);
+}
+
+#[test]
+fn move_workspaces_to_assigned_outputs() {
+    let ops = [
+        Op::AddOutput(1),
+        Op::AddOutput(2),
+        Op::AddNamedWorkspace {
+            ws_name: 2,
+            output_name: Some(2),
+        },
+        Op::MoveWorkspaceToMonitor {
+            ws_name: Some(2),
+            output_id: 1,
+        },
+        Op::MoveWorkspacesToAssignedOutputs,
+    ];
+
+    let layout = check_ops(&ops);
+    let (_, ws) = layout.find_workspace_by_name("ws2").unwrap();
+    assert_eq!(ws.current_output().unwrap().name(), "output2");
+}
+
+#[test]
+fn move_workspaces_to_assigned_outputs_while_disconnected() {
+    let ops = [
+        Op::AddOutput(1),
+        Op::AddNamedWorkspace {
+            ws_name: 2,
+            output_name: Some(2),
+        },
+        Op::MoveWorkspaceToMonitor {
+            ws_name: Some(2),
+            output_id: 1,
+        },
+        Op::MoveWorkspacesToAssignedOutputs,
+        Op::AddOutput(2),
+    ];
+
+    let layout = check_ops(&ops);
+    let (_, ws) = layout.find_workspace_by_name("ws2").unwrap();
+    assert_eq!(ws.current_output().unwrap().name(), "output2");
+}
+
+#[test]
+fn move_window_to_workspace_without_focus() {
+    let ops = [
+        Op::AddOutput(1),
+        Op::AddWindow {
+            params: TestWindowParams::new(0),
+        },
+        Op::AddWindow {
+            params: TestWindowParams::new(1),
+        },
+        Op::MoveWindowToWorkspace {
+            window_id: None,
+            workspace_idx: 1,
+            focus: false,
+        },
+    ];
+
+    let layout = check_ops(&ops);
+    let (_, idx, _) = layout
+        .workspaces()
+        .find(|(_, _, ws)| ws.has_window(&1))
+        .unwrap();
+    assert_eq!(idx, 1);
+
+    // The view stays on the first workspace.
+    assert_eq!(layout.focus().map(|win| win.id()), Some(&0));
+}
+
+#[test]
+fn move_column_to_output_without_focus() {
+    let ops = [
+        Op::AddOutput(1),
+        Op::AddOutput(2),
+        Op::FocusOutput(1),
+        Op::AddWindow {
+            params: TestWindowParams::new(0),
+        },
+        Op::AddWindow {
+            params: TestWindowParams::new(1),
+        },
+        Op::MoveColumnToOutput {
+            output_id: 2,
+            target_ws_idx: None,
+            focus: false,
+        },
+    ];
+
+    let layout = check_ops(&ops);
+    let (mon, _, _) = layout
+        .workspaces()
+        .find(|(_, _, ws)| ws.has_window(&1))
+        .unwrap();
+    assert_eq!(mon.unwrap().output().name(), "output2");
+
+    assert_eq!(layout.active_output().unwrap().name(), "output1");
+    assert_eq!(layout.focus().map(|win| win.id()), Some(&0));
+}
+
+#[test]
+fn swap_workspaces_on_same_monitor() {
+    let ops = [
+        Op::AddOutput(1),
+        Op::AddWindow {
+            params: TestWindowParams::new(0),
+        },
+        Op::FocusWorkspaceDown,
+        Op::AddWindow {
+            params: TestWindowParams::new(1),
+        },
+        Op::SwapWorkspaces { idx_a: 0, idx_b: 1 },
+        Op::AdvanceAnimations { msec_delta: 1000 },
+    ];
+
+    let layout = check_ops(&ops);
+    let windows: Vec<_> = layout
+        .workspaces()
+        .map(|(_, _, ws)| ws.windows().map(|win| *win.id()).collect::<Vec<_>>())
+        .collect();
+    assert_eq!(windows, [vec![1], vec![0], vec![]]);
+
+    // The focused workspace stays focused.
+    assert_eq!(layout.focus().map(|win| win.id()), Some(&1));
+}
+
+#[test]
+fn swap_workspaces_across_monitors() {
+    let ops = [
+        Op::AddOutput(1),
+        Op::AddOutput(2),
+        Op::FocusOutput(1),
+        Op::AddWindow {
+            params: TestWindowParams::new(0),
+        },
+        Op::FocusOutput(2),
+        Op::AddWindow {
+            params: TestWindowParams::new(1),
+        },
+        Op::FocusOutput(1),
+        Op::SwapWorkspaces { idx_a: 0, idx_b: 2 },
+    ];
+
+    let layout = check_ops(&ops);
+    let windows: Vec<_> = layout
+        .workspaces()
+        .map(|(mon, _, ws)| {
+            let windows: Vec<_> = ws.windows().map(|win| *win.id()).collect();
+            (mon.unwrap().output().name(), windows)
+        })
+        .collect();
+    assert_eq!(
+        windows,
+        [
+            (String::from("output1"), vec![1]),
+            (String::from("output1"), vec![]),
+            (String::from("output2"), vec![0]),
+            (String::from("output2"), vec![]),
+        ]
+    );
+
+    // The focus follows the focused workspace to the other monitor.
+    assert_eq!(layout.active_output().unwrap().name(), "output2");
+    assert_eq!(layout.focus().map(|win| win.id()), Some(&0));
+}
+
+#[test]
+fn move_workspaces_to_output_keeps_focus() {
+    let ops = [
+        Op::AddOutput(1),
+        Op::AddWindow {
+            params: TestWindowParams::new(0),
+        },
+        Op::FocusWorkspaceDown,
+        Op::AddWindow {
+            params: TestWindowParams::new(1),
+        },
+        Op::AddOutput(2),
+        Op::FocusOutput(1),
+    ];
+
+    let mut layout = check_ops(&ops);
+    let ids: Vec<_> = layout
+        .workspaces()
+        .filter(|(_, _, ws)| ws.windows().any(|win| *win.id() == 0))
+        .map(|(_, _, ws)| ws.id())
+        .collect();
+    let output = layout
+        .outputs()
+        .find(|output| output.name() == "output2")
+        .unwrap()
+        .clone();
+
+    layout.move_workspaces_to_output(&ids, &output);
+    layout.verify_invariants();
+
+    // The moved workspace is shown on the other output, but the focus stays.
+    let mon = layout.monitor_for_output(&output).unwrap();
+    assert_eq!(mon.active_workspace_ref().id(), ids[0]);
+    assert_eq!(layout.active_output().unwrap().name(), "output1");
+    assert_eq!(layout.focus().map(|win| win.id()), Some(&1));
+}
+
+#[test]
+fn compact_workspaces_removes_active_empty_workspace() {
+    let ops = [
+        Op::AddOutput(0),
+        Op::AddWindow {
+            params: TestWindowParams::new(0),
+        },
+        Op::FocusWorkspaceDown,
+        Op::AddWindow {
+            params: TestWindowParams::new(1),
+        },
+        Op::CloseWindow(1),
+        Op::CompactWorkspaces,
+    ];
+
+    let layout = check_ops(&ops);
+    let counts: Vec<_> = layout
+        .workspaces()
+        .map(|(_, _, ws)| ws.windows().count())
+        .collect();
+    assert_eq!(counts, &[1, 0]);
+}
+
 #[test]
 fn preset_column_width_fixed_correct_with_border() {
     let ops = [
@@ -3067,6 +4047,87 @@ fn preset_column_width_reset_after_set_width() {
     assert_eq!(win.requested_size().unwrap().w, 500);
 }
 
+#[test]
+fn min_window_size_clamps_preset_width() {
+    let ops = [
+        Op::AddOutput(0),
+        Op::AddWindow {
+            params: TestWindowParams::new(0),
+        },
+        Op::SwitchPresetColumnWidth,
+    ];
+
+    let options = Options {
+        preset_column_widths: vec![PresetSize::Fixed(100)],
+        min_window_size: niri_config::MinWindowSize {
+            width: Some(200),
+            height: None,
+        },
+        ..Default::default()
+    };
+    let layout = check_ops_with_options(options, &ops);
+    let win = layout.windows().next().unwrap().1;
+    assert_eq!(win.requested_size().unwrap().w, 200);
+}
+
+fn view_pos_after_unfocused_window_opens(
+    reveal_new_windows: RevealNewWindows,
+    focused_widths: &[i32],
+) -> f64 {
+    let options = Options {
+        reveal_new_windows,
+        ..Default::default()
+    };
+    let mut layout = check_ops_with_options(options, &[Op::AddOutput(1)]);
+
+    // The last window opens unfocused and doesn't fit on the screen next to the focused one.
+    let focused = focused_widths
+        .iter()
+        .map(|width| (ActivateWindow::Yes, *width));
+    let windows = focused.chain([(ActivateWindow::No, 1000)]);
+    for (id, (activate, width)) in windows.enumerate() {
+        let mut params = TestWindowParams::new(id);
+        params.bbox = Rectangle::from_size(Size::from((width, 200)));
+        layout.add_window(
+            TestWindow::new(params),
+            AddWindowTarget::Auto,
+            Some(PresetSize::Fixed(width)),
+            None,
+            false,
+            false,
+            activate,
+        );
+        layout.verify_invariants();
+    }
+    let focused_id = focused_widths.len() - 1;
+    assert_eq!(layout.focus().map(|win| win.id()), Some(&focused_id));
+
+    layout
+        .active_workspace()
+        .unwrap()
+        .scrolling()
+        .target_view_pos()
+}
+
+#[test]
+fn reveal_new_windows() {
+    let never = view_pos_after_unfocused_window_opens(RevealNewWindows::Never, &[1000]);
+    let partial = view_pos_after_unfocused_window_opens(RevealNewWindows::Partial, &[1000]);
+    let always = view_pos_after_unfocused_window_opens(RevealNewWindows::Always, &[1000]);
+
+    // Partial can't scroll at all since the focused window takes up most of the screen.
+    assert_eq!(partial, never);
+    assert!(never < always);
+
+    let never = view_pos_after_unfocused_window_opens(RevealNewWindows::Never, &[500, 500]);
+    let partial = view_pos_after_unfocused_window_opens(RevealNewWindows::Partial, &[500, 500]);
+    let always = view_pos_after_unfocused_window_opens(RevealNewWindows::Always, &[500, 500]);
+
+    // The focused window is in the middle, so partial can scroll the first one out of the view.
+    assert!(never < partial);
+    assert!(partial < always);
+}
+
 fn parent_id_causes_loop(layout: &Layout<TestWindow>, id: usize, mut parent_id: usize) -> bool {
     if parent_id == id {
         return true;
@@ -3136,6 +4197,30 @@ fn arbitrary_center_focused_column() -> impl Strategy<Value = CenterFocusedColum
     ]
 }
 
+fn arbitrary_new_column_position() -> impl Strategy<Value = NewColumnPosition> {
+    prop_oneof![
+        Just(NewColumnPosition::AfterFocused),
+        Just(NewColumnPosition::First),
+        Just(NewColumnPosition::Last),
+    ]
+}
+
+fn arbitrary_new_window_position_in_column() -> impl Strategy<Value = NewWindowPositionInColumn> {
+    prop_oneof![
+        Just(NewWindowPositionInColumn::AfterFocused),
+        Just(NewWindowPositionInColumn::Top),
+        Just(NewWindowPositionInColumn::Bottom),
+    ]
+}
+
+fn arbitrary_workspace_spillover() -> impl Strategy<Value = WorkspaceSpillover> {
+    prop_oneof![
+        Just(WorkspaceSpillover::Primary),
+        (1..=5usize).prop_map(|id| WorkspaceSpillover::Output(format!("output{id}"))),
+        Just(WorkspaceSpillover::Distribute),
+    ]
+}
+
 prop_compose! {
     fn arbitrary_focus_ring()(
         off in any::<bool>(),
@@ -3185,6 +4270,10 @@ prop_compose! {
         center_focused_column in arbitrary_center_focused_column(),
         always_center_single_column in any::<bool>(),
         empty_workspace_above_first in any::<bool>(),
+        new_column_position in arbitrary_new_column_position(),
+        new_window_position_in_column in arbitrary_new_window_position_in_column(),
+        workspace_spillover in arbitrary_workspace_spillover(),
+        floating_min_visible in proptest::option::of(1f64..500.),
     ) -> Options {
         Options {
             gaps,
@@ -3192,9 +4281,13 @@ prop_compose! {
             center_focused_column,
             always_center_single_column,
             empty_workspace_above_first,
+            new_column_position,
+            new_window_position_in_column,
             focus_ring,
             border,
             shadow,
+            workspace_spillover,
+            floating_min_visible,
             ..Default::default()
         }
     }
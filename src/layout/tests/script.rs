@@ -0,0 +1,534 @@
+//! Layout test scripts: plain text files with layout operations and assertions.
+//!
+//! Scripts make it easy to describe a layout scenario without writing Rust, for example to attach
+//! a reproducer to a bug report. Run one with `cargo xtask layout-test path/to/file.ops`.
+//!
+//! Every line is an operation or an assertion, with positional arguments and `key=value` pairs.
+//! `#` starts a comment. Windows, outputs and named workspaces are referred to by number, same as
+//! in [`Op`]: output `1` is called `output1`, and named workspace `1` is called `ws1`. Workspace
+//! indices start at 0.
+//!
+//! ```text
+//! output 1
+//! window 1
+//! window 2 width=300 floating=true
+//! focus-column-left
+//! assert focus 1
+//! assert window 2 workspace=0 floating=true
+//! ```
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, ensure, Context as _};
+use niri_ipc::SizeChange;
+
+use super::{Op, TestWindow, TestWindowParams};
+use crate::layout::{Layout, LayoutElement as _};
+
+/// Environment variable with the path of the script for [`script_from_env`].
+const SCRIPT_ENV: &str = "NIRI_LAYOUT_TEST";
+
+#[derive(Debug)]
+enum Step {
+    Op(Op),
+    Assert(Assertion),
+}
+
+#[derive(Debug)]
+enum Assertion {
+    Focus(Option<usize>),
+    ActiveOutput(Option<usize>),
+    ActiveWorkspace(usize),
+    WorkspaceCount(usize),
+    Window {
+        id: usize,
+        output: Option<usize>,
+        workspace: Option<usize>,
+        floating: Option<bool>,
+        width: Option<i32>,
+        height: Option<i32>,
+    },
+    NoWindow(usize),
+}
+
+/// Arguments of one script line.
+struct Args<'a> {
+    positional: Vec<&'a str>,
+    named: HashMap<&'a str, &'a str>,
+}
+
+impl<'a> Args<'a> {
+    fn new(words: &[&'a str]) -> anyhow::Result<Self> {
+        let mut positional = Vec::new();
+        let mut named = HashMap::new();
+        for &word in words {
+            match word.split_once('=') {
+                Some((key, value)) => {
+                    ensure!(
+                        named.insert(key, value).is_none(),
+                        "argument {key:?} given twice"
+                    );
+                }
+                None => positional.push(word),
+            }
+        }
+        positional.reverse();
+
+        Ok(Self { positional, named })
+    }
+
+    fn next<T>(&mut self, what: &str) -> anyhow::Result<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let value = self
+            .positional
+            .pop()
+            .ok_or_else(|| anyhow!("missing {what}"))?;
+        parse(what, value)
+    }
+
+    fn next_or_none<T>(&mut self, what: &str) -> anyhow::Result<Option<T>>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        match self.next::<String>(what)?.as_str() {
+            "none" => Ok(None),
+            value => parse(what, value).map(Some),
+        }
+    }
+
+    fn get<T>(&mut self, key: &str) -> anyhow::Result<Option<T>>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.named
+            .remove(key)
+            .map(|value| parse(key, value))
+            .transpose()
+    }
+
+    fn finish(self) -> anyhow::Result<()> {
+        if let Some(value) = self.positional.last() {
+            bail!("unexpected argument {value:?}");
+        }
+        if let Some(key) = self.named.keys().next() {
+            bail!("unknown argument {key:?}");
+        }
+        Ok(())
+    }
+}
+
+fn parse<T>(what: &str, value: &str) -> anyhow::Result<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    value
+        .parse()
+        .map_err(|err| anyhow!("invalid {what} {value:?}: {err}"))
+}
+
+/// Operations without arguments.
+fn simple_op(name: &str) -> Option<Op> {
+    let op = match name {
+        "focus-column-left" => Op::FocusColumnLeft,
+        "focus-column-right" => Op::FocusColumnRight,
+        "focus-column-first" => Op::FocusColumnFirst,
+        "focus-column-last" => Op::FocusColumnLast,
+        "focus-column-right-or-first" => Op::FocusColumnRightOrFirst,
+        "focus-column-left-or-last" => Op::FocusColumnLeftOrLast,
+        "focus-window-down" => Op::FocusWindowDown,
+        "focus-window-up" => Op::FocusWindowUp,
+        "focus-window-down-or-column-left" => Op::FocusWindowDownOrColumnLeft,
+        "focus-window-down-or-column-right" => Op::FocusWindowDownOrColumnRight,
+        "focus-window-up-or-column-left" => Op::FocusWindowUpOrColumnLeft,
+        "focus-window-up-or-column-right" => Op::FocusWindowUpOrColumnRight,
+        "focus-window-or-workspace-down" => Op::FocusWindowOrWorkspaceDown,
+        "focus-window-or-workspace-up" => Op::FocusWindowOrWorkspaceUp,
+        "save-focus" => Op::SaveFocus,
+        "restore-focus" => Op::RestoreFocus,
+        "focus-previous-in-workspace" => Op::FocusPreviousInWorkspace,
+        "move-column-left" => Op::MoveColumnLeft,
+        "move-column-right" => Op::MoveColumnRight,
+        "move-column-to-first" => Op::MoveColumnToFirst,
+        "move-column-to-last" => Op::MoveColumnToLast,
+        "move-window-down" => Op::MoveWindowDown,
+        "move-window-up" => Op::MoveWindowUp,
+        "move-window-down-or-to-workspace-down" => Op::MoveWindowDownOrToWorkspaceDown,
+        "move-window-up-or-to-workspace-up" => Op::MoveWindowUpOrToWorkspaceUp,
+        "consume-window-into-column" => Op::ConsumeWindowIntoColumn,
+        "expel-window-from-column" => Op::ExpelWindowFromColumn,
+        "center-column" => Op::CenterColumn,
+        "focus-workspace-down" => Op::FocusWorkspaceDown,
+        "focus-workspace-up" => Op::FocusWorkspaceUp,
+        "focus-workspace-previous" => Op::FocusWorkspacePrevious,
+        "move-window-to-workspace-down" => Op::MoveWindowToWorkspaceDown,
+        "move-window-to-workspace-up" => Op::MoveWindowToWorkspaceUp,
+        "move-column-to-workspace-down" => Op::MoveColumnToWorkspaceDown,
+        "move-column-to-workspace-up" => Op::MoveColumnToWorkspaceUp,
+        "move-workspace-down" => Op::MoveWorkspaceDown,
+        "move-workspace-up" => Op::MoveWorkspaceUp,
+        "sort-workspaces-by-name" => Op::SortWorkspacesByName,
+        "compact-workspaces" => Op::CompactWorkspaces,
+        "move-workspaces-to-assigned-outputs" => Op::MoveWorkspacesToAssignedOutputs,
+        "switch-preset-column-width" => Op::SwitchPresetColumnWidth,
+        "maximize-column" => Op::MaximizeColumn,
+        "focus-floating" => Op::FocusFloating,
+        "focus-tiling" => Op::FocusTiling,
+        "switch-focus-floating-tiling" => Op::SwitchFocusFloatingTiling,
+        _ => return None,
+    };
+    Some(op)
+}
+
+fn parse_window(args: &mut Args) -> anyhow::Result<Op> {
+    let mut params = TestWindowParams::new(args.next("window id")?);
+    if let Some(width) = args.get("width")? {
+        params.bbox.size.w = width;
+    }
+    if let Some(height) = args.get("height")? {
+        params.bbox.size.h = height;
+    }
+    params.is_floating = args.get("floating")?.unwrap_or(false);
+    params.parent_id = args.get("parent")?;
+
+    let next_to = args.get("next-to")?;
+    let workspace = args.get("workspace")?;
+    let op = match (next_to, workspace) {
+        (None, None) => Op::AddWindow { params },
+        (Some(next_to_id), None) => Op::AddWindowNextTo { params, next_to_id },
+        (None, Some(ws_name)) => Op::AddWindowToNamedWorkspace { params, ws_name },
+        (Some(_), Some(_)) => bail!("next-to and workspace can't be used together"),
+    };
+    Ok(op)
+}
+
+fn parse_op(name: &str, args: &mut Args) -> anyhow::Result<Op> {
+    if let Some(op) = simple_op(name) {
+        return Ok(op);
+    }
+
+    let op = match name {
+        "output" => {
+            let id = args.next("output id")?;
+            match args.get("scale")? {
+                Some(scale) => Op::AddScaledOutput { id, scale },
+                None => Op::AddOutput(id),
+            }
+        }
+        "remove-output" => Op::RemoveOutput(args.next("output id")?),
+        "focus-output" => Op::FocusOutput(args.next("output id")?),
+        "named-workspace" => Op::AddNamedWorkspace {
+            ws_name: args.next("workspace name")?,
+            output_name: args.get("output")?,
+        },
+        "unname-workspace" => Op::UnnameWorkspace {
+            ws_name: args.next("workspace name")?,
+        },
+        "window" => parse_window(args)?,
+        "close-window" => Op::CloseWindow(args.next("window id")?),
+        "fullscreen-window" => Op::FullscreenWindow(args.next("window id")?),
+        "focus-window" => Op::FocusWindow(args.next("window id")?),
+        "communicate" => Op::Communicate(args.next("window id")?),
+        "set-parent" => Op::SetParent {
+            id: args.next("window id")?,
+            new_parent_id: args.next_or_none("parent id")?,
+        },
+        "consume-or-expel-window-left" => Op::ConsumeOrExpelWindowLeft {
+            id: args.get("window")?,
+        },
+        "consume-or-expel-window-right" => Op::ConsumeOrExpelWindowRight {
+            id: args.get("window")?,
+        },
+        "center-window" => Op::CenterWindow {
+            id: args.get("window")?,
+        },
+        "focus-workspace" => Op::FocusWorkspace(args.next("workspace index")?),
+        "move-window-to-workspace" => Op::MoveWindowToWorkspace {
+            workspace_idx: args.next("workspace index")?,
+            window_id: args.get("window")?,
+            focus: args.get("focus")?.unwrap_or(true),
+        },
+        "move-column-to-workspace" => Op::MoveColumnToWorkspace(
+            args.next("workspace index")?,
+            args.get("focus")?.unwrap_or(true),
+        ),
+        "move-window-to-output" => Op::MoveWindowToOutput {
+            output_id: args.next("output id")?,
+            window_id: args.get("window")?,
+            target_ws_idx: args.get("workspace")?,
+            focus: args.get("focus")?.unwrap_or(true),
+        },
+        "move-column-to-output" => Op::MoveColumnToOutput {
+            output_id: args.next("output id")?,
+            target_ws_idx: args.get("workspace")?,
+            focus: args.get("focus")?.unwrap_or(true),
+        },
+        "move-workspace-to-output" => Op::MoveWorkspaceToOutput(args.next("output id")?),
+        "swap-workspaces" => Op::SwapWorkspaces {
+            idx_a: args.next("workspace index")?,
+            idx_b: args.next("workspace index")?,
+        },
+        "set-workspace-name" => Op::SetWorkspaceName {
+            new_ws_name: args.next("workspace name")?,
+            ws_name: args.get("workspace")?,
+        },
+        "unset-workspace-name" => Op::UnsetWorkspaceName {
+            ws_name: args.get("workspace")?,
+        },
+        "set-column-width" => Op::SetColumnWidth(args.next::<SizeChange>("size change")?),
+        "set-window-width" => Op::SetWindowWidth {
+            change: args.next("size change")?,
+            id: args.get("window")?,
+        },
+        "set-window-height" => Op::SetWindowHeight {
+            change: args.next("size change")?,
+            id: args.get("window")?,
+        },
+        "reset-window-height" => Op::ResetWindowHeight {
+            id: args.get("window")?,
+        },
+        "toggle-window-floating" => Op::ToggleWindowFloating {
+            id: args.get("window")?,
+        },
+        "set-window-floating" => Op::SetWindowFloating {
+            floating: args.next("floating")?,
+            id: args.get("window")?,
+        },
+        "refresh" => Op::Refresh {
+            is_active: args.get("active")?.unwrap_or(true),
+        },
+        "advance-animations" => Op::AdvanceAnimations {
+            msec_delta: args.next("milliseconds")?,
+        },
+        _ => bail!("unknown operation {name:?}"),
+    };
+    Ok(op)
+}
+
+fn parse_assertion(args: &mut Args) -> anyhow::Result<Assertion> {
+    let what: String = args.next("assertion")?;
+    let assertion = match what.as_str() {
+        "focus" => Assertion::Focus(args.next_or_none("window id")?),
+        "active-output" => Assertion::ActiveOutput(args.next_or_none("output id")?),
+        "active-workspace" => Assertion::ActiveWorkspace(args.next("workspace index")?),
+        "workspace-count" => Assertion::WorkspaceCount(args.next("count")?),
+        "window" => Assertion::Window {
+            id: args.next("window id")?,
+            output: args.get("output")?,
+            workspace: args.get("workspace")?,
+            floating: args.get("floating")?,
+            width: args.get("width")?,
+            height: args.get("height")?,
+        },
+        "no-window" => Assertion::NoWindow(args.next("window id")?),
+        _ => bail!("unknown assertion {what:?}"),
+    };
+    Ok(assertion)
+}
+
+fn parse_line(name: &str, words: &[&str]) -> anyhow::Result<Step> {
+    let mut args = Args::new(words)?;
+    let step = if name == "assert" {
+        Step::Assert(parse_assertion(&mut args)?)
+    } else {
+        Step::Op(parse_op(name, &mut args)?)
+    };
+    args.finish()?;
+    Ok(step)
+}
+
+/// Parses a script into steps along with their line numbers.
+fn parse_script(text: &str) -> anyhow::Result<Vec<(usize, Step)>> {
+    let mut steps = Vec::new();
+
+    for (idx, line) in text.lines().enumerate() {
+        let line_number = idx + 1;
+
+        let line = line.split_once('#').map_or(line, |(line, _)| line);
+        let words: Vec<_> = line.split_whitespace().collect();
+        let Some((name, words)) = words.split_first() else {
+            continue;
+        };
+
+        let step = parse_line(name, words).with_context(|| format!("line {line_number}"))?;
+        steps.push((line_number, step));
+    }
+
+    Ok(steps)
+}
+
+fn check(layout: &Layout<TestWindow>, assertion: &Assertion) -> anyhow::Result<()> {
+    let output_name = |id: usize| format!("output{id}");
+
+    match *assertion {
+        Assertion::Focus(expected) => {
+            let focus = layout.focus().map(|win| *win.id());
+            ensure!(
+                focus == expected,
+                "focus is {focus:?}, expected {expected:?}"
+            );
+        }
+        Assertion::ActiveOutput(expected) => {
+            let active = layout.active_output().map(|output| output.name());
+            let expected = expected.map(output_name);
+            ensure!(
+                active == expected,
+                "active output is {active:?}, expected {expected:?}"
+            );
+        }
+        Assertion::ActiveWorkspace(expected) => {
+            let mon = layout.active_monitor_ref().context("no active monitor")?;
+            let active = mon.active_workspace_idx();
+            ensure!(
+                active == expected,
+                "active workspace is {active}, expected {expected}"
+            );
+        }
+        Assertion::WorkspaceCount(expected) => {
+            let mon = layout.active_monitor_ref().context("no active monitor")?;
+            let count = mon.workspaces.len();
+            ensure!(
+                count == expected,
+                "active monitor has {count} workspaces, expected {expected}"
+            );
+        }
+        Assertion::Window {
+            id,
+            output,
+            workspace,
+            floating,
+            width,
+            height,
+        } => {
+            let (mon, ws_idx, ws) = layout
+                .workspaces()
+                .find(|(_, _, ws)| ws.has_window(&id))
+                .with_context(|| format!("window {id} not found"))?;
+
+            if let Some(expected) = output {
+                let actual = mon.map(|mon| mon.output_name().clone());
+                let expected = output_name(expected);
+                ensure!(
+                    actual.as_ref() == Some(&expected),
+                    "window {id} is on output {actual:?}, expected {expected:?}"
+                );
+            }
+            if let Some(expected) = workspace {
+                ensure!(
+                    ws_idx == expected,
+                    "window {id} is on workspace {ws_idx}, expected {expected}"
+                );
+            }
+            if let Some(expected) = floating {
+                let actual = ws.is_floating(&id);
+                ensure!(
+                    actual == expected,
+                    "window {id} floating is {actual}, expected {expected}"
+                );
+            }
+
+            let win = ws.windows().find(|win| *win.id() == id).unwrap();
+            let size = win.size();
+            if let Some(expected) = width {
+                ensure!(
+                    size.w == expected,
+                    "window {id} width is {}, expected {expected}",
+                    size.w
+                );
+            }
+            if let Some(expected) = height {
+                ensure!(
+                    size.h == expected,
+                    "window {id} height is {}, expected {expected}",
+                    size.h
+                );
+            }
+        }
+        Assertion::NoWindow(id) => {
+            ensure!(!layout.has_window(&id), "window {id} exists");
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs a script, checking the layout invariants after every operation.
+pub fn run_script(text: &str) -> anyhow::Result<()> {
+    let steps = parse_script(text)?;
+
+    let mut layout = Layout::default();
+    for (line_number, step) in steps {
+        match step {
+            Step::Op(op) => {
+                let res = panic::catch_unwind(AssertUnwindSafe(|| {
+                    op.apply(&mut layout);
+                    layout.verify_invariants();
+                }));
+                if res.is_err() {
+                    bail!("line {line_number}: panicked while applying {op:?}");
+                }
+            }
+            Step::Assert(assertion) => {
+                check(&layout, &assertion)
+                    .with_context(|| format!("line {line_number}: assertion failed"))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn run_script_file(path: &Path) -> anyhow::Result<()> {
+    let text =
+        fs::read_to_string(path).with_context(|| format!("error reading {}", path.display()))?;
+    run_script(&text).with_context(|| format!("error in {}", path.display()))
+}
+
+#[test]
+fn bundled_scripts() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/layout/tests/scripts");
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ops"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+
+    for path in paths {
+        if let Err(err) = run_script_file(&path) {
+            panic!("{err:?}");
+        }
+    }
+}
+
+#[test]
+#[ignore = "run with `cargo xtask layout-test <file>`"]
+fn script_from_env() {
+    let path = std::env::var_os(SCRIPT_ENV).unwrap_or_else(|| panic!("{SCRIPT_ENV} is not set"));
+    if let Err(err) = run_script_file(Path::new(&path)) {
+        panic!("{err:?}");
+    }
+}
+
+#[test]
+fn script_errors_point_at_line() {
+    let err = run_script("output 1\n\nwindow 1 size=5\n").unwrap_err();
+    assert_eq!(format!("{err:#}"), "line 3: unknown argument \"size\"");
+
+    let err = run_script("output 1\nwindow 1\nassert focus 2\n").unwrap_err();
+    assert_eq!(
+        format!("{err:#}"),
+        "line 3: assertion failed: focus is Some(1), expected Some(2)"
+    );
+}
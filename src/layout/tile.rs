@@ -9,6 +9,7 @@ use smithay::utils::{Logical, Point, Rectangle, Scale, Size, Transform};
 use super::focus_ring::{FocusRing, FocusRingRenderElement};
 use super::opening_window::{OpenAnimation, OpeningWindowRenderElement};
 use super::shadow::Shadow;
+use super::titlebar::{Titlebar, TitlebarRenderElement, TITLEBAR_HEIGHT};
 use super::{
     LayoutElement, LayoutElementRenderElement, LayoutElementRenderSnapshot, Options, SizeFrac,
     RESIZE_ANIMATION_THRESHOLD,
@@ -24,8 +25,12 @@ use crate::render_helpers::shadow::ShadowRenderElement;
 use crate::render_helpers::snapshot::RenderSnapshot;
 use crate::render_helpers::solid_color::{SolidColorBuffer, SolidColorRenderElement};
 use crate::render_helpers::{render_to_encompassing_texture, RenderTarget};
+use crate::utils::round_logical_in_physical;
 use crate::utils::transaction::Transaction;
 
+/// Tint over the windows selected for a batch action.
+const SELECTION_COLOR: Color = Color::new_unpremul(0.3, 0.5, 1., 0.35);
+
 /// Toplevel window with decorations.
 #[derive(Debug)]
 pub struct Tile<W: LayoutElement> {
@@ -41,6 +46,9 @@ pub struct Tile<W: LayoutElement> {
     /// The shadow around the window.
     shadow: Shadow,
 
+    /// The server-side titlebar above the window.
+    titlebar: Titlebar,
+
     /// Whether this tile is fullscreen.
     ///
     /// This will update only when the `window` actually goes fullscreen, rather than right away,
@@ -50,6 +58,9 @@ pub struct Tile<W: LayoutElement> {
     /// The black backdrop for fullscreen windows.
     fullscreen_backdrop: SolidColorBuffer,
 
+    /// The tint over the tile when the window is selected.
+    selection_overlay: SolidColorBuffer,
+
     /// Whether the tile should float upon unfullscreening.
     pub(super) unfullscreen_to_floating: bool,
 
@@ -117,6 +128,7 @@ niri_render_elements! {
         Resize = ResizeRenderElement,
         Border = BorderRenderElement,
         Shadow = ShadowRenderElement,
+        Titlebar = TitlebarRenderElement,
         ClippedSurface = ClippedSurfaceRenderElement<R>,
         ExtraDamage = ExtraDamage,
     }
@@ -157,8 +169,10 @@ impl<W: LayoutElement> Tile<W> {
             border: FocusRing::new(border_config.into()),
             focus_ring: FocusRing::new(focus_ring_config.into()),
             shadow: Shadow::new(shadow_config),
+            titlebar: Titlebar::new(),
             is_fullscreen,
             fullscreen_backdrop: SolidColorBuffer::new(view_size, [0., 0., 0., 1.]),
+            selection_overlay: SolidColorBuffer::new((0., 0.), SELECTION_COLOR.to_array_premul()),
             unfullscreen_to_floating: false,
             floating_window_size: None,
             floating_pos: None,
@@ -317,6 +331,7 @@ impl<W: LayoutElement> Tile<W> {
             .draw_border_with_background
             .unwrap_or_else(|| !self.window.has_ssd());
         let border_width = self.effective_border_width().unwrap_or(0.);
+        let titlebar_height = self.effective_titlebar_height().unwrap_or(0.);
         let radius = if self.is_fullscreen {
             CornerRadius::default()
         } else {
@@ -326,8 +341,10 @@ impl<W: LayoutElement> Tile<W> {
                     radius.expanded_by(border_width as f32)
                 })
         };
+        let mut decorated_size = self.animated_window_size();
+        decorated_size.h += titlebar_height;
         self.border.update_render_elements(
-            self.animated_window_size(),
+            decorated_size,
             is_active,
             !draw_border_with_background,
             Rectangle::new(
@@ -366,6 +383,20 @@ impl<W: LayoutElement> Tile<W> {
             radius,
             self.scale,
         );
+
+        self.selection_overlay.resize(self.animated_tile_size());
+
+        if titlebar_height > 0. {
+            let size = Size::from((self.animated_window_size().w, titlebar_height));
+            let title = self.window.title().unwrap_or_default();
+            self.titlebar.update_render_elements(
+                size,
+                self.window_size().w,
+                &title,
+                is_active,
+                self.scale,
+            );
+        }
     }
 
     pub fn scale(&self) -> f64 {
@@ -474,6 +505,32 @@ impl<W: LayoutElement> Tile<W> {
         Some(self.border.width())
     }
 
+    /// Returns whether the window should have a server-side titlebar, regardless of fullscreen.
+    fn has_titlebar(&self) -> bool {
+        self.window.rules().draw_titlebar == Some(true) && self.window.has_ssd()
+    }
+
+    pub fn is_picture_in_picture(&self) -> bool {
+        self.window.rules().picture_in_picture == Some(true)
+    }
+
+    fn titlebar_height(&self) -> f64 {
+        round_logical_in_physical(self.scale, TITLEBAR_HEIGHT)
+    }
+
+    /// Returns `None` if the titlebar is hidden and `Some(height)` if it should be shown.
+    pub fn effective_titlebar_height(&self) -> Option<f64> {
+        if self.is_fullscreen {
+            return None;
+        }
+
+        if !self.has_titlebar() {
+            return None;
+        }
+
+        Some(self.titlebar_height())
+    }
+
     /// Returns the location of the window's visual geometry within this Tile.
     pub fn window_loc(&self) -> Point<f64, Logical> {
         let mut loc = Point::from((0., 0.));
@@ -502,6 +559,10 @@ impl<W: LayoutElement> Tile<W> {
             loc += (width, width).into();
         }
 
+        if let Some(height) = self.effective_titlebar_height() {
+            loc.y += height;
+        }
+
         loc
     }
 
@@ -521,6 +582,10 @@ impl<W: LayoutElement> Tile<W> {
             size.h += width * 2.;
         }
 
+        if let Some(height) = self.effective_titlebar_height() {
+            size.h += height;
+        }
+
         size
     }
 
@@ -540,6 +605,10 @@ impl<W: LayoutElement> Tile<W> {
             size.h += width * 2.;
         }
 
+        if let Some(height) = self.effective_titlebar_height() {
+            size.h += height;
+        }
+
         size
     }
 
@@ -593,6 +662,10 @@ impl<W: LayoutElement> Tile<W> {
             size.h += width * 2.;
         }
 
+        if let Some(height) = self.effective_titlebar_height() {
+            size.h += height;
+        }
+
         size
     }
 
@@ -604,15 +677,38 @@ impl<W: LayoutElement> Tile<W> {
     }
 
     pub fn is_in_input_region(&self, mut point: Point<f64, Logical>) -> bool {
+        if self.is_click_through() {
+            return false;
+        }
+
         point -= self.window_loc().to_f64();
         self.window.is_in_input_region(point)
     }
 
     pub fn is_in_activation_region(&self, point: Point<f64, Logical>) -> bool {
+        if self.is_click_through() {
+            return false;
+        }
+
         let activation_region = Rectangle::from_size(self.tile_size());
         activation_region.contains(point)
     }
 
+    fn is_click_through(&self) -> bool {
+        self.window.rules().click_through == Some(true)
+    }
+
+    /// Returns whether the point is over the close button of the titlebar.
+    pub fn is_in_close_button(&self, point: Point<f64, Logical>) -> bool {
+        let Some(height) = self.effective_titlebar_height() else {
+            return false;
+        };
+
+        let loc = self.window_loc() - Point::from((0., height));
+        let size = Size::from((self.window_size().w, height));
+        Titlebar::is_in_close_button(size, point - loc)
+    }
+
     pub fn request_tile_size(
         &mut self,
         mut size: Size<f64, Logical>,
@@ -626,6 +722,11 @@ impl<W: LayoutElement> Tile<W> {
             size.h = f64::max(1., size.h - width * 2.);
         }
 
+        // Same as above, for the titlebar.
+        if self.has_titlebar() {
+            size.h = f64::max(1., size.h - self.titlebar_height());
+        }
+
         // The size request has to be i32 unfortunately, due to Wayland. We floor here instead of
         // round to avoid situations where proportionally-sized columns don't fit on the screen
         // exactly.
@@ -641,7 +742,11 @@ impl<W: LayoutElement> Tile<W> {
         }
     }
 
-    pub fn tile_height_for_window_height(&self, size: f64) -> f64 {
+    pub fn tile_height_for_window_height(&self, mut size: f64) -> f64 {
+        if self.has_titlebar() {
+            size += self.titlebar_height();
+        }
+
         if self.border.is_off() {
             size
         } else {
@@ -657,7 +762,11 @@ impl<W: LayoutElement> Tile<W> {
         }
     }
 
-    pub fn window_height_for_tile_height(&self, size: f64) -> f64 {
+    pub fn window_height_for_tile_height(&self, mut size: f64) -> f64 {
+        if self.has_titlebar() {
+            size -= self.titlebar_height();
+        }
+
         if self.border.is_off() {
             size
         } else {
@@ -666,12 +775,43 @@ impl<W: LayoutElement> Tile<W> {
     }
 
     pub fn request_fullscreen(&mut self) {
-        self.window
-            .request_fullscreen(self.view_size.to_i32_round());
+        let size = self
+            .window
+            .rules()
+            .compute_fullscreen_size(self.view_size.to_i32_round());
+        self.window.request_fullscreen(size);
+    }
+
+    /// Returns the min size of the window, including the minimum window size from the layout.
+    ///
+    /// Min size window rules take precedence over the layout setting, and the layout setting
+    /// never goes past the max size of the window.
+    pub fn window_min_size(&self) -> Size<i32, Logical> {
+        let mut size = self.window.min_size();
+        let max_size = self.window.max_size();
+        let rules = self.window.rules();
+        let global = self.options.min_window_size;
+
+        if let Some(w) = global.width.filter(|_| rules.min_width.is_none()) {
+            let mut w = i32::from(w);
+            if max_size.w > 0 {
+                w = w.min(max_size.w);
+            }
+            size.w = size.w.max(w);
+        }
+        if let Some(h) = global.height.filter(|_| rules.min_height.is_none()) {
+            let mut h = i32::from(h);
+            if max_size.h > 0 {
+                h = h.min(max_size.h);
+            }
+            size.h = size.h.max(h);
+        }
+
+        size
     }
 
     pub fn min_size(&self) -> Size<f64, Logical> {
-        let mut size = self.window.min_size().to_f64();
+        let mut size = self.window_min_size().to_f64();
 
         if let Some(width) = self.effective_border_width() {
             size.w = f64::max(1., size.w);
@@ -681,6 +821,11 @@ impl<W: LayoutElement> Tile<W> {
             size.h += width * 2.;
         }
 
+        if let Some(height) = self.effective_titlebar_height() {
+            size.h = f64::max(1., size.h);
+            size.h += height;
+        }
+
         size
     }
 
@@ -696,6 +841,12 @@ impl<W: LayoutElement> Tile<W> {
             }
         }
 
+        if let Some(height) = self.effective_titlebar_height() {
+            if size.h > 0. {
+                size.h += height;
+            }
+        }
+
         size
     }
 
@@ -736,6 +887,16 @@ impl<W: LayoutElement> Tile<W> {
         let clip_to_geometry = !self.is_fullscreen && rules.clip_to_geometry == Some(true);
         let radius = rules.geometry_corner_radius.unwrap_or_default();
 
+        // Clipping client-drawn shadows uses the plain geometry rectangle, since the client rounds
+        // its corners on its own.
+        let clip_csd_shadow =
+            !self.is_fullscreen && !clip_to_geometry && rules.clip_csd_shadow == Some(true);
+        let clip_radius = if clip_to_geometry {
+            radius
+        } else {
+            CornerRadius::default()
+        };
+
         // If we're resizing, try to render a shader, or a fallback.
         let mut resize_shader = None;
         let mut resize_popups = None;
@@ -773,13 +934,13 @@ impl<W: LayoutElement> Tile<W> {
 
                     // Clip blocked-out resizes unconditionally because they use solid color render
                     // elements.
-                    let clip_to_geometry = if target
+                    let (clip_to_geometry, radius) = if target
                         .should_block_out(resize.snapshot.block_out_from)
                         && target.should_block_out(rules.block_out_from)
                     {
-                        true
+                        (true, radius)
                     } else {
-                        clip_to_geometry
+                        (clip_to_geometry || clip_csd_shadow, clip_radius)
                     };
 
                     if let Some((texture_current, _sync_point, texture_current_geo)) = current {
@@ -831,6 +992,7 @@ impl<W: LayoutElement> Tile<W> {
 
             let geo = Rectangle::new(window_render_loc, window_size);
             let radius = radius.fit_to(window_size.w as f32, window_size.h as f32);
+            let clip_radius = clip_radius.fit_to(window_size.w as f32, window_size.h as f32);
 
             let clip_shader = ClippedSurfaceRenderElement::shader(renderer).cloned();
             let has_border_shader = BorderRenderElement::has_shader(renderer);
@@ -843,15 +1005,20 @@ impl<W: LayoutElement> Tile<W> {
             window_surface = Some(window.normal.into_iter().map(move |elem| match elem {
                 LayoutElementRenderElement::Wayland(elem) => {
                     // If we should clip to geometry, render a clipped window.
-                    if clip_to_geometry {
+                    if clip_to_geometry || clip_csd_shadow {
                         if let Some(shader) = clip_shader.clone() {
-                            if ClippedSurfaceRenderElement::will_clip(&elem, scale, geo, radius) {
+                            if ClippedSurfaceRenderElement::will_clip(
+                                &elem,
+                                scale,
+                                geo,
+                                clip_radius,
+                            ) {
                                 return ClippedSurfaceRenderElement::new(
                                     elem,
                                     scale,
                                     geo,
                                     shader.clone(),
-                                    radius,
+                                    clip_radius,
                                 )
                                 .into();
                             }
@@ -888,14 +1055,28 @@ impl<W: LayoutElement> Tile<W> {
                     // Otherwise, render the solid color as is.
                     LayoutElementRenderElement::SolidColor(elem).into()
                 }
+                LayoutElementRenderElement::Texture(elem) => {
+                    LayoutElementRenderElement::Texture(elem).into()
+                }
             }));
 
             window_popups = Some(window.popups.into_iter().map(Into::into));
         }
 
-        let rv = resize_popups
+        // The tint fades together with the window.
+        let selection: Option<TileRenderElement<R>> = self.window.is_selected().then(|| {
+            SolidColorRenderElement::from_buffer(
+                &self.selection_overlay,
+                location,
+                alpha,
+                Kind::Unspecified,
+            )
+            .into()
+        });
+
+        let rv = selection
             .into_iter()
-            .flatten()
+            .chain(resize_popups.into_iter().flatten())
             .chain(resize_shader)
             .chain(resize_fallback)
             .chain(window_popups.into_iter().flatten())
@@ -913,6 +1094,12 @@ impl<W: LayoutElement> Tile<W> {
         });
         let rv = rv.chain(elem);
 
+        let elem = self.effective_titlebar_height().map(|height| {
+            let loc = window_render_loc - Point::from((0., height));
+            self.titlebar.render(renderer, loc, scale.x).map(Into::into)
+        });
+        let rv = rv.chain(elem.into_iter().flatten());
+
         let elem = self.effective_border_width().map(|width| {
             self.border
                 .render(renderer, location + Point::from((width, width)))
@@ -0,0 +1,294 @@
+use std::fmt;
+
+use niri_config::Color;
+use pangocairo::cairo::{self, ImageSurface};
+use pangocairo::pango::{self, EllipsizeMode, FontDescription};
+use smithay::backend::renderer::element::Kind;
+use smithay::reexports::gbm::Format as Fourcc;
+use smithay::utils::{Logical, Point, Rectangle, Size, Transform};
+
+use crate::niri_render_elements;
+use crate::render_helpers::memory::MemoryBuffer;
+use crate::render_helpers::primary_gpu_texture::PrimaryGpuTextureRenderElement;
+use crate::render_helpers::renderer::NiriRenderer;
+use crate::render_helpers::solid_color::{SolidColorBuffer, SolidColorRenderElement};
+use crate::render_helpers::texture::{TextureBuffer, TextureRenderElement};
+use crate::utils::to_physical_precise_round;
+
+/// Height of the titlebar in logical pixels.
+pub const TITLEBAR_HEIGHT: f64 = 24.;
+/// Horizontal padding before the title text.
+const PADDING: f64 = 8.;
+/// Size of the cross inside the close button, relative to the titlebar height.
+const CROSS_SIZE: f64 = 0.3;
+const FONT: &str = "sans 12px";
+
+const ACTIVE_BACKGROUND: Color = Color::new_unpremul(0.2, 0.2, 0.2, 1.);
+const INACTIVE_BACKGROUND: Color = Color::new_unpremul(0.12, 0.12, 0.12, 1.);
+const ACTIVE_FOREGROUND: Color = Color::new_unpremul(1., 1., 1., 1.);
+const INACTIVE_FOREGROUND: Color = Color::new_unpremul(0.6, 0.6, 0.6, 1.);
+
+/// Server-side titlebar with the window title and a close button.
+#[derive(Default)]
+pub struct Titlebar {
+    background: SolidColorBuffer,
+    /// Rendered title text and the parameters it was rendered with.
+    title: Option<(TitleParams, Option<MemoryBuffer>)>,
+    /// Rendered close button and the parameters it was rendered with.
+    close_button: Option<(ButtonParams, Option<MemoryBuffer>)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct TitleParams {
+    text: String,
+    /// Maximum width of the text in physical pixels.
+    max_width: i32,
+    is_active: bool,
+    scale: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ButtonParams {
+    is_active: bool,
+    scale: f64,
+}
+
+niri_render_elements! {
+    TitlebarRenderElement => {
+        SolidColor = SolidColorRenderElement,
+        Texture = PrimaryGpuTextureRenderElement,
+    }
+}
+
+impl Titlebar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates the titlebar for rendering.
+    ///
+    /// `size` is the current (possibly animated) titlebar size, while `width` is the final width
+    /// that the title text is laid out for, so that it doesn't need to be redrawn every frame of
+    /// a resize animation.
+    pub fn update_render_elements(
+        &mut self,
+        size: Size<f64, Logical>,
+        width: f64,
+        title: &str,
+        is_active: bool,
+        scale: f64,
+    ) {
+        let background = if is_active {
+            ACTIVE_BACKGROUND
+        } else {
+            INACTIVE_BACKGROUND
+        };
+        self.background.resize(size);
+        self.background.set_color(background.to_array_premul());
+
+        let max_width = width - PADDING - close_button_width(size.h);
+        let params = TitleParams {
+            text: title.to_owned(),
+            max_width: to_physical_precise_round(scale, max_width),
+            is_active,
+            scale,
+        };
+        if self.title.as_ref().map_or(true, |(p, _)| *p != params) {
+            // Very narrow windows have no space left for the title.
+            let buffer = if params.max_width > 0 {
+                render_title(&params)
+                    .map_err(|err| warn!("error rendering titlebar title: {err:?}"))
+                    .ok()
+            } else {
+                None
+            };
+            self.title = Some((params, buffer));
+        }
+
+        let params = ButtonParams { is_active, scale };
+        if self
+            .close_button
+            .as_ref()
+            .map_or(true, |(p, _)| *p != params)
+        {
+            let buffer = render_close_button(params)
+                .map_err(|err| warn!("error rendering titlebar close button: {err:?}"))
+                .ok();
+            self.close_button = Some((params, buffer));
+        }
+    }
+
+    pub fn render<R: NiriRenderer>(
+        &self,
+        renderer: &mut R,
+        location: Point<f64, Logical>,
+        scale: f64,
+    ) -> impl Iterator<Item = TitlebarRenderElement> {
+        let mut rv = Vec::new();
+
+        let size = self.background.size();
+        let button_width = close_button_width(size.h);
+
+        if let Some((_, Some(buffer))) = &self.close_button {
+            if let Ok(buffer) =
+                TextureBuffer::from_memory_buffer(renderer.as_gles_renderer(), buffer)
+            {
+                let loc = location + Point::from((size.w - button_width, 0.));
+                let loc = loc.to_physical_precise_round(scale).to_logical(scale);
+                let elem = TextureRenderElement::from_texture_buffer(
+                    buffer,
+                    loc,
+                    1.,
+                    None,
+                    None,
+                    Kind::Unspecified,
+                );
+                rv.push(PrimaryGpuTextureRenderElement(elem).into());
+            }
+        }
+
+        if let Some((_, Some(buffer))) = &self.title {
+            if let Ok(buffer) =
+                TextureBuffer::from_memory_buffer(renderer.as_gles_renderer(), buffer)
+            {
+                // During a resize animation the titlebar can be narrower than the text was laid
+                // out for, so crop the text to fit.
+                let buf_size = buffer.logical_size();
+                let visible_width = f64::min(buf_size.w, size.w - PADDING - button_width);
+                if visible_width > 0. {
+                    let (src, elem_size) = if visible_width < buf_size.w {
+                        let visible = Size::from((visible_width, buf_size.h));
+                        (Some(Rectangle::from_size(visible)), Some(visible))
+                    } else {
+                        (None, None)
+                    };
+
+                    let loc = location + Point::from((PADDING, 0.));
+                    let loc = loc.to_physical_precise_round(scale).to_logical(scale);
+                    let elem = TextureRenderElement::from_texture_buffer(
+                        buffer,
+                        loc,
+                        1.,
+                        src,
+                        elem_size,
+                        Kind::Unspecified,
+                    );
+                    rv.push(PrimaryGpuTextureRenderElement(elem).into());
+                }
+            }
+        }
+
+        let elem =
+            SolidColorRenderElement::from_buffer(&self.background, location, 1., Kind::Unspecified);
+        rv.push(elem.into());
+
+        rv.into_iter()
+    }
+
+    /// Returns whether the point, relative to the titlebar, is over the close button.
+    pub fn is_in_close_button(size: Size<f64, Logical>, point: Point<f64, Logical>) -> bool {
+        let width = close_button_width(size.h);
+        let rect = Rectangle::new(
+            Point::from((size.w - width, 0.)),
+            Size::from((width, size.h)),
+        );
+        rect.contains(point)
+    }
+}
+
+impl fmt::Debug for Titlebar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Titlebar")
+            .field("background", &self.background)
+            .field("title", &self.title.as_ref().map(|(params, _)| params))
+            .finish_non_exhaustive()
+    }
+}
+
+/// The close button is a square at the right edge of the titlebar.
+fn close_button_width(height: f64) -> f64 {
+    height
+}
+
+fn foreground(is_active: bool) -> (f64, f64, f64) {
+    let color = if is_active {
+        ACTIVE_FOREGROUND
+    } else {
+        INACTIVE_FOREGROUND
+    };
+    let [r, g, b, _] = color.to_array_unpremul();
+    (r.into(), g.into(), b.into())
+}
+
+fn render_title(params: &TitleParams) -> anyhow::Result<MemoryBuffer> {
+    let _span = tracy_client::span!("titlebar::render_title");
+
+    let scale = params.scale;
+    let width = params.max_width;
+    let height: i32 = to_physical_precise_round(scale, TITLEBAR_HEIGHT);
+
+    let surface = ImageSurface::create(cairo::Format::ARgb32, width, height)?;
+    let cr = cairo::Context::new(&surface)?;
+
+    let mut font = FontDescription::from_string(FONT);
+    font.set_absolute_size(to_physical_precise_round(scale, font.size()));
+
+    let layout = pangocairo::functions::create_layout(&cr);
+    layout.context().set_round_glyph_positions(false);
+    layout.set_font_description(Some(&font));
+    layout.set_width(width * pango::SCALE);
+    layout.set_ellipsize(EllipsizeMode::End);
+    layout.set_single_paragraph_mode(true);
+    layout.set_text(&params.text);
+
+    let (_, text_height) = layout.pixel_size();
+    cr.move_to(0., f64::from(height - text_height) / 2.);
+    let (r, g, b) = foreground(params.is_active);
+    cr.set_source_rgb(r, g, b);
+    pangocairo::functions::show_layout(&cr, &layout);
+    drop(cr);
+
+    let data = surface.take_data().unwrap();
+    let buffer = MemoryBuffer::new(
+        data.to_vec(),
+        Fourcc::Argb8888,
+        (width, height),
+        scale,
+        Transform::Normal,
+    );
+
+    Ok(buffer)
+}
+
+fn render_close_button(params: ButtonParams) -> anyhow::Result<MemoryBuffer> {
+    let _span = tracy_client::span!("titlebar::render_close_button");
+
+    let scale = params.scale;
+    let size: i32 = to_physical_precise_round(scale, TITLEBAR_HEIGHT);
+    let half = f64::from(size) / 2.;
+    let arm = f64::from(size) * CROSS_SIZE / 2.;
+
+    let surface = ImageSurface::create(cairo::Format::ARgb32, size, size)?;
+    let cr = cairo::Context::new(&surface)?;
+
+    let (r, g, b) = foreground(params.is_active);
+    cr.set_source_rgb(r, g, b);
+    cr.set_line_width(scale * 1.5);
+    cr.move_to(half - arm, half - arm);
+    cr.line_to(half + arm, half + arm);
+    cr.move_to(half + arm, half - arm);
+    cr.line_to(half - arm, half + arm);
+    cr.stroke()?;
+    drop(cr);
+
+    let data = surface.take_data().unwrap();
+    let buffer = MemoryBuffer::new(
+        data.to_vec(),
+        Fourcc::Argb8888,
+        (size, size),
+        scale,
+        Transform::Normal,
+    );
+
+    Ok(buffer)
+}
@@ -3,7 +3,7 @@ use std::rc::Rc;
 use std::time::Duration;
 
 use niri_config::{CenterFocusedColumn, OutputName, PresetSize, Workspace as WorkspaceConfig};
-use niri_ipc::{PositionChange, SizeChange};
+use niri_ipc::{PositionChange, SizeChange, SnapPosition};
 use smithay::backend::renderer::gles::GlesRenderer;
 use smithay::desktop::{layer_map_for_output, Window};
 use smithay::output::Output;
@@ -13,11 +13,12 @@ use smithay::utils::{Logical, Point, Rectangle, Scale, Serial, Size, Transform};
 use smithay::wayland::compositor::with_states;
 use smithay::wayland::shell::xdg::SurfaceCachedState;
 
-use super::floating::{FloatingSpace, FloatingSpaceRenderElement};
+use super::floating::{FloatingSpace, FloatingSpaceRenderElement, SnapHint};
 use super::scrolling::{
     Column, ColumnWidth, InsertHint, InsertPosition, ScrollDirection, ScrollingSpace,
     ScrollingSpaceRenderElement,
 };
+use super::startup_placeholder::{StartupPlaceholder, StartupPlaceholderId};
 use super::tile::{Tile, TileRenderSnapshot};
 use super::{ActivateWindow, InteractiveResizeData, LayoutElement, Options, RemovedTile, SizeFrac};
 use crate::animation::Clock;
@@ -90,6 +91,12 @@ pub struct Workspace<W: LayoutElement> {
     /// Optional name of this workspace.
     pub(super) name: Option<String>,
 
+    /// Name derived from the windows on this workspace by the auto-naming rules.
+    auto_name: Option<String>,
+
+    /// Windows that were active on this workspace, most recent last.
+    focus_history: Vec<W::Id>,
+
     /// Unique ID of this workspace.
     id: WorkspaceId,
 }
@@ -167,6 +174,10 @@ pub enum WorkspaceAddWindowTarget<'a, W: LayoutElement> {
     /// No particular preference.
     #[default]
     Auto,
+    /// No particular preference, for a window that is just opening.
+    ///
+    /// Unlike [`Self::Auto`], this puts tiled windows where new columns are configured to go.
+    NewWindow,
     /// As a new column at this index.
     NewColumnAt(usize),
     /// Next to this existing window.
@@ -178,6 +189,10 @@ impl OutputId {
         let output_name = output.user_data().get::<OutputName>().unwrap();
         Self(output_name.format_make_model_serial_or_connector())
     }
+
+    pub fn from_name(name: String) -> Self {
+        Self(name)
+    }
 }
 
 impl FloatingActive {
@@ -240,6 +255,8 @@ impl<W: LayoutElement> Workspace<W> {
             base_options,
             options,
             name: config.map(|c| c.name.0),
+            auto_name: None,
+            focus_history: Vec::new(),
             id: WorkspaceId::next(),
         }
     }
@@ -293,6 +310,8 @@ impl<W: LayoutElement> Workspace<W> {
             base_options,
             options,
             name: config.map(|c| c.name.0),
+            auto_name: None,
+            focus_history: Vec::new(),
             id: WorkspaceId::next(),
         }
     }
@@ -313,6 +332,14 @@ impl<W: LayoutElement> Workspace<W> {
         self.name = None;
     }
 
+    pub fn auto_name(&self) -> Option<&String> {
+        self.auto_name.as_ref()
+    }
+
+    pub fn set_auto_name(&mut self, auto_name: Option<String>) {
+        self.auto_name = auto_name;
+    }
+
     pub fn has_windows_or_name(&self) -> bool {
         self.has_windows() || self.name.is_some()
     }
@@ -535,7 +562,7 @@ impl<W: LayoutElement> Workspace<W> {
         tile.unfullscreen_to_floating = is_floating;
 
         match target {
-            WorkspaceAddWindowTarget::Auto => {
+            WorkspaceAddWindowTarget::Auto | WorkspaceAddWindowTarget::NewWindow => {
                 // Don't steal focus from an active fullscreen window.
                 let activate = activate.map_smart(|| !self.is_active_fullscreen());
 
@@ -548,8 +575,10 @@ impl<W: LayoutElement> Workspace<W> {
                         self.floating_is_active = FloatingActive::Yes;
                     }
                 } else {
+                    let col_idx = matches!(target, WorkspaceAddWindowTarget::NewWindow)
+                        .then(|| self.scrolling.new_column_idx());
                     self.scrolling
-                        .add_tile(None, tile, activate, width, is_full_width, None);
+                        .add_tile(col_idx, tile, activate, width, is_full_width, None);
 
                     if activate {
                         self.floating_is_active = FloatingActive::No;
@@ -789,7 +818,7 @@ impl<W: LayoutElement> Workspace<W> {
         });
         toplevel.with_pending_state(|state| {
             if state.states.contains(xdg_toplevel::State::Fullscreen) {
-                state.size = Some(self.view_size.to_i32_round());
+                state.size = Some(rules.compute_fullscreen_size(self.view_size.to_i32_round()));
             } else {
                 let size =
                     self.new_window_size(width, height, is_floating, rules, (min_size, max_size));
@@ -1048,6 +1077,11 @@ impl<W: LayoutElement> Workspace<W> {
         }
     }
 
+    /// Moves the view to show a new window that opened unfocused, if configured to.
+    pub fn reveal_new_window(&mut self, window: &W::Id) {
+        self.scrolling.reveal_new_window(window);
+    }
+
     pub fn reset_window_height(&mut self, window: Option<&W::Id>) {
         if window.map_or(self.floating_is_active.get(), |id| {
             self.floating.has_window(id)
@@ -1226,6 +1260,48 @@ impl<W: LayoutElement> Workspace<W> {
         };
     }
 
+    pub fn snap_floating_window(
+        &mut self,
+        id: Option<&W::Id>,
+        position: SnapPosition,
+        animate: bool,
+    ) {
+        // Snapping only makes sense for floating windows.
+        if id.map_or(self.floating_is_active.get(), |id| {
+            self.floating.has_window(id)
+        }) {
+            self.floating.snap_window(id, position, animate);
+        }
+    }
+
+    pub fn bring_window_on_screen(&mut self, id: Option<&W::Id>) {
+        // Tiled windows are always reachable through the scrolling layout.
+        if id.map_or(self.floating_is_active.get(), |id| {
+            self.floating.has_window(id)
+        }) {
+            self.floating.bring_window_on_screen(id);
+        }
+    }
+
+    pub fn cycle_picture_in_picture_corner(&mut self, id: Option<&W::Id>) {
+        // Picture-in-picture windows usually aren't focused (they show on top of a fullscreen
+        // window), so without an explicit id fall back to any of them.
+        let id = id.cloned().or_else(|| {
+            let active = self
+                .active_window()
+                .filter(|win| win.rules().picture_in_picture == Some(true));
+            let win = active.or_else(|| self.picture_in_picture_windows().next());
+            win.map(|win| win.id().clone())
+        });
+        let Some(id) = id else {
+            return;
+        };
+
+        if self.floating.has_window(&id) {
+            self.floating.cycle_picture_in_picture_corner(Some(&id));
+        }
+    }
+
     pub fn move_floating_window(
         &mut self,
         id: Option<&W::Id>,
@@ -1279,6 +1355,14 @@ impl<W: LayoutElement> Workspace<W> {
         }
     }
 
+    /// Returns the floating picture-in-picture windows on this workspace.
+    pub fn picture_in_picture_windows(&self) -> impl Iterator<Item = &W> + '_ {
+        self.floating
+            .tiles()
+            .filter(|tile| tile.is_picture_in_picture())
+            .map(Tile::window)
+    }
+
     pub fn has_windows(&self) -> bool {
         self.windows().next().is_some()
     }
@@ -1303,7 +1387,8 @@ impl<W: LayoutElement> Workspace<W> {
 
         let floating = self.floating.tiles_with_render_positions();
         let visible = self.is_floating_visible();
-        let floating = floating.map(move |(tile, pos)| (tile, pos, visible));
+        let floating =
+            floating.map(move |(tile, pos)| (tile, pos, visible || tile.is_picture_in_picture()));
 
         floating.chain(scrolling)
     }
@@ -1347,19 +1432,18 @@ impl<W: LayoutElement> Workspace<W> {
         let scrolling = scrolling.into_iter().map(WorkspaceRenderElement::from);
 
         let floating_focus_ring = focus_ring && self.floating_is_active();
-        let floating = self.is_floating_visible().then(|| {
-            let view_rect = Rectangle::from_size(self.view_size);
-            let floating = self.floating.render_elements(
-                renderer,
-                view_rect,
-                scale,
-                target,
-                floating_focus_ring,
-            );
-            floating.into_iter().map(WorkspaceRenderElement::from)
-        });
+        let view_rect = Rectangle::from_size(self.view_size);
+        let floating = self.floating.render_elements(
+            renderer,
+            view_rect,
+            scale,
+            target,
+            floating_focus_ring,
+            !self.is_floating_visible(),
+        );
+        let floating = floating.into_iter().map(WorkspaceRenderElement::from);
 
-        floating.into_iter().flatten().chain(scrolling)
+        floating.chain(scrolling)
     }
 
     pub fn render_above_top_layer(&self) -> bool {
@@ -1447,7 +1531,8 @@ impl<W: LayoutElement> Workspace<W> {
             })
     }
 
-    pub fn resize_edges_under(&self, pos: Point<f64, Logical>) -> Option<ResizeEdge> {
+    /// Returns the tile under the position and the position within that tile.
+    fn tile_under(&self, pos: Point<f64, Logical>) -> Option<(&Tile<W>, Point<f64, Logical>)> {
         self.tiles_with_render_positions()
             .find_map(|(tile, tile_pos, visible)| {
                 // This logic should be consistent with window_under() in when it returns Some vs.
@@ -1461,26 +1546,93 @@ impl<W: LayoutElement> Workspace<W> {
                 if tile.is_in_input_region(pos_within_tile)
                     || tile.is_in_activation_region(pos_within_tile)
                 {
-                    let size = tile.tile_size().to_f64();
-
-                    let mut edges = ResizeEdge::empty();
-                    if pos_within_tile.x < size.w / 3. {
-                        edges |= ResizeEdge::LEFT;
-                    } else if 2. * size.w / 3. < pos_within_tile.x {
-                        edges |= ResizeEdge::RIGHT;
-                    }
-                    if pos_within_tile.y < size.h / 3. {
-                        edges |= ResizeEdge::TOP;
-                    } else if 2. * size.h / 3. < pos_within_tile.y {
-                        edges |= ResizeEdge::BOTTOM;
-                    }
-                    return Some(edges);
+                    return Some((tile, pos_within_tile));
                 }
 
                 None
             })
     }
 
+    /// Returns the window whose titlebar close button is under the position.
+    pub fn titlebar_close_button_under(&self, pos: Point<f64, Logical>) -> Option<&W> {
+        let (tile, pos_within_tile) = self.tile_under(pos)?;
+        let is_close = tile.is_in_close_button(pos_within_tile);
+        is_close.then(|| tile.window())
+    }
+
+    pub fn resize_edges_under(&self, pos: Point<f64, Logical>) -> Option<ResizeEdge> {
+        let (tile, pos_within_tile) = self.tile_under(pos)?;
+        let size = tile.tile_size().to_f64();
+
+        let mut edges = ResizeEdge::empty();
+        if pos_within_tile.x < size.w / 3. {
+            edges |= ResizeEdge::LEFT;
+        } else if 2. * size.w / 3. < pos_within_tile.x {
+            edges |= ResizeEdge::RIGHT;
+        }
+        if pos_within_tile.y < size.h / 3. {
+            edges |= ResizeEdge::TOP;
+        } else if 2. * size.h / 3. < pos_within_tile.y {
+            edges |= ResizeEdge::BOTTOM;
+        }
+        Some(edges)
+    }
+
+    /// Returns the corner of the window under the position that is closest to it.
+    pub fn resize_corner_under(&self, pos: Point<f64, Logical>) -> Option<ResizeEdge> {
+        let (tile, pos_within_tile) = self.tile_under(pos)?;
+        let size = tile.tile_size().to_f64();
+
+        let mut edges = if pos_within_tile.x < size.w / 2. {
+            ResizeEdge::LEFT
+        } else {
+            ResizeEdge::RIGHT
+        };
+        if pos_within_tile.y < size.h / 2. {
+            edges |= ResizeEdge::TOP;
+        } else {
+            edges |= ResizeEdge::BOTTOM;
+        }
+        Some(edges)
+    }
+
+    /// Returns the window with an edge within `edge_width` outside of the position.
+    ///
+    /// Positions on the window itself are not considered, so that they go to the window contents.
+    pub fn resize_edges_near(
+        &self,
+        pos: Point<f64, Logical>,
+        edge_width: f64,
+    ) -> Option<(&W, ResizeEdge)> {
+        self.tiles_with_render_positions()
+            .find_map(|(tile, tile_pos, visible)| {
+                if !visible {
+                    return None;
+                }
+
+                let pos_within_tile = pos - tile_pos;
+                let size = tile.tile_size().to_f64();
+
+                let mut edges = ResizeEdge::empty();
+                if -edge_width <= pos_within_tile.x && pos_within_tile.x < 0. {
+                    edges |= ResizeEdge::LEFT;
+                } else if size.w < pos_within_tile.x && pos_within_tile.x <= size.w + edge_width {
+                    edges |= ResizeEdge::RIGHT;
+                } else if !(0. ..=size.w).contains(&pos_within_tile.x) {
+                    return None;
+                }
+                if -edge_width <= pos_within_tile.y && pos_within_tile.y < 0. {
+                    edges |= ResizeEdge::TOP;
+                } else if size.h < pos_within_tile.y && pos_within_tile.y <= size.h + edge_width {
+                    edges |= ResizeEdge::BOTTOM;
+                } else if !(0. ..=size.h).contains(&pos_within_tile.y) {
+                    return None;
+                }
+
+                (!edges.is_empty()).then(|| (tile.window(), edges))
+            })
+    }
+
     pub fn descendants_added(&mut self, id: &W::Id) -> bool {
         self.floating.descendants_added(id)
     }
@@ -1496,6 +1648,33 @@ impl<W: LayoutElement> Workspace<W> {
             .refresh(is_active && !self.floating_is_active.get());
         self.floating
             .refresh(is_active && self.floating_is_active.get());
+
+        self.refresh_focus_history();
+    }
+
+    fn refresh_focus_history(&mut self) {
+        // Forget windows that were closed or moved to other workspaces.
+        let (scrolling, floating) = (&self.scrolling, &self.floating);
+        self.focus_history
+            .retain(|id| scrolling.contains(id) || floating.has_window(id));
+
+        if let Some(active) = self.active_window() {
+            let id = active.id();
+            if self.focus_history.last() != Some(id) {
+                let id = id.clone();
+                self.focus_history.retain(|x| *x != id);
+                self.focus_history.push(id);
+            }
+        }
+    }
+
+    /// Returns the window that was active on this workspace before the current active window.
+    pub fn previously_active_window(&self) -> Option<&W::Id> {
+        let active = self.active_window().map(|win| win.id());
+        self.focus_history
+            .iter()
+            .rev()
+            .find(|id| Some(*id) != active && self.has_window(id))
     }
 
     pub fn scroll_amount_to_activate(&self, window: &W::Id) -> f64 {
@@ -1542,6 +1721,30 @@ impl<W: LayoutElement> Workspace<W> {
         self.scrolling.clear_insert_hint();
     }
 
+    pub fn set_snap_hint(&mut self, snap_hint: SnapHint) {
+        self.floating.set_snap_hint(snap_hint);
+    }
+
+    pub fn clear_snap_hint(&mut self) {
+        self.floating.clear_snap_hint();
+    }
+
+    pub fn add_startup_placeholder(&mut self, placeholder: StartupPlaceholder) {
+        self.scrolling.add_startup_placeholder(placeholder);
+    }
+
+    pub fn has_startup_placeholder(&self, id: StartupPlaceholderId) -> bool {
+        self.scrolling.has_startup_placeholder(id)
+    }
+
+    pub fn remove_startup_placeholder(&mut self, id: StartupPlaceholderId) -> bool {
+        self.scrolling.remove_startup_placeholder(id)
+    }
+
+    pub fn remove_startup_placeholder_for_app_id(&mut self, app_id: &str) -> bool {
+        self.scrolling.remove_startup_placeholder_for_app_id(app_id)
+    }
+
     pub fn get_insert_position(&self, pos: Point<f64, Logical>) -> InsertPosition {
         self.scrolling.get_insert_position(pos)
     }
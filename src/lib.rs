@@ -13,10 +13,14 @@ pub mod input;
 pub mod ipc;
 pub mod layer;
 pub mod layout;
+#[cfg(feature = "metrics")]
+pub mod metrics;
 pub mod niri;
 pub mod protocols;
 pub mod render_helpers;
 pub mod rubber_band;
+#[cfg(feature = "xdp-gnome-screencast")]
+pub mod screen_recording;
 pub mod ui;
 pub mod utils;
 pub mod window;
@@ -14,7 +14,8 @@ use directories::ProjectDirs;
 use niri::cli::{Cli, Sub};
 #[cfg(feature = "dbus")]
 use niri::dbus;
-use niri::ipc::client::handle_msg;
+use niri::input::add_emergency_binds_if_needed;
+use niri::ipc::client::{handle_msg, watch_msg};
 use niri::niri::State;
 use niri::utils::spawning::{
     spawn, store_and_increase_nofile_rlimit, CHILD_ENV, REMOVE_ENV_RUST_BACKTRACE,
@@ -104,8 +105,18 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 info!("config is valid");
                 return Ok(());
             }
-            Sub::Msg { msg, json } => {
-                handle_msg(msg, json)?;
+            Sub::Msg {
+                msg,
+                json,
+                format,
+                watch,
+            } => {
+                let format = format.as_deref();
+                if watch {
+                    watch_msg(msg, json, format)?;
+                } else {
+                    handle_msg(msg, json, format)?;
+                }
                 return Ok(());
             }
             Sub::Panic => cause_panic(),
@@ -166,6 +177,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             config_errored = true;
         })
         .unwrap_or_default();
+    let no_binds = add_emergency_binds_if_needed(&mut config.binds);
 
     let spawn_at_startup = mem::take(&mut config.spawn_at_startup);
     *CHILD_ENV.write().unwrap() = mem::take(&mut config.environment);
@@ -215,6 +227,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     #[cfg(feature = "dbus")]
     dbus::DBusServers::start(&mut state, cli.session);
+    #[cfg(feature = "dbus")]
+    state.niri.watch_accelerometer_if_needed();
 
     if env::var_os("NIRI_DISABLE_SYSTEM_MANAGER_NOTIFY").map_or(true, |x| x != "1") {
         // Notify systemd we're ready.
@@ -246,7 +260,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     spawn(cli.command, None);
 
     for elem in spawn_at_startup {
-        spawn(elem.command, None);
+        state.spawn_at_startup(elem);
     }
 
     // Show the config error notification right away if needed.
@@ -254,6 +268,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         state.niri.config_error_notification.show();
     } else if config_created {
         state.niri.config_error_notification.show_created(path);
+    } else if no_binds {
+        state.niri.config_error_notification.show_no_binds();
     }
 
     // Run the compositor.
@@ -0,0 +1,418 @@
+//! Prometheus metrics exporter.
+//!
+//! Serves frame times, input latency, window counts and IPC request rates in the Prometheus text
+//! format over HTTP, for long-term monitoring of the compositor health.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+use anyhow::Context;
+use calloop::io::Async;
+use futures_util::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use niri_ipc::Request;
+use smithay::output::Output;
+use smithay::reexports::calloop::generic::Generic;
+use smithay::reexports::calloop::{Interest, LoopHandle, Mode, PostAction, RegistrationToken};
+
+use crate::niri::{Niri, State};
+
+/// Bucket upper bounds for the frame render time histogram, in seconds.
+const FRAME_TIME_BUCKETS: &[f64] = &[0.001, 0.002, 0.004, 0.008, 0.016, 0.033, 0.066, 0.133];
+
+/// Bucket upper bounds for the input latency histogram, in seconds.
+const INPUT_LATENCY_BUCKETS: &[f64] = &[0.0005, 0.001, 0.002, 0.005, 0.01, 0.02, 0.05, 0.1];
+
+/// Input latencies above this are assumed to come from bogus event timestamps.
+const MAX_INPUT_LATENCY: Duration = Duration::from_secs(10);
+
+pub struct Metrics {
+    config: niri_config::Metrics,
+    /// Listening socket source, if the exporter is running.
+    listener: Option<RegistrationToken>,
+    /// Frame render times, by output name.
+    ///
+    /// When outputs aren't labeled, all frames are recorded under an empty name.
+    frame_times: HashMap<String, Histogram>,
+    input_latency: Histogram,
+    /// Number of IPC requests, by request type.
+    ///
+    /// When request types aren't labeled, all requests are counted under an empty name.
+    ipc_requests: HashMap<String, u64>,
+}
+
+#[derive(Debug, Clone)]
+struct Histogram {
+    buckets: &'static [f64],
+    /// Number of observations in each bucket, non-cumulative.
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self {
+            config: niri_config::Metrics::default(),
+            listener: None,
+            frame_times: HashMap::new(),
+            input_latency: Histogram::new(INPUT_LATENCY_BUCKETS),
+            ipc_requests: HashMap::new(),
+        }
+    }
+
+    /// Applies the config, starting, restarting or stopping the exporter as needed.
+    pub fn set_config(
+        &mut self,
+        event_loop: &LoopHandle<'static, State>,
+        config: &niri_config::Metrics,
+    ) {
+        // Changing the labels changes the series, so start over.
+        if self.config.label_outputs != config.label_outputs {
+            self.frame_times.clear();
+        }
+        if self.config.label_ipc_requests != config.label_ipc_requests {
+            self.ipc_requests.clear();
+        }
+
+        let listen_changed = self.config.listen != config.listen;
+        self.config = config.clone();
+
+        if !listen_changed {
+            return;
+        }
+
+        if let Some(token) = self.listener.take() {
+            event_loop.remove(token);
+        }
+
+        if let Some(address) = &self.config.listen {
+            match start(event_loop, address) {
+                Ok(token) => {
+                    info!("serving metrics on {address}");
+                    self.listener = Some(token);
+                }
+                Err(err) => warn!("error starting metrics exporter: {err:?}"),
+            }
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.listener.is_some()
+    }
+
+    pub fn record_frame(&mut self, output: &Output, duration: Duration) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let name = if self.config.label_outputs {
+            output.name()
+        } else {
+            String::new()
+        };
+        self.frame_times
+            .entry(name)
+            .or_insert_with(|| Histogram::new(FRAME_TIME_BUCKETS))
+            .observe(duration);
+    }
+
+    pub fn record_input_latency(&mut self, latency: Duration) {
+        if !self.is_enabled() || latency > MAX_INPUT_LATENCY {
+            return;
+        }
+
+        self.input_latency.observe(latency);
+    }
+
+    pub fn record_ipc_request(&mut self, request: &Request) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let name = if self.config.label_ipc_requests {
+            request_name(request)
+        } else {
+            String::new()
+        };
+        *self.ipc_requests.entry(name).or_default() += 1;
+    }
+
+    pub fn remove_output(&mut self, output: &Output) {
+        // Don't keep series for disconnected outputs around forever.
+        self.frame_times.remove(&output.name());
+    }
+
+    fn render(&self, niri: &Niri) -> String {
+        let _span = tracy_client::span!("Metrics::render");
+
+        let mut buf = String::new();
+
+        let name = "niri_frame_render_seconds";
+        write_header(&mut buf, name, "histogram", "Time spent rendering a frame.");
+        let mut frame_times: Vec<_> = self.frame_times.iter().collect();
+        frame_times.sort_unstable_by_key(|(output, _)| *output);
+        for (output, histogram) in frame_times {
+            let labels = if self.config.label_outputs {
+                format!("output=\"{}\"", escape_label_value(output))
+            } else {
+                String::new()
+            };
+            histogram.write(&mut buf, name, &labels);
+        }
+
+        let name = "niri_input_latency_seconds";
+        let help = "Time from an input event to niri processing it.";
+        write_header(&mut buf, name, "histogram", help);
+        self.input_latency.write(&mut buf, name, "");
+
+        let name = "niri_ipc_requests_total";
+        write_header(&mut buf, name, "counter", "Number of IPC requests.");
+        let mut ipc_requests: Vec<_> = self.ipc_requests.iter().collect();
+        ipc_requests.sort_unstable_by_key(|(request, _)| *request);
+        for (request, count) in ipc_requests {
+            if self.config.label_ipc_requests {
+                let request = escape_label_value(request);
+                writeln!(buf, "{name}{{request=\"{request}\"}} {count}").unwrap();
+            } else {
+                writeln!(buf, "{name} {count}").unwrap();
+            }
+        }
+
+        let name = "niri_windows";
+        write_header(&mut buf, name, "gauge", "Number of open windows.");
+        writeln!(buf, "{name} {}", niri.layout.windows().count()).unwrap();
+
+        let name = "niri_outputs";
+        write_header(&mut buf, name, "gauge", "Number of connected outputs.");
+        writeln!(buf, "{name} {}", niri.global_space.outputs().count()).unwrap();
+
+        buf
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Histogram {
+    fn new(buckets: &'static [f64]) -> Self {
+        Self {
+            buckets,
+            counts: vec![0; buckets.len()],
+            sum: 0.,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: Duration) {
+        let value = value.as_secs_f64();
+        if let Some(idx) = self.buckets.iter().position(|le| value <= *le) {
+            self.counts[idx] += 1;
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn write(&self, buf: &mut String, name: &str, labels: &str) {
+        let sep = if labels.is_empty() { "" } else { "," };
+
+        let mut cumulative = 0;
+        for (le, count) in self.buckets.iter().zip(&self.counts) {
+            cumulative += count;
+            writeln!(
+                buf,
+                "{name}_bucket{{{labels}{sep}le=\"{le}\"}} {cumulative}"
+            )
+            .unwrap();
+        }
+        writeln!(
+            buf,
+            "{name}_bucket{{{labels}{sep}le=\"+Inf\"}} {}",
+            self.count
+        )
+        .unwrap();
+
+        let labels = if labels.is_empty() {
+            String::new()
+        } else {
+            format!("{{{labels}}}")
+        };
+        writeln!(buf, "{name}_sum{labels} {}", self.sum).unwrap();
+        writeln!(buf, "{name}_count{labels} {}", self.count).unwrap();
+    }
+}
+
+fn start(
+    event_loop: &LoopHandle<'static, State>,
+    address: &str,
+) -> anyhow::Result<RegistrationToken> {
+    let listener = TcpListener::bind(address).context("error binding socket")?;
+    listener
+        .set_nonblocking(true)
+        .context("error setting socket to non-blocking")?;
+
+    let source = Generic::new(listener, Interest::READ, Mode::Level);
+    let token = event_loop
+        .insert_source(source, |_, socket, state| {
+            match socket.accept() {
+                Ok((stream, _)) => on_new_client(state, stream),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => (),
+                Err(e) => return Err(e),
+            }
+
+            Ok(PostAction::Continue)
+        })
+        .unwrap();
+
+    Ok(token)
+}
+
+fn on_new_client(state: &mut State, stream: TcpStream) {
+    let _span = tracy_client::span!("metrics::on_new_client");
+
+    let stream = match state.niri.event_loop.adapt_io(stream) {
+        Ok(stream) => stream,
+        Err(err) => {
+            warn!("error making metrics stream async: {err:?}");
+            return;
+        }
+    };
+
+    // Scrapes are cheap and infrequent, so render the metrics right away rather than going back
+    // to the event loop once the request is read.
+    let body = state.niri.metrics.render(&state.niri);
+
+    let future = async move {
+        if let Err(err) = handle_client(stream, body).await {
+            debug!("error handling metrics client: {err:?}");
+        }
+    };
+    if let Err(err) = state.niri.scheduler.schedule(future) {
+        warn!("error scheduling metrics stream future: {err:?}");
+    }
+}
+
+async fn handle_client(stream: Async<'static, TcpStream>, body: String) -> anyhow::Result<()> {
+    let (read, mut write) = stream.split();
+    let mut read = BufReader::new(read);
+
+    let mut request_line = String::new();
+    read.read_line(&mut request_line)
+        .await
+        .context("error reading request")?;
+
+    // Skip the headers, we don't need any of them.
+    loop {
+        let mut line = String::new();
+        let n = read
+            .read_line(&mut line)
+            .await
+            .context("error reading request headers")?;
+        if n == 0 || line.trim_end().is_empty() {
+            break;
+        }
+    }
+
+    let mut parts = request_line.split_ascii_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let path = parts.next().unwrap_or_default();
+
+    let response = if method != "GET" {
+        response(
+            "405 Method Not Allowed",
+            "text/plain",
+            "only GET is supported\n",
+        )
+    } else if path == "/metrics" || path.starts_with("/metrics?") {
+        response("200 OK", "text/plain; version=0.0.4", &body)
+    } else {
+        response(
+            "404 Not Found",
+            "text/plain",
+            "metrics are served at /metrics\n",
+        )
+    };
+
+    write
+        .write_all(response.as_bytes())
+        .await
+        .context("error writing response")?;
+    write.close().await.context("error closing stream")?;
+
+    Ok(())
+}
+
+fn response(status: &str, content_type: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {status}\r\n\
+         Content-Type: {content_type}\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\
+         \r\n\
+         {body}",
+        body.len()
+    )
+}
+
+/// Returns the name of the request type, e.g. `Windows` or `Action`.
+fn request_name(request: &Request) -> String {
+    let name = format!("{request:?}");
+    let end = name
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(name.len());
+    name[..end].to_owned()
+}
+
+fn write_header(buf: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(buf, "# HELP {name} {help}").unwrap();
+    writeln!(buf, "# TYPE {name} {kind}").unwrap();
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', r#"\""#)
+        .replace('\n', r"\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_is_cumulative() {
+        let mut histogram = Histogram::new(&[0.001, 0.01]);
+        histogram.observe(Duration::from_micros(500));
+        histogram.observe(Duration::from_millis(5));
+        histogram.observe(Duration::from_millis(50));
+
+        let mut buf = String::new();
+        histogram.write(&mut buf, "test", "output=\"DP-1\"");
+        let lines: Vec<_> = buf.lines().filter(|l| !l.starts_with("test_sum")).collect();
+        assert_eq!(
+            lines,
+            [
+                "test_bucket{output=\"DP-1\",le=\"0.001\"} 1",
+                "test_bucket{output=\"DP-1\",le=\"0.01\"} 2",
+                "test_bucket{output=\"DP-1\",le=\"+Inf\"} 3",
+                "test_count{output=\"DP-1\"} 3",
+            ]
+        );
+    }
+
+    #[test]
+    fn request_names() {
+        assert_eq!(request_name(&Request::Windows), "Windows");
+        assert_eq!(
+            request_name(&Request::Action(niri_ipc::Action::Quit {
+                skip_confirmation: true
+            })),
+            "Action"
+        );
+    }
+}
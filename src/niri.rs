@@ -1,8 +1,9 @@
 use std::cell::{Cell, OnceCell, RefCell};
+use std::cmp::Reverse;
 use std::collections::{HashMap, HashSet};
 use std::ffi::OsString;
 use std::os::unix::net::UnixStream;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{self, Receiver, Sender};
@@ -14,11 +15,12 @@ use _server_decoration::server::org_kde_kwin_server_decoration_manager::Mode as
 use anyhow::{bail, ensure, Context};
 use calloop::futures::Scheduler;
 use niri_config::{
-    Config, FloatOrInt, Key, Modifiers, OutputName, PreviewRender, TrackLayout, WorkspaceReference,
-    DEFAULT_BACKGROUND_COLOR,
+    Config, FloatOrInt, FocusFollowsMouse, FocusFollowsMouseMode, ImageFormat, Key, Modifiers,
+    OutputName, OutputProfile, PreviewRender, SpawnAtStartup, TrackLayout, WarpMouseToFocus,
+    WorkspaceAutoNameRule, WorkspaceReference, DEFAULT_BACKGROUND_COLOR,
 };
 use smithay::backend::allocator::Fourcc;
-use smithay::backend::input::Keycode;
+use smithay::backend::input::{Keycode, TouchSlot};
 use smithay::backend::renderer::damage::OutputDamageTracker;
 use smithay::backend::renderer::element::memory::MemoryRenderBufferRenderElement;
 use smithay::backend::renderer::element::solid::{SolidColorBuffer, SolidColorRenderElement};
@@ -32,8 +34,10 @@ use smithay::backend::renderer::element::{
     default_primary_scanout_output_compare, Element as _, Id, Kind, PrimaryScanoutOutput,
     RenderElementStates,
 };
-use smithay::backend::renderer::gles::GlesRenderer;
+use smithay::backend::renderer::gles::{GlesRenderer, GlesTexture};
 use smithay::backend::renderer::sync::SyncPoint;
+#[cfg(feature = "xdp-gnome-screencast")]
+use smithay::backend::renderer::utils::CommitCounter;
 use smithay::backend::renderer::{Color32F, Unbind};
 use smithay::desktop::utils::{
     bbox_from_surface_tree, output_update, send_dmabuf_feedback_surface_tree,
@@ -54,6 +58,7 @@ use smithay::reexports::calloop::timer::{TimeoutAction, Timer};
 use smithay::reexports::calloop::{
     Interest, LoopHandle, LoopSignal, Mode, PostAction, RegistrationToken,
 };
+use smithay::reexports::rustix::pipe::{pipe_with, PipeFlags};
 use smithay::reexports::wayland_protocols::ext::session_lock::v1::server::ext_session_lock_v1::ExtSessionLockV1;
 use smithay::reexports::wayland_protocols::xdg::shell::server::xdg_toplevel::WmCapabilities;
 use smithay::reexports::wayland_protocols_misc::server_decoration as _server_decoration;
@@ -61,6 +66,7 @@ use smithay::reexports::wayland_protocols_wlr::screencopy::v1::server::zwlr_scre
 use smithay::reexports::wayland_server::backend::{
     ClientData, ClientId, DisconnectReason, GlobalId,
 };
+use smithay::reexports::wayland_server::protocol::wl_data_device_manager::DndAction;
 use smithay::reexports::wayland_server::protocol::wl_shm;
 use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
 use smithay::reexports::wayland_server::{Client, Display, DisplayHandle, Resource};
@@ -87,9 +93,14 @@ use smithay::wayland::pointer_gestures::PointerGesturesState;
 use smithay::wayland::presentation::PresentationState;
 use smithay::wayland::relative_pointer::RelativePointerManagerState;
 use smithay::wayland::security_context::SecurityContextState;
-use smithay::wayland::selection::data_device::{set_data_device_selection, DataDeviceState};
-use smithay::wayland::selection::primary_selection::PrimarySelectionState;
+use smithay::wayland::selection::data_device::{
+    request_data_device_client_selection, set_data_device_selection, DataDeviceState,
+};
+use smithay::wayland::selection::primary_selection::{
+    request_primary_client_selection, set_primary_selection, PrimarySelectionState,
+};
 use smithay::wayland::selection::wlr_data_control::DataControlState;
+use smithay::wayland::selection::SelectionTarget;
 use smithay::wayland::session_lock::{LockSurface, SessionLockManagerState, SessionLocker};
 use smithay::wayland::shell::kde::decoration::KdeDecorationState;
 use smithay::wayland::shell::wlr_layer::{self, Layer, WlrLayerShellState};
@@ -103,7 +114,9 @@ use smithay::wayland::tablet_manager::TabletManagerState;
 use smithay::wayland::text_input::TextInputManagerState;
 use smithay::wayland::viewporter::ViewporterState;
 use smithay::wayland::virtual_keyboard::VirtualKeyboardManagerState;
-use smithay::wayland::xdg_activation::XdgActivationState;
+use smithay::wayland::xdg_activation::{
+    XdgActivationState, XdgActivationToken, XdgActivationTokenData,
+};
 use smithay::wayland::xdg_foreign::XdgForeignState;
 
 use crate::animation::Clock;
@@ -115,50 +128,76 @@ use crate::dbus::gnome_shell_introspect::{self, IntrospectToNiri, NiriToIntrospe
 #[cfg(feature = "dbus")]
 use crate::dbus::gnome_shell_screenshot::{NiriToScreenshot, ScreenshotToNiri};
 #[cfg(feature = "xdp-gnome-screencast")]
-use crate::dbus::mutter_screen_cast::{self, ScreenCastToNiri};
+use crate::dbus::mutter_screen_cast::{self, CursorMode, ScreenCastToNiri};
 use crate::frame_clock::FrameClock;
 use crate::handlers::{configure_lock_surface, XDG_ACTIVATION_TOKEN_TIMEOUT};
+use crate::input::dwt_tracker::DwtTracker;
 use crate::input::scroll_tracker::ScrollTracker;
 use crate::input::{
-    apply_libinput_settings, mods_with_finger_scroll_binds, mods_with_mouse_binds,
-    mods_with_wheel_binds, TabletData,
+    add_emergency_binds_if_needed, apply_libinput_settings, mods_with_finger_scroll_binds,
+    mods_with_mouse_binds, mods_with_wheel_binds, CompositorMod, TabletData,
 };
 use crate::ipc::server::IpcServer;
 use crate::layer::mapped::LayerSurfaceRenderElement;
 use crate::layer::MappedLayer;
+use crate::layout::startup_placeholder::{
+    StartupApp, StartupPlaceholderId, STARTUP_PLACEHOLDER_SHOW_DELAY,
+    STARTUP_PLACEHOLDER_SPINNER_STEP, STARTUP_PLACEHOLDER_TIMEOUT,
+};
 use crate::layout::tile::TileRenderElement;
 use crate::layout::workspace::WorkspaceId;
 use crate::layout::{Layout, LayoutElement as _, MonitorRenderElement};
 use crate::niri_render_elements;
+use crate::protocols::fixes::FixesState;
 use crate::protocols::foreign_toplevel::{self, ForeignToplevelManagerState};
 use crate::protocols::gamma_control::GammaControlManagerState;
+use crate::protocols::image_copy_capture::ImageCopyCaptureState;
 use crate::protocols::mutter_x11_interop::MutterX11InteropManagerState;
 use crate::protocols::output_management::OutputManagementManagerState;
 use crate::protocols::screencopy::{Screencopy, ScreencopyBuffer, ScreencopyManagerState};
 use crate::protocols::virtual_pointer::VirtualPointerManagerState;
 use crate::pw_utils::{Cast, PipeWire};
 #[cfg(feature = "xdp-gnome-screencast")]
-use crate::pw_utils::{CastSizeChange, CastTarget, PwToNiri};
+use crate::pw_utils::{CastSink, CastSizeChange, CastTarget, CursorBitmap, CursorMeta, PwToNiri};
 use crate::render_helpers::debug::draw_opaque_regions;
 use crate::render_helpers::primary_gpu_texture::PrimaryGpuTextureRenderElement;
 use crate::render_helpers::renderer::NiriRenderer;
-use crate::render_helpers::texture::TextureBuffer;
+use crate::render_helpers::texture::{TextureBuffer, TextureRenderElement};
 use crate::render_helpers::{
-    render_to_dmabuf, render_to_encompassing_texture, render_to_shm, render_to_texture,
-    render_to_vec, shaders, RenderTarget, SplitElements,
+    render_to_dmabuf, render_to_encompassing_texture, render_to_reused_texture, render_to_shm,
+    render_to_texture, render_to_vec, shaders, RenderTarget, SplitElements,
 };
+#[cfg(feature = "xdp-gnome-screencast")]
+use crate::screen_recording::ScreenRecording;
+use crate::ui::clipboard_history::ClipboardHistoryPicker;
 use crate::ui::config_error_notification::ConfigErrorNotification;
-use crate::ui::exit_confirm_dialog::ExitConfirmDialog;
-use crate::ui::hotkey_overlay::HotkeyOverlay;
+use crate::ui::confirm_dialog::ConfirmDialog;
+use crate::ui::dnd_action_indicator::DndActionIndicator;
+use crate::ui::hotkey_overlay::{key_name, HotkeyOverlay};
 use crate::ui::screen_transition::{self, ScreenTransition};
+use crate::ui::screenshot_feedback::ScreenshotFeedback;
 use crate::ui::screenshot_ui::{OutputScreenshot, ScreenshotUi, ScreenshotUiRenderElement};
+use crate::ui::status_bar::{self, StatusBar};
+use crate::ui::watermark::Watermark;
+use crate::ui::workspace_rename_dialog::WorkspaceRenameDialog;
+use crate::utils::brightness::BrightnessControl;
+use crate::utils::clipboard_history::{self, ClipboardHistory, SelectionData};
+use crate::utils::desktop_entries::{find_icon, DesktopEntries};
+use crate::utils::focus_history::FocusHistory;
 use crate::utils::scale::{closest_representable_scale, guess_monitor_scale};
-use crate::utils::spawning::CHILD_ENV;
+use crate::utils::spawning::{
+    count_crash, is_crash, restart_delays, spawn, spawn_and_watch, CHILD_ENV, RESTART_MAX_CRASHES,
+    RESTART_MIN_DELAY, XWAYLAND_SATELLITE_SOCKET,
+};
 use crate::utils::{
-    center, center_f64, expand_home, get_monotonic_time, ipc_transform_to_smithay, logical_output,
-    make_screenshot_path, output_matches_name, output_size, send_scale_transform, write_png_rgba8,
+    center, center_f64, expand_home, get_monotonic_time, input_device_transform, is_laptop_panel,
+    logical_output, make_screen_recording_path, make_screenshot_path, output_matches_name,
+    output_size, output_transform, send_scale_transform, with_toplevel_role, write_image_rgba8,
+    write_png_rgba8, ScreenshotWindowInfo,
+};
+use crate::window::{
+    InitialConfigureState, Mapped, ResolvedWindowRules, SpawnRules, Unmapped, WindowRef,
 };
-use crate::window::{InitialConfigureState, Mapped, ResolvedWindowRules, Unmapped, WindowRef};
 
 const CLEAR_COLOR_LOCKED: [f32; 4] = [0.3, 0.1, 0.1, 1.];
 
@@ -176,6 +215,10 @@ pub struct Niri {
     /// reloading the config from disk to determine if the output configuration should be reloaded
     /// (and transient changes dropped).
     pub config_file_output_config: niri_config::Outputs,
+    /// Name of the output profile matching the connected outputs, if any.
+    ///
+    /// Its outputs are applied on top of the output config from the config file.
+    pub active_output_profile: Option<String>,
 
     pub event_loop: LoopHandle<'static, State>,
     pub scheduler: Scheduler<()>,
@@ -199,6 +242,9 @@ pub struct Niri {
     // however it may have none (when there are no outputs connected) or multiple (when mirroring).
     pub layout: Layout<Mapped>,
 
+    /// Since when auto-closing named workspaces have been empty.
+    pub empty_named_workspaces: HashMap<WorkspaceId, Duration>,
+
     // This space does not actually contain any windows, but all outputs are mapped into it
     // according to their global position.
     pub global_space: Space<Window>,
@@ -209,6 +255,13 @@ pub struct Niri {
     // Windows which don't have a buffer attached yet.
     pub unmapped_windows: HashMap<WlSurface, Unmapped>,
 
+    /// Startup placeholders of spawned applications, by their activation token.
+    pub startup_placeholders: HashMap<String, StartupPlaceholderId>,
+    /// Extra window rules of applications spawned with `spawn-with-rules`, by activation token.
+    pub spawn_rules: HashMap<String, SpawnRules>,
+    /// Shared with the threads that look up desktop entries.
+    pub desktop_entries: Arc<Mutex<DesktopEntries>>,
+
     /// Layer surfaces which don't have a buffer attached yet.
     pub unmapped_layer_surfaces: HashSet<WlSurface>,
 
@@ -237,9 +290,15 @@ pub struct Niri {
     /// startup, libinput will immediately send a closed event.
     pub is_lid_closed: bool,
 
+    /// Rotation of the device reported by the accelerometer, for outputs with auto-rotation.
+    pub accelerometer_rotation: Option<Transform>,
+
     pub devices: HashSet<input::Device>,
     pub tablets: HashMap<input::Device, TabletData>,
     pub touch: HashSet<input::Device>,
+    /// Built-in keyboards, the only ones that libinput pairs with touchpads for
+    /// disable-while-typing.
+    pub internal_keyboards: HashSet<input::Device>,
 
     // Smithay state.
     pub compositor_state: CompositorState,
@@ -250,6 +309,7 @@ pub struct Niri {
     pub session_lock_state: SessionLockManagerState,
     pub foreign_toplevel_state: ForeignToplevelManagerState,
     pub screencopy_state: ScreencopyManagerState,
+    pub image_copy_capture_state: ImageCopyCaptureState,
     pub output_management_state: OutputManagementManagerState,
     pub viewporter_state: ViewporterState,
     pub xdg_foreign_state: XdgForeignState,
@@ -279,6 +339,7 @@ pub struct Niri {
     pub gamma_control_manager_state: GammaControlManagerState,
     pub activation_state: XdgActivationState,
     pub mutter_x11_interop_state: MutterX11InteropManagerState,
+    pub fixes_state: FixesState,
 
     // This will not work as is outside of tests, so it is gated with #[cfg(test)] for now. In
     // particular, shaders will need to learn about the single pixel buffer. Also, it must be
@@ -294,11 +355,21 @@ pub struct Niri {
     pub suppressed_keys: HashSet<Keycode>,
     /// Button codes of the mouse buttons to suppress.
     pub suppressed_buttons: HashSet<u32>,
-    pub bind_cooldown_timers: HashMap<Key, RegistrationToken>,
+    /// Current touch points along with the windows that they started on.
+    pub touch_points: HashMap<TouchSlot, (Point<f64, Logical>, Option<Window>)>,
+    /// Binds on cooldown, with their timers and the times when their cooldowns end.
+    pub bind_cooldown_timers: HashMap<Key, (RegistrationToken, Duration)>,
     pub bind_repeat_timer: Option<RegistrationToken>,
+    /// Timer to show the hotkey overlay while the Mod key is held alone.
+    pub hotkey_overlay_mod_hold_timer: Option<RegistrationToken>,
     pub keyboard_focus: KeyboardFocus,
     pub layer_shell_on_demand_focus: Option<LayerSurface>,
     pub previously_focused_window: Option<Window>,
+    pub focus_history: FocusHistory,
+    /// Recent clipboard selections, kept when the clipboard history is enabled.
+    pub clipboard_history: ClipboardHistory,
+    /// Whether clicking windows selects them for a batch action.
+    pub window_select_mode: bool,
     pub idle_inhibiting_surfaces: HashSet<WlSurface>,
     pub is_fdo_idle_inhibited: Arc<AtomicBool>,
     pub keyboard_shortcuts_inhibiting_surfaces: HashMap<WlSurface, KeyboardShortcutsInhibitor>,
@@ -307,6 +378,9 @@ pub struct Niri {
     pub cursor_texture_cache: CursorTextureCache,
     pub cursor_shape_manager_state: CursorShapeManagerState,
     pub dnd_icon: Option<DndIcon>,
+    /// Action of the ongoing client drag-and-drop, if any.
+    pub dnd_action: Option<DndAction>,
+    pub dnd_action_indicator: DndActionIndicator,
     /// Contents under pointer.
     ///
     /// Periodically updated: on motion and other events and in the loop callback. If you require
@@ -327,8 +401,12 @@ pub struct Niri {
     /// various tooltips from sticking around.
     pub pointer_hidden: bool,
     pub pointer_inactivity_timer: Option<RegistrationToken>,
+    /// Whether cursor warps to focus are suppressed for the action being run.
+    pub suppress_warp_mouse_to_focus: bool,
+    pub focus_follows_mouse_timer: Option<RegistrationToken>,
     pub tablet_cursor_location: Option<Point<f64, Logical>>,
     pub gesture_swipe_3f_cumulative: Option<(f64, f64)>,
+    pub dwt_tracker: DwtTracker,
     pub vertical_wheel_tracker: ScrollTracker,
     pub horizontal_wheel_tracker: ScrollTracker,
     pub mods_with_mouse_binds: HashSet<Modifiers>,
@@ -336,13 +414,22 @@ pub struct Niri {
     pub vertical_finger_scroll_tracker: ScrollTracker,
     pub horizontal_finger_scroll_tracker: ScrollTracker,
     pub mods_with_finger_scroll_binds: HashSet<Modifiers>,
+    pub mods_with_background_wheel_binds: HashSet<Modifiers>,
+    pub mods_with_background_finger_scroll_binds: HashSet<Modifiers>,
 
     pub lock_state: LockState,
 
     pub screenshot_ui: ScreenshotUi,
+    pub screenshot_feedback: ScreenshotFeedback,
     pub config_error_notification: ConfigErrorNotification,
     pub hotkey_overlay: HotkeyOverlay,
-    pub exit_confirm_dialog: Option<ExitConfirmDialog>,
+    pub exit_confirm_dialog: Option<ConfirmDialog>,
+    /// Dialog confirming to close a window matched by the confirm-close window rule.
+    pub close_confirm_dialog: Option<(Window, ConfirmDialog)>,
+    pub workspace_rename_dialog: WorkspaceRenameDialog,
+    pub clipboard_history_picker: ClipboardHistoryPicker,
+    pub status_bar: StatusBar,
+    pub watermark: Watermark,
 
     pub debug_draw_opaque_regions: bool,
     pub debug_draw_damage: bool,
@@ -351,9 +438,21 @@ pub struct Niri {
     pub dbus: Option<crate::dbus::DBusServers>,
     #[cfg(feature = "dbus")]
     pub inhibit_power_key_fd: Option<zbus::zvariant::OwnedFd>,
+    #[cfg(feature = "dbus")]
+    pub is_accelerometer_watched: bool,
 
     pub ipc_server: Option<IpcServer>,
     pub ipc_outputs_changed: bool,
+    pub ipc_layers_changed: bool,
+
+    pub brightness: BrightnessControl,
+
+    #[cfg(feature = "metrics")]
+    pub metrics: crate::metrics::Metrics,
+
+    /// Built-in lock screen, shown when the screen locker dies while the session is locked.
+    #[cfg(feature = "fallback-lock")]
+    pub fallback_lock: Option<crate::ui::fallback_lock::FallbackLock>,
 
     // Casts are dropped before PipeWire to prevent a double-free (yay).
     pub casts: Vec<Cast>,
@@ -364,8 +463,21 @@ pub struct Niri {
     // Screencast output for each mapped window.
     #[cfg(feature = "xdp-gnome-screencast")]
     pub mapped_cast_output: HashMap<Window, Output>,
+
+    #[cfg(feature = "xdp-gnome-screencast")]
+    pub screen_recording: Option<ScreenRecording>,
+    /// Portal area cast waiting for the user to select the area in the screenshot UI.
+    #[cfg(feature = "xdp-gnome-screencast")]
+    pub pending_area_cast: Option<PendingAreaCast>,
+    /// Last cursor bitmap sent to the metadata cursor mode casts, along with the cursor elements
+    /// that it was rendered from.
+    #[cfg(feature = "xdp-gnome-screencast")]
+    cast_cursor_bitmap: Option<(CastCursorKey, Rc<CursorBitmap>)>,
 }
 
+#[cfg(feature = "xdp-gnome-screencast")]
+type CastCursorKey = (Vec<(Id, CommitCounter)>, Size<i32, Physical>);
+
 #[derive(Debug)]
 pub struct DndIcon {
     pub surface: WlSurface,
@@ -410,6 +522,18 @@ pub struct OutputState {
     screen_transition: Option<ScreenTransition>,
     /// Damage tracker used for the debug damage visualization.
     pub debug_damage_tracker: OutputDamageTracker,
+    /// Color temperature from the config, last applied to the output gamma.
+    pub color_temperature: Option<u32>,
+    /// Output whose contents this output shows instead of its own.
+    ///
+    /// Mirroring outputs are not part of the layout or the global space.
+    pub mirror_source: Option<Output>,
+    /// Logical outputs that this output is split into, with their geometry in physical pixels.
+    ///
+    /// Split outputs are not part of the layout or the global space, only their parts are.
+    pub split_parts: Vec<(Output, Rectangle<i32, Physical>)>,
+    /// Textures that the mirror source or the split parts are rendered into, kept between frames.
+    pub output_textures: RefCell<HashMap<(Output, RenderTarget), GlesTexture>>,
 }
 
 #[derive(Debug, Default)]
@@ -425,6 +549,8 @@ pub enum RedrawState {
     WaitingForEstimatedVBlank(RegistrationToken),
     /// A redraw is queued on top of the above.
     WaitingForEstimatedVBlankAndQueued(RegistrationToken),
+    /// A redraw is needed, but held back by a timer to stay under the max render FPS.
+    WaitingForThrottle(RegistrationToken),
 }
 
 pub struct PopupGrabState {
@@ -486,6 +612,23 @@ pub enum CenterCoords {
     Both,
 }
 
+#[cfg(feature = "xdp-gnome-screencast")]
+pub struct PendingAreaCast {
+    session_id: usize,
+    cursor_mode: CursorMode,
+    /// Filled in with the selection, for the stream parameters.
+    area: mutter_screen_cast::CastArea,
+    signal_ctx: zbus::object_server::SignalEmitter<'static>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ScreenRecordTarget {
+    /// The screenshot UI selection if it's open, otherwise the active output.
+    Output,
+    /// The window with the given id, or the focused window.
+    Window { id: Option<u64> },
+}
+
 #[derive(Default)]
 pub struct WindowOffscreenId(pub RefCell<Option<Id>>);
 
@@ -498,9 +641,9 @@ impl RedrawState {
             }
 
             // A redraw is already queued.
-            value @ (RedrawState::Queued | RedrawState::WaitingForEstimatedVBlankAndQueued(_)) => {
-                value
-            }
+            value @ (RedrawState::Queued
+            | RedrawState::WaitingForEstimatedVBlankAndQueued(_)
+            | RedrawState::WaitingForThrottle(_)) => value,
 
             // We're waiting for VBlank, request a redraw afterwards.
             RedrawState::WaitingForVBlank { .. } => RedrawState::WaitingForVBlank {
@@ -629,6 +772,8 @@ impl State {
         self.niri.popups.cleanup();
         self.refresh_popup_grab();
         self.update_keyboard_focus();
+        // Later focus changes weren't caused by what happened in this iteration.
+        self.niri.focus_history.clear_reason();
 
         // Needs to be called after updating the keyboard focus.
         self.niri.refresh_layout();
@@ -640,9 +785,18 @@ impl State {
         self.refresh_pointer_contents();
         foreign_toplevel::refresh(self);
         self.niri.refresh_window_rules();
+        self.niri.refresh_workspace_auto_names();
+        self.refresh_empty_named_workspaces();
+        self.refresh_windows_waiting_for_workspace();
+        self.refresh_output_profile();
         self.refresh_ipc_outputs();
         self.ipc_refresh_layout();
         self.ipc_refresh_keyboard_layout_index();
+        self.ipc_refresh_layers();
+        self.ipc_refresh_ui();
+
+        #[cfg(feature = "fallback-lock")]
+        self.refresh_fallback_lock();
 
         #[cfg(feature = "xdp-gnome-screencast")]
         self.niri.refresh_mapped_cast_outputs();
@@ -766,7 +920,7 @@ impl State {
     }
 
     pub fn maybe_warp_cursor_to_focus(&mut self) -> bool {
-        if !self.niri.config.borrow().input.warp_mouse_to_focus {
+        if !self.should_warp_cursor_to_focus() {
             return false;
         }
 
@@ -774,13 +928,45 @@ impl State {
     }
 
     pub fn maybe_warp_cursor_to_focus_centered(&mut self) -> bool {
-        if !self.niri.config.borrow().input.warp_mouse_to_focus {
+        if !self.should_warp_cursor_to_focus() {
             return false;
         }
 
         self.move_cursor_to_focused_tile(CenterCoords::Both)
     }
 
+    fn should_warp_cursor_to_focus(&self) -> bool {
+        if self.niri.suppress_warp_mouse_to_focus {
+            return false;
+        }
+
+        let warp = self.niri.config.borrow().input.warp_mouse_to_focus;
+
+        // The window rule overrides the global setting both ways.
+        let rule = self
+            .niri
+            .layout
+            .focus()
+            .and_then(|mapped| mapped.rules().warp_mouse_to_focus);
+        let warp = match (rule, warp) {
+            (Some(false), _) | (None, None) => return false,
+            (_, Some(warp)) => warp,
+            (Some(true), None) => WarpMouseToFocus {
+                only_across_outputs: false,
+            },
+        };
+
+        if warp.only_across_outputs {
+            let active_output = self.niri.layout.active_output();
+            if active_output.is_some() && self.niri.output_under_cursor().as_ref() == active_output
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+
     pub fn refresh_pointer_contents(&mut self) {
         let _span = tracy_client::span!("Niri::refresh_pointer_contents");
 
@@ -974,12 +1160,45 @@ impl State {
 
         let keyboard = self.niri.seat.get_keyboard().unwrap();
         if self.niri.keyboard_focus != focus {
+            // Put the layout focus back exactly where it was before a layer-shell surface took it,
+            // even if it moved in the meantime, e.g. due to a workspace switch while an app launcher
+            // was open. Only do this when the layer-shell surface went away by itself: if the focus
+            // went to the layout for another reason, like clicking on a window, keep it there.
+            match (&self.niri.keyboard_focus, &focus) {
+                (KeyboardFocus::Layout { .. }, KeyboardFocus::LayerShell { .. }) => {
+                    self.niri.layout.save_focus();
+                }
+                (KeyboardFocus::LayerShell { surface }, KeyboardFocus::Layout { .. }) => {
+                    let closed =
+                        !surface.alive() || self.niri.unmapped_layer_surfaces.contains(surface);
+                    if closed && self.niri.layout.restore_focus() {
+                        // FIXME: granular.
+                        self.niri.queue_redraw_all();
+
+                        let reason = niri_ipc::FocusReason::FocusRestored;
+                        self.niri.focus_history.set_reason(reason);
+
+                        // The restored window may be on a different output or workspace, with
+                        // different layer-shell surfaces, so compute the focus from scratch.
+                        self.update_keyboard_focus();
+                        return;
+                    }
+
+                    self.niri.layout.clear_saved_focus();
+                }
+                (_, KeyboardFocus::Layout { .. }) => self.niri.layout.clear_saved_focus(),
+                _ => (),
+            }
+
             trace!(
                 "keyboard focus changed from {:?} to {:?}",
                 self.niri.keyboard_focus,
                 focus
             );
 
+            let target = self.niri.focus_target(&focus);
+            self.niri.focus_history.record(target);
+
             // Tell the windows their new focus state for window rule purposes.
             let mut previous_focus = None;
             if let KeyboardFocus::Layout {
@@ -1123,7 +1342,11 @@ impl State {
             }
         };
 
-        self.niri.config_error_notification.hide();
+        if add_emergency_binds_if_needed(&mut config.binds) {
+            self.niri.config_error_notification.show_no_binds();
+        } else {
+            self.niri.config_error_notification.hide();
+        }
 
         // Find & orphan removed named workspaces.
         let mut removed_workspaces: Vec<String> = vec![];
@@ -1162,6 +1385,7 @@ impl State {
         let mut layer_rules_changed = false;
         let mut shaders_changed = false;
         let mut cursor_inactivity_timeout_changed = false;
+        let mut cast_max_fps_changed = false;
         let mut old_config = self.niri.config.borrow_mut();
 
         // Reload the cursor.
@@ -1206,6 +1430,12 @@ impl State {
             preserved_output_config = Some(mem::take(&mut old_config.outputs));
         }
 
+        if config.profiles != old_config.profiles {
+            output_config_changed = true;
+            // The preserved output config contains the outputs of the old profile.
+            preserved_output_config = None;
+        }
+
         if config.binds != old_config.binds {
             self.niri.hotkey_overlay.on_hotkey_config_updated();
             self.niri.mods_with_mouse_binds =
@@ -1216,6 +1446,22 @@ impl State {
                 mods_with_finger_scroll_binds(self.backend.mod_key(), &config.binds);
         }
 
+        if config.status_bar.on != old_config.status_bar.on {
+            self.niri.status_bar.set_on(config.status_bar.on);
+        }
+
+        if config.watermark.on != old_config.watermark.on {
+            self.niri.watermark.set_on(config.watermark.on);
+        }
+
+        if config.background_binds != old_config.background_binds {
+            let binds = &config.background_binds.0;
+            self.niri.mods_with_background_wheel_binds =
+                mods_with_wheel_binds(self.backend.mod_key(), binds);
+            self.niri.mods_with_background_finger_scroll_binds =
+                mods_with_finger_scroll_binds(self.backend.mod_key(), binds);
+        }
+
         if config.window_rules != old_config.window_rules {
             window_rules_changed = true;
         }
@@ -1258,6 +1504,24 @@ impl State {
             cursor_inactivity_timeout_changed = true;
         }
 
+        if config.screencast.max_fps != old_config.screencast.max_fps {
+            cast_max_fps_changed = true;
+        }
+
+        if config.clipboard.history_size != old_config.clipboard.history_size {
+            let max_entries = config.clipboard.history_size.unwrap_or(0);
+            self.niri
+                .clipboard_history
+                .truncate(usize::from(max_entries));
+        }
+
+        #[cfg(feature = "metrics")]
+        if config.metrics != old_config.metrics {
+            self.niri
+                .metrics
+                .set_config(&self.niri.event_loop, &config.metrics);
+        }
+
         if config.debug.keep_laptop_panel_on_when_lid_is_closed
             != old_config.debug.keep_laptop_panel_on_when_lid_is_closed
         {
@@ -1273,6 +1537,10 @@ impl State {
         // Release the borrow.
         drop(old_config);
 
+        // The bar height may have changed along with the config.
+        let height = self.niri.status_bar.reserved_height();
+        self.niri.layout.set_status_bar_height(height);
+
         // Now with a &mut self we can reload the xkb config.
         if let Some(mut xkb) = reload_xkb {
             let mut set_xkb_config = true;
@@ -1306,7 +1574,13 @@ impl State {
         }
 
         if output_config_changed {
-            self.reload_output_config();
+            #[cfg(feature = "dbus")]
+            self.niri.watch_accelerometer_if_needed();
+
+            // Re-apply the matching profile on top of the new output config. This reconfigures
+            // the outputs.
+            let profile = self.matching_output_profile();
+            self.apply_output_profile(profile);
         }
 
         if window_rules_changed {
@@ -1325,6 +1599,16 @@ impl State {
             self.niri.reset_pointer_inactivity_timer();
         }
 
+        if cast_max_fps_changed {
+            #[cfg(feature = "xdp-gnome-screencast")]
+            {
+                let max_fps = self.niri.config.borrow().screencast.max_fps;
+                for cast in &mut self.niri.casts {
+                    cast.set_max_fps(max_fps);
+                }
+            }
+        }
+
         // Can't really update xdg-decoration settings since we have to hide the globals for CSD
         // due to the SDL2 bug... I don't imagine clients are prepared for the xdg-decoration
         // global suddenly appearing? Either way, right now it's live-reloaded in a sense that new
@@ -1334,8 +1618,11 @@ impl State {
     }
 
     pub fn reload_output_config(&mut self) {
+        self.niri.refresh_output_mirrors();
+
         let mut resized_outputs = vec![];
         let mut recolored_outputs = vec![];
+        let mut retempered_outputs = vec![];
 
         for output in self.niri.global_space.outputs() {
             let name = output.user_data().get::<OutputName>().unwrap();
@@ -1352,9 +1639,7 @@ impl State {
                 });
             let scale = closest_representable_scale(scale.clamp(0.1, 10.));
 
-            let mut transform = config
-                .map(|c| ipc_transform_to_smithay(c.transform))
-                .unwrap_or(Transform::Normal);
+            let mut transform = output_transform(config, self.niri.accelerometer_rotation);
             // FIXME: fix winit damage on other transforms.
             if name.connector == "winit" {
                 transform = Transform::Flipped180;
@@ -1380,7 +1665,17 @@ impl State {
             background_color[3] = 1.;
             let background_color = Color32F::from(background_color);
 
+            let max_render_fps = config.and_then(|c| c.max_render_fps);
+            let color_temperature = config.and_then(|c| c.color_temperature);
+
             if let Some(state) = self.niri.output_state.get_mut(output) {
+                state.frame_clock.set_max_render_fps(max_render_fps);
+
+                if state.color_temperature != color_temperature {
+                    state.color_temperature = color_temperature;
+                    retempered_outputs.push(output.clone());
+                }
+
                 if state.background_buffer.color() != background_color {
                     state.background_buffer.set_color(background_color);
                     recolored_outputs.push(output.clone());
@@ -1388,6 +1683,15 @@ impl State {
             }
         }
 
+        // Ongoing touches were mapped with the old transform, and the next events would make them
+        // jump across the output.
+        if !resized_outputs.is_empty() && !self.niri.touch_points.is_empty() {
+            self.niri.touch_points.clear();
+            if let Some(touch) = self.niri.seat.get_touch() {
+                touch.cancel(self);
+            }
+        }
+
         for output in resized_outputs {
             self.niri.output_resized(&output);
         }
@@ -1396,6 +1700,10 @@ impl State {
             self.niri.queue_redraw(&output);
         }
 
+        for output in retempered_outputs {
+            self.apply_color_temperature(&output);
+        }
+
         self.backend.on_output_config_changed(&mut self.niri);
 
         self.niri.reposition_outputs(None);
@@ -1408,6 +1716,114 @@ impl State {
         self.niri.output_management_state.on_config_changed(config);
     }
 
+    /// Rotates the outputs with auto-rotation to the new device orientation.
+    pub fn on_accelerometer_rotation(&mut self, rotation: Transform) {
+        if self.niri.accelerometer_rotation == Some(rotation) {
+            return;
+        }
+        debug!("device rotated to {rotation:?}");
+
+        let is_first_reading = self.niri.accelerometer_rotation.is_none();
+        self.niri.accelerometer_rotation = Some(rotation);
+
+        let auto_rotate = (self.niri.config.borrow().outputs.0.iter()).any(|c| c.auto_rotate);
+        if !auto_rotate {
+            return;
+        }
+
+        // Cross-fade to hide the windows resizing to the new orientation.
+        if !is_first_reading {
+            self.backend.with_primary_renderer(|renderer| {
+                self.niri.do_screen_transition(renderer, None);
+            });
+        }
+
+        self.reload_output_config();
+    }
+
+    /// Applies the configured color temperature to the output gamma.
+    ///
+    /// Gamma control clients take priority; when they let go, the gamma control handler restores
+    /// the configured color temperature.
+    fn apply_color_temperature(&mut self, output: &Output) {
+        if self
+            .niri
+            .gamma_control_manager_state
+            .has_gamma_control(output)
+        {
+            return;
+        }
+
+        let Some(tty) = self.backend.tty_checked() else {
+            return;
+        };
+
+        let ramp = tty.color_temperature_ramp(output);
+        if let Err(err) = tty.set_gamma(output, ramp) {
+            warn!("error setting color temperature: {err:?}");
+        }
+    }
+
+    pub fn set_output_color_temperature(&mut self, output: Option<&str>, kelvin: u32) {
+        let name = match output {
+            Some(name) => name.to_owned(),
+            None => {
+                let Some(output) = self.niri.layout.active_output() else {
+                    return;
+                };
+                output.name()
+            }
+        };
+
+        self.modify_output_config(&name, move |config| {
+            config.color_temperature = Some(kelvin);
+        });
+
+        self.reload_output_config();
+    }
+
+    pub fn set_output_brightness(
+        &mut self,
+        output: Option<&str>,
+        change: niri_ipc::BrightnessChange,
+    ) {
+        let name = match output {
+            Some(name) => match self.niri.output_by_name_match(name) {
+                Some(output) => output.name(),
+                None => name.to_owned(),
+            },
+            None => {
+                // Brightness keys are meant for the built-in panel, so prefer it over the focused
+                // output.
+                let panel = self
+                    .niri
+                    .global_space
+                    .outputs()
+                    .find(|output| is_laptop_panel(&output.name()));
+                let Some(output) = panel.or_else(|| self.niri.layout.active_output()) else {
+                    return;
+                };
+                output.name()
+            }
+        };
+
+        self.niri.brightness.change(&name, change);
+    }
+
+    pub fn on_brightness_update(&mut self, connector: String, percent: Option<f64>) {
+        if !self.niri.brightness.update(connector.clone(), percent) {
+            return;
+        }
+
+        let ipc_outputs = self.backend.ipc_outputs();
+        for output in ipc_outputs.lock().unwrap().values_mut() {
+            if output.name == connector {
+                output.brightness = percent;
+            }
+        }
+        self.niri.ipc_outputs_changed = true;
+    }
+
     pub fn modify_output_config<F>(&mut self, name: &str, fun: F)
     where
         F: FnOnce(&mut niri_config::Output),
@@ -1416,7 +1832,13 @@ impl State {
         // user. Since if we add a new section and some existing section also matches the
         // output, then our new section won't do anything.
         let temp;
-        let match_name = if let Some(output) = self.niri.output_by_name_match(name) {
+        // Mirroring outputs aren't in the global space, so look through all of them.
+        let output = self
+            .niri
+            .output_state
+            .keys()
+            .find(|output| output_matches_name(output, name));
+        let match_name = if let Some(output) = output {
             output.user_data().get::<OutputName>().unwrap()
         } else if let Some(output_name) = self
             .backend
@@ -1451,7 +1873,53 @@ impl State {
         fun(config);
     }
 
+    /// Creates a virtual output, moves the given workspaces onto it, and returns its name.
+    ///
+    /// Returns `None` if the backend doesn't support virtual outputs.
+    pub fn create_virtual_output(
+        &mut self,
+        width: u16,
+        height: u16,
+        scale: Option<f64>,
+        workspaces: Vec<niri_ipc::WorkspaceReferenceArg>,
+    ) -> Option<String> {
+        let name = self
+            .backend
+            .create_virtual_output(&mut self.niri, width, height)?;
+
+        if let Some(scale) = scale {
+            // Like the output IPC actions, this changes the configuration temporarily.
+            self.modify_output_config(&name, |config| config.scale = Some(FloatOrInt(scale)));
+            self.reload_output_config();
+        }
+
+        // Resolve all references first, since moving workspaces changes their indices.
+        let ids: Vec<_> = workspaces
+            .into_iter()
+            .filter_map(|reference| {
+                let reference = WorkspaceReference::from(reference);
+                let ws = self.niri.layout.find_workspace_by_ref(reference);
+                ws.map(|ws| ws.id())
+            })
+            .collect();
+        if !ids.is_empty() {
+            let output = self.niri.output_by_name_match(&name).cloned();
+            if let Some(output) = output {
+                self.niri.layout.move_workspaces_to_output(&ids, &output);
+                self.niri.queue_redraw_all();
+            }
+        }
+
+        Some(name)
+    }
+
     pub fn apply_transient_output_config(&mut self, name: &str, action: niri_ipc::OutputAction) {
+        // Brightness lives in the hardware rather than in the output config.
+        if let niri_ipc::OutputAction::Brightness { percent } = action {
+            self.set_output_brightness(Some(name), percent);
+            return;
+        }
+
         self.modify_output_config(name, move |config| match action {
             niri_ipc::OutputAction::Off => config.off = true,
             niri_ipc::OutputAction::On => config.off = false,
@@ -1486,11 +1954,90 @@ impl State {
                     None
                 }
             }
+            niri_ipc::OutputAction::Mirror { mirror } => {
+                config.mirror = match mirror {
+                    niri_ipc::MirrorToSet::Off => None,
+                    niri_ipc::MirrorToSet::Output(name) => Some(name),
+                }
+            }
+            // Handled above.
+            niri_ipc::OutputAction::Brightness { .. } => (),
         });
 
         self.reload_output_config();
     }
 
+    /// Applies the output profile matching the connected outputs, if it changed.
+    pub fn refresh_output_profile(&mut self) {
+        // The connected outputs can only change together with the IPC outputs.
+        if !self.niri.ipc_outputs_changed {
+            return;
+        }
+
+        let profile = self.matching_output_profile();
+        if profile.as_ref().map(|p| &p.name) == self.niri.active_output_profile.as_ref() {
+            return;
+        }
+
+        self.apply_output_profile(profile);
+    }
+
+    fn matching_output_profile(&self) -> Option<OutputProfile> {
+        let ipc_outputs = self.backend.ipc_outputs();
+        let connected: Vec<_> = ipc_outputs
+            .lock()
+            .unwrap()
+            .values()
+            .map(OutputName::from_ipc_output)
+            .collect();
+
+        let config = self.niri.config.borrow();
+        config.profiles.find_matching(&connected).cloned()
+    }
+
+    /// Applies the profile on top of the config file outputs and reconfigures the outputs.
+    fn apply_output_profile(&mut self, profile: Option<OutputProfile>) {
+        let name = profile.as_ref().map(|p| p.name.clone());
+        if name != self.niri.active_output_profile {
+            if let Some(name) = &name {
+                info!("applying output profile {name}");
+            } else {
+                info!("no output profile matches the connected outputs");
+            }
+            self.niri.active_output_profile = name;
+        }
+
+        let outputs = self
+            .niri
+            .config_file_output_config
+            .with_profile(profile.as_ref());
+        self.niri.config.borrow_mut().outputs = outputs;
+        self.reload_output_config();
+
+        let Some(profile) = profile else {
+            return;
+        };
+
+        for ws in profile.workspaces {
+            let reference = WorkspaceReference::Name(ws.name.clone());
+            let Some((old_output, old_idx)) = self.niri.find_output_and_workspace_index(reference)
+            else {
+                warn!("output profile workspace {} does not exist", ws.name);
+                continue;
+            };
+            let Some(new_output) = self.niri.output_by_name_match(&ws.output).cloned() else {
+                warn!("output profile output {} is not enabled", ws.output);
+                continue;
+            };
+
+            self.niri
+                .layout
+                .move_workspace_to_output_by_id(old_idx, old_output, new_output);
+        }
+
+        self.niri.queue_redraw_all();
+    }
+
     pub fn refresh_ipc_outputs(&mut self) {
         if !self.niri.ipc_outputs_changed {
             return;
@@ -1513,71 +2060,334 @@ impl State {
         self.niri.on_ipc_outputs_changed();
 
         let new_config = self.backend.ipc_outputs().lock().unwrap().clone();
+        self.ipc_refresh_outputs(&new_config);
         self.niri.output_management_state.notify_changes(new_config);
     }
 
-    pub fn open_screenshot_ui(&mut self) {
-        if self.niri.is_locked() || self.niri.screenshot_ui.is_open() {
-            return;
-        }
+    /// Closes named workspaces that stayed empty for longer than their configured duration.
+    pub fn refresh_empty_named_workspaces(&mut self) {
+        let _span = tracy_client::span!("State::refresh_empty_named_workspaces");
 
-        let default_output = self
-            .niri
-            .output_under_cursor()
-            .or_else(|| self.niri.layout.active_output().cloned());
-        let Some(default_output) = default_output else {
-            return;
-        };
+        let config = self.niri.config.borrow();
+        let now = get_monotonic_time();
 
-        self.niri.update_render_elements(None);
+        let mut seen = HashSet::new();
+        let mut new_timeouts = Vec::new();
+        let mut to_close = Vec::new();
+        for (mon, ws_idx, ws) in self.niri.layout.workspaces() {
+            let Some(name) = ws.name() else {
+                continue;
+            };
+            if ws.has_windows() {
+                continue;
+            }
 
-        let Some(screenshots) = self
-            .backend
-            .with_primary_renderer(|renderer| self.niri.capture_screenshots(renderer).collect())
-        else {
-            return;
-        };
+            let timeout = config
+                .workspaces
+                .iter()
+                .find(|ws_config| ws_config.name.0.eq_ignore_ascii_case(name))
+                .and_then(|ws_config| ws_config.auto_close_if_empty_after);
+            let Some(timeout) = timeout else {
+                continue;
+            };
+            let timeout = timeout.0;
 
-        // Now that we captured the screenshots, clear grabs like drag-and-drop, etc.
-        self.niri.seat.get_pointer().unwrap().unset_grab(
-            self,
-            SERIAL_COUNTER.next_serial(),
-            get_monotonic_time().as_millis() as u32,
-        );
+            let id = ws.id();
+            seen.insert(id);
+            let since = *self
+                .niri
+                .empty_named_workspaces
+                .entry(id)
+                .or_insert_with(|| {
+                    new_timeouts.push(timeout);
+                    now
+                });
 
-        self.backend.with_primary_renderer(|renderer| {
+            // Don't close the workspace from under the user; wait until they switch away.
+            let is_active = mon.is_some_and(|mon| mon.active_workspace_idx() == ws_idx);
+            if !is_active && since + timeout <= now {
+                to_close.push((id, name.clone()));
+            }
+        }
+        self.niri
+            .empty_named_workspaces
+            .retain(|id, _| seen.contains(id));
+        drop(config);
+
+        // Wake up to check the workspaces again once they time out.
+        for timeout in new_timeouts {
+            let timer = Timer::from_duration(timeout);
             self.niri
-                .screenshot_ui
-                .open(renderer, screenshots, default_output)
-        });
+                .event_loop
+                .insert_source(timer, |_, _, _| TimeoutAction::Drop)
+                .unwrap();
+        }
 
-        self.niri
-            .cursor_manager
-            .set_cursor_image(CursorImageStatus::Named(CursorIcon::Crosshair));
+        if to_close.is_empty() {
+            return;
+        }
+
+        for (id, name) in to_close {
+            info!("closing workspace {name:?} after it stayed empty");
+            self.niri.empty_named_workspaces.remove(&id);
+            self.niri.layout.unname_workspace_by_id(id);
+            self.ipc_workspace_auto_closed(id, name);
+        }
         self.niri.queue_redraw_all();
     }
 
-    #[cfg(feature = "xdp-gnome-screencast")]
+    /// Shows the fallback lock if the screen locker died while the session is locked.
+    #[cfg(feature = "fallback-lock")]
+    fn refresh_fallback_lock(&mut self) {
+        let LockState::Locked(lock) = &self.niri.lock_state else {
+            return;
+        };
+        if lock.is_alive() || self.niri.fallback_lock.is_some() {
+            return;
+        }
+
+        warn!("screen locker died while the session is locked, showing the fallback lock");
+        self.niri.fallback_lock = Some(crate::ui::fallback_lock::FallbackLock::new());
+        self.niri.queue_redraw_all();
+    }
+
+    /// Checks the password typed into the fallback lock, and unlocks if it's right.
+    #[cfg(feature = "fallback-lock")]
+    pub fn check_fallback_lock_password(&mut self, password: zeroize::Zeroizing<String>) {
+        // The sender wakes up the event loop when dropped, which removes the source.
+        let (tx, rx) = calloop::channel::channel();
+        self.niri
+            .event_loop
+            .insert_source(rx, move |event, _, state| {
+                let calloop::channel::Event::Msg(is_correct) = event else {
+                    return;
+                };
+
+                // A new screen locker may have taken over in the meantime.
+                let Some(fallback_lock) = &mut state.niri.fallback_lock else {
+                    return;
+                };
+
+                if is_correct {
+                    info!("unlocking from the fallback lock");
+                    state.niri.unlock();
+                } else {
+                    fallback_lock.on_failed();
+                    state.niri.queue_redraw_all();
+                }
+            })
+            .unwrap();
+
+        // PAM can take a while, especially after a wrong password.
+        let res = thread::Builder::new()
+            .name("Fallback Lock Authentication".to_owned())
+            .spawn(move || {
+                let is_correct = match crate::utils::pam::authenticate(password) {
+                    Ok(()) => true,
+                    Err(err) => {
+                        warn!("fallback lock: {err:?}");
+                        false
+                    }
+                };
+                let _ = tx.send(is_correct);
+            });
+        if let Err(err) = res {
+            warn!("error spawning a thread to check the password: {err:?}");
+            if let Some(fallback_lock) = &mut self.niri.fallback_lock {
+                fallback_lock.on_failed();
+            }
+        }
+    }
+
+    /// Runs a `spawn-at-startup` command once its delay has passed.
+    pub fn spawn_at_startup(&mut self, config: SpawnAtStartup) {
+        let delay = Duration::from_millis(u64::from(config.delay_ms.unwrap_or(0)));
+        if delay.is_zero() && config.only_on_output.is_none() && !config.restart_on_crash {
+            spawn(config.command, None);
+            return;
+        }
+
+        // Go through the event loop even without a delay so that the outputs get connected first.
+        self.niri
+            .event_loop
+            .insert_source(Timer::from_duration(delay), move |_, _, state| {
+                state.run_startup_command(config.clone(), RESTART_MIN_DELAY, 0);
+                TimeoutAction::Drop
+            })
+            .unwrap();
+    }
+
+    /// Runs a startup command, restarting it after `restart_delay` if it crashes.
+    ///
+    /// `crashes` is the number of times the command crashed in a row so far.
+    fn run_startup_command(
+        &mut self,
+        config: SpawnAtStartup,
+        restart_delay: Duration,
+        crashes: u32,
+    ) {
+        if let Some(name) = &config.only_on_output {
+            let mut outputs = self.niri.global_space.outputs();
+            if !outputs.any(|output| output_matches_name(output, name)) {
+                debug!(
+                    "not spawning {:?}: output {name} is not connected",
+                    config.command
+                );
+                return;
+            }
+        }
+
+        if !config.restart_on_crash {
+            spawn(config.command, None);
+            return;
+        }
+
+        // Unlike the sync channel, this channel wakes up the event loop when the sender is
+        // dropped, so the source is removed once the watcher thread is done with the child.
+        let (tx, rx) = calloop::channel::channel();
+        let started_at = Instant::now();
+        let command = config.command.clone();
+        self.niri
+            .event_loop
+            .insert_source(rx, move |event, _, state| {
+                let calloop::channel::Event::Msg(status) = event else {
+                    return;
+                };
+                if !is_crash(status) {
+                    debug!(
+                        "{:?} exited with {status:?}, not restarting",
+                        config.command
+                    );
+                    return;
+                }
+
+                let uptime = started_at.elapsed();
+                let crashes = count_crash(crashes, uptime);
+                if crashes > RESTART_MAX_CRASHES {
+                    warn!(
+                        "{:?} crashed {RESTART_MAX_CRASHES} times in a row, not restarting",
+                        config.command
+                    );
+                    return;
+                }
+
+                let (delay, next_delay) = restart_delays(restart_delay, uptime);
+                warn!("{:?} crashed, restarting in {delay:?}", config.command);
+
+                let config = config.clone();
+                state
+                    .niri
+                    .event_loop
+                    .insert_source(Timer::from_duration(delay), move |_, _, state| {
+                        state.run_startup_command(config.clone(), next_delay, crashes);
+                        TimeoutAction::Drop
+                    })
+                    .unwrap();
+            })
+            .unwrap();
+
+        spawn_and_watch(command, move |status| {
+            let _ = tx.send(status);
+        });
+    }
+
+    pub fn open_screenshot_ui(&mut self) {
+        if self.niri.is_locked() || self.niri.screenshot_ui.is_open() {
+            return;
+        }
+
+        let default_output = self
+            .niri
+            .output_under_cursor()
+            .or_else(|| self.niri.layout.active_output().cloned());
+        let Some(default_output) = default_output else {
+            return;
+        };
+
+        self.niri.update_render_elements(None);
+
+        let Some(screenshots) = self
+            .backend
+            .with_primary_renderer(|renderer| self.niri.capture_screenshots(renderer).collect())
+        else {
+            return;
+        };
+
+        // Now that we captured the screenshots, clear grabs like drag-and-drop, etc.
+        self.niri.seat.get_pointer().unwrap().unset_grab(
+            self,
+            SERIAL_COUNTER.next_serial(),
+            get_monotonic_time().as_millis() as u32,
+        );
+
+        self.backend.with_primary_renderer(|renderer| {
+            self.niri
+                .screenshot_ui
+                .open(renderer, screenshots, default_output)
+        });
+
+        self.niri
+            .cursor_manager
+            .set_cursor_image(CursorImageStatus::Named(CursorIcon::Crosshair));
+        self.niri.queue_redraw_all();
+    }
+
+    #[cfg(feature = "xdp-gnome-screencast")]
+    fn redraw_cast_target(&mut self, target: CastTarget) {
+        match target {
+            CastTarget::Output(weak) => {
+                if let Some(output) = weak.upgrade() {
+                    self.niri.queue_redraw(&output);
+                }
+            }
+            CastTarget::Window { id } => {
+                self.backend.with_primary_renderer(|renderer| {
+                    // FIXME: target presentation time at the time of window commit?
+                    self.niri
+                        .render_window_for_screen_cast(renderer, id, get_monotonic_time());
+                });
+            }
+        }
+    }
+
+    #[cfg(feature = "xdp-gnome-screencast")]
     pub fn on_pw_msg(&mut self, msg: PwToNiri) {
         match msg {
             PwToNiri::StopCast { session_id } => self.niri.stop_cast(session_id),
-            PwToNiri::Redraw(target) => match target {
-                CastTarget::Output(weak) => {
-                    if let Some(output) = weak.upgrade() {
-                        self.niri.queue_redraw(&output);
-                    }
+            PwToNiri::Redraw { session_id } => {
+                let casts = &self.niri.casts;
+                let Some(cast) = casts.iter().find(|cast| cast.session_id == session_id) else {
+                    return;
+                };
+
+                self.redraw_cast_target(cast.target.clone());
+            }
+            PwToNiri::RecordingStreamAdded {
+                session_id,
+                node_id,
+            } => {
+                let Some(recording) = &mut self.niri.screen_recording else {
+                    return;
+                };
+                if recording.session_id != session_id {
+                    return;
                 }
-                CastTarget::Window { id } => {
-                    self.backend.with_primary_renderer(|renderer| {
-                        // FIXME: target presentation time at the time of window commit?
-                        self.niri
-                            .render_window_for_screen_cast(renderer, id, get_monotonic_time());
-                    });
+
+                if let Err(err) = recording.start_encoder(node_id, self.niri.pw_to_niri.clone()) {
+                    warn!("error starting screen recording encoder: {err:?}");
+                    self.niri.stop_cast(session_id);
                 }
-            },
+            }
             PwToNiri::FatalError => {
                 warn!("stopping PipeWire due to fatal error");
                 if let Some(pw) = self.niri.pipewire.take() {
+                    // The casts can't outlive PipeWire, so don't wait for the encoder to finish.
+                    if let Some(mut recording) = self.niri.screen_recording.take() {
+                        recording.stop_encoder();
+                        warn!("screen recording to {:?} was interrupted", recording.path);
+                        self.niri.queue_redraw_all();
+                    }
+
                     let ids: Vec<_> = self.niri.casts.iter().map(|cast| cast.session_id).collect();
                     for id in ids {
                         self.niri.stop_cast(id);
@@ -1589,9 +2399,68 @@ impl State {
     }
 
     #[cfg(feature = "xdp-gnome-screencast")]
-    pub fn on_screen_cast_msg(&mut self, msg: ScreenCastToNiri) {
+    #[allow(clippy::too_many_arguments)]
+    fn start_cast(
+        &mut self,
+        session_id: usize,
+        target: CastTarget,
+        size: Size<i32, Physical>,
+        refresh: u32,
+        alpha: bool,
+        cursor_mode: CursorMode,
+        sink: CastSink,
+    ) -> anyhow::Result<&mut Cast> {
         use smithay::reexports::gbm::Modifier;
 
+        let _span = tracy_client::span!("State::start_cast");
+
+        let Some(gbm) = self.backend.gbm_device() else {
+            bail!("no GBM device available");
+        };
+
+        let pw = if let Some(pw) = &self.niri.pipewire {
+            pw
+        } else {
+            let pipewire = PipeWire::new(&self.niri.event_loop, self.niri.pw_to_niri.clone())
+                .context("PipeWire failed to initialize")?;
+            self.niri.pipewire.insert(pipewire)
+        };
+
+        let mut render_formats = self
+            .backend
+            .with_primary_renderer(|renderer| {
+                renderer.egl_context().dmabuf_render_formats().clone()
+            })
+            .unwrap_or_default();
+
+        {
+            let config = self.niri.config.borrow();
+            if config.debug.force_pipewire_invalid_modifier {
+                render_formats = render_formats
+                    .into_iter()
+                    .filter(|f| f.modifier == Modifier::Invalid)
+                    .collect();
+            }
+        }
+
+        let mut cast = pw.start_cast(
+            gbm,
+            render_formats,
+            session_id,
+            target,
+            size,
+            refresh,
+            alpha,
+            cursor_mode,
+            sink,
+        )?;
+        cast.set_max_fps(self.niri.config.borrow().screencast.max_fps);
+        self.niri.casts.push(cast);
+        Ok(self.niri.casts.last_mut().unwrap())
+    }
+
+    #[cfg(feature = "xdp-gnome-screencast")]
+    pub fn on_screen_cast_msg(&mut self, msg: ScreenCastToNiri) {
         use crate::dbus::mutter_screen_cast::StreamTargetId;
 
         match msg {
@@ -1603,30 +2472,14 @@ impl State {
             } => {
                 let _span = tracy_client::span!("StartCast");
 
-                debug!(session_id, "StartCast");
-
-                let Some(gbm) = self.backend.gbm_device() else {
-                    warn!("error starting screencast: no GBM device available");
-                    self.niri.stop_cast(session_id);
-                    return;
-                };
+                debug!(session_id, ?cursor_mode, "StartCast");
 
-                let pw = if let Some(pw) = &self.niri.pipewire {
-                    pw
-                } else {
-                    match PipeWire::new(&self.niri.event_loop, self.niri.pw_to_niri.clone()) {
-                        Ok(pipewire) => self.niri.pipewire.insert(pipewire),
-                        Err(err) => {
-                            warn!(
-                                "error starting screencast: PipeWire failed to initialize: {err:?}"
-                            );
-                            self.niri.stop_cast(session_id);
-                            return;
-                        }
-                    }
-                };
+                let cursor_mode = cursor_mode.unwrap_or_else(|| {
+                    let config = self.niri.config.borrow();
+                    CursorMode::from(config.screencast.default_cursor_mode)
+                });
 
-                let (target, size, refresh, alpha) = match target {
+                let (target, size, refresh, alpha, crop) = match target {
                     StreamTargetId::Output { name } => {
                         let global_space = &self.niri.global_space;
                         let output = global_space.outputs().find(|out| out.name() == name);
@@ -1640,72 +2493,48 @@ impl State {
                         let transform = output.current_transform();
                         let size = transform.transform_size(mode.size);
                         let refresh = mode.refresh as u32;
-                        (CastTarget::Output(output.downgrade()), size, refresh, false)
+                        let target = CastTarget::Output(output.downgrade());
+                        (target, size, refresh, false, None)
                     }
                     StreamTargetId::Window { id } => {
-                        let mut window = None;
-                        self.niri.layout.with_windows(|mapped, _, _| {
-                            if mapped.id().get() != id {
-                                return;
-                            }
-
-                            window = Some(mapped.window.clone());
-                        });
-
-                        let Some(window) = window else {
+                        let Some((size, refresh)) = self.niri.window_cast_size_and_refresh(id)
+                        else {
                             warn!("error starting screencast: requested window is missing");
                             self.niri.stop_cast(session_id);
                             return;
                         };
 
-                        // Use the cached output since it will be present even if the output was
-                        // currently disconnected.
-                        let Some(output) = self.niri.mapped_cast_output.get(&window) else {
-                            warn!("error starting screencast: requested window is missing");
+                        (CastTarget::Window { id }, size, refresh, true, None)
+                    }
+                    StreamTargetId::Area(area) => {
+                        let rect = *area.lock().unwrap();
+                        let Some(rect) = rect else {
+                            self.start_area_cast_selection(
+                                session_id,
+                                cursor_mode,
+                                area,
+                                signal_ctx,
+                            );
+                            return;
+                        };
+
+                        let Some((output, crop)) = self.niri.area_cast_crop(rect) else {
+                            warn!("error starting screencast: requested area is outside outputs");
                             self.niri.stop_cast(session_id);
                             return;
                         };
 
-                        let scale = Scale::from(output.current_scale().fractional_scale());
-                        let bbox = window.bbox_with_popups().to_physical_precise_up(scale);
                         let refresh = output.current_mode().unwrap().refresh as u32;
-
-                        (CastTarget::Window { id }, bbox.size, refresh, true)
+                        let target = CastTarget::Output(output.downgrade());
+                        (target, crop.size, refresh, false, Some(crop))
                     }
                 };
 
-                let mut render_formats = self
-                    .backend
-                    .with_primary_renderer(|renderer| {
-                        renderer.egl_context().dmabuf_render_formats().clone()
-                    })
-                    .unwrap_or_default();
-
-                {
-                    let config = self.niri.config.borrow();
-                    if config.debug.force_pipewire_invalid_modifier {
-                        render_formats = render_formats
-                            .into_iter()
-                            .filter(|f| f.modifier == Modifier::Invalid)
-                            .collect();
-                    }
-                }
-
-                let res = pw.start_cast(
-                    gbm,
-                    render_formats,
-                    session_id,
-                    target,
-                    size,
-                    refresh,
-                    alpha,
-                    cursor_mode,
-                    signal_ctx,
-                );
+                let sink = CastSink::Portal(signal_ctx);
+                let res =
+                    self.start_cast(session_id, target, size, refresh, alpha, cursor_mode, sink);
                 match res {
-                    Ok(cast) => {
-                        self.niri.casts.push(cast);
-                    }
+                    Ok(cast) => cast.crop = crop,
                     Err(err) => {
                         warn!("error starting screencast: {err:?}");
                         self.niri.stop_cast(session_id);
@@ -1716,6 +2545,254 @@ impl State {
         }
     }
 
+    #[cfg(feature = "xdp-gnome-screencast")]
+    fn start_area_cast_selection(
+        &mut self,
+        session_id: usize,
+        cursor_mode: CursorMode,
+        area: mutter_screen_cast::CastArea,
+        signal_ctx: zbus::object_server::SignalEmitter<'static>,
+    ) {
+        if let Some(pending) = self.niri.pending_area_cast.take() {
+            debug!("replacing a pending area cast selection");
+            self.niri.stop_cast(pending.session_id);
+        }
+
+        self.open_screenshot_ui();
+        if !self.niri.screenshot_ui.is_open() {
+            warn!("error starting screencast: could not open the screenshot UI");
+            self.niri.stop_cast(session_id);
+            return;
+        }
+
+        self.niri.pending_area_cast = Some(PendingAreaCast {
+            session_id,
+            cursor_mode,
+            area,
+            signal_ctx,
+        });
+    }
+
+    /// Starts the pending area cast with the screenshot UI selection.
+    ///
+    /// Returns `false` if there's no pending area cast.
+    #[cfg(feature = "xdp-gnome-screencast")]
+    pub fn confirm_area_cast_selection(&mut self) -> bool {
+        let Some(pending) = self.niri.pending_area_cast.take() else {
+            return false;
+        };
+        let PendingAreaCast {
+            session_id,
+            cursor_mode,
+            area,
+            signal_ctx,
+        } = pending;
+
+        let Some((output, crop)) = self.niri.screenshot_ui.selection() else {
+            self.niri.stop_cast(session_id);
+            return true;
+        };
+        let output = output.clone();
+
+        // Report the selection in global logical coordinates before the stream starts.
+        let Some(output_geo) = self.niri.global_space.output_geometry(&output) else {
+            self.niri.stop_cast(session_id);
+            return true;
+        };
+        let scale = output.current_scale().fractional_scale();
+        let selected = crop.to_f64().to_logical(scale).to_i32_round();
+        *area.lock().unwrap() = Some(Rectangle::new(output_geo.loc + selected.loc, selected.size));
+
+        let refresh = output.current_mode().unwrap().refresh as u32;
+        let target = CastTarget::Output(output.downgrade());
+        let sink = CastSink::Portal(signal_ctx);
+        let res = self.start_cast(
+            session_id,
+            target,
+            crop.size,
+            refresh,
+            false,
+            cursor_mode,
+            sink,
+        );
+        match res {
+            Ok(cast) => cast.crop = Some(crop),
+            Err(err) => {
+                warn!("error starting screencast: {err:?}");
+                self.niri.stop_cast(session_id);
+            }
+        }
+
+        true
+    }
+
+    /// Limits the frame rate of the ongoing screencasts, or removes the limit if `fps` is 0.
+    pub fn set_cast_max_fps(&mut self, fps: u32) {
+        #[cfg(not(feature = "xdp-gnome-screencast"))]
+        {
+            let _ = fps;
+            warn!("screencasting requires the \"xdp-gnome-screencast\" feature");
+        }
+
+        #[cfg(feature = "xdp-gnome-screencast")]
+        for cast in &mut self.niri.casts {
+            cast.set_max_fps(Some(fps));
+        }
+    }
+
+    /// Redirects the ongoing window screencasts to a different window.
+    ///
+    /// Uses the focused window if `id` is `None`.
+    pub fn set_dynamic_cast_target(&mut self, id: Option<u64>) {
+        #[cfg(not(feature = "xdp-gnome-screencast"))]
+        {
+            let _ = id;
+            warn!("screencasting requires the \"xdp-gnome-screencast\" feature");
+        }
+
+        #[cfg(feature = "xdp-gnome-screencast")]
+        {
+            let id = match id {
+                Some(id) => id,
+                None => match self.niri.layout.focus() {
+                    Some(mapped) => mapped.id().get(),
+                    None => return,
+                },
+            };
+
+            let Some((_, refresh)) = self.niri.window_cast_size_and_refresh(id) else {
+                warn!("error setting dynamic cast target: window not found");
+                return;
+            };
+
+            let new_target = CastTarget::Window { id };
+            let recording_session_id = self.niri.screen_recording.as_ref().map(|r| r.session_id);
+
+            let mut changed = false;
+            for cast in &mut self.niri.casts {
+                // Recordings are named after their window, so keep them on it.
+                if Some(cast.session_id) == recording_session_id {
+                    continue;
+                }
+
+                if !matches!(cast.target, CastTarget::Window { .. }) || cast.target == new_target {
+                    continue;
+                }
+
+                debug!(cast.session_id, id, "switching window cast target");
+                cast.target = new_target.clone();
+                if let Err(err) = cast.set_refresh(refresh) {
+                    warn!("error changing cast FPS: {err:?}");
+                }
+                changed = true;
+            }
+
+            // Send a frame of the new window right away, the stream will resize itself if needed.
+            if changed {
+                self.redraw_cast_target(new_target);
+            }
+        }
+    }
+
+    pub fn toggle_screen_record(&mut self, target: ScreenRecordTarget) {
+        #[cfg(not(feature = "xdp-gnome-screencast"))]
+        {
+            let _ = target;
+            warn!("screen recording requires the \"xdp-gnome-screencast\" feature");
+        }
+
+        #[cfg(feature = "xdp-gnome-screencast")]
+        {
+            if let Some(recording) = &mut self.niri.screen_recording {
+                let session_id = recording.session_id;
+                if !recording.stop_encoder() {
+                    self.niri.stop_cast(session_id);
+                }
+                return;
+            }
+
+            if let Err(err) = self.start_screen_recording(target) {
+                warn!("error starting screen recording: {err:?}");
+            }
+        }
+    }
+
+    #[cfg(feature = "xdp-gnome-screencast")]
+    fn start_screen_recording(&mut self, target: ScreenRecordTarget) -> anyhow::Result<()> {
+        let _span = tracy_client::span!("State::start_screen_recording");
+
+        let (cast_target, size, refresh, crop, window) = match target {
+            ScreenRecordTarget::Output => {
+                // Record the screenshot UI selection if it's open, and the active output otherwise.
+                let (output, crop) = match self.niri.screenshot_ui.selection() {
+                    Some((output, rect)) => (output.clone(), Some(rect)),
+                    None => {
+                        let output = self.niri.layout.active_output().context("no outputs")?;
+                        (output.clone(), None)
+                    }
+                };
+
+                let mode = output.current_mode().unwrap();
+                let size = output.current_transform().transform_size(mode.size);
+                let size = crop.map_or(size, |crop| crop.size);
+                let refresh = mode.refresh as u32;
+
+                let target = CastTarget::Output(output.downgrade());
+                (target, size, refresh, crop, ScreenshotWindowInfo::default())
+            }
+            ScreenRecordTarget::Window { id } => {
+                let mapped = match id {
+                    Some(id) => {
+                        let mut windows = self.niri.layout.windows().map(|(_, mapped)| mapped);
+                        windows.find(|mapped| mapped.id().get() == id)
+                    }
+                    None => self.niri.layout.focus(),
+                };
+                let mapped = mapped.context("window not found")?;
+                let id = mapped.id().get();
+
+                let window = with_toplevel_role(mapped.toplevel(), |role| ScreenshotWindowInfo {
+                    app_id: role.app_id.clone(),
+                    title: role.title.clone(),
+                });
+
+                let (size, refresh) = self
+                    .niri
+                    .window_cast_size_and_refresh(id)
+                    .context("window is not on any output")?;
+
+                (CastTarget::Window { id }, size, refresh, None, window)
+            }
+        };
+
+        let path = make_screen_recording_path(&self.niri.config.borrow(), &window)?;
+
+        let session_id = mutter_screen_cast::next_session_id();
+        let alpha = matches!(cast_target, CastTarget::Window { .. });
+        let cast = self.start_cast(
+            session_id,
+            cast_target,
+            size,
+            refresh,
+            alpha,
+            CursorMode::Embedded,
+            CastSink::Recording,
+        )?;
+        cast.crop = crop;
+
+        if self.niri.close_screenshot_ui() {
+            self.niri
+                .cursor_manager
+                .set_cursor_image(CursorImageStatus::default_named());
+        }
+
+        info!("started screen recording to {path:?}");
+        self.niri.screen_recording = Some(ScreenRecording::new(session_id, path));
+        self.niri.queue_redraw_all();
+
+        Ok(())
+    }
+
     #[cfg(feature = "dbus")]
     pub fn on_screen_shot_msg(
         &mut self,
@@ -1814,7 +2891,9 @@ impl Niri {
         animation_clock.set_rate(rate);
         animation_clock.set_complete_instantly(config_.animations.off);
 
-        let layout = Layout::new(animation_clock.clone(), &config_);
+        let status_bar = StatusBar::new(config.clone());
+        let mut layout = Layout::new(animation_clock.clone(), &config_);
+        layout.set_status_bar_height(status_bar.reserved_height());
 
         let (blocker_cleared_tx, blocker_cleared_rx) = mpsc::channel();
 
@@ -1901,6 +2980,8 @@ impl Niri {
         output_management_state.on_config_changed(config_.outputs.clone());
         let screencopy_state =
             ScreencopyManagerState::new::<State, _>(&display_handle, client_is_unrestricted);
+        let image_copy_capture_state =
+            ImageCopyCaptureState::new::<State, _>(&display_handle, client_is_unrestricted);
         let viewporter_state = ViewporterState::new::<State>(&display_handle);
         let xdg_foreign_state = XdgForeignState::new::<State>(&display_handle);
 
@@ -1917,6 +2998,11 @@ impl Niri {
                     state.niri.activation_state.retain_tokens(|_, token_data| {
                         token_data.timestamp.elapsed() < XDG_ACTIVATION_TOKEN_TIMEOUT
                     });
+                    let activation_state = &state.niri.activation_state;
+                    state.niri.spawn_rules.retain(|token, _| {
+                        let token = XdgActivationToken::from(token.clone());
+                        activation_state.data_for_token(&token).is_some()
+                    });
                     TimeoutAction::ToDuration(XDG_ACTIVATION_TOKEN_TIMEOUT)
                 },
             )
@@ -1924,6 +3010,7 @@ impl Niri {
 
         let mutter_x11_interop_state =
             MutterX11InteropManagerState::new::<State, _>(&display_handle, move |_| true);
+        let fixes_state = FixesState::new::<State>(&display_handle);
 
         #[cfg(test)]
         let single_pixel_buffer_state = SinglePixelBufferState::new::<State>(&display_handle);
@@ -1945,8 +3032,13 @@ impl Niri {
         let mods_with_wheel_binds = mods_with_wheel_binds(backend.mod_key(), &config_.binds);
         let mods_with_finger_scroll_binds =
             mods_with_finger_scroll_binds(backend.mod_key(), &config_.binds);
+        let mods_with_background_wheel_binds =
+            mods_with_wheel_binds(backend.mod_key(), &config_.background_binds.0);
+        let mods_with_background_finger_scroll_binds =
+            mods_with_finger_scroll_binds(backend.mod_key(), &config_.background_binds.0);
 
         let screenshot_ui = ScreenshotUi::new(animation_clock.clone(), config.clone());
+        let screenshot_feedback = ScreenshotFeedback::new(animation_clock.clone(), config.clone());
         let config_error_notification =
             ConfigErrorNotification::new(animation_clock.clone(), config.clone());
 
@@ -1955,7 +3047,7 @@ impl Niri {
             hotkey_overlay.show();
         }
 
-        let exit_confirm_dialog = match ExitConfirmDialog::new() {
+        let exit_confirm_dialog = match ConfirmDialog::exit() {
             Ok(x) => Some(x),
             Err(err) => {
                 warn!("error creating the exit confirm dialog: {err:?}");
@@ -1973,6 +3065,19 @@ impl Niri {
             )
             .unwrap();
 
+        // Keep the status bar clock up to date.
+        event_loop
+            .insert_source(
+                Timer::from_duration(status_bar::time_until_next_minute()),
+                |_, _, state| {
+                    if state.niri.status_bar.is_on() {
+                        state.niri.queue_redraw_all();
+                    }
+                    TimeoutAction::ToDuration(status_bar::time_until_next_minute())
+                },
+            )
+            .unwrap();
+
         let socket_name = create_wayland_socket.then(|| {
             let socket_source = ListeningSocketSource::new_auto().unwrap();
             let socket_name = socket_source.socket_name().to_os_string();
@@ -1982,9 +3087,33 @@ impl Niri {
                         client,
                         restricted: false,
                         credentials_unknown: false,
+                        is_xwayland_satellite: false,
                     });
                 })
                 .unwrap();
+
+            // xwayland-satellite spawned by niri connects to a separate socket, which is how we
+            // tell that its windows are X11 windows.
+            let xwayland_socket_name = format!("{}-xwayland", socket_name.to_string_lossy());
+            match ListeningSocketSource::with_name(&xwayland_socket_name) {
+                Ok(socket_source) => {
+                    event_loop
+                        .insert_source(socket_source, move |client, _, state| {
+                            state.niri.insert_client(NewClient {
+                                client,
+                                restricted: false,
+                                credentials_unknown: false,
+                                is_xwayland_satellite: true,
+                            });
+                        })
+                        .unwrap();
+                    let _ = XWAYLAND_SATELLITE_SOCKET.set(xwayland_socket_name);
+                }
+                Err(err) => {
+                    warn!("error creating the xwayland-satellite socket: {err:?}");
+                }
+            }
+
             socket_name
         });
 
@@ -1996,6 +3125,19 @@ impl Niri {
             }
         };
 
+        let brightness = BrightnessControl::new(&event_loop);
+
+        #[cfg(feature = "metrics")]
+        let metrics = {
+            let mut metrics = crate::metrics::Metrics::new();
+            metrics.set_config(&event_loop, &config_.metrics);
+            metrics
+        };
+        #[cfg(not(feature = "metrics"))]
+        if config_.metrics.listen.is_some() {
+            warn!("metrics exporter is configured, but niri was built without the metrics feature");
+        }
+
         #[cfg(feature = "xdp-gnome-screencast")]
         let pw_to_niri = {
             let (pw_to_niri, from_pipewire) = calloop::channel::channel();
@@ -2036,6 +3178,7 @@ impl Niri {
         let mut niri = Self {
             config,
             config_file_output_config,
+            active_output_profile: None,
 
             event_loop,
             scheduler,
@@ -2047,10 +3190,14 @@ impl Niri {
             clock: animation_clock,
 
             layout,
+            empty_named_workspaces: HashMap::new(),
             global_space: Space::default(),
             sorted_outputs: Vec::default(),
             output_state: HashMap::new(),
             unmapped_windows: HashMap::new(),
+            startup_placeholders: HashMap::new(),
+            spawn_rules: HashMap::new(),
+            desktop_entries: DesktopEntries::shared(),
             unmapped_layer_surfaces: HashSet::new(),
             mapped_layer_surfaces: HashMap::new(),
             root_surface: HashMap::new(),
@@ -2059,10 +3206,12 @@ impl Niri {
             blocker_cleared_rx,
             monitors_active: true,
             is_lid_closed: false,
+            accelerometer_rotation: None,
 
             devices: HashSet::new(),
             tablets: HashMap::new(),
             touch: HashSet::new(),
+            internal_keyboards: HashSet::new(),
 
             compositor_state,
             xdg_shell_state,
@@ -2073,6 +3222,7 @@ impl Niri {
             foreign_toplevel_state,
             output_management_state,
             screencopy_state,
+            image_copy_capture_state,
             viewporter_state,
             xdg_foreign_state,
             text_input_state,
@@ -2098,13 +3248,16 @@ impl Niri {
             popup_grab: None,
             suppressed_keys: HashSet::new(),
             suppressed_buttons: HashSet::new(),
+            touch_points: HashMap::new(),
             bind_cooldown_timers: HashMap::new(),
             bind_repeat_timer: Option::default(),
+            hotkey_overlay_mod_hold_timer: None,
             presentation_state,
             security_context_state,
             gamma_control_manager_state,
             activation_state,
             mutter_x11_interop_state,
+            fixes_state,
             #[cfg(test)]
             single_pixel_buffer_state,
 
@@ -2112,6 +3265,9 @@ impl Niri {
             keyboard_focus: KeyboardFocus::Layout { surface: None },
             layer_shell_on_demand_focus: None,
             previously_focused_window: None,
+            focus_history: FocusHistory::new(),
+            clipboard_history: ClipboardHistory::new(),
+            window_select_mode: false,
             idle_inhibiting_surfaces: HashSet::new(),
             is_fdo_idle_inhibited: Arc::new(AtomicBool::new(false)),
             keyboard_shortcuts_inhibiting_surfaces: HashMap::new(),
@@ -2119,11 +3275,16 @@ impl Niri {
             cursor_texture_cache: Default::default(),
             cursor_shape_manager_state,
             dnd_icon: None,
+            dnd_action: None,
+            dnd_action_indicator: DndActionIndicator::new(),
             pointer_contents: PointContents::default(),
             pointer_hidden: false,
             pointer_inactivity_timer: None,
+            suppress_warp_mouse_to_focus: false,
+            focus_follows_mouse_timer: None,
             tablet_cursor_location: None,
             gesture_swipe_3f_cumulative: None,
+            dwt_tracker: DwtTracker::new(),
             vertical_wheel_tracker: ScrollTracker::new(120),
             horizontal_wheel_tracker: ScrollTracker::new(120),
             mods_with_mouse_binds,
@@ -2133,13 +3294,21 @@ impl Niri {
             vertical_finger_scroll_tracker: ScrollTracker::new(10),
             horizontal_finger_scroll_tracker: ScrollTracker::new(10),
             mods_with_finger_scroll_binds,
+            mods_with_background_wheel_binds,
+            mods_with_background_finger_scroll_binds,
 
             lock_state: LockState::Unlocked,
 
             screenshot_ui,
+            screenshot_feedback,
             config_error_notification,
             hotkey_overlay,
             exit_confirm_dialog,
+            close_confirm_dialog: None,
+            workspace_rename_dialog: WorkspaceRenameDialog::new(),
+            clipboard_history_picker: ClipboardHistoryPicker::new(),
+            status_bar,
+            watermark: Watermark::new(config.clone()),
 
             debug_draw_opaque_regions: false,
             debug_draw_damage: false,
@@ -2148,9 +3317,20 @@ impl Niri {
             dbus: None,
             #[cfg(feature = "dbus")]
             inhibit_power_key_fd: None,
+            #[cfg(feature = "dbus")]
+            is_accelerometer_watched: false,
 
             ipc_server,
             ipc_outputs_changed: false,
+            ipc_layers_changed: false,
+
+            brightness,
+
+            #[cfg(feature = "metrics")]
+            metrics,
+
+            #[cfg(feature = "fallback-lock")]
+            fallback_lock: None,
 
             pipewire: None,
             casts: vec![],
@@ -2159,6 +3339,13 @@ impl Niri {
 
             #[cfg(feature = "xdp-gnome-screencast")]
             mapped_cast_output: HashMap::new(),
+
+            #[cfg(feature = "xdp-gnome-screencast")]
+            screen_recording: None,
+            #[cfg(feature = "xdp-gnome-screencast")]
+            pending_area_cast: None,
+            #[cfg(feature = "xdp-gnome-screencast")]
+            cast_cursor_bitmap: None,
         };
 
         niri.reset_pointer_inactivity_timer();
@@ -2171,6 +3358,7 @@ impl Niri {
             client,
             restricted,
             credentials_unknown,
+            is_xwayland_satellite,
         } = client;
 
         let config = self.config.borrow();
@@ -2180,6 +3368,7 @@ impl Niri {
             primary_selection_disabled: config.clipboard.disable_primary,
             restricted,
             credentials_unknown,
+            is_xwayland_satellite,
         });
 
         if let Err(err) = self.display_handle.insert_client(client, data) {
@@ -2216,6 +3405,29 @@ impl Niri {
         Ok(())
     }
 
+    /// Starts watching the accelerometer if some output has auto-rotation enabled.
+    #[cfg(feature = "dbus")]
+    pub fn watch_accelerometer_if_needed(&mut self) {
+        if self.is_accelerometer_watched {
+            return;
+        }
+
+        let auto_rotate = (self.config.borrow().outputs.0.iter()).any(|c| c.auto_rotate);
+        if !auto_rotate {
+            return;
+        }
+        self.is_accelerometer_watched = true;
+
+        let (to_niri, from_accelerometer) = calloop::channel::channel();
+        self.event_loop
+            .insert_source(from_accelerometer, move |event, _, state| match event {
+                calloop::channel::Event::Msg(rotation) => state.on_accelerometer_rotation(rotation),
+                calloop::channel::Event::Closed => (),
+            })
+            .unwrap();
+        crate::dbus::iio_sensor_proxy::start(to_niri);
+    }
+
     /// Repositions all outputs, optionally adding a new output.
     pub fn reposition_outputs(&mut self, new_output: Option<&Output>) {
         let _span = tracy_client::span!("Niri::reposition_outputs");
@@ -2247,6 +3459,8 @@ impl Niri {
         for Data { output, .. } in &outputs {
             self.global_space.unmap_output(output);
         }
+        // Layer surfaces are listed for the outputs in the global space.
+        self.ipc_layers_changed = true;
 
         // Connectors can appear in udev in any order. If we sort by name then we get output
         // positioning that does not depend on the order they appeared.
@@ -2354,9 +3568,7 @@ impl Niri {
         });
         let scale = closest_representable_scale(scale.clamp(0.1, 10.));
 
-        let mut transform = c
-            .map(|c| ipc_transform_to_smithay(c.transform))
-            .unwrap_or(Transform::Normal);
+        let mut transform = output_transform(c, self.accelerometer_rotation);
 
         let mut background_color = c
             .map(|c| c.background_color)
@@ -2364,6 +3576,9 @@ impl Niri {
             .to_array_unpremul();
         background_color[3] = 1.;
 
+        let max_render_fps = c.and_then(|c| c.max_render_fps);
+        let color_temperature = c.and_then(|c| c.color_temperature);
+
         // FIXME: fix winit damage on other transforms.
         if name.connector == "winit" {
             transform = Transform::Flipped180;
@@ -2378,7 +3593,19 @@ impl Niri {
             None,
         );
 
-        self.layout.add_output(output.clone());
+        let mirror_source = self.mirror_source_for(&output);
+        if mirror_source.is_none() {
+            self.layout.add_output(output.clone());
+
+            // Bring back the workspaces that were moved away while this output was disconnected.
+            let ws_configs: Vec<_> = (self.config.borrow().workspaces.iter())
+                .filter(|ws| ws.return_on_connect)
+                .cloned()
+                .collect();
+            if !ws_configs.is_empty() {
+                self.layout.move_workspaces_to_assigned_outputs(&ws_configs);
+            }
+        }
 
         let lock_render_state = if self.is_locked() {
             // We haven't rendered anything yet so it's as good as locked.
@@ -2387,13 +3614,16 @@ impl Niri {
             LockRenderState::Unlocked
         };
 
+        let mut frame_clock = FrameClock::new(refresh_interval, vrr);
+        frame_clock.set_max_render_fps(max_render_fps);
+
         let size = output_size(&output).to_i32_round();
         let state = OutputState {
             global,
             redraw_state: RedrawState::Idle,
             on_demand_vrr_enabled: false,
             unfinished_animations_remain: false,
-            frame_clock: FrameClock::new(refresh_interval, vrr),
+            frame_clock,
             last_drm_sequence: None,
             frame_callback_sequence: 0,
             background_buffer: SolidColorBuffer::new(size, background_color),
@@ -2402,12 +3632,132 @@ impl Niri {
             lock_color_buffer: SolidColorBuffer::new(size, CLEAR_COLOR_LOCKED),
             screen_transition: None,
             debug_damage_tracker: OutputDamageTracker::from_output(&output),
+            color_temperature,
+            mirror_source: mirror_source.clone(),
+            split_parts: Vec::new(),
+            output_textures: RefCell::new(HashMap::new()),
         };
         let rv = self.output_state.insert(output.clone(), state);
         assert!(rv.is_none(), "output was already tracked");
 
         // Must be last since it will call queue_redraw(output) which needs things to be filled-in.
-        self.reposition_outputs(Some(&output));
+        if let Some(source) = mirror_source {
+            info!("output {} is mirroring {}", output.name(), source.name());
+            self.queue_redraw(&output);
+        } else {
+            self.reposition_outputs(Some(&output));
+        }
+
+        // Other outputs may be waiting to mirror this one.
+        self.refresh_output_mirrors();
+    }
+
+    /// Returns the output that this output should mirror according to the config.
+    ///
+    /// Outputs configured to mirror some other output can't be mirrored themselves.
+    fn mirror_source_for(&self, output: &Output) -> Option<Output> {
+        let config = self.config.borrow();
+        let name = output.user_data().get::<OutputName>().unwrap();
+        let target = config.outputs.find(name)?.mirror.as_deref()?;
+
+        self.output_state
+            .keys()
+            .filter(|source| *source != output)
+            .find(|source| output_matches_name(source, target))
+            .filter(|source| {
+                let name = source.user_data().get::<OutputName>().unwrap();
+                config
+                    .outputs
+                    .find(name)
+                    .map_or(true, |c| c.mirror.is_none())
+            })
+            .cloned()
+    }
+
+    /// Starts and stops mirroring outputs according to the config.
+    pub fn refresh_output_mirrors(&mut self) {
+        let mut changes: Vec<_> = self
+            .output_state
+            .iter()
+            .filter_map(|(output, state)| {
+                let source = self.mirror_source_for(output);
+                (state.mirror_source != source).then(|| (output.clone(), source))
+            })
+            .collect();
+        if changes.is_empty() {
+            return;
+        }
+
+        // Return outputs to the layout first, so that it doesn't run out of outputs in between.
+        changes.sort_by_key(|(_, source)| source.is_some());
+
+        for (output, source) in changes {
+            let state = self.output_state.get_mut(&output).unwrap();
+            state.mirror_source = source.clone();
+            state.output_textures.get_mut().clear();
+
+            if let Some(source) = source {
+                info!("output {} is mirroring {}", output.name(), source.name());
+            } else {
+                info!("output {} stopped mirroring", output.name());
+            }
+
+            self.refresh_output_in_layout(&output);
+            self.queue_redraw(&output);
+        }
+
+        self.ipc_outputs_changed = true;
+    }
+
+    /// Sets the logical outputs that this output is split into.
+    ///
+    /// The parts must already be added as outputs. Pass an empty `Vec` to join the output back.
+    pub fn set_split_parts(
+        &mut self,
+        output: &Output,
+        parts: Vec<(Output, Rectangle<i32, Physical>)>,
+    ) {
+        let state = self.output_state.get_mut(output).unwrap();
+        if parts.is_empty() {
+            info!("output {} is no longer split", output.name());
+        } else {
+            let names: Vec<_> = parts.iter().map(|(part, _)| part.name()).collect();
+            info!(
+                "output {} is split into {}",
+                output.name(),
+                names.join(", ")
+            );
+        }
+        state.split_parts = parts;
+        state.output_textures.get_mut().clear();
+
+        self.refresh_output_in_layout(output);
+        self.queue_redraw(output);
+        self.ipc_outputs_changed = true;
+    }
+
+    /// Adds the output to or removes it from the layout depending on whether it shows its own
+    /// contents.
+    fn refresh_output_in_layout(&mut self, output: &Output) {
+        let state = &self.output_state[output];
+        let should_be_in_layout = state.mirror_source.is_none() && state.split_parts.is_empty();
+        let is_in_layout = self.layout.monitor_for_output(output).is_some();
+        if should_be_in_layout == is_in_layout {
+            return;
+        }
+
+        if should_be_in_layout {
+            self.layout.add_output(output.clone());
+            self.reposition_outputs(Some(output));
+        } else {
+            for layer in layer_map_for_output(output).layers() {
+                layer.layer_surface().send_close();
+            }
+
+            self.layout.remove_output(output);
+            self.global_space.unmap_output(output);
+            self.reposition_outputs(None);
+        }
     }
 
     pub fn remove_output(&mut self, output: &Output) {
@@ -2415,25 +3765,37 @@ impl Niri {
             layer.layer_surface().send_close();
         }
 
-        self.layout.remove_output(output);
+        // Mirroring and split outputs aren't in the layout.
+        if self.layout.monitor_for_output(output).is_some() {
+            self.layout.remove_output(output);
+        }
         self.global_space.unmap_output(output);
         self.reposition_outputs(None);
         self.gamma_control_manager_state.output_removed(output);
 
         let state = self.output_state.remove(output).unwrap();
 
+        // Outputs mirroring this one go back to the layout.
+        self.refresh_output_mirrors();
+
         match state.redraw_state {
             RedrawState::Idle => (),
             RedrawState::Queued => (),
             RedrawState::WaitingForVBlank { .. } => (),
             RedrawState::WaitingForEstimatedVBlank(token) => self.event_loop.remove(token),
             RedrawState::WaitingForEstimatedVBlankAndQueued(token) => self.event_loop.remove(token),
+            RedrawState::WaitingForThrottle(token) => self.event_loop.remove(token),
         }
 
         #[cfg(feature = "xdp-gnome-screencast")]
         self.stop_casts_for_target(CastTarget::Output(output.downgrade()));
 
         self.remove_screencopy_output(output);
+        self.image_copy_capture_state.remove_output(output);
+        self.status_bar.remove_output(output);
+
+        #[cfg(feature = "metrics")]
+        self.metrics.remove_output(output);
 
         // Disable the output global and remove some time later to give the clients some time to
         // process it.
@@ -2472,7 +3834,7 @@ impl Niri {
             lock_state => self.lock_state = lock_state,
         }
 
-        if self.screenshot_ui.close() {
+        if self.close_screenshot_ui() {
             self.cursor_manager
                 .set_cursor_image(CursorImageStatus::default_named());
             self.queue_redraw_all();
@@ -2517,7 +3879,7 @@ impl Niri {
             // I haven't quite figured out how to draw the screenshot textures in
             // physical coordinates.
             if old_size != size || old_scale != scale || old_transform != transform {
-                self.screenshot_ui.close();
+                self.close_screenshot_ui();
                 self.cursor_manager
                     .set_cursor_image(CursorImageStatus::default_named());
                 self.queue_redraw_all();
@@ -2561,6 +3923,13 @@ impl Niri {
         Some((output, pos_within_output))
     }
 
+    /// Returns whether pointer input passes through this layer surface.
+    fn is_layer_click_through(&self, layer: &LayerSurface) -> bool {
+        self.mapped_layer_surfaces
+            .get(layer)
+            .is_some_and(|mapped| mapped.rules().click_through)
+    }
+
     /// Returns the window under the position to be activated.
     ///
     /// The cursor may be inside the window's activation region, but not within the window's input
@@ -2581,6 +3950,7 @@ impl Niri {
             layers
                 .layers_on(layer)
                 .rev()
+                .filter(|layer| !self.is_layer_click_through(layer))
                 .find_map(|layer| {
                     let layer_pos_within_output =
                         layers.layer_geometry(layer).unwrap().loc.to_f64();
@@ -2624,6 +3994,25 @@ impl Niri {
         self.window_under(pos)
     }
 
+    /// Selects or deselects the window, entering the window select mode.
+    pub fn toggle_window_selected(&mut self, window: &Window) {
+        self.window_select_mode = true;
+        self.layout.with_windows_mut(|mapped, _| {
+            if mapped.window == *window {
+                mapped.set_selected(!mapped.is_selected());
+            }
+        });
+        self.queue_redraw_all();
+    }
+
+    /// Leaves the window select mode, clearing the selection.
+    pub fn leave_window_select_mode(&mut self) {
+        self.window_select_mode = false;
+        self.layout
+            .with_windows_mut(|mapped, _| mapped.set_selected(false));
+        self.queue_redraw_all();
+    }
+
     /// Returns contents under the given point.
     ///
     /// We don't have a proper global space for all windows, so this function converts window
@@ -2676,6 +4065,7 @@ impl Niri {
             layers
                 .layers_on(layer)
                 .rev()
+                .filter(|layer| !self.is_layer_click_through(layer))
                 .find_map(|layer| {
                     let layer_pos_within_output =
                         layers.layer_geometry(layer).unwrap().loc.to_f64();
@@ -2757,6 +4147,37 @@ impl Niri {
         rv
     }
 
+    /// Returns whether the pointer is over an empty area of a workspace.
+    ///
+    /// This is an area without windows, either empty or showing a background layer-shell surface
+    /// like a wallpaper.
+    pub fn is_pointer_over_workspace_background(&mut self) -> bool {
+        if self.is_locked() || self.screenshot_ui.is_open() {
+            return false;
+        }
+
+        let pos = self.seat.get_pointer().unwrap().current_location();
+        let Some((output, pos_within_output)) = self.output_under(pos) else {
+            return false;
+        };
+
+        // Check the layout directly, since contents_under() doesn't report a window when the
+        // pointer is over its border.
+        if self
+            .layout
+            .window_under(output, pos_within_output)
+            .is_some()
+        {
+            return false;
+        }
+
+        let contents = self.contents_under(pos);
+        contents.window.is_none()
+            && contents
+                .layer
+                .map_or(true, |layer| layer.layer() == Layer::Background)
+    }
+
     pub fn output_under_cursor(&self) -> Option<Output> {
         let pos = self.seat.get_pointer().unwrap().current_location();
         self.global_space.output_under(pos).next().cloned()
@@ -2888,6 +4309,24 @@ impl Niri {
             .or_else(|| self.global_space.outputs().next())
     }
 
+    /// Returns the transform for mapping absolute input device coordinates onto the output.
+    ///
+    /// With `rotate_with_output` unset, the device keeps the configured output transform and
+    /// doesn't follow the auto-rotation.
+    pub fn input_device_transform(&self, output: &Output, rotate_with_output: bool) -> Transform {
+        let config = self.config.borrow();
+        let name = output.user_data().get::<OutputName>().unwrap();
+        let auto_rotation = self
+            .accelerometer_rotation
+            .filter(|_| config.outputs.find(name).is_some_and(|c| c.auto_rotate));
+
+        input_device_transform(
+            output.current_transform(),
+            auto_rotation,
+            rotate_with_output,
+        )
+    }
+
     pub fn output_by_name_match(&self, target: &str) -> Option<&Output> {
         self.global_space
             .outputs()
@@ -2932,6 +4371,15 @@ impl Niri {
     pub fn queue_redraw(&mut self, output: &Output) {
         let state = self.output_state.get_mut(output).unwrap();
         state.redraw_state = mem::take(&mut state.redraw_state).queue_redraw();
+
+        // Forward the damage to the outputs mirroring this one or split into it.
+        for state in self.output_state.values_mut() {
+            if state.mirror_source.as_ref() == Some(output)
+                || state.split_parts.iter().any(|(part, _)| part == output)
+            {
+                state.redraw_state = mem::take(&mut state.redraw_state).queue_redraw();
+            }
+        }
     }
 
     pub fn redraw_queued_outputs(&mut self, backend: &mut Backend) {
@@ -3037,6 +4485,14 @@ impl Niri {
             ));
         }
 
+        if let Some(action) = self.dnd_action {
+            if self.config.borrow().drag_and_drop.show_action_indicator {
+                let indicator = &self.dnd_action_indicator;
+                let elem = indicator.render(renderer, action, pointer_pos, output_scale.x);
+                pointer_elements.extend(elem.map(OutputRenderElements::Texture));
+            }
+        }
+
         pointer_elements
     }
 
@@ -3217,6 +4673,31 @@ impl Niri {
         self.idle_notifier_state.set_is_inhibited(is_inhibited);
     }
 
+    /// Sets the mark on the window with the given id, removing it from all other windows.
+    pub fn set_window_mark(&mut self, id: u64, mark: String) {
+        let exists = self.layout.windows().any(|(_, m)| m.id().get() == id);
+        if !exists {
+            return;
+        }
+
+        self.layout.with_windows_mut(|mapped, _| {
+            if mapped.id().get() == id {
+                mapped.add_mark(mark.clone());
+            } else {
+                mapped.remove_mark(Some(&mark));
+            }
+        });
+    }
+
+    /// Removes the mark from the window with the given id, or all marks if `mark` is `None`.
+    pub fn unset_window_mark(&mut self, id: u64, mark: Option<&str>) {
+        self.layout.with_windows_mut(|mapped, _| {
+            if mapped.id().get() == id {
+                mapped.remove_mark(mark);
+            }
+        });
+    }
+
     pub fn refresh_window_rules(&mut self) {
         let _span = tracy_client::span!("Niri::refresh_window_rules");
 
@@ -3247,6 +4728,34 @@ impl Niri {
         }
     }
 
+    pub fn refresh_workspace_auto_names(&mut self) {
+        let _span = tracy_client::span!("Niri::refresh_workspace_auto_names");
+
+        let config = self.config.borrow();
+        let rules = &config.workspace_auto_name.rules;
+
+        let mut changed = false;
+        for ws in self.layout.workspaces_mut() {
+            // Explicitly named workspaces keep their name.
+            let auto_name = if rules.is_empty() || ws.name().is_some() {
+                None
+            } else {
+                workspace_auto_name(rules, ws.windows())
+            };
+
+            if ws.auto_name() != auto_name.as_ref() {
+                ws.set_auto_name(auto_name);
+                changed = true;
+            }
+        }
+        drop(config);
+
+        if changed {
+            // The status bar shows the names.
+            self.queue_redraw_all();
+        }
+    }
+
     #[cfg(feature = "xdp-gnome-screencast")]
     pub fn refresh_mapped_cast_outputs(&mut self) {
         use std::collections::hash_map::Entry;
@@ -3299,6 +4808,7 @@ impl Niri {
         self.layout.advance_animations();
         self.config_error_notification.advance_animations();
         self.screenshot_ui.advance_animations();
+        self.screenshot_feedback.advance_animations();
 
         for state in self.output_state.values_mut() {
             if let Some(transition) = &mut state.screen_transition {
@@ -3362,6 +4872,14 @@ impl Niri {
             }
         }
 
+        let state = self.output_state.get(output).unwrap();
+        if let Some(source) = &state.mirror_source {
+            return self.render_mirror(renderer, output, source, include_pointer, target);
+        }
+        if !state.split_parts.is_empty() {
+            return self.render_split(renderer, output, include_pointer, target);
+        }
+
         let output_scale = Scale::from(output.current_scale().fractional_scale());
 
         // The pointer goes on the top.
@@ -3370,6 +4888,15 @@ impl Niri {
             elements = self.pointer_element(renderer, output);
         }
 
+        // Next, the watermark, so that it ends up on top of everything else in screencasts.
+        let watermark = self.watermark.render(renderer, output, target);
+        elements.extend(watermark.map(Into::into));
+
+        // Next, the screenshot feedback. Keep it out of screencasts and screenshots.
+        if target == RenderTarget::Output {
+            elements.extend(self.screenshot_feedback.render(output).map(Into::into));
+        }
+
         // Next, the screen transition texture.
         {
             let state = self.output_state.get(output).unwrap();
@@ -3385,13 +4912,45 @@ impl Niri {
             }
         }
 
+        // Next, the close confirm dialog.
+        if let Some((_, dialog)) = &self.close_confirm_dialog {
+            if let Some(element) = dialog.render(renderer, output) {
+                elements.push(element.into());
+            }
+        }
+
+        // Next, the workspace rename dialog.
+        if let Some(element) = self.workspace_rename_dialog.render(renderer, output) {
+            elements.push(element.into());
+        }
+
+        // Next, the clipboard history picker.
+        if let Some(element) = self.clipboard_history_picker.render(renderer, output) {
+            elements.push(element.into());
+        }
+
         // Next, the config error notification too.
         if let Some(element) = self.config_error_notification.render(renderer, output) {
             elements.push(element.into());
         }
 
+        // Next, the screen recording indicator. Keep it out of screencasts and screenshots.
+        #[cfg(feature = "xdp-gnome-screencast")]
+        if let Some(recording) = &self.screen_recording {
+            if target == RenderTarget::Output {
+                elements.push(recording.render_indicator(output).into());
+            }
+        }
+
         // If the session is locked, draw the lock surface.
         if self.is_locked() {
+            #[cfg(feature = "fallback-lock")]
+            if let Some(fallback_lock) = &self.fallback_lock {
+                if let Some(element) = fallback_lock.render(renderer, output) {
+                    elements.push(element.into());
+                }
+            }
+
             let state = self.output_state.get(output).unwrap();
             if let Some(surface) = state.lock_surface.as_ref() {
                 elements.extend(render_elements_from_surface_tree(
@@ -3487,6 +5046,9 @@ impl Niri {
         extend_from_layer(&mut layer_elems, Layer::Bottom);
         extend_from_layer(&mut layer_elems, Layer::Background);
 
+        // The status bar goes together with the top layer.
+        let status_bar = self.status_bar.render(renderer, output, &self.layout);
+
         // When rendering above the top layer, we put the regular monitor elements first.
         // Otherwise, we will render all layer-shell pop-ups and the top layer on top.
         if mon.render_above_top_layer() {
@@ -3495,10 +5057,12 @@ impl Niri {
 
             elements.extend(layer_elems.popups.drain(..).map(OutputRenderElements::from));
             elements.extend(top_layer_normal.into_iter().map(OutputRenderElements::from));
+            elements.extend(status_bar.map(Into::into));
             elements.extend(layer_elems.normal.drain(..).map(OutputRenderElements::from));
         } else {
             elements.extend(layer_elems.popups.drain(..).map(OutputRenderElements::from));
             elements.extend(top_layer_normal.into_iter().map(OutputRenderElements::from));
+            elements.extend(status_bar.map(Into::into));
 
             elements.extend(float_elements.into_iter().map(OutputRenderElements::from));
             elements.extend(monitor_elements.into_iter().map(OutputRenderElements::from));
@@ -3516,6 +5080,158 @@ impl Niri {
         elements
     }
 
+    /// Renders the contents of the source output scaled to fit the mirroring output.
+    fn render_mirror<R: NiriRenderer>(
+        &self,
+        renderer: &mut R,
+        output: &Output,
+        source: &Output,
+        include_pointer: bool,
+        target: RenderTarget,
+    ) -> Vec<OutputRenderElements<R>> {
+        let _span = tracy_client::span!("Niri::render_mirror");
+
+        let mut elements = vec![];
+
+        // Fit the contents into the output, keeping the aspect ratio.
+        let source_size = output_size(source);
+        let mirror_size = output_size(output);
+        let ratio = f64::min(mirror_size.w / source_size.w, mirror_size.h / source_size.h);
+        let size = source_size.upscale(ratio);
+        let location = Point::from(((mirror_size.w - size.w) / 2., (mirror_size.h - size.h) / 2.));
+
+        let renderer = renderer.as_gles_renderer();
+        let geometry = Rectangle::new(location, size);
+        let state = self.output_state.get(output).unwrap();
+        if let Some(element) = self.render_output_texture(
+            renderer,
+            &state.output_textures,
+            source,
+            include_pointer,
+            target,
+            geometry,
+        ) {
+            elements.push(element.into());
+        }
+
+        // The background fills the rest of the output.
+        let output_scale = Scale::from(output.current_scale().fractional_scale());
+        elements.push(
+            SolidColorRenderElement::from_buffer(
+                &state.background_buffer,
+                (0, 0),
+                output_scale,
+                1.,
+                Kind::Unspecified,
+            )
+            .into(),
+        );
+
+        elements
+    }
+
+    /// Renders the parts of a split output next to each other.
+    fn render_split<R: NiriRenderer>(
+        &self,
+        renderer: &mut R,
+        output: &Output,
+        include_pointer: bool,
+        target: RenderTarget,
+    ) -> Vec<OutputRenderElements<R>> {
+        let _span = tracy_client::span!("Niri::render_split");
+
+        let mut elements = vec![];
+
+        let state = self.output_state.get(output).unwrap();
+        let output_scale = Scale::from(output.current_scale().fractional_scale());
+
+        let renderer = renderer.as_gles_renderer();
+        for (part, geometry) in &state.split_parts {
+            let geometry = geometry.to_f64().to_logical(output_scale);
+            if let Some(element) = self.render_output_texture(
+                renderer,
+                &state.output_textures,
+                part,
+                include_pointer,
+                target,
+                geometry,
+            ) {
+                elements.push(element.into());
+            }
+        }
+
+        elements.push(
+            SolidColorRenderElement::from_buffer(
+                &state.background_buffer,
+                (0, 0),
+                output_scale,
+                1.,
+                Kind::Unspecified,
+            )
+            .into(),
+        );
+
+        elements
+    }
+
+    /// Renders the contents of another output into a texture placed at the given geometry.
+    ///
+    /// The texture is kept in `textures` to be reused on the next frame.
+    fn render_output_texture(
+        &self,
+        renderer: &mut GlesRenderer,
+        textures: &RefCell<HashMap<(Output, RenderTarget), GlesTexture>>,
+        source: &Output,
+        include_pointer: bool,
+        target: RenderTarget,
+        geometry: Rectangle<f64, Logical>,
+    ) -> Option<PrimaryGpuTextureRenderElement> {
+        // Only outputs with their own contents can be shown elsewhere. For example, the source may
+        // have started mirroring some other output by now, in which case it will stop being our
+        // source on the next mirror refresh.
+        self.layout.monitor_for_output(source)?;
+
+        let size = source.current_mode().unwrap().size;
+        let transform = source.current_transform();
+        let scale = Scale::from(source.current_scale().fractional_scale());
+
+        let source_elements =
+            self.render::<GlesRenderer>(renderer, source, include_pointer, target);
+        let key = (source.clone(), target);
+        let mut texture = textures.borrow_mut().remove(&key);
+        let res = render_to_reused_texture(
+            renderer,
+            &mut texture,
+            size,
+            scale,
+            transform,
+            Fourcc::Abgr8888,
+            source_elements.iter().rev(),
+        );
+        if let Some(texture) = texture {
+            textures.borrow_mut().insert(key, texture);
+        }
+
+        let texture = match res {
+            Ok((texture, _sync_point)) => texture,
+            Err(err) => {
+                warn!("error rendering output {}: {err:?}", source.name());
+                return None;
+            }
+        };
+
+        let buffer = TextureBuffer::from_texture(renderer, texture, scale, transform, Vec::new());
+        let element = TextureRenderElement::from_texture_buffer(
+            buffer,
+            geometry.loc,
+            1.,
+            None,
+            Some(geometry.size),
+            Kind::Unspecified,
+        );
+        Some(PrimaryGpuTextureRenderElement(element))
+    }
+
     fn render_layer<R: NiriRenderer>(
         &self,
         renderer: &mut R,
@@ -3560,6 +5276,8 @@ impl Niri {
             state.unfinished_animations_remain |=
                 self.config_error_notification.are_animations_ongoing();
             state.unfinished_animations_remain |= self.screenshot_ui.are_animations_ongoing();
+            state.unfinished_animations_remain |=
+                self.screenshot_feedback.are_animations_ongoing(output);
             state.unfinished_animations_remain |= state.screen_transition.is_some();
 
             // Also keep redrawing if the current cursor is animated.
@@ -3568,7 +5286,15 @@ impl Niri {
                 .is_current_cursor_animated(output.current_scale().integer_scale());
 
             // Render.
+            #[cfg(feature = "metrics")]
+            let render_start = Instant::now();
+
             res = backend.render(self, output, target_presentation_time);
+
+            #[cfg(feature = "metrics")]
+            if res == RenderResult::Submitted {
+                self.metrics.record_frame(output, render_start.elapsed());
+            }
         }
 
         let is_locked = self.is_locked();
@@ -3652,6 +5378,7 @@ impl Niri {
             }
 
             self.render_for_screencopy_with_damage(renderer, output);
+            self.render_for_image_copy_capture(renderer, output);
         });
     }
 
@@ -4125,6 +5852,7 @@ impl Niri {
         let scale = Scale::from(output.current_scale().fractional_scale());
 
         let mut elements = None;
+        let mut cursor_meta = None;
         let mut casts_to_stop = vec![];
 
         let mut casts = mem::take(&mut self.casts);
@@ -4137,7 +5865,20 @@ impl Niri {
                 continue;
             }
 
-            match cast.ensure_size(size) {
+            let crop = match cast.crop {
+                Some(crop) => match crop.intersection(Rectangle::from_size(size)) {
+                    Some(crop) => Some(crop),
+                    None => {
+                        warn!("cast crop is outside the output, stopping screencast");
+                        casts_to_stop.push(cast.session_id);
+                        continue;
+                    }
+                },
+                None => None,
+            };
+            let cast_size = crop.map_or(size, |crop| crop.size);
+
+            match cast.ensure_size(cast_size) {
                 Ok(CastSizeChange::Ready) => (),
                 Ok(CastSizeChange::Pending) => continue,
                 Err(err) => {
@@ -4150,13 +5891,62 @@ impl Niri {
                 continue;
             }
 
-            // FIXME: Hidden / embedded / metadata cursor
-            let elements = elements.get_or_insert_with(|| {
-                self.render(renderer, output, true, RenderTarget::Screencast)
+            // Render the pointer separately so that casts can leave it out or send it as
+            // metadata.
+            let (elements, pointer_count) = elements.get_or_insert_with(|| {
+                let mut elements = self.pointer_element(renderer, output);
+                let pointer_count = elements.len();
+                elements.extend(self.render(renderer, output, false, RenderTarget::Screencast));
+                (elements, pointer_count)
             });
 
-            if cast.dequeue_buffer_and_render(renderer, elements, size, scale) {
-                cast.last_frame_time = target_presentation_time;
+            let cursor = if cast.cursor_mode == CursorMode::Metadata {
+                let meta = cursor_meta.get_or_insert_with(|| {
+                    self.cast_cursor_meta(renderer, output, &elements[..*pointer_count])
+                });
+                meta.clone().map(|mut meta| {
+                    if let Some(crop) = crop {
+                        meta.position -= crop.loc;
+                    }
+                    meta
+                })
+            } else {
+                None
+            };
+
+            let elements = if cast.cursor_mode == CursorMode::Embedded {
+                &elements[..]
+            } else {
+                &elements[*pointer_count..]
+            };
+
+            let rendered = if let Some(crop) = crop {
+                let elements: Vec<_> = elements
+                    .iter()
+                    .map(|elem| {
+                        let offset = crop.loc.upscale(-1);
+                        RelocateRenderElement::from_element(elem, offset, Relocate::Relative)
+                    })
+                    .collect();
+                cast.dequeue_buffer_and_render(
+                    renderer,
+                    &elements,
+                    cursor.as_ref(),
+                    cast_size,
+                    scale,
+                )
+            } else {
+                cast.dequeue_buffer_and_render(
+                    renderer,
+                    elements,
+                    cursor.as_ref(),
+                    cast_size,
+                    scale,
+                )
+            };
+
+            if rendered {
+                cast.frame_rendered(target_presentation_time);
             }
         }
         self.casts = casts;
@@ -4212,11 +6002,24 @@ impl Niri {
                 continue;
             }
 
-            // FIXME: pointer.
-            let elements: Vec<_> = mapped.render_for_screen_cast(renderer, scale).collect();
+            let area_size = bbox.size.to_f64().to_logical(scale);
+            let watermark = self.watermark.render_in_area(
+                renderer,
+                output,
+                area_size,
+                RenderTarget::Screencast,
+            );
 
-            if cast.dequeue_buffer_and_render(renderer, &elements, bbox.size, scale) {
-                cast.last_frame_time = target_presentation_time;
+            // FIXME: pointer.
+            let elements: Vec<_> = watermark
+                .map(Into::into)
+                .into_iter()
+                .chain(mapped.render_for_screen_cast(renderer, scale))
+                .collect();
+
+            // FIXME: send the pointer as metadata.
+            if cast.dequeue_buffer_and_render(renderer, &elements, None, bbox.size, scale) {
+                cast.frame_rendered(target_presentation_time);
             }
         }
         self.casts = casts;
@@ -4292,15 +6095,24 @@ impl Niri {
             }
 
             let elements = elements.get_or_insert_with(|| {
+                let area_size = bbox.size.to_f64().to_logical(scale);
+                let watermark = self.watermark.render_in_area(
+                    renderer,
+                    output,
+                    area_size,
+                    RenderTarget::Screencast,
+                );
+
                 // FIXME: pointer.
-                mapped
-                    .render_for_screen_cast(renderer, scale)
-                    .rev()
+                watermark
+                    .map(Into::into)
+                    .into_iter()
+                    .chain(mapped.render_for_screen_cast(renderer, scale).rev())
                     .collect::<Vec<_>>()
             });
 
-            if cast.dequeue_buffer_and_render(renderer, elements, bbox.size, scale) {
-                cast.last_frame_time = target_presentation_time;
+            if cast.dequeue_buffer_and_render(renderer, elements, None, bbox.size, scale) {
+                cast.frame_rendered(target_presentation_time);
             }
         }
         self.casts = casts;
@@ -4312,6 +6124,82 @@ impl Niri {
         }
     }
 
+    /// Computes the cursor to send in the metadata cursor mode casts of this output.
+    ///
+    /// The position is in output-local physical coordinates.
+    #[cfg(feature = "xdp-gnome-screencast")]
+    fn cast_cursor_meta(
+        &mut self,
+        renderer: &mut GlesRenderer,
+        output: &Output,
+        pointer_elements: &[OutputRenderElements<GlesRenderer>],
+    ) -> Option<CursorMeta> {
+        let _span = tracy_client::span!("Niri::cast_cursor_meta");
+
+        let scale = Scale::from(output.current_scale().fractional_scale());
+        let geo = pointer_elements
+            .iter()
+            .map(|elem| elem.geometry(scale))
+            .reduce(|a, b| a.merge(b))?;
+        if geo.is_empty() {
+            return None;
+        }
+
+        let output_pos = self.global_space.output_geometry(output).unwrap().loc;
+        let pointer_pos = self
+            .tablet_cursor_location
+            .unwrap_or_else(|| self.seat.get_pointer().unwrap().current_location());
+        let position = (pointer_pos - output_pos.to_f64()).to_physical_precise_round(scale);
+
+        let key: CastCursorKey = (
+            pointer_elements
+                .iter()
+                .map(|elem| (elem.id().clone(), elem.current_commit()))
+                .collect(),
+            geo.size,
+        );
+
+        let bitmap = match &self.cast_cursor_bitmap {
+            Some((last_key, bitmap)) if *last_key == key => bitmap.clone(),
+            _ => {
+                let elements = pointer_elements.iter().rev().map(|elem| {
+                    RelocateRenderElement::from_element(
+                        elem,
+                        geo.loc.upscale(-1),
+                        Relocate::Relative,
+                    )
+                });
+                let pixels = match render_to_vec(
+                    renderer,
+                    geo.size,
+                    scale,
+                    Transform::Normal,
+                    Fourcc::Abgr8888,
+                    elements,
+                ) {
+                    Ok(pixels) => pixels,
+                    Err(err) => {
+                        warn!("error rendering cursor for screencast: {err:?}");
+                        return None;
+                    }
+                };
+
+                let bitmap = Rc::new(CursorBitmap {
+                    size: geo.size,
+                    pixels,
+                });
+                self.cast_cursor_bitmap = Some((key, bitmap.clone()));
+                bitmap
+            }
+        };
+
+        Some(CursorMeta {
+            position,
+            hotspot: position - geo.loc,
+            bitmap,
+        })
+    }
+
     pub fn render_for_screencopy_with_damage(
         &mut self,
         renderer: &mut GlesRenderer,
@@ -4488,11 +6376,173 @@ impl Niri {
         Ok((sync, damages))
     }
 
+    pub fn render_for_image_copy_capture(&mut self, renderer: &mut GlesRenderer, output: &Output) {
+        let _span = tracy_client::span!("Niri::render_for_image_copy_capture");
+
+        let mut capture_state = mem::take(&mut self.image_copy_capture_state);
+        let elements_with_cursor = OnceCell::new();
+        let elements_without_cursor = OnceCell::new();
+
+        for (session, data) in capture_state.sessions_mut() {
+            if data.output() != output {
+                continue;
+            }
+
+            let Some(capture) = data.take_pending(session) else {
+                continue;
+            };
+
+            let include_pointer = data.paint_cursors();
+            let elements = if include_pointer {
+                &elements_with_cursor
+            } else {
+                &elements_without_cursor
+            };
+            let elements = elements.get_or_init(|| {
+                self.render(
+                    renderer,
+                    output,
+                    include_pointer,
+                    RenderTarget::ScreenCapture,
+                )
+            });
+
+            let size = output.current_mode().unwrap().size;
+            let scale: Scale<f64> = output.current_scale().fractional_scale().into();
+            let transform = output.current_transform();
+
+            let damage_tracker = data.damage_tracker_mut();
+            let OutputModeSource::Static {
+                size: last_size,
+                scale: last_scale,
+                transform: last_transform,
+            } = damage_tracker.mode().clone()
+            else {
+                unreachable!("damage tracker must have static mode");
+            };
+            if size != last_size || scale != last_scale || transform != last_transform {
+                *damage_tracker = OutputDamageTracker::new(size, scale, transform);
+            }
+
+            // Just checked damage tracker has static mode
+            let Some(damages) = damage_tracker.damage_output(1, elements).unwrap().0 else {
+                trace!("no damage found, waiting till next redraw");
+                data.set_pending(capture);
+                continue;
+            };
+
+            // Convert from Physical coordinates back to Buffer coordinates.
+            let physical_size = transform.transform_size(size);
+            let damages = damages
+                .iter()
+                .map(|dmg| {
+                    dmg.to_logical(1)
+                        .to_buffer(1, transform.invert(), &physical_size.to_logical(1))
+                })
+                .collect::<Vec<_>>();
+
+            let res = render_to_shm(
+                renderer,
+                capture.buffer(),
+                size,
+                scale,
+                transform,
+                elements.iter().rev(),
+            );
+            if let Err(err) = renderer.unbind() {
+                warn!("error unbinding after rendering for image copy capture: {err:?}");
+            }
+
+            match res {
+                Ok(()) => capture.submit(transform, &damages),
+                Err(err) => {
+                    // Recreate damage tracker to report full damage next time.
+                    *data.damage_tracker_mut() =
+                        OutputDamageTracker::new((0, 0), 1.0, Transform::Normal);
+                    warn!("error rendering for image copy capture: {err:?}");
+                }
+            }
+        }
+
+        self.image_copy_capture_state = capture_state;
+    }
+
+    /// Converts an area in global logical coordinates to the output it starts on, and the
+    /// output-local physical crop rectangle.
+    #[cfg(feature = "xdp-gnome-screencast")]
+    fn area_cast_crop(
+        &self,
+        rect: Rectangle<i32, Logical>,
+    ) -> Option<(Output, Rectangle<i32, Physical>)> {
+        let output = self
+            .global_space
+            .outputs()
+            .find(|output| {
+                let geo = self.global_space.output_geometry(output).unwrap();
+                geo.contains(rect.loc)
+            })?
+            .clone();
+
+        let output_geo = self.global_space.output_geometry(&output).unwrap();
+        let local = Rectangle::new(rect.loc - output_geo.loc, rect.size);
+        let local = local.intersection(Rectangle::from_size(output_geo.size))?;
+
+        let scale = output.current_scale().fractional_scale();
+        let crop = local.to_f64().to_physical_precise_round(scale);
+        Some((output, crop))
+    }
+
+    #[cfg(feature = "xdp-gnome-screencast")]
+    fn window_cast_size_and_refresh(&self, id: u64) -> Option<(Size<i32, Physical>, u32)> {
+        let (_, mapped) = self
+            .layout
+            .windows()
+            .find(|(_, mapped)| mapped.id().get() == id)?;
+
+        // Use the cached output since it will be present even if the output was currently
+        // disconnected.
+        let output = self.mapped_cast_output.get(&mapped.window)?;
+
+        let scale = Scale::from(output.current_scale().fractional_scale());
+        let bbox = mapped
+            .window
+            .bbox_with_popups()
+            .to_physical_precise_up(scale);
+        let refresh = output.current_mode().unwrap().refresh as u32;
+
+        Some((bbox.size, refresh))
+    }
+
     #[cfg(feature = "xdp-gnome-screencast")]
     fn stop_cast(&mut self, session_id: usize) {
         let _span = tracy_client::span!("Niri::stop_cast");
 
-        debug!(session_id, "StopCast");
+        debug!(session_id, "StopCast");
+
+        if self
+            .pending_area_cast
+            .as_ref()
+            .is_some_and(|pending| pending.session_id == session_id)
+        {
+            self.pending_area_cast = None;
+        }
+
+        if let Some(recording) = self
+            .screen_recording
+            .as_mut()
+            .filter(|recording| recording.session_id == session_id)
+        {
+            // Keep the cast alive until the encoder finalizes the file, otherwise pipewiresrc can
+            // fail before the end-of-stream reaches the muxer. The encoder waiter stops the cast
+            // again once the encoder exits.
+            if recording.stop_encoder() {
+                return;
+            }
+
+            let recording = self.screen_recording.take().unwrap();
+            info!("saved screen recording to {:?}", recording.path);
+            self.queue_redraw_all();
+        }
 
         for i in (0..self.casts.len()).rev() {
             let cast = &self.casts[i];
@@ -4506,8 +6556,15 @@ impl Niri {
             }
         }
 
-        let dbus = &self.dbus.as_ref().unwrap();
-        let server = dbus.conn_screen_cast.as_ref().unwrap().object_server();
+        // Screen recording casts have no D-Bus session.
+        let Some(conn) = self
+            .dbus
+            .as_ref()
+            .and_then(|dbus| dbus.conn_screen_cast.as_ref())
+        else {
+            return;
+        };
+        let server = conn.object_server();
         let path = format!("/org/gnome/Mutter/ScreenCast/Session/u{}", session_id);
         if let Ok(iface) = server.interface::<_, mutter_screen_cast::Session>(path) {
             let _span = tracy_client::span!("invoking Session::stop");
@@ -4557,6 +6614,19 @@ impl Niri {
         self.queue_redraw_all();
     }
 
+    /// Closes the screenshot UI, returning whether it was open.
+    ///
+    /// A screencast waiting for the user to select its area stops here, unless the selection
+    /// was confirmed beforehand.
+    pub fn close_screenshot_ui(&mut self) -> bool {
+        #[cfg(feature = "xdp-gnome-screencast")]
+        if let Some(pending) = self.pending_area_cast.take() {
+            self.stop_cast(pending.session_id);
+        }
+
+        self.screenshot_ui.close()
+    }
+
     pub fn capture_screenshots<'a>(
         &'a self,
         renderer: &'a mut GlesRenderer,
@@ -4640,8 +6710,13 @@ impl Niri {
         let size = transform.transform_size(size);
 
         let scale = Scale::from(output.current_scale().fractional_scale());
-        let elements =
-            self.render::<GlesRenderer>(renderer, output, true, RenderTarget::ScreenCapture);
+        let include_pointer = self.config.borrow().screenshot.include_pointer;
+        let elements = self.render::<GlesRenderer>(
+            renderer,
+            output,
+            include_pointer,
+            RenderTarget::ScreenCapture,
+        );
         let elements = elements.iter().rev();
         let pixels = render_to_vec(
             renderer,
@@ -4652,8 +6727,12 @@ impl Niri {
             elements,
         )?;
 
-        self.save_screenshot(size, pixels, write_to_disk)
-            .context("error saving screenshot")
+        self.save_screenshot(size, pixels, write_to_disk, ScreenshotWindowInfo::default())
+            .context("error saving screenshot")?;
+
+        self.screenshot_feedback.show(output);
+
+        Ok(())
     }
 
     pub fn screenshot_window(
@@ -4697,7 +6776,12 @@ impl Niri {
             elements,
         )?;
 
-        self.save_screenshot(geo.size, pixels, write_to_disk)
+        let window = with_toplevel_role(mapped.toplevel(), |role| ScreenshotWindowInfo {
+            app_id: role.app_id.clone(),
+            title: role.title.clone(),
+        });
+
+        self.save_screenshot(geo.size, pixels, write_to_disk, window)
             .context("error saving screenshot")
     }
 
@@ -4706,16 +6790,22 @@ impl Niri {
         size: Size<i32, Physical>,
         pixels: Vec<u8>,
         write_to_disk: bool,
+        window: ScreenshotWindowInfo,
     ) -> anyhow::Result<()> {
-        let path = write_to_disk
-            .then(|| match make_screenshot_path(&self.config.borrow()) {
+        let format = self.config.borrow().screenshot.format;
+
+        let path = if write_to_disk {
+            let config = self.config.borrow();
+            match make_screenshot_path(&config, format.kind, &window) {
                 Ok(path) => path,
                 Err(err) => {
                     warn!("error making screenshot path: {err:?}");
                     None
                 }
-            })
-            .flatten();
+            }
+        } else {
+            None
+        };
 
         // Prepare to set the encoded image as our clipboard selection. This must be done from the
         // main thread.
@@ -4723,12 +6813,10 @@ impl Niri {
         self.event_loop
             .insert_source(rx, move |event, _, state| match event {
                 calloop::channel::Event::Msg(buf) => {
-                    set_data_device_selection(
-                        &state.niri.display_handle,
-                        &state.niri.seat,
-                        vec![String::from("image/png")],
-                        buf.clone(),
-                    );
+                    let mime_type = String::from(format.kind.mime_type());
+                    let data: SelectionData = Arc::from(vec![(mime_type, buf)]);
+                    state.niri.add_to_clipboard_history(data.clone());
+                    state.niri.set_clipboard_selection(data);
                 }
                 calloop::channel::Event::Closed => (),
             })
@@ -4739,7 +6827,7 @@ impl Niri {
             let mut buf = vec![];
 
             let w = std::io::Cursor::new(&mut buf);
-            if let Err(err) = write_png_rgba8(w, size.w as u32, size.h as u32, &pixels) {
+            if let Err(err) = write_image_rgba8(w, format, size.w as u32, size.h as u32, &pixels) {
                 warn!("error encoding screenshot image: {err:?}");
                 return;
             }
@@ -4781,6 +6869,160 @@ impl Niri {
         Ok(())
     }
 
+    /// Offers the data as the clipboard selection.
+    pub fn set_clipboard_selection(&mut self, data: SelectionData) {
+        self.clipboard_history
+            .selection_changed(SelectionTarget::Clipboard);
+        let mime_types = data
+            .iter()
+            .map(|(mime_type, _)| mime_type.clone())
+            .collect();
+        set_data_device_selection(&self.display_handle, &self.seat, mime_types, data);
+    }
+
+    /// Offers the data as the primary selection.
+    pub fn set_primary_selection(&mut self, data: SelectionData) {
+        self.clipboard_history
+            .selection_changed(SelectionTarget::Primary);
+        let mime_types = data
+            .iter()
+            .map(|(mime_type, _)| mime_type.clone())
+            .collect();
+        set_primary_selection(&self.display_handle, &self.seat, mime_types, data);
+    }
+
+    /// Returns whether new client selections of this kind need to be read.
+    pub fn wants_client_selection(&self, target: SelectionTarget) -> bool {
+        let config = self.config.borrow();
+        let config = &config.clipboard;
+        match target {
+            SelectionTarget::Clipboard => {
+                config.history_size.unwrap_or(0) > 0 || config.sync_clipboard_to_primary
+            }
+            SelectionTarget::Primary => config.sync_primary_to_clipboard,
+        }
+    }
+
+    /// Adds the data to the clipboard history if it's enabled.
+    ///
+    /// Returns `false` if the data was already the most recent entry.
+    fn add_to_clipboard_history(&mut self, data: SelectionData) -> bool {
+        let max_entries = self.config.borrow().clipboard.history_size.unwrap_or(0);
+        if max_entries == 0 {
+            return false;
+        }
+
+        self.clipboard_history.push(data, usize::from(max_entries))
+    }
+
+    /// Reads the selection of a client, for the clipboard history and selection syncing.
+    pub fn read_client_selection(
+        &mut self,
+        target: SelectionTarget,
+        serial: u64,
+        mime_types: Vec<String>,
+    ) {
+        let _span = tracy_client::span!("Niri::read_client_selection");
+
+        // The selection was replaced before we got to it.
+        if !self.clipboard_history.is_current(target, serial) {
+            return;
+        }
+
+        let mut pipes = Vec::with_capacity(mime_types.len());
+        for mime_type in mime_types {
+            let (read, write) = match pipe_with(PipeFlags::CLOEXEC) {
+                Ok(fds) => fds,
+                Err(err) => {
+                    warn!("error creating pipe for the {target:?} selection: {err:?}");
+                    return;
+                }
+            };
+
+            let res = match target {
+                SelectionTarget::Clipboard => {
+                    request_data_device_client_selection(&self.seat, mime_type.clone(), write)
+                }
+                SelectionTarget::Primary => {
+                    request_primary_client_selection(&self.seat, mime_type.clone(), write)
+                }
+            };
+            if let Err(err) = res {
+                debug!("error requesting the {target:?} selection: {err:?}");
+                return;
+            }
+
+            pipes.push((mime_type, read));
+        }
+
+        let (tx, rx) = calloop::channel::sync_channel::<SelectionData>(1);
+        self.event_loop
+            .insert_source(rx, move |event, _, state| match event {
+                calloop::channel::Event::Msg(data) => {
+                    state.niri.on_client_selection_read(target, serial, data);
+                }
+                calloop::channel::Event::Closed => (),
+            })
+            .unwrap();
+
+        // Reading blocks until the client writes everything.
+        thread::spawn(move || {
+            if let Some(data) = clipboard_history::read_selection(pipes) {
+                let _ = tx.send(data);
+            }
+        });
+    }
+
+    fn on_client_selection_read(
+        &mut self,
+        target: SelectionTarget,
+        serial: u64,
+        data: SelectionData,
+    ) {
+        let config = self.config.borrow().clipboard;
+        let is_current = self.clipboard_history.is_current(target, serial);
+
+        match target {
+            SelectionTarget::Clipboard => {
+                let is_new = self.add_to_clipboard_history(data.clone());
+
+                // Skip selections that were replaced in the meantime.
+                if !is_current {
+                    return;
+                }
+
+                if config.sync_clipboard_to_primary {
+                    self.set_primary_selection(data.clone());
+                }
+
+                // Take the selection over so that it survives its client. Skip ones that niri
+                // already offers to avoid fighting over the clipboard with other clipboard
+                // managers.
+                if is_new {
+                    self.set_clipboard_selection(data);
+                }
+            }
+            SelectionTarget::Primary => {
+                if is_current && config.sync_primary_to_clipboard {
+                    self.add_to_clipboard_history(data.clone());
+                    self.set_clipboard_selection(data);
+                }
+            }
+        }
+    }
+
+    /// Puts a clipboard history entry back into the clipboard.
+    ///
+    /// Returns `false` if there's no entry with this id.
+    pub fn set_clipboard_from_history(&mut self, id: u64) -> bool {
+        let Some(data) = self.clipboard_history.promote(id) else {
+            return false;
+        };
+
+        self.set_clipboard_selection(data);
+        true
+    }
+
     #[cfg(feature = "dbus")]
     pub fn screenshot_all_outputs(
         &mut self,
@@ -4823,7 +7065,9 @@ impl Niri {
             elements,
         )?;
 
-        let path = make_screenshot_path(&self.config.borrow())
+        // This is used for the D-Bus Screenshot API which expects a PNG.
+        let window = ScreenshotWindowInfo::default();
+        let path = make_screenshot_path(&self.config.borrow(), ImageFormat::Png, &window)
             .ok()
             .flatten()
             .unwrap_or_else(|| {
@@ -4877,7 +7121,14 @@ impl Niri {
 
         info!("locking session");
 
-        self.screenshot_ui.close();
+        self.close_screenshot_ui();
+        self.workspace_rename_dialog.hide();
+        self.clipboard_history_picker.hide();
+        self.close_confirm_dialog = None;
+        #[cfg(feature = "fallback-lock")]
+        {
+            self.fallback_lock = None;
+        }
         self.cursor_manager
             .set_cursor_image(CursorImageStatus::default_named());
 
@@ -4900,6 +7151,10 @@ impl Niri {
         for output_state in self.output_state.values_mut() {
             output_state.lock_surface = None;
         }
+        #[cfg(feature = "fallback-lock")]
+        {
+            self.fallback_lock = None;
+        }
         self.queue_redraw_all();
     }
 
@@ -5043,33 +7298,283 @@ impl Niri {
         // Recompute the current pointer focus because we don't update it during animations.
         let current_focus = self.contents_under(pointer.current_location());
 
-        if let Some(output) = &new_focus.output {
-            if current_focus.output.as_ref() != Some(output) {
-                self.layout.focus_output(output);
+        // Only focus what the pointer has just moved onto.
+        let mut target = PointContents::default();
+        if new_focus.output.is_some() && current_focus.output != new_focus.output {
+            target.output.clone_from(&new_focus.output);
+        }
+        if ffm.mode == FocusFollowsMouseMode::All {
+            if new_focus.window.is_some() && current_focus.window != new_focus.window {
+                target.window.clone_from(&new_focus.window);
+            }
+            if new_focus.layer.is_some() && current_focus.layer != new_focus.layer {
+                target.layer.clone_from(&new_focus.layer);
             }
         }
 
-        if let Some(window) = &new_focus.window {
-            if current_focus.window.as_ref() != Some(window) {
-                if !self.layout.should_trigger_focus_follows_mouse_on(window) {
-                    return;
+        if target.output.is_none() && target.window.is_none() && target.layer.is_none() {
+            return;
+        }
+
+        match ffm.delay_ms {
+            Some(delay_ms) if delay_ms > 0 => {
+                self.start_focus_follows_mouse_timer(
+                    Duration::from_millis(u64::from(delay_ms)),
+                    target,
+                );
+            }
+            _ => self.focus_follows_mouse(ffm, &target),
+        }
+    }
+
+    /// Focuses the target after the delay, if the pointer is still over it by then.
+    fn start_focus_follows_mouse_timer(&mut self, delay: Duration, mut target: PointContents) {
+        if let Some(token) = self.focus_follows_mouse_timer.take() {
+            self.event_loop.remove(token);
+        }
+
+        let timer = Timer::from_duration(delay);
+        let token = self
+            .event_loop
+            .insert_source(timer, move |_, _, state| {
+                let niri = &mut state.niri;
+                niri.focus_follows_mouse_timer = None;
+
+                let Some(ffm) = niri.config.borrow().input.focus_follows_mouse else {
+                    return TimeoutAction::Drop;
+                };
+
+                let pointer = niri.seat.get_pointer().unwrap();
+                if pointer.is_grabbed() {
+                    return TimeoutAction::Drop;
                 }
 
-                if let Some(threshold) = ffm.max_scroll_amount {
-                    if self.layout.scroll_amount_to_activate(window) > threshold.0 {
-                        return;
-                    }
+                // Drop the parts of the target that the pointer has since left.
+                let under = niri.contents_under(pointer.current_location());
+                if target.output != under.output {
+                    target.output = None;
+                }
+                if target.window != under.window {
+                    target.window = None;
                 }
+                if target.layer != under.layer {
+                    target.layer = None;
+                }
+
+                niri.focus_follows_mouse(ffm, &target);
+                niri.queue_redraw_all();
+
+                TimeoutAction::Drop
+            })
+            .unwrap();
+        self.focus_follows_mouse_timer = Some(token);
+    }
+
+    fn focus_follows_mouse(&mut self, ffm: FocusFollowsMouse, target: &PointContents) {
+        self.focus_history
+            .set_reason(niri_ipc::FocusReason::FocusFollowsMouse);
 
-                self.layout.activate_window_without_raising(window);
-                self.layer_shell_on_demand_focus = None;
+        if let Some(output) = &target.output {
+            self.layout.focus_output(output);
+        }
+
+        if let Some(window) = &target.window {
+            if !self.layout.should_trigger_focus_follows_mouse_on(window) {
+                return;
+            }
+
+            if let Some(threshold) = ffm.max_scroll_amount {
+                if self.layout.scroll_amount_to_activate(window) > threshold.0 {
+                    return;
+                }
             }
+
+            self.layout.activate_window_without_raising(window);
+            self.layer_shell_on_demand_focus = None;
+        }
+
+        if let Some(layer) = &target.layer {
+            self.layer_shell_on_demand_focus = Some(layer.clone());
+        }
+    }
+
+    /// Shows a placeholder for a spawned application until it opens its window.
+    ///
+    /// Only applications with a desktop entry get a placeholder, since other programs may not
+    /// open a window at all. Finding the desktop entry and the icon reads from the disk, so it
+    /// happens on a separate thread.
+    pub fn add_startup_placeholder(&mut self, program: &str, token: &XdgActivationToken) {
+        if self.config.borrow().startup_placeholder.off {
+            return;
         }
 
-        if let Some(layer) = &new_focus.layer {
-            if current_focus.layer.as_ref() != Some(layer) {
-                self.layer_shell_on_demand_focus = Some(layer.clone());
+        // Reserve the token right away. If the window activates with it before the lookup is
+        // done, the token is gone by the time the placeholder would show up.
+        let id = StartupPlaceholderId::next();
+        self.startup_placeholders
+            .insert(token.as_str().to_owned(), id);
+
+        // The sender wakes up the event loop when dropped, which removes the source.
+        let (tx, rx) = calloop::channel::channel();
+        let mut found = false;
+        self.event_loop
+            .insert_source(rx, move |event, _, state| match event {
+                calloop::channel::Event::Msg(app) => {
+                    found = true;
+                    state.niri.show_startup_placeholder(id, app);
+                }
+                // No desktop entry, so no placeholder.
+                calloop::channel::Event::Closed if !found => {
+                    state.niri.startup_placeholders.retain(|_, x| *x != id);
+                }
+                calloop::channel::Event::Closed => (),
+            })
+            .unwrap();
+
+        let desktop_entries = self.desktop_entries.clone();
+        let program = program.to_owned();
+        let res = thread::Builder::new()
+            .name("Startup Placeholder Lookup".to_owned())
+            .spawn(move || {
+                let entry = desktop_entries.lock().unwrap().find(&program).cloned();
+                let Some(entry) = entry else {
+                    return;
+                };
+
+                let name = Path::new(&program)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                let app = StartupApp {
+                    name,
+                    icon: entry.icon.as_deref().and_then(find_icon),
+                    app_id: Some(entry.app_id),
+                };
+                let _ = tx.send(app);
+            });
+        if let Err(err) = res {
+            warn!("error spawning a thread to look up the desktop entry: {err:?}");
+        }
+    }
+
+    fn show_startup_placeholder(&mut self, id: StartupPlaceholderId, app: StartupApp) {
+        // The window may have shown up during the lookup.
+        if !self.startup_placeholders.values().any(|x| *x == id) {
+            return;
+        }
+
+        self.layout.add_startup_placeholder(id, app);
+
+        // Redraw for every step of the spinner until the window shows up or the timeout passes.
+        // The placeholder itself only becomes visible after a delay.
+        let created_at = get_monotonic_time();
+        self.event_loop
+            .insert_source(
+                Timer::from_duration(STARTUP_PLACEHOLDER_SHOW_DELAY),
+                move |_, _, state| {
+                    let niri = &mut state.niri;
+                    if !niri.layout.has_startup_placeholder(id) {
+                        niri.startup_placeholders.retain(|_, x| *x != id);
+                        return TimeoutAction::Drop;
+                    }
+
+                    if get_monotonic_time().saturating_sub(created_at)
+                        >= STARTUP_PLACEHOLDER_TIMEOUT
+                    {
+                        niri.layout.remove_startup_placeholder(id);
+                        niri.startup_placeholders.retain(|_, x| *x != id);
+                        niri.queue_redraw_all();
+                        return TimeoutAction::Drop;
+                    }
+
+                    niri.queue_redraw_all();
+                    TimeoutAction::ToDuration(STARTUP_PLACEHOLDER_SPINNER_STEP)
+                },
+            )
+            .unwrap();
+    }
+
+    /// Returns whether the activation token comes from the client that has the keyboard focus.
+    ///
+    /// Such activation normally follows the user clicking something in that client, whereas
+    /// unfocused clients requesting activation are trying to steal focus.
+    pub fn is_activation_user_initiated(&self, token_data: &XdgActivationTokenData) -> bool {
+        let Some(surface) = &token_data.surface else {
+            return false;
+        };
+        let Some(focus) = self.keyboard_focus.surface() else {
+            return false;
+        };
+
+        let client = surface.client();
+        client.is_some() && client == focus.client()
+    }
+
+    /// Converts the keyboard focus into its IPC representation.
+    fn focus_target(&self, focus: &KeyboardFocus) -> niri_ipc::FocusTarget {
+        match focus {
+            KeyboardFocus::Layout {
+                surface: Some(surface),
+            } => match self.layout.find_window_and_output(surface) {
+                Some((mapped, _)) => niri_ipc::FocusTarget::Window {
+                    id: mapped.id().get(),
+                },
+                None => niri_ipc::FocusTarget::Nothing,
+            },
+            KeyboardFocus::Layout { surface: None } => niri_ipc::FocusTarget::Nothing,
+            KeyboardFocus::LayerShell { surface } => {
+                let namespace = self.global_space.outputs().find_map(|output| {
+                    let layers = layer_map_for_output(output);
+                    let layer = layers.layer_for_surface(surface, WindowSurfaceType::TOPLEVEL);
+                    layer.map(|layer| layer.namespace().to_owned())
+                });
+                niri_ipc::FocusTarget::LayerShell {
+                    namespace: namespace.unwrap_or_default(),
+                }
             }
+            KeyboardFocus::LockScreen { .. } => niri_ipc::FocusTarget::LockScreen,
+            KeyboardFocus::ScreenshotUi => niri_ipc::FocusTarget::ScreenshotUi,
+        }
+    }
+
+    /// Returns the binds on cooldown, soonest to expire first.
+    pub fn bind_cooldowns(&self, comp_mod: CompositorMod) -> Vec<niri_ipc::BindCooldown> {
+        let now = get_monotonic_time();
+
+        let mut cooldowns: Vec<_> = self
+            .bind_cooldown_timers
+            .iter()
+            .map(|(key, (_, ends_at))| (key, ends_at.saturating_sub(now)))
+            .collect();
+        cooldowns.sort_by_key(|(_, remaining)| *remaining);
+
+        cooldowns
+            .into_iter()
+            .map(|(key, remaining)| niri_ipc::BindCooldown {
+                key: key_name(comp_mod, key),
+                remaining_ms: u64::try_from(remaining.as_millis()).unwrap_or(u64::MAX),
+            })
+            .collect()
+    }
+
+    pub fn clear_bind_cooldowns(&mut self) {
+        for (_, (token, _)) in self.bind_cooldown_timers.drain() {
+            self.event_loop.remove(token);
+        }
+    }
+
+    /// Explains where the keyboard focus is and how it got there.
+    pub fn explain_focus(&self) -> niri_ipc::FocusExplanation {
+        niri_ipc::FocusExplanation {
+            focus: self.focus_target(&self.keyboard_focus),
+            is_locked: self.is_locked(),
+            is_screenshot_ui_open: self.screenshot_ui.is_open(),
+            on_demand_layer: self
+                .layer_shell_on_demand_focus
+                .as_ref()
+                .map(|layer| layer.namespace().to_owned()),
+            layout_focus: self.layout.focus().map(|mapped| mapped.id().get()),
+            history: self.focus_history.transitions().cloned().collect(),
         }
     }
 
@@ -5231,12 +7736,86 @@ impl Niri {
             .unwrap();
         self.pointer_inactivity_timer = Some(token);
     }
+
+    pub fn start_hotkey_overlay_mod_hold_timer(&mut self) {
+        self.cancel_hotkey_overlay_mod_hold_timer();
+
+        let Some(delay_ms) = self.config.borrow().hotkey_overlay.show_on_mod_hold_ms else {
+            return;
+        };
+
+        let duration = Duration::from_millis(u64::from(delay_ms));
+        let timer = Timer::from_duration(duration);
+        let token = self
+            .event_loop
+            .insert_source(timer, move |_, _, state| {
+                state.niri.hotkey_overlay_mod_hold_timer = None;
+                if state.niri.hotkey_overlay.show_on_mod_hold() {
+                    state.niri.queue_redraw_all();
+                }
+
+                TimeoutAction::Drop
+            })
+            .unwrap();
+        self.hotkey_overlay_mod_hold_timer = Some(token);
+    }
+
+    pub fn cancel_hotkey_overlay_mod_hold_timer(&mut self) {
+        if let Some(token) = self.hotkey_overlay_mod_hold_timer.take() {
+            self.event_loop.remove(token);
+        }
+    }
+}
+
+/// Picks the name matched by most windows, preferring earlier rules on ties.
+fn workspace_auto_name<'a>(
+    rules: &[WorkspaceAutoNameRule],
+    windows: impl Iterator<Item = &'a Mapped>,
+) -> Option<String> {
+    // (first matching rule index, name, number of windows)
+    let mut counts: Vec<(usize, &str, usize)> = Vec::new();
+
+    for mapped in windows {
+        let matched = with_toplevel_role(mapped.toplevel(), |role| {
+            rules.iter().position(|rule| {
+                let app_id_matches = rule.app_id.as_ref().map_or(true, |re| {
+                    role.app_id
+                        .as_ref()
+                        .is_some_and(|app_id| re.0.is_match(app_id))
+                });
+                let title_matches = rule.title.as_ref().map_or(true, |re| {
+                    role.title
+                        .as_ref()
+                        .is_some_and(|title| re.0.is_match(title))
+                });
+                app_id_matches && title_matches
+            })
+        });
+        let Some(idx) = matched else {
+            continue;
+        };
+
+        let name = rules[idx].name.as_str();
+        match counts.iter_mut().find(|(_, n, _)| *n == name) {
+            Some((first_idx, _, count)) => {
+                *first_idx = (*first_idx).min(idx);
+                *count += 1;
+            }
+            None => counts.push((idx, name, 1)),
+        }
+    }
+
+    counts
+        .into_iter()
+        .max_by_key(|(idx, _, count)| (*count, Reverse(*idx)))
+        .map(|(_, name, _)| name.to_owned())
 }
 
 pub struct NewClient {
     pub client: UnixStream,
     pub restricted: bool,
     pub credentials_unknown: bool,
+    pub is_xwayland_satellite: bool,
 }
 
 pub struct ClientState {
@@ -5247,6 +7826,8 @@ pub struct ClientState {
     pub restricted: bool,
     /// We cannot retrieve this client's socket credentials.
     pub credentials_unknown: bool,
+    /// Whether this client is xwayland-satellite, connected to its dedicated socket.
+    pub is_xwayland_satellite: bool,
 }
 
 impl ClientData for ClientState {
@@ -0,0 +1,77 @@
+use smithay::reexports::wayland_server::protocol::wl_fixes::{self, WlFixes};
+use smithay::reexports::wayland_server::{
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+};
+
+pub const VERSION: u32 = 1;
+
+pub struct FixesState {}
+
+impl FixesState {
+    pub fn new<D>(display: &DisplayHandle) -> Self
+    where
+        D: GlobalDispatch<WlFixes, ()>,
+        D: Dispatch<WlFixes, ()>,
+        D: 'static,
+    {
+        display.create_global::<D, WlFixes, _>(VERSION, ());
+
+        Self {}
+    }
+}
+
+impl<D> GlobalDispatch<WlFixes, (), D> for FixesState
+where
+    D: GlobalDispatch<WlFixes, ()>,
+    D: Dispatch<WlFixes, ()>,
+    D: 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<WlFixes>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl<D> Dispatch<WlFixes, (), D> for FixesState
+where
+    D: Dispatch<WlFixes, ()>,
+    D: 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _resource: &WlFixes,
+        request: <WlFixes as Resource>::Request,
+        _data: &(),
+        dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            wl_fixes::Request::Destroy => (),
+            wl_fixes::Request::DestroyRegistry { registry } => {
+                if let Err(err) = dhandle.backend_handle().destroy_object::<D>(&registry.id()) {
+                    warn!("error destroying registry: {err:?}");
+                }
+            }
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! delegate_fixes {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_server::protocol::wl_fixes::WlFixes: ()
+        ] => $crate::protocols::fixes::FixesState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_server::protocol::wl_fixes::WlFixes: ()
+        ] => $crate::protocols::fixes::FixesState);
+    };
+}
@@ -21,7 +21,7 @@ use zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1;
 use crate::niri::State;
 use crate::utils::with_toplevel_role;
 
-const VERSION: u32 = 3;
+pub const VERSION: u32 = 3;
 
 pub struct ForeignToplevelManagerState {
     display: DisplayHandle,
@@ -375,8 +375,10 @@ where
         let surface = surface.clone();
 
         match request {
+            // Maximizing is unimplemented for xdg-shell windows as well.
             zwlr_foreign_toplevel_handle_v1::Request::SetMaximized => (),
             zwlr_foreign_toplevel_handle_v1::Request::UnsetMaximized => (),
+            // There's no minimizing in niri, windows always stay in the layout.
             zwlr_foreign_toplevel_handle_v1::Request::SetMinimized => (),
             zwlr_foreign_toplevel_handle_v1::Request::UnsetMinimized => (),
             zwlr_foreign_toplevel_handle_v1::Request::Activate { .. } => {
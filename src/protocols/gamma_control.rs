@@ -14,7 +14,7 @@ use wayland_protocols_wlr::gamma_control::v1::server::{
 use zwlr_gamma_control_manager_v1::ZwlrGammaControlManagerV1;
 use zwlr_gamma_control_v1::ZwlrGammaControlV1;
 
-const VERSION: u32 = 1;
+pub const VERSION: u32 = 1;
 
 pub struct GammaControlManagerState {
     // Active gamma controls only. Failed ones are removed.
@@ -55,6 +55,11 @@ impl GammaControlManagerState {
         }
     }
 
+    /// Returns whether a client currently controls the gamma of the output.
+    pub fn has_gamma_control(&self, output: &Output) -> bool {
+        self.gamma_controls.contains_key(output)
+    }
+
     pub fn output_removed(&mut self, output: &Output) {
         if let Some(gamma_control) = self.gamma_controls.remove(output) {
             gamma_control.failed();
@@ -0,0 +1,658 @@
+//! ext-image-copy-capture, along with the output image capture source.
+//!
+//! Only output sources are supported, there are no toplevel sources. Cursor capture is
+//! unsupported too: capture sessions created from a cursor session are stopped right away, so
+//! their frames fail instead of never arriving. Clients can ask for the cursor to be painted into
+//! the output capture instead.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use smithay::backend::renderer::damage::OutputDamageTracker;
+use smithay::output::Output;
+use smithay::reexports::wayland_protocols::ext::image_capture_source::v1::server::ext_image_capture_source_v1::{
+    self, ExtImageCaptureSourceV1,
+};
+use smithay::reexports::wayland_protocols::ext::image_capture_source::v1::server::ext_output_image_capture_source_manager_v1::{
+    self, ExtOutputImageCaptureSourceManagerV1,
+};
+use smithay::reexports::wayland_protocols::ext::image_copy_capture::v1::server::ext_image_copy_capture_cursor_session_v1::{
+    self, ExtImageCopyCaptureCursorSessionV1,
+};
+use smithay::reexports::wayland_protocols::ext::image_copy_capture::v1::server::ext_image_copy_capture_frame_v1::{
+    self, ExtImageCopyCaptureFrameV1, FailureReason,
+};
+use smithay::reexports::wayland_protocols::ext::image_copy_capture::v1::server::ext_image_copy_capture_manager_v1::{
+    self, ExtImageCopyCaptureManagerV1, Options,
+};
+use smithay::reexports::wayland_protocols::ext::image_copy_capture::v1::server::ext_image_copy_capture_session_v1::{
+    self, ExtImageCopyCaptureSessionV1,
+};
+use smithay::reexports::wayland_server::backend::ClientId;
+use smithay::reexports::wayland_server::protocol::wl_buffer::WlBuffer;
+use smithay::reexports::wayland_server::protocol::wl_shm::Format;
+use smithay::reexports::wayland_server::{
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+};
+use smithay::utils::{Buffer, Physical, Rectangle, Size, Transform};
+use smithay::wayland::shm;
+use wayland_backend::protocol::WEnum;
+
+use crate::utils::get_monotonic_time;
+
+pub const VERSION: u32 = 1;
+pub const OUTPUT_SOURCE_VERSION: u32 = 1;
+
+#[derive(Default)]
+pub struct ImageCopyCaptureState {
+    sessions: HashMap<ExtImageCopyCaptureSessionV1, Session>,
+}
+
+pub struct ImageCopyCaptureGlobalData {
+    filter: Box<dyn for<'c> Fn(&'c Client) -> bool + Send + Sync>,
+}
+
+/// Capture session of an output.
+pub struct Session {
+    output: Output,
+    paint_cursors: bool,
+    /// Buffer size last sent to the client.
+    buffer_size: Size<i32, Physical>,
+    damage_tracker: OutputDamageTracker,
+    /// Frame object of the session, if any.
+    frame: Option<ExtImageCopyCaptureFrameV1>,
+    /// Frame waiting for the output to be rendered with damage.
+    pending: Option<CaptureFrame>,
+}
+
+/// Data of a capture source.
+pub struct ImageCaptureSourceData {
+    output: Option<Output>,
+}
+
+/// Data of a frame object.
+pub struct FrameData {
+    session: ExtImageCopyCaptureSessionV1,
+    inner: Mutex<FrameInner>,
+}
+
+#[derive(Default)]
+struct FrameInner {
+    buffer: Option<WlBuffer>,
+    captured: bool,
+}
+
+/// Frame whose capture was requested.
+pub struct CaptureFrame {
+    frame: ExtImageCopyCaptureFrameV1,
+    buffer: WlBuffer,
+    submitted: bool,
+}
+
+impl ImageCopyCaptureState {
+    pub fn new<D, F>(display: &DisplayHandle, filter: F) -> Self
+    where
+        D: GlobalDispatch<ExtImageCopyCaptureManagerV1, ImageCopyCaptureGlobalData>,
+        D: GlobalDispatch<ExtOutputImageCaptureSourceManagerV1, ImageCopyCaptureGlobalData>,
+        D: Dispatch<ExtImageCopyCaptureManagerV1, ()>,
+        D: Dispatch<ExtOutputImageCaptureSourceManagerV1, ()>,
+        D: Dispatch<ExtImageCaptureSourceV1, ImageCaptureSourceData>,
+        D: Dispatch<ExtImageCopyCaptureSessionV1, ()>,
+        D: Dispatch<ExtImageCopyCaptureCursorSessionV1, ()>,
+        D: Dispatch<ExtImageCopyCaptureFrameV1, FrameData>,
+        D: ImageCopyCaptureHandler,
+        D: 'static,
+        F: for<'c> Fn(&'c Client) -> bool + Send + Sync + Clone + 'static,
+    {
+        let global_data = ImageCopyCaptureGlobalData {
+            filter: Box::new(filter.clone()),
+        };
+        display.create_global::<D, ExtImageCopyCaptureManagerV1, _>(VERSION, global_data);
+
+        let global_data = ImageCopyCaptureGlobalData {
+            filter: Box::new(filter),
+        };
+        display.create_global::<D, ExtOutputImageCaptureSourceManagerV1, _>(
+            OUTPUT_SOURCE_VERSION,
+            global_data,
+        );
+
+        Self::default()
+    }
+
+    pub fn sessions_mut(
+        &mut self,
+    ) -> impl Iterator<Item = (&ExtImageCopyCaptureSessionV1, &mut Session)> {
+        self.sessions.iter_mut()
+    }
+
+    /// Stops all sessions capturing the output.
+    pub fn remove_output(&mut self, output: &Output) {
+        self.sessions.retain(|session, state| {
+            if state.output != *output {
+                return true;
+            }
+
+            if let Some(capture) = state.pending.take() {
+                capture.fail(FailureReason::Stopped);
+            }
+            session.stopped();
+            false
+        });
+    }
+}
+
+impl Session {
+    pub fn output(&self) -> &Output {
+        &self.output
+    }
+
+    pub fn paint_cursors(&self) -> bool {
+        self.paint_cursors
+    }
+
+    /// Takes the frame waiting to be captured.
+    ///
+    /// If the output size changed since the constraints were last sent, fails the pending frame
+    /// and sends the new buffer constraints instead.
+    pub fn take_pending(&mut self, session: &ExtImageCopyCaptureSessionV1) -> Option<CaptureFrame> {
+        let capture = self.pending.take()?;
+
+        let buffer_size = self.output.current_mode().unwrap().size;
+        if buffer_size != self.buffer_size {
+            self.buffer_size = buffer_size;
+            send_constraints(session, buffer_size);
+            capture.fail(FailureReason::BufferConstraints);
+            return None;
+        }
+
+        Some(capture)
+    }
+
+    /// Puts back a frame that has to wait for damage.
+    pub fn set_pending(&mut self, capture: CaptureFrame) {
+        self.pending = Some(capture);
+    }
+
+    pub fn damage_tracker_mut(&mut self) -> &mut OutputDamageTracker {
+        &mut self.damage_tracker
+    }
+}
+
+impl CaptureFrame {
+    pub fn buffer(&self) -> &WlBuffer {
+        &self.buffer
+    }
+
+    pub fn fail(mut self, reason: FailureReason) {
+        self.frame.failed(reason);
+        self.submitted = true;
+    }
+
+    /// Submits the captured contents.
+    pub fn submit(mut self, transform: Transform, damage: &[Rectangle<i32, Buffer>]) {
+        self.frame.transform(transform.into());
+
+        for Rectangle { loc, size } in damage {
+            self.frame.damage(loc.x, loc.y, size.w, size.h);
+        }
+
+        let timestamp = get_monotonic_time();
+        let tv_sec_hi = (timestamp.as_secs() >> 32) as u32;
+        let tv_sec_lo = (timestamp.as_secs() & 0xFFFFFFFF) as u32;
+        let tv_nsec = timestamp.subsec_nanos();
+        self.frame.presentation_time(tv_sec_hi, tv_sec_lo, tv_nsec);
+
+        self.frame.ready();
+        self.submitted = true;
+    }
+}
+
+impl Drop for CaptureFrame {
+    fn drop(&mut self) {
+        if !self.submitted {
+            self.frame.failed(FailureReason::Unknown);
+        }
+    }
+}
+
+/// Handler trait for ext-image-copy-capture.
+pub trait ImageCopyCaptureHandler {
+    fn image_copy_capture_state(&mut self) -> &mut ImageCopyCaptureState;
+
+    /// A frame capture was requested on this output.
+    ///
+    /// The frame should be captured on the next redraw of the output with damage.
+    fn capture_requested(&mut self, output: &Output);
+}
+
+fn send_constraints(session: &ExtImageCopyCaptureSessionV1, buffer_size: Size<i32, Physical>) {
+    session.buffer_size(buffer_size.w as u32, buffer_size.h as u32);
+    // Only SHM buffers are supported for now.
+    session.shm_format(Format::Xrgb8888);
+    session.done();
+}
+
+impl<D> GlobalDispatch<ExtImageCopyCaptureManagerV1, ImageCopyCaptureGlobalData, D>
+    for ImageCopyCaptureState
+where
+    D: GlobalDispatch<ExtImageCopyCaptureManagerV1, ImageCopyCaptureGlobalData>,
+    D: Dispatch<ExtImageCopyCaptureManagerV1, ()>,
+    D: 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _display: &DisplayHandle,
+        _client: &Client,
+        manager: New<ExtImageCopyCaptureManagerV1>,
+        _global_data: &ImageCopyCaptureGlobalData,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(manager, ());
+    }
+
+    fn can_view(client: Client, global_data: &ImageCopyCaptureGlobalData) -> bool {
+        (global_data.filter)(&client)
+    }
+}
+
+impl<D> GlobalDispatch<ExtOutputImageCaptureSourceManagerV1, ImageCopyCaptureGlobalData, D>
+    for ImageCopyCaptureState
+where
+    D: GlobalDispatch<ExtOutputImageCaptureSourceManagerV1, ImageCopyCaptureGlobalData>,
+    D: Dispatch<ExtOutputImageCaptureSourceManagerV1, ()>,
+    D: 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _display: &DisplayHandle,
+        _client: &Client,
+        manager: New<ExtOutputImageCaptureSourceManagerV1>,
+        _global_data: &ImageCopyCaptureGlobalData,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(manager, ());
+    }
+
+    fn can_view(client: Client, global_data: &ImageCopyCaptureGlobalData) -> bool {
+        (global_data.filter)(&client)
+    }
+}
+
+impl<D> Dispatch<ExtOutputImageCaptureSourceManagerV1, (), D> for ImageCopyCaptureState
+where
+    D: Dispatch<ExtOutputImageCaptureSourceManagerV1, ()>,
+    D: Dispatch<ExtImageCaptureSourceV1, ImageCaptureSourceData>,
+    D: 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _manager: &ExtOutputImageCaptureSourceManagerV1,
+        request: ext_output_image_capture_source_manager_v1::Request,
+        _data: &(),
+        _display: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            ext_output_image_capture_source_manager_v1::Request::CreateSource {
+                source,
+                output,
+            } => {
+                let output = Output::from_resource(&output);
+                data_init.init(source, ImageCaptureSourceData { output });
+            }
+            ext_output_image_capture_source_manager_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<ExtImageCaptureSourceV1, ImageCaptureSourceData, D> for ImageCopyCaptureState
+where
+    D: Dispatch<ExtImageCaptureSourceV1, ImageCaptureSourceData>,
+    D: 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _source: &ExtImageCaptureSourceV1,
+        _request: ext_image_capture_source_v1::Request,
+        _data: &ImageCaptureSourceData,
+        _display: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+    }
+}
+
+impl<D> Dispatch<ExtImageCopyCaptureManagerV1, (), D> for ImageCopyCaptureState
+where
+    D: Dispatch<ExtImageCopyCaptureManagerV1, ()>,
+    D: Dispatch<ExtImageCopyCaptureSessionV1, ()>,
+    D: Dispatch<ExtImageCopyCaptureCursorSessionV1, ()>,
+    D: ImageCopyCaptureHandler,
+    D: 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        manager: &ExtImageCopyCaptureManagerV1,
+        request: ext_image_copy_capture_manager_v1::Request,
+        _data: &(),
+        _display: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            ext_image_copy_capture_manager_v1::Request::CreateSession {
+                session,
+                source,
+                options,
+            } => {
+                let options = match options {
+                    WEnum::Value(options) => options,
+                    WEnum::Unknown(_) => {
+                        manager.post_error(
+                            ext_image_copy_capture_manager_v1::Error::InvalidOption,
+                            "invalid options",
+                        );
+                        return;
+                    }
+                };
+
+                let session = data_init.init(session, ());
+
+                let output = source
+                    .data::<ImageCaptureSourceData>()
+                    .and_then(|data| data.output.clone());
+                let Some(output) = output else {
+                    trace!("image copy capture client requested a missing output");
+                    session.stopped();
+                    return;
+                };
+
+                let buffer_size = output.current_mode().unwrap().size;
+                send_constraints(&session, buffer_size);
+
+                state.image_copy_capture_state().sessions.insert(
+                    session,
+                    Session {
+                        output,
+                        paint_cursors: options.contains(Options::PaintCursors),
+                        buffer_size,
+                        damage_tracker: OutputDamageTracker::new((0, 0), 1.0, Transform::Normal),
+                        frame: None,
+                        pending: None,
+                    },
+                );
+            }
+            ext_image_copy_capture_manager_v1::Request::CreatePointerCursorSession {
+                session,
+                ..
+            } => {
+                // The object must be created, but its capture sessions are stopped right away.
+                debug!("image copy capture client requested unsupported cursor capture");
+                data_init.init(session, ());
+            }
+            ext_image_copy_capture_manager_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<ExtImageCopyCaptureCursorSessionV1, (), D> for ImageCopyCaptureState
+where
+    D: Dispatch<ExtImageCopyCaptureCursorSessionV1, ()>,
+    D: Dispatch<ExtImageCopyCaptureSessionV1, ()>,
+    D: 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _cursor_session: &ExtImageCopyCaptureCursorSessionV1,
+        request: ext_image_copy_capture_cursor_session_v1::Request,
+        _data: &(),
+        _display: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            ext_image_copy_capture_cursor_session_v1::Request::GetCaptureSession { session } => {
+                // Cursor capture is unsupported, the cursor can be painted into the output
+                // capture instead. Frames on the stopped session fail with the stopped reason.
+                let session = data_init.init(session, ());
+                session.stopped();
+            }
+            ext_image_copy_capture_cursor_session_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<ExtImageCopyCaptureSessionV1, (), D> for ImageCopyCaptureState
+where
+    D: Dispatch<ExtImageCopyCaptureSessionV1, ()>,
+    D: Dispatch<ExtImageCopyCaptureFrameV1, FrameData>,
+    D: ImageCopyCaptureHandler,
+    D: 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        session: &ExtImageCopyCaptureSessionV1,
+        request: ext_image_copy_capture_session_v1::Request,
+        _data: &(),
+        _display: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            ext_image_copy_capture_session_v1::Request::CreateFrame { frame } => {
+                let frame = data_init.init(
+                    frame,
+                    FrameData {
+                        session: session.clone(),
+                        inner: Mutex::new(FrameInner::default()),
+                    },
+                );
+
+                let Some(state) = state.image_copy_capture_state().sessions.get_mut(session) else {
+                    // The session was stopped.
+                    frame.failed(FailureReason::Stopped);
+                    return;
+                };
+
+                if state.frame.as_ref().is_some_and(|frame| frame.is_alive()) {
+                    session.post_error(
+                        ext_image_copy_capture_session_v1::Error::DuplicateFrame,
+                        "session already has a frame",
+                    );
+                    return;
+                }
+
+                state.frame = Some(frame);
+            }
+            ext_image_copy_capture_session_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut D,
+        _client: ClientId,
+        session: &ExtImageCopyCaptureSessionV1,
+        _data: &(),
+    ) {
+        state.image_copy_capture_state().sessions.remove(session);
+    }
+}
+
+impl<D> Dispatch<ExtImageCopyCaptureFrameV1, FrameData, D> for ImageCopyCaptureState
+where
+    D: Dispatch<ExtImageCopyCaptureFrameV1, FrameData>,
+    D: ImageCopyCaptureHandler,
+    D: 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        frame: &ExtImageCopyCaptureFrameV1,
+        request: ext_image_copy_capture_frame_v1::Request,
+        data: &FrameData,
+        _display: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        let mut inner = data.inner.lock().unwrap();
+
+        match request {
+            ext_image_copy_capture_frame_v1::Request::AttachBuffer { buffer } => {
+                if inner.captured {
+                    frame.post_error(
+                        ext_image_copy_capture_frame_v1::Error::AlreadyCaptured,
+                        "frame was already captured",
+                    );
+                    return;
+                }
+
+                inner.buffer = Some(buffer);
+            }
+            ext_image_copy_capture_frame_v1::Request::DamageBuffer {
+                x,
+                y,
+                width,
+                height,
+            } => {
+                if inner.captured {
+                    frame.post_error(
+                        ext_image_copy_capture_frame_v1::Error::AlreadyCaptured,
+                        "frame was already captured",
+                    );
+                    return;
+                }
+
+                if x < 0 || y < 0 || width <= 0 || height <= 0 {
+                    frame.post_error(
+                        ext_image_copy_capture_frame_v1::Error::InvalidBufferDamage,
+                        "invalid buffer damage",
+                    );
+                    return;
+                }
+
+                // We always redraw the whole buffer, so there's no need to track buffer damage.
+            }
+            ext_image_copy_capture_frame_v1::Request::Capture => {
+                if inner.captured {
+                    frame.post_error(
+                        ext_image_copy_capture_frame_v1::Error::AlreadyCaptured,
+                        "frame was already captured",
+                    );
+                    return;
+                }
+
+                let Some(buffer) = inner.buffer.clone() else {
+                    frame.post_error(
+                        ext_image_copy_capture_frame_v1::Error::NoBuffer,
+                        "no buffer attached",
+                    );
+                    return;
+                };
+
+                inner.captured = true;
+                drop(inner);
+
+                let Some(session) = state
+                    .image_copy_capture_state()
+                    .sessions
+                    .get_mut(&data.session)
+                else {
+                    frame.failed(FailureReason::Stopped);
+                    return;
+                };
+
+                let size = session.buffer_size;
+                let is_valid = shm::with_buffer_contents(&buffer, |_, shm_len, buffer_data| {
+                    buffer_data.format == Format::Xrgb8888
+                        && buffer_data.width == size.w
+                        && buffer_data.height == size.h
+                        && buffer_data.stride == size.w * 4
+                        && shm_len == buffer_data.stride as usize * buffer_data.height as usize
+                })
+                .unwrap_or(false);
+                if !is_valid {
+                    frame.failed(FailureReason::BufferConstraints);
+                    return;
+                }
+
+                session.pending = Some(CaptureFrame {
+                    frame: frame.clone(),
+                    buffer,
+                    submitted: false,
+                });
+
+                let output = session.output.clone();
+                state.capture_requested(&output);
+            }
+            ext_image_copy_capture_frame_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut D,
+        _client: ClientId,
+        frame: &ExtImageCopyCaptureFrameV1,
+        data: &FrameData,
+    ) {
+        let Some(session) = state
+            .image_copy_capture_state()
+            .sessions
+            .get_mut(&data.session)
+        else {
+            return;
+        };
+
+        if session.pending.as_ref().is_some_and(|p| p.frame == *frame) {
+            // The frame is gone, so there's no one to notify.
+            let mut capture = session.pending.take().unwrap();
+            capture.submitted = true;
+        }
+        if session.frame.as_ref() == Some(frame) {
+            session.frame = None;
+        }
+    }
+}
+
+#[allow(missing_docs)]
+#[macro_export]
+macro_rules! delegate_image_copy_capture {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::ext::image_copy_capture::v1::server::ext_image_copy_capture_manager_v1::ExtImageCopyCaptureManagerV1: $crate::protocols::image_copy_capture::ImageCopyCaptureGlobalData
+        ] => $crate::protocols::image_copy_capture::ImageCopyCaptureState);
+
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::ext::image_capture_source::v1::server::ext_output_image_capture_source_manager_v1::ExtOutputImageCaptureSourceManagerV1: $crate::protocols::image_copy_capture::ImageCopyCaptureGlobalData
+        ] => $crate::protocols::image_copy_capture::ImageCopyCaptureState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::ext::image_copy_capture::v1::server::ext_image_copy_capture_manager_v1::ExtImageCopyCaptureManagerV1: ()
+        ] => $crate::protocols::image_copy_capture::ImageCopyCaptureState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::ext::image_capture_source::v1::server::ext_output_image_capture_source_manager_v1::ExtOutputImageCaptureSourceManagerV1: ()
+        ] => $crate::protocols::image_copy_capture::ImageCopyCaptureState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::ext::image_capture_source::v1::server::ext_image_capture_source_v1::ExtImageCaptureSourceV1: $crate::protocols::image_copy_capture::ImageCaptureSourceData
+        ] => $crate::protocols::image_copy_capture::ImageCopyCaptureState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::ext::image_copy_capture::v1::server::ext_image_copy_capture_session_v1::ExtImageCopyCaptureSessionV1: ()
+        ] => $crate::protocols::image_copy_capture::ImageCopyCaptureState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::ext::image_copy_capture::v1::server::ext_image_copy_capture_cursor_session_v1::ExtImageCopyCaptureCursorSessionV1: ()
+        ] => $crate::protocols::image_copy_capture::ImageCopyCaptureState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::ext::image_copy_capture::v1::server::ext_image_copy_capture_frame_v1::ExtImageCopyCaptureFrameV1: $crate::protocols::image_copy_capture::FrameData
+        ] => $crate::protocols::image_copy_capture::ImageCopyCaptureState);
+    };
+}
@@ -1,7 +1,10 @@
+pub mod fixes;
 pub mod foreign_toplevel;
 pub mod gamma_control;
+pub mod image_copy_capture;
 pub mod mutter_x11_interop;
 pub mod output_management;
+pub mod registry;
 pub mod screencopy;
 pub mod virtual_pointer;
 
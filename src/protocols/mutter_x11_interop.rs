@@ -5,7 +5,7 @@ use smithay::reexports::wayland_server::{
 
 use super::raw::mutter_x11_interop::v1::server::mutter_x11_interop;
 
-const VERSION: u32 = 1;
+pub const VERSION: u32 = 1;
 
 pub struct MutterX11InteropManagerState {}
 
@@ -24,7 +24,7 @@ use crate::backend::OutputId;
 use crate::niri::State;
 use crate::utils::ipc_transform_to_smithay;
 
-const VERSION: u32 = 4;
+pub const VERSION: u32 = 4;
 
 #[derive(Debug)]
 struct ClientData {
@@ -0,0 +1,135 @@
+//! Registry of the Wayland protocol globals advertised by niri.
+//!
+//! Smithay creates most globals internally and doesn't tell us their versions, so this list is
+//! maintained by hand. It is the place to audit when updating Smithay or bumping the version of
+//! one of our own protocol implementations: every global created in `Niri::new()` and in the
+//! backends must have a matching entry here.
+
+use super::{
+    fixes, foreign_toplevel, gamma_control, image_copy_capture, mutter_x11_interop,
+    output_management, screencopy, virtual_pointer,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProtocolGlobal {
+    /// Interface name of the global.
+    pub interface: &'static str,
+    /// Highest version of the global that we advertise.
+    pub version: u32,
+    /// Whether the global is hidden from sandboxed clients.
+    pub privileged: bool,
+    /// Whether the global only exists on the TTY backend.
+    pub tty_only: bool,
+}
+
+const fn global(interface: &'static str, version: u32) -> ProtocolGlobal {
+    ProtocolGlobal {
+        interface,
+        version,
+        privileged: false,
+        tty_only: false,
+    }
+}
+
+const fn privileged(interface: &'static str, version: u32) -> ProtocolGlobal {
+    ProtocolGlobal {
+        privileged: true,
+        ..global(interface, version)
+    }
+}
+
+const fn tty_only(global: ProtocolGlobal) -> ProtocolGlobal {
+    ProtocolGlobal {
+        tty_only: true,
+        ..global
+    }
+}
+
+pub const PROTOCOL_GLOBALS: &[ProtocolGlobal] = &[
+    // Core protocol.
+    global("wl_compositor", 6),
+    global("wl_subcompositor", 1),
+    global("wl_shm", 1),
+    global("wl_seat", 9),
+    global("wl_output", 4),
+    global("wl_data_device_manager", 3),
+    global("wl_fixes", fixes::VERSION),
+    // wayland-protocols.
+    global("xdg_wm_base", 6),
+    global("zxdg_decoration_manager_v1", 1),
+    global("zxdg_output_manager_v1", 3),
+    global("zwp_linux_dmabuf_v1", 5),
+    global("wp_fractional_scale_manager_v1", 1),
+    global("zwp_tablet_manager_v2", 1),
+    global("zwp_pointer_gestures_v1", 3),
+    global("zwp_relative_pointer_manager_v1", 1),
+    global("zwp_pointer_constraints_v1", 1),
+    global("ext_idle_notifier_v1", 1),
+    global("zwp_idle_inhibit_manager_v1", 1),
+    global("zwp_primary_selection_device_manager_v1", 1),
+    global("wp_presentation", 1),
+    privileged("wp_security_context_manager_v1", 1),
+    global("zwp_text_input_manager_v3", 1),
+    global("zwp_keyboard_shortcuts_inhibit_manager_v1", 1),
+    global("wp_viewporter", 1),
+    global("zxdg_exporter_v2", 1),
+    global("zxdg_importer_v2", 1),
+    global("xdg_activation_v1", 1),
+    global("wp_cursor_shape_manager_v1", 1),
+    privileged("ext_session_lock_manager_v1", 1),
+    privileged(
+        "ext_output_image_capture_source_manager_v1",
+        image_copy_capture::OUTPUT_SOURCE_VERSION,
+    ),
+    privileged(
+        "ext_image_copy_capture_manager_v1",
+        image_copy_capture::VERSION,
+    ),
+    tty_only(global("wp_drm_lease_device_v1", 1)),
+    // wlroots protocols.
+    privileged("zwlr_layer_shell_v1", 4),
+    privileged("zwlr_data_control_manager_v1", 2),
+    privileged(
+        "zwlr_foreign_toplevel_manager_v1",
+        foreign_toplevel::VERSION,
+    ),
+    privileged("zwlr_output_manager_v1", output_management::VERSION),
+    privileged("zwlr_screencopy_manager_v1", screencopy::VERSION),
+    privileged("zwlr_virtual_pointer_manager_v1", virtual_pointer::VERSION),
+    tty_only(privileged(
+        "zwlr_gamma_control_manager_v1",
+        gamma_control::VERSION,
+    )),
+    // Input method protocols.
+    privileged("zwp_input_method_manager_v2", 1),
+    privileged("zwp_virtual_keyboard_manager_v1", 1),
+    // Other protocols.
+    global("org_kde_kwin_server_decoration_manager", 1),
+    global("mutter_x11_interop", mutter_x11_interop::VERSION),
+];
+
+/// Returns the globals that exist on the current backend.
+pub fn protocol_globals(is_tty: bool) -> impl Iterator<Item = &'static ProtocolGlobal> {
+    PROTOCOL_GLOBALS
+        .iter()
+        .filter(move |g| is_tty || !g.tty_only)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn no_duplicate_globals() {
+        let mut seen = HashSet::new();
+        for global in PROTOCOL_GLOBALS {
+            assert!(
+                seen.insert(global.interface),
+                "{} is duplicated",
+                global.interface
+            );
+        }
+    }
+}
@@ -27,7 +27,7 @@ use smithay::wayland::{dmabuf, shm};
 
 use crate::utils::get_monotonic_time;
 
-const VERSION: u32 = 3;
+pub const VERSION: u32 = 3;
 
 pub struct ScreencopyQueue {
     damage_tracker: OutputDamageTracker,
@@ -21,7 +21,7 @@ use wayland_protocols_wlr::virtual_pointer::v1::server::{
 use zwlr_virtual_pointer_manager_v1::ZwlrVirtualPointerManagerV1;
 use zwlr_virtual_pointer_v1::ZwlrVirtualPointerV1;
 
-const VERSION: u32 = 2;
+pub const VERSION: u32 = 2;
 
 pub struct VirtualPointerManagerState {
     virtual_pointers: HashSet<ZwlrVirtualPointerV1>,
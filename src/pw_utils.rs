@@ -2,10 +2,11 @@ use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::io::Cursor;
 use std::iter::zip;
-use std::mem;
+use std::mem::{self, size_of};
 use std::os::fd::{AsFd, AsRawFd, BorrowedFd};
 use std::rc::Rc;
 use std::time::Duration;
+use std::{ptr, slice};
 
 use anyhow::Context as _;
 use calloop::timer::{TimeoutAction, Timer};
@@ -40,7 +41,7 @@ use smithay::output::{Output, OutputModeSource, WeakOutput};
 use smithay::reexports::calloop::generic::Generic;
 use smithay::reexports::calloop::{Interest, LoopHandle, Mode, PostAction};
 use smithay::reexports::gbm::Modifier;
-use smithay::utils::{Physical, Scale, Size, Transform};
+use smithay::utils::{Physical, Point, Scale, Size, Transform};
 use zbus::object_server::SignalEmitter;
 
 use crate::dbus::mutter_screen_cast::{self, CursorMode};
@@ -51,6 +52,11 @@ use crate::utils::get_monotonic_time;
 // Give a 0.1 ms allowance for presentation time errors.
 const CAST_DELAY_ALLOWANCE: Duration = Duration::from_micros(100);
 
+// Cursor bitmap sizes to allocate the cursor metadata for, same as Mutter.
+const CURSOR_META_DEFAULT_SIZE: i32 = 64;
+const CURSOR_META_MAX_SIZE: i32 = 1024;
+const CURSOR_BPP: i32 = 4;
+
 pub struct PipeWire {
     _context: Context,
     pub core: Core,
@@ -60,7 +66,8 @@ pub struct PipeWire {
 
 pub enum PwToNiri {
     StopCast { session_id: usize },
-    Redraw(CastTarget),
+    Redraw { session_id: usize },
+    RecordingStreamAdded { session_id: usize, node_id: u32 },
     FatalError,
 }
 
@@ -75,10 +82,44 @@ pub struct Cast {
     refresh: Rc<Cell<u32>>,
     offer_alpha: bool,
     pub cursor_mode: CursorMode,
-    pub last_frame_time: Duration,
+    last_frame_time: Duration,
     min_time_between_frames: Rc<Cell<Duration>>,
+    frame_limiter: FrameLimiter,
     dmabufs: Rc<RefCell<HashMap<i64, Dmabuf>>>,
     scheduled_redraw: Option<RegistrationToken>,
+    /// Part of the output to cast, in output-local physical coordinates.
+    pub crop: Option<smithay::utils::Rectangle<i32, Physical>>,
+    /// Cursor sent in the last frame in the metadata cursor mode.
+    last_cursor: Option<(Point<i32, Physical>, Point<i32, Physical>, Rc<CursorBitmap>)>,
+}
+
+/// Spaces out cast frames to stay at or under a frame rate limit.
+///
+/// Frames can only be rendered on output refresh cycles, so waiting for the full interval after
+/// every frame would consistently undershoot the limit. Instead, the limiter keeps track of the
+/// ideal frame times, and only resyncs to the actual frame time when a frame is very late.
+#[derive(Debug, Default)]
+struct FrameLimiter {
+    interval: Option<Duration>,
+    last_frame: Option<Duration>,
+}
+
+/// Cursor image sent as PipeWire metadata.
+pub struct CursorBitmap {
+    pub size: Size<i32, Physical>,
+    /// RGBA pixels.
+    pub pixels: Vec<u8>,
+}
+
+/// Cursor to attach to a frame in the metadata cursor mode.
+#[derive(Clone)]
+pub struct CursorMeta {
+    /// Location of the cursor hotspot within the frame.
+    pub position: Point<i32, Physical>,
+    /// Location of the hotspot within the bitmap.
+    pub hotspot: Point<i32, Physical>,
+    /// The bitmap is only sent to the consumer when it changes, compared by pointer.
+    pub bitmap: Rc<CursorBitmap>,
 }
 
 #[derive(Debug)]
@@ -114,6 +155,14 @@ pub enum CastTarget {
     Window { id: u64 },
 }
 
+/// Consumer of a cast's PipeWire stream.
+pub enum CastSink {
+    /// xdg-desktop-portal-gnome, notified through the Mutter ScreenCast D-Bus interface.
+    Portal(SignalEmitter<'static>),
+    /// Our own screen recording, which spawns an encoder once the stream is ready.
+    Recording,
+}
+
 macro_rules! make_params {
     ($params:ident, $formats:expr, $size:expr, $refresh:expr, $alpha:expr) => {
         let mut b1 = Vec::new();
@@ -194,7 +243,7 @@ impl PipeWire {
         refresh: u32,
         alpha: bool,
         cursor_mode: CursorMode,
-        signal_ctx: SignalEmitter<'static>,
+        sink: CastSink,
     ) -> anyhow::Result<Cast> {
         let _span = tracy_client::span!("PipeWire::start_cast");
 
@@ -204,14 +253,15 @@ impl PipeWire {
                 warn!("error sending StopCast to niri: {err:?}");
             }
         };
-        let target_ = target.clone();
         let to_niri_ = self.to_niri.clone();
         let redraw = move || {
-            if let Err(err) = to_niri_.send(PwToNiri::Redraw(target_.clone())) {
+            // The cast target can change, so niri looks it up by the session id.
+            if let Err(err) = to_niri_.send(PwToNiri::Redraw { session_id }) {
                 warn!("error sending Redraw to niri: {err:?}");
             }
         };
         let redraw_ = redraw.clone();
+        let to_niri_ = self.to_niri.clone();
 
         let stream = Stream::new(&self.core, "niri-screen-cast-src", Properties::new())
             .context("error creating Stream")?;
@@ -239,21 +289,37 @@ impl PipeWire {
                             if node_id.get().is_none() {
                                 let id = stream.node_id();
                                 node_id.set(Some(id));
-                                debug!("pw stream: sending signal with {id}");
-
-                                let _span = tracy_client::span!("sending PipeWireStreamAdded");
-                                async_io::block_on(async {
-                                    let res = mutter_screen_cast::Stream::pipe_wire_stream_added(
-                                        &signal_ctx,
-                                        id,
-                                    )
-                                    .await;
-
-                                    if let Err(err) = res {
-                                        warn!("error sending PipeWireStreamAdded: {err:?}");
-                                        stop_cast();
+
+                                match &sink {
+                                    CastSink::Portal(signal_ctx) => {
+                                        debug!("pw stream: sending signal with {id}");
+
+                                        let _span =
+                                            tracy_client::span!("sending PipeWireStreamAdded");
+                                        async_io::block_on(async {
+                                            let res =
+                                                mutter_screen_cast::Stream::pipe_wire_stream_added(
+                                                    signal_ctx, id,
+                                                )
+                                                .await;
+
+                                            if let Err(err) = res {
+                                                warn!("error sending PipeWireStreamAdded: {err:?}");
+                                                stop_cast();
+                                            }
+                                        });
+                                    }
+                                    CastSink::Recording => {
+                                        let msg = PwToNiri::RecordingStreamAdded {
+                                            session_id,
+                                            node_id: id,
+                                        };
+                                        if let Err(err) = to_niri_.send(msg) {
+                                            warn!("error sending RecordingStreamAdded: {err:?}");
+                                            stop_cast();
+                                        }
                                     }
-                                });
+                                }
                             }
 
                             is_active.set(false);
@@ -529,25 +595,40 @@ impl PipeWire {
                         ),
                     );
 
-                    // FIXME: Hidden / embedded / metadata cursor
-
-                    // let o2 = pod::object!(
-                    //     SpaTypes::ObjectParamMeta,
-                    //     ParamType::Meta,
-                    //     Property::new(SPA_PARAM_META_type,
-                    // pod::Value::Id(Id(SPA_META_Header))),
-                    //     Property::new(
-                    //         SPA_PARAM_META_size,
-                    //         pod::Value::Int(size_of::<spa_meta_header>() as i32)
-                    //     ),
-                    // );
                     let mut b1 = vec![];
-                    // let mut b2 = vec![];
-                    let mut params = [
-                        make_pod(&mut b1, o1), // make_pod(&mut b2, o2)
-                    ];
+                    let mut b2 = vec![];
+                    let pod1 = make_pod(&mut b1, o1);
+
+                    let mut p1;
+                    let mut p2;
+                    let params = if cursor_mode == CursorMode::Metadata {
+                        let o2 = pod::object!(
+                            SpaTypes::ObjectParamMeta,
+                            ParamType::Meta,
+                            Property::new(
+                                SPA_PARAM_META_type,
+                                pod::Value::Id(spa::utils::Id(SPA_META_Cursor))
+                            ),
+                            Property::new(
+                                SPA_PARAM_META_size,
+                                pod::Value::Choice(ChoiceValue::Int(Choice(
+                                    ChoiceFlags::empty(),
+                                    ChoiceEnum::Range {
+                                        default: cursor_meta_size(CURSOR_META_DEFAULT_SIZE),
+                                        min: cursor_meta_size(1),
+                                        max: cursor_meta_size(CURSOR_META_MAX_SIZE),
+                                    }
+                                ))),
+                            ),
+                        );
+                        p2 = [pod1, make_pod(&mut b2, o2)];
+                        &mut p2[..]
+                    } else {
+                        p1 = [pod1];
+                        &mut p1[..]
+                    };
 
-                    if let Err(err) = stream.update_params(&mut params) {
+                    if let Err(err) = stream.update_params(params) {
                         warn!("error updating stream params: {err:?}");
                         stop_cast();
                     }
@@ -662,8 +743,11 @@ impl PipeWire {
             cursor_mode,
             last_frame_time: Duration::ZERO,
             min_time_between_frames,
+            frame_limiter: FrameLimiter::default(),
             dmabufs,
             scheduled_redraw: None,
+            crop: None,
+            last_cursor: None,
         };
         Ok(cast)
     }
@@ -806,6 +890,7 @@ impl Cast {
         target_frame_time: Duration,
     ) -> bool {
         let delay = self.compute_extra_delay(target_frame_time);
+        let delay = delay.max(self.frame_limiter.delay(target_frame_time));
         if delay >= CAST_DELAY_ALLOWANCE {
             trace!("delay >= allowance, scheduling redraw");
             self.schedule_redraw(event_loop, output.clone(), target_frame_time + delay);
@@ -816,10 +901,26 @@ impl Cast {
         }
     }
 
+    /// Records that a frame for the given target time was sent to the stream.
+    pub fn frame_rendered(&mut self, target_frame_time: Duration) {
+        self.last_frame_time = target_frame_time;
+        self.frame_limiter.frame_rendered(target_frame_time);
+    }
+
+    /// Sets the frame rate limit of this cast, on top of the limit requested by the consumer.
+    pub fn set_max_fps(&mut self, max_fps: Option<u32>) {
+        self.frame_limiter = FrameLimiter::new(max_fps);
+    }
+
+    /// Renders a frame into the next stream buffer.
+    ///
+    /// In the metadata cursor mode, `cursor` is attached to the frame, and a change in the cursor
+    /// alone is enough to send a new frame.
     pub fn dequeue_buffer_and_render(
         &mut self,
         renderer: &mut GlesRenderer,
         elements: &[impl RenderElement<GlesRenderer>],
+        cursor: Option<&CursorMeta>,
         size: Size<i32, Physical>,
         scale: Scale<f64>,
     ) -> bool {
@@ -838,18 +939,63 @@ impl Cast {
             *damage_tracker = OutputDamageTracker::new(size, scale, Transform::Normal);
         }
 
+        let cursor = cursor.filter(|_| self.cursor_mode == CursorMode::Metadata);
+        let cursor_changed = match (&self.last_cursor, cursor) {
+            (None, None) => false,
+            (Some((position, hotspot, bitmap)), Some(cursor)) => {
+                *position != cursor.position
+                    || *hotspot != cursor.hotspot
+                    || !Rc::ptr_eq(bitmap, &cursor.bitmap)
+            }
+            _ => true,
+        };
+
         let (damage, _states) = damage_tracker.damage_output(1, elements).unwrap();
-        if damage.is_none() {
+        if damage.is_none() && !cursor_changed {
             trace!("no damage, skipping frame");
             return false;
         }
 
-        let Some(mut buffer) = self.stream.dequeue_buffer() else {
+        let pw_buffer = unsafe { self.stream.dequeue_raw_buffer() };
+        if pw_buffer.is_null() {
             warn!("no available buffer in pw stream, skipping frame");
             return false;
-        };
+        }
+
+        let rendered = unsafe { self.render_to_buffer(renderer, pw_buffer, elements, size, scale) };
+
+        if rendered && self.cursor_mode == CursorMode::Metadata {
+            let bitmap_changed = match (&self.last_cursor, cursor) {
+                (Some((_, _, last)), Some(cursor)) => !Rc::ptr_eq(last, &cursor.bitmap),
+                _ => true,
+            };
+
+            unsafe {
+                write_cursor_meta((*pw_buffer).buffer, cursor, bitmap_changed);
+            }
+
+            self.last_cursor = cursor.map(|c| (c.position, c.hotspot, c.bitmap.clone()));
+        }
 
-        let fd = buffer.datas_mut()[0].as_raw().fd;
+        unsafe {
+            self.stream.queue_raw_buffer(pw_buffer);
+        }
+
+        rendered
+    }
+
+    unsafe fn render_to_buffer(
+        &self,
+        renderer: &mut GlesRenderer,
+        pw_buffer: *mut pipewire::sys::pw_buffer,
+        elements: &[impl RenderElement<GlesRenderer>],
+        size: Size<i32, Physical>,
+        scale: Scale<f64>,
+    ) -> bool {
+        let spa_buffer = (*pw_buffer).buffer;
+        let datas = slice::from_raw_parts_mut((*spa_buffer).datas, (*spa_buffer).n_datas as usize);
+
+        let fd = datas[0].fd;
         let dmabuf = &self.dmabufs.borrow()[&fd];
 
         if let Err(err) = render_to_dmabuf(
@@ -864,17 +1010,15 @@ impl Cast {
             return false;
         }
 
-        for (data, (stride, offset)) in
-            zip(buffer.datas_mut(), zip(dmabuf.strides(), dmabuf.offsets()))
-        {
-            let chunk = data.chunk_mut();
-            *chunk.size_mut() = 1;
-            *chunk.stride_mut() = stride as i32;
-            *chunk.offset_mut() = offset;
+        for (data, (stride, offset)) in zip(datas, zip(dmabuf.strides(), dmabuf.offsets())) {
+            let chunk = data.chunk;
+            (*chunk).size = 1;
+            (*chunk).stride = stride as i32;
+            (*chunk).offset = offset;
 
             trace!(
                 "pw buffer: fd = {}, stride = {stride}, offset = {offset}",
-                data.as_raw().fd
+                data.fd
             );
         }
 
@@ -900,6 +1044,46 @@ impl CastState {
     }
 }
 
+impl FrameLimiter {
+    fn new(max_fps: Option<u32>) -> Self {
+        let interval = max_fps
+            .filter(|fps| *fps > 0)
+            .map(|fps| Duration::from_nanos(1_000_000_000 / u64::from(fps)));
+
+        Self {
+            interval,
+            last_frame: None,
+        }
+    }
+
+    /// Returns how long to wait before the frame at this target time can be rendered.
+    fn delay(&self, target_frame_time: Duration) -> Duration {
+        let (Some(interval), Some(last)) = (self.interval, self.last_frame) else {
+            return Duration::ZERO;
+        };
+
+        (last + interval).saturating_sub(target_frame_time)
+    }
+
+    fn frame_rendered(&mut self, target_frame_time: Duration) {
+        let Some(interval) = self.interval else {
+            return;
+        };
+
+        let next = self.last_frame.map(|last| last + interval);
+        self.last_frame = match next {
+            // Keep to the ideal frame times so that the rounding to refresh cycles averages out.
+            Some(next)
+                if next.saturating_sub(interval) <= target_frame_time
+                    && target_frame_time < next + interval =>
+            {
+                Some(next)
+            }
+            _ => Some(target_frame_time),
+        };
+    }
+}
+
 fn make_video_params(
     formats: &FormatSet,
     size: Size<u32, Physical>,
@@ -979,6 +1163,77 @@ fn make_video_params(
     )
 }
 
+fn cursor_meta_size(bitmap_size: i32) -> i32 {
+    (size_of::<spa_meta_cursor>() + size_of::<spa_meta_bitmap>()) as i32
+        + bitmap_size * bitmap_size * CURSOR_BPP
+}
+
+/// Fills the cursor metadata of the buffer, if it has any.
+///
+/// When `cursor` is `None`, marks the cursor as hidden.
+unsafe fn write_cursor_meta(
+    spa_buffer: *mut spa_buffer,
+    cursor: Option<&CursorMeta>,
+    with_bitmap: bool,
+) {
+    let metas = slice::from_raw_parts((*spa_buffer).metas, (*spa_buffer).n_metas as usize);
+    let Some(meta) = metas.iter().find(|meta| meta.type_ == SPA_META_Cursor) else {
+        return;
+    };
+
+    let meta_size = meta.size as usize;
+    if meta_size < size_of::<spa_meta_cursor>() {
+        return;
+    }
+
+    let meta_cursor = meta.data as *mut spa_meta_cursor;
+    let Some(cursor) = cursor else {
+        // Cursor id 0 tells the consumer that there's no cursor to draw.
+        (*meta_cursor).id = 0;
+        return;
+    };
+
+    (*meta_cursor).id = 1;
+    (*meta_cursor).flags = 0;
+    (*meta_cursor).position = spa_point {
+        x: cursor.position.x,
+        y: cursor.position.y,
+    };
+    (*meta_cursor).hotspot = spa_point {
+        x: cursor.hotspot.x,
+        y: cursor.hotspot.y,
+    };
+    // Offset 0 means that the bitmap didn't change.
+    (*meta_cursor).bitmap_offset = 0;
+
+    if !with_bitmap {
+        return;
+    }
+
+    let bitmap = &cursor.bitmap;
+    let bitmap_offset = size_of::<spa_meta_cursor>();
+    let stride = bitmap.size.w * CURSOR_BPP;
+    let needed = bitmap_offset + size_of::<spa_meta_bitmap>() + bitmap.pixels.len();
+    if needed > meta_size || bitmap.pixels.len() != (stride * bitmap.size.h) as usize {
+        trace!("cursor bitmap doesn't fit into the metadata");
+        return;
+    }
+
+    let meta_bitmap = meta_cursor.byte_add(bitmap_offset) as *mut spa_meta_bitmap;
+    (*meta_bitmap).format = SPA_VIDEO_FORMAT_RGBA;
+    (*meta_bitmap).size = spa_rectangle {
+        width: bitmap.size.w as u32,
+        height: bitmap.size.h as u32,
+    };
+    (*meta_bitmap).stride = stride;
+    (*meta_bitmap).offset = size_of::<spa_meta_bitmap>() as u32;
+
+    let pixels = meta_bitmap.byte_add(size_of::<spa_meta_bitmap>()) as *mut u8;
+    ptr::copy_nonoverlapping(bitmap.pixels.as_ptr(), pixels, bitmap.pixels.len());
+
+    (*meta_cursor).bitmap_offset = bitmap_offset as u32;
+}
+
 fn make_pod(buffer: &mut Vec<u8>, object: pod::Object) -> &Pod {
     PodSerializer::serialize(Cursor::new(&mut *buffer), &pod::Value::Object(object)).unwrap();
     Pod::from_bytes(buffer).unwrap()
@@ -1048,3 +1303,50 @@ fn allocate_dmabuf(
         .context("error exporting GBM buffer object as dmabuf")?;
     Ok(dmabuf)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_limiter_averages_out() {
+        // 144 Hz output with a 30 FPS limit.
+        let refresh = Duration::from_nanos(1_000_000_000 / 144);
+        let mut limiter = FrameLimiter::new(Some(30));
+
+        let mut frames = 0;
+        for cycle in 0..144 * 10 {
+            let target = refresh * cycle;
+            if limiter.delay(target) < CAST_DELAY_ALLOWANCE {
+                limiter.frame_rendered(target);
+                frames += 1;
+            }
+        }
+
+        assert_eq!(frames, 300);
+    }
+
+    #[test]
+    fn frame_limiter_resyncs_after_pause() {
+        let mut limiter = FrameLimiter::new(Some(10));
+        limiter.frame_rendered(Duration::ZERO);
+        assert_eq!(
+            limiter.delay(Duration::from_millis(40)),
+            Duration::from_millis(60)
+        );
+
+        // Nothing was rendered for a while, so the next frame becomes the new reference.
+        limiter.frame_rendered(Duration::from_secs(5));
+        assert_eq!(
+            limiter.delay(Duration::from_millis(5050)),
+            Duration::from_millis(50)
+        );
+    }
+
+    #[test]
+    fn frame_limiter_unlimited() {
+        let mut limiter = FrameLimiter::new(Some(0));
+        limiter.frame_rendered(Duration::ZERO);
+        assert_eq!(limiter.delay(Duration::from_millis(1)), Duration::ZERO);
+    }
+}
@@ -8,7 +8,7 @@ use smithay::backend::renderer::element::utils::{Relocate, RelocateRenderElement
 use smithay::backend::renderer::element::{Kind, RenderElement};
 use smithay::backend::renderer::gles::{GlesMapping, GlesRenderer, GlesTexture};
 use smithay::backend::renderer::sync::SyncPoint;
-use smithay::backend::renderer::{Bind, Color32F, ExportMem, Frame, Offscreen, Renderer};
+use smithay::backend::renderer::{Bind, Color32F, ExportMem, Frame, Offscreen, Renderer, Texture};
 use smithay::reexports::wayland_server::protocol::wl_buffer::WlBuffer;
 use smithay::reexports::wayland_server::protocol::wl_shm;
 use smithay::utils::{Logical, Physical, Point, Rectangle, Scale, Size, Transform};
@@ -38,7 +38,7 @@ pub mod surface;
 pub mod texture;
 
 /// What we're rendering for.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum RenderTarget {
     /// Rendering to display on screen.
     Output,
@@ -203,6 +203,40 @@ pub fn render_to_texture(
     Ok((texture, sync_point))
 }
 
+/// Renders elements into a texture that is kept between calls.
+///
+/// The texture is created on the first call and recreated when the size changes.
+pub fn render_to_reused_texture(
+    renderer: &mut GlesRenderer,
+    texture: &mut Option<GlesTexture>,
+    size: Size<i32, Physical>,
+    scale: Scale<f64>,
+    transform: Transform,
+    fourcc: Fourcc,
+    elements: impl Iterator<Item = impl RenderElement<GlesRenderer>>,
+) -> anyhow::Result<(GlesTexture, SyncPoint)> {
+    let _span = tracy_client::span!();
+
+    let buffer_size = size.to_logical(1).to_buffer(1, Transform::Normal);
+
+    let texture = match texture {
+        Some(texture) if texture.size() == buffer_size => texture.clone(),
+        _ => {
+            let new: GlesTexture = renderer
+                .create_buffer(fourcc, buffer_size)
+                .context("error creating texture")?;
+            texture.insert(new).clone()
+        }
+    };
+
+    renderer
+        .bind(texture.clone())
+        .context("error binding texture")?;
+
+    let sync_point = render_elements(renderer, size, scale, transform, elements)?;
+    Ok((texture, sync_point))
+}
+
 pub fn render_and_download(
     renderer: &mut GlesRenderer,
     size: Size<i32, Physical>,
@@ -0,0 +1,216 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use anyhow::Context;
+use smithay::backend::renderer::element::solid::{SolidColorBuffer, SolidColorRenderElement};
+use smithay::backend::renderer::element::Kind;
+use smithay::output::Output;
+use smithay::utils::{Logical, Point};
+
+use crate::pw_utils::PwToNiri;
+use crate::utils::output_size;
+
+/// Size of the recording indicator square, in logical pixels.
+const INDICATOR_SIZE: i32 = 12;
+/// Gap between the recording indicator and the output corner, in logical pixels.
+const INDICATOR_MARGIN: i32 = 8;
+const INDICATOR_COLOR: [f32; 4] = [0.9, 0.1, 0.1, 1.];
+
+/// Built-in screen recording to a file.
+///
+/// The frames come from a regular PipeWire cast, and are encoded by a GStreamer pipeline running
+/// in a `gst-launch-1.0` child process.
+pub struct ScreenRecording {
+    /// Session id of the cast feeding the recording.
+    pub session_id: usize,
+    /// Path that the recording is saved to.
+    pub path: PathBuf,
+    encoder: Option<Encoder>,
+    indicator: SolidColorBuffer,
+}
+
+struct Encoder {
+    pid: libc::pid_t,
+    exited: Arc<AtomicBool>,
+    /// Whether the encoder was asked to finish writing the file.
+    stopping: bool,
+}
+
+impl ScreenRecording {
+    pub fn new(session_id: usize, path: PathBuf) -> Self {
+        Self {
+            session_id,
+            path,
+            encoder: None,
+            indicator: SolidColorBuffer::new((INDICATOR_SIZE, INDICATOR_SIZE), INDICATOR_COLOR),
+        }
+    }
+
+    /// Renders the indicator in the top-right corner of the output.
+    pub fn render_indicator(&self, output: &Output) -> SolidColorRenderElement {
+        let scale = output.current_scale().fractional_scale();
+        let output_size = output_size(output);
+
+        let loc = Point::<f64, Logical>::from((
+            output_size.w - f64::from(INDICATOR_SIZE + INDICATOR_MARGIN),
+            f64::from(INDICATOR_MARGIN),
+        ));
+
+        SolidColorRenderElement::from_buffer(
+            &self.indicator,
+            loc.to_physical_precise_round(scale),
+            scale,
+            1.,
+            Kind::Unspecified,
+        )
+    }
+
+    /// Spawns the encoder for the cast's PipeWire node.
+    ///
+    /// When the encoder exits, the cast is stopped through `to_niri`.
+    pub fn start_encoder(
+        &mut self,
+        node_id: u32,
+        to_niri: calloop::channel::Sender<PwToNiri>,
+    ) -> anyhow::Result<()> {
+        if self.encoder.is_some() {
+            return Ok(());
+        }
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).context("error creating recording directory")?;
+        }
+
+        let (encoder, muxer) = encoder_elements(&self.path);
+
+        let mut command = Command::new("gst-launch-1.0");
+        command
+            .arg("-e")
+            .arg("pipewiresrc")
+            .arg(format!("path={node_id}"))
+            .arg("do-timestamp=true")
+            .arg("!")
+            .arg("videoconvert")
+            .arg("!")
+            .args(encoder)
+            .arg("!")
+            .arg(muxer)
+            .arg("!")
+            .arg("filesink")
+            .arg(format!("location={}", quote(&self.path)))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null());
+
+        debug!("spawning screen recording encoder: {command:?}");
+        let mut child = command
+            .spawn()
+            .context("error spawning gst-launch-1.0, is GStreamer installed?")?;
+
+        let pid = child.id() as libc::pid_t;
+        let exited = Arc::new(AtomicBool::new(false));
+
+        let exited_ = exited.clone();
+        let session_id = self.session_id;
+        thread::Builder::new()
+            .name("Screen Recording Encoder Waiter".to_owned())
+            .spawn(move || {
+                match child.wait() {
+                    Ok(status) if !status.success() => {
+                        warn!("screen recording encoder exited with {status}");
+                    }
+                    Ok(_) => (),
+                    Err(err) => warn!("error waiting for screen recording encoder: {err:?}"),
+                }
+
+                exited_.store(true, Ordering::SeqCst);
+                let _ = to_niri.send(PwToNiri::StopCast { session_id });
+            })
+            .context("error spawning encoder waiter thread")?;
+
+        self.encoder = Some(Encoder {
+            pid,
+            exited,
+            stopping: false,
+        });
+        Ok(())
+    }
+
+    /// Asks the encoder to finish writing the file.
+    ///
+    /// Returns `false` if there's no running encoder, in which case the cast should be stopped
+    /// right away. Otherwise, the cast must be kept alive until the encoder exits, at which point
+    /// the encoder waiter stops it.
+    pub fn stop_encoder(&mut self) -> bool {
+        let Some(encoder) = &mut self.encoder else {
+            return false;
+        };
+
+        if encoder.exited.load(Ordering::SeqCst) {
+            return false;
+        }
+
+        // gst-launch-1.0 -e handles SIGINT by sending end-of-stream, which finalizes the file. A
+        // second SIGINT would abort that, so only send it once.
+        if !encoder.stopping {
+            encoder.stopping = true;
+            unsafe {
+                libc::kill(encoder.pid, libc::SIGINT);
+            }
+        }
+
+        true
+    }
+}
+
+/// Returns the encoder and the muxer elements, picked by the file extension.
+fn encoder_elements(path: &Path) -> (&'static [&'static str], &'static str) {
+    const H264: &[&str] = &["x264enc", "tune=zerolatency", "!", "h264parse"];
+
+    let extension = path.extension().and_then(|ext| ext.to_str());
+    match extension {
+        Some("webm") => (&["vp8enc", "deadline=1"], "webmmux"),
+        Some("mkv") => (H264, "matroskamux"),
+        _ => (H264, "mp4mux"),
+    }
+}
+
+/// Quotes a path for the gst-launch-1.0 pipeline syntax.
+fn quote(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    let escaped = path.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("\"{escaped}\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoder_elements_by_extension() {
+        let (encoder, muxer) = encoder_elements(Path::new("/tmp/a.webm"));
+        assert_eq!(encoder, ["vp8enc", "deadline=1"]);
+        assert_eq!(muxer, "webmmux");
+
+        let (encoder, muxer) = encoder_elements(Path::new("/tmp/a.mkv"));
+        assert_eq!(encoder[0], "x264enc");
+        assert_eq!(muxer, "matroskamux");
+
+        let (encoder, muxer) = encoder_elements(Path::new("/tmp/a.mp4"));
+        assert_eq!(encoder[0], "x264enc");
+        assert_eq!(muxer, "mp4mux");
+
+        // Unknown and missing extensions fall back to MP4.
+        assert_eq!(encoder_elements(Path::new("/tmp/a.avi")).1, "mp4mux");
+        assert_eq!(encoder_elements(Path::new("/tmp/a")).1, "mp4mux");
+    }
+
+    #[test]
+    fn quote_escapes() {
+        assert_eq!(quote(Path::new("/tmp/a b.mp4")), r#""/tmp/a b.mp4""#);
+        assert_eq!(quote(Path::new(r#"/tmp/"a".mp4"#)), r#""/tmp/\"a\".mp4""#);
+        assert_eq!(quote(Path::new(r"/tmp/a\b.mp4")), r#""/tmp/a\\b.mp4""#);
+    }
+}
@@ -94,6 +94,7 @@ impl Fixture {
             client: sock1,
             restricted: false,
             credentials_unknown: false,
+            is_xwayland_satellite: false,
         });
 
         let client = Client::new(sock2);
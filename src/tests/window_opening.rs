@@ -1,8 +1,10 @@
 use std::fmt::{self, Write as _};
 
+use client::ClientId;
 use insta::assert_snapshot;
-use niri_config::Config;
+use niri_config::{Config, Workspace as WorkspaceConfig, WorkspaceName};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use wayland_client::protocol::wl_surface::WlSurface;
 
 use super::*;
 use crate::layout::LayoutElement;
@@ -95,6 +97,127 @@ fn dont_ack_initial_configure() {
     );
 }
 
+fn open_on_missing_workspace(fallback: &str) -> (Fixture, ClientId, WlSurface) {
+    let config = format!(
+        r##"
+window-rule {{
+    open-on-workspace "missing"
+    open-on-workspace-fallback "{fallback}"
+}}
+"##
+    );
+    let config = Config::parse("config.kdl", &config).unwrap();
+
+    let mut f = Fixture::with_config(config);
+    f.add_output(1, (1920, 1080));
+
+    let id = f.add_client();
+    let window = f.client(id).create_window();
+    let surface = window.surface.clone();
+    window.commit();
+    f.double_roundtrip(id);
+
+    (f, id, surface)
+}
+
+fn map_and_get_workspace_name(f: &mut Fixture, id: ClientId, surface: &WlSurface) -> String {
+    let window = f.client(id).window(surface);
+    window.attach_new_buffer();
+    window.ack_last_and_commit();
+    f.double_roundtrip(id);
+
+    let (_, _, ws) = f
+        .niri()
+        .layout
+        .workspaces()
+        .find(|(_, _, ws)| ws.has_windows())
+        .unwrap();
+    ws.name().cloned().unwrap_or(String::from("unnamed"))
+}
+
+#[test]
+fn open_on_missing_workspace_current() {
+    let (mut f, id, surface) = open_on_missing_workspace("current");
+    let ws = map_and_get_workspace_name(&mut f, id, &surface);
+    assert_eq!(ws, "unnamed");
+    assert!(f.niri().layout.find_workspace_by_name("missing").is_none());
+}
+
+#[test]
+fn open_on_missing_workspace_create() {
+    let (mut f, id, surface) = open_on_missing_workspace("create");
+    let ws = map_and_get_workspace_name(&mut f, id, &surface);
+    assert_eq!(ws, "missing");
+}
+
+#[test]
+fn open_on_missing_workspace_wait() {
+    let (mut f, id, surface) = open_on_missing_workspace("wait");
+
+    // The initial configure is held back.
+    let window = f.client(id).window(&surface);
+    assert_snapshot!(window.format_recent_configures(), @"");
+
+    f.niri().layout.ensure_named_workspace(&WorkspaceConfig {
+        name: WorkspaceName(String::from("missing")),
+        open_on_output: None,
+        return_on_connect: false,
+        icon: None,
+        auto_close_if_empty_after: None,
+    });
+    f.double_roundtrip(id);
+
+    let window = f.client(id).window(&surface);
+    assert_snapshot!(
+        window.format_recent_configures(),
+        @"size: 936 × 1048, bounds: 1888 × 1048, states: []"
+    );
+
+    let ws = map_and_get_workspace_name(&mut f, id, &surface);
+    assert_eq!(ws, "missing");
+}
+
+#[test]
+fn fullscreen_size_rule() {
+    let config = r##"
+window-rule {
+    fullscreen-size {
+        width 1280
+        height 720
+    }
+}
+"##;
+    let config = Config::parse("config.kdl", config).unwrap();
+
+    let mut f = Fixture::with_config(config);
+    f.add_output(1, (1920, 1080));
+
+    let id = f.add_client();
+    let window = f.client(id).create_window();
+    let surface = window.surface.clone();
+    window.commit();
+    f.roundtrip(id);
+
+    let window = f.client(id).window(&surface);
+    window.attach_new_buffer();
+    window.ack_last_and_commit();
+    f.double_roundtrip(id);
+
+    let _ = f.client(id).window(&surface).recent_configures();
+
+    let niri = f.niri();
+    let mapped = niri.layout.windows().next().unwrap().1;
+    let window = mapped.window.clone();
+    niri.layout.set_fullscreen(&window, true);
+    f.double_roundtrip(id);
+
+    // This should request the size from the rule rather than the output size.
+    assert_snapshot!(
+        f.client(id).window(&surface).format_recent_configures(),
+        @"size: 1280 × 720, bounds: 1888 × 1048, states: [Activated, Fullscreen]"
+    );
+}
+
 #[derive(Clone, Copy)]
 enum WantFullscreen {
     No,
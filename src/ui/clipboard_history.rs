@@ -0,0 +1,264 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use ordered_float::NotNan;
+use pangocairo::cairo::{self, ImageSurface};
+use pangocairo::pango::{Alignment, EllipsizeMode, FontDescription};
+use smithay::input::keyboard::Keysym;
+use smithay::output::Output;
+
+use crate::render_helpers::memory::MemoryBuffer;
+use crate::render_helpers::primary_gpu_texture::PrimaryGpuTextureRenderElement;
+use crate::render_helpers::renderer::NiriRenderer;
+use crate::ui::dialog::{render_centered, render_dialog};
+use crate::utils::clipboard_history::ClipboardEntry;
+use crate::utils::to_physical_precise_round;
+
+const TITLE: &str = "Clipboard history";
+const EMPTY: &str = "The clipboard history is empty.";
+const HINT: &str = "Press <span face='mono' bgcolor='#2C2C2C'> Enter </span> to copy, \
+                    <span face='mono' bgcolor='#2C2C2C'> Escape </span> to cancel.";
+const PADDING: i32 = 16;
+const FONT: &str = "sans 14px";
+const ENTRY_FONT: &str = "sans 16px";
+const BORDER: i32 = 4;
+const ENTRY_WIDTH: i32 = 500;
+const VISIBLE_ENTRIES: usize = 10;
+
+/// List of clipboard history entries to pick one to copy again.
+pub struct ClipboardHistoryPicker {
+    state: Option<OpenState>,
+    buffers: RefCell<HashMap<NotNan<f64>, Option<MemoryBuffer>>>,
+}
+
+struct OpenState {
+    /// Output to show the picker on.
+    output: Output,
+    /// Ids and labels of the entries, most recent first.
+    entries: Vec<(u64, String)>,
+    /// Index of the selected entry.
+    selected: usize,
+}
+
+impl ClipboardHistoryPicker {
+    pub fn new() -> Self {
+        Self {
+            state: None,
+            buffers: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn show<'a>(
+        &mut self,
+        output: Output,
+        entries: impl Iterator<Item = &'a ClipboardEntry>,
+    ) -> bool {
+        if self.state.is_some() {
+            return false;
+        }
+
+        self.state = Some(OpenState {
+            output,
+            entries: entries.map(|entry| (entry.id, label(entry))).collect(),
+            selected: 0,
+        });
+        self.buffers.borrow_mut().clear();
+        true
+    }
+
+    pub fn hide(&mut self) -> bool {
+        if self.state.take().is_some() {
+            self.buffers.borrow_mut().clear();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.state.is_some()
+    }
+
+    /// Handles a key press while the picker is open.
+    ///
+    /// Returns the id of the entry once the user picks one.
+    pub fn on_key_press(&mut self, keysym: Keysym) -> Option<u64> {
+        let state = self.state.as_mut()?;
+        let last = state.entries.len().saturating_sub(1);
+
+        match keysym {
+            Keysym::Return | Keysym::KP_Enter => {
+                let state = self.state.take().unwrap();
+                self.buffers.borrow_mut().clear();
+                return state.entries.get(state.selected).map(|(id, _)| *id);
+            }
+            Keysym::Escape => {
+                self.hide();
+                return None;
+            }
+            Keysym::Up | Keysym::KP_Up => state.selected = state.selected.saturating_sub(1),
+            Keysym::Down | Keysym::KP_Down => state.selected = usize::min(state.selected + 1, last),
+            Keysym::Home | Keysym::KP_Home => state.selected = 0,
+            Keysym::End | Keysym::KP_End => state.selected = last,
+            _ => return None,
+        }
+
+        self.buffers.borrow_mut().clear();
+        None
+    }
+
+    pub fn render<R: NiriRenderer>(
+        &self,
+        renderer: &mut R,
+        output: &Output,
+    ) -> Option<PrimaryGpuTextureRenderElement> {
+        let state = self.state.as_ref()?;
+        if state.output != *output {
+            return None;
+        }
+
+        let scale = output.current_scale().fractional_scale();
+
+        let mut buffers = self.buffers.borrow_mut();
+        let buffer = buffers
+            .entry(NotNan::new(scale).unwrap())
+            .or_insert_with(|| {
+                render(scale, &state.entries, state.selected)
+                    .map_err(|err| warn!("error rendering clipboard history: {err:?}"))
+                    .ok()
+            });
+        let buffer = buffer.as_ref()?;
+
+        render_centered(renderer, output, buffer)
+    }
+}
+
+impl Default for ClipboardHistoryPicker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns a single-line description of the entry.
+fn label(entry: &ClipboardEntry) -> String {
+    if let Some(text) = entry.text() {
+        // Only the start of the text fits on the line anyway.
+        let text: String = text.chars().take(256).collect();
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !text.is_empty() {
+            return text;
+        }
+    }
+
+    let mime_type = entry.mime_types().next().unwrap_or_default();
+    let size = entry.size();
+    if size < 1024 {
+        format!("[{mime_type}, {size} B]")
+    } else {
+        format!("[{mime_type}, {} KiB]", size / 1024)
+    }
+}
+
+fn render(scale: f64, entries: &[(u64, String)], selected: usize) -> anyhow::Result<MemoryBuffer> {
+    let _span = tracy_client::span!("clipboard_history::render");
+
+    let padding: i32 = to_physical_precise_round(scale, PADDING);
+    let entry_width: i32 = to_physical_precise_round(scale, ENTRY_WIDTH);
+
+    let mut font = FontDescription::from_string(FONT);
+    font.set_absolute_size(to_physical_precise_round(scale, font.size()));
+    let mut entry_font = FontDescription::from_string(ENTRY_FONT);
+    entry_font.set_absolute_size(to_physical_precise_round(scale, entry_font.size()));
+
+    // Scroll the list to keep the selected entry visible.
+    let first = selected.saturating_sub(VISIBLE_ENTRIES - 1);
+    let visible = entries.iter().skip(first).take(VISIBLE_ENTRIES);
+
+    let surface = ImageSurface::create(cairo::Format::ARgb32, 0, 0)?;
+    let cr = cairo::Context::new(&surface)?;
+
+    let layout = pangocairo::functions::create_layout(&cr);
+    layout.context().set_round_glyph_positions(false);
+    layout.set_font_description(Some(&font));
+    layout.set_text(TITLE);
+    let (title_width, title_height) = layout.pixel_size();
+
+    layout.set_alignment(Alignment::Center);
+    layout.set_markup(HINT);
+    let (hint_width, hint_height) = layout.pixel_size();
+
+    layout.set_font_description(Some(&entry_font));
+    layout.set_text(EMPTY);
+    let (_, entry_height) = layout.pixel_size();
+    let row_height = entry_height + padding / 2;
+    let rows = i32::max(1, visible.len() as i32);
+
+    let inner_width = title_width.max(entry_width + padding).max(hint_width);
+    let width = inner_width + padding * 2;
+    let height = title_height + row_height * rows + hint_height + padding * 4;
+
+    render_dialog(scale, width, height, BORDER, [0.5, 0.6, 0.9], |cr| {
+        let mut y = padding;
+
+        // Title.
+        cr.move_to(padding.into(), y.into());
+        let layout = pangocairo::functions::create_layout(cr);
+        layout.context().set_round_glyph_positions(false);
+        layout.set_font_description(Some(&font));
+        layout.set_text(TITLE);
+        cr.set_source_rgb(1., 1., 1.);
+        pangocairo::functions::show_layout(cr, &layout);
+        y += title_height + padding;
+
+        // Entries.
+        let layout = pangocairo::functions::create_layout(cr);
+        layout.context().set_round_glyph_positions(false);
+        layout.set_font_description(Some(&entry_font));
+        layout.set_width((inner_width - padding) * pango::SCALE);
+        layout.set_ellipsize(EllipsizeMode::End);
+
+        if entries.is_empty() {
+            cr.move_to(f64::from(padding + padding / 2), f64::from(y + padding / 4));
+            layout.set_text(EMPTY);
+            cr.set_source_rgb(0.7, 0.7, 0.7);
+            pangocairo::functions::show_layout(cr, &layout);
+        }
+
+        for (idx, (_, label)) in visible.enumerate() {
+            let row_y = y + row_height * idx as i32;
+
+            if first + idx == selected {
+                cr.rectangle(
+                    padding.into(),
+                    row_y.into(),
+                    inner_width.into(),
+                    row_height.into(),
+                );
+                cr.set_source_rgb(0.25, 0.3, 0.45);
+                cr.fill()?;
+            }
+
+            cr.move_to(
+                f64::from(padding + padding / 2),
+                f64::from(row_y + padding / 4),
+            );
+            layout.set_text(label);
+            cr.set_source_rgb(1., 1., 1.);
+            pangocairo::functions::show_layout(cr, &layout);
+        }
+        y += row_height * rows + padding;
+
+        // Hint.
+        cr.move_to(padding.into(), y.into());
+        let layout = pangocairo::functions::create_layout(cr);
+        layout.context().set_round_glyph_positions(false);
+        layout.set_font_description(Some(&font));
+        layout.set_width(inner_width * pango::SCALE);
+        layout.set_alignment(Alignment::Center);
+        layout.set_markup(HINT);
+        cr.set_source_rgb(0.7, 0.7, 0.7);
+        pangocairo::functions::show_layout(cr, &layout);
+
+        Ok(())
+    })
+}
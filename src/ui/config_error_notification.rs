@@ -1,6 +1,6 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use std::rc::Rc;
 use std::time::Duration;
 
@@ -23,6 +23,11 @@ use crate::utils::{output_size, to_physical_precise_round};
 const TEXT: &str = "Failed to parse the config file. \
                     Please run <span face='monospace' bgcolor='#000000'>niri validate</span> \
                     to see the errors.";
+const NO_BINDS_TEXT: &str = "The config has no key bindings. \
+                             Press <span face='monospace' bgcolor='#000000'>Super+Shift+T</span> \
+                             to open a terminal, \
+                             or <span face='monospace' bgcolor='#000000'>Super+Shift+E</span> \
+                             to quit.";
 const PADDING: i32 = 8;
 const FONT: &str = "sans 14px";
 const BORDER: i32 = 4;
@@ -30,15 +35,22 @@ const BORDER: i32 = 4;
 pub struct ConfigErrorNotification {
     state: State,
     buffers: RefCell<HashMap<NotNan<f64>, Option<TextureBuffer<GlesTexture>>>>,
-
-    // If set, this is a "Created config at {path}" notification. If unset, this is a config error
-    // notification.
-    created_path: Option<PathBuf>,
+    kind: Kind,
 
     clock: Clock,
     config: Rc<RefCell<Config>>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Kind {
+    /// The config failed to parse.
+    Error,
+    /// A default config was created at this path.
+    Created(PathBuf),
+    /// The config has no binds, so the emergency binds are in use.
+    NoBinds,
+}
+
 enum State {
     Hidden,
     Showing(Animation),
@@ -51,7 +63,7 @@ impl ConfigErrorNotification {
         Self {
             state: State::Hidden,
             buffers: RefCell::new(HashMap::new()),
-            created_path: None,
+            kind: Kind::Error,
             clock,
             config,
         }
@@ -68,21 +80,25 @@ impl ConfigErrorNotification {
         )
     }
 
-    pub fn show_created(&mut self, created_path: PathBuf) {
-        let created_path = Some(created_path);
-        if self.created_path != created_path {
-            self.created_path = created_path;
+    fn set_kind(&mut self, kind: Kind) {
+        if self.kind != kind {
+            self.kind = kind;
             self.buffers.borrow_mut().clear();
         }
+    }
 
+    pub fn show_created(&mut self, created_path: PathBuf) {
+        self.set_kind(Kind::Created(created_path));
+        self.state = State::Showing(self.animation(0., 1.));
+    }
+
+    pub fn show_no_binds(&mut self) {
+        self.set_kind(Kind::NoBinds);
         self.state = State::Showing(self.animation(0., 1.));
     }
 
     pub fn show(&mut self) {
-        if self.created_path.is_some() {
-            self.created_path = None;
-            self.buffers.borrow_mut().clear();
-        }
+        self.set_kind(Kind::Error);
 
         // Show from scratch even if already showing to bring attention.
         self.state = State::Showing(self.animation(0., 1.));
@@ -101,13 +117,14 @@ impl ConfigErrorNotification {
             State::Hidden => (),
             State::Showing(anim) => {
                 if anim.is_done() {
-                    let duration = if self.created_path.is_some() {
+                    let duration = match self.kind {
                         // Make this quite a bit longer because it comes with a monitor modeset
                         // (can take a while) and an important hotkeys popup diverting the
                         // attention.
-                        Duration::from_secs(8)
-                    } else {
-                        Duration::from_secs(4)
+                        Kind::Created(_) => Duration::from_secs(8),
+                        // Give enough time to read the binds.
+                        Kind::NoBinds => Duration::from_secs(8),
+                        Kind::Error => Duration::from_secs(4),
                     };
                     self.state = State::Shown(self.clock.now() + duration);
                 }
@@ -140,12 +157,12 @@ impl ConfigErrorNotification {
 
         let scale = output.current_scale().fractional_scale();
         let output_size = output_size(output);
-        let path = self.created_path.as_deref();
+        let kind = &self.kind;
 
         let mut buffers = self.buffers.borrow_mut();
         let buffer = buffers
             .entry(NotNan::new(scale).unwrap())
-            .or_insert_with(move || render(renderer.as_gles_renderer(), scale, path).ok());
+            .or_insert_with(move || render(renderer.as_gles_renderer(), scale, kind).ok());
         let buffer = buffer.clone()?;
 
         let size = buffer.logical_size();
@@ -176,21 +193,23 @@ impl ConfigErrorNotification {
 fn render(
     renderer: &mut GlesRenderer,
     scale: f64,
-    created_path: Option<&Path>,
+    kind: &Kind,
 ) -> anyhow::Result<TextureBuffer<GlesTexture>> {
     let _span = tracy_client::span!("config_error_notification::render");
 
     let padding: i32 = to_physical_precise_round(scale, PADDING);
 
-    let mut text = String::from(TEXT);
-    let mut border_color = (1., 0.3, 0.3);
-    if let Some(path) = created_path {
-        text = format!(
-            "Created a default config file at \
-             <span face='monospace' bgcolor='#000000'>{:?}</span>",
-            path
-        );
-        border_color = (0.5, 1., 0.5);
+    let (text, border_color) = match kind {
+        Kind::Error => (String::from(TEXT), (1., 0.3, 0.3)),
+        Kind::Created(path) => {
+            let text = format!(
+                "Created a default config file at \
+                 <span face='monospace' bgcolor='#000000'>{:?}</span>",
+                path
+            );
+            (text, (0.5, 1., 0.5))
+        }
+        Kind::NoBinds => (String::from(NO_BINDS_TEXT), (1., 0.8, 0.3)),
     };
 
     let mut font = FontDescription::from_string(FONT);
@@ -0,0 +1,135 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use ordered_float::NotNan;
+use pangocairo::cairo::{self, ImageSurface};
+use pangocairo::pango::{Alignment, FontDescription};
+use smithay::output::Output;
+
+use crate::render_helpers::memory::MemoryBuffer;
+use crate::render_helpers::primary_gpu_texture::PrimaryGpuTextureRenderElement;
+use crate::render_helpers::renderer::NiriRenderer;
+use crate::ui::dialog::{render_centered, render_dialog};
+use crate::utils::to_physical_precise_round;
+
+const HINT: &str = "Press <span face='mono' bgcolor='#2C2C2C'> Enter </span> to confirm.";
+const PADDING: i32 = 16;
+const FONT: &str = "sans 14px";
+const BORDER: i32 = 8;
+
+/// Dialog asking to confirm an action with Enter.
+pub struct ConfirmDialog {
+    /// Question to show, as Pango markup.
+    text: String,
+    is_open: bool,
+    buffers: RefCell<HashMap<NotNan<f64>, Option<MemoryBuffer>>>,
+}
+
+impl ConfirmDialog {
+    /// Creates the dialog confirming to exit niri.
+    pub fn exit() -> anyhow::Result<Self> {
+        Self::new(String::from("Are you sure you want to exit niri?"))
+    }
+
+    /// Creates the dialog confirming to close a window.
+    pub fn close_window(name: Option<&str>) -> anyhow::Result<Self> {
+        let text = match name {
+            Some(name) => {
+                let name = pango::glib::markup_escape_text(name);
+                format!("Are you sure you want to close <b>{name}</b>?")
+            }
+            None => String::from("Are you sure you want to close this window?"),
+        };
+        Self::new(text)
+    }
+
+    fn new(text: String) -> anyhow::Result<Self> {
+        // Render at scale 1 right away to have a fallback for other scales.
+        let buffer = render(1., &text)?;
+        Ok(Self {
+            text,
+            is_open: false,
+            buffers: RefCell::new(HashMap::from([(NotNan::new(1.).unwrap(), Some(buffer))])),
+        })
+    }
+
+    pub fn show(&mut self) -> bool {
+        if !self.is_open {
+            self.is_open = true;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn hide(&mut self) -> bool {
+        if self.is_open {
+            self.is_open = false;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.is_open
+    }
+
+    pub fn render<R: NiriRenderer>(
+        &self,
+        renderer: &mut R,
+        output: &Output,
+    ) -> Option<PrimaryGpuTextureRenderElement> {
+        if !self.is_open {
+            return None;
+        }
+
+        let scale = output.current_scale().fractional_scale();
+
+        let mut buffers = self.buffers.borrow_mut();
+        let fallback = buffers[&NotNan::new(1.).unwrap()].clone().unwrap();
+        let buffer = buffers
+            .entry(NotNan::new(scale).unwrap())
+            .or_insert_with(|| render(scale, &self.text).ok());
+        let buffer = buffer.as_ref().unwrap_or(&fallback);
+
+        render_centered(renderer, output, buffer)
+    }
+}
+
+fn render(scale: f64, text: &str) -> anyhow::Result<MemoryBuffer> {
+    let _span = tracy_client::span!("confirm_dialog::render");
+
+    let text = format!("{text}\n\n{HINT}");
+
+    let padding: i32 = to_physical_precise_round(scale, PADDING);
+
+    let mut font = FontDescription::from_string(FONT);
+    font.set_absolute_size(to_physical_precise_round(scale, font.size()));
+
+    let surface = ImageSurface::create(cairo::Format::ARgb32, 0, 0)?;
+    let cr = cairo::Context::new(&surface)?;
+    let layout = pangocairo::functions::create_layout(&cr);
+    layout.context().set_round_glyph_positions(false);
+    layout.set_font_description(Some(&font));
+    layout.set_alignment(Alignment::Center);
+    layout.set_markup(&text);
+
+    let (mut width, mut height) = layout.pixel_size();
+    width += padding * 2;
+    height += padding * 2;
+
+    render_dialog(scale, width, height, BORDER, [1., 0.3, 0.3], |cr| {
+        cr.move_to(padding.into(), padding.into());
+        let layout = pangocairo::functions::create_layout(cr);
+        layout.context().set_round_glyph_positions(false);
+        layout.set_font_description(Some(&font));
+        layout.set_alignment(Alignment::Center);
+        layout.set_markup(&text);
+
+        cr.set_source_rgb(1., 1., 1.);
+        pangocairo::functions::show_layout(cr, &layout);
+
+        Ok(())
+    })
+}
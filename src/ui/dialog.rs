@@ -0,0 +1,83 @@
+//! Helpers for the dialogs shown in the middle of an output.
+
+use pangocairo::cairo::{self, ImageSurface};
+use smithay::backend::renderer::element::Kind;
+use smithay::output::Output;
+use smithay::reexports::gbm::Format as Fourcc;
+use smithay::utils::Transform;
+
+use crate::render_helpers::memory::MemoryBuffer;
+use crate::render_helpers::primary_gpu_texture::PrimaryGpuTextureRenderElement;
+use crate::render_helpers::renderer::NiriRenderer;
+use crate::render_helpers::texture::{TextureBuffer, TextureRenderElement};
+use crate::utils::output_size;
+
+/// Renders a dialog of the given size in physical pixels.
+///
+/// `draw` draws the contents over the dark background, then the border goes on top.
+pub fn render_dialog(
+    scale: f64,
+    width: i32,
+    height: i32,
+    border: i32,
+    border_color: [f64; 3],
+    draw: impl FnOnce(&cairo::Context) -> anyhow::Result<()>,
+) -> anyhow::Result<MemoryBuffer> {
+    let surface = ImageSurface::create(cairo::Format::ARgb32, width, height)?;
+    let cr = cairo::Context::new(&surface)?;
+    cr.set_source_rgb(0.1, 0.1, 0.1);
+    cr.paint()?;
+
+    draw(&cr)?;
+
+    cr.move_to(0., 0.);
+    cr.line_to(width.into(), 0.);
+    cr.line_to(width.into(), height.into());
+    cr.line_to(0., height.into());
+    cr.line_to(0., 0.);
+    let [r, g, b] = border_color;
+    cr.set_source_rgb(r, g, b);
+    // Keep the border width even to avoid blurry edges.
+    cr.set_line_width((f64::from(border) / 2. * scale).round() * 2.);
+    cr.stroke()?;
+    drop(cr);
+
+    let data = surface.take_data().unwrap();
+    let buffer = MemoryBuffer::new(
+        data.to_vec(),
+        Fourcc::Argb8888,
+        (width, height),
+        scale,
+        Transform::Normal,
+    );
+
+    Ok(buffer)
+}
+
+/// Returns an element showing the dialog in the middle of the output.
+pub fn render_centered<R: NiriRenderer>(
+    renderer: &mut R,
+    output: &Output,
+    buffer: &MemoryBuffer,
+) -> Option<PrimaryGpuTextureRenderElement> {
+    let scale = output.current_scale().fractional_scale();
+    let output_size = output_size(output);
+
+    let size = buffer.logical_size();
+    let buffer = TextureBuffer::from_memory_buffer(renderer.as_gles_renderer(), buffer).ok()?;
+
+    let location = (output_size.to_f64().to_point() - size.to_point()).downscale(2.);
+    let mut location = location.to_physical_precise_round(scale).to_logical(scale);
+    location.x = f64::max(0., location.x);
+    location.y = f64::max(0., location.y);
+
+    let elem = TextureRenderElement::from_texture_buffer(
+        buffer,
+        location,
+        1.,
+        None,
+        None,
+        Kind::Unspecified,
+    );
+    Some(PrimaryGpuTextureRenderElement(elem))
+}
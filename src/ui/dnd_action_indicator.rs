@@ -0,0 +1,120 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::f64::consts::TAU;
+
+use ordered_float::NotNan;
+use pangocairo::cairo::{self, ImageSurface};
+use pangocairo::pango::FontDescription;
+use smithay::backend::renderer::element::Kind;
+use smithay::reexports::gbm::Format as Fourcc;
+use smithay::reexports::wayland_server::protocol::wl_data_device_manager::DndAction;
+use smithay::utils::{Logical, Point, Transform};
+
+use crate::render_helpers::memory::MemoryBuffer;
+use crate::render_helpers::primary_gpu_texture::PrimaryGpuTextureRenderElement;
+use crate::render_helpers::renderer::NiriRenderer;
+use crate::render_helpers::texture::{TextureBuffer, TextureRenderElement};
+use crate::utils::to_physical_precise_round;
+
+const SIZE: i32 = 18;
+/// Offset of the indicator from the pointer, so that it sits below and to the right of it.
+const OFFSET: f64 = 14.;
+const FONT: &str = "sans bold 12px";
+
+/// Badge next to the pointer showing the action of an ongoing drag-and-drop.
+#[derive(Default)]
+pub struct DndActionIndicator {
+    buffers: RefCell<HashMap<(u32, NotNan<f64>), Option<MemoryBuffer>>>,
+}
+
+impl DndActionIndicator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn render<R: NiriRenderer>(
+        &self,
+        renderer: &mut R,
+        action: DndAction,
+        pointer_pos: Point<f64, Logical>,
+        scale: f64,
+    ) -> Option<PrimaryGpuTextureRenderElement> {
+        let symbol = symbol(action)?;
+
+        let mut buffers = self.buffers.borrow_mut();
+        let buffer = buffers
+            .entry((action.bits(), NotNan::new(scale).unwrap()))
+            .or_insert_with(|| {
+                render(scale, symbol)
+                    .map_err(|err| warn!("error rendering drag-and-drop indicator: {err:?}"))
+                    .ok()
+            });
+        let buffer =
+            TextureBuffer::from_memory_buffer(renderer.as_gles_renderer(), buffer.as_ref()?)
+                .ok()?;
+
+        let location = pointer_pos + Point::from((OFFSET, OFFSET));
+        let location = location.to_physical_precise_round(scale).to_logical(scale);
+
+        let elem = TextureRenderElement::from_texture_buffer(
+            buffer,
+            location,
+            1.,
+            None,
+            None,
+            Kind::Unspecified,
+        );
+        Some(PrimaryGpuTextureRenderElement(elem))
+    }
+}
+
+fn symbol(action: DndAction) -> Option<&'static str> {
+    // The chosen action is always a single one, but check in order of importance just in case.
+    if action.contains(DndAction::Ask) {
+        Some("?")
+    } else if action.contains(DndAction::Copy) {
+        Some("+")
+    } else if action.contains(DndAction::Move) {
+        Some("→")
+    } else {
+        None
+    }
+}
+
+fn render(scale: f64, symbol: &str) -> anyhow::Result<MemoryBuffer> {
+    let _span = tracy_client::span!("dnd_action_indicator::render");
+
+    let size: i32 = to_physical_precise_round(scale, SIZE);
+    let half = f64::from(size) / 2.;
+
+    let surface = ImageSurface::create(cairo::Format::ARgb32, size, size)?;
+    let cr = cairo::Context::new(&surface)?;
+    cr.set_source_rgba(0.1, 0.1, 0.1, 0.9);
+    cr.arc(half, half, half, 0., TAU);
+    cr.fill()?;
+
+    let mut font = FontDescription::from_string(FONT);
+    font.set_absolute_size(to_physical_precise_round(scale, font.size()));
+
+    let layout = pangocairo::functions::create_layout(&cr);
+    layout.context().set_round_glyph_positions(false);
+    layout.set_font_description(Some(&font));
+    layout.set_text(symbol);
+
+    let (width, height) = layout.pixel_size();
+    cr.move_to(half - f64::from(width) / 2., half - f64::from(height) / 2.);
+    cr.set_source_rgb(1., 1., 1.);
+    pangocairo::functions::show_layout(&cr, &layout);
+    drop(cr);
+
+    let data = surface.take_data().unwrap();
+    let buffer = MemoryBuffer::new(
+        data.to_vec(),
+        Fourcc::Argb8888,
+        (size, size),
+        scale,
+        Transform::Normal,
+    );
+
+    Ok(buffer)
+}
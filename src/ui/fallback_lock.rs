@@ -0,0 +1,319 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use ordered_float::NotNan;
+use pangocairo::cairo::{self, ImageSurface};
+use pangocairo::pango::{Alignment, FontDescription};
+use smithay::input::keyboard::{Keysym, ModifiersState};
+use smithay::output::Output;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::render_helpers::memory::MemoryBuffer;
+use crate::render_helpers::primary_gpu_texture::PrimaryGpuTextureRenderElement;
+use crate::render_helpers::renderer::NiriRenderer;
+use crate::ui::dialog::{render_centered, render_dialog};
+use crate::utils::to_physical_precise_round;
+
+const TITLE: &str = "The screen locker has stopped";
+const HINT: &str = "Type your password and press \
+                    <span face='mono' bgcolor='#2C2C2C'> Enter </span> to unlock.";
+const PADDING: i32 = 16;
+const FONT: &str = "sans 14px";
+const TITLE_FONT: &str = "sans bold 18px";
+const BORDER: i32 = 4;
+/// Maximum password length in bytes.
+///
+/// The password buffer is allocated once with this capacity and never grows, so that no copies
+/// of the password are left behind in freed memory.
+const MAX_PASSWORD_LEN: usize = 1024;
+
+/// Built-in lock screen shown when the screen locker dies while the session is locked.
+pub struct FallbackLock {
+    /// Password typed so far.
+    password: Zeroizing<String>,
+    /// Whether the password is being checked.
+    is_checking: bool,
+    /// Whether the last password was wrong.
+    has_failed: bool,
+    buffers: RefCell<HashMap<NotNan<f64>, Option<MemoryBuffer>>>,
+}
+
+impl FallbackLock {
+    pub fn new() -> Self {
+        Self {
+            password: new_password_buffer(),
+            is_checking: false,
+            has_failed: false,
+            buffers: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Handles a key press.
+    ///
+    /// Returns the password once the user submits it. Key presses are ignored while the
+    /// password is being checked.
+    pub fn on_key_press(
+        &mut self,
+        keysym: Keysym,
+        mods: ModifiersState,
+    ) -> Option<Zeroizing<String>> {
+        if self.is_checking {
+            return None;
+        }
+
+        match keysym {
+            Keysym::Return | Keysym::KP_Enter => {
+                if self.password.is_empty() {
+                    return None;
+                }
+
+                self.is_checking = true;
+                self.has_failed = false;
+                self.buffers.borrow_mut().clear();
+                return Some(std::mem::replace(&mut self.password, new_password_buffer()));
+            }
+            Keysym::Escape => self.clear_password(),
+            Keysym::BackSpace if mods.ctrl => self.clear_password(),
+            Keysym::BackSpace => {
+                self.password.pop();
+            }
+            _ => {
+                if mods.ctrl || mods.alt || mods.logo {
+                    return None;
+                }
+
+                let Some(c) = keysym.key_char().filter(|c| !c.is_control()) else {
+                    return None;
+                };
+                // Growing the buffer would leave a copy of the password in freed memory.
+                if self.password.len() + c.len_utf8() > self.password.capacity() {
+                    return None;
+                }
+                self.password.push(c);
+            }
+        }
+
+        self.buffers.borrow_mut().clear();
+        None
+    }
+
+    /// Records that the submitted password was wrong.
+    pub fn on_failed(&mut self) {
+        self.is_checking = false;
+        self.has_failed = true;
+        self.buffers.borrow_mut().clear();
+    }
+
+    fn clear_password(&mut self) {
+        // Wipes the whole buffer, including any characters removed with Backspace.
+        self.password.zeroize();
+    }
+
+    pub fn render<R: NiriRenderer>(
+        &self,
+        renderer: &mut R,
+        output: &Output,
+    ) -> Option<PrimaryGpuTextureRenderElement> {
+        let scale = output.current_scale().fractional_scale();
+
+        let status = if self.is_checking {
+            String::from("Checking…")
+        } else if self.password.is_empty() && self.has_failed {
+            String::from("Wrong password, try again.")
+        } else {
+            "●".repeat(self.password.chars().count())
+        };
+
+        let mut buffers = self.buffers.borrow_mut();
+        let buffer = buffers
+            .entry(NotNan::new(scale).unwrap())
+            .or_insert_with(|| {
+                render(scale, &status)
+                    .map_err(|err| warn!("error rendering fallback lock: {err:?}"))
+                    .ok()
+            });
+        let buffer = buffer.as_ref()?;
+
+        render_centered(renderer, output, buffer)
+    }
+}
+
+impl Default for FallbackLock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for FallbackLock {
+    fn drop(&mut self) {
+        self.clear_password();
+    }
+}
+
+fn new_password_buffer() -> Zeroizing<String> {
+    Zeroizing::new(String::with_capacity(MAX_PASSWORD_LEN))
+}
+
+fn render(scale: f64, status: &str) -> anyhow::Result<MemoryBuffer> {
+    let _span = tracy_client::span!("fallback_lock::render");
+
+    let padding: i32 = to_physical_precise_round(scale, PADDING);
+
+    let mut font = FontDescription::from_string(FONT);
+    font.set_absolute_size(to_physical_precise_round(scale, font.size()));
+    let mut title_font = FontDescription::from_string(TITLE_FONT);
+    title_font.set_absolute_size(to_physical_precise_round(scale, title_font.size()));
+
+    // Keep the status line from collapsing when there's nothing typed yet.
+    let status = if status.is_empty() { " " } else { status };
+
+    let surface = ImageSurface::create(cairo::Format::ARgb32, 0, 0)?;
+    let cr = cairo::Context::new(&surface)?;
+
+    let layout = pangocairo::functions::create_layout(&cr);
+    layout.context().set_round_glyph_positions(false);
+    layout.set_font_description(Some(&title_font));
+    layout.set_text(TITLE);
+    let (title_width, title_height) = layout.pixel_size();
+
+    layout.set_font_description(Some(&font));
+    layout.set_alignment(Alignment::Center);
+    layout.set_markup(HINT);
+    let (hint_width, hint_height) = layout.pixel_size();
+
+    layout.set_text(status);
+    let (status_width, status_height) = layout.pixel_size();
+
+    let inner_width = title_width.max(hint_width).max(status_width);
+    let width = inner_width + padding * 2;
+    let height = title_height + hint_height + status_height + padding * 4;
+
+    render_dialog(scale, width, height, BORDER, [0.9, 0.3, 0.3], |cr| {
+        let mut y = padding;
+
+        let layout = pangocairo::functions::create_layout(cr);
+        layout.context().set_round_glyph_positions(false);
+        layout.set_width(inner_width * pango::SCALE);
+        layout.set_alignment(Alignment::Center);
+
+        // Title.
+        cr.move_to(padding.into(), y.into());
+        layout.set_font_description(Some(&title_font));
+        layout.set_text(TITLE);
+        cr.set_source_rgb(1., 1., 1.);
+        pangocairo::functions::show_layout(cr, &layout);
+        y += title_height + padding;
+
+        // Hint.
+        cr.move_to(padding.into(), y.into());
+        layout.set_font_description(Some(&font));
+        layout.set_markup(HINT);
+        cr.set_source_rgb(0.7, 0.7, 0.7);
+        pangocairo::functions::show_layout(cr, &layout);
+        y += hint_height + padding;
+
+        // Password dots or status.
+        cr.move_to(padding.into(), y.into());
+        layout.set_text(status);
+        cr.set_source_rgb(1., 1., 1.);
+        pangocairo::functions::show_layout(cr, &layout);
+
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_text(lock: &mut FallbackLock, keysyms: &[Keysym]) {
+        for keysym in keysyms {
+            assert!(lock
+                .on_key_press(*keysym, ModifiersState::default())
+                .is_none());
+        }
+    }
+
+    fn submit(lock: &mut FallbackLock) -> Option<Zeroizing<String>> {
+        lock.on_key_press(Keysym::Return, ModifiersState::default())
+    }
+
+    #[test]
+    fn typing_and_submitting() {
+        let mut lock = FallbackLock::new();
+        type_text(&mut lock, &[Keysym::a, Keysym::B, Keysym::_1]);
+
+        let password = submit(&mut lock).unwrap();
+        assert_eq!(password.as_str(), "aB1");
+        assert!(lock.password.is_empty());
+        assert!(lock.is_checking);
+    }
+
+    #[test]
+    fn empty_password_is_not_submitted() {
+        let mut lock = FallbackLock::new();
+        assert!(submit(&mut lock).is_none());
+        assert!(!lock.is_checking);
+    }
+
+    #[test]
+    fn keys_ignored_while_checking() {
+        let mut lock = FallbackLock::new();
+        type_text(&mut lock, &[Keysym::a]);
+        submit(&mut lock).unwrap();
+
+        type_text(&mut lock, &[Keysym::b]);
+        assert!(submit(&mut lock).is_none());
+        assert!(lock.password.is_empty());
+
+        lock.on_failed();
+        assert!(!lock.is_checking);
+        assert!(lock.has_failed);
+
+        type_text(&mut lock, &[Keysym::c]);
+        assert_eq!(submit(&mut lock).unwrap().as_str(), "c");
+        assert!(!lock.has_failed);
+    }
+
+    #[test]
+    fn editing() {
+        let mut lock = FallbackLock::new();
+        type_text(
+            &mut lock,
+            &[Keysym::a, Keysym::b, Keysym::BackSpace, Keysym::c],
+        );
+        assert_eq!(lock.password.as_str(), "ac");
+
+        type_text(&mut lock, &[Keysym::Escape]);
+        assert!(lock.password.is_empty());
+
+        type_text(&mut lock, &[Keysym::a, Keysym::b]);
+        let ctrl = ModifiersState {
+            ctrl: true,
+            ..Default::default()
+        };
+        assert!(lock.on_key_press(Keysym::BackSpace, ctrl).is_none());
+        assert!(lock.password.is_empty());
+    }
+
+    #[test]
+    fn modifiers_and_control_keys_are_not_typed() {
+        let mut lock = FallbackLock::new();
+        let ctrl = ModifiersState {
+            ctrl: true,
+            ..Default::default()
+        };
+        assert!(lock.on_key_press(Keysym::a, ctrl).is_none());
+        type_text(&mut lock, &[Keysym::Tab, Keysym::Shift_L, Keysym::Left]);
+        assert!(lock.password.is_empty());
+    }
+
+    #[test]
+    fn password_length_is_limited() {
+        let mut lock = FallbackLock::new();
+        for _ in 0..MAX_PASSWORD_LEN + 10 {
+            type_text(&mut lock, &[Keysym::a]);
+        }
+        assert_eq!(lock.password.len(), MAX_PASSWORD_LEN);
+    }
+}
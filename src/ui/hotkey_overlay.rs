@@ -29,6 +29,8 @@ const TITLE: &str = "Important Hotkeys";
 
 pub struct HotkeyOverlay {
     is_open: bool,
+    /// Whether the overlay was opened by holding the Mod key, and should close on release.
+    opened_on_mod_hold: bool,
     config: Rc<RefCell<Config>>,
     comp_mod: CompositorMod,
     buffers: RefCell<HashMap<WeakOutput, RenderedOverlay>>,
@@ -42,6 +44,7 @@ impl HotkeyOverlay {
     pub fn new(config: Rc<RefCell<Config>>, comp_mod: CompositorMod) -> Self {
         Self {
             is_open: false,
+            opened_on_mod_hold: false,
             config,
             comp_mod,
             buffers: RefCell::new(HashMap::new()),
@@ -51,6 +54,16 @@ impl HotkeyOverlay {
     pub fn show(&mut self) -> bool {
         if !self.is_open {
             self.is_open = true;
+            self.opened_on_mod_hold = false;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn show_on_mod_hold(&mut self) -> bool {
+        if self.show() {
+            self.opened_on_mod_hold = true;
             true
         } else {
             false
@@ -60,12 +73,18 @@ impl HotkeyOverlay {
     pub fn hide(&mut self) -> bool {
         if self.is_open {
             self.is_open = false;
+            self.opened_on_mod_hold = false;
             true
         } else {
             false
         }
     }
 
+    /// Hides the overlay if it was opened by holding the Mod key.
+    pub fn hide_on_mod_release(&mut self) -> bool {
+        self.opened_on_mod_hold && self.hide()
+    }
+
     pub fn is_open(&self) -> bool {
         self.is_open
     }
@@ -169,33 +188,33 @@ fn render(
     ]);
 
     // Prefer move-column-to-workspace-down, but fall back to move-window-to-workspace-down.
-    if binds
+    if let Some(bind) = binds
         .iter()
-        .any(|bind| bind.action == Action::MoveColumnToWorkspaceDown)
+        .find(|bind| matches!(bind.action, Action::MoveColumnToWorkspaceDown(_)))
     {
-        actions.push(&Action::MoveColumnToWorkspaceDown);
-    } else if binds
+        actions.push(&bind.action);
+    } else if let Some(bind) = binds
         .iter()
-        .any(|bind| bind.action == Action::MoveWindowToWorkspaceDown)
+        .find(|bind| matches!(bind.action, Action::MoveWindowToWorkspaceDown(_)))
     {
-        actions.push(&Action::MoveWindowToWorkspaceDown);
+        actions.push(&bind.action);
     } else {
-        actions.push(&Action::MoveColumnToWorkspaceDown);
+        actions.push(&Action::MoveColumnToWorkspaceDown(None));
     }
 
     // Same for -up.
-    if binds
+    if let Some(bind) = binds
         .iter()
-        .any(|bind| bind.action == Action::MoveColumnToWorkspaceUp)
+        .find(|bind| matches!(bind.action, Action::MoveColumnToWorkspaceUp(_)))
     {
-        actions.push(&Action::MoveColumnToWorkspaceUp);
-    } else if binds
+        actions.push(&bind.action);
+    } else if let Some(bind) = binds
         .iter()
-        .any(|bind| bind.action == Action::MoveWindowToWorkspaceUp)
+        .find(|bind| matches!(bind.action, Action::MoveWindowToWorkspaceUp(_)))
     {
-        actions.push(&Action::MoveWindowToWorkspaceUp);
+        actions.push(&bind.action);
     } else {
-        actions.push(&Action::MoveColumnToWorkspaceUp);
+        actions.push(&Action::MoveColumnToWorkspaceUp(None));
     }
 
     actions.extend(&[
@@ -215,7 +234,7 @@ fn render(
     // Add the spawn actions.
     let mut spawn_actions = Vec::new();
     for bind in binds.iter().filter(|bind| {
-        matches!(bind.action, Action::Spawn(_))
+        matches!(bind.action, Action::Spawn(..))
             // Only show binds with Mod or Super to filter out stuff like volume up/down.
             && (bind.key.modifiers.contains(Modifiers::COMPOSITOR)
                 || bind.key.modifiers.contains(Modifiers::SUPER))
@@ -372,10 +391,10 @@ fn action_name(action: &Action) -> String {
         Action::MoveColumnRight => String::from("Move Column Right"),
         Action::FocusWorkspaceDown => String::from("Switch Workspace Down"),
         Action::FocusWorkspaceUp => String::from("Switch Workspace Up"),
-        Action::MoveColumnToWorkspaceDown => String::from("Move Column to Workspace Down"),
-        Action::MoveColumnToWorkspaceUp => String::from("Move Column to Workspace Up"),
-        Action::MoveWindowToWorkspaceDown => String::from("Move Window to Workspace Down"),
-        Action::MoveWindowToWorkspaceUp => String::from("Move Window to Workspace Up"),
+        Action::MoveColumnToWorkspaceDown(_) => String::from("Move Column to Workspace Down"),
+        Action::MoveColumnToWorkspaceUp(_) => String::from("Move Column to Workspace Up"),
+        Action::MoveWindowToWorkspaceDown(_) => String::from("Move Window to Workspace Down"),
+        Action::MoveWindowToWorkspaceUp(_) => String::from("Move Window to Workspace Up"),
         Action::SwitchPresetColumnWidth => String::from("Switch Preset Column Widths"),
         Action::MaximizeColumn => String::from("Maximize Column"),
         Action::ConsumeOrExpelWindowLeft => String::from("Consume or Expel Window Left"),
@@ -385,7 +404,7 @@ fn action_name(action: &Action) -> String {
             String::from("Switch Focus Between Floating and Tiling")
         }
         Action::Screenshot => String::from("Take a Screenshot"),
-        Action::Spawn(args) => format!(
+        Action::Spawn(args, _) => format!(
             "Spawn <span face='monospace' bgcolor='#000000'>{}</span>",
             args.first().unwrap_or(&String::new())
         ),
@@ -393,7 +412,7 @@ fn action_name(action: &Action) -> String {
     }
 }
 
-fn key_name(comp_mod: CompositorMod, key: &Key) -> String {
+pub fn key_name(comp_mod: CompositorMod, key: &Key) -> String {
     let mut name = String::new();
 
     let has_comp_mod = key.modifiers.contains(Modifiers::COMPOSITOR);
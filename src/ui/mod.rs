@@ -1,5 +1,14 @@
+pub mod clipboard_history;
 pub mod config_error_notification;
-pub mod exit_confirm_dialog;
+pub mod confirm_dialog;
+pub mod dialog;
+pub mod dnd_action_indicator;
+#[cfg(feature = "fallback-lock")]
+pub mod fallback_lock;
 pub mod hotkey_overlay;
 pub mod screen_transition;
+pub mod screenshot_feedback;
 pub mod screenshot_ui;
+pub mod status_bar;
+pub mod watermark;
+pub mod workspace_rename_dialog;
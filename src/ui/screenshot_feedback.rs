@@ -0,0 +1,136 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::rc::Rc;
+
+use niri_config::{Color, Config, ScreenshotFeedbackKind};
+use smithay::backend::renderer::element::solid::{SolidColorBuffer, SolidColorRenderElement};
+use smithay::backend::renderer::element::Kind;
+use smithay::output::Output;
+use smithay::utils::{Logical, Point};
+
+use crate::animation::{Animation, Clock, Curve};
+use crate::utils::output_size;
+
+const FLASH_COLOR: Color = Color::new_unpremul(1., 1., 1., 1.);
+const SHUTTER_COLOR: Color = Color::new_unpremul(0., 0., 0., 1.);
+
+/// Visual feedback on an output when a screenshot of it is taken.
+pub struct ScreenshotFeedback {
+    /// Ongoing feedback for every output.
+    ongoing: HashMap<Output, Ongoing>,
+    clock: Clock,
+    config: Rc<RefCell<Config>>,
+}
+
+struct Ongoing {
+    kind: ScreenshotFeedbackKind,
+    anim: Animation,
+    /// Flash overlay, or the top shutter blade.
+    first: SolidColorBuffer,
+    /// The bottom shutter blade.
+    second: SolidColorBuffer,
+}
+
+impl ScreenshotFeedback {
+    pub fn new(clock: Clock, config: Rc<RefCell<Config>>) -> Self {
+        Self {
+            ongoing: HashMap::new(),
+            clock,
+            config,
+        }
+    }
+
+    pub fn show(&mut self, output: &Output) {
+        let config = self.config.borrow().screenshot.feedback;
+
+        let color = match config.kind {
+            ScreenshotFeedbackKind::None => return,
+            ScreenshotFeedbackKind::Flash => config.color.unwrap_or(FLASH_COLOR),
+            ScreenshotFeedbackKind::Shutter => config.color.unwrap_or(SHUTTER_COLOR),
+        };
+        if config.duration_ms == 0 {
+            return;
+        }
+
+        let anim = Animation::ease(
+            self.clock.clone(),
+            0.,
+            1.,
+            0.,
+            u64::from(config.duration_ms),
+            Curve::Linear,
+        );
+
+        let size = output_size(output).to_i32_ceil();
+        let color = color.to_array_premul();
+        self.ongoing.insert(
+            output.clone(),
+            Ongoing {
+                kind: config.kind,
+                anim,
+                first: SolidColorBuffer::new(size, color),
+                second: SolidColorBuffer::new(size, color),
+            },
+        );
+    }
+
+    pub fn advance_animations(&mut self) {
+        self.ongoing.retain(|_, ongoing| !ongoing.anim.is_done());
+    }
+
+    pub fn are_animations_ongoing(&self, output: &Output) -> bool {
+        self.ongoing.contains_key(output)
+    }
+
+    pub fn render(&self, output: &Output) -> impl Iterator<Item = SolidColorRenderElement> {
+        let mut rv = Vec::new();
+
+        let Some(ongoing) = self.ongoing.get(output) else {
+            return rv.into_iter();
+        };
+
+        let scale = output.current_scale().fractional_scale();
+        let progress = ongoing.anim.clamped_value().clamp(0., 1.);
+
+        match ongoing.kind {
+            ScreenshotFeedbackKind::None => (),
+            ScreenshotFeedbackKind::Flash => {
+                // Show the flash right away, then fade it out.
+                let alpha = (1. - progress) as f32;
+                rv.push(SolidColorRenderElement::from_buffer(
+                    &ongoing.first,
+                    (0, 0),
+                    scale,
+                    alpha,
+                    Kind::Unspecified,
+                ));
+            }
+            ScreenshotFeedbackKind::Shutter => {
+                // The blades close towards the middle of the output and open back up.
+                let size = output_size(output);
+                let closed = (progress * PI).sin() * size.h / 2.;
+
+                let top = Point::<f64, Logical>::from((0., closed - size.h));
+                let bottom = Point::<f64, Logical>::from((0., size.h - closed));
+
+                rv.push(SolidColorRenderElement::from_buffer(
+                    &ongoing.first,
+                    top.to_physical_precise_round(scale),
+                    scale,
+                    1.,
+                    Kind::Unspecified,
+                ));
+                rv.push(SolidColorRenderElement::from_buffer(
+                    &ongoing.second,
+                    bottom.to_physical_precise_round(scale),
+                    scale,
+                    1.,
+                    Kind::Unspecified,
+                ));
+            }
+        }
+
+        rv.into_iter()
+    }
+}
@@ -182,16 +182,17 @@ impl ScreenshotUi {
             })
             .collect();
 
-        let open_anim = {
+        let (open_anim, show_pointer) = {
             let c = config.borrow();
-            Animation::new(clock.clone(), 0., 1., 0., c.animations.screenshot_ui_open.0)
+            let anim = Animation::new(clock.clone(), 0., 1., 0., c.animations.screenshot_ui_open.0);
+            (anim, c.screenshot.include_pointer)
         };
 
         *self = Self::Open {
             selection,
             output_data,
             mouse_down: false,
-            show_pointer: true,
+            show_pointer,
             open_anim,
             clock: clock.clone(),
             config: config.clone(),
@@ -491,6 +492,19 @@ impl ScreenshotUi {
         }
     }
 
+    /// Returns the current selection output and the selected rectangle.
+    pub fn selection(&self) -> Option<(&Output, Rectangle<i32, Physical>)> {
+        if let Self::Open {
+            selection: (output, a, b),
+            ..
+        } = self
+        {
+            Some((output, rect_from_corner_points(*a, *b)))
+        } else {
+            None
+        }
+    }
+
     pub fn output_size(&self, output: &Output) -> Option<(Size<i32, Physical>, f64, Transform)> {
         if let Self::Open { output_data, .. } = self {
             let data = output_data.get(output)?;
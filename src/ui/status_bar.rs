@@ -0,0 +1,364 @@
+//! Minimal status bar drawn by niri itself, for systems without a bar.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::mem::MaybeUninit;
+use std::rc::Rc;
+use std::time::Duration;
+
+use niri_config::{Color, Config};
+use pangocairo::cairo::{self, ImageSurface};
+use pangocairo::pango::FontDescription;
+use smithay::backend::renderer::element::Kind;
+use smithay::backend::renderer::gles::{GlesRenderer, GlesTexture};
+use smithay::desktop::layer_map_for_output;
+use smithay::output::Output;
+use smithay::reexports::gbm::Format as Fourcc;
+use smithay::utils::{Logical, Point, Rectangle, Size, Transform};
+
+use crate::layout::workspace::WorkspaceId;
+use crate::layout::Layout;
+use crate::render_helpers::primary_gpu_texture::PrimaryGpuTextureRenderElement;
+use crate::render_helpers::renderer::NiriRenderer;
+use crate::render_helpers::texture::{TextureBuffer, TextureRenderElement};
+use crate::utils::to_physical_precise_round;
+use crate::window::Mapped;
+
+const PADDING: i32 = 8;
+const FONT: &str = "sans 12px";
+
+pub struct StatusBar {
+    config: Rc<RefCell<Config>>,
+    /// Whether the bar is shown.
+    ///
+    /// Starts out as configured, and can be toggled at runtime.
+    is_on: bool,
+    bars: RefCell<HashMap<Output, RenderedBar>>,
+}
+
+/// Everything shown on the bar of one output.
+#[derive(Debug, Clone, PartialEq)]
+struct Contents {
+    output_name: String,
+    workspaces: Vec<BarWorkspace>,
+    clock: String,
+    is_focused_output: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct BarWorkspace {
+    id: WorkspaceId,
+    label: String,
+    is_active: bool,
+}
+
+struct RenderedBar {
+    contents: Contents,
+    config: niri_config::StatusBar,
+    scale: f64,
+    width: i32,
+    buffer: Option<TextureBuffer<GlesTexture>>,
+    /// Horizontal extents of the workspace labels, in logical coordinates.
+    workspace_ranges: Vec<(f64, f64, WorkspaceId)>,
+}
+
+impl StatusBar {
+    pub fn new(config: Rc<RefCell<Config>>) -> Self {
+        let is_on = config.borrow().status_bar.on;
+        Self {
+            config,
+            is_on,
+            bars: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.is_on
+    }
+
+    pub fn toggle(&mut self) {
+        self.is_on = !self.is_on;
+    }
+
+    /// Applies the `on` setting after it changed in the config.
+    pub fn set_on(&mut self, on: bool) {
+        self.is_on = on;
+    }
+
+    /// Returns the height to reserve for the bar at the top of the outputs.
+    pub fn reserved_height(&self) -> f64 {
+        if self.is_on {
+            f64::from(self.config.borrow().status_bar.height)
+        } else {
+            0.
+        }
+    }
+
+    /// Returns the area of the bar within the output.
+    ///
+    /// The bar goes below the exclusive zones of layer-shell panels, like a panel itself.
+    fn area(&self, output: &Output) -> Option<Rectangle<f64, Logical>> {
+        if !self.is_on {
+            return None;
+        }
+
+        let height = f64::from(self.config.borrow().status_bar.height);
+        let zone = layer_map_for_output(output).non_exclusive_zone().to_f64();
+        Some(Rectangle::new(zone.loc, Size::from((zone.size.w, height))))
+    }
+
+    /// Returns whether the point is over the bar.
+    pub fn is_under(&self, output: &Output, pos_within_output: Point<f64, Logical>) -> bool {
+        self.area(output)
+            .is_some_and(|area| area.contains(pos_within_output))
+    }
+
+    /// Returns the workspace whose label is under the point.
+    pub fn workspace_under(
+        &self,
+        output: &Output,
+        pos_within_output: Point<f64, Logical>,
+    ) -> Option<WorkspaceId> {
+        let area = self.area(output)?;
+        if !area.contains(pos_within_output) {
+            return None;
+        }
+
+        let bars = self.bars.borrow();
+        let bar = bars.get(output)?;
+        let x = pos_within_output.x - area.loc.x;
+        bar.workspace_ranges
+            .iter()
+            .find(|(start, end, _)| (*start..*end).contains(&x))
+            .map(|(_, _, id)| *id)
+    }
+
+    /// Forgets the bar of a removed output.
+    pub fn remove_output(&self, output: &Output) {
+        self.bars.borrow_mut().remove(output);
+    }
+
+    pub fn render<R: NiriRenderer>(
+        &self,
+        renderer: &mut R,
+        output: &Output,
+        layout: &Layout<Mapped>,
+    ) -> Option<PrimaryGpuTextureRenderElement> {
+        let area = self.area(output)?;
+        let config = self.config.borrow();
+
+        let scale = output.current_scale().fractional_scale();
+        let width = to_physical_precise_round(scale, area.size.w);
+        let contents = contents(&config, output, layout);
+
+        let mut bars = self.bars.borrow_mut();
+        let bar = bars.entry(output.clone()).or_insert_with(|| RenderedBar {
+            contents: contents.clone(),
+            config: config.status_bar,
+            scale,
+            width,
+            buffer: None,
+            workspace_ranges: Vec::new(),
+        });
+
+        let is_stale = bar.buffer.is_none()
+            || bar.contents != contents
+            || bar.config != config.status_bar
+            || bar.scale != scale
+            || bar.width != width;
+        if is_stale {
+            let renderer = renderer.as_gles_renderer();
+            match render(renderer, scale, width, &contents, &config.status_bar) {
+                Ok((buffer, workspace_ranges)) => {
+                    bar.buffer = Some(buffer);
+                    bar.workspace_ranges = workspace_ranges;
+                }
+                Err(err) => {
+                    warn!("error rendering status bar: {err:?}");
+                    bar.buffer = None;
+                    bar.workspace_ranges.clear();
+                }
+            }
+            bar.contents = contents;
+            bar.config = config.status_bar;
+            bar.scale = scale;
+            bar.width = width;
+        }
+
+        let buffer = bar.buffer.clone()?;
+        let location = area.loc.to_physical_precise_round(scale).to_logical(scale);
+
+        let elem = TextureRenderElement::from_texture_buffer(
+            buffer,
+            location,
+            1.,
+            None,
+            None,
+            Kind::Unspecified,
+        );
+        Some(PrimaryGpuTextureRenderElement(elem))
+    }
+}
+
+fn contents(config: &Config, output: &Output, layout: &Layout<Mapped>) -> Contents {
+    let workspaces = layout
+        .workspaces()
+        .filter_map(|(mon, idx, ws)| {
+            let mon = mon.filter(|mon| mon.output() == output)?;
+
+            let icon = ws.name().and_then(|name| {
+                let ws_config = config
+                    .workspaces
+                    .iter()
+                    .find(|ws_config| ws_config.name.0.eq_ignore_ascii_case(name))?;
+                ws_config.icon.clone()
+            });
+            let label = icon
+                .or_else(|| ws.name().cloned())
+                .or_else(|| ws.auto_name().cloned())
+                .unwrap_or_else(|| (idx + 1).to_string());
+
+            Some(BarWorkspace {
+                id: ws.id(),
+                label,
+                is_active: mon.active_workspace_idx() == idx,
+            })
+        })
+        .collect();
+
+    Contents {
+        output_name: output.name(),
+        workspaces,
+        clock: clock().unwrap_or_default(),
+        is_focused_output: layout.active_output() == Some(output),
+    }
+}
+
+/// Returns the local time formatted as hours and minutes.
+fn clock() -> Option<String> {
+    let (hour, min, _) = local_time()?;
+    Some(format!("{hour:02}:{min:02}"))
+}
+
+/// Returns how long until the clock on the bar changes.
+pub fn time_until_next_minute() -> Duration {
+    let sec = local_time().map_or(0, |(_, _, sec)| sec);
+    // Leap seconds can make the seconds go up to 60.
+    Duration::from_secs(60 - sec.clamp(0, 59) as u64)
+}
+
+/// Returns the local time as hours, minutes and seconds.
+fn local_time() -> Option<(i32, i32, i32)> {
+    // SAFETY: time() accepts a null pointer. localtime_r() is the thread-safe variant that writes
+    // into our tm, which we only read after it reports success.
+    unsafe {
+        let time = libc::time(std::ptr::null_mut());
+        if time == -1 {
+            return None;
+        }
+
+        let mut tm = MaybeUninit::<libc::tm>::uninit();
+        if libc::localtime_r(&time, tm.as_mut_ptr()).is_null() {
+            return None;
+        }
+        let tm = tm.assume_init();
+
+        Some((tm.tm_hour, tm.tm_min, tm.tm_sec))
+    }
+}
+
+fn set_source_color(cr: &cairo::Context, color: Color) {
+    let [r, g, b, a] = color.to_array_unpremul();
+    cr.set_source_rgba(r.into(), g.into(), b.into(), a.into());
+}
+
+#[allow(clippy::type_complexity)]
+fn render(
+    renderer: &mut GlesRenderer,
+    scale: f64,
+    width: i32,
+    contents: &Contents,
+    config: &niri_config::StatusBar,
+) -> anyhow::Result<(TextureBuffer<GlesTexture>, Vec<(f64, f64, WorkspaceId)>)> {
+    let _span = tracy_client::span!("status_bar::render");
+
+    let padding: i32 = to_physical_precise_round(scale, PADDING);
+    let height: i32 = to_physical_precise_round(scale, config.height);
+
+    let mut font = FontDescription::from_string(FONT);
+    font.set_absolute_size(to_physical_precise_round(scale, font.size()));
+
+    let surface = ImageSurface::create(cairo::Format::ARgb32, width, height)?;
+    let cr = cairo::Context::new(&surface)?;
+    set_source_color(&cr, config.background_color);
+    cr.paint()?;
+
+    let layout = pangocairo::functions::create_layout(&cr);
+    layout.context().set_round_glyph_positions(false);
+    layout.set_font_description(Some(&font));
+
+    let mut x = padding;
+
+    // Output name.
+    layout.set_text(&contents.output_name);
+    let (text_width, text_height) = layout.pixel_size();
+    set_source_color(&cr, config.text_color);
+    cr.move_to(x.into(), f64::from(height - text_height) / 2.);
+    pangocairo::functions::show_layout(&cr, &layout);
+    x += text_width + padding;
+
+    // Workspaces.
+    let mut workspace_ranges = Vec::new();
+    for ws in &contents.workspaces {
+        layout.set_text(&ws.label);
+        let (text_width, text_height) = layout.pixel_size();
+        let label_width = text_width + padding * 2;
+
+        if ws.is_active {
+            let mut color = config.active_workspace_color;
+            // Dim the active workspace on unfocused outputs.
+            if !contents.is_focused_output {
+                color.a *= 0.5;
+            }
+            set_source_color(&cr, color);
+            cr.rectangle(x.into(), 0., label_width.into(), height.into());
+            cr.fill()?;
+        }
+
+        set_source_color(&cr, config.text_color);
+        cr.move_to((x + padding).into(), f64::from(height - text_height) / 2.);
+        pangocairo::functions::show_layout(&cr, &layout);
+
+        let start = f64::from(x) / scale;
+        let end = f64::from(x + label_width) / scale;
+        workspace_ranges.push((start, end, ws.id));
+        x += label_width;
+    }
+
+    // Clock on the right.
+    layout.set_text(&contents.clock);
+    let (text_width, text_height) = layout.pixel_size();
+    set_source_color(&cr, config.text_color);
+    cr.move_to(
+        (width - padding - text_width).into(),
+        f64::from(height - text_height) / 2.,
+    );
+    pangocairo::functions::show_layout(&cr, &layout);
+
+    drop(layout);
+    drop(cr);
+
+    let data = surface.take_data().unwrap();
+    let buffer = TextureBuffer::from_memory(
+        renderer,
+        &data,
+        Fourcc::Argb8888,
+        (width, height),
+        false,
+        scale,
+        Transform::Normal,
+        Vec::new(),
+    )?;
+
+    Ok((buffer, workspace_ranges))
+}
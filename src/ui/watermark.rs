@@ -0,0 +1,250 @@
+//! Text or image drawn over the outputs, for demos and screencasts.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+use std::rc::Rc;
+
+use anyhow::{ensure, Context};
+use niri_config::{Config, RelativeTo};
+use ordered_float::NotNan;
+use pangocairo::cairo::{self, ImageSurface};
+use pangocairo::pango::FontDescription;
+use smithay::backend::renderer::element::Kind;
+use smithay::backend::renderer::gles::{GlesRenderer, GlesTexture};
+use smithay::output::Output;
+use smithay::reexports::gbm::Format as Fourcc;
+use smithay::utils::{Logical, Point, Size, Transform};
+
+use crate::render_helpers::primary_gpu_texture::PrimaryGpuTextureRenderElement;
+use crate::render_helpers::renderer::NiriRenderer;
+use crate::render_helpers::texture::{TextureBuffer, TextureRenderElement};
+use crate::render_helpers::RenderTarget;
+use crate::utils::{expand_home, output_matches_name, output_size, to_physical_precise_round};
+
+const MARGIN: i32 = 16;
+const SPACING: i32 = 8;
+const FONT: &str = "sans bold 24px";
+
+pub struct Watermark {
+    config: Rc<RefCell<Config>>,
+    /// Whether the watermark is shown.
+    ///
+    /// Starts out as configured, and can be toggled at runtime.
+    is_on: bool,
+    /// Config that the buffers were rendered with.
+    rendered_config: RefCell<niri_config::Watermark>,
+    buffers: RefCell<HashMap<NotNan<f64>, Option<TextureBuffer<GlesTexture>>>>,
+}
+
+impl Watermark {
+    pub fn new(config: Rc<RefCell<Config>>) -> Self {
+        let rendered_config = config.borrow().watermark.clone();
+        Self {
+            config,
+            is_on: rendered_config.on,
+            rendered_config: RefCell::new(rendered_config),
+            buffers: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.is_on = !self.is_on;
+    }
+
+    /// Applies the `on` setting after it changed in the config.
+    pub fn set_on(&mut self, on: bool) {
+        self.is_on = on;
+    }
+
+    pub fn render<R: NiriRenderer>(
+        &self,
+        renderer: &mut R,
+        output: &Output,
+        target: RenderTarget,
+    ) -> Option<PrimaryGpuTextureRenderElement> {
+        self.render_in_area(renderer, output, output_size(output), target)
+    }
+
+    /// Renders the watermark over an area of the given size, like a window in a window cast.
+    ///
+    /// `output` is the output that the area is on.
+    pub fn render_in_area<R: NiriRenderer>(
+        &self,
+        renderer: &mut R,
+        output: &Output,
+        area_size: Size<f64, Logical>,
+        target: RenderTarget,
+    ) -> Option<PrimaryGpuTextureRenderElement> {
+        if !self.is_on {
+            return None;
+        }
+
+        let config = self.config.borrow();
+        let config = &config.watermark;
+        if config.screencast_only && target != RenderTarget::Screencast {
+            return None;
+        }
+        if !config.outputs.is_empty()
+            && !config
+                .outputs
+                .iter()
+                .any(|name| output_matches_name(output, name))
+        {
+            return None;
+        }
+
+        let mut rendered_config = self.rendered_config.borrow_mut();
+        if *rendered_config != *config {
+            *rendered_config = config.clone();
+            self.buffers.borrow_mut().clear();
+        }
+
+        let scale = output.current_scale().fractional_scale();
+
+        let mut buffers = self.buffers.borrow_mut();
+        let buffer = buffers
+            .entry(NotNan::new(scale).unwrap())
+            .or_insert_with(|| {
+                render(renderer.as_gles_renderer(), scale, config)
+                    .map_err(|err| warn!("error rendering watermark: {err:?}"))
+                    .ok()
+            });
+        let buffer = buffer.clone()?;
+
+        let size = buffer.logical_size();
+        let margin = f64::from(MARGIN);
+        let right = area_size.w - size.w - margin;
+        let bottom = area_size.h - size.h - margin;
+        let location = match config.position {
+            RelativeTo::TopLeft => Point::from((margin, margin)),
+            RelativeTo::TopRight => Point::from((right, margin)),
+            RelativeTo::BottomLeft => Point::from((margin, bottom)),
+            RelativeTo::BottomRight => Point::from((right, bottom)),
+        };
+        let mut location = location.to_physical_precise_round(scale).to_logical(scale);
+        location.x = f64::max(0., location.x);
+        location.y = f64::max(0., location.y);
+
+        let elem = TextureRenderElement::from_texture_buffer(
+            buffer,
+            location,
+            config.opacity.0 as f32,
+            None,
+            None,
+            Kind::Unspecified,
+        );
+        Some(PrimaryGpuTextureRenderElement(elem))
+    }
+}
+
+fn load_image(path: &str) -> anyhow::Result<ImageSurface> {
+    let path = Path::new(path);
+    let path = expand_home(path)?.unwrap_or_else(|| path.to_owned());
+    let mut file = File::open(&path).with_context(|| format!("error opening {path:?}"))?;
+    let image = ImageSurface::create_from_png(&mut file)?;
+    ensure!(image.width() > 0 && image.height() > 0, "image is empty");
+    Ok(image)
+}
+
+fn render(
+    renderer: &mut GlesRenderer,
+    scale: f64,
+    config: &niri_config::Watermark,
+) -> anyhow::Result<TextureBuffer<GlesTexture>> {
+    let _span = tracy_client::span!("watermark::render");
+
+    let spacing: i32 = to_physical_precise_round(scale, SPACING);
+
+    let mut font = FontDescription::from_string(FONT);
+    font.set_absolute_size(to_physical_precise_round(scale, font.size()));
+
+    // The image is sized in logical pixels.
+    let image = config.image.as_deref().map(load_image).transpose()?;
+    let (image_width, image_height) = image.as_ref().map_or((0, 0), |image| {
+        (
+            to_physical_precise_round(scale, image.width()),
+            to_physical_precise_round(scale, image.height()),
+        )
+    });
+
+    let surface = ImageSurface::create(cairo::Format::ARgb32, 0, 0)?;
+    let cr = cairo::Context::new(&surface)?;
+    let layout = pangocairo::functions::create_layout(&cr);
+    layout.context().set_round_glyph_positions(false);
+    layout.set_font_description(Some(&font));
+    layout.set_text(config.text.as_deref().unwrap_or_default());
+    let (text_width, text_height) = match config.text {
+        Some(_) => layout.pixel_size(),
+        None => (0, 0),
+    };
+
+    let gap = if image.is_some() && config.text.is_some() {
+        spacing
+    } else {
+        0
+    };
+    let width = image_width.max(text_width);
+    let height = image_height + gap + text_height;
+    ensure!(
+        width > 0 && height > 0,
+        "watermark has neither text nor image"
+    );
+
+    let surface = ImageSurface::create(cairo::Format::ARgb32, width, height)?;
+    let cr = cairo::Context::new(&surface)?;
+
+    // Align the contents towards the edge of the output.
+    let align = |size: i32| match config.position {
+        RelativeTo::TopLeft | RelativeTo::BottomLeft => 0.,
+        RelativeTo::TopRight | RelativeTo::BottomRight => f64::from(width - size),
+    };
+
+    if let Some(image) = &image {
+        cr.save()?;
+        cr.translate(align(image_width), 0.);
+        cr.scale(
+            f64::from(image_width) / f64::from(image.width()),
+            f64::from(image_height) / f64::from(image.height()),
+        );
+        cr.set_source_surface(image, 0., 0.)?;
+        cr.paint()?;
+        cr.restore()?;
+    }
+
+    if let Some(text) = &config.text {
+        let layout = pangocairo::functions::create_layout(&cr);
+        layout.context().set_round_glyph_positions(false);
+        layout.set_font_description(Some(&font));
+        layout.set_text(text);
+
+        let x = align(text_width);
+        let y = f64::from(image_height + gap);
+
+        // Dark shadow to keep the text readable on light backgrounds.
+        let offset = f64::max(1., scale.round());
+        cr.move_to(x + offset, y + offset);
+        cr.set_source_rgba(0., 0., 0., 0.6);
+        pangocairo::functions::show_layout(&cr, &layout);
+
+        cr.move_to(x, y);
+        cr.set_source_rgb(1., 1., 1.);
+        pangocairo::functions::show_layout(&cr, &layout);
+    }
+    drop(cr);
+
+    let data = surface.take_data().unwrap();
+    let buffer = TextureBuffer::from_memory(
+        renderer,
+        &data,
+        Fourcc::Argb8888,
+        (width, height),
+        false,
+        scale,
+        Transform::Normal,
+        Vec::new(),
+    )?;
+
+    Ok(buffer)
+}
@@ -0,0 +1,250 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use ordered_float::NotNan;
+use pangocairo::cairo::{self, ImageSurface};
+use pangocairo::pango::{Alignment, FontDescription};
+use smithay::input::keyboard::{Keysym, ModifiersState};
+use smithay::output::Output;
+
+use crate::layout::workspace::WorkspaceId;
+use crate::render_helpers::memory::MemoryBuffer;
+use crate::render_helpers::primary_gpu_texture::PrimaryGpuTextureRenderElement;
+use crate::render_helpers::renderer::NiriRenderer;
+use crate::ui::dialog::{render_centered, render_dialog};
+use crate::utils::to_physical_precise_round;
+
+const TITLE: &str = "Rename workspace";
+const HINT: &str = "Press <span face='mono' bgcolor='#2C2C2C'> Enter </span> to confirm, \
+                    <span face='mono' bgcolor='#2C2C2C'> Escape </span> to cancel.\n\
+                    Leave empty to unset the name.";
+const PADDING: i32 = 16;
+const FONT: &str = "sans 14px";
+const ENTRY_FONT: &str = "sans 18px";
+const BORDER: i32 = 4;
+const MIN_ENTRY_WIDTH: i32 = 300;
+
+/// Text entry for renaming a workspace.
+pub struct WorkspaceRenameDialog {
+    state: Option<OpenState>,
+    buffers: RefCell<HashMap<NotNan<f64>, Option<MemoryBuffer>>>,
+}
+
+struct OpenState {
+    /// Workspace being renamed.
+    workspace: WorkspaceId,
+    /// Output to show the dialog on.
+    output: Output,
+    /// Text typed so far.
+    text: String,
+}
+
+impl WorkspaceRenameDialog {
+    pub fn new() -> Self {
+        Self {
+            state: None,
+            buffers: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn show(&mut self, workspace: WorkspaceId, output: Output, name: Option<&str>) -> bool {
+        if self
+            .state
+            .as_ref()
+            .is_some_and(|state| state.workspace == workspace)
+        {
+            return false;
+        }
+
+        self.state = Some(OpenState {
+            workspace,
+            output,
+            text: name.unwrap_or_default().to_owned(),
+        });
+        self.buffers.borrow_mut().clear();
+        true
+    }
+
+    pub fn hide(&mut self) -> bool {
+        if self.state.take().is_some() {
+            self.buffers.borrow_mut().clear();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.state.is_some()
+    }
+
+    /// Handles a key press while the dialog is open.
+    ///
+    /// Returns the workspace and its new name once the user confirms. An empty name means that
+    /// the name should be unset.
+    pub fn on_key_press(
+        &mut self,
+        keysym: Keysym,
+        mods: ModifiersState,
+    ) -> Option<(WorkspaceId, String)> {
+        let state = self.state.as_mut()?;
+
+        match keysym {
+            Keysym::Return | Keysym::KP_Enter => {
+                let state = self.state.take().unwrap();
+                self.buffers.borrow_mut().clear();
+                return Some((state.workspace, state.text.trim().to_owned()));
+            }
+            Keysym::Escape => {
+                self.hide();
+                return None;
+            }
+            Keysym::BackSpace if mods.ctrl => state.text.clear(),
+            Keysym::BackSpace => {
+                state.text.pop();
+            }
+            _ => {
+                if mods.ctrl || mods.alt || mods.logo {
+                    return None;
+                }
+
+                let Some(c) = keysym.key_char().filter(|c| !c.is_control()) else {
+                    return None;
+                };
+                state.text.push(c);
+            }
+        }
+
+        self.buffers.borrow_mut().clear();
+        None
+    }
+
+    pub fn render<R: NiriRenderer>(
+        &self,
+        renderer: &mut R,
+        output: &Output,
+    ) -> Option<PrimaryGpuTextureRenderElement> {
+        let state = self.state.as_ref()?;
+        if state.output != *output {
+            return None;
+        }
+
+        let scale = output.current_scale().fractional_scale();
+
+        let mut buffers = self.buffers.borrow_mut();
+        let buffer = buffers
+            .entry(NotNan::new(scale).unwrap())
+            .or_insert_with(|| {
+                render(scale, &state.text)
+                    .map_err(|err| warn!("error rendering workspace rename dialog: {err:?}"))
+                    .ok()
+            });
+        let buffer = buffer.as_ref()?;
+
+        render_centered(renderer, output, buffer)
+    }
+}
+
+impl Default for WorkspaceRenameDialog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn render(scale: f64, text: &str) -> anyhow::Result<MemoryBuffer> {
+    let _span = tracy_client::span!("workspace_rename_dialog::render");
+
+    let padding: i32 = to_physical_precise_round(scale, PADDING);
+    let min_entry_width: i32 = to_physical_precise_round(scale, MIN_ENTRY_WIDTH);
+
+    let mut font = FontDescription::from_string(FONT);
+    font.set_absolute_size(to_physical_precise_round(scale, font.size()));
+    let mut entry_font = FontDescription::from_string(ENTRY_FONT);
+    entry_font.set_absolute_size(to_physical_precise_round(scale, entry_font.size()));
+
+    // Show a cursor at the end of the text.
+    let entry_text = format!("{text}▏");
+
+    let surface = ImageSurface::create(cairo::Format::ARgb32, 0, 0)?;
+    let cr = cairo::Context::new(&surface)?;
+
+    let layout = pangocairo::functions::create_layout(&cr);
+    layout.context().set_round_glyph_positions(false);
+    layout.set_font_description(Some(&font));
+    layout.set_text(TITLE);
+    let (title_width, title_height) = layout.pixel_size();
+
+    layout.set_font_description(Some(&entry_font));
+    layout.set_text(&entry_text);
+    let (entry_width, entry_height) = layout.pixel_size();
+    let entry_width = entry_width.max(min_entry_width);
+
+    layout.set_font_description(Some(&font));
+    layout.set_alignment(Alignment::Center);
+    layout.set_markup(HINT);
+    let (hint_width, hint_height) = layout.pixel_size();
+
+    let inner_width = title_width.max(entry_width + padding).max(hint_width);
+    let width = inner_width + padding * 2;
+    let entry_box_height = entry_height + padding;
+    let height = title_height + entry_box_height + hint_height + padding * 4;
+
+    render_dialog(scale, width, height, BORDER, [0.5, 0.6, 0.9], |cr| {
+        let mut y = padding;
+
+        // Title.
+        cr.move_to(padding.into(), y.into());
+        let layout = pangocairo::functions::create_layout(cr);
+        layout.context().set_round_glyph_positions(false);
+        layout.set_font_description(Some(&font));
+        layout.set_text(TITLE);
+        cr.set_source_rgb(1., 1., 1.);
+        pangocairo::functions::show_layout(cr, &layout);
+        y += title_height + padding;
+
+        // Entry box.
+        cr.rectangle(
+            padding.into(),
+            y.into(),
+            inner_width.into(),
+            entry_box_height.into(),
+        );
+        cr.set_source_rgb(0.17, 0.17, 0.17);
+        cr.fill()?;
+
+        // Keep the end of a long text visible.
+        cr.save()?;
+        cr.rectangle(
+            padding.into(),
+            y.into(),
+            inner_width.into(),
+            entry_box_height.into(),
+        );
+        cr.clip();
+
+        let layout = pangocairo::functions::create_layout(cr);
+        layout.context().set_round_glyph_positions(false);
+        layout.set_font_description(Some(&entry_font));
+        layout.set_text(&entry_text);
+        let (text_width, _) = layout.pixel_size();
+        let x = padding + padding / 2 + i32::min(0, inner_width - padding - text_width);
+        cr.move_to(x.into(), f64::from(y + padding / 2));
+        cr.set_source_rgb(1., 1., 1.);
+        pangocairo::functions::show_layout(cr, &layout);
+        cr.restore()?;
+        y += entry_box_height + padding;
+
+        // Hint.
+        cr.move_to(padding.into(), y.into());
+        let layout = pangocairo::functions::create_layout(cr);
+        layout.context().set_round_glyph_positions(false);
+        layout.set_font_description(Some(&font));
+        layout.set_width(inner_width * pango::SCALE);
+        layout.set_alignment(Alignment::Center);
+        layout.set_markup(HINT);
+        cr.set_source_rgb(0.7, 0.7, 0.7);
+        pangocairo::functions::show_layout(cr, &layout);
+
+        Ok(())
+    })
+}
@@ -0,0 +1,357 @@
+//! Output brightness control through the backlight or DDC/CI.
+//!
+//! Talking to the hardware can be slow (DDC/CI in particular needs delays of tens of milliseconds
+//! between commands), so all of it happens on a separate thread.
+
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{bail, ensure, Context};
+use niri_ipc::BrightnessChange;
+use smithay::reexports::calloop::{self, LoopHandle};
+
+use crate::niri::State;
+use crate::utils::is_laptop_panel;
+
+/// I2C address of the DDC/CI display.
+const DDC_ADDR: u8 = 0x37;
+/// VCP feature code of the display luminance.
+const VCP_BRIGHTNESS: u8 = 0x10;
+/// `I2C_SLAVE` ioctl from linux/i2c-dev.h.
+const I2C_SLAVE: libc::c_ulong = 0x0703;
+/// How long the display needs to process a DDC/CI command.
+const DDC_DELAY: Duration = Duration::from_millis(50);
+
+/// Backlight types in order of preference, same as in systemd-backlight.
+const BACKLIGHT_TYPES: [&str; 3] = ["firmware", "platform", "raw"];
+
+pub struct BrightnessControl {
+    to_worker: mpsc::Sender<Message>,
+    /// Last known brightness per connector name.
+    brightness: HashMap<String, f64>,
+}
+
+enum Message {
+    Query(String),
+    Change(String, BrightnessChange),
+}
+
+/// Brightness read or set by the worker, or `None` if the connector has no brightness control.
+struct Update(String, Option<f64>);
+
+enum Device {
+    Backlight { name: String, path: PathBuf },
+    Ddc { path: PathBuf },
+}
+
+impl BrightnessControl {
+    pub fn new(event_loop: &LoopHandle<'static, State>) -> Self {
+        let (to_worker, from_main) = mpsc::channel();
+        let (to_main, from_worker) = calloop::channel::channel();
+
+        event_loop
+            .insert_source(from_worker, move |event, _, state| match event {
+                calloop::channel::Event::Msg(Update(connector, percent)) => {
+                    state.on_brightness_update(connector, percent)
+                }
+                calloop::channel::Event::Closed => (),
+            })
+            .unwrap();
+
+        let res = thread::Builder::new()
+            .name("Brightness Control".to_owned())
+            .spawn(move || run(from_main, to_main));
+        if let Err(err) = res {
+            warn!("error spawning the brightness control thread: {err:?}");
+        }
+
+        Self {
+            to_worker,
+            brightness: HashMap::new(),
+        }
+    }
+
+    /// Returns the last known brightness of the connector in percent.
+    pub fn get(&self, connector: &str) -> Option<f64> {
+        self.brightness.get(connector).copied()
+    }
+
+    /// Re-reads the brightness of the connector in the background.
+    ///
+    /// The device is looked up again too, since a different monitor may have been plugged in.
+    pub fn refresh(&self, connector: &str) {
+        let _ = self.to_worker.send(Message::Query(connector.to_owned()));
+    }
+
+    /// Updates the last known brightness with a value from the worker.
+    ///
+    /// Returns `true` if it changed.
+    pub fn update(&mut self, connector: String, percent: Option<f64>) -> bool {
+        match percent {
+            Some(percent) => self.brightness.insert(connector, percent) != Some(percent),
+            None => self.brightness.remove(&connector).is_some(),
+        }
+    }
+
+    pub fn change(&self, connector: &str, change: BrightnessChange) {
+        let _ = self
+            .to_worker
+            .send(Message::Change(connector.to_owned(), change));
+    }
+}
+
+fn run(from_main: mpsc::Receiver<Message>, to_main: calloop::channel::Sender<Update>) {
+    let mut devices = HashMap::new();
+
+    while let Ok(message) = from_main.recv() {
+        let (connector, change) = match message {
+            Message::Query(connector) => {
+                devices.remove(&connector);
+                (connector, None)
+            }
+            Message::Change(connector, change) => (connector, Some(change)),
+        };
+
+        let device = devices
+            .entry(connector.clone())
+            .or_insert_with(|| Device::find(&connector));
+        let Some(device) = device else {
+            if change.is_some() {
+                warn!("output {connector} does not support brightness control");
+            } else {
+                let _ = to_main.send(Update(connector, None));
+            }
+            continue;
+        };
+
+        let res = device.get().and_then(|current| {
+            let Some(change) = change else {
+                return Ok(current);
+            };
+
+            let percent = match change {
+                BrightnessChange::SetPercent(percent) => percent,
+                BrightnessChange::AdjustPercent(delta) => current + delta,
+            };
+            ensure!(percent.is_finite(), "brightness must be a finite number");
+            let percent = percent.clamp(0., 100.);
+            device.set(percent)?;
+            Ok(percent)
+        });
+
+        let percent = match res {
+            Ok(percent) => percent,
+            Err(err) => {
+                // Many monitors don't support DDC/CI, so only complain when asked to change it.
+                if change.is_some() {
+                    warn!("error changing brightness of {connector}: {err:?}");
+                } else {
+                    debug!("error reading brightness of {connector}: {err:?}");
+                    let _ = to_main.send(Update(connector, None));
+                }
+                continue;
+            }
+        };
+
+        if to_main.send(Update(connector, Some(percent))).is_err() {
+            break;
+        }
+    }
+}
+
+impl Device {
+    fn find(connector: &str) -> Option<Self> {
+        let drm_dir = connector_sysfs_dir(connector)?;
+
+        // Backlight devices of laptop panels are usually children of the connector.
+        if let Some(device) = fs::read_dir(&drm_dir).ok()?.flatten().find_map(|entry| {
+            let path = entry.path();
+            path.join("max_brightness")
+                .exists()
+                .then(|| Self::Backlight {
+                    name: entry.file_name().to_string_lossy().into_owned(),
+                    path,
+                })
+        }) {
+            return Some(device);
+        }
+
+        // Firmware and platform backlights aren't tied to a connector, so assume that they belong
+        // to the internal panel.
+        if is_laptop_panel(connector) {
+            if let Some(device) = find_backlight() {
+                return Some(device);
+            }
+        }
+
+        // External monitors expose DDC/CI over the I2C bus linked from the connector.
+        let ddc = fs::read_link(drm_dir.join("ddc")).ok()?;
+        let bus = ddc.file_name()?;
+        Some(Self::Ddc {
+            path: Path::new("/dev").join(bus),
+        })
+    }
+
+    /// Returns the current brightness in percent.
+    fn get(&self) -> anyhow::Result<f64> {
+        let (current, max) = match self {
+            Device::Backlight { path, .. } => (
+                read_u32(&path.join("brightness"))?,
+                read_u32(&path.join("max_brightness"))?,
+            ),
+            Device::Ddc { path } => {
+                let mut file = open_ddc(path)?;
+                ddc_get_brightness(&mut file)?
+            }
+        };
+
+        ensure!(max > 0, "maximum brightness is zero");
+        Ok(f64::from(current) / f64::from(max) * 100.)
+    }
+
+    fn set(&self, percent: f64) -> anyhow::Result<()> {
+        match self {
+            Device::Backlight { name, path } => {
+                let max = read_u32(&path.join("max_brightness"))?;
+                let value = (percent / 100. * f64::from(max)).round() as u32;
+                set_backlight(name, path, value)
+            }
+            Device::Ddc { path } => {
+                let mut file = open_ddc(path)?;
+                let (_, max) = ddc_get_brightness(&mut file)?;
+                let value = (percent / 100. * f64::from(max)).round() as u16;
+                ddc_set_brightness(&mut file, value)
+            }
+        }
+    }
+}
+
+fn connector_sysfs_dir(connector: &str) -> Option<PathBuf> {
+    // Connector directories are named like card1-eDP-1.
+    fs::read_dir("/sys/class/drm")
+        .ok()?
+        .flatten()
+        .find(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.starts_with("card")
+                && name
+                    .split_once('-')
+                    .is_some_and(|(_, rest)| rest == connector)
+        })
+        .map(|entry| entry.path())
+}
+
+fn find_backlight() -> Option<Device> {
+    let devices: Vec<_> = fs::read_dir("/sys/class/backlight")
+        .ok()?
+        .flatten()
+        .map(|entry| {
+            let ty = fs::read_to_string(entry.path().join("type")).unwrap_or_default();
+            (entry, ty)
+        })
+        .collect();
+
+    BACKLIGHT_TYPES.iter().find_map(|wanted| {
+        let (entry, _) = devices.iter().find(|(_, ty)| ty.trim() == *wanted)?;
+        Some(Device::Backlight {
+            name: entry.file_name().to_string_lossy().into_owned(),
+            path: entry.path(),
+        })
+    })
+}
+
+fn read_u32(path: &Path) -> anyhow::Result<u32> {
+    let value = fs::read_to_string(path).with_context(|| format!("error reading {path:?}"))?;
+    value
+        .trim()
+        .parse()
+        .with_context(|| format!("error parsing {path:?}"))
+}
+
+#[cfg(feature = "dbus")]
+fn set_backlight(name: &str, _path: &Path, value: u32) -> anyhow::Result<()> {
+    // Writing to sysfs directly requires root, so go through logind.
+    let conn = zbus::blocking::Connection::system().context("error connecting to system bus")?;
+
+    conn.call_method(
+        Some("org.freedesktop.login1"),
+        "/org/freedesktop/login1/session/auto",
+        Some("org.freedesktop.login1.Session"),
+        "SetBrightness",
+        &("backlight", name, value),
+    )
+    .context("error setting brightness through logind")?;
+
+    Ok(())
+}
+
+#[cfg(not(feature = "dbus"))]
+fn set_backlight(_name: &str, path: &Path, value: u32) -> anyhow::Result<()> {
+    let path = path.join("brightness");
+    fs::write(&path, value.to_string()).with_context(|| format!("error writing {path:?}"))
+}
+
+fn open_ddc(path: &Path) -> anyhow::Result<File> {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .with_context(|| format!("error opening {path:?}"))?;
+
+    // SAFETY: I2C_SLAVE takes the address as an integer argument.
+    let res = unsafe { libc::ioctl(file.as_raw_fd(), I2C_SLAVE, libc::c_ulong::from(DDC_ADDR)) };
+    if res < 0 {
+        return Err(std::io::Error::last_os_error()).context("error setting the DDC/CI address");
+    }
+
+    Ok(file)
+}
+
+fn ddc_write(file: &mut File, data: &[u8]) -> anyhow::Result<()> {
+    // Source address, length with the high bit set, then the data and a checksum which also
+    // covers the destination address.
+    let mut packet = vec![0x51, 0x80 | data.len() as u8];
+    packet.extend_from_slice(data);
+    let checksum = packet.iter().fold(DDC_ADDR << 1, |acc, x| acc ^ x);
+    packet.push(checksum);
+
+    file.write_all(&packet)
+        .context("error writing DDC/CI command")?;
+    thread::sleep(DDC_DELAY);
+    Ok(())
+}
+
+/// Returns the current and the maximum brightness.
+fn ddc_get_brightness(file: &mut File) -> anyhow::Result<(u32, u32)> {
+    ddc_write(file, &[0x01, VCP_BRIGHTNESS])?;
+
+    let mut reply = [0; 11];
+    file.read_exact(&mut reply)
+        .context("error reading DDC/CI reply")?;
+
+    // The reply checksum is computed with the host address of 0x50.
+    let checksum = reply[..10].iter().fold(0x50, |acc, x| acc ^ x);
+    ensure!(checksum == reply[10], "invalid DDC/CI reply checksum");
+
+    let [_, _, opcode, result, code, _, max_hi, max_lo, cur_hi, cur_lo, _] = reply;
+    ensure!(opcode == 0x02, "unexpected DDC/CI reply opcode {opcode:#x}");
+    if result != 0 || code != VCP_BRIGHTNESS {
+        bail!("monitor does not support brightness over DDC/CI");
+    }
+
+    let max = u32::from(u16::from_be_bytes([max_hi, max_lo]));
+    let current = u32::from(u16::from_be_bytes([cur_hi, cur_lo]));
+    Ok((current, max))
+}
+
+fn ddc_set_brightness(file: &mut File, value: u16) -> anyhow::Result<()> {
+    let [hi, lo] = value.to_be_bytes();
+    ddc_write(file, &[0x03, VCP_BRIGHTNESS, hi, lo])
+}
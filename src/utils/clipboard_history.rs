@@ -0,0 +1,408 @@
+//! Built-in clipboard history.
+//!
+//! Every new clipboard selection is read in full in all of its mime types and kept around. Once
+//! read, niri offers the selection as its own, so that the clipboard survives the client that set
+//! it going away.
+//!
+//! The same machinery mirrors the primary selection and the clipboard into each other.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use smithay::wayland::selection::SelectionTarget;
+
+/// Contents of a selection offered by niri, for every mime type.
+pub type SelectionData = Arc<[(String, Arc<[u8]>)]>;
+
+/// Selections bigger than this in all of their mime types together are not kept.
+const MAX_SIZE: usize = 16 * 1024 * 1024;
+
+/// The oldest entries are dropped to keep the whole history under this size.
+const MAX_HISTORY_SIZE: usize = 64 * 1024 * 1024;
+
+/// Reading a selection gives up after this long, in case the client never closes the pipe.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Mime type that password managers set on selections that shouldn't end up in a history.
+const PASSWORD_HINT_MIME_TYPE: &str = "x-kde-passwordManagerHint";
+
+/// X11 targets that describe the selection rather than carry its contents.
+const META_MIME_TYPES: &[&str] = &["TARGETS", "MULTIPLE", "TIMESTAMP", "SAVE_TARGETS"];
+
+/// Text mime types, most preferred first.
+const TEXT_MIME_TYPES: &[&str] = &[
+    "text/plain;charset=utf-8",
+    "UTF8_STRING",
+    "text/plain",
+    "STRING",
+    "TEXT",
+];
+
+#[derive(Debug, Default)]
+pub struct ClipboardHistory {
+    /// Entries, most recent first.
+    entries: VecDeque<ClipboardEntry>,
+    next_id: u64,
+    /// Incremented whenever the clipboard selection changes.
+    clipboard_serial: u64,
+    /// Incremented whenever the primary selection changes.
+    primary_serial: u64,
+}
+
+#[derive(Debug)]
+pub struct ClipboardEntry {
+    pub id: u64,
+    pub data: SelectionData,
+}
+
+impl ClipboardHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that the selection changed and returns its new serial.
+    pub fn selection_changed(&mut self, target: SelectionTarget) -> u64 {
+        let serial = match target {
+            SelectionTarget::Clipboard => &mut self.clipboard_serial,
+            SelectionTarget::Primary => &mut self.primary_serial,
+        };
+        *serial = serial.wrapping_add(1);
+        *serial
+    }
+
+    /// Returns whether the selection hasn't changed since the serial was returned.
+    pub fn is_current(&self, target: SelectionTarget, serial: u64) -> bool {
+        let current = match target {
+            SelectionTarget::Clipboard => self.clipboard_serial,
+            SelectionTarget::Primary => self.primary_serial,
+        };
+        current == serial
+    }
+
+    /// Adds a selection as the most recent entry and drops the entries past `max_entries`.
+    ///
+    /// A selection equal to an existing entry moves that entry to the front instead. Returns
+    /// `false` if the selection was already the most recent entry.
+    pub fn push(&mut self, data: SelectionData, max_entries: usize) -> bool {
+        if let Some(idx) = self.entries.iter().position(|entry| entry.data == data) {
+            if idx == 0 {
+                return false;
+            }
+            let entry = self.entries.remove(idx).unwrap();
+            self.entries.push_front(entry);
+        } else {
+            self.next_id += 1;
+            self.entries.push_front(ClipboardEntry {
+                id: self.next_id,
+                data,
+            });
+        }
+
+        self.truncate(max_entries);
+        true
+    }
+
+    /// Drops the entries past `max_entries`, and the oldest entries past the size budget.
+    pub fn truncate(&mut self, max_entries: usize) {
+        self.entries.truncate(max_entries);
+        self.truncate_to_size(MAX_HISTORY_SIZE);
+    }
+
+    fn truncate_to_size(&mut self, max_size: usize) {
+        let mut total = 0;
+        let past_budget = self.entries.iter().position(|entry| {
+            total += entry.total_size();
+            total > max_size
+        });
+
+        // Always keep the most recent entry, since it's the current selection.
+        if let Some(idx) = past_budget {
+            self.entries.truncate(usize::max(idx, 1));
+        }
+    }
+
+    /// Moves the entry to the front and returns its contents.
+    pub fn promote(&mut self, id: u64) -> Option<SelectionData> {
+        let idx = self.entries.iter().position(|entry| entry.id == id)?;
+        let entry = self.entries.remove(idx).unwrap();
+        let data = entry.data.clone();
+        self.entries.push_front(entry);
+        Some(data)
+    }
+
+    /// Returns the entries, most recent first.
+    pub fn entries(&self) -> impl Iterator<Item = &ClipboardEntry> + '_ {
+        self.entries.iter()
+    }
+}
+
+impl ClipboardEntry {
+    pub fn mime_types(&self) -> impl Iterator<Item = &str> + '_ {
+        self.data.iter().map(|(mime_type, _)| mime_type.as_str())
+    }
+
+    /// Returns the text contents, if the entry is available as text.
+    pub fn text(&self) -> Option<String> {
+        let (_, buf) = TEXT_MIME_TYPES.iter().find_map(|text_mime_type| {
+            self.data
+                .iter()
+                .find(|(mime_type, _)| mime_type.eq_ignore_ascii_case(text_mime_type))
+        })?;
+        Some(String::from_utf8_lossy(buf).into_owned())
+    }
+
+    /// Returns the size of the biggest representation, in bytes.
+    pub fn size(&self) -> usize {
+        self.data
+            .iter()
+            .map(|(_, buf)| buf.len())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns the size of all representations together, in bytes.
+    fn total_size(&self) -> usize {
+        self.data.iter().map(|(_, buf)| buf.len()).sum()
+    }
+
+    pub fn to_ipc(&self) -> niri_ipc::ClipboardEntry {
+        niri_ipc::ClipboardEntry {
+            id: self.id,
+            mime_types: self.mime_types().map(String::from).collect(),
+            text: self.text(),
+            size: self.size() as u64,
+        }
+    }
+}
+
+/// Returns the mime types to read from a new selection, or `None` if it shouldn't be kept.
+pub fn mime_types_to_read(mime_types: Vec<String>) -> Option<Vec<String>> {
+    if mime_types.iter().any(|m| m == PASSWORD_HINT_MIME_TYPE) {
+        return None;
+    }
+
+    let mime_types: Vec<_> = mime_types
+        .into_iter()
+        .filter(|m| !META_MIME_TYPES.contains(&m.as_str()))
+        .collect();
+    (!mime_types.is_empty()).then_some(mime_types)
+}
+
+/// Reads a selection from pipes, one per mime type.
+///
+/// This blocks until the source client has written everything or [`READ_TIMEOUT`] passes, so call
+/// it from a thread. Returns `None` if any of the mime types fails to read, or if the selection is
+/// too big.
+pub fn read_selection(pipes: Vec<(String, OwnedFd)>) -> Option<SelectionData> {
+    let deadline = Instant::now() + READ_TIMEOUT;
+    let total = AtomicUsize::new(0);
+
+    // The client may write the mime types in any order, so read them all at once.
+    let data = thread::scope(|s| {
+        let handles: Vec<_> = pipes
+            .into_iter()
+            .map(|(mime_type, fd)| {
+                let total = &total;
+                s.spawn(move || {
+                    let buf = read_pipe(fd, &mime_type, total, deadline)?;
+                    Some((mime_type, Arc::from(buf)))
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().ok().flatten())
+            .collect::<Option<Vec<_>>>()
+    })?;
+
+    Some(Arc::from(data))
+}
+
+/// Reads the pipe to the end, counting the bytes in `total` across all mime types.
+fn read_pipe(
+    fd: OwnedFd,
+    mime_type: &str,
+    total: &AtomicUsize,
+    deadline: Instant,
+) -> Option<Vec<u8>> {
+    let mut file = File::from(fd);
+    let mut buf = Vec::new();
+    let mut chunk = vec![0; 64 * 1024];
+
+    loop {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        if !wait_readable(&file, timeout) {
+            debug!("not keeping clipboard selection: reading {mime_type} timed out");
+            return None;
+        }
+
+        match file.read(&mut chunk) {
+            Ok(0) => return Some(buf),
+            Ok(n) => {
+                if total.fetch_add(n, Ordering::Relaxed) + n > MAX_SIZE {
+                    debug!("not keeping clipboard selection: it is too big");
+                    return None;
+                }
+                buf.extend_from_slice(&chunk[..n]);
+            }
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+            Err(err) => {
+                warn!("error reading clipboard selection as {mime_type}: {err:?}");
+                return None;
+            }
+        }
+    }
+}
+
+/// Waits for the file to become readable, or for the writer to close it.
+///
+/// Returns `false` if the timeout passes first.
+fn wait_readable(file: &File, timeout: Duration) -> bool {
+    let mut pollfd = libc::pollfd {
+        fd: file.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let timeout = i32::try_from(timeout.as_millis()).unwrap_or(i32::MAX);
+
+    loop {
+        let rv = unsafe { libc::poll(&mut pollfd, 1, timeout) };
+        if rv < 0 && io::Error::last_os_error().kind() == io::ErrorKind::Interrupted {
+            continue;
+        }
+        return rv > 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::os::unix::net::UnixStream;
+
+    use super::*;
+
+    fn data(text: &str) -> SelectionData {
+        Arc::from(vec![(
+            String::from("text/plain"),
+            Arc::from(text.as_bytes()),
+        )])
+    }
+
+    fn texts(history: &ClipboardHistory) -> Vec<String> {
+        history.entries().map(|e| e.text().unwrap()).collect()
+    }
+
+    #[test]
+    fn push_keeps_max_entries() {
+        let mut history = ClipboardHistory::new();
+        history.push(data("a"), 2);
+        history.push(data("b"), 2);
+        history.push(data("c"), 2);
+        assert_eq!(texts(&history), ["c", "b"]);
+    }
+
+    #[test]
+    fn push_moves_duplicate_to_front() {
+        let mut history = ClipboardHistory::new();
+        history.push(data("a"), 10);
+        history.push(data("b"), 10);
+        let id = history.entries().nth(1).unwrap().id;
+
+        assert!(history.push(data("a"), 10));
+        assert_eq!(texts(&history), ["a", "b"]);
+        assert_eq!(history.entries().next().unwrap().id, id);
+
+        assert!(!history.push(data("a"), 10));
+        assert_eq!(texts(&history), ["a", "b"]);
+    }
+
+    #[test]
+    fn promote_moves_entry_to_front() {
+        let mut history = ClipboardHistory::new();
+        history.push(data("a"), 10);
+        history.push(data("b"), 10);
+        let id = history.entries().nth(1).unwrap().id;
+
+        assert_eq!(history.promote(id), Some(data("a")));
+        assert_eq!(texts(&history), ["a", "b"]);
+        assert_eq!(history.promote(id + 100), None);
+    }
+
+    #[test]
+    fn truncate_keeps_size_budget() {
+        let mut history = ClipboardHistory::new();
+        history.push(data("aaaa"), 10);
+        history.push(data("bbbb"), 10);
+        history.push(data("cccc"), 10);
+
+        history.truncate_to_size(8);
+        assert_eq!(texts(&history), ["cccc", "bbbb"]);
+
+        // The current selection stays even if it's over the budget on its own.
+        history.truncate_to_size(2);
+        assert_eq!(texts(&history), ["cccc"]);
+    }
+
+    #[test]
+    fn read_gives_up_on_unclosed_pipe() {
+        let (read, mut write) = UnixStream::pair().unwrap();
+        write.write_all(b"a").unwrap();
+
+        let total = AtomicUsize::new(0);
+        let deadline = Instant::now() + Duration::from_millis(50);
+        assert_eq!(
+            read_pipe(OwnedFd::from(read), "text/plain", &total, deadline),
+            None
+        );
+    }
+
+    #[test]
+    fn read_counts_size_across_mime_types() {
+        let (read, mut write) = UnixStream::pair().unwrap();
+        write.write_all(b"abc").unwrap();
+        drop(write);
+
+        let total = AtomicUsize::new(MAX_SIZE - 2);
+        let deadline = Instant::now() + Duration::from_secs(1);
+        assert_eq!(
+            read_pipe(OwnedFd::from(read), "text/plain", &total, deadline),
+            None
+        );
+    }
+
+    #[test]
+    fn text_prefers_utf8() {
+        let entry = ClipboardEntry {
+            id: 1,
+            data: Arc::from(vec![
+                (String::from("text/html"), Arc::from(&b"<b>a</b>"[..])),
+                (String::from("STRING"), Arc::from(&b"latin"[..])),
+                (String::from("UTF8_STRING"), Arc::from("ütf".as_bytes())),
+            ]),
+        };
+        assert_eq!(entry.text().as_deref(), Some("ütf"));
+        assert_eq!(entry.size(), 8);
+    }
+
+    #[test]
+    fn password_selections_are_skipped() {
+        let mime_types = vec![
+            String::from("text/plain"),
+            String::from(PASSWORD_HINT_MIME_TYPE),
+        ];
+        assert_eq!(mime_types_to_read(mime_types), None);
+
+        let mime_types = vec![String::from("TARGETS"), String::from("UTF8_STRING")];
+        assert_eq!(
+            mime_types_to_read(mime_types),
+            Some(vec![String::from("UTF8_STRING")])
+        );
+    }
+}
@@ -0,0 +1,100 @@
+//! Gamma ramps for adjusting the output color temperature.
+
+/// Color temperature that leaves the colors unchanged.
+pub const NEUTRAL_TEMPERATURE: u32 = 6500;
+
+const MIN_TEMPERATURE: u32 = 1000;
+const MAX_TEMPERATURE: u32 = 25000;
+
+/// Returns the gamma ramp for the given color temperature in kelvin.
+///
+/// The ramp has the red, green and blue channels one after another, as expected by the gamma
+/// control protocol and the DRM backend. Returns `None` for the neutral temperature, which
+/// corresponds to resetting the gamma.
+pub fn gamma_ramp(gamma_size: usize, kelvin: u32) -> Option<Vec<u16>> {
+    if kelvin == NEUTRAL_TEMPERATURE || gamma_size < 2 {
+        return None;
+    }
+
+    let whitepoint = whitepoint(kelvin);
+
+    let mut ramp = Vec::with_capacity(gamma_size * 3);
+    for factor in whitepoint {
+        ramp.extend((0..gamma_size).map(|i| {
+            let value = i as f64 / (gamma_size - 1) as f64 * factor;
+            (value * f64::from(u16::MAX)).round() as u16
+        }));
+    }
+
+    Some(ramp)
+}
+
+/// Returns the RGB multipliers for the color temperature, relative to the neutral one.
+fn whitepoint(kelvin: u32) -> [f64; 3] {
+    let [r, g, b] = blackbody(kelvin);
+    let [nr, ng, nb] = blackbody(NEUTRAL_TEMPERATURE);
+    let rgb = [r / nr, g / ng, b / nb];
+
+    // Keep the brightest channel at full intensity.
+    let max = rgb.into_iter().fold(f64::EPSILON, f64::max);
+    rgb.map(|x| (x / max).clamp(0., 1.))
+}
+
+/// Approximates the color of a black body radiator, following Tanner Helland's curve fit.
+fn blackbody(kelvin: u32) -> [f64; 3] {
+    let t = f64::from(kelvin.clamp(MIN_TEMPERATURE, MAX_TEMPERATURE)) / 100.;
+
+    let r = if t <= 66. {
+        255.
+    } else {
+        329.698_727_446 * (t - 60.).powf(-0.133_204_759_2)
+    };
+
+    let g = if t <= 66. {
+        99.470_802_586_1 * t.ln() - 161.119_568_166_1
+    } else {
+        288.122_169_528_3 * (t - 60.).powf(-0.075_514_849_2)
+    };
+
+    let b = if t >= 66. {
+        255.
+    } else if t <= 19. {
+        0.
+    } else {
+        138.517_731_223_1 * (t - 10.).ln() - 305.044_792_730_7
+    };
+
+    [r, g, b].map(|x| (x / 255.).clamp(0., 1.))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn neutral_resets_gamma() {
+        assert_eq!(gamma_ramp(256, NEUTRAL_TEMPERATURE), None);
+    }
+
+    #[test]
+    fn warm_ramp_reduces_blue() {
+        let ramp = gamma_ramp(256, 3000).unwrap();
+        assert_eq!(ramp.len(), 256 * 3);
+
+        let (red, rest) = ramp.split_at(256);
+        let (green, blue) = rest.split_at(256);
+        assert_eq!(red[0], 0);
+        assert_eq!(red[255], u16::MAX);
+        assert!(green[255] < red[255]);
+        assert!(blue[255] < green[255]);
+    }
+
+    #[test]
+    fn cool_ramp_reduces_red() {
+        let ramp = gamma_ramp(256, 10000).unwrap();
+        let (red, rest) = ramp.split_at(256);
+        let (_, blue) = rest.split_at(256);
+        assert_eq!(blue[255], u16::MAX);
+        assert!(red[255] < blue[255]);
+    }
+}
@@ -0,0 +1,395 @@
+//! Minimal lookup of installed applications through their desktop entries.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+
+use directories::BaseDirs;
+
+/// Information about an application from its desktop entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DesktopEntry {
+    /// App ID that the application windows are expected to have.
+    pub app_id: String,
+    /// Name of the application.
+    pub name: Option<String>,
+    /// Icon name or absolute path.
+    pub icon: Option<String>,
+    /// Command that launches the application, without field codes.
+    pub command: Vec<String>,
+}
+
+/// Index of desktop entries by the program that they run, by app ID and by desktop file ID.
+///
+/// The index is rebuilt whenever one of the application directories changes.
+#[derive(Debug, Default)]
+pub struct DesktopEntries {
+    dirs: Vec<(PathBuf, Option<SystemTime>)>,
+    by_program: HashMap<String, DesktopEntry>,
+    /// Entries by lowercase app ID.
+    by_app_id: HashMap<String, DesktopEntry>,
+    /// Entries by desktop file name without the extension.
+    by_desktop_id: HashMap<String, DesktopEntry>,
+}
+
+impl DesktopEntries {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the index shared by the whole compositor.
+    ///
+    /// Window rule matching needs it too, and has no access to the compositor state.
+    pub fn shared() -> Arc<Mutex<Self>> {
+        static SHARED: OnceLock<Arc<Mutex<DesktopEntries>>> = OnceLock::new();
+        SHARED
+            .get_or_init(|| Arc::new(Mutex::new(Self::new())))
+            .clone()
+    }
+
+    /// Finds the desktop entry of an application by its program name.
+    pub fn find(&mut self, program: &str) -> Option<&DesktopEntry> {
+        let program = Path::new(program).file_name()?.to_str()?;
+        self.refresh();
+        self.by_program.get(program)
+    }
+
+    /// Finds the desktop entry of an application by the app ID of its windows.
+    pub fn find_by_app_id(&mut self, app_id: &str) -> Option<&DesktopEntry> {
+        self.refresh();
+        self.by_app_id.get(&app_id.to_lowercase())
+    }
+
+    /// Finds a desktop entry by its file name without the extension.
+    pub fn find_by_desktop_id(&mut self, desktop_id: &str) -> Option<&DesktopEntry> {
+        self.refresh();
+        self.by_desktop_id.get(desktop_id)
+    }
+
+    fn refresh(&mut self) {
+        let dirs: Vec<_> = data_dirs()
+            .into_iter()
+            .map(|dir| {
+                let dir = dir.join("applications");
+                let modified = fs::metadata(&dir).and_then(|m| m.modified()).ok();
+                (dir, modified)
+            })
+            .collect();
+        if dirs != self.dirs {
+            self.rebuild(dirs);
+        }
+    }
+
+    fn rebuild(&mut self, dirs: Vec<(PathBuf, Option<SystemTime>)>) {
+        let _span = tracy_client::span!("DesktopEntries::rebuild");
+
+        self.by_program.clear();
+        self.by_app_id.clear();
+        self.by_desktop_id.clear();
+
+        // Earlier directories take precedence, so don't overwrite existing entries.
+        for (dir, _) in &dirs {
+            let Ok(files) = fs::read_dir(dir) else {
+                continue;
+            };
+
+            for file in files.flatten() {
+                let path = file.path();
+                if path.extension().map_or(true, |ext| ext != "desktop") {
+                    continue;
+                }
+
+                let Ok(contents) = fs::read_to_string(&path) else {
+                    continue;
+                };
+                let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                    continue;
+                };
+
+                if let Some((program, entry)) = parse(stem, &contents) {
+                    self.by_desktop_id
+                        .entry(stem.to_owned())
+                        .or_insert_with(|| entry.clone());
+                    self.by_app_id
+                        .entry(entry.app_id.to_lowercase())
+                        .or_insert_with(|| entry.clone());
+                    if let Some(program) = program {
+                        self.by_program.entry(program).or_insert(entry);
+                    }
+                }
+            }
+        }
+
+        self.dirs = dirs;
+    }
+}
+
+/// Parses a desktop entry, returning the program name along with the entry.
+///
+/// The program name is missing for sandboxed apps, which all run the same program.
+fn parse(desktop_id: &str, contents: &str) -> Option<(Option<String>, DesktopEntry)> {
+    let mut in_main_group = false;
+    let mut exec = None;
+    let mut name = None;
+    let mut icon = None;
+    let mut wm_class = None;
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_main_group = line == "[Desktop Entry]";
+            continue;
+        }
+        if !in_main_group {
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        match key.trim() {
+            "Type" if value.trim() != "Application" => return None,
+            "Hidden" | "NoDisplay" if value.trim() == "true" => return None,
+            "Exec" => exec = Some(value.trim()),
+            "Name" => name = Some(value.trim().to_owned()),
+            "Icon" => icon = Some(value.trim().to_owned()),
+            "StartupWMClass" => wm_class = Some(value.trim().to_owned()),
+            _ => (),
+        }
+    }
+
+    let command = exec_command(exec?);
+    if command.is_empty() {
+        return None;
+    }
+
+    let program = exec_program(&command);
+    let entry = DesktopEntry {
+        app_id: wm_class.unwrap_or_else(|| desktop_id.to_owned()),
+        name: name.filter(|name| !name.is_empty()),
+        icon: icon.filter(|icon| !icon.is_empty()),
+        command,
+    };
+    Some((program, entry))
+}
+
+/// Splits an `Exec` line into arguments, dropping the field codes.
+///
+/// Field codes stand for files and URLs to open, so without any, they expand to nothing.
+fn exec_command(exec: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut arg = String::new();
+    let mut in_arg = false;
+    let mut in_quotes = false;
+
+    let mut chars = exec.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                in_arg = true;
+            }
+            '\\' if in_quotes => arg.extend(chars.next()),
+            '%' => {
+                if chars.next() == Some('%') {
+                    arg.push('%');
+                }
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if in_arg || !arg.is_empty() {
+                    args.push(std::mem::take(&mut arg));
+                }
+                in_arg = false;
+            }
+            c => arg.push(c),
+        }
+    }
+    if in_arg || !arg.is_empty() {
+        args.push(arg);
+    }
+
+    args
+}
+
+/// Returns the name of the program that a command runs.
+fn exec_program(command: &[String]) -> Option<String> {
+    let mut args = command
+        .iter()
+        // Skip env and its variable assignments.
+        .skip_while(|arg| *arg == "env" || arg.contains('='));
+
+    let program = Path::new(args.next()?).file_name()?.to_str()?;
+
+    // Sandboxed apps all run the same program, so they can't be told apart by it.
+    if program == "flatpak" {
+        return None;
+    }
+
+    Some(program.to_owned())
+}
+
+/// Returns the name of the browser web app (PWA) that a window belongs to.
+///
+/// Chromium-based browsers give web app windows app IDs like `chrome-<extension id>-Default`,
+/// which don't say anything about the app. The browser also installs a desktop entry with the
+/// same name as the app ID, so the name is taken from there. If there's no desktop entry, the
+/// window title is used instead.
+///
+/// Returns `None` for windows that aren't web apps.
+pub fn pwa_name(app_id: &str, title: Option<&str>) -> Option<String> {
+    if !is_pwa_app_id(app_id) {
+        return None;
+    }
+
+    let entries = DesktopEntries::shared();
+    let mut entries = entries.lock().unwrap();
+    let name = entries
+        .find_by_desktop_id(app_id)
+        .and_then(|entry| entry.name.clone());
+
+    name.or_else(|| title.map(str::to_owned))
+}
+
+/// Returns whether an app ID looks like one of a Chromium web app.
+fn is_pwa_app_id(app_id: &str) -> bool {
+    // Like chrome-fmpnliohjhemenmnlpbfagaolkdacoja-Default, with the extension ID in the middle.
+    let mut parts = app_id.split('-');
+    parts.next().is_some_and(|browser| !browser.is_empty())
+        && parts.any(|part| part.len() == 32 && part.bytes().all(|c| (b'a'..=b'p').contains(&c)))
+}
+
+/// Looks up a PNG application icon in the hicolor theme and in pixmaps.
+pub fn find_icon(icon: &str) -> Option<PathBuf> {
+    let _span = tracy_client::span!("find_icon");
+
+    let path = Path::new(icon);
+    if path.is_absolute() {
+        let is_png = path.extension().is_some_and(|ext| ext == "png");
+        return (is_png && path.is_file()).then(|| path.to_owned());
+    }
+
+    let file_name = format!("{icon}.png");
+    let dirs = data_dirs();
+
+    for dir in &dirs {
+        for size in ["256x256", "128x128", "96x96", "64x64", "48x48"] {
+            let path = dir
+                .join("icons/hicolor")
+                .join(size)
+                .join("apps")
+                .join(&file_name);
+            if path.is_file() {
+                return Some(path);
+            }
+        }
+    }
+
+    dirs.iter()
+        .map(|dir| dir.join("pixmaps").join(&file_name))
+        .find(|path| path.is_file())
+}
+
+/// Returns the XDG data directories, most important first.
+fn data_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = BaseDirs::new()
+        .map(|dirs| dirs.data_dir().to_owned())
+        .into_iter()
+        .collect();
+
+    let data_dirs = env::var_os("XDG_DATA_DIRS")
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".into());
+    dirs.extend(env::split_paths(&data_dirs));
+
+    dirs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_entry() {
+        let contents = "\
+[Desktop Entry]
+Type=Application
+Name=Files
+Exec=nautilus --new-window %U
+Icon=org.gnome.Nautilus
+
+[Desktop Action new-window]
+Exec=something-else
+";
+        let (program, entry) = parse("org.gnome.Nautilus", contents).unwrap();
+        assert_eq!(program.as_deref(), Some("nautilus"));
+        assert_eq!(entry.app_id, "org.gnome.Nautilus");
+        assert_eq!(entry.name.as_deref(), Some("Files"));
+        assert_eq!(entry.icon.as_deref(), Some("org.gnome.Nautilus"));
+        assert_eq!(entry.command, ["nautilus", "--new-window"]);
+    }
+
+    #[test]
+    fn parse_wm_class_and_env() {
+        let contents = "\
+[Desktop Entry]
+Type=Application
+Exec=env FOO=1 /usr/bin/alacritty
+StartupWMClass=Alacritty
+";
+        let (program, entry) = parse("Alacritty", contents).unwrap();
+        assert_eq!(program.as_deref(), Some("alacritty"));
+        assert_eq!(entry.app_id, "Alacritty");
+        assert_eq!(entry.icon, None);
+    }
+
+    #[test]
+    fn parse_skips_hidden_and_flatpak() {
+        let hidden = "[Desktop Entry]\nType=Application\nExec=foo\nNoDisplay=true\n";
+        assert_eq!(parse("foo", hidden), None);
+
+        let flatpak = "[Desktop Entry]\nType=Application\nExec=/usr/bin/flatpak run org.Foo\n";
+        let (program, entry) = parse("org.Foo", flatpak).unwrap();
+        assert_eq!(program, None);
+        assert_eq!(entry.command, ["/usr/bin/flatpak", "run", "org.Foo"]);
+    }
+
+    #[test]
+    fn pwa_app_ids_and_names() {
+        assert!(is_pwa_app_id(
+            "chrome-fmpnliohjhemenmnlpbfagaolkdacoja-Default"
+        ));
+        assert!(is_pwa_app_id(
+            "msedge-cinhimbnkkaeohfgghhklpknlkffjgod-Profile_1"
+        ));
+        assert!(!is_pwa_app_id("org.gnome.Nautilus"));
+        assert!(!is_pwa_app_id("google-chrome"));
+
+        let contents = "\
+[Desktop Entry]
+Version=1.0
+Type=Application
+Name=YouTube Music
+Exec=/opt/google/chrome/google-chrome --profile-directory=Default --app-id=cinhimbnkkaeohfgghhklpknlkffjgod
+StartupWMClass=crx_cinhimbnkkaeohfgghhklpknlkffjgod
+
+[Desktop Action Explore]
+Name=Explore
+";
+        let id = "chrome-cinhimbnkkaeohfgghhklpknlkffjgod-Default";
+        let (_, entry) = parse(id, contents).unwrap();
+        assert_eq!(entry.name.as_deref(), Some("YouTube Music"));
+        assert_eq!(entry.app_id, "crx_cinhimbnkkaeohfgghhklpknlkffjgod");
+    }
+
+    #[test]
+    fn exec_quotes_and_field_codes() {
+        let command = exec_command(r#""/opt/My App/app" --name="a b" %U --file=%f 100%%"#);
+        assert_eq!(
+            command,
+            ["/opt/My App/app", "--name=a b", "--file=", "100%"]
+        );
+    }
+}
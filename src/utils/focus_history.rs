@@ -0,0 +1,91 @@
+//! Recent keyboard focus changes along with their causes, for `niri msg explain-focus`.
+//!
+//! The keyboard focus is computed in one place from a lot of state, so by the time it changes,
+//! its cause is long gone. Instead, the code handling the cause sets a pending reason, which is
+//! attached to the focus change that follows in the same event loop iteration.
+
+use std::collections::VecDeque;
+
+use niri_ipc::{FocusReason, FocusTarget, FocusTransition};
+
+/// Number of recent focus changes to keep.
+const HISTORY_LEN: usize = 16;
+
+#[derive(Debug, Default)]
+pub struct FocusHistory {
+    pending_reason: Option<FocusReason>,
+    transitions: VecDeque<FocusTransition>,
+}
+
+impl FocusHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the reason for the focus change that may follow.
+    pub fn set_reason(&mut self, reason: FocusReason) {
+        self.pending_reason = Some(reason);
+    }
+
+    /// Forgets the pending reason, once the focus is up to date.
+    pub fn clear_reason(&mut self) {
+        self.pending_reason = None;
+    }
+
+    /// Records a focus change, attributing it to the pending reason.
+    pub fn record(&mut self, target: FocusTarget) {
+        let reason = self.pending_reason.take().unwrap_or(FocusReason::Other);
+
+        if self.transitions.len() == HISTORY_LEN {
+            self.transitions.pop_front();
+        }
+        self.transitions
+            .push_back(FocusTransition { target, reason });
+    }
+
+    /// Returns the recent focus changes, oldest first.
+    pub fn transitions(&self) -> impl Iterator<Item = &FocusTransition> + '_ {
+        self.transitions.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reason_applies_to_one_change() {
+        let mut history = FocusHistory::new();
+        history.set_reason(FocusReason::Click);
+        history.record(FocusTarget::Window { id: 1 });
+        history.record(FocusTarget::Nothing);
+
+        let reasons: Vec<_> = history.transitions().map(|t| t.reason).collect();
+        assert_eq!(reasons, [FocusReason::Click, FocusReason::Other]);
+    }
+
+    #[test]
+    fn cleared_reason_is_not_used() {
+        let mut history = FocusHistory::new();
+        history.set_reason(FocusReason::Bind);
+        history.clear_reason();
+        history.record(FocusTarget::ScreenshotUi);
+
+        let reasons: Vec<_> = history.transitions().map(|t| t.reason).collect();
+        assert_eq!(reasons, [FocusReason::Other]);
+    }
+
+    #[test]
+    fn old_changes_are_dropped() {
+        let mut history = FocusHistory::new();
+        for id in 0..HISTORY_LEN as u64 + 2 {
+            history.record(FocusTarget::Window { id });
+        }
+
+        assert_eq!(history.transitions().count(), HISTORY_LEN);
+        assert_eq!(
+            history.transitions().next().unwrap().target,
+            FocusTarget::Window { id: 2 }
+        );
+    }
+}
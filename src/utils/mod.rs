@@ -11,7 +11,7 @@ use anyhow::{ensure, Context};
 use bitflags::bitflags;
 use directories::UserDirs;
 use git_version::git_version;
-use niri_config::{Config, OutputName};
+use niri_config::{Config, ImageFormat, OutputName, ScreenshotFormat};
 use smithay::input::pointer::CursorIcon;
 use smithay::output::{self, Output};
 use smithay::reexports::rustix::time::{clock_gettime, ClockId};
@@ -28,7 +28,14 @@ use wayland_backend::server::Credentials;
 
 use crate::niri::ClientState;
 
+pub mod brightness;
+pub mod clipboard_history;
+pub mod color_temperature;
+pub mod desktop_entries;
+pub mod focus_history;
 pub mod id;
+#[cfg(feature = "fallback-lock")]
+pub mod pam;
 pub mod scale;
 pub mod spawning;
 pub mod transaction;
@@ -171,6 +178,82 @@ pub fn ipc_transform_to_smithay(transform: niri_ipc::Transform) -> Transform {
     }
 }
 
+/// Returns the transform to apply to an output.
+///
+/// For outputs with auto-rotation, the configured transform describes the monitor in the normal
+/// device orientation, and the accelerometer rotation goes on top.
+pub fn output_transform(
+    config: Option<&niri_config::Output>,
+    accelerometer_rotation: Option<Transform>,
+) -> Transform {
+    let transform = config
+        .map(|c| ipc_transform_to_smithay(c.transform))
+        .unwrap_or(Transform::Normal);
+
+    match accelerometer_rotation {
+        Some(rotation) if config.is_some_and(|c| c.auto_rotate) => {
+            rotate_transform(transform, rotation)
+        }
+        _ => transform,
+    }
+}
+
+/// Adds a rotation on top of a transform.
+///
+/// `rotation` must not be flipped.
+pub fn rotate_transform(transform: Transform, rotation: Transform) -> Transform {
+    const ROTATED: [Transform; 4] = [
+        Transform::Normal,
+        Transform::_90,
+        Transform::_180,
+        Transform::_270,
+    ];
+    const FLIPPED: [Transform; 4] = [
+        Transform::Flipped,
+        Transform::Flipped90,
+        Transform::Flipped180,
+        Transform::Flipped270,
+    ];
+
+    let steps = ROTATED.iter().position(|t| *t == rotation).unwrap_or(0);
+    let family = if FLIPPED.contains(&transform) {
+        &FLIPPED
+    } else {
+        &ROTATED
+    };
+    let idx = family.iter().position(|t| *t == transform).unwrap();
+    family[(idx + steps) % 4]
+}
+
+/// Returns the transform for mapping an absolute input device, like a touchscreen, onto an output.
+///
+/// `auto_rotation` is the accelerometer rotation included in `output_transform`, if any. Devices
+/// that don't rotate with the output keep the transform without it.
+pub fn input_device_transform(
+    output_transform: Transform,
+    auto_rotation: Option<Transform>,
+    rotate_with_output: bool,
+) -> Transform {
+    match auto_rotation {
+        Some(rotation) if !rotate_with_output => {
+            rotate_transform(output_transform, rotation.invert())
+        }
+        _ => output_transform,
+    }
+}
+
+/// Maps a position from an absolute input device onto an output of the given size.
+///
+/// `position` receives the output size in the orientation of the device.
+pub fn map_absolute_position(
+    transform: Transform,
+    output_size: Size<i32, Logical>,
+    position: impl FnOnce(Size<i32, Logical>) -> Point<f64, Logical>,
+) -> Point<f64, Logical> {
+    let size = transform.invert().transform_size(output_size);
+    transform.transform_point_in(position(size), &size.to_f64())
+}
+
 pub fn send_scale_transform(
     surface: &WlSurface,
     data: &SurfaceData,
@@ -192,12 +275,43 @@ pub fn expand_home(path: &Path) -> anyhow::Result<Option<PathBuf>> {
     }
 }
 
-pub fn make_screenshot_path(config: &Config) -> anyhow::Result<Option<PathBuf>> {
-    let Some(path) = &config.screenshot_path else {
+/// Window information used to fill in the `%{...}` tokens of screenshot and recording paths.
+#[derive(Debug, Default, Clone)]
+pub struct ScreenshotWindowInfo {
+    pub app_id: Option<String>,
+    pub title: Option<String>,
+}
+
+pub fn make_screenshot_path(
+    config: &Config,
+    format: ImageFormat,
+    window: &ScreenshotWindowInfo,
+) -> anyhow::Result<Option<PathBuf>> {
+    let Some(path) = config.screenshot_path_template() else {
         return Ok(None);
     };
 
-    let format = CString::new(path.clone()).context("path must not contain nul bytes")?;
+    let mut path = expand_path_template(path, window)?;
+
+    // The default path ends in .png; make it follow the configured format.
+    if format != ImageFormat::Png && path.extension().is_some_and(|ext| ext == "png") {
+        path.set_extension(format.extension());
+    }
+
+    Ok(Some(path))
+}
+
+pub fn make_screen_recording_path(
+    config: &Config,
+    window: &ScreenshotWindowInfo,
+) -> anyhow::Result<PathBuf> {
+    expand_path_template(&config.screen_recording.path, window)
+}
+
+/// Expands `%{...}` tokens, strftime(3) formatting and `~` in a path template.
+fn expand_path_template(template: &str, window: &ScreenshotWindowInfo) -> anyhow::Result<PathBuf> {
+    let template = expand_screenshot_tokens(template, window);
+    let format = CString::new(template).context("path must not contain nul bytes")?;
 
     let mut buf = [0u8; 2048];
     let mut path;
@@ -218,7 +332,36 @@ pub fn make_screenshot_path(config: &Config) -> anyhow::Result<Option<PathBuf>>
         path = expanded;
     }
 
-    Ok(Some(path))
+    Ok(path)
+}
+
+/// Maximum length of a value substituted into the screenshot path, in bytes.
+const MAX_SCREENSHOT_TOKEN_LEN: usize = 200;
+
+fn expand_screenshot_tokens(template: &str, window: &ScreenshotWindowInfo) -> String {
+    // The values end up in a file name and then go through strftime, so make them safe for both.
+    fn sanitize(value: Option<&str>) -> String {
+        let mut value = value.unwrap_or_default();
+
+        // Window titles can be long enough to go past the file name length limit (255 bytes).
+        if value.len() > MAX_SCREENSHOT_TOKEN_LEN {
+            let mut end = MAX_SCREENSHOT_TOKEN_LEN;
+            while !value.is_char_boundary(end) {
+                end -= 1;
+            }
+            value = &value[..end];
+        }
+
+        if value == "." || value == ".." {
+            return String::from("_");
+        }
+
+        value.replace(['/', '\0'], "_").replace('%', "%%")
+    }
+
+    template
+        .replace("%{app-id}", &sanitize(window.app_id.as_deref()))
+        .replace("%{window-title}", &sanitize(window.title.as_deref()))
 }
 
 pub fn write_png_rgba8(
@@ -235,6 +378,32 @@ pub fn write_png_rgba8(
     writer.write_image_data(pixels)
 }
 
+pub fn write_image_rgba8(
+    w: impl Write,
+    format: ScreenshotFormat,
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+) -> anyhow::Result<()> {
+    match format.kind {
+        ImageFormat::Png => write_png_rgba8(w, width, height, pixels)?,
+        ImageFormat::Jpeg => {
+            let width = u16::try_from(width).context("image is too wide for JPEG")?;
+            let height = u16::try_from(height).context("image is too tall for JPEG")?;
+            let quality = format.quality.clamp(1, 100);
+            let encoder = jpeg_encoder::Encoder::new(w, quality);
+            encoder.encode(pixels, width, height, jpeg_encoder::ColorType::Rgba)?;
+        }
+        ImageFormat::Webp => {
+            // image-webp only supports lossless encoding, so quality is ignored.
+            let encoder = image_webp::WebPEncoder::new(w);
+            encoder.encode(pixels, width, height, image_webp::ColorType::Rgba8)?;
+        }
+    }
+
+    Ok(())
+}
+
 pub fn output_matches_name(output: &Output, target: &str) -> bool {
     let name = output.user_data().get::<OutputName>().unwrap();
     name.matches(target)
@@ -273,6 +442,20 @@ pub fn get_credentials_for_surface(surface: &WlSurface) -> Option<Credentials> {
     client.get_credentials(&dh).ok()
 }
 
+/// Returns whether the surface belongs to xwayland-satellite, i.e. is an X11 window.
+pub fn is_xwayland_satellite_surface(surface: &WlSurface) -> bool {
+    let Some(handle) = surface.handle().upgrade() else {
+        return false;
+    };
+    let dh = DisplayHandle::from(handle);
+
+    let Ok(client) = dh.get_client(surface.id()) else {
+        return false;
+    };
+    let data = client.get_data::<ClientState>().unwrap();
+    data.is_xwayland_satellite
+}
+
 pub fn ensure_min_max_size(mut x: i32, min_size: i32, max_size: i32) -> i32 {
     if max_size > 0 {
         x = min(x, max_size);
@@ -410,4 +593,147 @@ mod tests {
         check((0, 0, 10, 20), (20, 30, 4, 50), (6, 0));
         check((0, 0, 10, 20), (20, 30, 40, 50), (0, 0));
     }
+
+    #[test]
+    fn test_expand_screenshot_tokens() {
+        let window = ScreenshotWindowInfo {
+            app_id: Some(String::from("org.gnome.Nautilus")),
+            title: Some(String::from("100% /home")),
+        };
+        assert_eq!(
+            expand_screenshot_tokens("~/%{app-id}/%{window-title} %H.png", &window),
+            "~/org.gnome.Nautilus/100%% _home %H.png"
+        );
+
+        let empty = ScreenshotWindowInfo::default();
+        assert_eq!(
+            expand_screenshot_tokens("%{app-id}-%{window-title}", &empty),
+            "-"
+        );
+    }
+
+    #[test]
+    fn test_expand_screenshot_tokens_limits() {
+        // Truncated at a char boundary: 'é' takes 2 bytes, so 100 of them take exactly 200.
+        let window = ScreenshotWindowInfo {
+            app_id: None,
+            title: Some("é".repeat(150)),
+        };
+        let expanded = expand_screenshot_tokens("%{window-title}", &window);
+        assert_eq!(expanded, "é".repeat(100));
+
+        let window = ScreenshotWindowInfo {
+            app_id: None,
+            title: Some(format!("a{}", "é".repeat(150))),
+        };
+        let expanded = expand_screenshot_tokens("%{window-title}", &window);
+        assert_eq!(expanded, format!("a{}", "é".repeat(99)));
+
+        for title in [".", ".."] {
+            let window = ScreenshotWindowInfo {
+                app_id: Some(String::from(title)),
+                title: Some(String::from(title)),
+            };
+            assert_eq!(
+                expand_screenshot_tokens("~/%{app-id}/%{window-title}.png", &window),
+                "~/_/_.png"
+            );
+        }
+    }
+
+    #[test]
+    fn test_rotate_transform() {
+        assert_eq!(
+            rotate_transform(Transform::Normal, Transform::_90),
+            Transform::_90
+        );
+        assert_eq!(
+            rotate_transform(Transform::_270, Transform::_180),
+            Transform::_90
+        );
+        assert_eq!(
+            rotate_transform(Transform::Flipped, Transform::_90),
+            Transform::Flipped90
+        );
+        assert_eq!(
+            rotate_transform(Transform::Flipped270, Transform::_90),
+            Transform::Flipped
+        );
+
+        let all = [
+            Transform::Normal,
+            Transform::_90,
+            Transform::_180,
+            Transform::_270,
+            Transform::Flipped,
+            Transform::Flipped90,
+            Transform::Flipped180,
+            Transform::Flipped270,
+        ];
+        for transform in all {
+            assert_eq!(rotate_transform(transform, Transform::Normal), transform);
+
+            let twice =
+                rotate_transform(rotate_transform(transform, Transform::_90), Transform::_90);
+            assert_eq!(twice, rotate_transform(transform, Transform::_180));
+
+            let full = (0..4).fold(transform, |t, _| rotate_transform(t, Transform::_90));
+            assert_eq!(full, transform);
+        }
+    }
+
+    #[test]
+    fn test_input_device_transform() {
+        // Not auto-rotated.
+        for rotate in [false, true] {
+            assert_eq!(
+                input_device_transform(Transform::_90, None, rotate),
+                Transform::_90
+            );
+        }
+
+        // Configured as flipped, then rotated by the accelerometer.
+        let transform = rotate_transform(Transform::Flipped, Transform::_90);
+        assert_eq!(
+            input_device_transform(transform, Some(Transform::_90), true),
+            Transform::Flipped90
+        );
+        assert_eq!(
+            input_device_transform(transform, Some(Transform::_90), false),
+            Transform::Flipped
+        );
+    }
+
+    #[test]
+    fn test_map_absolute_position() {
+        let map = |transform, size: (i32, i32), (x, y): (f64, f64)| {
+            map_absolute_position(transform, Size::from(size), |size| {
+                Point::from((f64::from(size.w) * x, f64::from(size.h) * y))
+            })
+        };
+
+        assert_eq!(
+            map(Transform::Normal, (1920, 1080), (0.25, 0.5)),
+            Point::from((480., 540.))
+        );
+        assert_eq!(
+            map(Transform::_180, (1920, 1080), (0.25, 0.5)),
+            Point::from((1440., 540.))
+        );
+
+        // A rotated output is portrait, but the device still spans all of it.
+        assert_eq!(
+            map(Transform::_90, (1080, 1920), (0.5, 0.5)),
+            Point::from((540., 960.))
+        );
+        let mut corners: Vec<_> = [(0., 0.), (1., 0.), (0., 1.), (1., 1.)]
+            .into_iter()
+            .map(|pos| {
+                let pos = map(Transform::_90, (1080, 1920), pos);
+                (pos.x as i32, pos.y as i32)
+            })
+            .collect();
+        corners.sort();
+        assert_eq!(corners, [(0, 0), (0, 1920), (1080, 0), (1080, 1920)]);
+    }
 }
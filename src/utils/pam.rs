@@ -0,0 +1,167 @@
+//! Password checking through PAM, for the fallback lock screen.
+
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::ptr::{self, null_mut};
+
+use anyhow::{bail, ensure};
+use zeroize::{Zeroize, Zeroizing};
+
+/// PAM service used to check the password.
+///
+/// Ship `resources/niri.pam` as `/etc/pam.d/niri` to configure it.
+const SERVICE: &CStr = c"niri";
+
+const PAM_SUCCESS: c_int = 0;
+const PAM_BUF_ERR: c_int = 5;
+const PAM_PROMPT_ECHO_OFF: c_int = 1;
+const PAM_PROMPT_ECHO_ON: c_int = 2;
+
+#[repr(C)]
+struct PamMessage {
+    msg_style: c_int,
+    msg: *const c_char,
+}
+
+#[repr(C)]
+struct PamResponse {
+    resp: *mut c_char,
+    resp_retcode: c_int,
+}
+
+#[repr(C)]
+struct PamConv {
+    conv: unsafe extern "C" fn(
+        num_msg: c_int,
+        msg: *mut *const PamMessage,
+        resp: *mut *mut PamResponse,
+        appdata_ptr: *mut c_void,
+    ) -> c_int,
+    appdata_ptr: *mut c_void,
+}
+
+#[link(name = "pam")]
+extern "C" {
+    fn pam_start(
+        service_name: *const c_char,
+        user: *const c_char,
+        pam_conversation: *const PamConv,
+        pamh: *mut *mut c_void,
+    ) -> c_int;
+    fn pam_authenticate(pamh: *mut c_void, flags: c_int) -> c_int;
+    fn pam_end(pamh: *mut c_void, pam_status: c_int) -> c_int;
+}
+
+/// Answers every PAM prompt with the password.
+unsafe extern "C" fn conversation(
+    num_msg: c_int,
+    msg: *mut *const PamMessage,
+    resp: *mut *mut PamResponse,
+    appdata_ptr: *mut c_void,
+) -> c_int {
+    let Ok(num_msg) = usize::try_from(num_msg) else {
+        return PAM_BUF_ERR;
+    };
+
+    // PAM frees the responses, so they must come from the libc allocator.
+    let responses = libc::calloc(num_msg, std::mem::size_of::<PamResponse>()).cast::<PamResponse>();
+    if responses.is_null() {
+        return PAM_BUF_ERR;
+    }
+
+    let password = appdata_ptr.cast::<CString>();
+    for i in 0..num_msg {
+        let message = &**msg.add(i);
+        if matches!(message.msg_style, PAM_PROMPT_ECHO_OFF | PAM_PROMPT_ECHO_ON) {
+            (*responses.add(i)).resp = libc::strdup((*password).as_ptr());
+        }
+    }
+
+    *resp = responses;
+    PAM_SUCCESS
+}
+
+/// Returns the name of the user running niri.
+///
+/// This runs on the authentication thread, so it uses the reentrant `getpwuid_r()`.
+fn current_user() -> anyhow::Result<CString> {
+    let mut buf = vec![0 as c_char; 1024];
+    loop {
+        let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut result = null_mut();
+        let rv = unsafe {
+            libc::getpwuid_r(
+                libc::getuid(),
+                &mut passwd,
+                buf.as_mut_ptr(),
+                buf.len(),
+                &mut result,
+            )
+        };
+
+        if rv == libc::ERANGE && buf.len() < 1024 * 1024 {
+            buf.resize(buf.len() * 2, 0);
+            continue;
+        }
+
+        ensure!(
+            rv == 0 && !result.is_null(),
+            "error looking up the current user"
+        );
+        return Ok(unsafe { CStr::from_ptr(passwd.pw_name) }.to_owned());
+    }
+}
+
+/// Moves the password into a `CString` without leaving copies of it behind.
+fn to_c_string(password: Zeroizing<String>) -> anyhow::Result<Zeroizing<CString>> {
+    // Reserve room for the NUL up front so that CString::new() doesn't reallocate.
+    let mut bytes = Zeroizing::new(Vec::with_capacity(password.len() + 1));
+    bytes.extend_from_slice(password.as_bytes());
+    drop(password);
+
+    match CString::new(std::mem::take(&mut *bytes)) {
+        Ok(password) => Ok(Zeroizing::new(password)),
+        Err(err) => {
+            err.into_vec().zeroize();
+            bail!("password contains a NUL byte");
+        }
+    }
+}
+
+/// Checks the password of the user running niri.
+///
+/// This blocks, possibly for a few seconds after a wrong password, so call it from a thread.
+pub fn authenticate(password: Zeroizing<String>) -> anyhow::Result<()> {
+    let user = current_user()?;
+    let mut password = to_c_string(password)?;
+
+    let conv = PamConv {
+        conv: conversation,
+        appdata_ptr: ptr::addr_of_mut!(*password).cast(),
+    };
+
+    let result = unsafe {
+        let mut pamh = null_mut();
+        let rv = pam_start(SERVICE.as_ptr(), user.as_ptr(), &conv, &mut pamh);
+        if rv != PAM_SUCCESS {
+            Err(rv)
+        } else {
+            let rv = pam_authenticate(pamh, 0);
+            pam_end(pamh, rv);
+            if rv == PAM_SUCCESS {
+                Ok(())
+            } else {
+                Err(rv)
+            }
+        }
+    };
+
+    // Don't leave the password lying around in memory. PAM gets its own copies of the password
+    // through strdup() and is responsible for wiping them.
+    drop(password);
+
+    if let Err(rv) = result {
+        bail!("authentication failed with PAM error {rv}");
+    }
+
+    Ok(())
+}
@@ -1,14 +1,15 @@
 use std::ffi::OsStr;
-use std::os::unix::process::CommandExt;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
 use std::path::Path;
-use std::process::{Child, Command, Stdio};
+use std::process::{Child, Command, ExitStatus, Stdio};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::RwLock;
-use std::{io, thread};
+use std::sync::{OnceLock, RwLock};
+use std::time::Duration;
+use std::{fs, io, thread};
 
 use atomic::Atomic;
 use libc::{getrlimit, rlim_t, rlimit, setrlimit, RLIMIT_NOFILE};
-use niri_config::Environment;
+use niri_config::{Environment, EnvironmentVariable};
 use smithay::wayland::xdg_activation::XdgActivationToken;
 
 use crate::utils::expand_home;
@@ -16,6 +17,16 @@ use crate::utils::expand_home;
 pub static REMOVE_ENV_RUST_BACKTRACE: AtomicBool = AtomicBool::new(false);
 pub static REMOVE_ENV_RUST_LIB_BACKTRACE: AtomicBool = AtomicBool::new(false);
 pub static CHILD_ENV: RwLock<Environment> = RwLock::new(Environment(Vec::new()));
+/// Name of the Wayland socket that xwayland-satellite should connect to.
+pub static XWAYLAND_SATELLITE_SOCKET: OnceLock<String> = OnceLock::new();
+
+// Commands that keep crashing get restarted with an increasing delay. Once a command has been
+// running for a while, the delay goes back to the minimum. Commands that crash too many times in
+// a row are not restarted anymore.
+pub const RESTART_MIN_DELAY: Duration = Duration::from_secs(1);
+const RESTART_MAX_DELAY: Duration = Duration::from_secs(60);
+const RESTART_RESET_AFTER: Duration = Duration::from_secs(60);
+pub const RESTART_MAX_CRASHES: u32 = 10;
 
 static ORIGINAL_NOFILE_RLIMIT_CUR: Atomic<rlim_t> = Atomic::new(0);
 static ORIGINAL_NOFILE_RLIMIT_MAX: Atomic<rlim_t> = Atomic::new(0);
@@ -63,6 +74,16 @@ pub fn restore_nofile_rlimit() {
 
 /// Spawns the command to run independently of the compositor.
 pub fn spawn<T: AsRef<OsStr> + Send + 'static>(command: Vec<T>, token: Option<XdgActivationToken>) {
+    spawn_with_env(command, token, Vec::new());
+}
+
+/// Spawns the command like [`spawn()`], with extra environment variables on top of the configured
+/// ones.
+pub fn spawn_with_env<T: AsRef<OsStr> + Send + 'static>(
+    command: Vec<T>,
+    token: Option<XdgActivationToken>,
+    env: Vec<EnvironmentVariable>,
+) {
     let _span = tracy_client::span!();
 
     if command.is_empty() {
@@ -74,7 +95,7 @@ pub fn spawn<T: AsRef<OsStr> + Send + 'static>(command: Vec<T>, token: Option<Xd
         .name("Command Spawner".to_owned())
         .spawn(move || {
             let (command, args) = command.split_first().unwrap();
-            spawn_sync(command, args, token);
+            spawn_sync(command, args, token, &env);
         });
 
     if let Err(err) = res {
@@ -82,14 +103,152 @@ pub fn spawn<T: AsRef<OsStr> + Send + 'static>(command: Vec<T>, token: Option<Xd
     }
 }
 
+/// Spawns the command and calls `on_exit` with its exit status.
+///
+/// Unlike [`spawn()`], the command remains our child so that we can wait for it, which makes this
+/// suitable for commands that should be restarted. `on_exit` is called from a separate thread, and
+/// only if the command could be started in the first place.
+pub fn spawn_and_watch(command: Vec<String>, on_exit: impl FnOnce(ExitStatus) + Send + 'static) {
+    let _span = tracy_client::span!();
+
+    if command.is_empty() {
+        return;
+    }
+
+    let res = thread::Builder::new()
+        .name("Command Watcher".to_owned())
+        .spawn(move || {
+            let (command, args) = command.split_first().unwrap();
+            let mut process = prepare_command(command.as_ref(), args, None, &[]);
+
+            unsafe {
+                process.pre_exec(|| {
+                    restore_nofile_rlimit();
+                    Ok(())
+                });
+            }
+
+            let mut child = match process.spawn() {
+                Ok(child) => child,
+                Err(err) => {
+                    warn!("error spawning {command:?}: {err:?}");
+                    return;
+                }
+            };
+
+            #[cfg(feature = "systemd")]
+            if let Err(err) = systemd::start_systemd_scope(command.as_ref(), &[child.id()]) {
+                trace!("error starting systemd scope for spawned command: {err:?}");
+            }
+
+            match child.wait() {
+                Ok(status) => {
+                    if !status.success() {
+                        warn!("{command:?} did not exit successfully: {status:?}");
+                    }
+                    on_exit(status);
+                }
+                Err(err) => {
+                    warn!("error waiting for child: {err:?}");
+                }
+            }
+        });
+
+    if let Err(err) = res {
+        warn!("error spawning a thread to spawn the command: {err:?}");
+    }
+}
+
+/// Returns whether a watched command crashed, as opposed to exiting successfully or being stopped.
+///
+/// Getting killed with SIGTERM or SIGINT counts as being stopped on purpose, for example with
+/// `pkill`.
+pub fn is_crash(status: ExitStatus) -> bool {
+    if status.success() {
+        return false;
+    }
+
+    !matches!(status.signal(), Some(libc::SIGTERM | libc::SIGINT))
+}
+
+/// Returns the exit status of a process that is exiting, or has exited but was not reaped yet.
+///
+/// The kernel records the exit status before closing the process files, so it is available by the
+/// time a client's Wayland connection closes. Returns `None` once the process is gone, and a
+/// successful status while it is still running.
+pub fn exit_status_of_process(pid: i32) -> Option<ExitStatus> {
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    parse_exit_status(&stat)
+}
+
+fn parse_exit_status(stat: &str) -> Option<ExitStatus> {
+    // The command name in parentheses comes second and can contain spaces and parentheses.
+    let (_, fields) = stat.rsplit_once(')')?;
+    // The fields after it start from the 3rd, and the exit code is the 52nd.
+    let exit_code = fields.split_whitespace().nth(52 - 3)?;
+    exit_code.parse().ok().map(ExitStatus::from_raw)
+}
+
+/// Returns the number of crashes in a row, including this one.
+///
+/// A crash after the command has been running for a while starts a new count.
+pub fn count_crash(crashes: u32, uptime: Duration) -> u32 {
+    if uptime >= RESTART_RESET_AFTER {
+        1
+    } else {
+        crashes + 1
+    }
+}
+
+/// Returns the delay before restarting a crashed command, and the delay for the restart after.
+///
+/// `delay` is the delay planned for this restart, and `uptime` is how long the command ran before
+/// crashing.
+pub fn restart_delays(delay: Duration, uptime: Duration) -> (Duration, Duration) {
+    let delay = if uptime >= RESTART_RESET_AFTER {
+        RESTART_MIN_DELAY
+    } else {
+        delay
+    };
+    let next_delay = (delay * 2).min(RESTART_MAX_DELAY);
+    (delay, next_delay)
+}
+
 fn spawn_sync(
     command: impl AsRef<OsStr>,
     args: impl IntoIterator<Item = impl AsRef<OsStr>>,
     token: Option<XdgActivationToken>,
+    env: &[EnvironmentVariable],
 ) {
     let _span = tracy_client::span!();
 
-    let mut command = command.as_ref();
+    let command = command.as_ref();
+    let process = prepare_command(command, args, token, env);
+
+    let Some(mut child) = do_spawn(command, process) else {
+        return;
+    };
+
+    match child.wait() {
+        Ok(status) => {
+            if !status.success() {
+                warn!("child did not exit successfully: {status:?}");
+            }
+        }
+        Err(err) => {
+            warn!("error waiting for child: {err:?}");
+        }
+    }
+}
+
+/// Sets up the command with the environment that niri passes to its children.
+fn prepare_command(
+    command: &OsStr,
+    args: impl IntoIterator<Item = impl AsRef<OsStr>>,
+    token: Option<XdgActivationToken>,
+    extra_env: &[EnvironmentVariable],
+) -> Command {
+    let mut command = command;
 
     // Expand `~` at the start.
     let expanded = expand_home(Path::new(command));
@@ -116,9 +275,9 @@ fn spawn_sync(
         process.env_remove("RUST_LIB_BACKTRACE");
     }
 
-    // Set configured environment.
+    // Set configured environment, then the one for this command.
     let env = CHILD_ENV.read().unwrap();
-    for var in &env.0 {
+    for var in env.0.iter().chain(extra_env) {
         if let Some(value) = &var.value {
             process.env(&var.name, value);
         } else {
@@ -127,25 +286,19 @@ fn spawn_sync(
     }
     drop(env);
 
+    // Connect xwayland-satellite to its own socket, so that we know which client it is.
+    if Path::new(command).file_name() == Some(OsStr::new("xwayland-satellite")) {
+        if let Some(socket_name) = XWAYLAND_SATELLITE_SOCKET.get() {
+            process.env("WAYLAND_DISPLAY", socket_name);
+        }
+    }
+
     if let Some(token) = token.as_ref() {
         process.env("XDG_ACTIVATION_TOKEN", token.as_str());
         process.env("DESKTOP_STARTUP_ID", token.as_str());
     }
 
-    let Some(mut child) = do_spawn(command, process) else {
-        return;
-    };
-
-    match child.wait() {
-        Ok(status) => {
-            if !status.success() {
-                warn!("child did not exit successfully: {status:?}");
-            }
-        }
-        Err(err) => {
-            warn!("error waiting for child: {err:?}");
-        }
-    }
+    process
 }
 
 #[cfg(not(feature = "systemd"))]
@@ -299,7 +452,7 @@ mod systemd {
 
                     // Start a systemd scope for the grandchild.
                     #[cfg(feature = "systemd")]
-                    if let Err(err) = start_systemd_scope(command, child.id(), pid as u32) {
+                    if let Err(err) = start_systemd_scope(command, &[child.id(), pid as u32]) {
                         trace!("error starting systemd scope for spawned command: {err:?}");
                     }
                 }
@@ -349,16 +502,13 @@ mod systemd {
         }
     }
 
-    /// Puts a (newly spawned) pid into a transient systemd scope.
+    /// Puts (newly spawned) pids into a transient systemd scope.
     ///
-    /// This separates the pid from the compositor scope, which for example prevents the OOM killer
-    /// from bringing down the compositor together with a misbehaving client.
+    /// This separates the pids from the compositor scope, which for example prevents the OOM killer
+    /// from bringing down the compositor together with a misbehaving client. The scope is named
+    /// after the last pid, which should be the one running the command.
     #[cfg(feature = "systemd")]
-    fn start_systemd_scope(
-        name: &OsStr,
-        intermediate_pid: u32,
-        child_pid: u32,
-    ) -> anyhow::Result<()> {
+    pub fn start_systemd_scope(name: &OsStr, pids: &[u32]) -> anyhow::Result<()> {
         use std::fmt::Write as _;
         use std::os::unix::ffi::OsStrExt;
         use std::sync::OnceLock;
@@ -390,6 +540,7 @@ mod systemd {
             }
         }
 
+        let child_pid = pids.last().copied().unwrap_or_default();
         let _ = write!(scope_name, "-{child_pid}.scope");
 
         // Ask systemd to start a transient scope.
@@ -411,7 +562,6 @@ mod systemd {
             .receive_signal("JobRemoved")
             .context("error creating a signal iterator")?;
 
-        let pids: &[_] = &[intermediate_pid, child_pid];
         let properties: &[_] = &[
             ("PIDs", Value::new(pids)),
             ("CollectMode", Value::new("inactive-or-failed")),
@@ -437,3 +587,83 @@ mod systemd {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn restart_delay_doubles() {
+        let short = secs(1);
+        assert_eq!(restart_delays(RESTART_MIN_DELAY, short), (secs(1), secs(2)));
+        assert_eq!(restart_delays(secs(2), short), (secs(2), secs(4)));
+        assert_eq!(restart_delays(secs(4), short), (secs(4), secs(8)));
+    }
+
+    #[test]
+    fn restart_delay_is_capped() {
+        let short = secs(1);
+        assert_eq!(restart_delays(secs(32), short), (secs(32), secs(60)));
+        assert_eq!(restart_delays(secs(60), short), (secs(60), secs(60)));
+    }
+
+    #[test]
+    fn restart_delay_resets_after_uptime() {
+        assert_eq!(restart_delays(secs(60), secs(59)), (secs(60), secs(60)));
+        assert_eq!(restart_delays(secs(60), secs(60)), (secs(1), secs(2)));
+        assert_eq!(restart_delays(secs(16), secs(600)), (secs(1), secs(2)));
+    }
+
+    #[test]
+    fn exit_status_parsing() {
+        let stat = |exit_code: i32| {
+            let fields = "0 ".repeat(52 - 4);
+            format!("1234 (Web (Content)) Z {fields}{exit_code}\n")
+        };
+
+        let status = parse_exit_status(&stat(libc::SIGSEGV | 0x80)).unwrap();
+        assert_eq!(status.signal(), Some(libc::SIGSEGV));
+        assert!(status.core_dumped());
+
+        let status = parse_exit_status(&stat(1 << 8)).unwrap();
+        assert_eq!(status.code(), Some(1));
+
+        let status = parse_exit_status(&stat(0)).unwrap();
+        assert!(status.success());
+
+        assert_eq!(parse_exit_status("1234 (truncated) R 1 2 3"), None);
+        assert_eq!(parse_exit_status("garbage"), None);
+    }
+
+    #[test]
+    fn exit_status_of_running_process() {
+        let status = exit_status_of_process(std::process::id() as i32).unwrap();
+        assert!(status.success());
+        assert!(!is_crash(status));
+    }
+
+    #[test]
+    fn crash_detection() {
+        // Raw wait statuses: the exit code goes in the second byte, the signal in the first.
+        let exited = |code: i32| ExitStatus::from_raw(code << 8);
+        let killed = ExitStatus::from_raw;
+
+        assert!(!is_crash(exited(0)));
+        assert!(is_crash(exited(1)));
+        assert!(is_crash(killed(libc::SIGSEGV)));
+        assert!(is_crash(killed(libc::SIGKILL)));
+        assert!(!is_crash(killed(libc::SIGTERM)));
+        assert!(!is_crash(killed(libc::SIGINT)));
+    }
+
+    #[test]
+    fn crash_count_resets_after_uptime() {
+        assert_eq!(count_crash(0, secs(1)), 1);
+        assert_eq!(count_crash(3, secs(59)), 4);
+        assert_eq!(count_crash(3, secs(60)), 1);
+    }
+}
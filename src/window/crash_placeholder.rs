@@ -0,0 +1,266 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+use ordered_float::NotNan;
+use pangocairo::cairo::{self, ImageSurface};
+use pangocairo::pango::{Alignment, FontDescription};
+use smithay::backend::renderer::element::Kind;
+use smithay::reexports::gbm::Format as Fourcc;
+use smithay::utils::{Logical, Point, Size, Transform};
+
+use crate::animation::Clock;
+use crate::layout::startup_placeholder::{
+    draw_icon, draw_letter, StartupApp, STARTUP_PLACEHOLDER_TIMEOUT,
+};
+use crate::layout::LayoutElementRenderElement;
+use crate::render_helpers::memory::MemoryBuffer;
+use crate::render_helpers::primary_gpu_texture::PrimaryGpuTextureRenderElement;
+use crate::render_helpers::renderer::NiriRenderer;
+use crate::render_helpers::solid_color::{SolidColorBuffer, SolidColorRenderElement};
+use crate::render_helpers::texture::{TextureBuffer, TextureRenderElement};
+use crate::utils::to_physical_precise_round;
+
+const BACKGROUND_COLOR: [f32; 4] = [0.15, 0.15, 0.15, 1.];
+const ICON_SIZE: i32 = 64;
+const SPACING: i32 = 16;
+const FONT: &str = "sans 14px";
+
+/// Placeholder shown in place of a window whose client crashed.
+///
+/// It keeps the window slot in the layout for a while, so that the restarted application can
+/// take it over.
+pub struct CrashPlaceholder {
+    /// Application that crashed.
+    app: StartupApp,
+
+    /// Command that restarts the application, if known.
+    command: Option<Vec<String>>,
+
+    /// Size of the placeholder, starting from the size of the crashed window.
+    size: Size<i32, Logical>,
+
+    /// How long to keep the placeholder after the crash.
+    timeout: Duration,
+
+    /// Time when the window crashed, or when the application was restarted.
+    started_at: Duration,
+
+    /// Whether the application was restarted and the placeholder waits for its window.
+    is_restarting: bool,
+
+    /// Clock for the timeout.
+    clock: Clock,
+
+    /// Background of the placeholder.
+    background: RefCell<SolidColorBuffer>,
+
+    /// Rendered contents, for every scale and restarting state.
+    buffers: RefCell<HashMap<(NotNan<f64>, bool), Option<MemoryBuffer>>>,
+}
+
+impl CrashPlaceholder {
+    pub fn new(
+        app: StartupApp,
+        command: Option<Vec<String>>,
+        size: Size<i32, Logical>,
+        timeout: Duration,
+        clock: Clock,
+    ) -> Self {
+        Self {
+            app,
+            command: command.filter(|command| !command.is_empty()),
+            size,
+            timeout,
+            started_at: clock.now(),
+            is_restarting: false,
+            clock,
+            background: RefCell::new(SolidColorBuffer::new(size.to_f64(), BACKGROUND_COLOR)),
+            buffers: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn app_id(&self) -> Option<&str> {
+        self.app.app_id.as_deref()
+    }
+
+    pub fn size(&self) -> Size<i32, Logical> {
+        self.size
+    }
+
+    pub fn set_size(&mut self, size: Size<i32, Logical>) {
+        self.size = size;
+    }
+
+    /// Returns the command to restart the application, and starts waiting for its window.
+    ///
+    /// Returns `None` if the command is unknown or the application is already restarting.
+    pub fn restart(&mut self) -> Option<Vec<String>> {
+        if self.is_restarting {
+            return None;
+        }
+        let command = self.command.clone()?;
+
+        self.is_restarting = true;
+        self.started_at = self.clock.now();
+        Some(command)
+    }
+
+    /// Returns how long the placeholder has left before it should be dismissed.
+    pub fn time_left(&self) -> Duration {
+        // Once restarted, wait for the window as long as for any other spawned application.
+        let timeout = if self.is_restarting {
+            STARTUP_PLACEHOLDER_TIMEOUT
+        } else {
+            self.timeout
+        };
+
+        let elapsed = self.clock.now().saturating_sub(self.started_at);
+        timeout.saturating_sub(elapsed)
+    }
+
+    pub fn render<R: NiriRenderer>(
+        &self,
+        renderer: &mut R,
+        location: Point<f64, Logical>,
+        scale: f64,
+        alpha: f32,
+    ) -> Vec<LayoutElementRenderElement<R>> {
+        let mut rv = Vec::new();
+
+        let size = self.size.to_f64();
+
+        if let Some(buffer) = self.content_buffer(scale) {
+            if let Ok(buffer) =
+                TextureBuffer::from_memory_buffer(renderer.as_gles_renderer(), &buffer)
+            {
+                let content_size = buffer.logical_size();
+                // Hide the contents rather than overflow a small window.
+                if content_size.w <= size.w && content_size.h <= size.h {
+                    let loc = location + (size.to_point() - content_size.to_point()).downscale(2.);
+                    let loc = loc.to_physical_precise_round(scale).to_logical(scale);
+
+                    let elem = TextureRenderElement::from_texture_buffer(
+                        buffer,
+                        loc,
+                        alpha,
+                        None,
+                        None,
+                        Kind::Unspecified,
+                    );
+                    rv.push(PrimaryGpuTextureRenderElement(elem).into());
+                }
+            }
+        }
+
+        let mut background = self.background.borrow_mut();
+        background.resize(size);
+        let elem =
+            SolidColorRenderElement::from_buffer(&background, location, alpha, Kind::Unspecified);
+        rv.push(elem.into());
+
+        rv
+    }
+
+    fn content_buffer(&self, scale: f64) -> Option<MemoryBuffer> {
+        let hint = if self.is_restarting {
+            "Restarting…"
+        } else if self.command.is_some() {
+            "Click to restart"
+        } else {
+            ""
+        };
+
+        let mut buffers = self.buffers.borrow_mut();
+        buffers
+            .entry((NotNan::new(scale).unwrap(), self.is_restarting))
+            .or_insert_with(|| {
+                render(scale, &self.app, hint)
+                    .map_err(|err| warn!("error rendering crash placeholder: {err:?}"))
+                    .ok()
+            })
+            .clone()
+    }
+}
+
+impl fmt::Debug for CrashPlaceholder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CrashPlaceholder")
+            .field("app", &self.app)
+            .field("command", &self.command)
+            .field("size", &self.size)
+            .field("is_restarting", &self.is_restarting)
+            .finish_non_exhaustive()
+    }
+}
+
+fn render(scale: f64, app: &StartupApp, hint: &str) -> anyhow::Result<MemoryBuffer> {
+    let _span = tracy_client::span!("crash_placeholder::render");
+
+    let icon_size: i32 = to_physical_precise_round(scale, ICON_SIZE);
+    let spacing: i32 = to_physical_precise_round(scale, SPACING);
+
+    let mut text = format!("{} quit unexpectedly", app.name);
+    if !hint.is_empty() {
+        text.push('\n');
+        text.push_str(hint);
+    }
+
+    let mut font = FontDescription::from_string(FONT);
+    font.set_absolute_size(to_physical_precise_round(scale, font.size()));
+
+    let surface = ImageSurface::create(cairo::Format::ARgb32, 0, 0)?;
+    let cr = cairo::Context::new(&surface)?;
+    let layout = pangocairo::functions::create_layout(&cr);
+    layout.context().set_round_glyph_positions(false);
+    layout.set_font_description(Some(&font));
+    layout.set_alignment(Alignment::Center);
+    layout.set_text(&text);
+
+    let (text_width, text_height) = layout.pixel_size();
+    let width = text_width.max(icon_size);
+    let height = icon_size + spacing + text_height;
+
+    let surface = ImageSurface::create(cairo::Format::ARgb32, width, height)?;
+    let cr = cairo::Context::new(&surface)?;
+
+    cr.save()?;
+    cr.translate(f64::from(width - icon_size) / 2., 0.);
+    let mut has_icon = false;
+    if let Some(icon) = &app.icon {
+        match draw_icon(&cr, icon, icon_size) {
+            Ok(()) => has_icon = true,
+            Err(err) => warn!("error drawing icon {icon:?}: {err:?}"),
+        }
+    }
+    if !has_icon {
+        draw_letter(&cr, &app.name, icon_size, scale)?;
+    }
+    cr.restore()?;
+
+    cr.move_to(
+        f64::from(width - text_width) / 2.,
+        f64::from(icon_size + spacing),
+    );
+    let layout = pangocairo::functions::create_layout(&cr);
+    layout.context().set_round_glyph_positions(false);
+    layout.set_font_description(Some(&font));
+    layout.set_alignment(Alignment::Center);
+    layout.set_text(&text);
+
+    cr.set_source_rgb(1., 1., 1.);
+    pangocairo::functions::show_layout(&cr, &layout);
+    drop(cr);
+
+    let data = surface.take_data().unwrap();
+    let buffer = MemoryBuffer::new(
+        data.to_vec(),
+        Fourcc::Argb8888,
+        (width, height),
+        scale,
+        Transform::Normal,
+    );
+
+    Ok(buffer)
+}
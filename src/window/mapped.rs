@@ -18,6 +18,7 @@ use smithay::wayland::seat::WaylandFocus;
 use smithay::wayland::shell::xdg::{SurfaceCachedState, ToplevelSurface};
 use wayland_backend::server::Credentials;
 
+use super::crash_placeholder::CrashPlaceholder;
 use super::{ResolvedWindowRules, WindowRef};
 use crate::handlers::KdeDecorationsModeState;
 use crate::layout::{
@@ -27,6 +28,7 @@ use crate::layout::{
 use crate::niri::WindowOffscreenId;
 use crate::niri_render_elements;
 use crate::render_helpers::border::BorderRenderElement;
+use crate::render_helpers::primary_gpu_texture::PrimaryGpuTextureRenderElement;
 use crate::render_helpers::renderer::NiriRenderer;
 use crate::render_helpers::snapshot::RenderSnapshot;
 use crate::render_helpers::solid_color::{SolidColorBuffer, SolidColorRenderElement};
@@ -35,7 +37,8 @@ use crate::render_helpers::{BakedBuffer, RenderTarget, SplitElements};
 use crate::utils::id::IdCounter;
 use crate::utils::transaction::Transaction;
 use crate::utils::{
-    get_credentials_for_surface, send_scale_transform, with_toplevel_role, ResizeEdge,
+    get_credentials_for_surface, get_monotonic_time, send_scale_transform, with_toplevel_role,
+    ResizeEdge,
 };
 
 #[derive(Debug)]
@@ -63,15 +66,34 @@ pub struct Mapped {
     /// Whether this window has the keyboard focus.
     is_focused: bool,
 
+    /// Monotonic time when this window started wanting attention, if it does.
+    ///
+    /// Set when an activation request for this window was blocked, and cleared once it's focused.
+    urgent_since: Option<Duration>,
+
+    /// Monotonic time when this window was last focused, if it ever was.
+    focus_timestamp: Option<Duration>,
+
+    /// Whether this window is selected for a batch action.
+    is_selected: bool,
+
     /// Whether this window is the active window in its column.
     is_active_in_column: bool,
 
     /// Whether this window is floating.
     is_floating: bool,
 
+    /// Whether this window is in a maximized (full-width) column.
+    is_maximized: bool,
+
     /// Whether this window should ignore opacity set through window rules.
     ignore_opacity_window_rule: bool,
 
+    /// Marks set on this window through actions.
+    ///
+    /// A mark can be set on at most one window at a time, which is enforced by the caller.
+    marks: Vec<String>,
+
     /// Buffer to draw instead of the window when it should be blocked out.
     block_out_buffer: RefCell<SolidColorBuffer>,
 
@@ -99,6 +121,11 @@ pub struct Mapped {
     ///
     /// Used for double-resize-click tracking.
     last_interactive_resize_start: Cell<Option<(Duration, ResizeEdge)>>,
+
+    /// Placeholder shown instead of the window after its client crashed.
+    ///
+    /// The toplevel is dead at this point, so the placeholder takes over its size and rendering.
+    crash_placeholder: Option<CrashPlaceholder>,
 }
 
 niri_render_elements! {
@@ -106,6 +133,7 @@ niri_render_elements! {
         Layout = LayoutElementRenderElement<R>,
         // Blocked-out window with rounded corners.
         Border = BorderRenderElement,
+        Watermark = PrimaryGpuTextureRenderElement,
     }
 }
 
@@ -168,9 +196,14 @@ impl Mapped {
             rules,
             need_to_recompute_rules: false,
             is_focused: false,
+            urgent_since: None,
+            focus_timestamp: None,
+            is_selected: false,
             is_active_in_column: true,
             is_floating: false,
+            is_maximized: false,
             ignore_opacity_window_rule: false,
+            marks: Vec::new(),
             block_out_buffer: RefCell::new(SolidColorBuffer::new((0., 0.), [0., 0., 0., 1.])),
             animate_next_configure: false,
             animate_serials: Vec::new(),
@@ -180,6 +213,7 @@ impl Mapped {
             pending_transactions: Vec::new(),
             interactive_resize: None,
             last_interactive_resize_start: Cell::new(None),
+            crash_placeholder: None,
         }
     }
 
@@ -230,6 +264,31 @@ impl Mapped {
         self.is_focused
     }
 
+    pub fn is_urgent(&self) -> bool {
+        self.urgent_since.is_some()
+    }
+
+    pub fn focus_timestamp(&self) -> Option<Duration> {
+        self.focus_timestamp
+    }
+
+    pub fn urgent_since(&self) -> Option<Duration> {
+        self.urgent_since
+    }
+
+    pub fn set_urgent(&mut self) {
+        // A focused window has the user's attention already.
+        if self.is_focused {
+            self.urgent_since = None;
+        } else if self.urgent_since.is_none() {
+            self.urgent_since = Some(get_monotonic_time());
+        }
+    }
+
+    pub fn set_selected(&mut self, selected: bool) {
+        self.is_selected = selected;
+    }
+
     pub fn is_active_in_column(&self) -> bool {
         self.is_active_in_column
     }
@@ -238,10 +297,42 @@ impl Mapped {
         self.is_floating
     }
 
+    pub fn is_maximized(&self) -> bool {
+        self.is_maximized
+    }
+
     pub fn toggle_ignore_opacity_window_rule(&mut self) {
         self.ignore_opacity_window_rule = !self.ignore_opacity_window_rule;
     }
 
+    pub fn marks(&self) -> &[String] {
+        &self.marks
+    }
+
+    pub fn has_mark(&self, mark: &str) -> bool {
+        self.marks.iter().any(|m| m == mark)
+    }
+
+    pub fn add_mark(&mut self, mark: String) {
+        if self.has_mark(&mark) {
+            return;
+        }
+
+        self.marks.push(mark);
+        self.need_to_recompute_rules = true;
+    }
+
+    /// Removes the mark, or all marks if `mark` is `None`.
+    pub fn remove_mark(&mut self, mark: Option<&str>) {
+        let len = self.marks.len();
+        match mark {
+            Some(mark) => self.marks.retain(|m| m != mark),
+            None => self.marks.clear(),
+        }
+
+        self.need_to_recompute_rules |= self.marks.len() != len;
+    }
+
     pub fn set_is_focused(&mut self, is_focused: bool) {
         if self.is_focused == is_focused {
             return;
@@ -249,6 +340,11 @@ impl Mapped {
 
         self.is_focused = is_focused;
         self.need_to_recompute_rules = true;
+
+        if is_focused {
+            self.urgent_since = None;
+            self.focus_timestamp = Some(get_monotonic_time());
+        }
     }
 
     fn render_snapshot(&self, renderer: &mut GlesRenderer) -> LayoutElementRenderSnapshot {
@@ -348,6 +444,26 @@ impl Mapped {
         &self.last_interactive_resize_start
     }
 
+    /// Replaces the window with a placeholder after its client crashed.
+    pub fn set_crashed(&mut self, placeholder: CrashPlaceholder) {
+        self.crash_placeholder = Some(placeholder);
+        self.interactive_resize = None;
+        self.transaction_for_next_configure = None;
+        self.pending_transactions.clear();
+    }
+
+    pub fn is_crashed(&self) -> bool {
+        self.crash_placeholder.is_some()
+    }
+
+    pub fn crash_placeholder(&self) -> Option<&CrashPlaceholder> {
+        self.crash_placeholder.as_ref()
+    }
+
+    pub fn crash_placeholder_mut(&mut self) -> Option<&mut CrashPlaceholder> {
+        self.crash_placeholder.as_mut()
+    }
+
     pub fn render_for_screen_cast<R: NiriRenderer>(
         &self,
         renderer: &mut R,
@@ -413,14 +529,26 @@ impl LayoutElement for Mapped {
     }
 
     fn size(&self) -> Size<i32, Logical> {
+        if let Some(placeholder) = &self.crash_placeholder {
+            return placeholder.size();
+        }
+
         self.window.geometry().size
     }
 
     fn buf_loc(&self) -> Point<i32, Logical> {
+        if self.crash_placeholder.is_some() {
+            return Point::from((0, 0));
+        }
+
         Point::from((0, 0)) - self.window.geometry().loc
     }
 
     fn is_in_input_region(&self, point: Point<f64, Logical>) -> bool {
+        if let Some(placeholder) = &self.crash_placeholder {
+            return Rectangle::from_size(placeholder.size().to_f64()).contains(point);
+        }
+
         let surface_local = point + self.window.geometry().loc.to_f64();
         self.window.is_in_input_region(&surface_local)
     }
@@ -435,7 +563,9 @@ impl LayoutElement for Mapped {
     ) -> SplitElements<LayoutElementRenderElement<R>> {
         let mut rv = SplitElements::default();
 
-        if target.should_block_out(self.rules.block_out_from) {
+        if let Some(placeholder) = &self.crash_placeholder {
+            rv.normal = placeholder.render(renderer, location, scale.x, alpha);
+        } else if target.should_block_out(self.rules.block_out_from) {
             let mut buffer = self.block_out_buffer.borrow_mut();
             buffer.resize(self.window.geometry().size.to_f64());
             let elem =
@@ -479,7 +609,9 @@ impl LayoutElement for Mapped {
         alpha: f32,
         target: RenderTarget,
     ) -> Vec<LayoutElementRenderElement<R>> {
-        if target.should_block_out(self.rules.block_out_from) {
+        if let Some(placeholder) = &self.crash_placeholder {
+            placeholder.render(renderer, location, scale.x, alpha)
+        } else if target.should_block_out(self.rules.block_out_from) {
             let mut buffer = self.block_out_buffer.borrow_mut();
             buffer.resize(self.window.geometry().size.to_f64());
             let elem =
@@ -507,7 +639,7 @@ impl LayoutElement for Mapped {
         alpha: f32,
         target: RenderTarget,
     ) -> Vec<LayoutElementRenderElement<R>> {
-        if target.should_block_out(self.rules.block_out_from) {
+        if self.crash_placeholder.is_some() || target.should_block_out(self.rules.block_out_from) {
             vec![]
         } else {
             let mut rv = vec![];
@@ -537,12 +669,19 @@ impl LayoutElement for Mapped {
         animate: bool,
         transaction: Option<Transaction>,
     ) {
-        let changed = self.toplevel().with_pending_state(|state| {
+        // The dead window won't respond, so resize the placeholder right away.
+        if let Some(placeholder) = &mut self.crash_placeholder {
+            placeholder.set_size(size);
+            return;
+        }
+
+        let (changed, fullscreen_changed) = self.toplevel().with_pending_state(|state| {
             let changed = state.size != Some(size);
             state.size = Some(size);
-            state.states.unset(xdg_toplevel::State::Fullscreen);
-            changed
+            let fullscreen_changed = state.states.unset(xdg_toplevel::State::Fullscreen);
+            (changed, fullscreen_changed)
         });
+        self.need_to_recompute_rules |= fullscreen_changed;
 
         if changed && animate {
             self.animate_next_configure = true;
@@ -561,6 +700,11 @@ impl LayoutElement for Mapped {
     }
 
     fn request_size_once(&mut self, size: Size<i32, Logical>, animate: bool) {
+        if let Some(placeholder) = &mut self.crash_placeholder {
+            placeholder.set_size(size);
+            return;
+        }
+
         // Assume that when calling this function, the window is going floating, so it can no
         // longer participate in any transactions with other windows.
         self.transaction_for_next_configure = None;
@@ -609,12 +753,13 @@ impl LayoutElement for Mapped {
             return;
         }
 
-        let changed = self.toplevel().with_pending_state(|state| {
+        let (changed, fullscreen_changed) = self.toplevel().with_pending_state(|state| {
             let changed = state.size != Some(size);
             state.size = Some(size);
-            state.states.unset(xdg_toplevel::State::Fullscreen);
-            changed
+            let fullscreen_changed = state.states.unset(xdg_toplevel::State::Fullscreen);
+            (changed, fullscreen_changed)
         });
+        self.need_to_recompute_rules |= fullscreen_changed;
 
         if changed && animate {
             self.animate_next_configure = true;
@@ -624,10 +769,16 @@ impl LayoutElement for Mapped {
     }
 
     fn request_fullscreen(&mut self, size: Size<i32, Logical>) {
-        self.toplevel().with_pending_state(|state| {
+        if let Some(placeholder) = &mut self.crash_placeholder {
+            placeholder.set_size(size);
+            return;
+        }
+
+        let fullscreen_changed = self.toplevel().with_pending_state(|state| {
             state.size = Some(size);
-            state.states.set(xdg_toplevel::State::Fullscreen);
+            state.states.set(xdg_toplevel::State::Fullscreen)
         });
+        self.need_to_recompute_rules |= fullscreen_changed;
 
         self.request_size_once = None;
     }
@@ -678,6 +829,10 @@ impl LayoutElement for Mapped {
         }
     }
 
+    fn title(&self) -> Option<String> {
+        with_toplevel_role(self.toplevel(), |role| role.title.clone())
+    }
+
     fn output_enter(&self, output: &Output) {
         let overlap = Rectangle::from_size(Size::from((i32::MAX, i32::MAX)));
         self.window.output_enter(output, overlap)
@@ -718,6 +873,12 @@ impl LayoutElement for Mapped {
         self.need_to_recompute_rules |= changed;
     }
 
+    fn set_maximized(&mut self, maximized: bool) {
+        let changed = self.is_maximized != maximized;
+        self.is_maximized = maximized;
+        self.need_to_recompute_rules |= changed;
+    }
+
     fn set_bounds(&self, bounds: Size<i32, Logical>) {
         self.toplevel().with_pending_state(|state| {
             state.bounds = Some(bounds);
@@ -725,6 +886,10 @@ impl LayoutElement for Mapped {
     }
 
     fn configure_intent(&self) -> ConfigureIntent {
+        if self.crash_placeholder.is_some() {
+            return ConfigureIntent::NotNeeded;
+        }
+
         let _span =
             trace_span!("configure_intent", surface = ?self.toplevel().wl_surface().id()).entered();
 
@@ -774,6 +939,10 @@ impl LayoutElement for Mapped {
     }
 
     fn send_pending_configure(&mut self) {
+        if self.crash_placeholder.is_some() {
+            return;
+        }
+
         let toplevel = self.toplevel();
         let _span =
             trace_span!("send_pending_configure", surface = ?toplevel.wl_surface().id()).entered();
@@ -857,11 +1026,19 @@ impl LayoutElement for Mapped {
         self.ignore_opacity_window_rule
     }
 
+    fn is_selected(&self) -> bool {
+        self.is_selected
+    }
+
     fn requested_size(&self) -> Option<Size<i32, Logical>> {
         self.toplevel().with_pending_state(|state| state.size)
     }
 
     fn expected_size(&self) -> Option<Size<i32, Logical>> {
+        if let Some(placeholder) = &self.crash_placeholder {
+            return Some(placeholder.size());
+        }
+
         // We can only use current size if it's not fullscreen.
         let current_size = (!self.is_fullscreen()).then(|| self.window.geometry().size);
 
@@ -1,8 +1,8 @@
 use std::cmp::{max, min};
 
 use niri_config::{
-    BlockOutFrom, BorderRule, CornerRadius, FloatingPosition, Match, PresetSize, ShadowRule,
-    WindowRule,
+    BlockOutFrom, BorderRule, CornerRadius, FloatingPosition, ForcedSize, FullscreenSize, Match,
+    OpenOnWorkspaceFallback, PresetSize, RegexEq, RevealNewWindows, ShadowRule, WindowRule,
 };
 use smithay::reexports::wayland_protocols::xdg::shell::server::xdg_toplevel;
 use smithay::utils::{Logical, Size};
@@ -11,7 +11,10 @@ use smithay::wayland::shell::xdg::{
     SurfaceCachedState, ToplevelSurface, XdgToplevelSurfaceRoleAttributes,
 };
 
-use crate::utils::with_toplevel_role;
+use crate::utils::desktop_entries::pwa_name;
+use crate::utils::{is_xwayland_satellite_surface, with_toplevel_role};
+
+pub mod crash_placeholder;
 
 pub mod mapped;
 pub use mapped::Mapped;
@@ -26,6 +29,17 @@ pub enum WindowRef<'a> {
     Mapped(&'a Mapped),
 }
 
+/// Extra rules for the window of an application spawned with `spawn-with-rules`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpawnRules {
+    pub open_floating: Option<bool>,
+    pub open_fullscreen: Option<bool>,
+    pub default_width: Option<u16>,
+    pub default_height: Option<u16>,
+    /// Output that was focused at spawn time, if the window should open there.
+    pub open_on_output: Option<String>,
+}
+
 /// Rules fully resolved for a window.
 #[derive(Debug, PartialEq)]
 pub struct ResolvedWindowRules {
@@ -52,6 +66,9 @@ pub struct ResolvedWindowRules {
     /// Workspace to open this window on.
     pub open_on_workspace: Option<String>,
 
+    /// What to do when the workspace to open this window on doesn't exist.
+    pub open_on_workspace_fallback: Option<OpenOnWorkspaceFallback>,
+
     /// Whether the window should open full-width.
     pub open_maximized: Option<bool>,
 
@@ -64,6 +81,18 @@ pub struct ResolvedWindowRules {
     /// Whether the window should open focused.
     pub open_focused: Option<bool>,
 
+    /// Whether the window should never take focus on its own.
+    ///
+    /// Overrides `open_focused` and also blocks activation requests that the user didn't
+    /// initiate.
+    pub never_steal_focus: Option<bool>,
+
+    /// Whether the view should move to show the window if it opens unfocused.
+    pub reveal_on_open: Option<RevealNewWindows>,
+
+    /// Size to force on an X11 window at initial configure.
+    pub force_initial_size: Option<ForcedSize>,
+
     /// Extra bound on the minimum window width.
     pub min_width: Option<u16>,
     /// Extra bound on the minimum window height.
@@ -73,6 +102,9 @@ pub struct ResolvedWindowRules {
     /// Extra bound on the maximum window height.
     pub max_height: Option<u16>,
 
+    /// Size to request from the window in fullscreen instead of the output size.
+    pub fullscreen_size: Option<FullscreenSize>,
+
     /// Focus ring overrides.
     pub focus_ring: BorderRule,
     /// Window border overrides.
@@ -85,6 +117,11 @@ pub struct ResolvedWindowRules {
     /// `None` means using the SSD heuristic.
     pub draw_border_with_background: Option<bool>,
 
+    /// Whether to draw a server-side titlebar for this window.
+    ///
+    /// Only takes effect for windows using server-side decorations.
+    pub draw_titlebar: Option<bool>,
+
     /// Extra opacity to draw this window with.
     pub opacity: Option<f32>,
 
@@ -94,6 +131,9 @@ pub struct ResolvedWindowRules {
     /// Whether to clip this window to its geometry, including the corner radius.
     pub clip_to_geometry: Option<bool>,
 
+    /// Whether to clip client-drawn shadows outside this window's geometry.
+    pub clip_csd_shadow: Option<bool>,
+
     /// Whether to block out this window from certain render targets.
     pub block_out_from: Option<BlockOutFrom>,
 
@@ -102,6 +142,21 @@ pub struct ResolvedWindowRules {
 
     /// Multiplier for all scroll events sent to this window.
     pub scroll_factor: Option<f64>,
+
+    /// Whether to warp the mouse to this window when it is focused.
+    pub warp_mouse_to_focus: Option<bool>,
+
+    /// Whether this window is a picture-in-picture window.
+    ///
+    /// Such windows float in a corner of the output above fullscreen windows and follow the
+    /// active workspace.
+    pub picture_in_picture: Option<bool>,
+
+    /// Whether pointer input should pass through this window to whatever is below.
+    pub click_through: Option<bool>,
+
+    /// Whether closing this window with a close action asks for confirmation first.
+    pub confirm_close: Option<bool>,
 }
 
 impl<'a> WindowRef<'a> {
@@ -140,6 +195,28 @@ impl<'a> WindowRef<'a> {
             WindowRef::Mapped(mapped) => mapped.is_floating(),
         }
     }
+
+    pub fn is_maximized(self) -> bool {
+        match self {
+            // FIXME: Same as with is-floating, this makes a cycle with the open-maximized rule, so
+            // it cannot be used for initial configure rules.
+            WindowRef::Unmapped(_) => false,
+            WindowRef::Mapped(mapped) => mapped.is_maximized(),
+        }
+    }
+
+    pub fn has_mark(self, re: &RegexEq) -> bool {
+        match self {
+            // Marks can only be set on mapped windows.
+            WindowRef::Unmapped(_) => false,
+            WindowRef::Mapped(mapped) => mapped.marks().iter().any(|mark| re.0.is_match(mark)),
+        }
+    }
+
+    /// Returns whether this is an X11 window, running through xwayland-satellite.
+    pub fn is_x11(self) -> bool {
+        is_xwayland_satellite_surface(self.toplevel().wl_surface())
+    }
 }
 
 impl ResolvedWindowRules {
@@ -150,14 +227,19 @@ impl ResolvedWindowRules {
             default_floating_position: None,
             open_on_output: None,
             open_on_workspace: None,
+            open_on_workspace_fallback: None,
             open_maximized: None,
             open_fullscreen: None,
             open_floating: None,
             open_focused: None,
+            never_steal_focus: None,
+            reveal_on_open: None,
+            force_initial_size: None,
             min_width: None,
             min_height: None,
             max_width: None,
             max_height: None,
+            fullscreen_size: None,
             focus_ring: BorderRule {
                 off: false,
                 on: false,
@@ -187,12 +269,18 @@ impl ResolvedWindowRules {
                 inactive_color: None,
             },
             draw_border_with_background: None,
+            draw_titlebar: None,
             opacity: None,
             geometry_corner_radius: None,
             clip_to_geometry: None,
+            clip_csd_shadow: None,
             block_out_from: None,
             variable_refresh_rate: None,
             scroll_factor: None,
+            warp_mouse_to_focus: None,
+            picture_in_picture: None,
+            click_through: None,
+            confirm_close: None,
         }
     }
 
@@ -249,6 +337,10 @@ impl ResolvedWindowRules {
                     open_on_workspace = Some(x);
                 }
 
+                if let Some(x) = rule.open_on_workspace_fallback {
+                    resolved.open_on_workspace_fallback = Some(x);
+                }
+
                 if let Some(x) = rule.open_maximized {
                     resolved.open_maximized = Some(x);
                 }
@@ -265,6 +357,18 @@ impl ResolvedWindowRules {
                     resolved.open_focused = Some(x);
                 }
 
+                if let Some(x) = rule.never_steal_focus {
+                    resolved.never_steal_focus = Some(x);
+                }
+
+                if let Some(x) = rule.reveal_on_open {
+                    resolved.reveal_on_open = Some(x);
+                }
+
+                if let Some(x) = rule.force_initial_size {
+                    resolved.force_initial_size = Some(x);
+                }
+
                 if let Some(x) = rule.min_width {
                     resolved.min_width = Some(x);
                 }
@@ -277,6 +381,9 @@ impl ResolvedWindowRules {
                 if let Some(x) = rule.max_height {
                     resolved.max_height = Some(x);
                 }
+                if let Some(x) = rule.fullscreen_size {
+                    resolved.fullscreen_size = Some(x);
+                }
 
                 resolved.focus_ring.merge_with(&rule.focus_ring);
                 resolved.border.merge_with(&rule.border);
@@ -285,6 +392,9 @@ impl ResolvedWindowRules {
                 if let Some(x) = rule.draw_border_with_background {
                     resolved.draw_border_with_background = Some(x);
                 }
+                if let Some(x) = rule.draw_titlebar {
+                    resolved.draw_titlebar = Some(x);
+                }
                 if let Some(x) = rule.opacity {
                     resolved.opacity = Some(x);
                 }
@@ -294,6 +404,9 @@ impl ResolvedWindowRules {
                 if let Some(x) = rule.clip_to_geometry {
                     resolved.clip_to_geometry = Some(x);
                 }
+                if let Some(x) = rule.clip_csd_shadow {
+                    resolved.clip_csd_shadow = Some(x);
+                }
                 if let Some(x) = rule.block_out_from {
                     resolved.block_out_from = Some(x);
                 }
@@ -303,15 +416,55 @@ impl ResolvedWindowRules {
                 if let Some(x) = rule.scroll_factor {
                     resolved.scroll_factor = Some(x.0);
                 }
+                if let Some(x) = rule.warp_mouse_to_focus {
+                    resolved.warp_mouse_to_focus = Some(x);
+                }
+                if let Some(x) = rule.picture_in_picture {
+                    resolved.picture_in_picture = Some(x);
+                }
+                if let Some(x) = rule.click_through {
+                    resolved.click_through = Some(x);
+                }
+                if let Some(x) = rule.confirm_close {
+                    resolved.confirm_close = Some(x);
+                }
             }
 
             resolved.open_on_output = open_on_output.map(|x| x.to_owned());
             resolved.open_on_workspace = open_on_workspace.map(|x| x.to_owned());
         });
 
+        if let WindowRef::Unmapped(Unmapped {
+            spawn_rules: Some(spawn_rules),
+            ..
+        }) = window
+        {
+            resolved.apply_spawn_rules(spawn_rules);
+        }
+
         resolved
     }
 
+    /// Applies the rules given when spawning the window's application on top of the config ones.
+    fn apply_spawn_rules(&mut self, rules: &SpawnRules) {
+        if let Some(x) = rules.open_floating {
+            self.open_floating = Some(x);
+        }
+        if let Some(x) = rules.open_fullscreen {
+            self.open_fullscreen = Some(x);
+        }
+        if let Some(x) = rules.default_width {
+            self.default_width = Some(Some(PresetSize::Fixed(i32::from(x))));
+        }
+        if let Some(x) = rules.default_height {
+            self.default_height = Some(Some(PresetSize::Fixed(i32::from(x))));
+        }
+        if let Some(x) = &rules.open_on_output {
+            self.open_on_output = Some(x.clone());
+            self.open_on_workspace = None;
+        }
+    }
+
     pub fn apply_min_size(&self, min_size: Size<i32, Logical>) -> Size<i32, Logical> {
         let mut size = min_size;
 
@@ -356,11 +509,24 @@ impl ResolvedWindowRules {
         (min_size, max_size)
     }
 
+    /// Returns the size to request from a fullscreen window on an output of `view_size`.
+    pub fn compute_fullscreen_size(&self, view_size: Size<i32, Logical>) -> Size<i32, Logical> {
+        match self.fullscreen_size {
+            Some(size) => Size::from((i32::from(size.width), i32::from(size.height))),
+            None => view_size,
+        }
+    }
+
     pub fn compute_open_floating(&self, toplevel: &ToplevelSurface) -> bool {
         if let Some(res) = self.open_floating {
             return res;
         }
 
+        // Picture-in-picture windows only make sense as floating.
+        if self.picture_in_picture == Some(true) {
+            return true;
+        }
+
         // Windows with a parent (usually dialogs) open as floating by default.
         if toplevel.parent().is_some() {
             return true;
@@ -416,6 +582,24 @@ fn window_matches(window: WindowRef, role: &XdgToplevelSurfaceRoleAttributes, m:
         }
     }
 
+    if let Some(mark_re) = &m.mark {
+        if !window.has_mark(mark_re) {
+            return false;
+        }
+    }
+
+    if let Some(pwa_name_re) = &m.pwa_name {
+        let Some(app_id) = &role.app_id else {
+            return false;
+        };
+        let Some(name) = pwa_name(app_id, role.title.as_deref()) else {
+            return false;
+        };
+        if !pwa_name_re.0.is_match(&name) {
+            return false;
+        }
+    }
+
     if let Some(is_active_in_column) = m.is_active_in_column {
         if window.is_active_in_column() != is_active_in_column {
             return false;
@@ -428,5 +612,27 @@ fn window_matches(window: WindowRef, role: &XdgToplevelSurfaceRoleAttributes, m:
         }
     }
 
+    if let Some(is_fullscreen) = m.is_fullscreen {
+        // Like with is-active, this corresponds to the window having a pending Fullscreen state.
+        let pending_fullscreen = server_pending
+            .states
+            .contains(xdg_toplevel::State::Fullscreen);
+        if is_fullscreen != pending_fullscreen {
+            return false;
+        }
+    }
+
+    if let Some(is_maximized) = m.is_maximized {
+        if window.is_maximized() != is_maximized {
+            return false;
+        }
+    }
+
+    if let Some(is_x11) = m.is_x11 {
+        if window.is_x11() != is_x11 {
+            return false;
+        }
+    }
+
     true
 }
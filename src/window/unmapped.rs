@@ -4,7 +4,8 @@ use smithay::output::Output;
 use smithay::wayland::shell::xdg::ToplevelSurface;
 use smithay::wayland::xdg_activation::XdgActivationTokenData;
 
-use super::ResolvedWindowRules;
+use super::{ResolvedWindowRules, SpawnRules};
+use crate::layout::startup_placeholder::StartupPlaceholderId;
 
 #[derive(Debug)]
 pub struct Unmapped {
@@ -12,6 +13,10 @@ pub struct Unmapped {
     pub state: InitialConfigureState,
     /// Activation token, if one was used on this unmapped window.
     pub activation_token_data: Option<XdgActivationTokenData>,
+    /// Placeholder shown for this window while its application was starting up.
+    pub startup_placeholder: Option<StartupPlaceholderId>,
+    /// Extra rules from the `spawn-with-rules` that started this window's application.
+    pub spawn_rules: Option<SpawnRules>,
 }
 
 #[allow(clippy::large_enum_variant)]
@@ -21,6 +26,16 @@ pub enum InitialConfigureState {
     NotConfigured {
         /// Whether the window requested to be fullscreened, and the requested output, if any.
         wants_fullscreen: Option<Option<Output>>,
+
+        /// Name of the workspace that the initial configure is held back for.
+        ///
+        /// Set when the window rules ask to wait for the target workspace to appear.
+        waiting_for_workspace: Option<String>,
+
+        /// Whether the target workspace didn't appear in time.
+        ///
+        /// The window then opens as if the fallback was `current`.
+        workspace_wait_timed_out: bool,
     },
     /// The window has been configured.
     Configured {
@@ -74,8 +89,12 @@ impl Unmapped {
             window,
             state: InitialConfigureState::NotConfigured {
                 wants_fullscreen: None,
+                waiting_for_workspace: None,
+                workspace_wait_timed_out: false,
             },
             activation_token_data: None,
+            startup_placeholder: None,
+            spawn_rules: None,
         }
     }
 
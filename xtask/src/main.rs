@@ -0,0 +1,56 @@
+//! Development tasks for niri, run with `cargo xtask <task>`.
+
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::{bail, Context};
+
+const USAGE: &str = "\
+Usage: cargo xtask <task>
+
+Tasks:
+    layout-test <file>...    Run layout test scripts (see src/layout/tests/script.rs)";
+
+fn main() -> anyhow::Result<()> {
+    let mut args = env::args().skip(1);
+    match args.next().as_deref() {
+        Some("layout-test") => layout_test(args.collect()),
+        Some("help" | "-h" | "--help") => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        Some(task) => bail!("unknown task {task:?}\n\n{USAGE}"),
+        None => bail!("no task given\n\n{USAGE}"),
+    }
+}
+
+fn layout_test(files: Vec<String>) -> anyhow::Result<()> {
+    if files.is_empty() {
+        bail!("no layout test scripts given\n\n{USAGE}");
+    }
+
+    let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+
+    for file in files {
+        // Tests run from the crate directory, so pass an absolute path.
+        let path = Path::new(&file)
+            .canonicalize()
+            .with_context(|| format!("error finding {file}"))?;
+
+        println!("running {file}");
+        let status = Command::new(&cargo)
+            .args(["test", "--package", "niri", "--lib", "--"])
+            .args(["layout::tests::script::script_from_env", "--exact"])
+            .args(["--ignored", "--nocapture", "--quiet"])
+            .env("NIRI_LAYOUT_TEST", &path)
+            .status()
+            .context("error running cargo")?;
+
+        if !status.success() {
+            bail!("layout test {file} failed");
+        }
+    }
+
+    Ok(())
+}